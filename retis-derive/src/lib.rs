@@ -1,5 +1,7 @@
 use quote::quote;
-use syn::{parse_macro_input, Fields, Ident, Item, ItemStruct};
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Fields, Ident, Item, ItemStruct, LitStr, Token,
+};
 
 #[proc_macro_attribute]
 pub fn event_section(
@@ -101,12 +103,67 @@ fn item_get_props(item: &Item) -> EventTypeProps {
     }
 }
 
+/// Translate the `#[retis(...)]` field attributes into their serde
+/// counterparts. Those are used to evolve event sections without breaking the
+/// deserialization of events stored by older versions:
+///
+/// - `#[retis(rename_from = "old_name")]`: the field was previously named
+///   `old_name`. Can be used multiple times.
+/// - `#[retis(default)]` or `#[retis(default = "path")]`: use the field default
+///   value (or the result of calling `path`) if it is missing.
+fn fields_translate_attrs(fields: &mut Fields) -> syn::Result<()> {
+    for field in fields.iter_mut() {
+        let mut attrs: Vec<Attribute> = Vec::new();
+
+        for attr in field.attrs.drain(..) {
+            if !attr.path().is_ident("retis") {
+                attrs.push(attr);
+                continue;
+            }
+
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename_from") {
+                    let name: LitStr = meta.value()?.parse()?;
+                    attrs.push(parse_quote!(#[serde(alias = #name)]));
+                } else if meta.path.is_ident("default") {
+                    if meta.input.peek(Token![=]) {
+                        let path: LitStr = meta.value()?.parse()?;
+                        attrs.push(parse_quote!(#[serde(default = #path)]));
+                    } else {
+                        attrs.push(parse_quote!(#[serde(default)]));
+                    }
+                } else {
+                    return Err(meta.error("unsupported retis attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        field.attrs = attrs;
+    }
+    Ok(())
+}
+
+fn item_translate_attrs(item: &mut Item) -> syn::Result<()> {
+    match item {
+        Item::Struct(item) => fields_translate_attrs(&mut item.fields),
+        Item::Enum(item) => item
+            .variants
+            .iter_mut()
+            .try_for_each(|v| fields_translate_attrs(&mut v.fields)),
+        _ => Ok(()),
+    }
+}
+
 #[proc_macro_attribute]
 pub fn event_type(
     _args: proc_macro::TokenStream,
     item: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-    let input: Item = parse_macro_input!(item);
+    let mut input: Item = parse_macro_input!(item);
+    if let Err(e) = item_translate_attrs(&mut input) {
+        return e.to_compile_error().into();
+    }
     let props = item_get_props(&input);
     let mut pyclass_args = Vec::new();
     let mut derives = vec![
@@ -0,0 +1,278 @@
+//! Binary event file format.
+//!
+//! The binary format is a compact framing of events and series, allowing to
+//! iterate over events and to skip unwanted sections without parsing the full
+//! file line by line. Section data is still stored using its json
+//! representation so it can be validated against the section definitions when
+//! reading it back.
+//!
+//! The file layout is as follow (all integers are little endian):
+//!
+//! ```text
+//! header:  magic ("RETISBIN", 8 bytes) | version (u8) | file type (u8)
+//! event:   number of sections (u16) | section...
+//! section: section id (u8) | data length (u32) | json data
+//! series:  number of events (u32) | event...
+//! ```
+//!
+//! The file type is 0 for files containing events and 1 for files containing
+//! series.
+
+use std::io::{ErrorKind, Read, Write};
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::{file::FileType, Event, EventSeries, SectionId};
+
+/// Magic identifying binary event files.
+pub const BINARY_MAGIC: &[u8; 8] = b"RETISBIN";
+/// Current version of the binary format.
+pub const BINARY_VERSION: u8 = 1;
+/// Maximum length of the data of a section. Sections are much smaller in
+/// practice; this protects from allocating huge buffers when reading corrupted
+/// files.
+pub const BINARY_MAX_SECTION_LEN: usize = 1 << 20;
+
+/// Writes events or series in the binary format.
+pub struct BinaryWriter<W: Write> {
+    writer: W,
+    filetype: FileType,
+}
+
+impl<W: Write> BinaryWriter<W> {
+    /// Create a new binary writer and write the file header.
+    pub fn new(mut writer: W, filetype: FileType) -> Result<Self> {
+        writer.write_all(BINARY_MAGIC)?;
+        writer.write_all(&[
+            BINARY_VERSION,
+            match filetype {
+                FileType::Event => 0,
+                FileType::Series => 1,
+            },
+        ])?;
+
+        Ok(Self { writer, filetype })
+    }
+
+    /// Write a single event.
+    pub fn write_event(&mut self, event: &Event) -> Result<()> {
+        if !matches!(self.filetype, FileType::Event) {
+            bail!("Cannot write an event to a series file");
+        }
+        write_event(&mut self.writer, event)
+    }
+
+    /// Write a single series.
+    pub fn write_series(&mut self, series: &EventSeries) -> Result<()> {
+        if !matches!(self.filetype, FileType::Series) {
+            bail!("Cannot write a series to an event file");
+        }
+
+        self.writer
+            .write_all(&(series.events.len() as u32).to_le_bytes())?;
+        series
+            .events
+            .iter()
+            .try_for_each(|e| write_event(&mut self.writer, e))
+    }
+
+    /// Type of the file being written.
+    pub fn file_type(&self) -> &FileType {
+        &self.filetype
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+fn write_event<W: Write>(writer: &mut W, event: &Event) -> Result<()> {
+    let sections: Vec<SectionId> = event.sections().collect();
+    writer.write_all(&(sections.len() as u16).to_le_bytes())?;
+
+    for id in sections {
+        // Unwrap as we're iterating over the existing sections.
+        let data = serde_json::to_vec(&event.get(id).unwrap().to_json())?;
+        if data.len() > BINARY_MAX_SECTION_LEN {
+            bail!(
+                "{} section is too large ({} bytes)",
+                id.to_str(),
+                data.len()
+            );
+        }
+
+        writer.write_all(&[id as u8])?;
+        writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        writer.write_all(&data)?;
+    }
+
+    Ok(())
+}
+
+/// Check if the given data starts with the binary magic.
+pub fn is_binary(data: &[u8]) -> bool {
+    data.starts_with(BINARY_MAGIC)
+}
+
+/// Read and validate a binary file header, returning the type of the file.
+pub fn read_header<R: Read>(reader: &mut R) -> Result<FileType> {
+    let mut header = [0u8; BINARY_MAGIC.len() + 2];
+    reader
+        .read_exact(&mut header)
+        .map_err(|e| anyhow!("Could not read binary header: {e}"))?;
+
+    if !is_binary(&header) {
+        bail!("Not a binary event file (invalid magic)");
+    }
+
+    let version = header[BINARY_MAGIC.len()];
+    if version != BINARY_VERSION {
+        bail!("Unsupported binary format version ({version})");
+    }
+
+    Ok(match header[BINARY_MAGIC.len() + 1] {
+        0 => FileType::Event,
+        1 => FileType::Series,
+        x => bail!("Invalid binary file type ({x})"),
+    })
+}
+
+/// Read the next event from a binary file. Returns None if the end of the file
+/// was reached. Sections unknown to this version of Retis are skipped and
+/// their ids (`#<id>`) added to `unknown`.
+pub fn read_event<R: Read>(reader: &mut R, unknown: &mut Vec<String>) -> Result<Option<Event>> {
+    let mut count = [0u8; 2];
+    if !read_exact_or_eof(reader, &mut count)? {
+        return Ok(None);
+    }
+
+    read_event_sections(reader, u16::from_le_bytes(count), unknown).map(Some)
+}
+
+/// Read the next series from a binary file. Returns None if the end of the
+/// file was reached. See `read_event`.
+pub fn read_series<R: Read>(
+    reader: &mut R,
+    unknown: &mut Vec<String>,
+) -> Result<Option<EventSeries>> {
+    let mut count = [0u8; 4];
+    if !read_exact_or_eof(reader, &mut count)? {
+        return Ok(None);
+    }
+
+    let mut series = EventSeries::default();
+    for _ in 0..u32::from_le_bytes(count) {
+        let mut count = [0u8; 2];
+        reader.read_exact(&mut count)?;
+        series.events.push(read_event_sections(
+            reader,
+            u16::from_le_bytes(count),
+            unknown,
+        )?);
+    }
+
+    Ok(Some(series))
+}
+
+fn read_event_sections<R: Read>(
+    reader: &mut R,
+    count: u16,
+    unknown: &mut Vec<String>,
+) -> Result<Event> {
+    let mut event = Event::new();
+
+    // Read all sections before unmarshaling them so we do not leave the reader
+    // in the middle of an event in case of an invalid section.
+    let mut sections = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let mut header = [0u8; 5];
+        reader.read_exact(&mut header)?;
+
+        let len = u32::from_le_bytes(header[1..5].try_into()?) as usize;
+        if len > BINARY_MAX_SECTION_LEN {
+            bail!("Invalid section length ({len} bytes)");
+        }
+
+        let mut data = vec![0u8; len];
+        reader.read_exact(&mut data)?;
+
+        sections.push((header[0], data));
+    }
+
+    for (id, data) in sections {
+        let id = match SectionId::from_u8(id) {
+            Ok(id) => id,
+            Err(_) => {
+                unknown.push(format!("#{id}"));
+                continue;
+            }
+        };
+        let value: serde_json::Value = serde_json::from_slice(&data)
+            .map_err(|e| anyhow!("Failed to parse {} section data: {e}", id.to_str()))?;
+        event.insert_json_section(id.to_str(), value)?;
+    }
+
+    Ok(event)
+}
+
+// Fill the whole buffer, returning false if EOF was reached before reading
+// anything. Reaching EOF after reading some data is an error.
+fn read_exact_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) if read == 0 => return Ok(false),
+            Ok(0) => bail!("Unexpected end of file"),
+            Ok(n) => read += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::FileEventsFactory;
+
+    #[test]
+    fn binary_round_trip() {
+        let mut fact = FileEventsFactory::new("test_data/test_events.json").unwrap();
+        let mut events = Vec::new();
+        while let Some(event) = fact.next_event().unwrap() {
+            events.push(event);
+        }
+
+        let mut writer = BinaryWriter::new(Vec::new(), FileType::Event).unwrap();
+        events.iter().for_each(|e| writer.write_event(e).unwrap());
+        assert!(writer.write_series(&EventSeries::default()).is_err());
+
+        let data = writer.writer;
+        assert!(is_binary(&data));
+
+        let mut reader = data.as_slice();
+        let mut unknown = Vec::new();
+        assert!(matches!(read_header(&mut reader).unwrap(), FileType::Event));
+        for event in events.iter() {
+            let read = read_event(&mut reader, &mut unknown).unwrap().unwrap();
+            assert_eq!(read.to_json(), event.to_json());
+        }
+        assert!(read_event(&mut reader, &mut unknown).unwrap().is_none());
+        assert!(unknown.is_empty());
+
+        // Truncated input.
+        let mut reader = &data[..data.len() - 1];
+        read_header(&mut reader).unwrap();
+        assert!((0..events.len()).any(|_| read_event(&mut reader, &mut unknown).is_err()));
+
+        // Corrupted section length.
+        let mut corrupted = data.clone();
+        let offset = BINARY_MAGIC.len() + 2 + 2 + 1;
+        corrupted[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut reader = corrupted.as_slice();
+        read_header(&mut reader).unwrap();
+        assert!(read_event(&mut reader, &mut unknown).is_err());
+    }
+}
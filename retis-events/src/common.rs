@@ -12,14 +12,408 @@ pub struct StartupEvent {
     pub retis_version: String,
     /// CLOCK_MONOTONIC offset in regards to local machine time.
     pub clock_monotonic_offset: TimeSpec,
+    /// Probes and BPF programs found attached by others on the targets used
+    /// by Retis, when the collection started.
+    #[retis(default)]
+    pub attached_probes: Vec<AttachedProbe>,
+    /// Physical location of the events ring buffer, if asked to be recorded
+    /// for post-mortem analysis.
+    #[retis(default)]
+    pub events_ringbuf: Option<RingBufLayout>,
+    /// Unique identifier of the collection.
+    #[retis(default)]
+    pub session_id: String,
 }
 
 impl EventFmt for StartupEvent {
     fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
-        write!(f, "Retis version {}", self.retis_version)
+        write!(f, "Retis version {}", self.retis_version)?;
+
+        if !self.attached_probes.is_empty() {
+            write!(f, " (probes already attached: ")?;
+            self.attached_probes
+                .iter()
+                .enumerate()
+                .try_for_each(|(i, probe)| {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{probe}")
+                })?;
+            write!(f, ")")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Probe or BPF program attached by another tool on a target also used by
+/// Retis.
+#[event_type]
+pub struct AttachedProbe {
+    /// Target the probe is attached to (e.g. `kprobe:ip_rcv`,
+    /// `tp:skb:kfree_skb` or `tc`).
+    pub target: String,
+    /// Id of the BPF program, if known.
+    pub prog_id: Option<u32>,
+    /// Name of the BPF program, if known.
+    pub prog_name: Option<String>,
+}
+
+impl fmt::Display for AttachedProbe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.target)?;
+        match (self.prog_id, &self.prog_name) {
+            (Some(id), Some(name)) => write!(f, " (prog {id} {name})"),
+            (Some(id), None) => write!(f, " (prog {id})"),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Physical location of a BPF ring buffer, used to recover its content from a
+/// kernel crash dump.
+#[event_type]
+pub struct RingBufLayout {
+    /// Page size of the machine the events were collected on.
+    pub page_size: u64,
+    /// Size of the data area of the ring buffer, in bytes.
+    pub data_size: u64,
+    /// Page frames backing the ring buffer: the consumer position page, the
+    /// producer position page and then the data pages.
+    pub pfns: Vec<PfnRange>,
+}
+
+/// Range of contiguous page frames.
+#[event_type]
+pub struct PfnRange {
+    /// First page frame number.
+    pub start: u64,
+    /// Number of page frames.
+    pub count: u64,
+}
+
+/// Clock synchronization event section. Periodically generated during a
+/// collection with a fresh sample of the monotonic clock offset, as the one
+/// taken at startup drifts over long collections. The timestamp of the common
+/// section is the monotonic time the sample was taken at.
+#[event_section(SectionId::ClockSync)]
+pub struct ClockSyncEvent {
+    /// CLOCK_MONOTONIC offset in regards to local machine time.
+    pub clock_monotonic_offset: TimeSpec,
+}
+
+impl EventFmt for ClockSyncEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(
+            f,
+            "clock sync offset {}.{:09}",
+            self.clock_monotonic_offset.sec(),
+            self.clock_monotonic_offset.nsec()
+        )
+    }
+}
+
+/// Ethtool statistics event section. Generated at the end of a collection with
+/// the driver statistics of network interfaces which changed during the
+/// collection, so events can be reconciled with NIC level counters (e.g.
+/// drops).
+#[event_section(SectionId::EthtoolStats)]
+pub struct EthtoolStatsEvent {
+    /// Interfaces whose statistics changed.
+    pub interfaces: Vec<EthtoolIfStats>,
+}
+
+impl EventFmt for EthtoolStatsEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(f, "ethtool stats")?;
+        if self.interfaces.is_empty() {
+            write!(f, " unchanged")?;
+        }
+
+        for iface in self.interfaces.iter() {
+            write!(f, " {} [", iface.name)?;
+            let mut space = DelimWriter::new(' ');
+            for counter in iface.counters.iter() {
+                space.write(f)?;
+                write!(f, "{} +{}", counter.name, counter.delta)?;
+            }
+            write!(f, "]")?;
+        }
+        Ok(())
+    }
+}
+
+/// Driver statistics of an interface.
+#[event_type]
+pub struct EthtoolIfStats {
+    /// Interface name.
+    pub name: String,
+    /// Interface index.
+    pub ifindex: u32,
+    /// Counters which changed during the collection.
+    pub counters: Vec<EthtoolCounter>,
+}
+
+/// Driver statistics counter.
+#[event_type]
+pub struct EthtoolCounter {
+    /// Counter name, as reported by `ethtool -S`.
+    pub name: String,
+    /// Difference between the values at the end and at the start of the
+    /// collection.
+    pub delta: u64,
+}
+
+/// Section holding the changes of the network stack counters
+/// (`/proc/net/snmp` and `/proc/net/netstat`) over a collection.
+#[event_section(SectionId::Netstat)]
+pub struct NetstatEvent {
+    /// Counters which changed during the collection.
+    pub counters: Vec<NetstatCounter>,
+}
+
+impl EventFmt for NetstatEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(f, "netstat")?;
+        if self.counters.is_empty() {
+            return write!(f, " unchanged");
+        }
+
+        write!(f, " [")?;
+        let mut space = DelimWriter::new(' ');
+        for counter in self.counters.iter() {
+            space.write(f)?;
+            write!(f, "{} {:+}", counter.name, counter.delta)?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// Network stack counter.
+#[event_type]
+pub struct NetstatCounter {
+    /// Counter name, using the `nstat` naming (e.g. `TcpExtListenDrops`).
+    pub name: String,
+    /// Difference between the values at the end and at the start of the
+    /// collection. Some entries are gauges (e.g. `TcpCurrEstab`) and can go
+    /// down.
+    pub delta: i64,
+}
+
+/// Description of a probe attached during the collection, emitted once per
+/// probe so events can be explained without access to the collecting host.
+#[event_section(SectionId::ProbeDesc)]
+pub struct ProbeDescEvent {
+    /// Probe type, as in the `kernel` section (e.g. "kprobe"), or "usdt".
+    pub probe_type: String,
+    /// Symbol the probe is attached to, as in the `kernel` section.
+    pub symbol: String,
+    /// Parameters of the symbol, as C declarations (e.g. `struct sk_buff
+    /// *skb`). Parameters of tracepoints have no name. Empty if not
+    /// available.
+    pub args: Vec<String>,
+    /// Collectors having a hook on the probe.
+    pub collectors: Vec<String>,
+}
+
+impl EventFmt for ProbeDescEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(
+            f,
+            "probe [{}] {}({})",
+            match self.probe_type.as_str() {
+                "raw_tracepoint" => "tp",
+                "kprobe" => "k",
+                "kretprobe" => "kr",
+                "usdt" => "u",
+                _ => "invalid",
+            },
+            self.symbol,
+            self.args.join(", ")
+        )?;
+
+        if !self.collectors.is_empty() {
+            write!(f, " collectors {}", self.collectors.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// BPF objects (programs, maps and links) loaded by Retis for the collection,
+/// so they can be matched against the output of tools like `bpftool` and
+/// leftovers can be found after an unclean exit.
+#[event_section(SectionId::BpfObjects)]
+pub struct BpfObjectsEvent {
+    /// BPF programs.
+    pub progs: Vec<BpfProg>,
+    /// BPF maps.
+    pub maps: Vec<BpfMap>,
+    /// BPF links, attaching programs to their targets.
+    pub links: Vec<BpfLink>,
+}
+
+/// BPF program.
+#[event_type]
+pub struct BpfProg {
+    /// Program id.
+    pub id: u32,
+    /// Program name, as reported by the kernel (truncated to 15 characters).
+    pub name: String,
+    /// Program type (e.g. `kprobe`, `tracing`, `ext`).
+    pub r#type: String,
+    /// Ids of the maps used by the program.
+    pub map_ids: Vec<u32>,
+}
+
+/// BPF map.
+#[event_type]
+pub struct BpfMap {
+    /// Map id.
+    pub id: u32,
+    /// Map name, as reported by the kernel (truncated to 15 characters).
+    pub name: String,
+    /// Map type (e.g. `hash`, `ringbuf`).
+    pub r#type: String,
+}
+
+/// BPF link.
+#[event_type]
+pub struct BpfLink {
+    /// Link id.
+    pub id: u32,
+    /// Id of the program attached by the link.
+    pub prog_id: u32,
+    /// Link type (e.g. `raw_tracepoint`, `tracing`, `kprobe_multi`).
+    pub r#type: String,
+    /// Attach point of the link, when known (e.g. a tracepoint name).
+    pub target: Option<String>,
+}
+
+impl EventFmt for BpfObjectsEvent {
+    fn event_fmt(&self, f: &mut Formatter, format: &DisplayFormat) -> fmt::Result {
+        let sep = |f: &mut Formatter| match format.multiline {
+            true => writeln!(f),
+            false => write!(f, ", "),
+        };
+
+        write!(
+            f,
+            "bpf objects ({} progs, {} maps, {} links)",
+            self.progs.len(),
+            self.maps.len(),
+            self.links.len()
+        )?;
+
+        for prog in self.progs.iter() {
+            sep(f)?;
+            write!(f, "prog {} {} [{}]", prog.id, prog.name, prog.r#type)?;
+            if !prog.map_ids.is_empty() {
+                write!(
+                    f,
+                    " maps {}",
+                    prog.map_ids
+                        .iter()
+                        .map(|id| id.to_string())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                )?;
+            }
+        }
+        for map in self.maps.iter() {
+            sep(f)?;
+            write!(f, "map {} {} [{}]", map.id, map.name, map.r#type)?;
+        }
+        for link in self.links.iter() {
+            sep(f)?;
+            write!(
+                f,
+                "link {} prog {} [{}]",
+                link.id, link.prog_id, link.r#type
+            )?;
+            if let Some(target) = &link.target {
+                write!(f, " {target}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Log message emitted by Retis during the collection, including the ones
+/// coming from the BPF programs (prefixed by `[eBPF]`).
+#[event_section(SectionId::Log)]
+pub struct LogEvent {
+    /// Level of the message (error, warn, info, debug or trace).
+    pub level: String,
+    /// The message itself.
+    pub msg: String,
+}
+
+impl EventFmt for LogEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(f, "log [{}] {}", self.level, self.msg)
+    }
+}
+
+/// Events the BPF programs could not report, e.g. because the events ring
+/// buffer was full. Reported periodically while events are being lost, so
+/// incomplete parts of a capture can be localized.
+#[event_section(SectionId::LostEvents)]
+pub struct LostEventsEvent {
+    /// Number of events lost since the previous report.
+    pub count: u64,
+    /// Number of events lost since the collection started.
+    pub total: u64,
+}
+
+impl EventFmt for LostEventsEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(f, "lost {} event(s) ({} total)", self.count, self.total)
+    }
+}
+
+/// Host an event was collected on. Only set when events collected on multiple
+/// hosts are merged (see `retis remote`).
+#[event_section(SectionId::Host)]
+pub struct HostEvent {
+    /// Name of the host, as used to connect to it.
+    pub name: String,
+}
+
+impl EventFmt for HostEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(f, "host {}", self.name)
     }
 }
 
+/// Sections of an event which could not be parsed. Those are dropped from the
+/// event while the other sections are kept.
+#[event_section(SectionId::ParseError)]
+pub struct ParseErrorEvent {
+    /// Parse errors, one per failing section.
+    pub errors: Vec<SectionParseError>,
+}
+
+impl EventFmt for ParseErrorEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(f, "parse errors:")?;
+        for error in self.errors.iter() {
+            write!(f, " [{}: {}]", error.owner, error.error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Error encountered while parsing a section.
+#[event_type]
+pub struct SectionParseError {
+    /// Owner of the section which failed to be parsed.
+    pub owner: String,
+    /// Error reported while parsing the section.
+    pub error: String,
+}
+
 /// Information about a given task.
 #[event_type]
 #[derive(Default)]
@@ -32,12 +426,23 @@ pub struct TaskEvent {
     pub comm: String,
 }
 
+/// Format a signed duration in nanoseconds, e.g. `+1.50µs`.
+fn signed_duration(ns: i128) -> String {
+    let sign = if ns < 0 { '-' } else { '+' };
+    format!("{sign}{}", Duration::from_nanos(ns.unsigned_abs() as u64))
+}
+
 /// Common event section.
 #[event_section(SectionId::Common)]
 #[derive(Default)]
 pub struct CommonEvent {
     /// Timestamp of when the event was generated.
     pub timestamp: u64,
+    /// Per-cpu sequence number of the event. Events lost on their way to
+    /// userspace (e.g. the ring buffer being full) still consume a number,
+    /// which allows to detect and localize them.
+    #[retis(default)]
+    pub seq: Option<u64>,
     /// SMP processor id.
     pub smp_id: Option<u32>,
     /// Information about the task linked to the event.
@@ -48,7 +453,7 @@ impl EventFmt for CommonEvent {
     fn event_fmt(&self, f: &mut Formatter, format: &DisplayFormat) -> fmt::Result {
         match format.time_format {
             TimeFormat::MonotonicTimestamp => write!(f, "{}", self.timestamp)?,
-            TimeFormat::UtcDate => match format.monotonic_offset {
+            TimeFormat::UtcDate => match format.offset_at(self.timestamp) {
                 Some(offset) => {
                     let timestamp = TimeSpec::new(0, self.timestamp as i64) + offset;
                     let time: DateTime<Utc> = timestamp.into();
@@ -56,6 +461,10 @@ impl EventFmt for CommonEvent {
                 }
                 None => write!(f, "{}", self.timestamp)?,
             },
+            TimeFormat::Relative => {
+                let (start, prev) = format.relative_to(self.timestamp);
+                write!(f, "{} (Δ{})", signed_duration(start), signed_duration(prev))?;
+            }
         }
 
         if let Some(smp_id) = self.smp_id {
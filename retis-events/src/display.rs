@@ -1,11 +1,13 @@
 use std::{
     fmt::{self, Write},
-    result, str,
+    result,
+    str::{self, FromStr},
 };
 
+use anyhow::{bail, Result};
 use log::warn;
 
-use super::TimeSpec;
+use super::{Event, TimeSpec};
 
 /// Controls how the time should be displayed in the events.
 #[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
@@ -13,10 +15,13 @@ pub enum TimeFormat {
     #[default]
     MonotonicTimestamp,
     UtcDate,
+    /// Time elapsed since the first event (of the series, if any), followed
+    /// by the time elapsed since the previous event.
+    Relative,
 }
 
 /// Controls how an event is formatted.
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone)]
 pub struct DisplayFormat {
     /// Can the formatting logic use more than a single line?
     pub multiline: bool,
@@ -24,6 +29,23 @@ pub struct DisplayFormat {
     pub time_format: TimeFormat,
     /// Offset of the monotonic clock to the wall-clock time.
     pub monotonic_offset: Option<TimeSpec>,
+    /// Show the vendor of MAC addresses, when known (see `oui`).
+    pub oui: bool,
+    /// Samples of the monotonic clock offset, ordered by timestamp, used to
+    /// compensate its drift.
+    clock_samples: Vec<ClockSample>,
+    /// Timestamps of the first and of the previous events, used to display
+    /// relative times.
+    relative_ref: Option<(u64, u64)>,
+}
+
+/// Sample of the monotonic clock offset to the wall-clock time.
+#[derive(Debug, Clone, Copy)]
+struct ClockSample {
+    /// Monotonic timestamp the sample was taken at.
+    timestamp: u64,
+    /// Offset of the monotonic clock to the wall-clock time.
+    offset: TimeSpec,
 }
 
 impl DisplayFormat {
@@ -43,11 +65,101 @@ impl DisplayFormat {
         self
     }
 
+    /// Configure the display of MAC addresses vendors.
+    pub fn oui(mut self, enabled: bool) -> Self {
+        self.oui = enabled;
+        self
+    }
+
     /// Sets the monotonic clock to the wall-clock time.
     pub fn monotonic_offset(mut self, offset: TimeSpec) -> Self {
         self.monotonic_offset = Some(offset);
         self
     }
+
+    /// Add a sample of the monotonic clock offset, taken at the `timestamp`
+    /// monotonic time. Samples can be added in any order; the drift between
+    /// them is used to compute the offset at other timestamps.
+    pub fn add_clock_sample(&mut self, timestamp: u64, offset: TimeSpec) {
+        let sample = ClockSample { timestamp, offset };
+        match self
+            .clock_samples
+            .binary_search_by_key(&timestamp, |s| s.timestamp)
+        {
+            Ok(pos) => self.clock_samples[pos] = sample,
+            Err(pos) => self.clock_samples.insert(pos, sample),
+        }
+
+        self.monotonic_offset = self.clock_samples.last().map(|s| s.offset);
+    }
+
+    /// Update the clock information using the startup and clock
+    /// synchronization sections of an event, if any.
+    pub fn update_clock(&mut self, event: &Event) {
+        let offset = match (event.startup(), event.clock_sync()) {
+            (_, Some(sync)) => sync.clock_monotonic_offset,
+            (Some(startup), None) => startup.clock_monotonic_offset,
+            (None, None) => return,
+        };
+
+        match event.common() {
+            Some(common) => self.add_clock_sample(common.timestamp, offset),
+            None => self.monotonic_offset = Some(offset),
+        }
+    }
+
+    /// Record the timestamp of an event once displayed, to be used as a
+    /// reference by the relative time format.
+    pub fn update_relative_time(&mut self, event: &Event) {
+        if let Some(common) = event.common() {
+            let start = match self.relative_ref {
+                Some((start, _)) => start,
+                None => common.timestamp,
+            };
+            self.relative_ref = Some((start, common.timestamp));
+        }
+    }
+
+    /// Forget the relative time references, e.g. when starting a new series.
+    pub fn reset_relative_time(&mut self) {
+        self.relative_ref = None;
+    }
+
+    /// Times elapsed since the first and the previous events, in nanoseconds.
+    /// The first event displayed has no elapsed time.
+    pub fn relative_to(&self, timestamp: u64) -> (i128, i128) {
+        match self.relative_ref {
+            Some((start, prev)) => (
+                timestamp as i128 - start as i128,
+                timestamp as i128 - prev as i128,
+            ),
+            None => (0, 0),
+        }
+    }
+
+    /// Offset of the monotonic clock to the wall-clock time at the `timestamp`
+    /// monotonic time, compensating the clock drift if samples are available.
+    /// The offset is interpolated between the two samples surrounding
+    /// `timestamp`, and only extrapolated from the two closest samples outside
+    /// of the sampled range.
+    pub fn offset_at(&self, timestamp: u64) -> Option<TimeSpec> {
+        let samples = &self.clock_samples;
+        let (prev, next) = match samples.len() {
+            0 => return self.monotonic_offset,
+            1 => return Some(samples[0].offset),
+            len => {
+                let pos = samples
+                    .partition_point(|s| s.timestamp <= timestamp)
+                    .clamp(1, len - 1);
+                (samples[pos - 1], samples[pos])
+            }
+        };
+
+        let drift = next.offset.as_nanos() - prev.offset.as_nanos();
+        let correction = drift * (timestamp as i128 - prev.timestamp as i128)
+            / (next.timestamp - prev.timestamp) as i128;
+        Some(TimeSpec::from_nanos(prev.offset.as_nanos() + correction))
+    }
 }
 
 /// `Formatter` implements `std::fmt::Write` and controls how events are being
@@ -323,3 +435,197 @@ impl DelimWriter {
         !self.first
     }
 }
+
+/// User-provided template used to format events, e.g.
+/// `{common.timestamp} {kernel.symbol}`. Placeholders are keys of the flattened
+/// json representation of events (see `Event::to_json_flat`), `{{` and `}}` can
+/// be used to write literal braces.
+#[derive(Clone, Debug)]
+pub struct EventTemplate(Vec<TemplatePart>);
+
+#[derive(Clone, Debug, PartialEq)]
+enum TemplatePart {
+    Literal(String),
+    Field(String),
+}
+
+impl FromStr for EventTemplate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.next_if_eq(&'{').is_some() => literal.push('{'),
+                '}' if chars.next_if_eq(&'}').is_some() => literal.push('}'),
+                '{' => {
+                    let mut field = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => field.push(c),
+                            None => bail!("Unterminated placeholder in template '{s}'"),
+                        }
+                    }
+
+                    let field = field.trim();
+                    if field.is_empty() || field.contains('{') {
+                        bail!("Invalid placeholder '{{{field}}}' in template '{s}'");
+                    }
+
+                    if !literal.is_empty() {
+                        parts.push(TemplatePart::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(TemplatePart::Field(field.to_string()));
+                }
+                '}' => bail!("Unmatched '}}' in template '{s}' (use '}}}}' for a literal one)"),
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            parts.push(TemplatePart::Literal(literal));
+        }
+        Ok(Self(parts))
+    }
+}
+
+impl EventTemplate {
+    /// Render an event following the template. Fields not found in the event
+    /// are left empty.
+    pub fn render(&self, event: &Event) -> String {
+        let json = event.to_json_flat();
+
+        self.0.iter().fold(String::new(), |mut out, part| {
+            match part {
+                TemplatePart::Literal(literal) => out.push_str(literal),
+                TemplatePart::Field(field) => match json.get(field) {
+                    Some(serde_json::Value::String(val)) => out.push_str(val),
+                    Some(serde_json::Value::Null) | None => (),
+                    Some(val) => out.push_str(&val.to_string()),
+                },
+            }
+            out
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CommonEvent, KernelEvent, SectionId};
+
+    #[test]
+    fn clock_drift() {
+        let mut format = DisplayFormat::new();
+        assert!(format.offset_at(0).is_none());
+
+        let offset_at = |format: &DisplayFormat, ts| format.offset_at(ts).unwrap().as_nanos();
+        let base = TimeSpec::new(1000, 0).as_nanos();
+
+        // The offset grows by 1ms every 10s.
+        format.add_clock_sample(10_000_000_000, TimeSpec::new(1000, 0));
+        assert_eq!(offset_at(&format, 20_000_000_000), base);
+        format.add_clock_sample(20_000_000_000, TimeSpec::new(1000, 1_000_000));
+
+        assert_eq!(
+            format.monotonic_offset.unwrap().as_nanos(),
+            base + 1_000_000
+        );
+        assert_eq!(offset_at(&format, 25_000_000_000), base + 1_500_000);
+        assert_eq!(offset_at(&format, 15_000_000_000), base + 500_000);
+
+        // The offset then grows by 3ms every 10s.
+        format.add_clock_sample(30_000_000_000, TimeSpec::new(1000, 4_000_000));
+        assert_eq!(
+            format.monotonic_offset.unwrap().as_nanos(),
+            base + 4_000_000
+        );
+        assert_eq!(offset_at(&format, 15_000_000_000), base + 500_000);
+        assert_eq!(offset_at(&format, 25_000_000_000), base + 2_500_000);
+        assert_eq!(offset_at(&format, 20_000_000_000), base + 1_000_000);
+        assert_eq!(offset_at(&format, 35_000_000_000), base + 5_500_000);
+        assert_eq!(offset_at(&format, 5_000_000_000), base - 500_000);
+
+        // The result does not depend on the order the samples were added in.
+        let mut reordered = DisplayFormat::new();
+        reordered.add_clock_sample(30_000_000_000, TimeSpec::new(1000, 4_000_000));
+        reordered.add_clock_sample(10_000_000_000, TimeSpec::new(1000, 0));
+        reordered.add_clock_sample(20_000_000_000, TimeSpec::new(1000, 1_000_000));
+        assert_eq!(
+            reordered.monotonic_offset.unwrap().as_nanos(),
+            base + 4_000_000
+        );
+        for ts in [5, 15, 20, 25, 35].map(|s| s * 1_000_000_000) {
+            assert_eq!(offset_at(&reordered, ts), offset_at(&format, ts));
+        }
+    }
+
+    #[test]
+    fn relative_time() {
+        let event = |timestamp| {
+            let mut event = Event::new();
+            event
+                .insert_section(
+                    SectionId::Common,
+                    Box::new(CommonEvent {
+                        timestamp,
+                        ..Default::default()
+                    }),
+                )
+                .unwrap();
+            event
+        };
+
+        let mut format = DisplayFormat::new().time_format(TimeFormat::Relative);
+        assert_eq!(format.relative_to(100), (0, 0));
+        format.update_relative_time(&event(100));
+        assert_eq!(format.relative_to(150), (50, 50));
+        format.update_relative_time(&event(150));
+        assert_eq!(format.relative_to(140), (40, -10));
+
+        format.reset_relative_time();
+        assert_eq!(format.relative_to(1000), (0, 0));
+    }
+
+    #[test]
+    fn template() {
+        let mut event = Event::new();
+        event
+            .insert_section(
+                SectionId::Common,
+                Box::new(CommonEvent {
+                    timestamp: 42,
+                    smp_id: Some(3),
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        event
+            .insert_section(
+                SectionId::Kernel,
+                Box::new(KernelEvent {
+                    symbol: "ip_rcv".to_string(),
+                    probe_type: "kprobe".to_string(),
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+
+        let render = |t: &str| EventTemplate::from_str(t).unwrap().render(&event);
+        assert_eq!(
+            render("{common.timestamp} [{common.smp_id}] {kernel.symbol}"),
+            "42 [3] ip_rcv"
+        );
+        assert_eq!(render("{ kernel.probe_type }:{skb.ip.saddr}|"), "kprobe:|");
+        assert_eq!(render("{{{kernel.symbol}}}"), "{ip_rcv}");
+        assert_eq!(render("no field"), "no field");
+
+        assert!(EventTemplate::from_str("{kernel.symbol").is_err());
+        assert!(EventTemplate::from_str("kernel.symbol}").is_err());
+        assert!(EventTemplate::from_str("{}").is_err());
+    }
+}
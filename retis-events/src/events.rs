@@ -53,30 +53,49 @@ impl Event {
         Event::default()
     }
 
-    /// Create an Event from a json object.
-    pub(crate) fn from_json_obj(mut obj: HashMap<String, serde_json::Value>) -> Result<Event> {
+    /// Create an Event from a json object. Sections unknown to this version of
+    /// Retis (e.g. the file was produced by a newer one) are skipped and their
+    /// names added to `unknown`.
+    pub(crate) fn from_json_obj(
+        mut obj: HashMap<String, serde_json::Value>,
+        unknown: &mut Vec<String>,
+    ) -> Result<Event> {
         let mut event = Event::new();
 
         for (owner, value) in obj.drain() {
-            let parser = event_sections()?
-                .get(&owner)
-                .ok_or_else(|| anyhow!("json contains an unsupported event {}", owner))?;
-
-            debug!("Unmarshaling event section {owner}: {value}");
-            let section = parser(value).map_err(|e| {
-                anyhow!("Failed to create EventSection for owner {owner} from json: {e}")
-            })?;
-            event.insert_section(SectionId::from_u8(section.id())?, section)?;
+            if !event_sections()?.contains_key(owner.as_str()) {
+                unknown.push(owner);
+                continue;
+            }
+            event.insert_json_section(&owner, value)?;
         }
         Ok(event)
     }
 
-    /// Create an Event from a json string.
-    pub(crate) fn from_json(line: String) -> Result<Event> {
+    /// Unmarshal a section from its json representation, validating it against
+    /// the section definition, and insert it into the event.
+    pub(crate) fn insert_json_section(
+        &mut self,
+        owner: &str,
+        value: serde_json::Value,
+    ) -> Result<()> {
+        let parser = event_sections()?
+            .get(owner)
+            .ok_or_else(|| anyhow!("json contains an unsupported event {}", owner))?;
+
+        debug!("Unmarshaling event section {owner}: {value}");
+        let section = parser(value).map_err(|e| {
+            anyhow!("Failed to create EventSection for owner {owner} from json: {e}")
+        })?;
+        self.insert_section(SectionId::from_u8(section.id())?, section)
+    }
+
+    /// Create an Event from a json string. See `Event::from_json_obj`.
+    pub(crate) fn from_json(line: String, unknown: &mut Vec<String>) -> Result<Event> {
         let event_js: HashMap<String, serde_json::Value> = serde_json::from_str(line.as_str())
             .map_err(|e| anyhow!("Failed to parse json event at line {line}: {e}"))?;
 
-        Self::from_json_obj(event_js)
+        Self::from_json_obj(event_js, unknown)
     }
 
     /// Insert a new event field into an event.
@@ -127,12 +146,96 @@ impl Event {
         serde_json::Value::Object(event)
     }
 
+    /// Encode the event into a single-level json object, nested objects being
+    /// flattened using dotted keys (e.g. `skb.ip.saddr`). Arrays are kept
+    /// as-is.
+    pub fn to_json_flat(&self) -> serde_json::Value {
+        let mut flat = serde_json::Map::new();
+        flatten_json(String::new(), self.to_json(), &mut flat);
+        serde_json::Value::Object(flat)
+    }
+
     /// Iterator over the existing sections
     pub fn sections(&self) -> impl Iterator<Item = SectionId> + '_ {
         self.0.keys().map(|s| s.to_owned())
     }
+
+    /// Timestamp of the event, if it has a common section.
+    pub fn timestamp(&self) -> Option<u64> {
+        self.common().map(|common| common.timestamp)
+    }
+}
+
+/// Insert all non-object values found in `value` into `flat`, using their path
+/// as key.
+fn flatten_json(
+    prefix: String,
+    value: serde_json::Value,
+    flat: &mut serde_json::Map<String, serde_json::Value>,
+) {
+    match value {
+        serde_json::Value::Object(obj) => obj.into_iter().for_each(|(key, value)| {
+            let key = match prefix.is_empty() {
+                true => key,
+                false => format!("{prefix}.{key}"),
+            };
+            flatten_json(key, value, flat);
+        }),
+        value => {
+            flat.insert(prefix, value);
+        }
+    }
+}
+
+/// Generates typed accessors to event sections, so users don't have to
+/// downcast sections themselves using `Event::get_section`.
+macro_rules! section_accessors {
+    ($($name:ident: $section:ident => $type:ty),* $(,)?) => {
+        impl Event {
+            $(
+                #[doc = concat!("Get the `", stringify!($type), "` section, if any.")]
+                pub fn $name(&self) -> Option<&$type> {
+                    self.get_section::<$type>(SectionId::$section)
+                }
+            )*
+        }
+    };
 }
 
+section_accessors!(
+    common: Common => CommonEvent,
+    kernel: Kernel => KernelEvent,
+    user: Userspace => UserEvent,
+    tracking: Tracking => TrackingInfo,
+    skb_tracking: SkbTracking => SkbTrackingEvent,
+    skb_drop: SkbDrop => SkbDropEvent,
+    skb: Skb => SkbEvent,
+    ovs: Ovs => OvsEvent,
+    nft: Nft => NftEvent,
+    ct: Ct => CtEvent,
+    startup: Startup => StartupEvent,
+    neigh: Neigh => NeighEvent,
+    sock: Sock => SockEvent,
+    netlink: Netlink => NetlinkEvent,
+    marker: Marker => MarkerEvent,
+    clock_sync: ClockSync => ClockSyncEvent,
+    icmp: Icmp => IcmpEvent,
+    severity: Severity => SeverityEvent,
+    tc: Tc => TcEvent,
+    ethtool_stats: EthtoolStats => EthtoolStatsEvent,
+    netstat: Netstat => NetstatEvent,
+    probe_desc: ProbeDesc => ProbeDescEvent,
+    host: Host => HostEvent,
+    parse_error: ParseError => ParseErrorEvent,
+    ipvs: Ipvs => IpvsEvent,
+    seg6: Seg6 => Seg6Event,
+    mptcp: Mptcp => MptcpEvent,
+    tls: Tls => TlsEvent,
+    bpf_objects: BpfObjects => BpfObjectsEvent,
+    log: Log => LogEvent,
+    lost_events: LostEvents => LostEventsEvent,
+);
+
 impl EventFmt for Event {
     fn event_fmt(&self, f: &mut Formatter, format: &DisplayFormat) -> std::fmt::Result {
         // First format the first event line starting with the always-there
@@ -141,6 +244,10 @@ impl EventFmt for Event {
             .get(&SectionId::Common)
             .unwrap()
             .event_fmt(f, format)?;
+        if let Some(host) = self.0.get(&SectionId::Host) {
+            write!(f, " ")?;
+            host.event_fmt(f, format)?;
+        }
         if let Some(kernel) = self.0.get(&SectionId::Kernel) {
             write!(f, " ")?;
             kernel.event_fmt(f, format)?;
@@ -162,27 +269,32 @@ impl EventFmt for Event {
             write!(f, " ")?;
             skb_drop.event_fmt(f, format)?;
         }
+        if let Some(severity) = self.0.get(&SectionId::Severity) {
+            write!(f, " ")?;
+            severity.event_fmt(f, format)?;
+        }
 
         // Separator between each following sections.
         let sep = if format.multiline { '\n' } else { ' ' };
 
         // If we have a stack trace, show it.
-        if let Some(kernel) = self.get_section::<KernelEvent>(SectionId::Kernel) {
-            if let Some(stack) = &kernel.stack_trace {
-                f.conf.inc_level(4);
-                write!(f, "{sep}")?;
-                stack.event_fmt(f, format)?;
-                f.conf.reset_level();
-            }
+        let stack = self
+            .kernel()
+            .and_then(|kernel| kernel.stack_trace.as_ref())
+            .or_else(|| self.user().and_then(|user| user.stack_trace.as_ref()));
+        if let Some(stack) = stack {
+            f.conf.inc_level(4);
+            write!(f, "{sep}")?;
+            stack.event_fmt(f, format)?;
+            f.conf.reset_level();
         }
 
         f.conf.inc_level(2);
 
-        // Finally show all sections.
+        // Finally show all sections, but the ones already displayed.
         (SectionId::Skb as u8..SectionId::_MAX as u8)
-            .collect::<Vec<u8>>()
-            .iter()
-            .filter_map(|id| self.0.get(&SectionId::from_u8(*id).unwrap()))
+            .filter(|id| *id != SectionId::Severity as u8 && *id != SectionId::Host as u8)
+            .filter_map(|id| self.0.get(&SectionId::from_u8(id).unwrap()))
             .try_for_each(|section| {
                 write!(f, "{sep}")?;
                 section.event_fmt(f, format)
@@ -207,8 +319,28 @@ pub enum SectionId {
     Nft = 9,
     Ct = 10,
     Startup = 11,
+    Neigh = 12,
+    Sock = 13,
+    Netlink = 14,
+    Marker = 15,
+    ClockSync = 16,
+    Icmp = 17,
+    Severity = 18,
+    Tc = 19,
+    EthtoolStats = 20,
+    Netstat = 21,
+    ProbeDesc = 22,
+    Host = 23,
+    ParseError = 24,
+    Ipvs = 25,
+    Seg6 = 26,
+    Mptcp = 27,
+    Tls = 28,
+    BpfObjects = 29,
+    Log = 30,
+    LostEvents = 31,
     // TODO: use std::mem::variant_count once in stable.
-    _MAX = 12,
+    _MAX = 32,
 }
 
 impl SectionId {
@@ -227,6 +359,26 @@ impl SectionId {
             9 => Nft,
             10 => Ct,
             11 => Startup,
+            12 => Neigh,
+            13 => Sock,
+            14 => Netlink,
+            15 => Marker,
+            16 => ClockSync,
+            17 => Icmp,
+            18 => Severity,
+            19 => Tc,
+            20 => EthtoolStats,
+            21 => Netstat,
+            22 => ProbeDesc,
+            23 => Host,
+            24 => ParseError,
+            25 => Ipvs,
+            26 => Seg6,
+            27 => Mptcp,
+            28 => Tls,
+            29 => BpfObjects,
+            30 => Log,
+            31 => LostEvents,
             x => bail!("Can't construct a SectionId from {}", x),
         })
     }
@@ -246,6 +398,26 @@ impl SectionId {
             Nft => "nft",
             Ct => "ct",
             Startup => "startup",
+            Neigh => "neigh",
+            Sock => "sock",
+            Netlink => "netlink",
+            Marker => "marker",
+            ClockSync => "clock-sync",
+            Icmp => "icmp",
+            Severity => "severity",
+            Tc => "tc",
+            EthtoolStats => "ethtool-stats",
+            Netstat => "netstat",
+            ProbeDesc => "probe-desc",
+            Host => "host",
+            ParseError => "parse-error",
+            Ipvs => "ipvs",
+            Seg6 => "seg6",
+            Mptcp => "mptcp",
+            Tls => "tls",
+            BpfObjects => "bpf-objects",
+            Log => "log",
+            LostEvents => "lost-events",
             _MAX => "_max",
         }
     }
@@ -276,6 +448,26 @@ impl FromStr for SectionId {
             "nft" => Nft,
             "ct" => Ct,
             "startup" => Startup,
+            "neigh" => Neigh,
+            "sock" => Sock,
+            "netlink" => Netlink,
+            "marker" => Marker,
+            "clock-sync" => ClockSync,
+            "icmp" => Icmp,
+            "severity" => Severity,
+            "tc" => Tc,
+            "ethtool-stats" => EthtoolStats,
+            "netstat" => Netstat,
+            "probe-desc" => ProbeDesc,
+            "host" => Host,
+            "parse-error" => ParseError,
+            "ipvs" => Ipvs,
+            "seg6" => Seg6,
+            "mptcp" => Mptcp,
+            "tls" => Tls,
+            "bpf-objects" => BpfObjects,
+            "log" => Log,
+            "lost-events" => LostEvents,
             x => bail!("Can't construct a SectionId from {}", x),
         })
     }
@@ -308,6 +500,26 @@ fn event_sections() -> Result<&'static EventSectionMap> {
         insert_section!(events, CtEvent);
         insert_section!(events, StartupEvent);
         insert_section!(events, TrackingInfo);
+        insert_section!(events, NeighEvent);
+        insert_section!(events, SockEvent);
+        insert_section!(events, NetlinkEvent);
+        insert_section!(events, MarkerEvent);
+        insert_section!(events, ClockSyncEvent);
+        insert_section!(events, IcmpEvent);
+        insert_section!(events, SeverityEvent);
+        insert_section!(events, TcEvent);
+        insert_section!(events, EthtoolStatsEvent);
+        insert_section!(events, NetstatEvent);
+        insert_section!(events, ProbeDescEvent);
+        insert_section!(events, HostEvent);
+        insert_section!(events, ParseErrorEvent);
+        insert_section!(events, IpvsEvent);
+        insert_section!(events, Seg6Event);
+        insert_section!(events, MptcpEvent);
+        insert_section!(events, TlsEvent);
+        insert_section!(events, BpfObjectsEvent);
+        insert_section!(events, LogEvent);
+        insert_section!(events, LostEventsEvent);
 
         Ok(events)
     })
@@ -379,8 +591,56 @@ impl EventSeries {
         serde_json::Value::Array(self.events.iter().map(|e| e.to_json()).collect())
     }
 
-    /// Create an EventSeries from a json string.
-    pub(crate) fn from_json(line: String) -> Result<EventSeries> {
+    /// Encode the EventSeries into a json array of flattened events (see
+    /// `Event::to_json_flat`).
+    pub fn to_json_flat(&self) -> serde_json::Value {
+        serde_json::Value::Array(self.events.iter().map(|e| e.to_json_flat()).collect())
+    }
+
+    /// Iterator over the events of the series, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &Event> {
+        self.events.iter()
+    }
+
+    /// Tracking id of the series, if its events are tracked.
+    pub fn tracking_id(&self) -> Option<u128> {
+        self.iter()
+            .find_map(|event| event.tracking())
+            .map(|info| info.skb.tracking_id())
+    }
+
+    /// Time elapsed between the first and the last events of the series.
+    pub fn duration(&self) -> Option<Duration> {
+        let mut timestamps = self.iter().filter_map(|event| event.timestamp());
+        let first = timestamps.next()?;
+        let last = timestamps.last().unwrap_or(first);
+        Some(Duration::from_nanos(last.saturating_sub(first)))
+    }
+
+    /// Time elapsed between the first event matching `from` and the first
+    /// event matching `to` following it, e.g. between two probes.
+    ///
+    /// ```
+    /// # use retis_events::EventSeries;
+    /// # let series = EventSeries::default();
+    /// let latency = series.latency_between(
+    ///     |e| e.kernel().is_some_and(|k| k.symbol == "ip_rcv"),
+    ///     |e| e.skb_drop().is_some(),
+    /// );
+    /// ```
+    pub fn latency_between<F, T>(&self, from: F, to: T) -> Option<Duration>
+    where
+        F: Fn(&Event) -> bool,
+        T: Fn(&Event) -> bool,
+    {
+        let mut events = self.iter().skip_while(|event| !from(event));
+        let start = events.next()?.timestamp()?;
+        let end = events.find(|event| to(event))?.timestamp()?;
+        Some(Duration::from_nanos(end.saturating_sub(start)))
+    }
+
+    /// Create an EventSeries from a json string. See `Event::from_json_obj`.
+    pub(crate) fn from_json(line: String, unknown: &mut Vec<String>) -> Result<EventSeries> {
         let mut series = EventSeries::default();
 
         let mut series_js: Vec<HashMap<String, serde_json::Value>> =
@@ -388,7 +648,7 @@ impl EventSeries {
                 .map_err(|e| anyhow!("Failed to parse json series at line {line}: {e}"))?;
 
         for obj in series_js.drain(..) {
-            let event = Event::from_json_obj(obj)?;
+            let event = Event::from_json_obj(obj, unknown)?;
             series.events.push(event);
         }
         Ok(series)
@@ -421,3 +681,112 @@ pub mod test {
 
 #[cfg(feature = "test-events")]
 pub use test::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_type;
+
+    fn default_field() -> u32 {
+        42
+    }
+
+    #[event_type]
+    #[derive(Default)]
+    struct EvolvedSection {
+        #[retis(rename_from = "old_name", rename_from = "older_name")]
+        new_name: u32,
+        #[retis(default)]
+        added: bool,
+        #[retis(default = "default_field")]
+        added_with_default: u32,
+    }
+
+    #[test]
+    fn section_evolution() {
+        for json in [
+            r#"{"old_name":1}"#,
+            r#"{"older_name":1}"#,
+            r#"{"new_name":1,"added":false,"added_with_default":42}"#,
+        ] {
+            let section: EvolvedSection = serde_json::from_str(json).unwrap();
+            assert_eq!(section.new_name, 1);
+            assert!(!section.added);
+            assert_eq!(section.added_with_default, 42);
+        }
+
+        assert!(serde_json::from_str::<EvolvedSection>(r#"{"added":true}"#).is_err());
+    }
+
+    fn event(timestamp: u64, symbol: &str) -> Event {
+        let mut event = Event::new();
+        event
+            .insert_section(
+                SectionId::Common,
+                Box::new(CommonEvent {
+                    timestamp,
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        event
+            .insert_section(
+                SectionId::Kernel,
+                Box::new(KernelEvent {
+                    symbol: symbol.to_string(),
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        event
+    }
+
+    #[test]
+    fn accessors() {
+        let event = event(42, "ip_rcv");
+        assert_eq!(event.timestamp(), Some(42));
+        assert_eq!(event.kernel().unwrap().symbol, "ip_rcv");
+        assert!(event.skb().is_none());
+        assert!(Event::new().timestamp().is_none());
+    }
+
+    #[test]
+    fn json_flat() {
+        let event = event(42, "ip_rcv");
+        let flat = event.to_json_flat();
+        let flat = flat.as_object().unwrap();
+
+        assert_eq!(flat["common.timestamp"], 42);
+        assert_eq!(flat["kernel.symbol"], "ip_rcv");
+        assert!(flat.values().all(|v| !v.is_object()));
+    }
+
+    #[test]
+    fn series() {
+        let series = EventSeries {
+            events: vec![
+                event(10, "ip_rcv"),
+                event(15, "ip_local_deliver"),
+                event(40, "kfree_skb"),
+            ],
+        };
+        let probe =
+            |name: &'static str| move |e: &Event| e.kernel().is_some_and(|k| k.symbol == name);
+
+        assert_eq!(series.duration(), Some(Duration(30)));
+        assert_eq!(
+            series.latency_between(probe("ip_rcv"), probe("kfree_skb")),
+            Some(Duration(30))
+        );
+        assert_eq!(
+            series.latency_between(probe("ip_local_deliver"), probe("kfree_skb")),
+            Some(Duration(25))
+        );
+        assert_eq!(
+            series.latency_between(probe("kfree_skb"), probe("ip_rcv")),
+            None
+        );
+        assert!(series.tracking_id().is_none());
+        assert!(EventSeries::default().duration().is_none());
+    }
+}
@@ -1,14 +1,20 @@
-//! Handles the file (json) to Rust event retrieval and the unmarshaling process.
+//! Handles the file (json or binary, optionally compressed) to Rust event
+//! retrieval and the unmarshaling process.
 
 use std::{
-    fs::File,
-    io::{BufRead, BufReader, Seek},
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read},
+    os::unix::fs::FileTypeExt,
     path::Path,
+    thread,
+    time::Duration,
 };
 
 use anyhow::{anyhow, bail, Result};
+use log::warn;
 
-use super::{Event, EventSeries};
+use super::{binary, Event, EventSeries};
 
 // Type of file that is being processed.
 #[derive(Debug, Clone)]
@@ -19,25 +25,133 @@ pub enum FileType {
     Series,
 }
 
+// Format of the file that is being processed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FileFormat {
+    /// One json object per line.
+    Json,
+    /// Binary format, see `crate::binary`.
+    Binary,
+}
+
+/// Magic number of zstd frames.
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Interval at which a followed file is checked for new data.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Reader waiting for data to be appended to a file once its end is reached,
+/// like `tail -f`, for as long as `running` returns true.
+struct FollowReader<F> {
+    file: File,
+    running: F,
+}
+
+impl<F> Read for FollowReader<F>
+where
+    F: Fn() -> bool,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.file.read(buf)? {
+                0 if !buf.is_empty() && (self.running)() => thread::sleep(FOLLOW_INTERVAL),
+                n => return Ok(n),
+            }
+        }
+    }
+}
+
+/// Is the input a stream (`-` for the standard input, a named pipe, etc.),
+/// which can only be read once and not rewound, instead of a regular file.
+pub fn is_stream<P>(file: P) -> bool
+where
+    P: AsRef<Path>,
+{
+    let file = file.as_ref();
+    file == Path::new("-")
+        || fs::metadata(file).is_ok_and(|m| {
+            let t = m.file_type();
+            t.is_fifo() || t.is_socket() || t.is_char_device()
+        })
+}
+
 /// File events factory retrieving and unmarshaling events
 /// parts.
 pub struct FileEventsFactory {
-    reader: BufReader<File>,
+    reader: BufReader<Box<dyn Read + Send + Sync>>,
     filetype: FileType,
+    format: FileFormat,
+    /// First line of json files, read when detecting the file type. Files are
+    /// not rewound so they can be pipes (e.g. `/dev/stdin`).
+    first: Option<String>,
+    /// Sections unknown to this version of Retis (e.g. the file was produced
+    /// by a newer one), which were skipped, and the number of events
+    /// containing them.
+    unknown: BTreeMap<String, u64>,
+    /// Was the end of the file reached (and the unknown sections reported)?
+    done: bool,
 }
 
 impl FileEventsFactory {
+    /// Read events from a file, `-` meaning the standard input. Named pipes
+    /// and other streams are supported.
     pub fn new<P>(file: P) -> Result<Self>
     where
         P: AsRef<Path>,
     {
-        let mut reader = BufReader::new(
-            File::open(&file)
-                .map_err(|e| anyhow!("Could not open {}: {e}", file.as_ref().display()))?,
-        );
-        let filetype = Self::detect_type(&mut reader)?;
+        match file.as_ref() == Path::new("-") {
+            true => Self::from_input(Box::new(io::stdin())),
+            false => Self::from_input(Box::new(Self::open(file)?)),
+        }
+    }
+
+    /// Follow a file being written to (e.g. by a running collection), like
+    /// `tail -f`: once the end of the file is reached, wait for new events to
+    /// be appended instead of stopping. The end of the file is only reported
+    /// once `running` returns false. Streams are read as-is, as they already
+    /// wait for new data and their end is final.
+    pub fn follow<P, F>(file: P, running: F) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        if is_stream(&file) {
+            return Self::new(file);
+        }
+
+        Self::from_input(Box::new(FollowReader {
+            file: Self::open(file)?,
+            running,
+        }))
+    }
 
-        Ok(FileEventsFactory { reader, filetype })
+    fn open<P>(file: P) -> Result<File>
+    where
+        P: AsRef<Path>,
+    {
+        File::open(&file).map_err(|e| anyhow!("Could not open {}: {e}", file.as_ref().display()))
+    }
+
+    fn from_input(input: Box<dyn Read + Send + Sync>) -> Result<Self> {
+        let mut file = BufReader::new(input);
+
+        // Compressed files are transparently decompressed.
+        let input: Box<dyn Read + Send + Sync> = match file.fill_buf()?.starts_with(ZSTD_MAGIC) {
+            true => Box::new(zstd::Decoder::with_buffer(file)?),
+            false => Box::new(file),
+        };
+
+        let mut reader = BufReader::new(input);
+        let (format, filetype, first) = Self::detect_type(&mut reader)?;
+
+        Ok(FileEventsFactory {
+            reader,
+            filetype,
+            format,
+            first,
+            unknown: BTreeMap::new(),
+            done: false,
+        })
     }
 }
 
@@ -49,13 +163,15 @@ impl FileEventsFactory {
             FileType::Event => (),
             FileType::Series => bail!("Cannot read event from sorted file"),
         }
-        let mut line = String::new();
+        let mut unknown = Vec::new();
+        let event = match self.format {
+            FileFormat::Binary => binary::read_event(&mut self.reader, &mut unknown),
+            FileFormat::Json => self.next_json(|line| Event::from_json(line, &mut unknown)),
+        };
 
-        match self.reader.read_line(&mut line) {
-            Err(e) => Err(e.into()),
-            Ok(0) => Ok(None),
-            Ok(_) => Ok(Some(Event::from_json(line)?)),
-        }
+        self.account_unknown(unknown);
+        self.check_end(&event);
+        event
     }
 
     /// Retrieve the next series or None if we've reached the end of the file.
@@ -65,19 +181,88 @@ impl FileEventsFactory {
             FileType::Event => bail!("Cannot read series from unsorted file"),
             FileType::Series => (),
         }
-        let mut line = String::new();
+        let mut unknown = Vec::new();
+        let series = match self.format {
+            FileFormat::Binary => binary::read_series(&mut self.reader, &mut unknown),
+            FileFormat::Json => self.next_json(|line| EventSeries::from_json(line, &mut unknown)),
+        };
+
+        self.account_unknown(unknown);
+        self.check_end(&series);
+        series
+    }
+
+    /// Account the unknown sections found in an event or series.
+    fn account_unknown(&mut self, unknown: Vec<String>) {
+        unknown
+            .into_iter()
+            .for_each(|name| *self.unknown.entry(name).or_default() += 1);
+    }
+
+    /// Report the unknown sections once the end of the file is reached, instead
+    /// of failing on or warning for each event containing them.
+    fn check_end<T>(&mut self, next: &Result<Option<T>>) {
+        if self.done || !matches!(next, Ok(None)) {
+            return;
+        }
+        self.done = true;
+
+        if self.unknown.is_empty() {
+            return;
+        }
+
+        let sections = self
+            .unknown
+            .iter()
+            .map(|(name, count)| format!("{name} ({count} event(s))"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        warn!("Skipped sections unknown to this version of Retis (was the file produced by a newer one?): {sections}");
+    }
 
+    /// Sections unknown to this version of Retis which were skipped, and the
+    /// number of events containing them.
+    pub fn unknown_sections(&self) -> &BTreeMap<String, u64> {
+        &self.unknown
+    }
+
+    /// Read and parse the next json line. An invalid last line not ending with
+    /// a newline is the result of an interrupted write (e.g. the collection
+    /// was killed): it is ignored so the file can be read up to the last
+    /// complete event.
+    fn next_json<T, F>(&mut self, mut parse: F) -> Result<Option<T>>
+    where
+        F: FnMut(String) -> Result<T>,
+    {
+        if let Some(line) = self.first.take() {
+            return Ok(Some(parse(line)?));
+        }
+
+        let mut line = String::new();
         match self.reader.read_line(&mut line) {
             Err(e) => Err(e.into()),
             Ok(0) => Ok(None),
-            Ok(_) => Ok(Some(EventSeries::from_json(line)?)),
+            Ok(_) if !line.ends_with('\n') => match parse(line) {
+                Ok(t) => Ok(Some(t)),
+                Err(e) => {
+                    warn!("Ignoring truncated data at the end of the file ({e})");
+                    Ok(None)
+                }
+            },
+            Ok(_) => Ok(Some(parse(line)?)),
         }
     }
 
-    fn detect_type<T>(reader: &mut T) -> Result<FileType>
+    /// Detect the format and type of the file. For json files, the first line
+    /// is returned as it was consumed.
+    fn detect_type<T>(reader: &mut T) -> Result<(FileFormat, FileType, Option<String>)>
     where
-        T: BufRead + Seek,
+        T: BufRead,
     {
+        if binary::is_binary(reader.fill_buf()?) {
+            return Ok((FileFormat::Binary, binary::read_header(reader)?, None));
+        }
+
         let mut line = String::new();
 
         match reader.read_line(&mut line) {
@@ -85,21 +270,25 @@ impl FileEventsFactory {
             Ok(0) => return Err(anyhow!("File is empty")),
             Ok(_) => (),
         }
-        reader.rewind()?;
 
         let first: serde_json::Value = serde_json::from_str(line.as_str())
             .map_err(|e| anyhow!("Failed to parse event file: {:?}", e))?;
 
-        match first {
-            serde_json::Value::Object(_) => Ok(FileType::Event),
-            serde_json::Value::Array(_) => Ok(FileType::Series),
+        let filetype = match first {
+            serde_json::Value::Object(_) => FileType::Event,
+            serde_json::Value::Array(_) => FileType::Series,
             _ => bail!("File contains invalid json data"),
-        }
+        };
+        Ok((FileFormat::Json, filetype, Some(line)))
     }
 
     pub fn file_type(&self) -> &FileType {
         &self.filetype
     }
+
+    pub fn file_format(&self) -> FileFormat {
+        self.format
+    }
 }
 
 #[cfg(test)]
@@ -116,4 +305,130 @@ mod tests {
         }
         assert!(events.len() == 4);
     }
+
+    #[test]
+    fn read_truncated_file() {
+        let data = std::fs::read("test_data/test_events.json").unwrap();
+        let file = tempfile::NamedTempFile::new().unwrap();
+
+        // Cut the file in the middle of the last event.
+        std::fs::write(file.path(), &data[..data.len() - 10]).unwrap();
+
+        let mut fact = FileEventsFactory::new(file.path()).unwrap();
+        let mut events = Vec::new();
+        while let Some(event) = fact.next_event().unwrap() {
+            events.push(event)
+        }
+        assert!(events.len() == 3);
+    }
+
+    #[test]
+    fn read_compressed_file() {
+        let data = std::fs::read("test_data/test_events.json").unwrap();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), zstd::encode_all(data.as_slice(), 0).unwrap()).unwrap();
+
+        let mut fact = FileEventsFactory::new(file.path()).unwrap();
+        let mut events = Vec::new();
+        while let Some(event) = fact.next_event().unwrap() {
+            events.push(event)
+        }
+        assert!(events.len() == 4);
+    }
+
+    #[test]
+    fn follow_file() {
+        let data = std::fs::read_to_string("test_data/test_events.json").unwrap();
+        let lines = data.lines().collect::<Vec<_>>();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let path = file.path().to_path_buf();
+        std::fs::write(&path, format!("{}\n{}", lines[0], &lines[1][..10])).unwrap();
+
+        let running = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let mut fact = FileEventsFactory::follow(&path, {
+            let running = running.clone();
+            move || running.load(std::sync::atomic::Ordering::Relaxed)
+        })
+        .unwrap();
+        assert!(fact.next_event().unwrap().is_some());
+
+        // Complete the partially written event while the factory waits for it.
+        let writer = std::thread::spawn({
+            let path = path.clone();
+            let rest = format!("{}\n", &lines[1][10..]);
+            let running = running.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(500));
+                let mut file = std::fs::OpenOptions::new()
+                    .append(true)
+                    .open(&path)
+                    .unwrap();
+                std::io::Write::write_all(&mut file, rest.as_bytes()).unwrap();
+                std::thread::sleep(Duration::from_millis(500));
+                running.store(false, std::sync::atomic::Ordering::Relaxed);
+            }
+        });
+
+        assert!(fact.next_event().unwrap().is_some());
+        assert!(fact.next_event().unwrap().is_none());
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn read_fifo() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events");
+        assert!(std::process::Command::new("mkfifo")
+            .arg(&path)
+            .status()
+            .unwrap()
+            .success());
+        assert!(is_stream(&path));
+        assert!(is_stream("-"));
+        assert!(!is_stream("test_data/test_events.json"));
+
+        let writer = std::thread::spawn({
+            let path = path.clone();
+            move || std::fs::write(&path, std::fs::read("test_data/test_events.json").unwrap())
+        });
+
+        // Streams can't be followed, reading them stops once the writer
+        // closes them.
+        let mut fact = FileEventsFactory::follow(&path, || true).unwrap();
+        let mut events = 0;
+        while fact.next_event().unwrap().is_some() {
+            events += 1;
+        }
+        assert_eq!(events, 4);
+        writer.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn read_unknown_sections() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            concat!(
+                r#"{"common":{"timestamp":42},"future":{"field":1}}"#,
+                "\n",
+                r#"{"common":{"timestamp":43},"future":{"field":2},"later":{}}"#,
+                "\n",
+                r#"{"common":{"timestamp":44}}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+
+        let mut fact = FileEventsFactory::new(file.path()).unwrap();
+        let mut events = Vec::new();
+        while let Some(event) = fact.next_event().unwrap() {
+            events.push(event)
+        }
+        assert!(events.len() == 3);
+        assert!(events.iter().all(|e| e.common().is_some()));
+        assert_eq!(
+            fact.unknown_sections().iter().collect::<Vec<_>>(),
+            vec![(&"future".to_string(), &2), (&"later".to_string(), &1)]
+        );
+    }
 }
@@ -0,0 +1,453 @@
+//! # Filter
+//!
+//! Expressions used to filter events, e.g. when post-processing them. They are
+//! evaluated over the flattened json representation of events (see
+//! `Event::to_json_flat`), fields being referenced by their dotted keys:
+//!
+//! ```text
+//! skb.ip.saddr == "10.0.0.1" && kernel.symbol ~ "drop"
+//! ```
+//!
+//! Supported operators, by decreasing precedence:
+//! - `(` and `)` for grouping.
+//! - `!`, negating an expression.
+//! - `==`, `!=`, `<`, `<=`, `>`, `>=` comparing a field to a value (a quoted
+//!   string, a number or a boolean), and `~` checking a field contains a
+//!   string. A field alone checks it is present in the event, e.g. `skb.tcp`
+//!   matches events having TCP information.
+//! - `&&`.
+//! - `||`.
+//!
+//! Comparisons on fields not found in the event are false. Array fields match
+//! if any of their elements does.
+
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::{Map, Number, Value};
+
+use super::{Event, EventSeries};
+
+/// Filter on events, parsed from an expression.
+#[derive(Clone)]
+pub struct EventFilter {
+    /// Expression as given by the user, for display purposes.
+    input: String,
+    expr: Expr,
+}
+
+impl FromStr for EventFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+        };
+
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            bail!("Unexpected '{token}' in filter expression '{s}'");
+        }
+
+        Ok(Self {
+            input: s.to_string(),
+            expr,
+        })
+    }
+}
+
+impl fmt::Debug for EventFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EventFilter({})", self.input)
+    }
+}
+
+impl EventFilter {
+    /// Does the event match the filter?
+    pub fn matches(&self, event: &Event) -> bool {
+        match event.to_json_flat() {
+            Value::Object(fields) => self.expr.eval(&fields),
+            _ => false,
+        }
+    }
+
+    /// Does any event of the series match the filter?
+    pub fn matches_series(&self, series: &EventSeries) -> bool {
+        series.events.iter().any(|e| self.matches(e))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    /// Field, or section (e.g. `skb.tcp`), is present in the event.
+    Exists(String),
+    Cmp(String, Op, Literal),
+}
+
+impl Expr {
+    fn eval(&self, fields: &Map<String, Value>) -> bool {
+        match self {
+            Expr::Or(l, r) => l.eval(fields) || r.eval(fields),
+            Expr::And(l, r) => l.eval(fields) && r.eval(fields),
+            Expr::Not(e) => !e.eval(fields),
+            Expr::Exists(field) => fields.iter().any(|(key, value)| {
+                !value.is_null()
+                    && key
+                        .strip_prefix(field.as_str())
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+            }),
+            Expr::Cmp(field, op, lit) => match fields.get(field) {
+                Some(Value::Array(values)) => values.iter().any(|v| op.eval(v, lit)),
+                Some(value) => op.eval(value, lit),
+                None => false,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+impl Op {
+    fn eval(&self, value: &Value, lit: &Literal) -> bool {
+        if *self == Op::Contains {
+            return to_string(value).contains(&lit.to_string());
+        }
+
+        let ord = match (value, lit) {
+            (Value::Number(v), Literal::Num(l)) => cmp_numbers(v, l),
+            (Value::Bool(v), Literal::Bool(l)) => Some(v.cmp(l)),
+            (Value::String(v), Literal::Str(l)) => Some(v.as_str().cmp(l)),
+            // Allow matching non-string values using their representation,
+            // e.g. `skb.tcp.dport == "80"`.
+            (Value::Null, _) => None,
+            (v, Literal::Str(l)) => Some(to_string(v).as_str().cmp(l)),
+            _ => None,
+        };
+
+        match ord {
+            Some(ord) => match self {
+                Op::Eq => ord == Ordering::Equal,
+                Op::Ne => ord != Ordering::Equal,
+                Op::Lt => ord == Ordering::Less,
+                Op::Le => ord != Ordering::Greater,
+                Op::Gt => ord == Ordering::Greater,
+                Op::Ge => ord != Ordering::Less,
+                Op::Contains => unreachable!(),
+            },
+            // Values of different types are never equal.
+            None => *self == Op::Ne,
+        }
+    }
+}
+
+/// String representation of a json value, strings not being quoted.
+fn to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+/// Compare two json numbers, as integers if possible.
+fn cmp_numbers(a: &Number, b: &Number) -> Option<Ordering> {
+    let int = |n: &Number| {
+        n.as_u64()
+            .map(i128::from)
+            .or_else(|| n.as_i64().map(i128::from))
+    };
+
+    match (int(a), int(b)) {
+        (Some(a), Some(b)) => Some(a.cmp(&b)),
+        _ => a.as_f64()?.partial_cmp(&b.as_f64()?),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Literal {
+    Str(String),
+    Num(Number),
+    Bool(bool),
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Str(s) => write!(f, "{s}"),
+            Literal::Num(n) => write!(f, "{n}"),
+            Literal::Bool(b) => write!(f, "{b}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Lit(Literal),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(s) => write!(f, "{s}"),
+            Token::Lit(Literal::Str(s)) => write!(f, "\"{s}\""),
+            Token::Lit(l) => write!(f, "{l}"),
+            Token::Op(op) => write!(
+                f,
+                "{}",
+                match op {
+                    Op::Eq => "==",
+                    Op::Ne => "!=",
+                    Op::Lt => "<",
+                    Op::Le => "<=",
+                    Op::Gt => ">",
+                    Op::Ge => ">=",
+                    Op::Contains => "~",
+                }
+            ),
+            Token::And => write!(f, "&&"),
+            Token::Or => write!(f, "||"),
+            Token::Not => write!(f, "!"),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '~' => Token::Op(Op::Contains),
+            '&' if chars.next_if_eq(&'&').is_some() => Token::And,
+            '|' if chars.next_if_eq(&'|').is_some() => Token::Or,
+            '=' if chars.next_if_eq(&'=').is_some() => Token::Op(Op::Eq),
+            '!' if chars.next_if_eq(&'=').is_some() => Token::Op(Op::Ne),
+            '!' => Token::Not,
+            '<' if chars.next_if_eq(&'=').is_some() => Token::Op(Op::Le),
+            '<' => Token::Op(Op::Lt),
+            '>' if chars.next_if_eq(&'=').is_some() => Token::Op(Op::Ge),
+            '>' => Token::Op(Op::Gt),
+            '"' | '\'' => {
+                let mut lit = String::new();
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        Some('\\') => match chars.next() {
+                            Some(e) => lit.push(e),
+                            None => bail!("Unterminated string in filter expression '{s}'"),
+                        },
+                        Some(c) => lit.push(c),
+                        None => bail!("Unterminated string in filter expression '{s}'"),
+                    }
+                }
+                Token::Lit(Literal::Str(lit))
+            }
+            c if c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.' => {
+                let mut word = c.to_string();
+                while let Some(c) = chars
+                    .next_if(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-' || *c == '.')
+                {
+                    word.push(c);
+                }
+                parse_word(word)?
+            }
+            c => bail!("Unexpected '{c}' in filter expression '{s}'"),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// Parse a word: a number, a boolean or a field.
+fn parse_word(word: String) -> Result<Token> {
+    if word == "true" || word == "false" {
+        return Ok(Token::Lit(Literal::Bool(word == "true")));
+    }
+
+    if word.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+        let num = match word.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16).ok().map(Number::from),
+            None => serde_json::from_str::<Number>(&word).ok(),
+        };
+        return num
+            .map(|n| Token::Lit(Literal::Num(n)))
+            .ok_or_else(|| anyhow!("Invalid number '{word}' in filter expression"));
+    }
+
+    Ok(Token::Ident(word))
+}
+
+/// Recursive descent parser of filter expressions.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn next_if(&mut self, token: &Token) -> bool {
+        match self.tokens.get(self.pos) {
+            Some(t) if t == token => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.next_if(&Token::Or) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_unary()?;
+        while self.next_if(&Token::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LParen) => {
+                let expr = self.parse_or()?;
+                if !self.next_if(&Token::RParen) {
+                    bail!("Missing ')' in filter expression");
+                }
+                Ok(expr)
+            }
+            Some(Token::Ident(field)) => {
+                let op = match self.tokens.get(self.pos) {
+                    Some(Token::Op(op)) => *op,
+                    _ => return Ok(Expr::Exists(field)),
+                };
+                self.pos += 1;
+
+                match self.next() {
+                    Some(Token::Lit(lit)) => Ok(Expr::Cmp(field, op, lit)),
+                    Some(token) => bail!("Expected a value after '{field}', found '{token}'"),
+                    None => bail!("Expected a value after '{field}'"),
+                }
+            }
+            Some(token) => bail!("Expected a field, found '{token}'"),
+            None => bail!("Unexpected end of filter expression"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CommonEvent, KernelEvent, SectionId};
+
+    fn event() -> Event {
+        let mut event = Event::new();
+        event
+            .insert_section(
+                SectionId::Common,
+                Box::new(CommonEvent {
+                    timestamp: 1234,
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        event
+            .insert_section(
+                SectionId::Kernel,
+                Box::new(KernelEvent {
+                    symbol: "kfree_skb_reason".to_string(),
+                    probe_type: "raw_tracepoint".to_string(),
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        event
+    }
+
+    #[test]
+    fn matches() {
+        let event = event();
+        let matches = |s: &str| EventFilter::from_str(s).unwrap().matches(&event);
+
+        assert!(matches("kernel.symbol == \"kfree_skb_reason\""));
+        assert!(matches("kernel.symbol ~ 'skb'"));
+        assert!(!matches("kernel.symbol ~ 'consume'"));
+        assert!(matches(
+            "common.timestamp > 1000 && common.timestamp <= 1234"
+        ));
+        assert!(matches("common.timestamp == 0x4d2"));
+        assert!(matches("common.timestamp == '1234'"));
+        assert!(!matches("common.timestamp != 1234"));
+        assert!(matches(
+            "skb.ip.saddr == '10.0.0.1' || kernel.symbol ~ \"kfree\""
+        ));
+        assert!(!matches(
+            "skb.ip.saddr == '10.0.0.1' && kernel.symbol ~ \"kfree\""
+        ));
+        assert!(matches("!skb && kernel"));
+        assert!(matches(
+            "!(skb.ip.saddr == '10.0.0.1') && !(common.timestamp < 10)"
+        ));
+        assert!(matches("!skb-drop.drop_reason"));
+        assert!(!matches("kernel.symbol == 42"));
+        assert!(matches("kernel.symbol != 42"));
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            EventFilter::from_str("a || b && !c").unwrap().expr,
+            Expr::Or(
+                Box::new(Expr::Exists("a".to_string())),
+                Box::new(Expr::And(
+                    Box::new(Expr::Exists("b".to_string())),
+                    Box::new(Expr::Not(Box::new(Expr::Exists("c".to_string())))),
+                )),
+            )
+        );
+
+        assert!(EventFilter::from_str("").is_err());
+        assert!(EventFilter::from_str("a ==").is_err());
+        assert!(EventFilter::from_str("a == 'b").is_err());
+        assert!(EventFilter::from_str("(a == 1").is_err());
+        assert!(EventFilter::from_str("a == 1)").is_err());
+        assert!(EventFilter::from_str("a == b").is_err());
+        assert!(EventFilter::from_str("a & b").is_err());
+        assert!(EventFilter::from_str("a == 1x").is_err());
+    }
+}
@@ -30,6 +30,22 @@ pub fn etype_str(etype: u16) -> Option<&'static str> {
     })
 }
 
+/// Returns a translation of packet types (`PACKET_*`, see
+/// include/uapi/linux/if_packet.h) into a readable format.
+pub(crate) fn pkt_type_str(pkt_type: u8) -> Option<&'static str> {
+    Some(match pkt_type {
+        0 => "host",
+        1 => "broadcast",
+        2 => "multicast",
+        3 => "otherhost",
+        4 => "outgoing",
+        5 => "loopback",
+        6 => "user",
+        7 => "kernel",
+        _ => return None,
+    })
+}
+
 /// Returns a translation of some protocols into a readable format.
 pub(crate) fn protocol_str(protocol: u8) -> Option<&'static str> {
     Some(match protocol {
@@ -41,6 +57,7 @@ pub(crate) fn protocol_str(protocol: u8) -> Option<&'static str> {
         27 => "RDP",
         33 => "DCCP",
         41 => "IPv6",
+        43 => "IPv6-Route",
         47 => "GRE",
         50 => "ESP",
         51 => "AH",
@@ -54,6 +71,169 @@ pub(crate) fn protocol_str(protocol: u8) -> Option<&'static str> {
     })
 }
 
+/// Returns a translation of ICMP types and codes into a readable format.
+pub(crate) fn icmp_str(r#type: u8, code: u8) -> (Option<&'static str>, Option<&'static str>) {
+    let type_str = match r#type {
+        0 => "echo-reply",
+        3 => "dest-unreachable",
+        4 => "source-quench",
+        5 => "redirect",
+        8 => "echo-request",
+        9 => "router-advertisement",
+        10 => "router-solicitation",
+        11 => "time-exceeded",
+        12 => "parameter-problem",
+        13 => "timestamp-request",
+        14 => "timestamp-reply",
+        15 => "info-request",
+        16 => "info-reply",
+        17 => "address-mask-request",
+        18 => "address-mask-reply",
+        _ => return (None, None),
+    };
+
+    let code_str = match (r#type, code) {
+        (3, 0) => "net",
+        (3, 1) => "host",
+        (3, 2) => "protocol",
+        (3, 3) => "port",
+        (3, 4) => "frag-needed",
+        (3, 5) => "source-route-failed",
+        (3, 6) => "net-unknown",
+        (3, 7) => "host-unknown",
+        (3, 8) => "source-host-isolated",
+        (3, 9) => "net-prohibited",
+        (3, 10) => "host-prohibited",
+        (3, 11) => "net-tos",
+        (3, 12) => "host-tos",
+        (3, 13) => "admin-prohibited",
+        (3, 14) => "host-precedence-violation",
+        (3, 15) => "precedence-cutoff",
+        (5, 0) => "net",
+        (5, 1) => "host",
+        (5, 2) => "net-tos",
+        (5, 3) => "host-tos",
+        (11, 0) => "ttl",
+        (11, 1) => "frag-reassembly",
+        (12, 0) => "pointer",
+        (12, 1) => "missing-option",
+        (12, 2) => "bad-length",
+        _ => return (Some(type_str), None),
+    };
+
+    (Some(type_str), Some(code_str))
+}
+
+/// Returns a translation of ICMPv6 types and codes, including neighbor
+/// discovery messages, into a readable format.
+pub(crate) fn icmpv6_str(r#type: u8, code: u8) -> (Option<&'static str>, Option<&'static str>) {
+    let type_str = match r#type {
+        1 => "dest-unreachable",
+        2 => "packet-too-big",
+        3 => "time-exceeded",
+        4 => "parameter-problem",
+        128 => "echo-request",
+        129 => "echo-reply",
+        130 => "mld-listener-query",
+        131 => "mld-listener-report",
+        132 => "mld-listener-done",
+        133 => "router-solicitation",
+        134 => "router-advertisement",
+        135 => "neighbor-solicitation",
+        136 => "neighbor-advertisement",
+        137 => "redirect",
+        143 => "mld2-listener-report",
+        _ => return (None, None),
+    };
+
+    let code_str = match (r#type, code) {
+        (1, 0) => "no-route",
+        (1, 1) => "admin-prohibited",
+        (1, 2) => "beyond-scope",
+        (1, 3) => "addr",
+        (1, 4) => "port",
+        (1, 5) => "policy-fail",
+        (1, 6) => "reject-route",
+        (3, 0) => "hop-limit",
+        (3, 1) => "frag-reassembly",
+        (4, 0) => "header-field",
+        (4, 1) => "next-header",
+        (4, 2) => "option",
+        _ => return (Some(type_str), None),
+    };
+
+    (Some(type_str), Some(code_str))
+}
+
+/// Returns a translation of SCTP chunk types into a readable format.
+pub(crate) fn sctp_chunk_str(r#type: u8) -> Option<&'static str> {
+    Some(match r#type {
+        0 => "DATA",
+        1 => "INIT",
+        2 => "INIT_ACK",
+        3 => "SACK",
+        4 => "HEARTBEAT",
+        5 => "HEARTBEAT_ACK",
+        6 => "ABORT",
+        7 => "SHUTDOWN",
+        8 => "SHUTDOWN_ACK",
+        9 => "ERROR",
+        10 => "COOKIE_ECHO",
+        11 => "COOKIE_ACK",
+        12 => "ECNE",
+        13 => "CWR",
+        14 => "SHUTDOWN_COMPLETE",
+        15 => "AUTH",
+        64 => "I_DATA",
+        128 => "ASCONF_ACK",
+        130 => "RE_CONFIG",
+        132 => "PAD",
+        192 => "FORWARD_TSN",
+        193 => "ASCONF",
+        194 => "I_FORWARD_TSN",
+        _ => return None,
+    })
+}
+
+/// Returns a translation of DNS record types into a readable format.
+pub(crate) fn dns_type_str(r#type: u16) -> Option<&'static str> {
+    Some(match r#type {
+        1 => "A",
+        2 => "NS",
+        5 => "CNAME",
+        6 => "SOA",
+        12 => "PTR",
+        15 => "MX",
+        16 => "TXT",
+        28 => "AAAA",
+        33 => "SRV",
+        35 => "NAPTR",
+        41 => "OPT",
+        43 => "DS",
+        46 => "RRSIG",
+        48 => "DNSKEY",
+        64 => "SVCB",
+        65 => "HTTPS",
+        252 => "AXFR",
+        255 => "ANY",
+        257 => "CAA",
+        _ => return None,
+    })
+}
+
+/// Returns a translation of DNS response codes into a readable format.
+pub(crate) fn dns_rcode_str(rcode: u8) -> Option<&'static str> {
+    Some(match rcode {
+        0 => "NOERROR",
+        1 => "FORMERR",
+        2 => "SERVFAIL",
+        3 => "NXDOMAIN",
+        4 => "NOTIMP",
+        5 => "REFUSED",
+        _ => return None,
+    })
+}
+
 /// u128 representation in the events. We can't use the Rust primitive as serde
 /// does not handle the type well.
 #[event_type]
@@ -82,6 +262,28 @@ impl U128 {
 #[cfg_attr(feature = "python", derive(pyo3::IntoPyObject))]
 pub struct RawPacket(pub Vec<u8>);
 
+impl RawPacket {
+    /// Get `len` bytes of the packet starting at `offset`, if they were
+    /// captured.
+    pub fn bytes(&self, offset: usize, len: usize) -> Option<&[u8]> {
+        self.0.get(offset..offset.checked_add(len)?)
+    }
+
+    /// Extract a header field of `len` bytes (up to 8) starting at `offset`,
+    /// as an integer in host order. Fields are expected in network order.
+    pub fn field(&self, offset: usize, len: usize) -> Option<u64> {
+        if len == 0 || len > 8 {
+            return None;
+        }
+
+        Some(
+            self.bytes(offset, len)?
+                .iter()
+                .fold(0, |val, byte| val << 8 | *byte as u64),
+        )
+    }
+}
+
 impl serde::Serialize for RawPacket {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
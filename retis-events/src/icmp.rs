@@ -0,0 +1,168 @@
+use std::fmt;
+
+use super::{
+    helpers::{icmp_str, icmpv6_str},
+    skb::write_icmp,
+    *,
+};
+use crate::{event_section, event_type, Formatter};
+
+/// Functions sending ICMP errors and TCP resets on behalf of other parts of
+/// the kernel, skipped when looking for the origin of a reject.
+const REJECT_SEND_FUNCS: &[&str] = &[
+    "__icmp_send",
+    "icmp_send",
+    "__icmpv6_send",
+    "icmpv6_send",
+    "icmp6_send",
+    "icmp_ndo_send",
+    "icmpv6_ndo_send",
+    "tcp_v4_send_reset",
+    "tcp_v6_send_reset",
+    "nf_send_reset",
+    "nf_send_reset6",
+];
+
+/// Reason for the kernel not to answer a packet, or to only answer it with an
+/// error.
+#[event_type]
+#[serde(rename_all = "snake_case")]
+pub enum IcmpEventKind {
+    /// ICMP packet not sent because of the global ICMP rate limit.
+    GlobalRateLimit,
+    /// ICMP packet not sent because of the per-destination ICMP rate limit.
+    PeerRateLimit,
+    /// No socket found for the packet, an ICMP port unreachable error is sent.
+    NoSocketUnreach,
+    /// No socket found for the packet, a TCP reset is sent.
+    NoSocketReset,
+    /// An ICMP error, other than port unreachable, is sent.
+    LocalError,
+    /// A TCP reset is sent for a packet matching a socket, or by netfilter
+    /// (e.g. REJECT targets).
+    LocalReset,
+}
+
+/// ICMP error sent by the kernel.
+#[event_type]
+pub struct IcmpError {
+    /// The error is an ICMPv6 one.
+    pub icmpv6: bool,
+    /// ICMP type.
+    pub r#type: u8,
+    /// ICMP code.
+    pub code: u8,
+}
+
+/// ICMP event section. Reports ICMP packets not sent because of rate limiting,
+/// failed socket lookups and the other ICMP errors and TCP resets originated
+/// by the kernel.
+#[event_section(SectionId::Icmp)]
+pub struct IcmpEvent {
+    /// What the kernel did, or refused to do.
+    pub kind: IcmpEventKind,
+    /// ICMP error sent, for `LocalError` events.
+    pub error: Option<IcmpError>,
+}
+
+impl IcmpEvent {
+    /// Is the event reporting a packet rejected by the kernel (an ICMP error or
+    /// a TCP reset being sent)?
+    pub fn is_reject(&self) -> bool {
+        !matches!(
+            self.kind,
+            IcmpEventKind::GlobalRateLimit | IcmpEventKind::PeerRateLimit
+        )
+    }
+}
+
+impl Event {
+    /// Function which rejected the packet, for events reporting an ICMP error
+    /// or TCP reset being sent by the kernel: the first function of the stack
+    /// trace not only sending the error or reset (e.g. `nf_send_unreach` or
+    /// `ip_forward`). This requires the stack trace to be collected.
+    pub fn reject_origin(&self) -> Option<&str> {
+        if !self.icmp()?.is_reject() {
+            return None;
+        }
+
+        self.kernel()?
+            .stack_trace
+            .as_ref()?
+            .raw()
+            .iter()
+            .map(|frame| frame.split_once('+').map_or(frame.as_str(), |(f, _)| f))
+            .find(|func| !REJECT_SEND_FUNCS.contains(func))
+    }
+}
+
+impl EventFmt for IcmpEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        match self.kind {
+            IcmpEventKind::GlobalRateLimit => write!(f, "icmp rate limited (global)"),
+            IcmpEventKind::PeerRateLimit => write!(f, "icmp rate limited (destination)"),
+            IcmpEventKind::NoSocketUnreach => write!(f, "no socket, sending port unreachable"),
+            IcmpEventKind::NoSocketReset => write!(f, "no socket, sending reset"),
+            IcmpEventKind::LocalError => {
+                write!(f, "locally originated reject, sending ")?;
+                match &self.error {
+                    Some(e) if e.icmpv6 => {
+                        write!(f, "icmpv6 ")?;
+                        write_icmp(f, e.r#type, e.code, icmpv6_str(e.r#type, e.code))
+                    }
+                    Some(e) => {
+                        write!(f, "icmp ")?;
+                        write_icmp(f, e.r#type, e.code, icmp_str(e.r#type, e.code))
+                    }
+                    None => write!(f, "icmp error"),
+                }
+            }
+            IcmpEventKind::LocalReset => write!(f, "locally originated reject, sending reset"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FormatterConf, KernelEvent, StackTrace};
+
+    #[test]
+    fn reject() {
+        let icmp = IcmpEvent {
+            kind: IcmpEventKind::LocalError,
+            error: Some(IcmpError {
+                icmpv6: false,
+                r#type: 3,
+                code: 13,
+            }),
+        };
+        assert_eq!(
+            format!(
+                "{}",
+                icmp.display(&DisplayFormat::new(), &FormatterConf::new())
+            ),
+            "locally originated reject, sending icmp type dest-unreachable (3) code admin-prohibited (13)"
+        );
+
+        let mut event = Event::new();
+        event
+            .insert_section(
+                SectionId::Kernel,
+                Box::new(KernelEvent {
+                    symbol: "__icmp_send".to_string(),
+                    stack_trace: Some(StackTrace(vec![
+                        "__icmp_send+0x0".to_string(),
+                        "nf_send_unreach+0x5e".to_string(),
+                        "nft_reject_inet_eval+0x3c".to_string(),
+                    ])),
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        event
+            .insert_section(SectionId::Icmp, Box::new(icmp))
+            .unwrap();
+        assert_eq!(event.reject_origin(), Some("nf_send_unreach"));
+    }
+}
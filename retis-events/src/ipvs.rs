@@ -0,0 +1,105 @@
+use std::fmt;
+
+use super::*;
+use crate::{event_section, event_type, Formatter};
+
+/// IPVS event section. Reports the load balancing decisions taken by IPVS
+/// (e.g. kube-proxy in IPVS mode) when scheduling new connections.
+#[event_section(SectionId::Ipvs)]
+#[derive(Default)]
+pub struct IpvsEvent {
+    /// Virtual service the packet matched.
+    pub service: IpvsService,
+    /// Connection created to the real server chosen by the scheduler, if any.
+    pub conn: Option<IpvsConn>,
+    /// Why no connection was created: `no_dest` (no real server available),
+    /// `ignored` (the packet was not scheduled) or `error`.
+    pub unscheduled: Option<String>,
+}
+
+/// IPVS virtual service.
+#[event_type]
+#[derive(Default)]
+pub struct IpvsService {
+    /// Protocol of the service (tcp, udp, sctp).
+    pub protocol: String,
+    /// Virtual address and port of the service. Not set for services defined
+    /// by a firewall mark.
+    pub addr: Option<IpvsEndpoint>,
+    /// Firewall mark defining the service, if any.
+    pub fwmark: Option<u32>,
+    /// Name of the scheduler (e.g. rr, wrr, lc, sh).
+    pub scheduler: Option<String>,
+    /// Service flags (persistent, one_packet, sched1, sched2, sched3).
+    pub flags: Vec<String>,
+}
+
+/// IPVS connection.
+#[event_type]
+#[derive(Default)]
+pub struct IpvsConn {
+    /// Client address and port.
+    pub client: IpvsEndpoint,
+    /// Virtual address and port.
+    pub virt: IpvsEndpoint,
+    /// Address and port of the real server.
+    pub real: IpvsEndpoint,
+    /// Forwarding method (masq, local, tunnel, droute, bypass).
+    pub fwd: String,
+    /// Connection flags (template, one_packet, no_cport).
+    pub flags: Vec<String>,
+}
+
+/// Address and port.
+#[event_type]
+#[derive(Default)]
+pub struct IpvsEndpoint {
+    pub addr: String,
+    pub port: u16,
+}
+
+impl fmt::Display for IpvsEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.addr.contains(':') {
+            true => write!(f, "[{}]:{}", self.addr, self.port),
+            false => write!(f, "{}:{}", self.addr, self.port),
+        }
+    }
+}
+
+impl EventFmt for IpvsEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        let svc = &self.service;
+
+        write!(f, "ipvs {}", svc.protocol)?;
+        if let Some(addr) = &svc.addr {
+            write!(f, " {addr}")?;
+        }
+        if let Some(fwmark) = svc.fwmark {
+            write!(f, " fwmark {fwmark}")?;
+        }
+        if let Some(scheduler) = &svc.scheduler {
+            write!(f, " sched {scheduler}")?;
+        }
+        if !svc.flags.is_empty() {
+            write!(f, " [{}]", svc.flags.join(","))?;
+        }
+
+        if let Some(conn) = &self.conn {
+            write!(
+                f,
+                " client {} -> rs {} fwd {}",
+                conn.client, conn.real, conn.fwd
+            )?;
+            if !conn.flags.is_empty() {
+                write!(f, " [{}]", conn.flags.join(","))?;
+            }
+        }
+
+        if let Some(unscheduled) = &self.unscheduled {
+            write!(f, " unscheduled ({unscheduled})")?;
+        }
+
+        Ok(())
+    }
+}
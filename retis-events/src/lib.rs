@@ -12,8 +12,12 @@ pub use events::*;
 pub mod display;
 pub use display::*;
 
+pub mod binary;
 pub mod file;
+pub mod filter;
+pub use filter::*;
 pub mod helpers;
+pub mod oui;
 #[cfg(feature = "python")]
 pub mod python;
 #[cfg(feature = "python-embed")]
@@ -23,20 +27,44 @@ pub mod common;
 pub use common::*;
 pub mod ct;
 pub use ct::*;
+pub mod icmp;
+pub use icmp::*;
 pub mod kernel;
 pub use kernel::*;
+pub mod marker;
+pub use marker::*;
+pub mod neigh;
+pub use neigh::*;
+pub mod netlink;
+pub use netlink::*;
 pub mod nft;
 pub use nft::*;
 pub mod ovs;
 pub use ovs::*;
 pub mod time;
 pub use time::*;
+pub mod units;
+pub use units::*;
+pub mod severity;
+pub use severity::*;
 pub mod skb;
 pub use skb::*;
 pub mod skb_drop;
 pub use skb_drop::*;
 pub mod skb_tracking;
 pub use skb_tracking::*;
+pub mod sock;
+pub use sock::*;
+pub mod tc;
+pub use tc::*;
+pub mod ipvs;
+pub use ipvs::*;
+pub mod seg6;
+pub use seg6::*;
+pub mod mptcp;
+pub use mptcp::*;
+pub mod tls;
+pub use tls::*;
 pub mod user;
 pub use user::*;
 
@@ -0,0 +1,22 @@
+use std::fmt;
+
+use super::*;
+use crate::{event_section, Formatter};
+
+/// Marker event section. Injected on user request during a collection (e.g.
+/// using `retis mark`) so events can be segmented by experiment phases.
+#[event_section(SectionId::Marker)]
+pub struct MarkerEvent {
+    /// Text given by the user, can be empty.
+    pub text: String,
+}
+
+impl EventFmt for MarkerEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(f, "marker")?;
+        if !self.text.is_empty() {
+            write!(f, " \"{}\"", self.text)?;
+        }
+        Ok(())
+    }
+}
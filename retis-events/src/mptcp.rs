@@ -0,0 +1,106 @@
+use std::fmt;
+
+use super::*;
+use crate::{event_section, event_type, Formatter};
+
+/// MPTCP event section. Reports the lifecycle of MPTCP subflows and the
+/// signaling handled by the path manager.
+#[event_section(SectionId::Mptcp)]
+#[derive(Default)]
+pub struct MptcpEvent {
+    /// Kind of the event: `connect` (subflow creation), `join` (subflow joined
+    /// the connection), `join_refused`, `close` (subflow closed), `sched`
+    /// (subflow picked by the scheduler), `add_addr`, `rm_addr` or `prio`
+    /// (ADD_ADDR, RM_ADDR or MP_PRIO options received).
+    pub kind: String,
+    /// Token identifying the MPTCP connection.
+    pub token: u32,
+    /// Subflow the event relates to, if any.
+    pub subflow: Option<MptcpSubflow>,
+    /// Address connected to (`connect`) or announced (`add_addr`).
+    pub addr: Option<MptcpAddr>,
+    /// Ids of the removed addresses (`rm_addr`).
+    pub rm_ids: Vec<u8>,
+    /// Error returned when creating a subflow (`connect`).
+    pub error: Option<i32>,
+}
+
+/// MPTCP subflow.
+#[event_type]
+#[derive(Default)]
+pub struct MptcpSubflow {
+    /// Subflow id, not available on all kernels.
+    pub id: Option<u32>,
+    /// Local and remote address ids.
+    pub local_id: i16,
+    pub remote_id: u8,
+    /// Is the subflow a backup one.
+    pub backup: bool,
+    /// Addresses and ports of the underlying TCP socket.
+    pub saddr: String,
+    pub sport: u16,
+    pub daddr: String,
+    pub dport: u16,
+}
+
+/// MPTCP address, as handled by the path manager.
+#[event_type]
+#[derive(Default)]
+pub struct MptcpAddr {
+    /// Address id.
+    pub id: u8,
+    pub addr: String,
+    /// Port, if any.
+    pub port: Option<u16>,
+}
+
+fn fmt_endpoint(f: &mut Formatter, addr: &str, port: u16) -> fmt::Result {
+    match addr.contains(':') {
+        true => write!(f, "[{addr}]:{port}"),
+        false => write!(f, "{addr}:{port}"),
+    }
+}
+
+impl EventFmt for MptcpEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(f, "mptcp {} token {:#x}", self.kind, self.token)?;
+
+        if let Some(subflow) = &self.subflow {
+            write!(f, " subflow")?;
+            if let Some(id) = subflow.id {
+                write!(f, " {id}")?;
+            }
+            write!(f, " ")?;
+            fmt_endpoint(f, &subflow.saddr, subflow.sport)?;
+            write!(f, " > ")?;
+            fmt_endpoint(f, &subflow.daddr, subflow.dport)?;
+            write!(f, " ids {}/{}", subflow.local_id, subflow.remote_id)?;
+            if subflow.backup {
+                write!(f, " backup")?;
+            }
+        }
+
+        if let Some(addr) = &self.addr {
+            write!(f, " addr {} ", addr.id)?;
+            match addr.port {
+                Some(port) => fmt_endpoint(f, &addr.addr, port)?,
+                None => write!(f, "{}", addr.addr)?,
+            }
+        }
+
+        if !self.rm_ids.is_empty() {
+            let ids = self
+                .rm_ids
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>();
+            write!(f, " rm_ids {}", ids.join(","))?;
+        }
+
+        if let Some(error) = self.error {
+            write!(f, " error {error}")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use std::fmt;
+
+use super::*;
+use crate::{event_section, event_type, Formatter};
+
+/// Neighbor event section. Generated at post-processing for events related to
+/// a neighbor resolution failure, with the state of the neighbor table for the
+/// packet destination at the time of processing.
+#[event_section(SectionId::Neigh)]
+pub struct NeighEvent {
+    /// Packet destination.
+    pub addr: String,
+    /// Next hops the destination is routed through, looked up in the neighbor
+    /// table: the route gateways, or the destination itself if on-link.
+    pub nexthops: Vec<String>,
+    /// Neighbor entries matching the next hops, empty if none was found.
+    pub entries: Vec<NeighEntry>,
+}
+
+/// Neighbor table entry.
+#[event_type]
+pub struct NeighEntry {
+    /// Address of the neighbor.
+    pub addr: String,
+    /// Name of the interface the entry is attached to, if known.
+    pub dev: Option<String>,
+    /// Link-layer address of the neighbor, if resolved.
+    pub lladdr: Option<String>,
+    /// Neighbor state, e.g. "reachable", "stale" or "failed".
+    pub state: String,
+}
+
+impl EventFmt for NeighEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(f, "neigh {}", self.addr)?;
+
+        // Only show the next hops if the destination is not on-link.
+        if self.nexthops.iter().any(|nh| *nh != self.addr) {
+            write!(f, " via {}", self.nexthops.join(","))?;
+        }
+
+        if self.entries.is_empty() {
+            return write!(f, " no entry");
+        }
+
+        self.entries.iter().enumerate().try_for_each(|(i, entry)| {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            // Entries can only be told apart by their address with multiple
+            // next hops.
+            if self.nexthops.len() > 1 {
+                write!(f, " {}", entry.addr)?;
+            }
+            if let Some(dev) = &entry.dev {
+                write!(f, " dev {dev}")?;
+            }
+            if let Some(lladdr) = &entry.lladdr {
+                write!(f, " lladdr {lladdr}")?;
+            }
+            write!(f, " {}", entry.state)
+        })
+    }
+}
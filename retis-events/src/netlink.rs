@@ -0,0 +1,67 @@
+use std::fmt;
+
+use super::*;
+use crate::{event_section, Formatter};
+
+/// Netlink event section. Describes the first message of a netlink skb sent
+/// to or by the kernel.
+#[event_section(SectionId::Netlink)]
+pub struct NetlinkEvent {
+    /// Netlink protocol, e.g. "route", "netfilter" or "generic".
+    pub protocol: String,
+    /// Generic netlink family (e.g. "ovs_flow") or netfilter subsystem (e.g.
+    /// "nftables"), if any.
+    pub family: Option<String>,
+    /// Message type, from the netlink header.
+    pub r#type: u16,
+    /// Generic netlink command, if any.
+    pub cmd: Option<u8>,
+    /// Name of the message type or of the generic netlink command, if known
+    /// (e.g. "RTM_NEWROUTE").
+    pub msg: Option<String>,
+    /// Message flags.
+    pub flags: u16,
+    /// Message sequence number.
+    pub seq: u32,
+    /// Message length.
+    pub len: u32,
+    /// Port id of the sender, 0 for the kernel.
+    pub portid: u32,
+    /// Port id of the receiver for unicast messages, 0 for the kernel.
+    pub dst_portid: Option<u32>,
+    /// Multicast group for broadcast messages.
+    pub group: Option<u32>,
+}
+
+impl EventFmt for NetlinkEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(f, "netlink {}", self.protocol)?;
+        if let Some(family) = &self.family {
+            write!(f, " {family}")?;
+        }
+        if let Some(msg) = &self.msg {
+            write!(f, " {msg}")?;
+        }
+        match self.cmd {
+            Some(cmd) => write!(f, " (cmd {cmd})")?,
+            None => write!(f, " ({})", self.r#type)?,
+        }
+
+        write!(
+            f,
+            " flags {:#x} seq {} len {} ",
+            self.flags, self.seq, self.len
+        )?;
+
+        let port = |portid: u32| match portid {
+            0 => "kernel".to_string(),
+            portid => portid.to_string(),
+        };
+        write!(f, "{} > ", port(self.portid))?;
+        match (self.group, self.dst_portid) {
+            (Some(group), _) => write!(f, "group {group}"),
+            (None, Some(portid)) => write!(f, "{}", port(portid)),
+            (None, None) => write!(f, "?"),
+        }
+    }
+}
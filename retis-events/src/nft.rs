@@ -1,7 +1,7 @@
 use std::{fmt, str};
 
 use super::*;
-use crate::{event_section, Formatter};
+use crate::{event_section, event_type, Formatter};
 
 /// Nft event section
 #[event_section(SectionId::Nft)]
@@ -14,7 +14,25 @@ pub struct NftEvent {
     pub table_handle: i64,
     pub chain_handle: i64,
     pub rule_handle: Option<i64>,
+    /// Description of the rule (its expressions and comment), if known.
+    #[retis(default)]
+    pub rule: Option<String>,
     pub policy: bool,
+    /// Evaluation of the chain up to this event, if tracked (`--nft-eval`).
+    #[retis(default)]
+    pub eval: Option<NftEval>,
+}
+
+/// Evaluation of a base chain by nft_do_chain, from its entry up to the event.
+#[event_type]
+#[derive(Copy, Default, PartialEq)]
+pub struct NftEval {
+    /// Rules which matched (non-matching rules are not accounted).
+    pub rules: u32,
+    /// Chains traversed, including the base one.
+    pub chains: u32,
+    /// Time spent evaluating the chains.
+    pub latency: Duration,
 }
 
 impl EventFmt for NftEvent {
@@ -28,6 +46,9 @@ impl EventFmt for NftEvent {
         if let Some(rule) = self.rule_handle {
             write!(f, " handle {rule}")?;
         }
+        if let Some(rule) = &self.rule {
+            write!(f, " [{rule}]")?;
+        }
 
         write!(f, " {}", self.verdict)?;
 
@@ -39,6 +60,14 @@ impl EventFmt for NftEvent {
             write!(f, " chain {name}")?;
         }
 
+        if let Some(eval) = &self.eval {
+            write!(
+                f,
+                " (rules {} chains {} {})",
+                eval.rules, eval.chains, eval.latency
+            )?;
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,98 @@
+//! # OUI
+//!
+//! Lookup of the vendor owning a MAC address, from its Organizationally Unique
+//! Identifier (the first 3 bytes). The embedded database is only available
+//! when built with the `oui` feature and covers vendors commonly found when
+//! debugging servers and virtualized setups (NICs, switches, hypervisors); it
+//! is not a full copy of the IEEE registry.
+
+/// Is the OUI database available?
+pub const OUI_AVAILABLE: bool = cfg!(feature = "oui");
+
+/// Vendors indexed by OUI. Must be kept sorted. Some entries are well known
+/// locally administered prefixes (e.g. the QEMU and OpenStack defaults).
+#[cfg(feature = "oui")]
+const OUI_DB: &[(u32, &str)] = &[
+    (0x00000c, "Cisco"),
+    (0x0002c9, "Mellanox"),
+    (0x0003ff, "Microsoft"),
+    (0x000569, "VMware"),
+    (0x000585, "Juniper"),
+    (0x000743, "Chelsio"),
+    (0x000af7, "Broadcom"),
+    (0x000c29, "VMware"),
+    (0x000d3a, "Microsoft"),
+    (0x000e1e, "QLogic"),
+    (0x000f53, "Solarflare"),
+    (0x001018, "Broadcom"),
+    (0x00144f, "Oracle"),
+    (0x00155d, "Microsoft"),
+    (0x00163e, "Xensource"),
+    (0x001a4a, "Qumranet"),
+    (0x001b21, "Intel"),
+    (0x001c14, "VMware"),
+    (0x001c42, "Parallels"),
+    (0x001c73, "Arista"),
+    (0x002128, "Oracle"),
+    (0x002248, "Microsoft"),
+    (0x002590, "Super Micro"),
+    (0x005056, "VMware"),
+    (0x009027, "Intel"),
+    (0x00a0c9, "Intel"),
+    (0x00e04c, "Realtek"),
+    (0x080027, "PCS Systemtechnik (VirtualBox)"),
+    (0x0c42a1, "Mellanox"),
+    (0x0cc47a, "Super Micro"),
+    (0x141877, "Dell"),
+    (0x248a07, "Mellanox"),
+    (0x28993a, "Arista"),
+    (0x444ca8, "Arista"),
+    (0x525400, "QEMU"),
+    (0x98039b, "Mellanox"),
+    (0xac1f6b, "Super Micro"),
+    (0xb827eb, "Raspberry Pi"),
+    (0xdca632, "Raspberry Pi"),
+    (0xe45f01, "Raspberry Pi"),
+    (0xf8bc12, "Dell"),
+    (0xfa163e, "OpenStack"),
+];
+
+/// Returns the vendor of a MAC address (`aa:bb:cc:dd:ee:ff`), if known. Always
+/// returns None if the OUI database is not available.
+pub fn oui_vendor(mac: &str) -> Option<&'static str> {
+    let mut oui = 0;
+    let mut bytes = mac.split(':');
+    for _ in 0..3 {
+        oui = (oui << 8) | u8::from_str_radix(bytes.next()?, 16).ok()? as u32;
+    }
+
+    lookup(oui)
+}
+
+#[cfg(feature = "oui")]
+fn lookup(oui: u32) -> Option<&'static str> {
+    OUI_DB
+        .binary_search_by_key(&oui, |(oui, _)| *oui)
+        .ok()
+        .map(|i| OUI_DB[i].1)
+}
+
+#[cfg(not(feature = "oui"))]
+fn lookup(_: u32) -> Option<&'static str> {
+    None
+}
+
+#[cfg(all(test, feature = "oui"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oui() {
+        assert!(OUI_DB.windows(2).all(|w| w[0].0 < w[1].0));
+
+        assert_eq!(oui_vendor("00:50:56:c0:00:08"), Some("VMware"));
+        assert_eq!(oui_vendor("52:54:00:12:34:56"), Some("QEMU"));
+        assert_eq!(oui_vendor("ff:ff:ff:ff:ff:ff"), None);
+        assert_eq!(oui_vendor("invalid"), None);
+    }
+}
@@ -55,6 +55,14 @@ pub enum OvsEvent {
         #[serde(flatten)]
         action_execute: ActionEvent,
     },
+
+    /// Restart event. It indicates ovs-vswitchd restarted during the collection and its USDT
+    /// probes were attached to the new process.
+    #[serde(rename = "restart")]
+    Restart {
+        #[serde(flatten)]
+        restart: RestartEvent,
+    },
 }
 
 impl EventFmt for OvsEvent {
@@ -67,6 +75,7 @@ impl EventFmt for OvsEvent {
             RecvUpcall { recv_upcall } => recv_upcall,
             Operation { flow_operation } => flow_operation,
             Action { action_execute } => action_execute,
+            Restart { restart } => restart,
         };
 
         disp.event_fmt(f, format)
@@ -83,6 +92,26 @@ fn fmt_upcall_cmd(cmd: u8) -> &'static str {
     }
 }
 
+/// ovs-vswitchd restart event.
+#[event_type]
+#[derive(Copy, Default, PartialEq)]
+pub struct RestartEvent {
+    /// Pid of the previous ovs-vswitchd process.
+    pub old_pid: i32,
+    /// Pid of the new ovs-vswitchd process.
+    pub pid: i32,
+}
+
+impl EventFmt for RestartEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(
+            f,
+            "ovs-vswitchd restarted (pid {} -> {})",
+            self.old_pid, self.pid
+        )
+    }
+}
+
 /// OVS upcall event
 #[event_type]
 #[derive(Copy, Default, PartialEq)]
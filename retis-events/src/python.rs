@@ -167,7 +167,7 @@ impl PyEventSeries {
             .ok_or_else(|| PyRuntimeError::new_err("Malformed Series with < 1 events"))?
             .try_borrow(py)?
             .0
-            .get_section::<CommonEvent>(SectionId::Common)
+            .common()
             .unwrap()
             .timestamp;
         Ok(format!(
@@ -219,15 +219,7 @@ pub(crate) struct PyEventReader {
 impl PyEventReader {
     #[new]
     pub(crate) fn new(path: PathBuf) -> PyResult<Self> {
-        let factory = file::FileEventsFactory::new(path)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
-
-        if matches!(factory.file_type(), file::FileType::Series) {
-            return Err(PyRuntimeError::new_err(
-                "Cannot create a EventReader from a sorted file. Use an SeriesReader instead",
-            ));
-        }
-        Ok(PyEventReader { factory })
+        Self::from_factory(open_factory(path)?)
     }
 
     // Implementation of the iterator protocol.
@@ -254,6 +246,17 @@ impl PyEventReader {
     }
 }
 
+impl PyEventReader {
+    fn from_factory(factory: file::FileEventsFactory) -> PyResult<Self> {
+        if matches!(factory.file_type(), file::FileType::Series) {
+            return Err(PyRuntimeError::new_err(
+                "Cannot create a EventReader from a sorted file. Use an SeriesReader instead",
+            ));
+        }
+        Ok(PyEventReader { factory })
+    }
+}
+
 /// Python series reader
 ///
 /// Objects of this class can read events from unsorted event files.
@@ -276,15 +279,7 @@ pub(crate) struct PySeriesReader {
 impl PySeriesReader {
     #[new]
     pub(crate) fn new(path: PathBuf) -> PyResult<Self> {
-        let factory = file::FileEventsFactory::new(path)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
-
-        if matches!(factory.file_type(), file::FileType::Event) {
-            return Err(PyRuntimeError::new_err(
-                "Cannot create a SeriesReader from an unsorted file. Use an EventReader instead",
-            ));
-        }
-        Ok(PySeriesReader { factory })
+        Self::from_factory(open_factory(path)?)
     }
 
     // Implementation of the iterator protocol.
@@ -312,6 +307,21 @@ impl PySeriesReader {
     }
 }
 
+impl PySeriesReader {
+    fn from_factory(factory: file::FileEventsFactory) -> PyResult<Self> {
+        if matches!(factory.file_type(), file::FileType::Event) {
+            return Err(PyRuntimeError::new_err(
+                "Cannot create a SeriesReader from an unsorted file. Use an EventReader instead",
+            ));
+        }
+        Ok(PySeriesReader { factory })
+    }
+}
+
+fn open_factory(path: PathBuf) -> PyResult<file::FileEventsFactory> {
+    file::FileEventsFactory::new(path).map_err(|e| PyRuntimeError::new_err(e.to_string()))
+}
+
 /// Python event file
 ///
 /// Objects of this class can read files generated by retis and create
@@ -334,18 +344,20 @@ impl PySeriesReader {
 pub(crate) struct PyEventFile {
     path: PathBuf,
     ftype: file::FileType,
+    /// Factory used to detect the file type, handed to the first reader so
+    /// streams (e.g. `-` for the standard input) can be read.
+    factory: Option<file::FileEventsFactory>,
 }
 
 #[pymethods]
 impl PyEventFile {
     #[new]
     pub(crate) fn new(path: PathBuf) -> PyResult<Self> {
-        let temp = file::FileEventsFactory::new(&path)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
-        let ftype = temp.file_type();
+        let factory = open_factory(path.clone())?;
         Ok(PyEventFile {
             path,
-            ftype: ftype.clone(),
+            ftype: factory.file_type().clone(),
+            factory: Some(factory),
         })
     }
 
@@ -357,12 +369,25 @@ impl PyEventFile {
         }
     }
 
-    pub(crate) fn events(&self) -> PyResult<PyEventReader> {
-        PyEventReader::new(self.path.clone())
+    pub(crate) fn events(&mut self) -> PyResult<PyEventReader> {
+        PyEventReader::from_factory(self.factory()?)
     }
 
-    pub(crate) fn series(&self) -> PyResult<PySeriesReader> {
-        PySeriesReader::new(self.path.clone())
+    pub(crate) fn series(&mut self) -> PyResult<PySeriesReader> {
+        PySeriesReader::from_factory(self.factory()?)
+    }
+}
+
+impl PyEventFile {
+    /// Factory for a new reader. Streams can only be read once.
+    fn factory(&mut self) -> PyResult<file::FileEventsFactory> {
+        match self.factory.take() {
+            Some(factory) => Ok(factory),
+            None if file::is_stream(&self.path) => Err(PyRuntimeError::new_err(
+                "Cannot read a stream more than once",
+            )),
+            None => open_factory(self.path.clone()),
+        }
     }
 }
 
@@ -0,0 +1,22 @@
+use std::fmt;
+
+use super::*;
+use crate::{event_section, Formatter};
+
+/// Seg6 event section. Reports the IPv6 Segment Routing (SRv6) processing
+/// applied to packets. The Segment Routing Header itself is reported by the skb
+/// section.
+#[event_section(SectionId::Seg6)]
+#[derive(Default)]
+pub struct Seg6Event {
+    /// Behavior applied to the packet: `H.Encaps` or `H.Insert` when an SRH is
+    /// added, or the seg6_local behavior (e.g. `End`, `End.X`, `End.DT4`,
+    /// `End.DT6`).
+    pub behavior: String,
+}
+
+impl EventFmt for Seg6Event {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(f, "seg6 {}", self.behavior)
+    }
+}
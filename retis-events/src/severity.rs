@@ -0,0 +1,55 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Result};
+
+use super::*;
+use crate::{event_section, event_type, Formatter};
+
+/// Severity of an event.
+#[event_type]
+#[derive(Copy, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warn,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warn => "warn",
+            Severity::Error => "error",
+        })
+    }
+}
+
+impl FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "info" => Severity::Info,
+            "warn" => Severity::Warn,
+            "error" => Severity::Error,
+            x => bail!("Unknown severity '{x}' (expected info, warn or error)"),
+        })
+    }
+}
+
+/// Severity event section. Added by the classification logic at
+/// post-processing time to events standing out (e.g. drops).
+#[event_section(SectionId::Severity)]
+pub struct SeverityEvent {
+    /// Severity of the event.
+    pub level: Severity,
+    /// Why the event was given this severity.
+    pub reason: String,
+}
+
+impl EventFmt for SeverityEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(f, "[{}: {}]", self.level, self.reason)
+    }
+}
@@ -1,7 +1,11 @@
 use std::fmt;
 
 use super::{
-    helpers::{etype_str, protocol_str, RawPacket},
+    helpers::{
+        dns_rcode_str, dns_type_str, etype_str, icmp_str, icmpv6_str, pkt_type_str, protocol_str,
+        sctp_chunk_str, RawPacket,
+    },
+    oui::oui_vendor,
     *,
 };
 use crate::{event_section, event_type, Formatter};
@@ -18,14 +22,24 @@ pub struct SkbEvent {
     pub arp: Option<SkbArpEvent>,
     /// IPv4 or IPv6 fields, if any.
     pub ip: Option<SkbIpEvent>,
+    /// IPv6 Segment Routing Header fields, if any.
+    pub srh: Option<SkbSrhEvent>,
     /// TCP fields, if any.
     pub tcp: Option<SkbTcpEvent>,
     /// UDP fields, if any.
     pub udp: Option<SkbUdpEvent>,
+    /// SCTP fields, if any.
+    pub sctp: Option<SkbSctpEvent>,
     /// ICMP fields, if any.
     pub icmp: Option<SkbIcmpEvent>,
     /// ICMPv6 fields, if any.
     pub icmpv6: Option<SkbIcmpV6Event>,
+    /// DNS message fields, if any.
+    pub dns: Option<SkbDnsEvent>,
+    /// Tunnel encapsulation (VXLAN, GENEVE or GRE) fields, if any.
+    pub encap: Option<SkbEncapEvent>,
+    /// Headers of the encapsulated packet, if any.
+    pub inner: Option<SkbInnerEvent>,
     /// Net device data, if any.
     pub dev: Option<SkbDevEvent>,
     /// Net namespace data, if any.
@@ -36,39 +50,74 @@ pub struct SkbEvent {
     pub data_ref: Option<SkbDataRefEvent>,
     /// GSO information.
     pub gso: Option<SkbGsoEvent>,
+    /// Fragments information, for non-linear skbs.
+    pub frags: Option<SkbFragsEvent>,
+    /// Net device offload features, if any.
+    pub features: Option<SkbFeaturesEvent>,
+    /// NET_RX softirq context, if the packet was processed in it.
+    pub softirq: Option<SkbSoftirqEvent>,
     /// Raw packet and related metadata.
     pub packet: Option<SkbPacketEvent>,
+    /// Origin of the packet, if it could be guessed.
+    pub origin: Option<SkbOrigin>,
 }
 
-impl EventFmt for SkbEvent {
-    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
-        let mut len = 0;
-
-        let mut space = DelimWriter::new(' ');
+impl SkbEvent {
+    /// Number and size of the UDP datagrams of an skb using UDP segmentation,
+    /// the last one being possibly shorter. The number of datagrams is
+    /// computed from the UDP length if not reported.
+    pub fn udp_segments(&self) -> Option<(u32, u32)> {
+        let gso = self
+            .gso
+            .as_ref()
+            .filter(|gso| gso.is_udp_l4() && gso.size != 0)?;
+        let segs = match (gso.segs, &self.udp) {
+            (0, Some(udp)) => (udp.len.saturating_sub(8) as u32).div_ceil(gso.size),
+            (segs, _) => segs,
+        };
 
-        if let Some(ns) = &self.ns {
-            space.write(f)?;
-            write!(f, "ns {}", ns.netns)?;
+        match segs {
+            0 => None,
+            segs => Some((segs, gso.size)),
         }
+    }
+}
 
-        if let Some(dev) = &self.dev {
-            space.write(f)?;
+/// Headers of a packet, from L2 to L4, used to display both the packet and the
+/// one it encapsulates, if any.
+#[derive(Default)]
+struct SkbHeaders<'a> {
+    eth: Option<&'a SkbEthEvent>,
+    vlan: Option<&'a SkbVlanEvent>,
+    arp: Option<&'a SkbArpEvent>,
+    ip: Option<&'a SkbIpEvent>,
+    srh: Option<&'a SkbSrhEvent>,
+    tcp: Option<&'a SkbTcpEvent>,
+    udp: Option<&'a SkbUdpEvent>,
+    sctp: Option<&'a SkbSctpEvent>,
+    icmp: Option<&'a SkbIcmpEvent>,
+    icmpv6: Option<&'a SkbIcmpV6Event>,
+    dns: Option<&'a SkbDnsEvent>,
+    /// See `SkbEvent::udp_segments`.
+    udp_segments: Option<(u32, u32)>,
+}
 
-            if dev.ifindex > 0 {
-                write!(f, "if {}", dev.ifindex)?;
-                if !dev.name.is_empty() {
-                    write!(f, " ({})", dev.name)?;
-                }
-            }
-            if let Some(rx_ifindex) = dev.rx_ifindex {
-                write!(f, " rxif {}", rx_ifindex)?;
-            }
-        }
+impl SkbHeaders<'_> {
+    fn write(
+        &self,
+        f: &mut Formatter,
+        format: &DisplayFormat,
+        space: &mut DelimWriter,
+    ) -> fmt::Result {
+        let mut len = 0;
 
         if let Some(eth) = &self.eth {
             space.write(f)?;
 
-            write!(f, "{} > {} ethertype", eth.src, eth.dst)?;
+            write_mac(f, &eth.src, format)?;
+            write!(f, " > ")?;
+            write_mac(f, &eth.dst, format)?;
+            write!(f, " ethertype")?;
             if let Some(etype) = etype_str(eth.etype) {
                 write!(f, " {etype}")?;
             }
@@ -80,11 +129,22 @@ impl EventFmt for SkbEvent {
 
             let drop = if vlan.dei { " drop" } else { "" };
             let accel = if vlan.acceleration { " accel" } else { "" };
-            write!(
-                f,
-                "vlan (id {} prio {}{}{})",
-                vlan.vid, vlan.pcp, drop, accel
-            )?;
+            write!(f, "vlan (")?;
+            // Only report the protocol of 802.1ad tags, 802.1Q being the
+            // common case.
+            if let Some(tpid) = vlan.tpid.filter(|tpid| *tpid != 0x8100) {
+                write!(f, "tpid {tpid:#06x} ")?;
+            }
+            write!(f, "id {} prio {}{}{})", vlan.vid, vlan.pcp, drop, accel)?;
+
+            for tag in vlan.inner.iter() {
+                write!(f, " vlan (")?;
+                if tag.tpid != 0x8100 {
+                    write!(f, "tpid {:#06x} ", tag.tpid)?;
+                }
+                let drop = if tag.dei { " drop" } else { "" };
+                write!(f, "id {} prio {}{})", tag.vid, tag.pcp, drop)?;
+            }
         }
 
         if let Some(arp) = &self.arp {
@@ -125,6 +185,12 @@ impl EventFmt for SkbEvent {
                 write!(f, "{}.{} > {}.{}", ip.saddr, tcp.sport, ip.daddr, tcp.dport)?;
             } else if let Some(udp) = &self.udp {
                 write!(f, "{}.{} > {}.{}", ip.saddr, udp.sport, ip.daddr, udp.dport)?;
+            } else if let Some(sctp) = &self.sctp {
+                write!(
+                    f,
+                    "{}.{} > {}.{}",
+                    ip.saddr, sctp.sport, ip.daddr, sctp.dport
+                )?;
             } else {
                 write!(f, "{} > {}", ip.saddr, ip.daddr)?;
             }
@@ -182,6 +248,26 @@ impl EventFmt for SkbEvent {
             write!(f, " ({})", ip.protocol)?;
         }
 
+        if let Some(srh) = &self.srh {
+            space.write(f)?;
+
+            write!(
+                f,
+                "srh [segleft {} last {}",
+                srh.segments_left, srh.last_entry
+            )?;
+            if let Some(active) = &srh.active {
+                write!(f, " active {active}")?;
+            }
+            if srh.tag != 0 {
+                write!(f, " tag {:#x}", srh.tag)?;
+            }
+            if !srh.segments.is_empty() {
+                write!(f, " segs {}", srh.segments.join(","))?;
+            }
+            write!(f, "]")?;
+        }
+
         if let Some(tcp) = &self.tcp {
             space.write(f)?;
 
@@ -225,18 +311,173 @@ impl EventFmt for SkbEvent {
             let len = udp.len;
             // Substract the UDP header size when reporting the length.
             write!(f, "len {}", len.saturating_sub(8))?;
+
+            // UDP segmentation, the skb holds multiple datagrams.
+            if let Some((segs, size)) = self.udp_segments {
+                write!(f, " segs {segs} size {size}")?;
+            }
+        }
+
+        if let Some(sctp) = &self.sctp {
+            space.write(f)?;
+            write!(f, "vtag {:#x}", sctp.vtag)?;
+
+            if !sctp.chunks.is_empty() {
+                let chunks = sctp
+                    .chunks
+                    .iter()
+                    .map(|chunk| match sctp_chunk_str(*chunk) {
+                        Some(chunk) => chunk.to_string(),
+                        None => chunk.to_string(),
+                    })
+                    .collect::<Vec<_>>();
+                write!(f, " chunks [{}]", chunks.join(","))?;
+            }
         }
 
         if let Some(icmp) = &self.icmp {
             space.write(f)?;
-            // TODO: text version
-            write!(f, "type {} code {}", icmp.r#type, icmp.code)?;
+            write_icmp(f, icmp.r#type, icmp.code, icmp_str(icmp.r#type, icmp.code))?;
         }
 
         if let Some(icmpv6) = &self.icmpv6 {
             space.write(f)?;
-            // TODO: text version
-            write!(f, "type {} code {}", icmpv6.r#type, icmpv6.code)?;
+            write_icmp(
+                f,
+                icmpv6.r#type,
+                icmpv6.code,
+                icmpv6_str(icmpv6.r#type, icmpv6.code),
+            )?;
+        }
+
+        if let Some(dns) = &self.dns {
+            space.write(f)?;
+            write!(f, "dns id {:#06x}", dns.id)?;
+
+            if dns.response {
+                match dns_rcode_str(dns.rcode) {
+                    Some(rcode) => write!(f, " response {rcode}")?,
+                    None => write!(f, " response rcode {}", dns.rcode)?,
+                }
+            } else {
+                write!(f, " query")?;
+            }
+            if dns.opcode != 0 {
+                write!(f, " opcode {}", dns.opcode)?;
+            }
+
+            let questions = dns
+                .questions
+                .iter()
+                .map(|q| format!("{} {}", dns_type(q.r#type), q.name))
+                .collect::<Vec<_>>();
+            if !questions.is_empty() {
+                write!(f, " {}", questions.join(", "))?;
+            }
+
+            let answers = dns
+                .answers
+                .iter()
+                .map(|a| match &a.data {
+                    Some(data) => format!("{} {} {data}", a.name, dns_type(a.r#type)),
+                    None => format!("{} {}", a.name, dns_type(a.r#type)),
+                })
+                .collect::<Vec<_>>();
+            if !answers.is_empty() {
+                write!(f, " answers [{}]", answers.join(", "))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl EventFmt for SkbEvent {
+    fn event_fmt(&self, f: &mut Formatter, format: &DisplayFormat) -> fmt::Result {
+        let mut space = DelimWriter::new(' ');
+
+        if let Some(ns) = &self.ns {
+            space.write(f)?;
+            write!(f, "ns {}", ns.netns)?;
+        }
+
+        if let Some(dev) = &self.dev {
+            space.write(f)?;
+
+            if dev.ifindex > 0 {
+                write!(f, "if {}", dev.ifindex)?;
+                if !dev.name.is_empty() {
+                    write!(f, " ({})", dev.name)?;
+                }
+            }
+            if let Some(rx_ifindex) = dev.rx_ifindex {
+                write!(f, " rxif {}", rx_ifindex)?;
+            }
+        }
+
+        if let Some(origin) = &self.origin {
+            space.write(f)?;
+            write!(
+                f,
+                "{}",
+                match origin {
+                    SkbOrigin::Local => "local",
+                    SkbOrigin::Received => "received",
+                    SkbOrigin::Forwarded => "forwarded",
+                }
+            )?;
+        }
+
+        SkbHeaders {
+            eth: self.eth.as_ref(),
+            vlan: self.vlan.as_ref(),
+            arp: self.arp.as_ref(),
+            ip: self.ip.as_ref(),
+            srh: self.srh.as_ref(),
+            tcp: self.tcp.as_ref(),
+            udp: self.udp.as_ref(),
+            sctp: self.sctp.as_ref(),
+            icmp: self.icmp.as_ref(),
+            icmpv6: self.icmpv6.as_ref(),
+            dns: self.dns.as_ref(),
+            udp_segments: self.udp_segments(),
+        }
+        .write(f, format, &mut space)?;
+
+        if let Some(encap) = &self.encap {
+            space.write(f)?;
+            write!(
+                f,
+                "{}",
+                match encap.r#type {
+                    SkbEncapType::Vxlan => "vxlan",
+                    SkbEncapType::Geneve => "geneve",
+                    SkbEncapType::Gre => "gre",
+                }
+            )?;
+            if let Some(id) = encap.id {
+                match encap.r#type {
+                    SkbEncapType::Gre => write!(f, " (key {id:#x})")?,
+                    _ => write!(f, " (vni {id})")?,
+                }
+            }
+
+            if let Some(inner) = &self.inner {
+                write!(f, " [")?;
+                SkbHeaders {
+                    eth: inner.eth.as_ref(),
+                    arp: inner.arp.as_ref(),
+                    ip: inner.ip.as_ref(),
+                    tcp: inner.tcp.as_ref(),
+                    udp: inner.udp.as_ref(),
+                    sctp: inner.sctp.as_ref(),
+                    icmp: inner.icmp.as_ref(),
+                    icmpv6: inner.icmpv6.as_ref(),
+                    ..Default::default()
+                }
+                .write(f, format, &mut DelimWriter::new(' '))?;
+                write!(f, "]")?;
+            }
         }
 
         if self.meta.is_some() || self.data_ref.is_some() {
@@ -264,7 +505,26 @@ impl EventFmt for SkbEvent {
                 if meta.data_len != 0 {
                     write!(f, "data_len {} ", meta.data_len)?;
                 }
+                if meta.truesize != 0 {
+                    write!(
+                        f,
+                        "headroom {} tailroom {} truesize {} ",
+                        meta.headroom, meta.tailroom, meta.truesize
+                    )?;
+                }
                 write!(f, "priority {}", meta.priority)?;
+
+                match pkt_type_str(meta.pkt_type) {
+                    Some(pkt_type) => write!(f, " pkt_type {pkt_type}")?,
+                    None => write!(f, " pkt_type {}", meta.pkt_type)?,
+                }
+                if meta.protocol != 0 {
+                    write!(f, " protocol")?;
+                    if let Some(etype) = etype_str(meta.protocol) {
+                        write!(f, " {etype}")?;
+                    }
+                    write!(f, " ({:#06x})", meta.protocol)?;
+                }
             }
 
             if self.meta.is_some() && self.data_ref.is_some() {
@@ -306,6 +566,47 @@ impl EventFmt for SkbEvent {
             write!(f, "size {}]", gso.size)?;
         }
 
+        if let Some(frags) = &self.frags {
+            space.write(f)?;
+            write!(f, "frags [nr {}", frags.nr_frags)?;
+
+            if !frags.sizes.is_empty() {
+                let sizes = frags
+                    .sizes
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",");
+                write!(f, " sizes {sizes}")?;
+                if frags.sizes.len() < frags.nr_frags as usize {
+                    write!(f, ",...")?;
+                }
+            }
+
+            if frags.frag_list {
+                write!(f, " frag_list")?;
+            }
+
+            write!(f, "]")?;
+        }
+
+        if let Some(features) = &self.features {
+            space.write(f)?;
+            match features.offloads.is_empty() {
+                true => write!(f, "features {:#x}", features.raw)?,
+                false => write!(f, "offloads [{}]", features.offloads.join(" "))?,
+            }
+        }
+
+        if let Some(softirq) = &self.softirq {
+            space.write(f)?;
+            write!(
+                f,
+                "softirq [delay {} running {}]",
+                softirq.delay, softirq.running
+            )?;
+        }
+
         // If we didn't print any section, it means the section has raw packet
         // data but we were unable to decode it. Print something.
         if !space.used() {
@@ -317,6 +618,42 @@ impl EventFmt for SkbEvent {
     }
 }
 
+/// Write ICMP (or ICMPv6) type and code, using their names when known.
+pub(crate) fn write_icmp(
+    f: &mut Formatter,
+    ty: u8,
+    code: u8,
+    names: (Option<&str>, Option<&str>),
+) -> fmt::Result {
+    match names.0 {
+        Some(name) => write!(f, "type {name} ({ty})")?,
+        None => write!(f, "type {ty}")?,
+    }
+    match names.1 {
+        Some(name) => write!(f, " code {name} ({code})"),
+        None => write!(f, " code {code}"),
+    }
+}
+
+/// Write a MAC address, followed by its vendor if asked for and known.
+fn write_mac(f: &mut Formatter, mac: &str, format: &DisplayFormat) -> fmt::Result {
+    write!(f, "{mac}")?;
+    if format.oui {
+        if let Some(vendor) = oui_vendor(mac) {
+            write!(f, " ({vendor})")?;
+        }
+    }
+    Ok(())
+}
+
+/// Name of a DNS record type, using the RFC 3597 notation for unknown ones.
+fn dns_type(r#type: u16) -> String {
+    match dns_type_str(r#type) {
+        Some(name) => name.to_string(),
+        None => format!("TYPE{type}"),
+    }
+}
+
 /// Ethernet fields.
 #[event_type]
 pub struct SkbEthEvent {
@@ -330,6 +667,7 @@ pub struct SkbEthEvent {
 
 /// VLAN fields.
 #[event_type]
+#[derive(Default)]
 pub struct SkbVlanEvent {
     /// Priority Code Point, also called CoS.
     pub pcp: u8,
@@ -339,6 +677,40 @@ pub struct SkbVlanEvent {
     pub vid: u16,
     /// VLAN acceleration field.
     pub acceleration: bool,
+    /// Tag Protocol Identifier (0x8100 for 802.1Q, 0x88a8 for 802.1ad), if
+    /// the tag was found in the packet.
+    pub tpid: Option<u16>,
+    /// Inner tags found in the packet (e.g. QinQ), from the outermost to the
+    /// innermost.
+    #[retis(default)]
+    pub inner: Vec<SkbVlanTag>,
+}
+
+/// VLAN tag found in the packet.
+#[event_type]
+#[derive(Default)]
+pub struct SkbVlanTag {
+    /// Tag Protocol Identifier.
+    pub tpid: u16,
+    /// Priority Code Point, also called CoS.
+    pub pcp: u8,
+    /// Drop eligible indicator.
+    pub dei: bool,
+    /// VLAN ID.
+    pub vid: u16,
+}
+
+impl SkbVlanTag {
+    /// Builds a tag from its Tag Protocol Identifier and Tag Control
+    /// Information.
+    pub fn from_tci(tpid: u16, tci: u16) -> Self {
+        Self {
+            tpid,
+            pcp: (tci >> 13) as u8,
+            dei: tci & 0x1000 != 0,
+            vid: tci & 0xfff,
+        }
+    }
 }
 
 /// ARP fields.
@@ -356,6 +728,49 @@ pub struct SkbArpEvent {
     pub tpa: String,
 }
 
+/// Tunnel encapsulation fields.
+#[event_type]
+pub struct SkbEncapEvent {
+    /// Encapsulation type.
+    pub r#type: SkbEncapType,
+    /// Virtual network identifier (VXLAN, GENEVE) or key (GRE), if any.
+    pub id: Option<u32>,
+    /// Ethertype of the encapsulated packet, 0x6558 (Transparent Ethernet
+    /// Bridging) for Ethernet frames.
+    pub protocol: u16,
+}
+
+/// Tunnel encapsulation type.
+#[event_type]
+#[serde(rename_all = "snake_case")]
+pub enum SkbEncapType {
+    Vxlan,
+    Geneve,
+    Gre,
+}
+
+/// Headers of an encapsulated packet.
+#[event_type]
+#[derive(Default)]
+pub struct SkbInnerEvent {
+    /// Ethernet fields, if any.
+    pub eth: Option<SkbEthEvent>,
+    /// ARP fields, if any.
+    pub arp: Option<SkbArpEvent>,
+    /// IPv4 or IPv6 fields, if any.
+    pub ip: Option<SkbIpEvent>,
+    /// TCP fields, if any.
+    pub tcp: Option<SkbTcpEvent>,
+    /// UDP fields, if any.
+    pub udp: Option<SkbUdpEvent>,
+    /// SCTP fields, if any.
+    pub sctp: Option<SkbSctpEvent>,
+    /// ICMP fields, if any.
+    pub icmp: Option<SkbIcmpEvent>,
+    /// ICMPv6 fields, if any.
+    pub icmpv6: Option<SkbIcmpV6Event>,
+}
+
 /// ARP operation type.
 #[event_type]
 pub enum ArpOperation {
@@ -419,6 +834,25 @@ pub struct SkbIpv6Event {
     pub flow_label: u32,
 }
 
+/// IPv6 Segment Routing Header fields (routing header type 4), see RFC 8754.
+#[event_type]
+#[derive(Default)]
+pub struct SkbSrhEvent {
+    /// Index of the active segment in the segment list.
+    pub segments_left: u8,
+    /// Index of the last element of the segment list.
+    pub last_entry: u8,
+    /// Active segment (SID), if captured.
+    pub active: Option<String>,
+    /// Flags.
+    pub flags: u8,
+    /// Tag.
+    pub tag: u16,
+    /// Segment list, in the header order (the last segment of the path comes
+    /// first). Only the captured segments are reported.
+    pub segments: Vec<String>,
+}
+
 /// TCP fields.
 #[event_type]
 pub struct SkbTcpEvent {
@@ -446,6 +880,20 @@ pub struct SkbUdpEvent {
     pub len: u16,
 }
 
+/// SCTP fields.
+#[event_type]
+pub struct SkbSctpEvent {
+    /// Source port.
+    pub sport: u16,
+    /// Destination port.
+    pub dport: u16,
+    /// Verification tag.
+    pub vtag: u32,
+    /// Types of the chunks, in the packet order. Only the chunks starting in
+    /// the captured data are reported.
+    pub chunks: Vec<u8>,
+}
+
 /// ICMP fields.
 #[event_type]
 pub struct SkbIcmpEvent {
@@ -460,6 +908,61 @@ pub struct SkbIcmpV6Event {
     pub code: u8,
 }
 
+/// DNS message fields. Only the questions and answers in the captured data are
+/// reported.
+#[event_type]
+pub struct SkbDnsEvent {
+    /// Transaction id.
+    pub id: u16,
+    /// Is the message a response?
+    pub response: bool,
+    /// Kind of query (0 is a standard query).
+    pub opcode: u8,
+    /// Response code.
+    pub rcode: u8,
+    /// Questions.
+    pub questions: Vec<SkbDnsQuestion>,
+    /// Answer records.
+    pub answers: Vec<SkbDnsAnswer>,
+}
+
+/// DNS question.
+#[event_type]
+pub struct SkbDnsQuestion {
+    /// Queried name.
+    pub name: String,
+    /// Queried record type.
+    pub r#type: u16,
+}
+
+/// DNS answer record.
+#[event_type]
+pub struct SkbDnsAnswer {
+    /// Name the record applies to.
+    pub name: String,
+    /// Record type.
+    pub r#type: u16,
+    /// Time to live, in seconds.
+    pub ttl: u32,
+    /// Record data, for address (A, AAAA) and name (NS, CNAME, PTR) records.
+    pub data: Option<String>,
+}
+
+/// Origin of a packet, from the host point of view. It is derived from the
+/// state of the skb at the probe point (`skb->skb_iif`, `skb->dev`, `skb->sk`
+/// and `skb->pkt_type`) and is therefore a best guess.
+#[event_type]
+#[serde(rename_all = "snake_case")]
+pub enum SkbOrigin {
+    /// Generated by the host.
+    Local,
+    /// Received on a device, for the host or not yet known to be forwarded
+    /// (e.g. before the routing decision).
+    Received,
+    /// Received on a device and sent on another one (routed or bridged).
+    Forwarded,
+}
+
 /// Network device fields.
 #[event_type]
 #[derive(Default)]
@@ -482,6 +985,7 @@ pub struct SkbNsEvent {
 
 /// Skb metadata & releated fields.
 #[event_type]
+#[derive(Default)]
 pub struct SkbMetaEvent {
     /// Total number of bytes in the packet.
     pub len: u32,
@@ -498,6 +1002,32 @@ pub struct SkbMetaEvent {
     pub csum_level: u8,
     /// QoS priority.
     pub priority: u32,
+    /// Packet type (`PACKET_*`, see include/uapi/linux/if_packet.h), from
+    /// `skb->pkt_type`.
+    #[retis(default)]
+    pub pkt_type: u8,
+    /// L3 protocol (ethertype), from `skb->protocol`. 0 if not set yet.
+    #[retis(default)]
+    pub protocol: u16,
+    /// Space available before the data, in bytes (`skb_headroom()`).
+    #[retis(default)]
+    pub headroom: u32,
+    /// Space available after the data in the linear area, in bytes
+    /// (`skb_tailroom()`). Always 0 for non-linear skbs.
+    #[retis(default)]
+    pub tailroom: u32,
+    /// Memory accounted to the skb, in bytes (`skb->truesize`). 0 if not
+    /// reported.
+    #[retis(default)]
+    pub truesize: u32,
+    /// Offset of the end of the data in the linear area, from the head of the
+    /// buffer (`skb->tail`).
+    #[retis(default)]
+    pub tail: u32,
+    /// Offset of the end of the linear area, from the head of the buffer
+    /// (`skb->end`).
+    #[retis(default)]
+    pub end: u32,
 }
 
 /// Skb data & refcnt fields.
@@ -517,6 +1047,7 @@ pub struct SkbDataRefEvent {
 
 /// GSO information.
 #[event_type]
+#[derive(Default)]
 pub struct SkbGsoEvent {
     /// GSO flags, see `SKBFL_*` in include/linux/skbuff.h
     pub flags: u8,
@@ -530,6 +1061,50 @@ pub struct SkbGsoEvent {
     pub r#type: u32,
 }
 
+/// GSO type of UDP segmentation, see `SKB_GSO_UDP_L4` in
+/// include/linux/skbuff.h.
+const SKB_GSO_UDP_L4: u32 = 1 << 17;
+
+impl SkbGsoEvent {
+    /// Is the skb made of multiple UDP datagrams of `size` bytes each (UDP GSO
+    /// or GRO, e.g. used by QUIC)?
+    pub fn is_udp_l4(&self) -> bool {
+        self.r#type & SKB_GSO_UDP_L4 != 0
+    }
+}
+
+/// Fragments of a non-linear skb, see `skb_shared_info`.
+#[event_type]
+pub struct SkbFragsEvent {
+    /// Number of fragments in `skb_shared_info->frags`.
+    pub nr_frags: u8,
+    /// Sizes of the first fragments (at most 8), in bytes.
+    pub sizes: Vec<u32>,
+    /// Is `skb_shared_info->frag_list` used?
+    pub frag_list: bool,
+}
+
+/// Offload related features of the net device, from `skb->dev->features`.
+#[event_type]
+pub struct SkbFeaturesEvent {
+    /// Raw features (`netdev_features_t`). Bits depend on the kernel version.
+    pub raw: u64,
+    /// Enabled checksum, segmentation and receive offloads, using the ethtool
+    /// names (e.g. `tx-tcp-segmentation` or `rx-gro`).
+    pub offloads: Vec<String>,
+}
+
+/// NET_RX softirq context in which a packet was processed.
+#[event_type]
+pub struct SkbSoftirqEvent {
+    /// Time between the softirq being raised (e.g. by the device IRQ when
+    /// scheduling NAPI) and it starting to run. A high value means the CPU was
+    /// busy, or ksoftirqd was not scheduled in time.
+    pub delay: Duration,
+    /// Time the softirq was running for when the packet was seen.
+    pub running: Duration,
+}
+
 /// Raw packet and related metadata extracted from skbs.
 #[event_type]
 pub struct SkbPacketEvent {
@@ -539,4 +1114,388 @@ pub struct SkbPacketEvent {
     pub capture_len: u32,
     /// Raw packet data.
     pub packet: RawPacket,
+    /// Offset of the network header in the packet, if known.
+    #[retis(default)]
+    pub l3_offset: Option<u32>,
+    /// Offset of the transport header in the packet, if known.
+    #[retis(default)]
+    pub l4_offset: Option<u32>,
+}
+
+#[cfg_attr(feature = "python", pyo3::pymethods)]
+impl SkbPacketEvent {
+    /// Get `len` bytes of the packet starting at `offset`, if they were
+    /// captured.
+    pub fn bytes(&self, offset: usize, len: usize) -> Option<Vec<u8>> {
+        self.packet.bytes(offset, len).map(|b| b.to_vec())
+    }
+
+    /// Extract a header field of `len` bytes (up to 8) starting at `offset`
+    /// in the packet, as an integer.
+    pub fn field(&self, offset: usize, len: usize) -> Option<u64> {
+        self.packet.field(offset, len)
+    }
+
+    /// Extract a header field of `len` bytes (up to 8) starting at `offset`
+    /// in the network header, as an integer.
+    pub fn l3_field(&self, offset: usize, len: usize) -> Option<u64> {
+        self.packet
+            .field((self.l3_offset? as usize).checked_add(offset)?, len)
+    }
+
+    /// Extract a header field of `len` bytes (up to 8) starting at `offset`
+    /// in the transport header, as an integer.
+    pub fn l4_field(&self, offset: usize, len: usize) -> Option<u64> {
+        self.packet
+            .field((self.l4_offset? as usize).checked_add(offset)?, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packet_fields() {
+        // Ethernet + IPv4 (ihl 5) + TCP ports, truncated.
+        let mut data = vec![0; 14];
+        data.extend([
+            0x45, 0, 0, 52, 0, 0, 0, 0, 64, 6, 0, 0, 1, 1, 1, 1, 10, 0, 0, 1,
+        ]);
+        data.extend([0x01, 0xbb, 0x9d, 0x0c]);
+        let packet = SkbPacketEvent {
+            len: 66,
+            capture_len: data.len() as u32,
+            packet: RawPacket(data),
+            l3_offset: Some(14),
+            l4_offset: Some(34),
+        };
+
+        assert_eq!(packet.field(12, 2), Some(0));
+        assert_eq!(packet.l3_field(9, 1), Some(6));
+        assert_eq!(packet.l3_field(12, 4), Some(0x01010101));
+        assert_eq!(packet.l4_field(0, 2), Some(443));
+        assert_eq!(packet.l4_field(2, 2), Some(0x9d0c));
+        assert_eq!(packet.bytes(34, 2), Some(vec![0x01, 0xbb]));
+
+        // Not captured or invalid lengths.
+        assert_eq!(packet.l4_field(4, 4), None);
+        assert_eq!(packet.field(0, 0), None);
+        assert_eq!(packet.field(0, 9), None);
+        assert_eq!(packet.bytes(usize::MAX, 2), None);
+    }
+    #[test]
+    fn meta_display() {
+        let event = SkbEvent {
+            meta: Some(SkbMetaEvent {
+                len: 98,
+                data_len: 0,
+                hash: 0,
+                ip_summed: 0,
+                csum: 0,
+                csum_level: 0,
+                priority: 0,
+                pkt_type: 3,
+                protocol: 0x0800,
+                ..Default::default()
+            }),
+            origin: Some(SkbOrigin::Received),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format!(
+                "{}",
+                event.display(&DisplayFormat::new(), &FormatterConf::new())
+            ),
+            "received skb [csum none len 98 priority 0 pkt_type otherhost protocol IPv4 (0x0800)]"
+        );
+
+        let event = SkbEvent {
+            meta: Some(SkbMetaEvent {
+                len: 98,
+                headroom: 64,
+                tailroom: 158,
+                truesize: 768,
+                tail: 162,
+                end: 320,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format!(
+                "{}",
+                event.display(&DisplayFormat::new(), &FormatterConf::new())
+            ),
+            "skb [csum none len 98 headroom 64 tailroom 158 truesize 768 priority 0 pkt_type host]"
+        );
+    }
+
+    #[test]
+    fn frags_display() {
+        let event = SkbEvent {
+            frags: Some(SkbFragsEvent {
+                nr_frags: 10,
+                sizes: vec![1448; 8],
+                frag_list: true,
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format!(
+                "{}",
+                event.display(&DisplayFormat::new(), &FormatterConf::new())
+            ),
+            "frags [nr 10 sizes 1448,1448,1448,1448,1448,1448,1448,1448,... frag_list]"
+        );
+    }
+
+    #[test]
+    fn udp_segments() {
+        let mut event = SkbEvent {
+            udp: Some(SkbUdpEvent {
+                sport: 443,
+                dport: 35000,
+                len: 11808,
+            }),
+            gso: Some(SkbGsoEvent {
+                size: 1200,
+                segs: 10,
+                r#type: SKB_GSO_UDP_L4,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(event.udp_segments(), Some((10, 1200)));
+        assert_eq!(
+            format!(
+                "{}",
+                event.display(&DisplayFormat::new(), &FormatterConf::new())
+            ),
+            "len 11800 segs 10 size 1200 gso [type 0x20000 segs 10 size 1200]"
+        );
+
+        // Number of segments not reported.
+        event.gso.as_mut().unwrap().segs = 0;
+        assert_eq!(event.udp_segments(), Some((10, 1200)));
+
+        // TCP segmentation.
+        event.gso.as_mut().unwrap().r#type = 0x1;
+        assert_eq!(event.udp_segments(), None);
+    }
+
+    #[test]
+    fn srh_display() {
+        let event = SkbEvent {
+            srh: Some(SkbSrhEvent {
+                segments_left: 1,
+                last_entry: 1,
+                active: Some("fc00::2".to_string()),
+                segments: vec!["fc00::1".to_string(), "fc00::2".to_string()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format!(
+                "{}",
+                event.display(&DisplayFormat::new(), &FormatterConf::new())
+            ),
+            "srh [segleft 1 last 1 active fc00::2 segs fc00::1,fc00::2]"
+        );
+    }
+
+    #[test]
+    fn encap_display() {
+        let ip = |saddr: &str, daddr: &str, protocol, len| SkbIpEvent {
+            saddr: saddr.to_string(),
+            daddr: daddr.to_string(),
+            version: SkbIpVersion::V6 {
+                v6: SkbIpv6Event { flow_label: 0 },
+            },
+            protocol,
+            len,
+            ttl: 64,
+            ecn: 0,
+        };
+
+        let mut event = SkbEvent {
+            ip: Some(ip("fc00::1", "fc00::2", 17, 78)),
+            udp: Some(SkbUdpEvent {
+                sport: 51234,
+                dport: 4789,
+                len: 78,
+            }),
+            encap: Some(SkbEncapEvent {
+                r#type: SkbEncapType::Vxlan,
+                id: Some(42),
+                protocol: 0x6558,
+            }),
+            inner: Some(SkbInnerEvent {
+                ip: Some(ip("2001:db8::1", "2001:db8::2", 58, 16)),
+                icmpv6: Some(SkbIcmpV6Event {
+                    r#type: 128,
+                    code: 0,
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            format!(
+                "{}",
+                event.display(&DisplayFormat::new(), &FormatterConf::new())
+            ),
+            "fc00::1.51234 > fc00::2.4789 ttl 64 len 78 proto UDP (17) len 70 vxlan (vni 42) \
+[2001:db8::1 > 2001:db8::2 ttl 64 len 16 proto ICMPv6 (58) type echo-request (128) code 0]"
+        );
+
+        event.encap = Some(SkbEncapEvent {
+            r#type: SkbEncapType::Gre,
+            id: None,
+            protocol: 0x86dd,
+        });
+        event.inner = None;
+        assert!(format!(
+            "{}",
+            event.display(&DisplayFormat::new(), &FormatterConf::new())
+        )
+        .ends_with(" gre"));
+    }
+
+    #[test]
+    fn vlan_display() {
+        let display = |event: &SkbEvent| {
+            format!(
+                "{}",
+                event.display(&DisplayFormat::new(), &FormatterConf::new())
+            )
+        };
+
+        let mut event = SkbEvent {
+            vlan: Some(SkbVlanEvent {
+                pcp: 3,
+                vid: 100,
+                acceleration: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(display(&event), "vlan (id 100 prio 3 accel)");
+
+        // QinQ, the outer tag being in the packet.
+        event.vlan = Some(SkbVlanEvent {
+            pcp: 0,
+            vid: 10,
+            tpid: Some(0x88a8),
+            inner: vec![SkbVlanTag::from_tci(0x8100, 0x3064)],
+            ..Default::default()
+        });
+        assert_eq!(
+            display(&event),
+            "vlan (tpid 0x88a8 id 10 prio 0) vlan (id 100 prio 1 drop)"
+        );
+    }
+
+    #[test]
+    fn sctp_display() {
+        let event = SkbEvent {
+            ip: Some(SkbIpEvent {
+                saddr: "10.0.0.1".to_string(),
+                daddr: "10.0.0.2".to_string(),
+                version: SkbIpVersion::V4 {
+                    v4: SkbIpv4Event {
+                        tos: 0,
+                        id: 0,
+                        flags: 2,
+                        offset: 0,
+                    },
+                },
+                protocol: 132,
+                len: 80,
+                ttl: 64,
+                ecn: 0,
+            }),
+            sctp: Some(SkbSctpEvent {
+                sport: 36412,
+                dport: 38412,
+                vtag: 0xc0de,
+                chunks: vec![0, 3, 42],
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            format!(
+                "{}",
+                event.display(&DisplayFormat::new(), &FormatterConf::new())
+            ),
+            "10.0.0.1.36412 > 10.0.0.2.38412 ttl 64 tos 0x0 id 0 off 0 [DF] len 80 proto SCTP (132) \
+vtag 0xc0de chunks [DATA,SACK,42]"
+        );
+    }
+
+    #[test]
+    fn dns_display() {
+        let display = |dns: SkbDnsEvent| {
+            let event = SkbEvent {
+                dns: Some(dns),
+                ..Default::default()
+            };
+            format!(
+                "{}",
+                event.display(&DisplayFormat::new(), &FormatterConf::new())
+            )
+        };
+        let question = SkbDnsQuestion {
+            name: "www.example.com".to_string(),
+            r#type: 28,
+        };
+
+        assert_eq!(
+            display(SkbDnsEvent {
+                id: 0x1a2b,
+                response: false,
+                opcode: 0,
+                rcode: 0,
+                questions: vec![question.clone()],
+                answers: Vec::new(),
+            }),
+            "dns id 0x1a2b query AAAA www.example.com"
+        );
+        assert_eq!(
+            display(SkbDnsEvent {
+                id: 0x1a2b,
+                response: true,
+                opcode: 0,
+                rcode: 0,
+                questions: vec![question],
+                answers: vec![
+                    SkbDnsAnswer {
+                        name: "www.example.com".to_string(),
+                        r#type: 5,
+                        ttl: 300,
+                        data: Some("example.com".to_string()),
+                    },
+                    SkbDnsAnswer {
+                        name: "example.com".to_string(),
+                        r#type: 28,
+                        ttl: 300,
+                        data: Some("2001:db8::1".to_string()),
+                    },
+                    SkbDnsAnswer {
+                        name: "example.com".to_string(),
+                        r#type: 99,
+                        ttl: 300,
+                        data: None,
+                    },
+                ],
+            }),
+            "dns id 0x1a2b response NOERROR AAAA www.example.com answers [www.example.com CNAME \
+example.com, example.com AAAA 2001:db8::1, example.com TYPE99]"
+        );
+    }
 }
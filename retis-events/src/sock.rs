@@ -0,0 +1,68 @@
+use std::fmt;
+
+use super::{helpers::protocol_str, *};
+use crate::{event_section, Formatter};
+
+/// Socket event section. Reports the memory accounting of a socket and its
+/// limits, to help understanding drops and stalls caused by socket buffers
+/// exhaustion.
+#[event_section(SectionId::Sock)]
+pub struct SockEvent {
+    /// Socket cookie, if one was generated for the socket.
+    pub cookie: Option<u64>,
+    /// Socket protocol, e.g. 6 for TCP.
+    pub protocol: u16,
+    /// Memory used by the receive queue, in bytes.
+    pub rmem_alloc: i32,
+    /// Receive buffer size limit, in bytes.
+    pub rcvbuf: i32,
+    /// Number of packets in the receive queue.
+    #[retis(default)]
+    pub rcvq_len: u32,
+    /// TCP only: bytes received but not read yet by the application.
+    pub tcp_unread: Option<u32>,
+    /// Transmit memory committed, in bytes.
+    pub wmem_alloc: i32,
+    /// Transmit memory queued and not yet committed, in bytes.
+    pub wmem_queued: i32,
+    /// Send buffer size limit, in bytes.
+    pub sndbuf: i32,
+    /// Memory pre-allocated to the socket, in bytes. Can be negative.
+    pub forward_alloc: i32,
+    /// The socket protocol is under global memory pressure.
+    pub memory_pressure: bool,
+}
+
+impl EventFmt for SockEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(f, "sock")?;
+        if let Some(cookie) = self.cookie {
+            write!(f, " cookie {cookie:#x}")?;
+        }
+
+        match u8::try_from(self.protocol).ok().and_then(protocol_str) {
+            Some(name) => write!(f, " proto {name}")?,
+            None => write!(f, " proto {}", self.protocol)?,
+        }
+
+        write!(f, " rmem {}/{}", self.rmem_alloc, self.rcvbuf)?;
+        if self.rcvq_len != 0 {
+            write!(f, " rcvq {}", self.rcvq_len)?;
+        }
+        if let Some(unread) = self.tcp_unread {
+            write!(f, " unread {unread}")?;
+        }
+
+        write!(
+            f,
+            " wmem {}/{} queued {} fwd_alloc {}",
+            self.wmem_alloc, self.sndbuf, self.wmem_queued, self.forward_alloc
+        )?;
+
+        if self.memory_pressure {
+            write!(f, " memory-pressure")?;
+        }
+
+        Ok(())
+    }
+}
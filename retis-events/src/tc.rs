@@ -0,0 +1,57 @@
+use std::fmt;
+
+use super::*;
+use crate::{event_section, Formatter};
+
+/// Tc event section. Reports packets entering and leaving tc BPF programs
+/// (cls_bpf filters).
+#[event_section(SectionId::Tc)]
+#[derive(Default)]
+pub struct TcEvent {
+    /// Id of the BPF program.
+    pub prog_id: u32,
+    /// Name of the BPF program.
+    pub prog_name: String,
+    /// Path of the program in the BPF filesystem, if pinned.
+    pub pinned: Option<String>,
+    /// Name of the filter, as set by the tool which attached it (e.g. the
+    /// object file and section names).
+    pub filter_name: Option<String>,
+    /// Priority of the filter.
+    pub prio: u32,
+    /// Action returned by the filter (e.g. ok, shot, redirect). Only set when
+    /// the packet leaves the program.
+    pub action: Option<String>,
+}
+
+impl EventFmt for TcEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(
+            f,
+            "tc bpf {} prog {} ({})",
+            if self.action.is_some() {
+                "exit"
+            } else {
+                "enter"
+            },
+            self.prog_id,
+            self.prog_name,
+        )?;
+
+        if let Some(pinned) = &self.pinned {
+            write!(f, " pinned {pinned}")?;
+        }
+
+        if let Some(name) = &self.filter_name {
+            write!(f, " filter {name}")?;
+        }
+
+        write!(f, " prio {}", self.prio)?;
+
+        if let Some(action) = &self.action {
+            write!(f, " action {action}")?;
+        }
+
+        Ok(())
+    }
+}
@@ -32,6 +32,21 @@ impl TimeSpec {
     pub fn nsec(&self) -> i64 {
         self.nsec
     }
+
+    /// Total number of nanoseconds.
+    pub fn as_nanos(&self) -> i128 {
+        self.sec as i128 * Self::NSECS_IN_SEC as i128 + self.nsec as i128
+    }
+
+    /// Constructs a TimeSpec from a number of nanoseconds, which can be
+    /// negative.
+    pub fn from_nanos(ns: i128) -> Self {
+        let nsecs = Self::NSECS_IN_SEC as i128;
+        Self {
+            sec: ns.div_euclid(nsecs) as i64,
+            nsec: ns.rem_euclid(nsecs) as i64,
+        }
+    }
 }
 
 impl ops::Add for TimeSpec {
@@ -121,4 +136,15 @@ mod tests {
         assert_eq!(tmp.sec(), -1);
         assert_eq!(tmp.nsec(), TimeSpec::NSECS_IN_SEC - 1);
     }
+
+    #[test]
+    fn timespec_nanos() {
+        let ts = TimeSpec::from_nanos(42 * 1_000_000_000 + 7);
+        assert_eq!((ts.sec(), ts.nsec()), (42, 7));
+        assert_eq!(ts.as_nanos(), 42_000_000_007);
+
+        let ts = TimeSpec::from_nanos(-1);
+        assert_eq!((ts.sec(), ts.nsec()), (-1, TimeSpec::NSECS_IN_SEC - 1));
+        assert_eq!(ts.as_nanos(), -1);
+    }
 }
@@ -0,0 +1,64 @@
+use std::fmt;
+
+use super::*;
+use crate::{event_section, Formatter};
+
+/// Value returned when a record could not be decrypted (-EBADMSG).
+pub const TLS_DECRYPT_ERROR: i32 = -74;
+
+/// TLS event section. Reports the records sent and received on kTLS sockets.
+#[event_section(SectionId::Tls)]
+#[derive(Default)]
+pub struct TlsEvent {
+    /// Direction of the record: `tx` or `rx`.
+    pub dir: String,
+    /// Mode of the TLS context in this direction: `sw` (kernel crypto), `hw`
+    /// (crypto offloaded to the device), `hw_record` (full TLS offload) or
+    /// `base` (no crypto configured).
+    pub mode: String,
+    /// TLS version (e.g. `1.2`, `1.3`).
+    pub version: String,
+    /// Cipher used (e.g. `aes-gcm-128`, `chacha20-poly1305`).
+    pub cipher: String,
+    /// Sequence number of the record.
+    pub seq: u64,
+    /// Addresses and ports of the socket.
+    pub saddr: String,
+    pub sport: u16,
+    pub daddr: String,
+    pub dport: u16,
+    /// Error returned when processing a received record, if any. A
+    /// `TLS_DECRYPT_ERROR` error means the record could not be decrypted.
+    pub error: Option<i32>,
+}
+
+impl EventFmt for TlsEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(
+            f,
+            "tls {} {} v{} {} seq {}",
+            self.dir, self.mode, self.version, self.cipher, self.seq
+        )?;
+
+        match self.saddr.contains(':') {
+            true => write!(
+                f,
+                " [{}]:{} > [{}]:{}",
+                self.saddr, self.sport, self.daddr, self.dport
+            )?,
+            false => write!(
+                f,
+                " {}:{} > {}:{}",
+                self.saddr, self.sport, self.daddr, self.dport
+            )?,
+        }
+
+        match self.error {
+            Some(TLS_DECRYPT_ERROR) => write!(f, " decryption failed")?,
+            Some(error) => write!(f, " error {error}")?,
+            None => (),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+//! # Units
+//!
+//! Helper types for durations, rates and sizes reported in events. They are
+//! stored as plain integers (and serialized as such) but are displayed in a
+//! humanized way, so all sections format those values consistently.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Format `val` using the largest unit it is at least one of. `units` is a list
+/// of (unit, unit size) ordered by increasing size; the first one is used for
+/// values smaller than the second one and is displayed without decimals. Width
+/// and alignment flags are honored.
+fn humanize(f: &mut fmt::Formatter, val: u64, units: &[(&str, u64)]) -> fmt::Result {
+    let (unit, size) = units
+        .iter()
+        .rev()
+        .find(|(_, size)| val >= *size)
+        .unwrap_or(&units[0]);
+
+    f.pad(&match *size {
+        1 => format!("{val}{unit}"),
+        size => format!("{:.2}{unit}", val as f64 / size as f64),
+    })
+}
+
+/// Duration, in nanoseconds. Displayed as ns, µs, ms or s.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+#[cfg_attr(feature = "python", derive(pyo3::IntoPyObject))]
+pub struct Duration(pub u64);
+
+impl Duration {
+    pub fn from_nanos(ns: u64) -> Self {
+        Self(ns)
+    }
+
+    pub fn as_nanos(&self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        humanize(
+            f,
+            self.0,
+            &[
+                ("ns", 1),
+                ("µs", 1_000),
+                ("ms", 1_000_000),
+                ("s", 1_000_000_000),
+            ],
+        )
+    }
+}
+
+/// Rate, in bits per second. Displayed as bps, Kbps, Mbps, Gbps or Tbps.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+#[cfg_attr(feature = "python", derive(pyo3::IntoPyObject))]
+pub struct Rate(pub u64);
+
+impl Rate {
+    /// Rate of `bytes` transferred over `duration`. A zero duration results in
+    /// a zero rate.
+    pub fn from_bytes(bytes: Bytes, duration: Duration) -> Self {
+        match duration.0 {
+            0 => Self(0),
+            ns => Self((bytes.0 as u128 * 8 * 1_000_000_000 / ns as u128) as u64),
+        }
+    }
+
+    pub fn as_bps(&self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        humanize(
+            f,
+            self.0,
+            &[
+                ("bps", 1),
+                ("Kbps", 1_000),
+                ("Mbps", 1_000_000),
+                ("Gbps", 1_000_000_000),
+                ("Tbps", 1_000_000_000_000),
+            ],
+        )
+    }
+}
+
+/// Size, in bytes. Displayed as B, KiB, MiB, GiB or TiB.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+#[cfg_attr(feature = "python", derive(pyo3::IntoPyObject))]
+pub struct Bytes(pub u64);
+
+impl Bytes {
+    pub fn as_bytes(&self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        humanize(
+            f,
+            self.0,
+            &[
+                ("B", 1),
+                ("KiB", 1 << 10),
+                ("MiB", 1 << 20),
+                ("GiB", 1 << 30),
+                ("TiB", 1 << 40),
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(Duration(0).to_string(), "0ns");
+        assert_eq!(Duration(999).to_string(), "999ns");
+        assert_eq!(Duration(1_500).to_string(), "1.50µs");
+        assert_eq!(Duration(2_340_000).to_string(), "2.34ms");
+        assert_eq!(Duration(61_000_000_000).to_string(), "61.00s");
+
+        assert_eq!(Rate(800).to_string(), "800bps");
+        assert_eq!(Rate(1_000).to_string(), "1.00Kbps");
+        assert_eq!(Rate(9_410_000_000).to_string(), "9.41Gbps");
+
+        assert_eq!(Bytes(1023).to_string(), "1023B");
+        assert_eq!(Bytes(1536).to_string(), "1.50KiB");
+        assert_eq!(Bytes(3 << 30).to_string(), "3.00GiB");
+
+        assert_eq!(format!("{:>8}", Duration(1_500)), "  1.50µs");
+    }
+
+    #[test]
+    fn rate() {
+        assert_eq!(
+            Rate::from_bytes(Bytes(125_000), Duration(1_000_000_000)),
+            Rate(1_000_000)
+        );
+        assert_eq!(Rate::from_bytes(Bytes(1500), Duration(0)), Rate(0));
+    }
+
+    #[test]
+    fn serde() {
+        assert_eq!(serde_json::to_string(&Duration(42)).unwrap(), "42");
+        assert_eq!(serde_json::from_str::<Bytes>("42").unwrap(), Bytes(42));
+    }
+}
@@ -5,7 +5,7 @@ use crate::{event_section, Formatter};
 
 #[event_section(SectionId::Userspace)]
 pub struct UserEvent {
-    /// Probe type: for now only "usdt" is supported.
+    /// Probe type: one of "usdt" or "uprobe".
     pub probe_type: String,
     /// Symbol name associated with the event (i.e. which probe generated the
     /// event).
@@ -18,6 +18,12 @@ pub struct UserEvent {
     pub pid: i32,
     /// Thread id.
     pub tid: i32,
+    /// Start time of the process (monotonic clock, in nanoseconds). Together
+    /// with the pid it identifies a process, even if the pid was reused.
+    #[retis(default)]
+    pub start_time: u64,
+    /// Userspace stack trace, from the probed function (uprobes only).
+    pub stack_trace: Option<StackTrace>,
 }
 
 impl EventFmt for UserEvent {
@@ -1,31 +1,48 @@
 use anyhow::{bail, Result};
-use clap::{arg, builder::PossibleValuesParser, Parser};
+use clap::{arg, builder::PossibleValuesParser, Parser, Subcommand};
 
 use crate::{benchmark::*, cli::*};
 
 /// Benchmark parts of Retis
 #[derive(Parser, Debug, Default)]
-#[command(name = "benchmark")]
+#[command(
+    name = "benchmark",
+    visible_alias = "bench",
+    args_conflicts_with_subcommands = true
+)]
 pub(crate) struct Benchmark {
     #[arg(
         value_parser=PossibleValuesParser::new(["events_parsing", "events_output"]),
         help = "Benchmark to run",
     )]
-    pub(super) r#type: String,
+    pub(super) r#type: Option<String>,
     #[arg(
         long,
         default_value = "false",
         help = "Run fake benchmarks to ensure there is no runtime issue"
     )]
     pub(super) ci: bool,
+    #[command(subcommand)]
+    pub(super) command: Option<BenchmarkCommand>,
+}
+
+/// Benchmark tools.
+#[derive(Subcommand, Debug)]
+pub(crate) enum BenchmarkCommand {
+    Gen(gen::Gen),
 }
 
 impl SubCommandParserRunner for Benchmark {
     fn run(&mut self) -> Result<()> {
-        match self.r#type.as_str() {
-            "events_parsing" => events_parsing::bench(self.ci)?,
-            "events_output" => events_output::bench(self.ci)?,
-            x => bail!("Unknown benchmark '{x}'"),
+        if let Some(BenchmarkCommand::Gen(gen)) = &self.command {
+            return gen.run();
+        }
+
+        match self.r#type.as_deref() {
+            Some("events_parsing") => events_parsing::bench(self.ci)?,
+            Some("events_output") => events_output::bench(self.ci)?,
+            Some(x) => bail!("Unknown benchmark '{x}'"),
+            None => bail!("No benchmark or tool specified"),
         }
 
         Ok(())
@@ -0,0 +1,291 @@
+//! # Gen
+//!
+//! Generate synthetic event files, for testing post-processing performances and
+//! downstream integrations without having to collect real events.
+
+use std::{fs::OpenOptions, io::BufWriter, path::PathBuf, thread};
+
+use anyhow::{bail, Result};
+use clap::Args;
+use log::info;
+
+use super::helpers::*;
+use crate::{
+    bindings::{
+        common_uapi::kernel_event,
+        events_uapi::{common_event, common_task_event},
+        skb_drop_hook_uapi::skb_drop_event,
+        skb_hook_uapi::*,
+        tracking_hook_uapi::skb_tracking_event,
+    },
+    collect::collector::section_factories,
+    core::{events::*, kernel::Symbol},
+    events::*,
+    helpers::time::monotonic_clock_offset,
+    process::display::*,
+};
+
+/// Symbol used to report dropped packets.
+const DROP_SYMBOL: &str = "skb:kfree_skb";
+
+/// Generate a synthetic event file
+#[derive(Args, Debug, Default)]
+pub(crate) struct Gen {
+    /// File to write the generated events to.
+    #[arg(short, long, default_value = "retis.data")]
+    pub(super) out: PathBuf,
+
+    /// Number of events to generate.
+    #[arg(short, long, default_value = "100000")]
+    pub(super) events: u64,
+
+    /// Number of distinct flows. Packets are spread over flows following a
+    /// Zipf distribution, a few flows carrying most of the traffic.
+    #[arg(long, default_value = "1000")]
+    pub(super) flows: u32,
+
+    /// Comma separated list of probes packets go through, in order. Each probe
+    /// can be given the probability for a packet to hit it, using
+    /// SYMBOL[=PROBABILITY].
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "net:netif_receive_skb,ip_rcv,tcp_v4_rcv=0.8"
+    )]
+    pub(super) probes: Vec<String>,
+
+    /// Ratio of packets being dropped, between 0 and 1. Dropped packets stop
+    /// at a random probe and generate an extra skb drop event.
+    #[arg(long, default_value = "0.01")]
+    pub(super) drop_rate: f64,
+
+    /// Seed of the pseudo-random generator, for reproducible outputs.
+    #[arg(long, default_value = "1")]
+    pub(super) seed: u64,
+}
+
+/// A probe packets can go through.
+struct GenProbe {
+    /// Address of the probed symbol.
+    addr: u64,
+    /// Raw probe type, as reported in kernel events.
+    probe_type: u8,
+    /// Probability for a packet to hit the probe.
+    probability: f64,
+}
+
+impl GenProbe {
+    fn from_str(probe: &str) -> Result<Self> {
+        let (name, probability) = match probe.rsplit_once('=') {
+            Some((name, p)) => (name, p.parse::<f64>()?),
+            None => (probe, 1.0),
+        };
+        if !(0.0..=1.0).contains(&probability) {
+            bail!("Invalid probability for probe {name} ({probability})");
+        }
+
+        let symbol = Symbol::from_name(name)?;
+        Ok(Self {
+            addr: symbol.addr()?,
+            probe_type: match symbol {
+                Symbol::Event(_) => 2, // Raw tracepoint.
+                Symbol::Func(_) => 0,  // Kprobe.
+            },
+            probability,
+        })
+    }
+}
+
+/// Small xorshift64* pseudo-random generator. Good enough for generating
+/// events while keeping the output reproducible for a given seed.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // The state must not be 0.
+        Self(seed.max(1))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+
+    /// Returns a value in [0, 1).
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a value in [low, high).
+    fn range(&mut self, low: u64, high: u64) -> u64 {
+        low + self.next_u64() % (high - low)
+    }
+}
+
+/// Zipf distribution over flows, using an exponent of 1.
+struct FlowDistribution(Vec<f64>);
+
+impl FlowDistribution {
+    fn new(flows: u32) -> Self {
+        let mut sum = 0.0;
+        Self(
+            (1..=flows)
+                .map(|k| {
+                    sum += 1.0 / k as f64;
+                    sum
+                })
+                .collect(),
+        )
+    }
+
+    fn sample(&self, rng: &mut Rng) -> u32 {
+        // Unwrap as the distribution can't be empty.
+        let x = rng.next_f64() * self.0.last().unwrap();
+        self.0.partition_point(|w| *w < x) as u32
+    }
+}
+
+// Build a raw event for the given parameters.
+fn build_event(config: &RawEventConfig) -> Result<Vec<u8>> {
+    let mut event = Vec::with_capacity(BPF_RAW_EVENT_DATA_SIZE);
+
+    common_event::build_raw_with(&mut event, config)?;
+    common_task_event::build_raw_with(&mut event, config)?;
+    kernel_event::build_raw_with(&mut event, config)?;
+    skb_tracking_event::build_raw_with(&mut event, config)?;
+    skb_netdev_event::build_raw_with(&mut event, config)?;
+    skb_netns_event::build_raw_with(&mut event, config)?;
+    skb_packet_event::build_raw_with(&mut event, config)?;
+    skb_origin_event::build_raw_with(&mut event, config)?;
+    if config.drop_reason.is_some() {
+        skb_drop_event::build_raw_with(&mut event, config)?;
+    }
+
+    Ok(finalize_raw_event(event))
+}
+
+impl Gen {
+    pub(super) fn run(&self) -> Result<()> {
+        if self.flows == 0 {
+            bail!("At least one flow is required");
+        }
+        if !(0.0..=1.0).contains(&self.drop_rate) {
+            bail!("Invalid drop rate ({})", self.drop_rate);
+        }
+
+        let probes = self
+            .probes
+            .iter()
+            .map(|p| GenProbe::from_str(p))
+            .collect::<Result<Vec<_>>>()?;
+        if probes.is_empty() {
+            bail!("At least one probe is required");
+        }
+        let drop = GenProbe::from_str(DROP_SYMBOL)?;
+
+        let mut factories = section_factories()?;
+        let mut rng = Rng::new(self.seed);
+        let flows = FlowDistribution::new(self.flows);
+        let cpus = thread::available_parallelism()?.get() as u32;
+
+        let mut p = PrintEvent::new(
+            Box::new(BufWriter::new(
+                OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .truncate(true)
+                    .open(&self.out)
+                    .or_else(|_| bail!("Could not create or open '{}'", self.out.display()))?,
+            )),
+            PrintEventFormat::Json,
+        );
+
+        // Start with a startup event, as collected files do.
+        let mut startup = Event::new();
+        startup.insert_section(
+            SectionId::Startup,
+            Box::new(StartupEvent {
+                retis_version: option_env!("RELEASE_VERSION")
+                    .unwrap_or("unspec")
+                    .to_string(),
+                clock_monotonic_offset: monotonic_clock_offset()?,
+                attached_probes: Vec::new(),
+                events_ringbuf: None,
+                session_id: String::new(),
+            }),
+        )?;
+        p.process_one(&startup)?;
+
+        let mut config = RawEventConfig {
+            timestamp: 1_000_000_000,
+            ..Default::default()
+        };
+        let (mut generated, mut packets) = (0, 0u64);
+        // Per-cpu sequence numbers, as the BPF side would assign.
+        let mut seqs = vec![0u64; cpus as usize];
+        while generated < self.events {
+            let flow = flows.sample(&mut rng);
+            // Dropped packets stop after a random probe.
+            let drop_at = (rng.next_f64() < self.drop_rate)
+                .then(|| rng.range(0, probes.len() as u64) as usize);
+
+            config.flow = flow;
+            // Packets of a given flow are processed on the same cpu, as RSS
+            // would do.
+            config.smp_id = flow % cpus;
+            config.orig_head = 0xffff_8880_0000_0000 + (packets << 8);
+            config.tracking_timestamp = config.timestamp;
+            config.drop_reason = None;
+            packets += 1;
+
+            for (i, probe) in probes.iter().enumerate() {
+                if generated == self.events {
+                    break;
+                }
+
+                if rng.next_f64() < probe.probability {
+                    config.symbol = probe.addr;
+                    config.probe_type = probe.probe_type;
+                    config.seq = next_seq(&mut seqs, config.smp_id);
+                    p.process_one(&parse_raw_event(&build_event(&config)?, &mut factories)?)?;
+                    config.timestamp += rng.range(100, 5000);
+                    generated += 1;
+                }
+
+                if drop_at == Some(i) {
+                    break;
+                }
+            }
+
+            if drop_at.is_some() && generated < self.events {
+                config.symbol = drop.addr;
+                config.probe_type = drop.probe_type;
+                // Use one of the first, generic, core drop reasons.
+                config.drop_reason = Some(rng.range(2, 7) as i32);
+                config.seq = next_seq(&mut seqs, config.smp_id);
+                p.process_one(&parse_raw_event(&build_event(&config)?, &mut factories)?)?;
+                generated += 1;
+            }
+
+            // Time between two packets.
+            config.timestamp += rng.range(1000, 20000);
+        }
+
+        p.flush()?;
+        info!(
+            "{generated} event(s) for {packets} packet(s) written to {}",
+            self.out.display()
+        );
+
+        Ok(())
+    }
+}
+
+/// Get the next sequence number of a cpu.
+fn next_seq(seqs: &mut [u64], cpu: u32) -> u64 {
+    let seq = seqs[cpu as usize];
+    seqs[cpu as usize] += 1;
+    seq
+}
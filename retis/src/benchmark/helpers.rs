@@ -26,6 +26,37 @@ use crate::{
 /// unmarshaling part is skipped if a field is not set, it should be set.
 pub(crate) trait RawSectionBuilder {
     fn build_raw(out: &mut Vec<u8>) -> Result<()>;
+
+    /// Build a raw event section using the provided parameters, for sections
+    /// whose content should vary between events (eg. when generating synthetic
+    /// events). Defaults to `build_raw`.
+    fn build_raw_with(out: &mut Vec<u8>, _config: &RawEventConfig) -> Result<()> {
+        Self::build_raw(out)
+    }
+}
+
+/// Parameters used when building raw event sections with
+/// `RawSectionBuilder::build_raw_with`.
+#[derive(Debug, Default)]
+pub(crate) struct RawEventConfig {
+    /// Timestamp of the event.
+    pub(crate) timestamp: u64,
+    /// Per-cpu sequence number of the event.
+    pub(crate) seq: u64,
+    /// CPU on which the event was generated.
+    pub(crate) smp_id: u32,
+    /// Address of the probed symbol.
+    pub(crate) symbol: u64,
+    /// Type of the probe (raw value of the kernel event probe type).
+    pub(crate) probe_type: u8,
+    /// Flow the packet belongs to. Used to build the packet addresses & ports.
+    pub(crate) flow: u32,
+    /// Tracking id (original head) of the packet.
+    pub(crate) orig_head: u64,
+    /// Timestamp at which the packet was first seen.
+    pub(crate) tracking_timestamp: u64,
+    /// Drop reason, if the packet was dropped.
+    pub(crate) drop_reason: Option<i32>,
 }
 
 /// Build a raw event section in an existing raw event vector.
@@ -62,10 +93,17 @@ pub(super) fn build_raw_event() -> Result<Vec<u8>> {
     skb_netdev_event::build_raw(&mut event)?;
     skb_netns_event::build_raw(&mut event)?;
     skb_packet_event::build_raw(&mut event)?;
+    skb_origin_event::build_raw(&mut event)?;
     ct_meta_event::build_raw(&mut event)?;
     ct_event::build_raw(&mut event)?;
     exec_event::build_raw(&mut event)?;
 
+    Ok(finalize_raw_event(event))
+}
+
+/// Wrap the raw sections of an event into a raw event and represent it as an u8
+/// vector.
+pub(super) fn finalize_raw_event(mut event: Vec<u8>) -> Vec<u8> {
     // Construct the raw event.
     let size = event.len() as u16;
     event.append(&mut vec![0; BPF_RAW_EVENT_DATA_SIZE - event.len()]);
@@ -77,5 +115,5 @@ pub(super) fn build_raw_event() -> Result<Vec<u8>> {
     };
 
     // And convert it to a Vec<u8>.
-    Ok(as_u8_vec(&raw))
+    as_u8_vec(&raw)
 }
@@ -3,3 +3,4 @@ pub(crate) mod helpers;
 
 mod events_output;
 mod events_parsing;
+mod gen;
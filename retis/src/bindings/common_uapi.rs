@@ -16,6 +16,7 @@ pub struct retis_probe_offsets {
     pub net: s8,
     pub nft_pktinfo: s8,
     pub nft_traceinfo: s8,
+    pub sock: s8,
 }
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone)]
@@ -29,4 +30,5 @@ pub struct kernel_event {
 pub struct retis_probe_config {
     pub offsets: retis_probe_offsets,
     pub stack_trace: u8_,
+    pub measure_overhead: u8_,
 }
@@ -27,6 +27,7 @@ impl Default for retis_log_event {
 #[derive(Debug, Default, Copy, Clone)]
 pub struct common_event {
     pub timestamp: u64_,
+    pub seq: u64_,
     pub smp_id: u32_,
 }
 #[repr(C)]
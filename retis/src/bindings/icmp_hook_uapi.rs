@@ -0,0 +1,31 @@
+/* automatically generated by rust-bindgen 0.70.1 */
+
+pub type __u8 = ::std::os::raw::c_uchar;
+pub type __u64 = ::std::os::raw::c_ulonglong;
+pub type u8_ = __u8;
+pub type u64_ = __u64;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct icmp_config {
+    pub global_ksym: u64_,
+    pub peer_ksym: u64_,
+    pub icmp_send_ksym: u64_,
+    pub icmp6_send_ksym: u64_,
+    pub nf_reset_ksym: u64_,
+    pub nf_reset6_ksym: u64_,
+}
+pub const ICMP_GLOBAL_RATE_LIMIT: icmp_event_kind = 1;
+pub const ICMP_PEER_RATE_LIMIT: icmp_event_kind = 2;
+pub const ICMP_NO_SOCKET_UNREACH: icmp_event_kind = 3;
+pub const ICMP_NO_SOCKET_RESET: icmp_event_kind = 4;
+pub const ICMP_LOCAL_ERROR: icmp_event_kind = 5;
+pub const ICMP_LOCAL_RESET: icmp_event_kind = 6;
+pub type icmp_event_kind = ::std::os::raw::c_uint;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct icmp_event {
+    pub kind: u8_,
+    pub type_: u8_,
+    pub code: u8_,
+    pub icmpv6: u8_,
+}
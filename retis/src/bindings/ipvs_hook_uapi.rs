@@ -0,0 +1,42 @@
+/* automatically generated by rust-bindgen 0.70.1 */
+
+pub const IPVS_SCHED_NAME_LEN: enum_IPVS_SCHED_NAME_LEN = 16;
+pub type enum_IPVS_SCHED_NAME_LEN = ::std::os::raw::c_uint;
+pub type __u8 = ::std::os::raw::c_uchar;
+pub type __u16 = ::std::os::raw::c_ushort;
+pub type __s32 = ::std::os::raw::c_int;
+pub type __u32 = ::std::os::raw::c_uint;
+pub type u8_ = __u8;
+pub type u16_ = __u16;
+pub type s32 = __s32;
+pub type u32_ = __u32;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct ipvs_event {
+    pub af: u16_,
+    pub protocol: u16_,
+    pub port: u16_,
+    pub fwmark: u32_,
+    pub svc_flags: u32_,
+    pub addr: [u8_; 16usize],
+    pub sched: [::std::os::raw::c_char; 16usize],
+    pub conn: u8_,
+    pub ignored: s32,
+    pub daf: u16_,
+    pub cport: u16_,
+    pub vport: u16_,
+    pub dport: u16_,
+    pub conn_flags: u32_,
+    pub caddr: [u8_; 16usize],
+    pub vaddr: [u8_; 16usize],
+    pub daddr: [u8_; 16usize],
+}
+impl Default for ipvs_event {
+    fn default() -> Self {
+        let mut s = ::std::mem::MaybeUninit::<Self>::uninit();
+        unsafe {
+            ::std::ptr::write_bytes(s.as_mut_ptr(), 0, 1);
+            s.assume_init()
+        }
+    }
+}
@@ -21,6 +21,7 @@ impl Default for retis_probe_offsets {
             net: -1,
             nft_pktinfo: -1,
             nft_traceinfo: -1,
+            sock: -1,
         }
     }
 }
@@ -46,8 +47,41 @@ impl Default for nft_offsets {
     }
 }
 
+pub(crate) mod nft_common_uapi;
+
+pub(crate) mod icmp_hook_uapi;
+use icmp_hook_uapi::icmp_config;
+
+unsafe impl plain::Plain for icmp_config {}
+
+pub(crate) mod netlink_hook_uapi;
+use netlink_hook_uapi::netlink_config;
+
+unsafe impl plain::Plain for netlink_config {}
+
 pub(crate) mod skb_drop_hook_uapi;
 
+pub(crate) mod sock_hook_uapi;
+
+pub(crate) mod tc_hook_uapi;
+
+pub(crate) mod ipvs_hook_uapi;
+
+pub(crate) mod seg6_hook_uapi;
+use seg6_hook_uapi::seg6_config;
+
+unsafe impl plain::Plain for seg6_config {}
+
+pub(crate) mod mptcp_hook_uapi;
+use mptcp_hook_uapi::mptcp_config;
+
+unsafe impl plain::Plain for mptcp_config {}
+
+pub(crate) mod tls_hook_uapi;
+use tls_hook_uapi::tls_config;
+
+unsafe impl plain::Plain for tls_config {}
+
 pub(crate) mod skb_tracking_uapi;
 use skb_tracking_uapi::*;
 
@@ -58,6 +92,7 @@ pub(crate) mod tracking_hook_uapi;
 
 pub(crate) mod if_vlan_uapi;
 pub(crate) mod skb_hook_uapi;
+pub(crate) mod skb_softirq_uapi;
 
 pub(crate) mod kernel_enqueue_uapi;
 pub(crate) mod kernel_exec_tp_uapi;
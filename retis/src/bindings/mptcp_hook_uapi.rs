@@ -0,0 +1,60 @@
+/* automatically generated by rust-bindgen 0.70.1 */
+
+pub const MPTCP_RM_IDS_MAX: enum_MPTCP_RM_IDS_MAX = 8;
+pub type enum_MPTCP_RM_IDS_MAX = ::std::os::raw::c_uint;
+pub type __u8 = ::std::os::raw::c_uchar;
+pub type __s16 = ::std::os::raw::c_short;
+pub type __u16 = ::std::os::raw::c_ushort;
+pub type __s32 = ::std::os::raw::c_int;
+pub type __u32 = ::std::os::raw::c_uint;
+pub type __u64 = ::std::os::raw::c_ulonglong;
+pub type u8_ = __u8;
+pub type s16 = __s16;
+pub type u16_ = __u16;
+pub type s32 = __s32;
+pub type u32_ = __u32;
+pub type u64_ = __u64;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct mptcp_config {
+    pub connect_ksym: u64_,
+    pub join_ksym: u64_,
+    pub close_ksym: u64_,
+    pub sched_ksym: u64_,
+    pub add_addr_ksym: u64_,
+    pub rm_addr_ksym: u64_,
+    pub prio_ksym: u64_,
+}
+pub const MPTCP_CONNECT: mptcp_event_kind = 1;
+pub const MPTCP_JOIN: mptcp_event_kind = 2;
+pub const MPTCP_JOIN_REFUSED: mptcp_event_kind = 3;
+pub const MPTCP_CLOSE: mptcp_event_kind = 4;
+pub const MPTCP_SCHED: mptcp_event_kind = 5;
+pub const MPTCP_ADD_ADDR: mptcp_event_kind = 6;
+pub const MPTCP_RM_ADDR: mptcp_event_kind = 7;
+pub const MPTCP_PRIO: mptcp_event_kind = 8;
+pub type mptcp_event_kind = ::std::os::raw::c_uint;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct mptcp_event {
+    pub kind: u8_,
+    pub token: u32_,
+    pub has_subflow: u8_,
+    pub subflow_id: u32_,
+    pub local_id: s16,
+    pub remote_id: u8_,
+    pub backup: u8_,
+    pub family: u16_,
+    pub sport: u16_,
+    pub dport: u16_,
+    pub saddr: [u8_; 16usize],
+    pub daddr: [u8_; 16usize],
+    pub has_addr: u8_,
+    pub addr_id: u8_,
+    pub addr_family: u16_,
+    pub addr_port: u16_,
+    pub addr: [u8_; 16usize],
+    pub rm_nr: u8_,
+    pub rm_ids: [u8_; 8usize],
+    pub ret: s32,
+}
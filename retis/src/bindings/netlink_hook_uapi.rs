@@ -0,0 +1,32 @@
+/* automatically generated by rust-bindgen 0.70.1 */
+
+pub const NETLINK_GENL_FAMILIES_MAX: enum_NETLINK_GENL_FAMILIES_MAX = 8;
+pub type enum_NETLINK_GENL_FAMILIES_MAX = ::std::os::raw::c_uint;
+pub type __u8 = ::std::os::raw::c_uchar;
+pub type __u16 = ::std::os::raw::c_ushort;
+pub type __u32 = ::std::os::raw::c_uint;
+pub type __u64 = ::std::os::raw::c_ulonglong;
+pub type u8_ = __u8;
+pub type u16_ = __u16;
+pub type u32_ = __u32;
+pub type u64_ = __u64;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct netlink_config {
+    pub broadcast_ksym: u64_,
+    pub protocols: u32_,
+    pub genl_families: [u16_; 8usize],
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct netlink_event {
+    pub len: u32_,
+    pub seq: u32_,
+    pub portid: u32_,
+    pub dst_portid: u32_,
+    pub group: u32_,
+    pub type_: u16_,
+    pub flags: u16_,
+    pub protocol: u8_,
+    pub genl_cmd: u8_,
+}
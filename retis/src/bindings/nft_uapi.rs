@@ -35,7 +35,12 @@ pub struct nft_event {
     pub t_handle: s64,
     pub c_handle: s64,
     pub r_handle: s64,
+    pub family: u8_,
     pub policy: u8_,
+    pub eval: u8_,
+    pub eval_rules: u32_,
+    pub eval_chains: u32_,
+    pub eval_latency: u64_,
 }
 impl Default for nft_event {
     fn default() -> Self {
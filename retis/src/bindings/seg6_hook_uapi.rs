@@ -0,0 +1,24 @@
+/* automatically generated by rust-bindgen 0.70.1 */
+
+pub type __u8 = ::std::os::raw::c_uchar;
+pub type __u32 = ::std::os::raw::c_uint;
+pub type __u64 = ::std::os::raw::c_ulonglong;
+pub type u8_ = __u8;
+pub type u32_ = __u32;
+pub type u64_ = __u64;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct seg6_config {
+    pub encap_ksym: u64_,
+    pub inline_ksym: u64_,
+}
+pub const SEG6_ENCAP: seg6_event_kind = 1;
+pub const SEG6_INLINE: seg6_event_kind = 2;
+pub const SEG6_LOCAL: seg6_event_kind = 3;
+pub type seg6_event_kind = ::std::os::raw::c_uint;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct seg6_event {
+    pub kind: u8_,
+    pub action: u32_,
+}
@@ -1,9 +1,11 @@
 /* automatically generated by rust-bindgen 0.70.1 */
 
 pub type __u8 = ::std::os::raw::c_uchar;
+pub type __u16 = ::std::os::raw::c_ushort;
 pub type __u32 = ::std::os::raw::c_uint;
 pub type __u64 = ::std::os::raw::c_ulonglong;
 pub type u8_ = __u8;
+pub type u16_ = __u16;
 pub type u32_ = __u32;
 pub type u64_ = __u64;
 pub const SECTION_PACKET: skb_sections = 1;
@@ -13,11 +15,16 @@ pub const SECTION_NS: skb_sections = 4;
 pub const SECTION_META: skb_sections = 5;
 pub const SECTION_DATA_REF: skb_sections = 6;
 pub const SECTION_GSO: skb_sections = 7;
+pub const SECTION_ORIGIN: skb_sections = 8;
+pub const SECTION_FEATURES: skb_sections = 9;
+pub const SECTION_SOFTIRQ: skb_sections = 10;
+pub const SECTION_FRAGS: skb_sections = 11;
 pub type skb_sections = ::std::os::raw::c_uint;
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone)]
 pub struct skb_config {
     pub sections: u64_,
+    pub capture_len: u32_,
 }
 pub const IFNAMSIZ: enum_IFNAMSIZ = 16;
 pub type enum_IFNAMSIZ = ::std::os::raw::c_uint;
@@ -43,6 +50,13 @@ pub struct skb_meta_event {
     pub csum: u32_,
     pub csum_level: u8_,
     pub priority: u32_,
+    pub pkt_type: u8_,
+    pub protocol: u16_,
+    pub headroom: u32_,
+    pub tailroom: u32_,
+    pub truesize: u32_,
+    pub tail: u32_,
+    pub end: u32_,
 }
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone)]
@@ -63,19 +77,40 @@ pub struct skb_gso_event {
     pub gso_type: u32_,
 }
 #[repr(C)]
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct skb_features_event {
+    pub features: u64_,
+    pub ifindex: u32_,
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct skb_softirq_event {
+    pub delay: u64_,
+    pub running: u64_,
+}
+pub const SKB_FRAGS_MAX: enum_SKB_FRAGS_MAX = 8;
+pub type enum_SKB_FRAGS_MAX = ::std::os::raw::c_uint;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct skb_frags_event {
+    pub sizes: [u32_; 8usize],
+    pub nr_frags: u8_,
+    pub frag_list: u8_,
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct skb_origin_event {
+    pub ifindex: u32_,
+    pub iif: u32_,
+    pub pkt_type: u8_,
+    pub has_sk: u8_,
+}
+pub const PACKET_CAPTURE_MAX: enum_PACKET_CAPTURE_MAX = 512;
+pub type enum_PACKET_CAPTURE_MAX = ::std::os::raw::c_uint;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
 pub struct skb_packet_event {
     pub len: u32_,
     pub capture_len: u32_,
-    pub packet: [u8_; 255usize],
     pub fake_eth: u8_,
 }
-impl Default for skb_packet_event {
-    fn default() -> Self {
-        let mut s = ::std::mem::MaybeUninit::<Self>::uninit();
-        unsafe {
-            ::std::ptr::write_bytes(s.as_mut_ptr(), 0, 1);
-            s.assume_init()
-        }
-    }
-}
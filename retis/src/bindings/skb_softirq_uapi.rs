@@ -0,0 +1,11 @@
+/* automatically generated by rust-bindgen 0.70.1 */
+
+pub type __u64 = ::std::os::raw::c_ulonglong;
+pub type u64_ = __u64;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct skb_softirq_state {
+    pub raised: u64_,
+    pub entry: u64_,
+    pub delay: u64_,
+}
@@ -0,0 +1,28 @@
+/* automatically generated by rust-bindgen 0.70.1 */
+
+pub type __u8 = ::std::os::raw::c_uchar;
+pub type __u16 = ::std::os::raw::c_ushort;
+pub type __s32 = ::std::os::raw::c_int;
+pub type __u32 = ::std::os::raw::c_uint;
+pub type __u64 = ::std::os::raw::c_ulonglong;
+pub type u8_ = __u8;
+pub type u16_ = __u16;
+pub type s32 = __s32;
+pub type u32_ = __u32;
+pub type u64_ = __u64;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct sock_event {
+    pub cookie: u64_,
+    pub rmem_alloc: s32,
+    pub rcvbuf: s32,
+    pub rcvq_len: u32_,
+    pub tcp_unread: u32_,
+    pub wmem_alloc: s32,
+    pub wmem_queued: s32,
+    pub sndbuf: s32,
+    pub forward_alloc: s32,
+    pub protocol: u16_,
+    pub memory_pressure: u8_,
+    pub is_tcp: u8_,
+}
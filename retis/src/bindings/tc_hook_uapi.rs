@@ -0,0 +1,31 @@
+/* automatically generated by rust-bindgen 0.70.1 */
+
+pub const TC_PROG_NAME_LEN: enum_TC_PROG_NAME_LEN = 16;
+pub type enum_TC_PROG_NAME_LEN = ::std::os::raw::c_uint;
+pub const TC_FILTER_NAME_LEN: enum_TC_FILTER_NAME_LEN = 64;
+pub type enum_TC_FILTER_NAME_LEN = ::std::os::raw::c_uint;
+pub type __u8 = ::std::os::raw::c_uchar;
+pub type __s32 = ::std::os::raw::c_int;
+pub type __u32 = ::std::os::raw::c_uint;
+pub type u8_ = __u8;
+pub type s32 = __s32;
+pub type u32_ = __u32;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct tc_event {
+    pub prog_id: u32_,
+    pub prio: u32_,
+    pub action: s32,
+    pub exit: u8_,
+    pub prog_name: [::std::os::raw::c_char; 16usize],
+    pub filter_name: [::std::os::raw::c_char; 64usize],
+}
+impl Default for tc_event {
+    fn default() -> Self {
+        let mut s = ::std::mem::MaybeUninit::<Self>::uninit();
+        unsafe {
+            ::std::ptr::write_bytes(s.as_mut_ptr(), 0, 1);
+            s.assume_init()
+        }
+    }
+}
@@ -0,0 +1,34 @@
+/* automatically generated by rust-bindgen 0.70.1 */
+
+pub type __u8 = ::std::os::raw::c_uchar;
+pub type __u16 = ::std::os::raw::c_ushort;
+pub type __s32 = ::std::os::raw::c_int;
+pub type __u64 = ::std::os::raw::c_ulonglong;
+pub type u8_ = __u8;
+pub type u16_ = __u16;
+pub type s32 = __s32;
+pub type u64_ = __u64;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct tls_config {
+    pub tx_ksym: u64_,
+    pub rx_ksym: u64_,
+}
+pub const TLS_TX: tls_event_dir = 1;
+pub const TLS_RX: tls_event_dir = 2;
+pub type tls_event_dir = ::std::os::raw::c_uint;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct tls_event {
+    pub dir: u8_,
+    pub conf: u8_,
+    pub version: u16_,
+    pub cipher: u16_,
+    pub rec_seq: [u8_; 8usize],
+    pub family: u16_,
+    pub sport: u16_,
+    pub dport: u16_,
+    pub saddr: [u8_; 16usize],
+    pub daddr: [u8_; 16usize],
+    pub ret: s32,
+}
@@ -17,7 +17,16 @@ use log::debug;
 #[cfg(feature = "benchmark")]
 use crate::benchmark::cli::Benchmark;
 use crate::{
-    collect::cli::Collect,
+    collect::{
+        cli::Collect,
+        control::{Reload, UpdateFilter},
+        marker::Mark,
+        postmortem::Postmortem,
+        probe_test::ProbeTest,
+        remote::Remote,
+        replay::Replay,
+    },
+    core::inspect::symbols::parse_kaslr_offset,
     generate::Complete,
     inspect::Inspect,
     process::cli::*,
@@ -179,6 +188,20 @@ pub(crate) struct MainConfig {
         help = "Path to kernel configuration (e.g. /boot/config-6.3.8-200.fc38.x86_64; default: auto-detect)"
     )]
     pub(crate) kconf: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Path to a kernel symbol map in the kallsyms format, used instead of /proc/kallsyms
+(e.g. a copy of /proc/kallsyms, a System.map file or a symbol list extracted from a crash dump)"
+    )]
+    pub(crate) symbols: Option<PathBuf>,
+    #[arg(
+        long,
+        requires = "symbols",
+        value_parser = parse_kaslr_offset,
+        help = "KASLR offset to add to the addresses of the --symbols map, in hexadecimal. Required
+when the map is not the running kernel one; use 0 if the map holds runtime addresses"
+    )]
+    pub(crate) kaslr_offset: Option<u64>,
 }
 
 /// ThinCli handles the first (a.k.a "thin") round of Command Line Interface parsing.
@@ -373,23 +396,37 @@ pub(crate) struct CliConfig {
 }
 
 /// Type of the "format" argument.
-// It is an enum that maps 1:1 with the formats defined in events library.
+// Text formats map 1:1 with the formats defined in events library.
 #[derive(Debug, Default, Clone, Copy, Eq, PartialEq, ValueEnum)]
 pub(crate) enum CliDisplayFormat {
     SingleLine,
     #[default]
     MultiLine,
+    /// Single-level JSON, nested fields using dotted keys (e.g. skb.ip.saddr).
+    JsonFlat,
 }
 
 /// Create and register a ThinCli
 pub(crate) fn get_cli() -> Result<ThinCli> {
     let mut cli = ThinCli::new()?;
     cli.add_subcommand(Box::new(Collect::new()?))?;
+    cli.add_subcommand(Box::new(Mark::new()?))?;
+    cli.add_subcommand(Box::new(UpdateFilter::new()?))?;
+    cli.add_subcommand(Box::new(Reload::new()?))?;
+    cli.add_subcommand(Box::new(Postmortem::new()?))?;
+    cli.add_subcommand(Box::new(Remote::new()?))?;
+    cli.add_subcommand(Box::new(Replay::new()?))?;
+    cli.add_subcommand(Box::new(ProbeTest::new()?))?;
     cli.add_subcommand(Box::new(Print::new()?))?;
     cli.add_subcommand(Box::new(Sort::new()?))?;
+    cli.add_subcommand(Box::new(Timeline::new()?))?;
+    cli.add_subcommand(Box::new(Stats::new()?))?;
+    cli.add_subcommand(Box::new(Flamegraph::new()?))?;
+    cli.add_subcommand(Box::new(Analyze::new()?))?;
     #[cfg(feature = "python")]
     cli.add_subcommand(Box::new(PythonCli::new()?))?;
     cli.add_subcommand(Box::new(Pcap::new()?))?;
+    cli.add_subcommand(Box::new(Convert::new()?))?;
     cli.add_subcommand(Box::new(Inspect::new()?))?;
     cli.add_subcommand(Box::new(ProfileCmd::new()?))?;
     cli.add_subcommand(Box::new(Complete::new()?))?;
@@ -2,13 +2,47 @@
 //!
 //! Collect is a dynamic CLI subcommand that allows collectors to register their arguments.
 
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
 use anyhow::Result;
-use clap::{builder::PossibleValuesParser, Parser};
+use clap::{
+    builder::{PossibleValue, PossibleValuesParser},
+    Parser, ValueEnum,
+};
 
-use super::Collectors;
-use crate::{cli::*, collect::collector::*};
+use super::{cmd::CmdFailure, Collectors};
+use crate::{
+    cli::*,
+    collect::collector::*,
+    core::probe::kernel::packs::PROBE_PACKS,
+    events::EventTemplate,
+    helpers::{
+        mem::parse_size,
+        time::{parse_duration, parse_nonzero_duration},
+    },
+};
+
+/// Collectors which can be enabled using --collectors.
+pub(super) const COLLECTORS: &[&str] = &[
+    "skb-tracking",
+    "skb",
+    "skb-drop",
+    "ovs",
+    "nft",
+    "ct",
+    "sock",
+    "netlink",
+    "icmp",
+    "tc",
+    "ipvs",
+    "seg6",
+    "mptcp",
+    "tls",
+];
+
+/// Collectors adding probes and events on top of the others, not enabled in
+/// auto-mode. They must be explicitly enabled using --collectors.
+pub(super) const OPT_IN_COLLECTORS: &[&str] = &["sock"];
 
 /// Collect events.
 ///
@@ -23,12 +57,15 @@ pub(crate) struct Collect {
     #[arg(
         short,
         long,
-        value_parser=PossibleValuesParser::new([
-            "skb-tracking", "skb", "skb-drop", "ovs", "nft", "ct",
-        ]),
+        value_parser=PossibleValuesParser::new(COLLECTORS.iter().flat_map(|c| [
+            PossibleValue::new(*c),
+            PossibleValue::new(format!("+{c}")).hide(true),
+        ])),
         value_delimiter=',',
-        help = "Comma-separated list of collectors to enable. When not specified default to
-auto-mode (all collectors are enabled unless a prerequisite is missing)."
+        help = "Comma-separated list of collectors to enable. Collectors are skipped if a
+prerequisite is missing, unless prefixed with '+' (e.g. '+ovs') in which case the collection
+fails. When not specified default to auto-mode (all collectors but sock are enabled unless a
+prerequisite is missing)."
     )]
     pub(super) collectors: Option<Vec<String>>,
     // Use the plural in the struct but singular for the cli parameter as we're
@@ -47,14 +84,29 @@ Valid TYPEs:
 - kprobe | k: kernel probes.
 - kretprobe | kr: kernel return probes.
 - raw_tracepoint | tp: kernel tracepoints.
+- uprobe | u: userspace probes, TARGET being PATH:FUNCTION. All processes running the binary
+  are probed. Events include the userspace stack trace.
+- usdt: userspace statically defined tracepoints, TARGET being (PATH|PID):PROVIDER::NAME. When a
+  PATH is given all processes running the binary are probed.
 
-Wildcards (*) can be used, eg. \"kprobe:tcp_*\" or \"tp:skb:*\".
+Wildcards (*) can be used, eg. \"kprobe:tcp_*\" or \"tp:skb:*\". They are not supported by
+uprobes and USDTs.
 
 Examples:
   --probe tp:skb:kfree_skb --probe kprobe:consume_skb
-  --probe skb:kfree_skb --probe consume_skb"
+  --probe skb:kfree_skb --probe consume_skb
+  --probe uprobe:/usr/sbin/ovs-vswitchd:dpif_netlink_operate
+  --probe usdt:/usr/sbin/ovs-vswitchd:dpif_netlink_operate__::op_flow_execute"
     )]
     pub(super) probes: Vec<String>,
+    #[arg(
+        long,
+        value_name = "PACK",
+        value_parser=PossibleValuesParser::new(PROBE_PACKS.iter().map(|p| PossibleValue::new(p.name).help(p.about))),
+        help = "Add a curated set of probes on a network driver's functions. Can be used multiple
+times. Probes not found on the running kernel are skipped."
+    )]
+    pub(super) probe_pack: Vec<String>,
     #[arg(
         id = "filter-packet",
         short,
@@ -64,6 +116,14 @@ Examples:
 Example: --filter-packet "ip dst host 10.0.0.1""#
     )]
     pub(super) packet_filter: Option<String>,
+    #[arg(
+        long,
+        requires = "filter-packet",
+        help = "Add probes covering the receive, forwarding, local delivery and transmit paths of the
+traffic matched by the packet filter (e.g. TCP over IPv4 for \"tcp and host 10.0.0.1\"). Without
+this option, the probes are suggested when no probe is given explicitly."
+    )]
+    pub(super) auto_probes: bool,
     #[arg(
         id = "filter-meta",
         short = 'm',
@@ -85,15 +145,160 @@ Examples of meta filters:
         num_args = 0..=1,
         default_missing_value = "retis.data",
         help = "Write the events to a file rather than to sdout. If the flag is used without a file name,
-defaults to \"retis.data\"."
+defaults to \"retis.data\". \"-\" writes the events to stdout, in the file format, so they can be piped
+to another command (e.g. `retis collect -o - | ssh host retis print /dev/stdin`)."
     )]
     pub(super) out: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "ALGO",
+        requires = "out",
+        help = "Compress the events written using --out. Compressed files are transparently
+decompressed when read."
+    )]
+    pub(super) compress: Option<Compression>,
     #[arg(
         long,
         help = "Write the events to stdout even if --out is used.",
         default_value = "false"
     )]
     pub(super) print: bool,
+    #[arg(
+        long,
+        value_name = "DURATION",
+        requires = "out",
+        value_parser = parse_nonzero_duration,
+        help = "Flush and sync the events file (--out) to disk at the given interval. If the collection
+is interrupted abruptly (crash, OOM kill, etc) the file is readable up to the last checkpoint."
+    )]
+    pub(super) checkpoint_interval: Option<Duration>,
+    #[arg(
+        long,
+        default_value = "false",
+        requires = "out",
+        help = "Record the physical location of the events ring buffer in the events file (--out), so
+the events not yet consumed when the kernel crashed can later be recovered from a crash dump using
+`retis postmortem`. Requires CAP_SYS_ADMIN. Not supported when falling back to perf buffers."
+    )]
+    pub(super) postmortem: bool,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Also write the raw events, as received from the BPF programs and before being parsed,
+to the given file. Those can later be replayed using `retis replay`, e.g. to reproduce parsing issues
+offline."
+    )]
+    pub(super) raw_out: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Stream the packets of the events (requires the skb collector) in the PCAP-ng format to the
+given file or named pipe while collecting. If PATH does not exist a named pipe is created (and removed
+once done), allowing live analysis using e.g. `wireshark -k -i PATH`. Opening a named pipe blocks
+until a reader opens it."
+    )]
+    pub(super) pcap_out: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = "1m",
+        value_parser = parse_duration,
+        help = "Record a sample of the monotonic clock offset to the wall-clock time in the events at the
+given interval, to compensate the clock drift when displaying UTC times of long collections. 0
+disables it."
+    )]
+    pub(super) clock_sync_interval: Duration,
+    #[arg(
+        long,
+        value_name = "IFACES",
+        num_args = 0..,
+        value_delimiter = ',',
+        help = "Snapshot the driver statistics (as reported by `ethtool -S`) of the given comma separated
+list of interfaces, or of all interfaces if none is given, when the collection starts and stops. The
+counters which changed are reported in an event at the end of the collection."
+    )]
+    pub(super) ethtool_stats: Option<Vec<String>>,
+    #[arg(
+        long,
+        help = "Snapshot the network stack counters (/proc/net/snmp and /proc/net/netstat, as reported by
+`nstat`) when the collection starts and stops. The counters which changed are reported in an event at
+the end of the collection. See `retis stats`."
+    )]
+    pub(super) netstat: bool,
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_size,
+        help = "Limit the memory used by Retis, in bytes or using a K, M or G suffix. When approaching
+the limit events are sampled, and dropped once it is reached. Dropped events are reported."
+    )]
+    pub(super) mem_limit: Option<u64>,
+    #[arg(
+        long,
+        value_name = "ENTRIES",
+        default_value = "128",
+        value_parser = clap::value_parser!(u32).range(1..=65536),
+        help = "Number of eBPF log messages (of up to 127 bytes) which can be pending in the log map.
+Messages emitted when it is full are lost; those are counted and reported at the end of the collection."
+    )]
+    pub(super) log_map_entries: u32,
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_nonzero_duration,
+        help = "Periodically report the skb tracking map usage (number of entries and oldest ones),
+every DURATION. This helps diagnosing correlation issues in long captures."
+    )]
+    pub(super) tracking_report: Option<Duration>,
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = "5s",
+        value_parser = parse_nonzero_duration,
+        help = "Interval at which the skb and OVS tracking maps are scanned for stale entries."
+    )]
+    pub(crate) tracking_gc_interval: Duration,
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = "1m",
+        value_parser = parse_nonzero_duration,
+        help = "Age after which entries of the skb and OVS tracking maps are considered stale and
+removed. This is a tradeoff between having consistent data and not having the maps full of old
+entries; stale entries should not happen much."
+    )]
+    pub(crate) tracking_gc_limit: Duration,
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        help = "Stop attaching probes after the given time. Probes not attached by then are skipped
+and reported, and the collection starts with the probes already attached."
+    )]
+    pub(super) attach_timeout: Option<Duration>,
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Measure the overhead of kernel probes (sampled) and report it for each probe at the end
+of the collection. This helps identifying probes too expensive to be used on production hosts."
+    )]
+    pub(super) measure_overhead: bool,
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Emit an event describing each attached probe: its symbol, the symbol parameters (from
+BTF) and the collectors hooking it. This allows explaining probes when processing events without
+access to the collecting host."
+    )]
+    pub(super) describe_probes: bool,
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Emit an event listing the BPF programs, maps and links loaded by Retis (ids, names, types
+and attach points). This allows reconciling the output of tools like bpftool with the collection
+and finding leftovers after an unclean exit."
+    )]
+    pub(super) describe_bpf: bool,
     #[arg(
         long,
         default_value = "false",
@@ -121,9 +326,64 @@ Notes:
     pub(crate) probe_stack: bool,
     #[arg(
         long,
-        help = "Execute a command and terminate the collection once done."
+        value_name = "N",
+        requires = "probe_stack",
+        help = "Only consider stack traces of drop events in probe-stack mode, and probe up to N
+functions preceding each drop. Functions not compatible or already probed are skipped and do not
+count. This converges on the code path leading to the drops. Requires the skb-drop collector.
+
+If no explicit probe is given, only tp:skb:kfree_skb is used as a starting point."
+    )]
+    pub(crate) probe_stack_drops: Option<usize>,
+    #[arg(
+        long,
+        help = "Execute a command and terminate the collection once done. Can be used multiple times,
+commands are then run sequentially. Commands are given information about the collection using
+environment variables: RETIS_SESSION_ID, RETIS_PID, RETIS_OUTPUT (if --out is used) and
+RETIS_PHASE (setup, cmd or teardown)."
+    )]
+    pub(super) cmd: Vec<String>,
+    #[arg(
+        long,
+        value_name = "CMD",
+        help = "Execute a command once the probes are attached, before the ones given with --cmd. Can
+be used multiple times."
     )]
-    pub(super) cmd: Option<String>,
+    pub(super) setup: Vec<String>,
+    #[arg(
+        long,
+        value_name = "CMD",
+        help = "Execute a command when the collection ends, whatever the reason. Can be used
+multiple times, commands are then run in the reverse order so they can undo the --setup ones."
+    )]
+    pub(super) teardown: Vec<String>,
+    #[arg(
+        long,
+        value_enum,
+        default_value = "stop",
+        help = "What to do when a --setup or --cmd command fails."
+    )]
+    pub(super) cmd_failure: CmdFailure,
+    #[arg(
+        long,
+        value_name = "FD",
+        help = "Write machine-readable status records (JSON, one per line) to the given file
+descriptor, inherited from the parent process. Records are emitted when the collection starts
+(started), once probes are attached (probes_attached), when the first event is received
+(first_event), when stopping (stopping) and to report lost events (losses)."
+    )]
+    pub(super) status_fd: Option<i32>,
+    #[arg(
+        long,
+        value_name = "LEVEL",
+        num_args = 0..=1,
+        default_missing_value = "warn",
+        value_parser=PossibleValuesParser::new(["error", "warn", "info", "debug", "trace"]),
+        help = "Also store the Retis log messages, including the ones from the BPF programs, as log
+events. Messages up to LEVEL (warn if not given) are stored, whatever the --log-level. This keeps
+the warnings which occurred during the collection next to the events they affected."
+    )]
+    pub(super) capture_log: Option<String>,
     #[arg(
         long,
         default_value = "false",
@@ -152,6 +412,22 @@ fully operational:
     #[arg(long, help = "Format used when printing an event.")]
     #[clap(value_enum, default_value_t=CliDisplayFormat::MultiLine)]
     pub(super) format: CliDisplayFormat,
+    #[arg(
+        long,
+        conflicts_with = "format",
+        help = "Format events using a template, e.g. '{common.timestamp} {kernel.symbol}'.
+Placeholders are keys of the flattened JSON representation of events (see --format json-flat).
+Fields missing in an event are left empty. '{{' and '}}' can be used for literal braces."
+    )]
+    pub(super) format_template: Option<EventTemplate>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "YAML file overriding the options which can be changed while collecting: format,
+format-template, utc, clock-sync-interval and checkpoint-interval (0 disables checkpoints). The file
+is read again on SIGHUP or when using `retis reload`, without restarting the collection."
+    )]
+    pub(super) config: Option<PathBuf>,
 
     /// Embed below all the per-collector arguments.
     #[command(flatten)]
@@ -168,11 +444,23 @@ pub(crate) struct CollectorsArgs {
 
     #[command(flatten, next_help_heading = "collector 'nft'")]
     pub(crate) nft: nft::NftCollectorArgs,
+
+    #[command(flatten, next_help_heading = "collector 'netlink'")]
+    pub(crate) netlink: netlink::NetlinkCollectorArgs,
+
+    #[command(flatten, next_help_heading = "collector 'mptcp'")]
+    pub(crate) mptcp: mptcp::MptcpCollectorArgs,
+}
+
+/// Compression algorithm of the events file (--compress).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Compression {
+    Zstd,
 }
 
 impl SubCommandParserRunner for Collect {
     fn run(&mut self) -> Result<()> {
-        let mut collectors = Collectors::new()?;
+        let mut collectors = Collectors::new(self)?;
 
         collectors.check(self)?;
         collectors.init(self)?;
@@ -0,0 +1,262 @@
+//! # Commands
+//!
+//! Commands run alongside a collection (`--setup`, `--cmd` and `--teardown`),
+//! to script experiments. Commands are run using `sh -c` and are given
+//! information about the collection through environment variables:
+//!
+//! - `RETIS_SESSION_ID`: unique identifier of the collection, also stored in
+//!   its startup event.
+//! - `RETIS_PID`: pid of the collecting process (e.g. for `retis mark --pid`).
+//! - `RETIS_OUTPUT`: events file of the collection, if any.
+//! - `RETIS_PHASE`: `setup`, `cmd` or `teardown`.
+
+use std::{
+    mem,
+    path::PathBuf,
+    process::{self, Command, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use anyhow::{anyhow, bail, Result};
+use clap::ValueEnum;
+use log::{info, warn};
+
+use crate::helpers::signals::Running;
+
+/// What to do when a command fails.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum CmdFailure {
+    /// End the collection, without running the remaining commands.
+    #[default]
+    Stop,
+    /// End the collection, without running the remaining commands, and exit
+    /// with an error.
+    Abort,
+    /// Report the failure and run the remaining commands.
+    Continue,
+}
+
+/// Runs the commands of a collection.
+pub(crate) struct CmdRunner {
+    setup: Vec<String>,
+    cmds: Vec<String>,
+    teardown: Vec<String>,
+    failure: CmdFailure,
+    env: Vec<(&'static str, String)>,
+    thread: Option<thread::JoinHandle<()>>,
+    /// Command which failed, when the collection must be aborted.
+    aborted: Arc<Mutex<Option<String>>>,
+}
+
+impl CmdRunner {
+    pub(crate) fn new(
+        setup: Vec<String>,
+        cmds: Vec<String>,
+        teardown: Vec<String>,
+        failure: CmdFailure,
+        session_id: &str,
+        out: Option<&PathBuf>,
+    ) -> Self {
+        let mut env = vec![
+            ("RETIS_SESSION_ID", session_id.to_string()),
+            ("RETIS_PID", process::id().to_string()),
+        ];
+        if let Some(out) = out {
+            env.push(("RETIS_OUTPUT", out.display().to_string()));
+        }
+
+        Self {
+            setup,
+            cmds,
+            teardown,
+            failure,
+            env,
+            thread: None,
+            aborted: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Run the setup commands and then the main ones in a dedicated thread.
+    /// The collection is terminated once the main commands are done, or if a
+    /// command fails and the failure policy asks so.
+    pub(crate) fn start(&mut self, run: Running) {
+        if self.setup.is_empty() && self.cmds.is_empty() {
+            return;
+        }
+
+        let setup = self.setup.clone();
+        let cmds = self.cmds.clone();
+        let failure = self.failure;
+        let env = self.env.clone();
+        let aborted = self.aborted.clone();
+
+        self.thread = Some(thread::spawn(move || {
+            let steps = setup
+                .iter()
+                .map(|cmd| ("setup", cmd))
+                .chain(cmds.iter().map(|cmd| ("cmd", cmd)));
+
+            for (phase, cmd) in steps {
+                if !run.running() {
+                    return;
+                }
+
+                if let Err(e) = run_cmd(cmd, phase, &env) {
+                    warn!("{e}");
+                    match failure {
+                        CmdFailure::Continue => continue,
+                        CmdFailure::Abort => {
+                            if let Ok(mut aborted) = aborted.lock() {
+                                *aborted = Some(cmd.clone());
+                            }
+                        }
+                        CmdFailure::Stop => (),
+                    }
+                    info!("Command failed, terminating ...");
+                    run.terminate();
+                    return;
+                }
+            }
+
+            if !cmds.is_empty() {
+                info!("Command(s) done, terminating ...");
+                run.terminate();
+            }
+        }));
+    }
+
+    /// Wait for the running commands, run the teardown ones and report if the
+    /// collection was aborted.
+    pub(crate) fn stop(&mut self) -> Result<()> {
+        // Commands might still be running if the collection was interrupted;
+        // do not wait for them.
+        if let Some(thread) = self.thread.take() {
+            if thread.is_finished() && thread.join().is_err() {
+                bail!("Could not join the command thread");
+            }
+        }
+
+        self.run_teardown();
+
+        match self
+            .aborted
+            .lock()
+            .map_err(|e| anyhow!("Could not lock the command state: {e}"))?
+            .as_ref()
+        {
+            Some(cmd) => bail!("Collection aborted, command '{cmd}' failed"),
+            None => Ok(()),
+        }
+    }
+
+    /// Run the teardown commands, once. Teardown commands undo the setup ones,
+    /// run them in reverse order.
+    fn run_teardown(&mut self) {
+        mem::take(&mut self.teardown).iter().rev().for_each(|cmd| {
+            if let Err(e) = run_cmd(cmd, "teardown", &self.env) {
+                warn!("{e}");
+            }
+        });
+    }
+}
+
+// Run the teardown commands even if the collection failed before being
+// stopped, so the setup does not stay in place.
+impl Drop for CmdRunner {
+    fn drop(&mut self) {
+        self.run_teardown();
+    }
+}
+
+/// Run a single command and wait for its completion.
+fn run_cmd(cmd: &str, phase: &str, env: &[(&'static str, String)]) -> Result<()> {
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .envs(env.iter().map(|(k, v)| (*k, v.as_str())))
+        .env("RETIS_PHASE", phase)
+        .stderr(Stdio::null())
+        .stdout(Stdio::null())
+        .status()
+        .map_err(|e| anyhow!("Failed to execute {phase} command '{cmd}': {e}"))?;
+
+    match status.success() {
+        true => {
+            info!("Command '{cmd}' returned ({status})");
+            Ok(())
+        }
+        false => bail!("Command '{cmd}' ({phase}) failed ({status})"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cmd_runner() {
+        let run = Running::new();
+        let mut runner = CmdRunner::new(
+            vec!["test \"$RETIS_PHASE\" = setup".to_string()],
+            vec![
+                "test \"$RETIS_SESSION_ID\" = test-id".to_string(),
+                "test \"$RETIS_OUTPUT\" = retis.data".to_string(),
+            ],
+            Vec::new(),
+            CmdFailure::Abort,
+            "test-id",
+            Some(&PathBuf::from("retis.data")),
+        );
+        runner.start(run.clone());
+        runner.thread.take().unwrap().join().unwrap();
+        assert!(!run.running());
+        assert!(runner.stop().is_ok());
+
+        let run = Running::new();
+        let mut runner = CmdRunner::new(
+            Vec::new(),
+            vec!["false".to_string(), "true".to_string()],
+            Vec::new(),
+            CmdFailure::Abort,
+            "test-id",
+            None,
+        );
+        runner.start(run.clone());
+        runner.thread.take().unwrap().join().unwrap();
+        assert!(!run.running());
+        assert!(runner.stop().is_err());
+    }
+
+    #[test]
+    fn teardown_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("teardown");
+        let teardown = vec![format!("echo x >> {}", marker.display())];
+
+        // Teardown commands run on drop when the runner was not stopped...
+        let runner = CmdRunner::new(
+            Vec::new(),
+            Vec::new(),
+            teardown.clone(),
+            CmdFailure::Stop,
+            "test-id",
+            None,
+        );
+        drop(runner);
+        assert_eq!(std::fs::read_to_string(&marker).unwrap(), "x\n");
+
+        // ...and only once otherwise.
+        let mut runner = CmdRunner::new(
+            Vec::new(),
+            Vec::new(),
+            teardown,
+            CmdFailure::Stop,
+            "test-id",
+            None,
+        );
+        runner.stop().unwrap();
+        drop(runner);
+        assert_eq!(std::fs::read_to_string(&marker).unwrap(), "x\nx\n");
+    }
+}
@@ -2,28 +2,43 @@
 use std::os::fd::{AsFd, AsRawFd};
 use std::{
     collections::{HashMap, HashSet},
-    fs::OpenOptions,
-    io::{self, BufWriter},
-    process::{Command, Stdio},
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::Path,
+    process,
+    str::FromStr,
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{anyhow, bail, Result};
-use log::{debug, info, warn};
+use log::{debug, info, warn, LevelFilter};
 use nix::{errno::Errno, mount::*, unistd::Uid};
 
 use super::{
-    cli::Collect,
+    cli::{Collect, Compression, COLLECTORS, OPT_IN_COLLECTORS},
+    cmd::CmdRunner,
     collector::{
-        ct::CtCollector, nft::NftCollector, ovs::OvsCollector, skb::SkbCollector,
-        skb_drop::SkbDropCollector, skb_tracking::SkbTrackingCollector,
+        ct::CtCollector, icmp::IcmpCollector, ipvs::IpvsCollector, mptcp::MptcpCollector,
+        netlink::NetlinkCollector, nft::NftCollector, ovs::OvsCollector, seg6::Seg6Collector,
+        skb::SkbCollector, skb_drop::SkbDropCollector, skb_tracking::SkbTrackingCollector,
+        sock::SockCollector, tc::TcCollector, tls::TlsCollector,
     },
+    config::{reload, ReloadListener, ReloadRequest, ReloadableOptions},
+    control::ControlListener,
+    ethtool::EthtoolStats,
+    marker::MarkerListener,
+    netstat::Netstat,
+    pcap_stream::PcapStream,
+    status::{Status, StatusReporter},
 };
 use crate::{
     bindings::packet_filter_uapi,
-    cli::CliDisplayFormat,
-    collect::collector::{section_factories, skb::SkbEventFactory},
+    collect::collector::{
+        nft::{nft_rules, NftEventFactory},
+        section_factories,
+        skb::SkbEventFactory,
+    },
     core::{
         events::{BpfEventsFactory, EventResult, FactoryId, RetisEventsFactory},
         filters::{
@@ -31,16 +46,21 @@ use crate::{
             meta::filter::FilterMeta,
             packets::filter::FilterPacket,
         },
-        inspect::check::collection_prerequisites,
+        inspect::{check::collection_prerequisites, fields::OptionalField},
         kernel::Symbol,
         probe::{
-            kernel::{probe_stack::ProbeStack, utils::probe_from_cli},
+            kernel::{
+                auto::{auto_probes, auto_probes_list},
+                packs::probe_pack,
+                probe_stack::ProbeStack,
+                utils::probe_from_cli,
+            },
             *,
         },
         tracking::{gc::TrackingGC, skb_tracking::init_tracking},
     },
-    events::*,
-    helpers::{signals::Running, time::*},
+    events::{file::is_stream, *},
+    helpers::{logger::Logger, manifest::Manifest, mem::MemGuard, signals::Running, time::*},
     process::display::*,
 };
 
@@ -61,6 +81,12 @@ pub(crate) trait Collector {
     fn known_kernel_types(&self) -> Option<Vec<&'static str>> {
         None
     }
+    /// List of event fields the collector only reports on kernels matching a
+    /// requirement (e.g. having a given struct member). Fields not available
+    /// on the running kernel are reported to the user at startup.
+    fn optional_fields(&self) -> Vec<OptionalField> {
+        Vec::new()
+    }
     /// Check if the collector can run (eg. all prerequisites are matched). This
     /// is a separate step from init to allow skipping collectors when they are
     /// not explicitly selected by the user.
@@ -94,6 +120,12 @@ pub(crate) trait Collector {
     fn stop(&mut self) -> Result<()> {
         Ok(())
     }
+    /// Called periodically while collecting, to let the collector follow
+    /// changes of its targets (e.g. attaching probes to a process which
+    /// restarted).
+    fn refresh(&mut self, _: &mut ProbeRuntimeManager) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// Main collectors object and API.
@@ -110,11 +142,17 @@ pub(crate) struct Collectors {
     events_factory: Arc<RetisEventsFactory>,
     // Did we mount debugfs ourselves?
     mounted_debugfs: bool,
+    // Memory guard, if a memory limit was set.
+    mem_guard: Option<MemGuard>,
+    // Unique identifier of the collection.
+    session_id: String,
+    // Machine-readable status reporting.
+    status: StatusReporter,
 }
 
 impl Collectors {
-    pub(super) fn new() -> Result<Self> {
-        let factory = BpfEventsFactory::new()?;
+    pub(super) fn new(collect: &Collect) -> Result<Self> {
+        let factory = BpfEventsFactory::new(collect.log_map_entries)?;
         let probes = ProbeManager::new()?;
 
         Ok(Collectors {
@@ -127,9 +165,27 @@ impl Collectors {
             tracking_config_map: None,
             events_factory: Arc::new(RetisEventsFactory::default()),
             mounted_debugfs: false,
+            mem_guard: None,
+            session_id: Self::new_session_id(),
+            status: StatusReporter::default(),
         })
     }
 
+    /// Generate a unique identifier for the collection.
+    fn new_session_id() -> String {
+        match fs::read_to_string("/proc/sys/kernel/random/uuid") {
+            Ok(uuid) => uuid.trim().to_string(),
+            Err(_) => format!(
+                "{:x}-{}",
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_nanos(),
+                process::id()
+            ),
+        }
+    }
+
     /// Setup user defined input filter.
     fn setup_filters(probes: &mut ProbeBuilderManager, collect: &Collect) -> Result<()> {
         if let Some(f) = &collect.packet_filter {
@@ -181,6 +237,15 @@ impl Collectors {
         if collect.probe_stack && collect.packet_filter.is_none() && collect.meta_filter.is_none() {
             bail!("Probe-stack mode requires filtering (--filter-packet and/or --filter-meta)");
         }
+        if collect.probe_stack_drops == Some(0) {
+            bail!("--probe-stack-drops requires at least one function to be probed");
+        }
+
+        // Events written to stdout using `--out -` can't be mixed with their
+        // text representation.
+        if collect.print && collect.out.as_deref() == Some(Path::new("-")) {
+            bail!("--print can't be used when writing events to stdout (--out -)");
+        }
 
         // --allow-system-changes requires root.
         if collect.allow_system_changes && !Uid::effective().is_root() {
@@ -220,6 +285,13 @@ impl Collectors {
     pub(super) fn init(&mut self, collect: &Collect) -> Result<()> {
         self.run.register_term_signals()?;
 
+        self.status = StatusReporter::new(collect.status_fd)?;
+        self.status.report(Status::Started {
+            pid: process::id(),
+            session_id: &self.session_id,
+            output: collect.out.as_deref(),
+        });
+
         // Check if we need to report stack traces in the events.
         if collect.stack || collect.probe_stack {
             self.probes
@@ -227,32 +299,47 @@ impl Collectors {
                 .set_probe_opt(probe::ProbeOption::StackTrace)?;
         }
 
-        // Generate an initial event with the startup section.
-        self.events_factory.add_event(|event| {
-            event.insert_section(
-                SectionId::Startup,
-                Box::new(StartupEvent {
-                    retis_version: option_env!("RELEASE_VERSION")
-                        .unwrap_or("unspec")
-                        .to_string(),
-                    clock_monotonic_offset: monotonic_clock_offset()?,
-                }),
-            )
-        })?;
+        if collect.measure_overhead {
+            self.probes
+                .builder_mut()?
+                .set_probe_opt(probe::ProbeOption::MeasureOverhead)?;
+        }
+
+        if let Some(timeout) = collect.attach_timeout {
+            self.probes.builder_mut()?.set_attach_timeout(timeout);
+        }
 
+        // List of collectors to initialize, along with whether they are
+        // required (prefixed with '+' on the command line) or can be skipped
+        // if a prerequisite is missing.
         let (auto_mode, collectors) = match &collect.collectors {
-            Some(collectors) => (
-                false,
-                collectors.iter().map(|c| c.as_ref()).collect::<Vec<&str>>(),
-            ),
+            Some(collectors) => {
+                let mut list: Vec<(&str, bool)> = Vec::new();
+                collectors.iter().for_each(|c| {
+                    let (name, required) = match c.strip_prefix('+') {
+                        Some(name) => (name, true),
+                        None => (c.as_str(), false),
+                    };
+                    match list.iter_mut().find(|(n, _)| *n == name) {
+                        Some((_, r)) => *r |= required,
+                        None => list.push((name, required)),
+                    }
+                });
+                (false, list)
+            }
             None => (
                 true,
-                vec!["skb-tracking", "skb", "skb-drop", "ovs", "nft", "ct"],
+                COLLECTORS
+                    .iter()
+                    .filter(|name| !OPT_IN_COLLECTORS.contains(name))
+                    .map(|name| (*name, false))
+                    .collect(),
             ),
         };
 
         // Try initializing all collectors.
-        for name in collectors {
+        let mut skipped = false;
+        for (name, required) in collectors {
             let mut c: Box<dyn Collector> = match name {
                 "skb-tracking" => Box::new(SkbTrackingCollector::new()?),
                 "skb" => Box::new(SkbCollector::new()?),
@@ -260,21 +347,31 @@ impl Collectors {
                 "ovs" => Box::new(OvsCollector::new()?),
                 "nft" => Box::new(NftCollector::new()?),
                 "ct" => Box::new(CtCollector::new()?),
+                "sock" => Box::new(SockCollector::new()?),
+                "netlink" => Box::new(NetlinkCollector::new()?),
+                "icmp" => Box::new(IcmpCollector::new()?),
+                "tc" => Box::new(TcCollector::new()?),
+                "ipvs" => Box::new(IpvsCollector::new()?),
+                "seg6" => Box::new(Seg6Collector::new()?),
+                "mptcp" => Box::new(MptcpCollector::new()?),
+                "tls" => Box::new(TlsCollector::new()?),
                 _ => bail!("Unknown collector {name}"),
             };
 
             // Check if the collector can run (prerequisites are met).
             if let Err(e) = c.can_run(collect) {
-                // Do not issue an error if the list of collectors was set by
-                // default, aka. auto-detect mode.
-                if auto_mode {
-                    debug!("Cannot run collector {name}: {e}");
-                    continue;
-                } else {
+                // Only issue an error if the collector is required. Collectors
+                // set by default, aka. auto-detect mode, never are.
+                if required {
                     bail!("Cannot run collector {name}: {e}");
                 }
+                debug!("Cannot run collector {name}: {e}");
+                skipped = true;
+                continue;
             }
 
+            // Hooks registered by the collector are tagged with its name.
+            self.probes.builder_mut()?.set_collector(Some(name));
             if let Err(e) = c.init(
                 collect,
                 self.probes.builder_mut()?,
@@ -282,6 +379,7 @@ impl Collectors {
             ) {
                 bail!("Could not initialize collector {name}: {e}");
             }
+            self.probes.builder_mut()?.set_collector(None);
 
             // If the collector provides known kernel types, meaning we have a
             // dynamic collector, retrieve and store them for later processing.
@@ -294,8 +392,15 @@ impl Collectors {
             self.collectors.insert(name.to_string(), c);
         }
 
-        //  If auto-mode is used, print the list of collectors that were started.
-        if auto_mode {
+        if collect.probe_stack_drops.is_some() && !self.collectors.contains_key("skb-drop") {
+            bail!("--probe-stack-drops requires the skb-drop collector");
+        }
+
+        self.report_missing_fields()?;
+
+        // If auto-mode is used or if some collectors were skipped, print the
+        // list of collectors that were started.
+        if auto_mode || skipped {
             info!(
                 "Collector(s) started: {}",
                 self.collectors
@@ -308,20 +413,32 @@ impl Collectors {
 
         // Initialize tracking & filters.
         if !cfg!(test) && self.known_kernel_types.contains("struct sk_buff *") {
-            let (gc, map) = init_tracking(self.probes.builder_mut()?)?;
+            let (gc, map) = init_tracking(
+                self.probes.builder_mut()?,
+                collect.tracking_gc_interval,
+                collect.tracking_gc_limit,
+                collect.tracking_report,
+            )?;
             self.tracking_gc = Some(gc);
             self.tracking_config_map = Some(map);
         }
         Self::setup_filters(self.probes.builder_mut()?, collect)?;
 
         // If probe_stack is on and user hasn't provided a starting point, use
-        // skb:consume_skb & skb:kfree_skb.
-        if collect.probe_stack && collect.probes.is_empty() {
-            self.probes
-                .builder_mut()?
-                .register_probe(Probe::raw_tracepoint(Symbol::from_name(
-                    "skb:consume_skb",
-                )?)?)?;
+        // skb:consume_skb & skb:kfree_skb (only the latter when following
+        // drops).
+        if collect.probe_stack
+            && collect.probes.is_empty()
+            && collect.probe_pack.is_empty()
+            && !collect.auto_probes
+        {
+            if collect.probe_stack_drops.is_none() {
+                self.probes
+                    .builder_mut()?
+                    .register_probe(Probe::raw_tracepoint(Symbol::from_name(
+                        "skb:consume_skb",
+                    )?)?)?;
+            }
             self.probes
                 .builder_mut()?
                 .register_probe(Probe::raw_tracepoint(Symbol::from_name("skb:kfree_skb")?)?)?;
@@ -349,6 +466,123 @@ impl Collectors {
                 .try_for_each(|p| self.probes.builder_mut()?.register_probe(p))?;
             Ok(())
         })?;
+        collect
+            .probe_pack
+            .iter()
+            .try_for_each(|name| -> Result<()> {
+                probe_pack(name)?
+                    .probes(filter)?
+                    .drain(..)
+                    .try_for_each(|p| self.probes.builder_mut()?.register_probe(p))?;
+                Ok(())
+            })?;
+
+        // Add (or suggest) probes covering the traffic matched by the packet
+        // filter.
+        if let Some(packet_filter) = &collect.packet_filter {
+            if collect.auto_probes {
+                auto_probes(packet_filter, filter)?
+                    .drain(..)
+                    .try_for_each(|p| self.probes.builder_mut()?.register_probe(p))?;
+            } else if collect.probes.is_empty() && collect.probe_pack.is_empty() {
+                info!(
+                    "Probes covering the filtered traffic can be added using --auto-probes: {}",
+                    auto_probes_list(packet_filter).join(", ")
+                );
+            }
+        }
+
+        // Look for probes and BPF programs attached by others on the targets
+        // we're about to use, before our own are attached.
+        let attached_probes = match cfg!(test) {
+            true => Vec::new(),
+            false => inventory::attached_probes(
+                self.probes.builder()?.probes(),
+                self.collectors.contains_key("tc"),
+            ),
+        };
+        Self::report_attached_probes(&attached_probes);
+
+        // Record where the events ring buffer lives in physical memory, for
+        // its content to be recoverable from a crash dump.
+        let events_ringbuf = match collect.postmortem {
+            true => match self.factory.ringbuf_layout() {
+                Ok(layout) => Some(layout),
+                Err(e) => bail!("Could not record the events ring buffer location: {e}"),
+            },
+            false => None,
+        };
+
+        // Generate an initial event with the startup section.
+        self.events_factory.add_event(|event| {
+            event.insert_section(
+                SectionId::Startup,
+                Box::new(StartupEvent {
+                    retis_version: option_env!("RELEASE_VERSION")
+                        .unwrap_or("unspec")
+                        .to_string(),
+                    clock_monotonic_offset: monotonic_clock_offset()?,
+                    attached_probes,
+                    events_ringbuf,
+                    session_id: self.session_id.clone(),
+                }),
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// Warn about the probes attached by others which might interfere with
+    /// ours. tc BPF programs are the ones observed by the tc collector and are
+    /// only listed.
+    fn report_attached_probes(attached_probes: &[AttachedProbe]) {
+        let (tc, probes): (Vec<_>, Vec<_>) = attached_probes.iter().partition(|p| p.target == "tc");
+
+        if !probes.is_empty() {
+            warn!(
+                "Probes already attached to targets used by Retis, events might be missed or reordered:\n{}",
+                probes
+                    .iter()
+                    .map(|p| format!("  {p}"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+
+        if !tc.is_empty() {
+            info!(
+                "tc BPF programs loaded: {}",
+                tc.iter()
+                    .map(|p| format!(
+                        "{} ({})",
+                        p.prog_name.as_deref().unwrap_or("?"),
+                        p.prog_id.unwrap_or_default()
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+
+    /// Report the optional event fields of the collectors which will be
+    /// missing on the running kernel.
+    fn report_missing_fields(&self) -> Result<()> {
+        let mut missing = Vec::new();
+        for (name, c) in self.collectors.iter() {
+            for field in c.optional_fields() {
+                if !field.available()? {
+                    missing.push(format!("  {name}: {} ({})", field.name, field.req));
+                }
+            }
+        }
+
+        if !missing.is_empty() {
+            missing.sort();
+            warn!(
+                "Event fields not supported by the running kernel, they won't be reported:\n{}",
+                missing.join("\n")
+            );
+        }
 
         Ok(())
     }
@@ -356,6 +590,12 @@ impl Collectors {
     /// Start the event retrieval for all collectors by calling
     /// their `start()` function.
     pub(super) fn start(&mut self, collect: &Collect) -> Result<()> {
+        if let (Some(level), Some(logger)) = (&collect.capture_log, Logger::get()) {
+            let level = LevelFilter::from_str(level)
+                .map_err(|e| anyhow!("Invalid log level: {level} ({e})"))?;
+            logger.mirror_to_events(self.events_factory.clone(), level);
+        }
+
         // Create factories.
         #[cfg_attr(test, allow(unused_mut))]
         let mut section_factories = section_factories()?;
@@ -375,6 +615,18 @@ impl Collectors {
                         .any(|s| s == "all" || s == "eth"),
                 );
         }
+        if self.collectors.contains_key("nft") {
+            if let Some(nft_factory) = section_factories.get_mut(&FactoryId::Nft) {
+                match nft_rules() {
+                    Ok(rules) => nft_factory
+                        .as_any_mut()
+                        .downcast_mut::<NftEventFactory>()
+                        .ok_or_else(|| anyhow!("Failed to downcast NftEventFactory"))?
+                        .set_rules(rules),
+                    Err(e) => info!("Rules won't be reported in nft events: {e}"),
+                }
+            }
+        }
 
         #[cfg(not(test))]
         {
@@ -388,6 +640,15 @@ impl Collectors {
             self.probes
                 .builder_mut()?
                 .reuse_map("log_map", self.factory.log_map_fd())?;
+            self.probes
+                .builder_mut()?
+                .reuse_map("log_lost_map", self.factory.log_lost_map_fd())?;
+            self.probes
+                .builder_mut()?
+                .reuse_map("events_seq_map", self.factory.seq_map_fd())?;
+            self.probes
+                .builder_mut()?
+                .reuse_map("events_lost_map", self.factory.lost_map_fd())?;
             match section_factories.get_mut(&FactoryId::Kernel) {
                 Some(kernel_factory) => {
                     kernel_factory
@@ -405,16 +666,39 @@ impl Collectors {
             gc.start(self.run.clone())?;
         }
 
+        if let Some(limit) = collect.mem_limit {
+            self.mem_guard = Some(MemGuard::new(limit)?);
+        }
+
+        if let Some(raw_out) = &collect.raw_out {
+            self.factory.record_raw(raw_out)?;
+        }
+
         // Start factory
-        self.factory.start(section_factories)?;
+        self.factory
+            .start(section_factories, self.mem_guard.clone())?;
 
         // Attach probes and start collectors. We're using an open coded take &
         // replace combination. We could use a Cell<> instead but that would
         // complicate the use of self.probes (additional .get() calls) while
         // behaving the same.
+        let descs = match collect.describe_probes {
+            true => Self::describe_probes(self.probes.builder()?),
+            false => Vec::new(),
+        };
         let probes = std::mem::take(&mut self.probes);
         let _ = std::mem::replace(&mut self.probes, probes.into_runtime()?);
 
+        // Describe the probes which could be attached.
+        let attached = self.probes.runtime()?.attached_probes();
+        for (key, desc) in descs.into_iter() {
+            if attached.contains(&key) {
+                self.events_factory.add_event(|event| {
+                    event.insert_section(SectionId::ProbeDesc, Box::new(desc.clone()))
+                })?;
+            }
+        }
+
         for (name, c) in &mut self.collectors {
             debug!("Starting collector {name}");
             if c.start().is_err() {
@@ -422,15 +706,85 @@ impl Collectors {
             }
         }
 
+        // Describe the BPF objects we loaded, once all collectors are started
+        // as some load their own programs.
+        if collect.describe_bpf {
+            self.events_factory.add_event(|event| {
+                event.insert_section(SectionId::BpfObjects, Box::new(inventory::own_objects()))
+            })?;
+        }
+
+        self.status.report(Status::ProbesAttached {
+            probes: self.probes.runtime()?.attached_probes().len(),
+        });
+
         Ok(())
     }
 
+    /// Describe the registered probes (symbol, parameters and collectors
+    /// hooking them), indexed by the probe key.
+    fn describe_probes(builder: &ProbeBuilderManager) -> Vec<(String, ProbeDescEvent)> {
+        builder
+            .probes()
+            .map(|probe| {
+                let (probe_type, symbol, args) = match probe.r#type() {
+                    ProbeType::Kprobe(kp) => ("kprobe", kp.symbol.name(), kp.symbol.parameters()),
+                    ProbeType::Kretprobe(kp) => {
+                        ("kretprobe", kp.symbol.name(), kp.symbol.parameters())
+                    }
+                    ProbeType::RawTracepoint(kp) => {
+                        ("raw_tracepoint", kp.symbol.name(), kp.symbol.parameters())
+                    }
+                    ProbeType::Usdt(up) => (
+                        "usdt",
+                        format!("{}:{}", up.provider, up.name),
+                        Ok(Vec::new()),
+                    ),
+                    ProbeType::Uprobe(up) => ("uprobe", up.function.clone(), Ok(Vec::new())),
+                };
+
+                (
+                    probe.key(),
+                    ProbeDescEvent {
+                        probe_type: probe_type.to_string(),
+                        symbol,
+                        args: args.unwrap_or_default(),
+                        collectors: builder.probe_collectors(probe),
+                    },
+                )
+            })
+            .collect()
+    }
+
     /// Stop the event retrieval for all collectors in the group by calling
     /// their `stop()` function. All the collectors are in charge to clean-up
     /// their temporary side effects and exit gracefully.
     fn stop(&mut self) -> Result<()> {
         self.probes.runtime_mut()?.detach()?;
-        self.probes.runtime_mut()?.report_counters()?;
+        let lost = self.probes.runtime_mut()?.report_counters()?;
+        match self.factory.lost_events() {
+            Ok(0) => (),
+            Ok(lost) => warn!(
+                "{lost} event(s) could not be reported by the BPF programs, the capture is incomplete"
+            ),
+            Err(e) => warn!("Could not get the number of lost events: {e}"),
+        }
+        let lost_logs = self.factory.lost_logs().unwrap_or_else(|e| {
+            warn!("Could not get the number of lost eBPF log messages: {e}");
+            0
+        });
+        if lost_logs > 0 {
+            warn!("lost {lost_logs} eBPF log message(s), consider increasing --log-map-entries");
+        }
+        self.status.report(Status::Losses {
+            probes: lost,
+            memory: self
+                .mem_guard
+                .as_ref()
+                .map(|guard| guard.dropped())
+                .unwrap_or_default(),
+            logs: lost_logs,
+        });
 
         for (name, c) in &mut self.collectors {
             debug!("Stopping collector {name}");
@@ -463,70 +817,145 @@ impl Collectors {
     /// (e.g. ctrl+c), then return after properly cleaning up. This is the main
     /// collector cmd loop.
     pub(super) fn process(&mut self, collect: &Collect) -> Result<()> {
+        let start = SystemTime::now();
+        // Options which can be changed while collecting, see --config.
+        let mut options = ReloadableOptions::new(collect)?;
         let mut printers = Vec::new();
 
         // Write events to stdout if we don't write to a file (--out) or if
-        // explicitly asked to (--print).
-        if collect.out.is_none() || collect.print {
-            let format = DisplayFormat::new()
-                .multiline(collect.format == CliDisplayFormat::MultiLine)
-                .time_format(if collect.utc {
-                    TimeFormat::UtcDate
-                } else {
-                    TimeFormat::MonotonicTimestamp
-                })
-                .monotonic_offset(monotonic_clock_offset()?);
-
+        // explicitly asked to (--print). It is always the first printer.
+        let stdout = collect.out.is_none() || collect.print;
+        if stdout {
             printers.push(PrintEvent::new(
                 Box::new(io::stdout()),
-                PrintEventFormat::Text(format),
+                stdout_format(&options)?,
             ));
         }
 
-        // Write the events to a file if asked to.
+        // Write the events to a file, or to stdout using `--out -`, if asked
+        // to.
+        let mut out_file = None;
         if let Some(out) = collect.out.as_ref() {
-            printers.push(PrintEvent::new(
-                Box::new(BufWriter::new(
-                    OpenOptions::new()
-                        .create(true)
-                        .write(true)
-                        .truncate(true)
-                        .open(out)
-                        .or_else(|_| bail!("Could not create or open '{}'", out.display()))?,
-                )),
-                PrintEventFormat::Json,
-            ));
-        }
+            let writer: Box<dyn Write> = if out == Path::new("-") {
+                Box::new(io::stdout())
+            } else {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .truncate(true)
+                    .open(out)
+                    .or_else(|_| bail!("Could not create or open '{}'", out.display()))?;
 
-        if let Some(cmd) = collect.cmd.to_owned() {
-            let run = self.run.clone();
-            std::thread::spawn(move || {
-                match Command::new("sh")
-                    .arg("-c")
-                    .arg(&cmd)
-                    .stderr(Stdio::null())
-                    .stdout(Stdio::null())
-                    .status()
-                {
-                    Err(e) => warn!("Failed to execute command {e}"),
-                    Ok(status) => {
-                        info!("Command returned ({status}), terminating ...");
-                    }
-                }
+                out_file = Some(file.try_clone()?);
+                Box::new(file)
+            };
 
-                run.terminate();
-            });
+            // The compressed stream is completed when the printer is dropped.
+            let writer: Box<dyn Write> = match collect.compress {
+                Some(Compression::Zstd) => Box::new(zstd::Encoder::new(writer, 0)?.auto_finish()),
+                None => Box::new(BufWriter::new(writer)),
+            };
+
+            printers.push(PrintEvent::new(writer, PrintEventFormat::Json));
         }
 
+        // Stream the packets to a file or named pipe, if asked to.
+        let mut pcap = match &collect.pcap_out {
+            Some(path) => Some(PcapStream::new(path)?),
+            None => None,
+        };
+
         let (mut iccount, mut eccount) = (0, 0);
         let mut probe_stack = ProbeStack::new(
             collect.stack,
+            collect.probe_stack_drops,
             self.probes.runtime_mut()?.attached_probes(),
             self.known_kernel_types.clone(),
         );
 
+        let mut checkpoint = Checkpoint::from_options(&options, out_file.as_ref())?;
+        let mut clock_sync = ClockSync::from_options(&options);
+        let mut loss_report = LossReport::new();
+
+        // Reload the configuration on SIGHUP, or when requested on the
+        // control socket (e.g. using `retis reload`).
+        let reload_request = collect.config.as_ref().map(|_| ReloadRequest::default());
+        let mut reload_listener = match &reload_request {
+            Some(request) => Some(ReloadListener::new(request.clone())?),
+            None => None,
+        };
+
+        // Listen for markers requested by the user, e.g. using `retis mark`.
+        let mut marker = match MarkerListener::new(self.events_factory.clone()) {
+            Ok(marker) => Some(marker),
+            Err(e) => {
+                warn!("Could not listen for markers: {e}");
+                None
+            }
+        };
+
+        // Listen for requests to change the filters, e.g. using `retis filter`.
+        let mut control = match self
+            .probes
+            .runtime()
+            .and_then(|runtime| runtime.meta_map())
+            .and_then(|meta_map| ControlListener::new(meta_map, reload_request.clone()))
+        {
+            Ok(control) => Some(control),
+            Err(e) => {
+                warn!("Could not listen for control requests: {e}");
+                None
+            }
+        };
+
+        // Run the commands scripting the experiment, if any. Do it once the
+        // listeners are set up, so commands can interact with the collection.
+        let mut cmds = CmdRunner::new(
+            collect.setup.clone(),
+            collect.cmd.clone(),
+            collect.teardown.clone(),
+            collect.cmd_failure,
+            &self.session_id,
+            collect.out.as_ref(),
+        );
+        cmds.start(self.run.clone());
+
+        // Snapshot the driver and network stack statistics, their changes are
+        // reported once the collection ends.
+        let ethtool_stats = match &collect.ethtool_stats {
+            Some(ifaces) => Some(EthtoolStats::snapshot(ifaces)?),
+            None => None,
+        };
+        let netstat = match collect.netstat {
+            true => Some(Netstat::snapshot()?),
+            false => None,
+        };
+
         use EventResult::*;
         while self.run.running() {
+            if reload_request.as_ref().is_some_and(|r| r.take()) && reload(collect, &mut options) {
+                if stdout {
+                    printers[0].set_format(stdout_format(&options)?);
+                }
+                checkpoint = Checkpoint::from_options(&options, out_file.as_ref())?;
+                clock_sync = ClockSync::from_options(&options);
+            }
+            if let Some(checkpoint) = checkpoint.as_mut() {
+                checkpoint.run(&mut printers)?;
+            }
+            if let Some(guard) = self.mem_guard.as_mut() {
+                guard.update()?;
+            }
+            if let Some(clock_sync) = clock_sync.as_mut() {
+                clock_sync.run(&self.events_factory)?;
+            }
+            loss_report.run(&self.factory, &self.events_factory, false)?;
+            for (name, c) in self.collectors.iter_mut() {
+                if let Err(e) = c.refresh(self.probes.runtime_mut()?) {
+                    warn!("Could not refresh collector {name}: {e}");
+                }
+            }
+
             // First always try to dequeue all Retis events. This is not a
             // blocking call.
             while let Some(event) = self.events_factory.next_event() {
@@ -546,16 +975,241 @@ impl Collectors {
                     printers
                         .iter_mut()
                         .try_for_each(|p| p.process_one(&event))?;
+                    if let Some(pcap) = pcap.as_mut() {
+                        pcap.process_one(&event);
+                    }
                     eccount += 1;
+                    if eccount == 1 {
+                        self.status.report(Status::FirstEvent);
+                    }
                 }
                 Timeout => continue,
             }
         }
 
+        self.status.report(Status::Stopping { events: eccount });
+
+        if let Some(marker) = marker.as_mut() {
+            marker.stop()?;
+        }
+        if let Some(control) = control.as_mut() {
+            control.stop()?;
+        }
+        if let Some(reload_listener) = reload_listener.as_mut() {
+            reload_listener.stop()?;
+        }
+
+        if let Some(ethtool_stats) = ethtool_stats {
+            let stats = ethtool_stats.event();
+            self.events_factory.add_event(|event| {
+                event.insert_section(SectionId::EthtoolStats, Box::new(stats.clone()))
+            })?;
+        }
+        if let Some(netstat) = netstat {
+            let stats = netstat.event();
+            self.events_factory.add_event(|event| {
+                event.insert_section(SectionId::Netstat, Box::new(stats.clone()))
+            })?;
+        }
+        loss_report.run(&self.factory, &self.events_factory, true)?;
+        // Log events can't be written past this point.
+        if let Some(logger) = Logger::get() {
+            logger.stop_mirror();
+        }
+        while let Some(event) = self.events_factory.next_event() {
+            printers
+                .iter_mut()
+                .try_for_each(|p| p.process_one(&event))?;
+            iccount += 1;
+        }
+
         printers.iter_mut().try_for_each(|p| p.flush())?;
+        // Complete the outputs (e.g. compressed streams).
+        drop(printers);
+        if let Some(pcap) = pcap {
+            pcap.finish()?;
+        }
+        if let Some(checkpoint) = checkpoint.as_mut() {
+            checkpoint.sync()?;
+        }
         info!("{} event(s) processed", eccount);
+
+        // Describe the events file, for its integrity to be checked when
+        // processing it. Streams (stdout, named pipes) can't be read back.
+        if let Some(out) = collect.out.as_ref().filter(|out| !is_stream(out)) {
+            if let Err(e) = Manifest::new(out, &self.session_id, start, eccount + iccount)
+                .and_then(|manifest| manifest.write(out))
+            {
+                warn!("Could not write the manifest of {}: {e}", out.display());
+            }
+        }
         debug!("{} internal event(s) processed", iccount);
+        if let Some(guard) = &self.mem_guard {
+            guard.report();
+        }
+
+        self.stop()?;
+        if collect.measure_overhead {
+            self.probes.runtime()?.report_overhead()?;
+        }
 
-        self.stop()
+        cmds.stop()
+    }
+}
+
+/// Format of the stdout printer.
+fn stdout_format(options: &ReloadableOptions) -> Result<PrintEventFormat> {
+    let format = DisplayFormat::new()
+        .time_format(if options.utc {
+            TimeFormat::UtcDate
+        } else {
+            TimeFormat::MonotonicTimestamp
+        })
+        .monotonic_offset(monotonic_clock_offset()?);
+
+    Ok(PrintEventFormat::from_cli(
+        options.format,
+        options.format_template.clone(),
+        format,
+    ))
+}
+
+/// Periodically flushes the printers and syncs the events file to disk, so the
+/// file is readable up to the last checkpoint if the collection is interrupted
+/// abruptly. Events are written one per line, so a checkpoint always ends with
+/// a complete event.
+struct Checkpoint {
+    /// Events file, shared with its printer.
+    file: File,
+    interval: Duration,
+    last: Instant,
+}
+
+impl Checkpoint {
+    fn new(file: File, interval: Duration) -> Self {
+        Self {
+            file,
+            interval,
+            last: Instant::now(),
+        }
+    }
+
+    /// Checkpoints of the events file, if enabled.
+    fn from_options(options: &ReloadableOptions, file: Option<&File>) -> Result<Option<Self>> {
+        Ok(match (options.checkpoint_interval, file) {
+            (Some(interval), Some(file)) => Some(Self::new(file.try_clone()?, interval)),
+            (Some(_), None) => {
+                warn!("Checkpoints require writing events to a file (--out <FILE>), ignoring");
+                None
+            }
+            _ => None,
+        })
+    }
+
+    /// Make a checkpoint if the interval elapsed since the last one.
+    fn run(&mut self, printers: &mut [PrintEvent]) -> Result<()> {
+        if self.last.elapsed() < self.interval {
+            return Ok(());
+        }
+
+        printers.iter_mut().try_for_each(|p| p.flush())?;
+        self.sync()
+    }
+
+    fn sync(&mut self) -> Result<()> {
+        self.file.sync_data()?;
+        self.last = Instant::now();
+        Ok(())
+    }
+}
+
+/// Periodically records a fresh sample of the monotonic clock offset in the
+/// events stream. The offset taken at startup drifts over long collections
+/// and samples are used to keep the displayed wall-clock time accurate.
+struct ClockSync {
+    interval: Duration,
+    last: Instant,
+}
+
+impl ClockSync {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: Instant::now(),
+        }
+    }
+
+    /// Clock synchronization, unless disabled.
+    fn from_options(options: &ReloadableOptions) -> Option<Self> {
+        match options.clock_sync_interval {
+            interval if interval.is_zero() => None,
+            interval => Some(Self::new(interval)),
+        }
+    }
+
+    /// Record a sample if the interval elapsed since the last one.
+    fn run(&mut self, events_factory: &RetisEventsFactory) -> Result<()> {
+        if self.last.elapsed() < self.interval {
+            return Ok(());
+        }
+
+        self.last = Instant::now();
+        events_factory.add_event(|event| {
+            event.insert_section(
+                SectionId::ClockSync,
+                Box::new(ClockSyncEvent {
+                    clock_monotonic_offset: monotonic_clock_offset()?,
+                }),
+            )
+        })
+    }
+}
+
+/// Reports the events the BPF programs could not report (e.g. the events
+/// buffer was full) in the events stream, while events are being lost. This
+/// allows finding the incomplete parts of a capture.
+struct LossReport {
+    last: Instant,
+    /// Number of lost events, as of the last report.
+    total: u64,
+}
+
+impl LossReport {
+    /// Minimum interval between two reports.
+    const INTERVAL: Duration = Duration::from_secs(1);
+
+    fn new() -> Self {
+        Self {
+            last: Instant::now(),
+            total: 0,
+        }
+    }
+
+    /// Report the events lost since the last report, if any. Unless `force` is
+    /// set, this is done at most once per interval.
+    fn run(
+        &mut self,
+        factory: &BpfEventsFactory,
+        events_factory: &RetisEventsFactory,
+        force: bool,
+    ) -> Result<()> {
+        if !force && self.last.elapsed() < Self::INTERVAL {
+            return Ok(());
+        }
+
+        self.last = Instant::now();
+        let total = factory.lost_events()?;
+        if total <= self.total {
+            return Ok(());
+        }
+
+        let count = total - self.total;
+        self.total = total;
+        events_factory.add_event(|event| {
+            event.insert_section(
+                SectionId::LostEvents,
+                Box::new(LostEventsEvent { count, total }),
+            )
+        })
     }
 }
@@ -2,7 +2,10 @@ use anyhow::Result;
 
 use crate::{
     collect::{
-        collector::{ct::*, nft::*, ovs::*, skb::*, skb_drop::*, skb_tracking::*},
+        collector::{
+            ct::*, icmp::*, ipvs::*, mptcp::*, netlink::*, nft::*, ovs::*, seg6::*, skb::*,
+            skb_drop::*, skb_tracking::*, sock::*, tc::*, tls::*,
+        },
         Collector,
     },
     core::{
@@ -27,6 +30,14 @@ pub(crate) fn section_factories() -> Result<SectionFactories> {
     factories.insert(FactoryId::Ovs, Box::new(OvsEventFactory::new()?));
     factories.insert(FactoryId::Nft, Box::<NftEventFactory>::default());
     factories.insert(FactoryId::Ct, Box::new(CtEventFactory::new()?));
+    factories.insert(FactoryId::Sock, Box::<SockEventFactory>::default());
+    factories.insert(FactoryId::Netlink, Box::new(NetlinkEventFactory::new()?));
+    factories.insert(FactoryId::Icmp, Box::<IcmpEventFactory>::default());
+    factories.insert(FactoryId::Tc, Box::<TcEventFactory>::default());
+    factories.insert(FactoryId::Ipvs, Box::<IpvsEventFactory>::default());
+    factories.insert(FactoryId::Seg6, Box::<Seg6EventFactory>::default());
+    factories.insert(FactoryId::Mptcp, Box::<MptcpEventFactory>::default());
+    factories.insert(FactoryId::Tls, Box::<TlsEventFactory>::default());
 
     Ok(factories)
 }
@@ -61,6 +72,11 @@ pub(crate) fn get_known_types() -> Result<Vec<&'static str>> {
             .unwrap_or_default(),
     );
     known_types.append(&mut CtCollector::new()?.known_kernel_types().unwrap_or_default());
+    known_types.append(
+        &mut SockCollector::new()?
+            .known_kernel_types()
+            .unwrap_or_default(),
+    );
 
     Ok(known_types)
 }
@@ -7,7 +7,10 @@ use crate::{
     collect::{cli::Collect, Collector},
     core::{
         events::*,
-        inspect,
+        inspect::{
+            self,
+            fields::{FieldReq, OptionalField},
+        },
         probe::{Hook, ProbeBuilderManager},
     },
 };
@@ -24,6 +27,18 @@ impl Collector for CtCollector {
         Some(vec!["struct sk_buff *"])
     }
 
+    fn optional_fields(&self) -> Vec<OptionalField> {
+        vec![
+            OptionalField::new("all", FieldReq::Member("sk_buff", "_nfct")),
+            OptionalField::new("zone_id, zone_dir", FieldReq::Member("nf_conn", "zone")),
+            OptionalField::new("mark", FieldReq::Member("nf_conn", "mark")),
+            OptionalField::new(
+                "labels",
+                FieldReq::EnumValue("nf_ct_ext_id", "NF_CT_EXT_LABELS"),
+            ),
+        ]
+    }
+
     fn can_run(&mut self, _: &Collect) -> Result<()> {
         let kernel = &inspect::inspector()?.kernel;
 
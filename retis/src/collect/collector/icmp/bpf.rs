@@ -0,0 +1,46 @@
+//! Rust<>BPF types definitions for the icmp module.
+//! Please keep this file in sync with its BPF counterpart in bpf/icmp_hook.bpf.c
+
+use anyhow::{bail, Result};
+
+use crate::{
+    bindings::icmp_hook_uapi::*,
+    core::events::{
+        parse_single_raw_section, BpfRawSection, EventSectionFactory, FactoryId,
+        RawEventSectionFactory,
+    },
+    event_section_factory,
+    events::*,
+};
+
+#[event_section_factory(FactoryId::Icmp)]
+#[derive(Default)]
+pub(crate) struct IcmpEventFactory {}
+
+impl RawEventSectionFactory for IcmpEventFactory {
+    fn create(&mut self, raw_sections: Vec<BpfRawSection>) -> Result<Box<dyn EventSection>> {
+        let raw = parse_single_raw_section::<icmp_event>(&raw_sections)?;
+
+        let kind = match raw.kind as icmp_event_kind {
+            ICMP_GLOBAL_RATE_LIMIT => IcmpEventKind::GlobalRateLimit,
+            ICMP_PEER_RATE_LIMIT => IcmpEventKind::PeerRateLimit,
+            ICMP_NO_SOCKET_UNREACH => IcmpEventKind::NoSocketUnreach,
+            ICMP_NO_SOCKET_RESET => IcmpEventKind::NoSocketReset,
+            ICMP_LOCAL_ERROR => IcmpEventKind::LocalError,
+            ICMP_LOCAL_RESET => IcmpEventKind::LocalReset,
+            x => bail!("Unknown ICMP event kind ({x})"),
+        };
+
+        Ok(Box::new(IcmpEvent {
+            error: match kind {
+                IcmpEventKind::LocalError => Some(IcmpError {
+                    icmpv6: raw.icmpv6 != 0,
+                    r#type: raw.type_,
+                    code: raw.code,
+                }),
+                _ => None,
+            },
+            kind,
+        }))
+    }
+}
@@ -0,0 +1,127 @@
+#include <vmlinux.h>
+
+#include <common.h>
+
+/* Keep in sync with include/uapi/linux/icmp.h and icmpv6.h */
+#define ICMP_DEST_UNREACH	3
+#define ICMP_PORT_UNREACH	3
+#define ICMPV6_DEST_UNREACH	1
+#define ICMPV6_PORT_UNREACH	4
+
+/* ICMP hook configuration. Holds the address of the probed functions, to know
+ * where the hook is running.
+ *
+ * global_ksym:	    icmp_global_allow, global ICMP rate limit.
+ * peer_ksym:	    inet_peer_xrlim_allow, per-destination ICMP rate limit.
+ * icmp_send_ksym:  __icmp_send, sending an ICMP error.
+ * icmp6_send_ksym: icmp6_send, sending an ICMPv6 error.
+ * nf_reset_ksym:   nf_send_reset, sending a TCP reset from netfilter.
+ * nf_reset6_ksym:  nf_send_reset6, sending a TCP reset from netfilter (IPv6).
+ *
+ * Other probes are the TCP reset functions.
+ */
+struct icmp_config {
+	u64 global_ksym;
+	u64 peer_ksym;
+	u64 icmp_send_ksym;
+	u64 icmp6_send_ksym;
+	u64 nf_reset_ksym;
+	u64 nf_reset6_ksym;
+} __binding;
+struct {
+	__uint(type, BPF_MAP_TYPE_ARRAY);
+	__uint(max_entries, 1);
+	__type(key, u32);
+	__type(value, struct icmp_config);
+} icmp_config_map SEC(".maps");
+
+enum icmp_event_kind {
+	ICMP_GLOBAL_RATE_LIMIT = 1,
+	ICMP_PEER_RATE_LIMIT,
+	ICMP_NO_SOCKET_UNREACH,
+	ICMP_NO_SOCKET_RESET,
+	ICMP_LOCAL_ERROR,
+	ICMP_LOCAL_RESET,
+} __binding;
+
+/* type, code and icmpv6 are only set for ICMP_LOCAL_ERROR events. */
+struct icmp_event {
+	u8 kind;
+	u8 type;
+	u8 code;
+	u8 icmpv6;
+} __binding;
+
+DEFINE_HOOK_RAW(
+	struct icmp_config *cfg;
+	struct icmp_event *e;
+	u8 type = 0, code = 0;
+	bool icmpv6 = false;
+	u32 key = 0;
+	u8 kind;
+
+	cfg = bpf_map_lookup_elem(&icmp_config_map, &key);
+	if (!cfg)
+		return 0;
+
+	if (ctx->ksym == cfg->global_ksym || ctx->ksym == cfg->peer_ksym) {
+		/* Rate limiting functions return true when sending is allowed.
+		 * Only the lower byte holds the returned bool.
+		 */
+		if ((u8)ctx->regs.ret)
+			return -ENOMSG;
+
+		kind = ctx->ksym == cfg->global_ksym ?
+			ICMP_GLOBAL_RATE_LIMIT : ICMP_PEER_RATE_LIMIT;
+		goto report;
+	}
+
+	/* Other probes have an skb, apply the filters. */
+	if (!RETIS_TRACKABLE(ctx->filters_ret))
+		return -ENOMSG;
+
+	if (ctx->ksym == cfg->icmp_send_ksym ||
+	    ctx->ksym == cfg->icmp6_send_ksym) {
+		/* __icmp_send(skb_in, type, code, ...) and
+		 * icmp6_send(skb, type, code, ...).
+		 */
+		type = retis_get_param(ctx, 1, u8);
+		code = retis_get_param(ctx, 2, u8);
+		icmpv6 = ctx->ksym == cfg->icmp6_send_ksym;
+
+		/* Other errors are reported along with their type and code. */
+		if (icmpv6 ?
+		    type == ICMPV6_DEST_UNREACH && code == ICMPV6_PORT_UNREACH :
+		    type == ICMP_DEST_UNREACH && code == ICMP_PORT_UNREACH)
+			kind = ICMP_NO_SOCKET_UNREACH;
+		else
+			kind = ICMP_LOCAL_ERROR;
+	} else if (ctx->ksym == cfg->nf_reset_ksym ||
+		   ctx->ksym == cfg->nf_reset6_ksym) {
+		/* nf_send_reset{,6}(net, sk, oldskb, hook), e.g. REJECT
+		 * targets.
+		 */
+		kind = ICMP_LOCAL_RESET;
+	} else {
+		/* tcp_v{4,6}_send_reset(sk, skb, ...): no socket was found
+		 * when sk is NULL.
+		 */
+		kind = retis_get_param(ctx, 0, struct sock *) ?
+		       ICMP_LOCAL_RESET : ICMP_NO_SOCKET_RESET;
+	}
+
+report:
+	e = get_event_zsection(event, COLLECTOR_ICMP, 1, sizeof(*e));
+	if (!e)
+		return 0;
+
+	e->kind = kind;
+	if (kind == ICMP_LOCAL_ERROR) {
+		e->type = type;
+		e->code = code;
+		e->icmpv6 = icmpv6;
+	}
+	return 0;
+)
+
+char __license[] SEC("license") = "GPL";
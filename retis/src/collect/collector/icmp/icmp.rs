@@ -0,0 +1,144 @@
+use std::{
+    mem,
+    os::fd::{AsFd, AsRawFd},
+    sync::Arc,
+};
+
+use anyhow::{bail, Result};
+use libbpf_rs::MapCore;
+use log::debug;
+
+use super::icmp_hook;
+use crate::{
+    bindings::icmp_hook_uapi::icmp_config,
+    collect::{cli::Collect, Collector},
+    core::{
+        events::*,
+        kernel::Symbol,
+        probe::{Hook, Probe, ProbeBuilderManager, ProbeOption},
+    },
+};
+
+/// Functions deciding if an ICMP packet can be sent, for the global and the
+/// per-destination rate limits. Both return false when rate limited.
+const ICMP_RATE_LIMIT_FUNCS: &[&str] = &["icmp_global_allow", "inet_peer_xrlim_allow"];
+
+/// Functions sending an ICMP error, including the port unreachable one sent
+/// when no socket is found.
+const ICMP_SEND_FUNCS: &[&str] = &["__icmp_send", "icmp6_send"];
+
+/// Functions sending a TCP reset, including when no socket is found.
+const TCP_RESET_FUNCS: &[&str] = &["tcp_v4_send_reset", "tcp_v6_send_reset"];
+
+/// Functions sending a TCP reset from netfilter (e.g. REJECT targets).
+const NF_RESET_FUNCS: &[&str] = &["nf_send_reset", "nf_send_reset6"];
+
+#[derive(Default)]
+pub(crate) struct IcmpCollector {
+    // Used to keep a reference to our internal config map.
+    #[allow(dead_code)]
+    config_map: Option<libbpf_rs::MapHandle>,
+}
+
+impl IcmpCollector {
+    fn config_map() -> Result<libbpf_rs::MapHandle> {
+        let opts = libbpf_sys::bpf_map_create_opts {
+            sz: mem::size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
+            ..Default::default()
+        };
+
+        // Please keep in sync with its BPF counterpart in
+        // bpf/icmp_hook.bpf.c
+        libbpf_rs::MapHandle::create(
+            libbpf_rs::MapType::Array,
+            Some("icmp_config_map"),
+            mem::size_of::<u32>() as u32,
+            mem::size_of::<icmp_config>() as u32,
+            1,
+            &opts,
+        )
+        .or_else(|e| bail!("Could not create the icmp config map: {}", e))
+    }
+
+    /// Address of a function, or 0 if it can't be probed (e.g. IPv6 is not
+    /// available or the function was inlined).
+    fn ksym(func: &str) -> u64 {
+        match Symbol::from_name(func).and_then(|s| s.addr()) {
+            Ok(addr) => addr,
+            Err(e) => {
+                debug!("Not probing {func}: {e}");
+                0
+            }
+        }
+    }
+}
+
+impl Collector for IcmpCollector {
+    fn new() -> Result<Self> {
+        Ok(Self::default())
+    }
+
+    fn can_run(&mut self, _: &Collect) -> Result<()> {
+        for func in ICMP_RATE_LIMIT_FUNCS.iter().chain(&["__icmp_send"]) {
+            if let Err(e) = Symbol::from_name(func) {
+                bail!("Could not find {func}: {e}");
+            }
+        }
+        Ok(())
+    }
+
+    fn init(
+        &mut self,
+        _: &Collect,
+        probes: &mut ProbeBuilderManager,
+        _: Arc<RetisEventsFactory>,
+    ) -> Result<()> {
+        let cfg = icmp_config {
+            global_ksym: Symbol::from_name(ICMP_RATE_LIMIT_FUNCS[0])?.addr()?,
+            peer_ksym: Symbol::from_name(ICMP_RATE_LIMIT_FUNCS[1])?.addr()?,
+            icmp_send_ksym: Symbol::from_name(ICMP_SEND_FUNCS[0])?.addr()?,
+            icmp6_send_ksym: Self::ksym(ICMP_SEND_FUNCS[1]),
+            nf_reset_ksym: Self::ksym(NF_RESET_FUNCS[0]),
+            nf_reset6_ksym: Self::ksym(NF_RESET_FUNCS[1]),
+        };
+
+        let config_map = Self::config_map()?;
+        let key = 0_u32.to_ne_bytes();
+        config_map.update(
+            &key,
+            unsafe { plain::as_bytes(&cfg) },
+            libbpf_rs::MapFlags::empty(),
+        )?;
+
+        let hook = Hook::from(icmp_hook::DATA)
+            .reuse_map("icmp_config_map", config_map.as_fd().as_raw_fd())?
+            .to_owned();
+
+        // Rate limiting functions are probed on return, to get their verdict.
+        for func in ICMP_RATE_LIMIT_FUNCS.iter() {
+            let mut probe = Probe::kretprobe(Symbol::from_name(func)?)?;
+            probe.add_hook(hook.clone())?;
+            probes.register_probe(probe)?;
+        }
+
+        // Functions sending an error or a reset. Stack traces are collected to
+        // know which part of the kernel rejected the packet.
+        for func in ICMP_SEND_FUNCS
+            .iter()
+            .chain(TCP_RESET_FUNCS)
+            .chain(NF_RESET_FUNCS)
+        {
+            if Self::ksym(func) == 0 {
+                continue;
+            }
+
+            let mut probe = Probe::kprobe(Symbol::from_name(func)?)?;
+            probe.set_option(ProbeOption::StackTrace)?;
+            probe.add_hook(hook.clone())?;
+            probes.register_probe(probe)?;
+        }
+
+        self.config_map = Some(config_map);
+        Ok(())
+    }
+}
@@ -0,0 +1,16 @@
+//! # ICMP module
+//!
+//! Provides support for reporting ICMP packets the kernel refused to send
+//! because of rate limiting, and packets for which no socket was found.
+
+// Re-export icmp.rs
+#[allow(clippy::module_inception)]
+pub(crate) mod icmp;
+pub(crate) use icmp::*;
+
+pub(crate) mod bpf;
+pub(crate) use bpf::IcmpEventFactory;
+
+mod icmp_hook {
+    include!("bpf/.out/icmp_hook.rs");
+}
@@ -0,0 +1,140 @@
+//! Rust<>BPF types definitions for the ipvs module.
+//! Please keep this file in sync with its BPF counterpart in bpf/ipvs_hook.bpf.c
+
+use anyhow::Result;
+
+use crate::{
+    bindings::ipvs_hook_uapi::*,
+    core::events::{
+        parse_single_raw_section, BpfRawSection, EventSectionFactory, FactoryId,
+        RawEventSectionFactory,
+    },
+    event_section_factory,
+    events::*,
+    helpers::net::parse_inet_addr,
+    raw_to_string_opt,
+};
+
+/// Service flags, see include/uapi/linux/ip_vs.h.
+const SVC_FLAGS: &[(u32, &str)] = &[
+    (0x0001, "persistent"),
+    (0x0004, "one_packet"),
+    (0x0008, "sched1"),
+    (0x0010, "sched2"),
+    (0x0020, "sched3"),
+];
+
+/// Connection flags, see include/uapi/linux/ip_vs.h.
+const CONN_F_FWD_MASK: u32 = 0x0007;
+const CONN_FLAGS: &[(u32, &str)] = &[
+    (0x0800, "no_cport"),
+    (0x1000, "template"),
+    (0x2000, "one_packet"),
+];
+
+#[event_section_factory(FactoryId::Ipvs)]
+#[derive(Default)]
+pub(crate) struct IpvsEventFactory {}
+
+impl RawEventSectionFactory for IpvsEventFactory {
+    fn create(&mut self, raw_sections: Vec<BpfRawSection>) -> Result<Box<dyn EventSection>> {
+        let raw = parse_single_raw_section::<ipvs_event>(&raw_sections)?;
+
+        let service = IpvsService {
+            protocol: protocol(raw.protocol),
+            addr: match raw.fwmark {
+                0 => Some(IpvsEndpoint {
+                    addr: parse_inet_addr(raw.af, &raw.addr)?,
+                    port: u16::from_be(raw.port),
+                }),
+                _ => None,
+            },
+            fwmark: match raw.fwmark {
+                0 => None,
+                x => Some(x),
+            },
+            scheduler: raw_to_string_opt!(&raw.sched)?,
+            flags: flags(raw.svc_flags, SVC_FLAGS),
+        };
+
+        let (conn, unscheduled) = match raw.conn {
+            0 => (
+                None,
+                Some(
+                    match raw.ignored {
+                        0 => "no_dest",
+                        x if x > 0 => "ignored",
+                        _ => "error",
+                    }
+                    .to_string(),
+                ),
+            ),
+            _ => (
+                Some(IpvsConn {
+                    client: IpvsEndpoint {
+                        addr: parse_inet_addr(raw.af, &raw.caddr)?,
+                        port: u16::from_be(raw.cport),
+                    },
+                    virt: IpvsEndpoint {
+                        addr: parse_inet_addr(raw.af, &raw.vaddr)?,
+                        port: u16::from_be(raw.vport),
+                    },
+                    real: IpvsEndpoint {
+                        addr: parse_inet_addr(raw.daf, &raw.daddr)?,
+                        port: u16::from_be(raw.dport),
+                    },
+                    fwd: fwd_method(raw.conn_flags),
+                    flags: flags(raw.conn_flags, CONN_FLAGS),
+                }),
+                None,
+            ),
+        };
+
+        Ok(Box::new(IpvsEvent {
+            service,
+            conn,
+            unscheduled,
+        }))
+    }
+}
+
+fn protocol(protocol: u16) -> String {
+    match protocol {
+        6 => "tcp".to_string(),
+        17 => "udp".to_string(),
+        132 => "sctp".to_string(),
+        x => format!("proto {x}"),
+    }
+}
+
+/// Convert connection flags to the name of their forwarding method.
+fn fwd_method(flags: u32) -> String {
+    match flags & CONN_F_FWD_MASK {
+        0 => "masq".to_string(),
+        1 => "local".to_string(),
+        2 => "tunnel".to_string(),
+        3 => "droute".to_string(),
+        4 => "bypass".to_string(),
+        x => format!("unknown ({x})"),
+    }
+}
+
+fn flags(flags: u32, names: &[(u32, &str)]) -> Vec<String> {
+    names
+        .iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        assert_eq!(fwd_method(0x1003), "droute");
+        assert_eq!(flags(0x1003, CONN_FLAGS), vec!["template"]);
+        assert_eq!(flags(0x0009, SVC_FLAGS), vec!["persistent", "sched1"]);
+    }
+}
@@ -0,0 +1,114 @@
+#include <vmlinux.h>
+#include <bpf/bpf_core_read.h>
+
+#include <common.h>
+
+BINDING_DEF(IPVS_SCHED_NAME_LEN, 16)
+
+/* Types of the ip_vs module, which are not part of vmlinux.h. Keep in sync
+ * with include/net/ip_vs.h; relocations are done using the module BTF.
+ */
+struct ip_vs_scheduler___retis {
+	char *name;
+} __attribute__((preserve_access_index));
+
+struct ip_vs_service___retis {
+	u16 af;
+	u16 protocol;
+	union nf_inet_addr addr;
+	__be16 port;
+	u32 fwmark;
+	unsigned int flags;
+	struct ip_vs_scheduler___retis *scheduler;
+} __attribute__((preserve_access_index));
+
+struct ip_vs_conn___retis {
+	__be16 cport;
+	__be16 dport;
+	__be16 vport;
+	u16 af;
+	union nf_inet_addr caddr;
+	union nf_inet_addr vaddr;
+	union nf_inet_addr daddr;
+	volatile u32 flags;
+	u16 daf;
+} __attribute__((preserve_access_index));
+
+struct ipvs_event {
+	/* Virtual service. */
+	u16 af;
+	u16 protocol;
+	u16 port;
+	u32 fwmark;
+	u32 svc_flags;
+	u8 addr[16];
+	char sched[IPVS_SCHED_NAME_LEN];
+	/* Connection, if one was created. */
+	u8 conn;
+	s32 ignored;
+	u16 daf;
+	u16 cport;
+	u16 vport;
+	u16 dport;
+	u32 conn_flags;
+	u8 caddr[16];
+	u8 vaddr[16];
+	u8 daddr[16];
+} __binding;
+
+/* Probes are added on return of ip_vs_schedule(svc, skb, pd, ignored, iph),
+ * which selects a real server for a new connection using the scheduler of the
+ * service and returns the connection, or NULL.
+ */
+DEFINE_HOOK_RAW(
+	struct ip_vs_scheduler___retis *sched;
+	struct ip_vs_service___retis *svc;
+	struct ip_vs_conn___retis *cp;
+	struct ipvs_event *e;
+	int *ignored;
+
+	if (!RETIS_TRACKABLE(ctx->filters_ret))
+		return -ENOMSG;
+
+	svc = retis_get_param(ctx, 0, struct ip_vs_service___retis *);
+	if (!svc)
+		return -ENOMSG;
+
+	e = get_event_zsection(event, COLLECTOR_IPVS, 1, sizeof(*e));
+	if (!e)
+		return 0;
+
+	e->af = BPF_CORE_READ(svc, af);
+	e->protocol = BPF_CORE_READ(svc, protocol);
+	e->port = BPF_CORE_READ(svc, port);
+	e->fwmark = BPF_CORE_READ(svc, fwmark);
+	e->svc_flags = BPF_CORE_READ(svc, flags);
+	bpf_core_read(e->addr, sizeof(e->addr), &svc->addr);
+
+	sched = BPF_CORE_READ(svc, scheduler);
+	if (sched)
+		bpf_probe_read_kernel_str(e->sched, sizeof(e->sched),
+					  BPF_CORE_READ(sched, name));
+
+	ignored = retis_get_param(ctx, 3, int *);
+	if (ignored)
+		bpf_probe_read_kernel(&e->ignored, sizeof(e->ignored), ignored);
+
+	cp = (struct ip_vs_conn___retis *)ctx->regs.ret;
+	if (!cp)
+		return 0;
+
+	e->conn = 1;
+	e->daf = BPF_CORE_READ(cp, daf);
+	e->cport = BPF_CORE_READ(cp, cport);
+	e->vport = BPF_CORE_READ(cp, vport);
+	e->dport = BPF_CORE_READ(cp, dport);
+	e->conn_flags = BPF_CORE_READ(cp, flags);
+	bpf_core_read(e->caddr, sizeof(e->caddr), &cp->caddr);
+	bpf_core_read(e->vaddr, sizeof(e->vaddr), &cp->vaddr);
+	bpf_core_read(e->daddr, sizeof(e->daddr), &cp->daddr);
+
+	return 0;
+)
+
+char __license[] SEC("license") = "GPL";
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+
+use super::ipvs_hook;
+use crate::{
+    collect::{cli::Collect, Collector},
+    core::{
+        events::*,
+        kernel::Symbol,
+        probe::{Hook, Probe, ProbeBuilderManager},
+    },
+};
+
+/// Function selecting a real server for new connections.
+const IP_VS_SCHEDULE: &str = "ip_vs_schedule";
+
+#[derive(Default)]
+pub(crate) struct IpvsCollector {}
+
+impl Collector for IpvsCollector {
+    fn new() -> Result<Self> {
+        Ok(Self::default())
+    }
+
+    fn can_run(&mut self, _: &Collect) -> Result<()> {
+        // IPVS is usually built as a module, only loaded once a virtual
+        // service was added.
+        if let Err(e) = Symbol::from_name(IP_VS_SCHEDULE) {
+            bail!("Could not find {IP_VS_SCHEDULE} (is the ip_vs module loaded?): {e}");
+        }
+        Ok(())
+    }
+
+    fn init(
+        &mut self,
+        _: &Collect,
+        probes: &mut ProbeBuilderManager,
+        _: Arc<RetisEventsFactory>,
+    ) -> Result<()> {
+        // Report scheduling decisions once they are taken, to get the
+        // connection created (if any).
+        let mut probe = Probe::kretprobe(Symbol::from_name(IP_VS_SCHEDULE)?)?;
+        probe.add_hook(Hook::from(ipvs_hook::DATA))?;
+        probes.register_probe(probe)?;
+
+        Ok(())
+    }
+}
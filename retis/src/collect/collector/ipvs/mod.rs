@@ -0,0 +1,16 @@
+//! # Ipvs module
+//!
+//! Provides support for reporting the load balancing decisions taken by IPVS
+//! when scheduling new connections (virtual service, chosen real server).
+
+// Re-export ipvs.rs
+#[allow(clippy::module_inception)]
+pub(crate) mod ipvs;
+pub(crate) use ipvs::*;
+
+pub(crate) mod bpf;
+pub(crate) use bpf::IpvsEventFactory;
+
+mod ipvs_hook {
+    include!("bpf/.out/ipvs_hook.rs");
+}
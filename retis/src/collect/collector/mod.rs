@@ -9,8 +9,16 @@ pub(crate) mod collector;
 pub(crate) use collector::*;
 
 pub(crate) mod ct;
+pub(crate) mod icmp;
+pub(crate) mod ipvs;
+pub(crate) mod mptcp;
+pub(crate) mod netlink;
 pub(crate) mod nft;
 pub(crate) mod ovs;
+pub(crate) mod seg6;
 pub(crate) mod skb;
 pub(crate) mod skb_drop;
 pub(crate) mod skb_tracking;
+pub(crate) mod sock;
+pub(crate) mod tc;
+pub(crate) mod tls;
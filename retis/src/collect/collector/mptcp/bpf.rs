@@ -0,0 +1,80 @@
+//! Rust<>BPF types definitions for the mptcp module.
+//! Please keep this file in sync with its BPF counterpart in bpf/mptcp_hook.bpf.c
+
+use anyhow::{bail, Result};
+
+use crate::{
+    bindings::mptcp_hook_uapi::*,
+    core::events::{
+        parse_single_raw_section, BpfRawSection, EventSectionFactory, FactoryId,
+        RawEventSectionFactory,
+    },
+    event_section_factory,
+    events::*,
+    helpers::net::parse_inet_addr,
+};
+
+#[event_section_factory(FactoryId::Mptcp)]
+#[derive(Default)]
+pub(crate) struct MptcpEventFactory {}
+
+impl RawEventSectionFactory for MptcpEventFactory {
+    fn create(&mut self, raw_sections: Vec<BpfRawSection>) -> Result<Box<dyn EventSection>> {
+        let raw = parse_single_raw_section::<mptcp_event>(&raw_sections)?;
+
+        let kind = match raw.kind as u32 {
+            MPTCP_CONNECT => "connect",
+            MPTCP_JOIN => "join",
+            MPTCP_JOIN_REFUSED => "join_refused",
+            MPTCP_CLOSE => "close",
+            MPTCP_SCHED => "sched",
+            MPTCP_ADD_ADDR => "add_addr",
+            MPTCP_RM_ADDR => "rm_addr",
+            MPTCP_PRIO => "prio",
+            x => bail!("Unknown mptcp event kind ({x})"),
+        };
+
+        let subflow = match raw.has_subflow {
+            0 => None,
+            _ => Some(MptcpSubflow {
+                id: match raw.subflow_id {
+                    0 => None,
+                    x => Some(x),
+                },
+                local_id: raw.local_id,
+                remote_id: raw.remote_id,
+                backup: raw.backup != 0,
+                saddr: parse_inet_addr(raw.family, &raw.saddr)?,
+                sport: raw.sport,
+                daddr: parse_inet_addr(raw.family, &raw.daddr)?,
+                dport: raw.dport,
+            }),
+        };
+
+        let addr = match raw.has_addr {
+            0 => None,
+            _ => Some(MptcpAddr {
+                id: raw.addr_id,
+                addr: parse_inet_addr(raw.addr_family, &raw.addr)?,
+                port: match raw.addr_port {
+                    0 => None,
+                    x => Some(x),
+                },
+            }),
+        };
+
+        let rm_nr = (raw.rm_nr as usize).min(raw.rm_ids.len());
+
+        Ok(Box::new(MptcpEvent {
+            kind: kind.to_string(),
+            token: raw.token,
+            subflow,
+            addr,
+            rm_ids: raw.rm_ids[..rm_nr].to_vec(),
+            error: match raw.ret {
+                x if x < 0 && raw.kind as u32 == MPTCP_CONNECT => Some(x),
+                _ => None,
+            },
+        }))
+    }
+}
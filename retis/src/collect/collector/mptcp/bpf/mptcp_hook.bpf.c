@@ -0,0 +1,236 @@
+#include <vmlinux.h>
+#include <bpf/bpf_core_read.h>
+#include <bpf/bpf_endian.h>
+
+#include <common.h>
+
+#define AF_INET		2
+#define AF_INET6	10
+
+BINDING_DEF(MPTCP_RM_IDS_MAX, 8)
+
+/* Type of the MPTCP path manager, which is not part of vmlinux.h. Keep in sync
+ * with net/mptcp/protocol.h.
+ */
+struct mptcp_rm_list___retis {
+	u8 ids[MPTCP_RM_IDS_MAX];
+	u8 nr;
+} __attribute__((preserve_access_index));
+
+/* MPTCP hook configuration. Holds the address of the probed functions, to know
+ * where the hook is running. Probes which were not added have a 0 address.
+ *
+ * connect_ksym:  __mptcp_subflow_connect, subflow creation by the PM.
+ * join_ksym:	  mptcp_finish_join, subflow joining a connection.
+ * close_ksym:	  mptcp_close_ssk, subflow being closed.
+ * sched_ksym:	  mptcp:mptcp_subflow_get_send, subflow picked to send data.
+ * add_addr_ksym: mptcp_pm_add_addr_received, ADD_ADDR received.
+ * rm_addr_ksym:  mptcp_pm_rm_addr_received, RM_ADDR received.
+ * prio_ksym:	  mptcp_pm_mp_prio_received, MP_PRIO received.
+ */
+struct mptcp_config {
+	u64 connect_ksym;
+	u64 join_ksym;
+	u64 close_ksym;
+	u64 sched_ksym;
+	u64 add_addr_ksym;
+	u64 rm_addr_ksym;
+	u64 prio_ksym;
+} __binding;
+struct {
+	__uint(type, BPF_MAP_TYPE_ARRAY);
+	__uint(max_entries, 1);
+	__type(key, u32);
+	__type(value, struct mptcp_config);
+} mptcp_config_map SEC(".maps");
+
+enum mptcp_event_kind {
+	MPTCP_CONNECT = 1,
+	MPTCP_JOIN,
+	MPTCP_JOIN_REFUSED,
+	MPTCP_CLOSE,
+	MPTCP_SCHED,
+	MPTCP_ADD_ADDR,
+	MPTCP_RM_ADDR,
+	MPTCP_PRIO,
+} __binding;
+
+struct mptcp_event {
+	u8 kind;
+	/* Token of the MPTCP connection. */
+	u32 token;
+	/* Subflow, if has_subflow is set. */
+	u8 has_subflow;
+	u32 subflow_id;
+	s16 local_id;
+	u8 remote_id;
+	u8 backup;
+	u16 family;
+	u16 sport;
+	u16 dport;
+	u8 saddr[16];
+	u8 daddr[16];
+	/* Address connected to or announced, if has_addr is set. */
+	u8 has_addr;
+	u8 addr_id;
+	u16 addr_family;
+	u16 addr_port;
+	u8 addr[16];
+	/* Ids of the removed addresses. */
+	u8 rm_nr;
+	u8 rm_ids[MPTCP_RM_IDS_MAX];
+	/* Value returned by __mptcp_subflow_connect. */
+	s32 ret;
+} __binding;
+
+static __always_inline void mptcp_fill_sock(struct mptcp_event *e,
+					    struct sock *sk)
+{
+	e->family = BPF_CORE_READ(sk, __sk_common.skc_family);
+	e->sport = BPF_CORE_READ(sk, __sk_common.skc_num);
+	e->dport = bpf_ntohs(BPF_CORE_READ(sk, __sk_common.skc_dport));
+
+	if (e->family == AF_INET) {
+		bpf_core_read(e->saddr, sizeof(u32),
+			      &sk->__sk_common.skc_rcv_saddr);
+		bpf_core_read(e->daddr, sizeof(u32),
+			      &sk->__sk_common.skc_daddr);
+	} else if (e->family == AF_INET6) {
+		bpf_core_read(e->saddr, sizeof(e->saddr),
+			      &sk->__sk_common.skc_v6_rcv_saddr);
+		bpf_core_read(e->daddr, sizeof(e->daddr),
+			      &sk->__sk_common.skc_v6_daddr);
+	}
+}
+
+static __always_inline void mptcp_fill_token(struct mptcp_event *e,
+					     struct sock *sk)
+{
+	e->token = BPF_CORE_READ((struct mptcp_sock *)sk, token);
+}
+
+static __always_inline void
+mptcp_fill_subflow(struct mptcp_event *e,
+		   struct mptcp_subflow_context *subflow)
+{
+	struct sock *ssk;
+
+	if (!subflow)
+		return;
+
+	mptcp_fill_token(e, BPF_CORE_READ(subflow, conn));
+
+	e->has_subflow = 1;
+	/* Subflow ids were added in Linux v6.7. */
+	if (bpf_core_field_exists(subflow->subflow_id))
+		e->subflow_id = BPF_CORE_READ(subflow, subflow_id);
+	/* The local id size changed over time. */
+	e->local_id = BPF_CORE_READ_BITFIELD_PROBED(subflow, local_id);
+	e->remote_id = BPF_CORE_READ_BITFIELD_PROBED(subflow, remote_id);
+	e->backup = BPF_CORE_READ_BITFIELD_PROBED(subflow, backup);
+
+	ssk = BPF_CORE_READ(subflow, tcp_sock);
+	if (ssk)
+		mptcp_fill_sock(e, ssk);
+}
+
+static __always_inline struct mptcp_subflow_context *
+mptcp_subflow_ctx(struct sock *ssk)
+{
+	if (!ssk)
+		return NULL;
+	return BPF_CORE_READ((struct inet_connection_sock *)ssk, icsk_ulp_data);
+}
+
+static __always_inline void mptcp_fill_addr(struct mptcp_event *e,
+					    const struct mptcp_addr_info *addr)
+{
+	if (!addr)
+		return;
+
+	e->has_addr = 1;
+	e->addr_id = BPF_CORE_READ(addr, id);
+	e->addr_family = BPF_CORE_READ(addr, family);
+	e->addr_port = bpf_ntohs(BPF_CORE_READ(addr, port));
+	bpf_core_read(e->addr, sizeof(e->addr), &addr->addr6);
+}
+
+DEFINE_HOOK_RAW(
+	const struct mptcp_rm_list___retis *rm_list;
+	struct mptcp_config *cfg;
+	struct mptcp_event *e;
+	u32 key = 0;
+
+	/* Most MPTCP events have no skb, filters can't apply to those.
+	 * Otherwise only report events matching the filters.
+	 */
+	if (retis_get_sk_buff(ctx) && !RETIS_TRACKABLE(ctx->filters_ret))
+		return -ENOMSG;
+
+	cfg = bpf_map_lookup_elem(&mptcp_config_map, &key);
+	if (!cfg)
+		return 0;
+
+	e = get_event_zsection(event, COLLECTOR_MPTCP, 1, sizeof(*e));
+	if (!e)
+		return 0;
+
+	if (ctx->ksym == cfg->connect_ksym) {
+		/* __mptcp_subflow_connect(sk, local, remote), on return. */
+		e->kind = MPTCP_CONNECT;
+		mptcp_fill_token(e, retis_get_param(ctx, 0, struct sock *));
+		mptcp_fill_addr(e, retis_get_param(ctx, 2,
+						   const struct mptcp_addr_info *));
+		e->ret = (s32)ctx->regs.ret;
+	} else if (ctx->ksym == cfg->join_ksym) {
+		/* mptcp_finish_join(ssk), on return. Only the lower byte holds
+		 * the returned bool.
+		 */
+		e->kind = (u8)ctx->regs.ret ? MPTCP_JOIN : MPTCP_JOIN_REFUSED;
+		mptcp_fill_subflow(e, mptcp_subflow_ctx(
+			retis_get_param(ctx, 0, struct sock *)));
+	} else if (ctx->ksym == cfg->close_ksym) {
+		/* mptcp_close_ssk(sk, ssk, subflow). */
+		e->kind = MPTCP_CLOSE;
+		mptcp_fill_subflow(e, retis_get_param(ctx, 2,
+			struct mptcp_subflow_context *));
+	} else if (ctx->ksym == cfg->sched_ksym) {
+		/* mptcp:mptcp_subflow_get_send(subflow). */
+		e->kind = MPTCP_SCHED;
+		mptcp_fill_subflow(e, retis_get_param(ctx, 0,
+			struct mptcp_subflow_context *));
+	} else if (ctx->ksym == cfg->add_addr_ksym) {
+		/* mptcp_pm_add_addr_received(ssk, addr). */
+		e->kind = MPTCP_ADD_ADDR;
+		mptcp_fill_subflow(e, mptcp_subflow_ctx(
+			retis_get_param(ctx, 0, struct sock *)));
+		mptcp_fill_addr(e, retis_get_param(ctx, 1,
+						   const struct mptcp_addr_info *));
+	} else if (ctx->ksym == cfg->rm_addr_ksym) {
+		/* mptcp_pm_rm_addr_received(msk, rm_list). */
+		e->kind = MPTCP_RM_ADDR;
+		mptcp_fill_token(e, retis_get_param(ctx, 0, struct sock *));
+
+		rm_list = retis_get_param(ctx, 1,
+					  const struct mptcp_rm_list___retis *);
+		if (rm_list) {
+			e->rm_nr = BPF_CORE_READ(rm_list, nr);
+			bpf_core_read(e->rm_ids, sizeof(e->rm_ids),
+				      &rm_list->ids);
+		}
+	} else if (ctx->ksym == cfg->prio_ksym) {
+		/* mptcp_pm_mp_prio_received(ssk, bkup). Report the new
+		 * priority.
+		 */
+		e->kind = MPTCP_PRIO;
+		mptcp_fill_subflow(e, mptcp_subflow_ctx(
+			retis_get_param(ctx, 0, struct sock *)));
+		e->backup = retis_get_param(ctx, 1, u8);
+	} else {
+		return -ENOMSG;
+	}
+
+	return 0;
+)
+
+char __license[] SEC("license") = "GPL";
@@ -0,0 +1,16 @@
+//! # MPTCP module
+//!
+//! Provides support for reporting MPTCP subflows lifecycle (creation, join,
+//! close), scheduling decisions and path manager signaling.
+
+// Re-export mptcp.rs
+#[allow(clippy::module_inception)]
+pub(crate) mod mptcp;
+pub(crate) use mptcp::*;
+
+pub(crate) mod bpf;
+pub(crate) use bpf::MptcpEventFactory;
+
+mod mptcp_hook {
+    include!("bpf/.out/mptcp_hook.rs");
+}
@@ -0,0 +1,132 @@
+use std::{
+    mem,
+    os::fd::{AsFd, AsRawFd},
+    sync::Arc,
+};
+
+use anyhow::{bail, Result};
+use clap::{arg, Parser};
+use libbpf_rs::MapCore;
+use log::debug;
+
+use super::mptcp_hook;
+use crate::{
+    bindings::mptcp_hook_uapi::mptcp_config,
+    collect::{cli::Collect, Collector},
+    core::{
+        events::*,
+        kernel::Symbol,
+        probe::{Hook, Probe, ProbeBuilderManager},
+    },
+};
+
+/// Tracepoint fired each time the MPTCP scheduler picks a subflow to send data.
+const MPTCP_SCHED_TP: &str = "mptcp:mptcp_subflow_get_send";
+
+#[derive(Parser, Debug, Default)]
+pub(crate) struct MptcpCollectorArgs {
+    #[arg(
+        long,
+        help = "Report the subflow picked by the MPTCP scheduler each time data is sent. This can generate a large number of events."
+    )]
+    mptcp_sched: bool,
+}
+
+#[derive(Default)]
+pub(crate) struct MptcpCollector {
+    // Used to keep a reference to our internal config map.
+    #[allow(dead_code)]
+    config_map: Option<libbpf_rs::MapHandle>,
+}
+
+impl MptcpCollector {
+    fn config_map() -> Result<libbpf_rs::MapHandle> {
+        let opts = libbpf_sys::bpf_map_create_opts {
+            sz: mem::size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
+            ..Default::default()
+        };
+
+        // Please keep in sync with its BPF counterpart in
+        // bpf/mptcp_hook.bpf.c
+        libbpf_rs::MapHandle::create(
+            libbpf_rs::MapType::Array,
+            Some("mptcp_config_map"),
+            mem::size_of::<u32>() as u32,
+            mem::size_of::<mptcp_config>() as u32,
+            1,
+            &opts,
+        )
+        .or_else(|e| bail!("Could not create the mptcp config map: {}", e))
+    }
+}
+
+impl Collector for MptcpCollector {
+    fn new() -> Result<Self> {
+        Ok(Self::default())
+    }
+
+    fn can_run(&mut self, _: &Collect) -> Result<()> {
+        if let Err(e) = Symbol::from_name("mptcp_finish_join") {
+            bail!("Could not find mptcp_finish_join (is CONFIG_MPTCP enabled?): {e}");
+        }
+        Ok(())
+    }
+
+    fn init(
+        &mut self,
+        args: &Collect,
+        probes: &mut ProbeBuilderManager,
+        _: Arc<RetisEventsFactory>,
+    ) -> Result<()> {
+        let config_map = Self::config_map()?;
+        let hook = Hook::from(mptcp_hook::DATA)
+            .reuse_map("mptcp_config_map", config_map.as_fd().as_raw_fd())?
+            .to_owned();
+
+        let mut cfg = mptcp_config::default();
+
+        // Subflow creation and join are probed on return, to report their
+        // outcome. The other functions are probed on entry.
+        let mut targets = vec![
+            ("__mptcp_subflow_connect", true, &mut cfg.connect_ksym),
+            ("mptcp_finish_join", true, &mut cfg.join_ksym),
+            ("mptcp_close_ssk", false, &mut cfg.close_ksym),
+            ("mptcp_pm_add_addr_received", false, &mut cfg.add_addr_ksym),
+            ("mptcp_pm_rm_addr_received", false, &mut cfg.rm_addr_ksym),
+            ("mptcp_pm_mp_prio_received", false, &mut cfg.prio_ksym),
+        ];
+        if args.collector_args.mptcp.mptcp_sched {
+            targets.push((MPTCP_SCHED_TP, false, &mut cfg.sched_ksym));
+        }
+
+        for (func, ret, ksym) in targets.into_iter() {
+            let symbol = match Symbol::from_name(func) {
+                Ok(symbol) => symbol,
+                Err(e) => {
+                    debug!("Not probing {func}: {e}");
+                    continue;
+                }
+            };
+
+            *ksym = symbol.addr()?;
+
+            let mut probe = match (func == MPTCP_SCHED_TP, ret) {
+                (true, _) => Probe::raw_tracepoint(symbol)?,
+                (false, true) => Probe::kretprobe(symbol)?,
+                (false, false) => Probe::kprobe(symbol)?,
+            };
+            probe.add_hook(hook.clone())?;
+            probes.register_probe(probe)?;
+        }
+
+        let key = 0_u32.to_ne_bytes();
+        config_map.update(
+            &key,
+            unsafe { plain::as_bytes(&cfg) },
+            libbpf_rs::MapFlags::empty(),
+        )?;
+
+        self.config_map = Some(config_map);
+        Ok(())
+    }
+}
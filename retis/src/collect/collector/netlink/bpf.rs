@@ -0,0 +1,282 @@
+//! Rust<>BPF types definitions for the netlink module.
+//! Please keep this file in sync with its BPF counterpart in
+//! bpf/netlink_hook.bpf.c
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use log::warn;
+
+use crate::{
+    bindings::netlink_hook_uapi::netlink_event,
+    core::events::{
+        parse_single_raw_section, BpfRawSection, EventSectionFactory, FactoryId,
+        RawEventSectionFactory,
+    },
+    event_section_factory,
+    events::*,
+    helpers::netlink::genl_families,
+};
+
+// Netlink protocols, see include/uapi/linux/netlink.h.
+pub(super) const NETLINK_ROUTE: u8 = 0;
+pub(super) const NETLINK_NETFILTER: u8 = 12;
+pub(super) const NETLINK_GENERIC: u8 = 16;
+
+/// Message types below this one are reserved for control messages.
+const NLMSG_MIN_TYPE: u16 = 0x10;
+
+/// OpenVSwitch generic netlink families reporting control plane actions. The
+/// ovs_packet family is not part of it as it is used for upcalls, which are
+/// datapath events (see the ovs collector).
+pub(super) const OVS_GENL_FAMILIES: &[&str] = &[
+    "ovs_datapath",
+    "ovs_vport",
+    "ovs_flow",
+    "ovs_meter",
+    "ovs_ct_limit",
+];
+
+#[event_section_factory(FactoryId::Netlink)]
+#[derive(Default)]
+pub(crate) struct NetlinkEventFactory {
+    /// Map of generic netlink family ids to their names.
+    genl_families: HashMap<u16, String>,
+}
+
+impl RawEventSectionFactory for NetlinkEventFactory {
+    fn create(&mut self, raw_sections: Vec<BpfRawSection>) -> Result<Box<dyn EventSection>> {
+        let raw = parse_single_raw_section::<netlink_event>(&raw_sections)?;
+        let r#type = raw.type_;
+
+        let (family, cmd, msg) = match raw.protocol {
+            _ if r#type < NLMSG_MIN_TYPE => (None, None, nlmsg_str(r#type)),
+            NETLINK_ROUTE => (None, None, rtm_str(r#type)),
+            NETLINK_NETFILTER => {
+                let (subsys, msg) = nfnl_str(r#type);
+                (subsys.map(String::from), None, msg)
+            }
+            NETLINK_GENERIC => {
+                let family = self.genl_families.get(&r#type).cloned();
+                let msg = family
+                    .as_deref()
+                    .and_then(|family| ovs_cmd_str(family, raw.genl_cmd));
+                (family, Some(raw.genl_cmd), msg)
+            }
+            _ => (None, None, None),
+        };
+
+        Ok(Box::new(NetlinkEvent {
+            protocol: match raw.protocol {
+                NETLINK_ROUTE => "route".to_string(),
+                NETLINK_NETFILTER => "netfilter".to_string(),
+                NETLINK_GENERIC => "generic".to_string(),
+                x => x.to_string(),
+            },
+            family,
+            r#type,
+            cmd,
+            msg: msg.map(String::from),
+            flags: raw.flags,
+            seq: raw.seq,
+            len: raw.len,
+            portid: raw.portid,
+            dst_portid: match raw.group {
+                0 => Some(raw.dst_portid),
+                _ => None,
+            },
+            group: match raw.group {
+                0 => None,
+                group => Some(group),
+            },
+        }))
+    }
+}
+
+impl NetlinkEventFactory {
+    /// Initialize a new netlink factory.
+    pub(crate) fn new() -> Result<Self> {
+        Ok(Self {
+            genl_families: genl_families().unwrap_or_else(|e| {
+                warn!("Could not retrieve the generic netlink families: {e}");
+                HashMap::new()
+            }),
+        })
+    }
+}
+
+/// Netlink control messages, see include/uapi/linux/netlink.h.
+fn nlmsg_str(r#type: u16) -> Option<&'static str> {
+    Some(match r#type {
+        1 => "NLMSG_NOOP",
+        2 => "NLMSG_ERROR",
+        3 => "NLMSG_DONE",
+        4 => "NLMSG_OVERRUN",
+        _ => return None,
+    })
+}
+
+/// Rtnetlink messages, see include/uapi/linux/rtnetlink.h.
+fn rtm_str(r#type: u16) -> Option<&'static str> {
+    Some(match r#type {
+        16 => "RTM_NEWLINK",
+        17 => "RTM_DELLINK",
+        18 => "RTM_GETLINK",
+        19 => "RTM_SETLINK",
+        20 => "RTM_NEWADDR",
+        21 => "RTM_DELADDR",
+        22 => "RTM_GETADDR",
+        24 => "RTM_NEWROUTE",
+        25 => "RTM_DELROUTE",
+        26 => "RTM_GETROUTE",
+        28 => "RTM_NEWNEIGH",
+        29 => "RTM_DELNEIGH",
+        30 => "RTM_GETNEIGH",
+        32 => "RTM_NEWRULE",
+        33 => "RTM_DELRULE",
+        34 => "RTM_GETRULE",
+        36 => "RTM_NEWQDISC",
+        37 => "RTM_DELQDISC",
+        38 => "RTM_GETQDISC",
+        40 => "RTM_NEWTCLASS",
+        41 => "RTM_DELTCLASS",
+        42 => "RTM_GETTCLASS",
+        44 => "RTM_NEWTFILTER",
+        45 => "RTM_DELTFILTER",
+        46 => "RTM_GETTFILTER",
+        48 => "RTM_NEWACTION",
+        49 => "RTM_DELACTION",
+        50 => "RTM_GETACTION",
+        64 => "RTM_NEWNEIGHTBL",
+        66 => "RTM_GETNEIGHTBL",
+        67 => "RTM_SETNEIGHTBL",
+        80 => "RTM_NEWNETCONF",
+        81 => "RTM_DELNETCONF",
+        82 => "RTM_GETNETCONF",
+        84 => "RTM_NEWMDB",
+        85 => "RTM_DELMDB",
+        86 => "RTM_GETMDB",
+        88 => "RTM_NEWNSID",
+        89 => "RTM_DELNSID",
+        90 => "RTM_GETNSID",
+        104 => "RTM_NEWNEXTHOP",
+        105 => "RTM_DELNEXTHOP",
+        106 => "RTM_GETNEXTHOP",
+        _ => return None,
+    })
+}
+
+/// Nfnetlink subsystems and messages, see include/uapi/linux/netfilter/
+/// nfnetlink.h, nfnetlink_conntrack.h and nf_tables.h.
+fn nfnl_str(r#type: u16) -> (Option<&'static str>, Option<&'static str>) {
+    match r#type {
+        0x10 => return (None, Some("NFNL_MSG_BATCH_BEGIN")),
+        0x11 => return (None, Some("NFNL_MSG_BATCH_END")),
+        _ => (),
+    }
+
+    let (subsys, msg) = (r#type >> 8, r#type & 0xff);
+    let subsys_str = match subsys {
+        1 => "ctnetlink",
+        2 => "ctnetlink_exp",
+        3 => "queue",
+        4 => "ulog",
+        5 => "osf",
+        6 => "ipset",
+        7 => "acct",
+        8 => "cttimeout",
+        9 => "cthelper",
+        10 => "nftables",
+        11 => "nft_compat",
+        12 => "hook",
+        _ => return (None, None),
+    };
+
+    let msg_str = match (subsys, msg) {
+        (1, 0) => "IPCTNL_MSG_CT_NEW",
+        (1, 1) => "IPCTNL_MSG_CT_GET",
+        (1, 2) => "IPCTNL_MSG_CT_DELETE",
+        (1, 3) => "IPCTNL_MSG_CT_GET_CTRZERO",
+        (1, 4) => "IPCTNL_MSG_CT_GET_STATS_CPU",
+        (1, 5) => "IPCTNL_MSG_CT_GET_STATS",
+        (1, 6) => "IPCTNL_MSG_CT_GET_DYING",
+        (1, 7) => "IPCTNL_MSG_CT_GET_UNCONFIRMED",
+        (10, 0) => "NFT_MSG_NEWTABLE",
+        (10, 1) => "NFT_MSG_GETTABLE",
+        (10, 2) => "NFT_MSG_DELTABLE",
+        (10, 3) => "NFT_MSG_NEWCHAIN",
+        (10, 4) => "NFT_MSG_GETCHAIN",
+        (10, 5) => "NFT_MSG_DELCHAIN",
+        (10, 6) => "NFT_MSG_NEWRULE",
+        (10, 7) => "NFT_MSG_GETRULE",
+        (10, 8) => "NFT_MSG_DELRULE",
+        (10, 9) => "NFT_MSG_NEWSET",
+        (10, 10) => "NFT_MSG_GETSET",
+        (10, 11) => "NFT_MSG_DELSET",
+        (10, 12) => "NFT_MSG_NEWSETELEM",
+        (10, 13) => "NFT_MSG_GETSETELEM",
+        (10, 14) => "NFT_MSG_DELSETELEM",
+        (10, 15) => "NFT_MSG_NEWGEN",
+        (10, 16) => "NFT_MSG_GETGEN",
+        (10, 17) => "NFT_MSG_TRACE",
+        (10, 18) => "NFT_MSG_NEWOBJ",
+        (10, 19) => "NFT_MSG_GETOBJ",
+        (10, 20) => "NFT_MSG_DELOBJ",
+        (10, 21) => "NFT_MSG_GETOBJ_RESET",
+        (10, 22) => "NFT_MSG_NEWFLOWTABLE",
+        (10, 23) => "NFT_MSG_GETFLOWTABLE",
+        (10, 24) => "NFT_MSG_DELFLOWTABLE",
+        _ => return (Some(subsys_str), None),
+    };
+
+    (Some(subsys_str), Some(msg_str))
+}
+
+/// OpenVSwitch generic netlink commands, see include/uapi/linux/openvswitch.h.
+fn ovs_cmd_str(family: &str, cmd: u8) -> Option<&'static str> {
+    Some(match (family, cmd) {
+        ("ovs_datapath", 1) => "OVS_DP_CMD_NEW",
+        ("ovs_datapath", 2) => "OVS_DP_CMD_DEL",
+        ("ovs_datapath", 3) => "OVS_DP_CMD_GET",
+        ("ovs_datapath", 4) => "OVS_DP_CMD_SET",
+        ("ovs_vport", 1) => "OVS_VPORT_CMD_NEW",
+        ("ovs_vport", 2) => "OVS_VPORT_CMD_DEL",
+        ("ovs_vport", 3) => "OVS_VPORT_CMD_GET",
+        ("ovs_vport", 4) => "OVS_VPORT_CMD_SET",
+        ("ovs_flow", 1) => "OVS_FLOW_CMD_NEW",
+        ("ovs_flow", 2) => "OVS_FLOW_CMD_DEL",
+        ("ovs_flow", 3) => "OVS_FLOW_CMD_GET",
+        ("ovs_flow", 4) => "OVS_FLOW_CMD_SET",
+        ("ovs_meter", 1) => "OVS_METER_CMD_FEATURES",
+        ("ovs_meter", 2) => "OVS_METER_CMD_SET",
+        ("ovs_meter", 3) => "OVS_METER_CMD_DEL",
+        ("ovs_meter", 4) => "OVS_METER_CMD_GET",
+        ("ovs_ct_limit", 1) => "OVS_CT_LIMIT_CMD_SET",
+        ("ovs_ct_limit", 2) => "OVS_CT_LIMIT_CMD_DEL",
+        ("ovs_ct_limit", 3) => "OVS_CT_LIMIT_CMD_GET",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_msgs() {
+        assert_eq!(rtm_str(24), Some("RTM_NEWROUTE"));
+        assert_eq!(rtm_str(23), None);
+
+        assert_eq!(nfnl_str(0x10), (None, Some("NFNL_MSG_BATCH_BEGIN")));
+        assert_eq!(nfnl_str(0xa06), (Some("nftables"), Some("NFT_MSG_NEWRULE")));
+        assert_eq!(
+            nfnl_str(0x100),
+            (Some("ctnetlink"), Some("IPCTNL_MSG_CT_NEW"))
+        );
+        assert_eq!(nfnl_str(0x6ff), (Some("ipset"), None));
+        assert_eq!(nfnl_str(0xff00), (None, None));
+
+        assert_eq!(ovs_cmd_str("ovs_flow", 1), Some("OVS_FLOW_CMD_NEW"));
+        assert_eq!(ovs_cmd_str("ovs_packet", 1), None);
+    }
+}
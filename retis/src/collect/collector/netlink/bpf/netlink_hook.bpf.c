@@ -0,0 +1,120 @@
+#include <vmlinux.h>
+#include <bpf/bpf_core_read.h>
+
+#include <common.h>
+
+BINDING_DEF(NETLINK_GENL_FAMILIES_MAX, 8)
+/* Keep in sync with include/uapi/linux/netlink.h */
+#define NETLINK_GENERIC		16
+
+/* Netlink hook configuration.
+ *
+ * broadcast_ksym:  address of netlink_broadcast, to tell broadcast messages
+ *		    from unicast ones.
+ * protocols:	    bitmask of the netlink protocols (NETLINK_*) to report.
+ * genl_families:   generic netlink families to report, 0 terminated.
+ */
+struct netlink_config {
+	u64 broadcast_ksym;
+	u32 protocols;
+	u16 genl_families[NETLINK_GENL_FAMILIES_MAX];
+} __binding;
+struct {
+	__uint(type, BPF_MAP_TYPE_ARRAY);
+	__uint(max_entries, 1);
+	__type(key, u32);
+	__type(value, struct netlink_config);
+} netlink_config_map SEC(".maps");
+
+/* Description of the first netlink message of an skb. */
+struct netlink_event {
+	u32 len;
+	u32 seq;
+	/* Sender port id, 0 for the kernel. */
+	u32 portid;
+	/* Destination port id (unicast) or multicast group (broadcast). */
+	u32 dst_portid;
+	u32 group;
+	u16 type;
+	u16 flags;
+	u8 protocol;
+	/* Generic netlink command, if any. */
+	u8 genl_cmd;
+} __binding;
+
+static __always_inline bool genl_family_enabled(struct netlink_config *cfg,
+						u16 family)
+{
+	int i;
+
+	for (i = 0; i < NETLINK_GENL_FAMILIES_MAX; i++) {
+		if (!cfg->genl_families[i])
+			break;
+		if (cfg->genl_families[i] == family)
+			return true;
+	}
+	return false;
+}
+
+/* Control plane messages are not packets: packet filters do not apply. */
+DEFINE_HOOK_RAW(
+	struct netlink_config *cfg;
+	struct netlink_event *e;
+	struct genlmsghdr genlh;
+	struct nlmsghdr nlh;
+	struct sk_buff *skb;
+	struct sock *sk;
+	unsigned char *data;
+	u32 key = 0;
+	u16 protocol;
+
+	cfg = bpf_map_lookup_elem(&netlink_config_map, &key);
+	if (!cfg)
+		return 0;
+
+	sk = retis_get_sock(ctx);
+	skb = retis_get_sk_buff(ctx);
+	if (!sk || !skb)
+		return 0;
+
+	protocol = BPF_CORE_READ_BITFIELD_PROBED(sk, sk_protocol);
+	if (protocol >= 32 || !(cfg->protocols & (1 << protocol)))
+		return -ENOMSG;
+
+	data = BPF_CORE_READ(skb, data);
+	if (bpf_probe_read_kernel(&nlh, sizeof(nlh), data))
+		return 0;
+
+	genlh.cmd = 0;
+	if (protocol == NETLINK_GENERIC) {
+		if (!genl_family_enabled(cfg, nlh.nlmsg_type))
+			return -ENOMSG;
+		if (bpf_probe_read_kernel(&genlh, sizeof(genlh),
+					  data + sizeof(nlh)))
+			return 0;
+	}
+
+	e = get_event_zsection(event, COLLECTOR_NETLINK, 1, sizeof(*e));
+	if (!e)
+		return 0;
+
+	e->len = nlh.nlmsg_len;
+	e->seq = nlh.nlmsg_seq;
+	e->type = nlh.nlmsg_type;
+	e->flags = nlh.nlmsg_flags;
+	e->protocol = protocol;
+	e->genl_cmd = genlh.cmd;
+	e->portid = BPF_CORE_READ((struct netlink_sock *)sk, portid);
+
+	/* netlink_unicast(ssk, skb, portid, nonblock) and
+	 * netlink_broadcast(ssk, skb, portid, group, allocation).
+	 */
+	if (ctx->ksym == cfg->broadcast_ksym)
+		e->group = retis_get_param(ctx, 3, u32);
+	else
+		e->dst_portid = retis_get_param(ctx, 2, u32);
+
+	return 0;
+)
+
+char __license[] SEC("license") = "GPL";
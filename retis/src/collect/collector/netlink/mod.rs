@@ -0,0 +1,16 @@
+//! # Netlink module
+//!
+//! Provides support for reporting netlink messages exchanged with the kernel,
+//! so control plane actions show up alongside datapath events.
+
+// Re-export netlink.rs
+#[allow(clippy::module_inception)]
+pub(crate) mod netlink;
+pub(crate) use netlink::*;
+
+pub(crate) mod bpf;
+pub(crate) use bpf::NetlinkEventFactory;
+
+mod netlink_hook {
+    include!("bpf/.out/netlink_hook.rs");
+}
@@ -0,0 +1,144 @@
+use std::{
+    mem,
+    os::fd::{AsFd, AsRawFd},
+    sync::Arc,
+};
+
+use anyhow::{bail, Result};
+use clap::{arg, builder::PossibleValuesParser, Parser};
+use libbpf_rs::MapCore;
+use log::warn;
+
+use super::{bpf::*, netlink_hook};
+use crate::{
+    bindings::netlink_hook_uapi::{netlink_config, NETLINK_GENL_FAMILIES_MAX},
+    collect::{cli::Collect, Collector},
+    core::{
+        events::*,
+        kernel::Symbol,
+        probe::{Hook, Probe, ProbeBuilderManager},
+    },
+    helpers::netlink::genl_families,
+};
+
+/// Functions sending netlink messages, both to and from the kernel.
+const NETLINK_SEND_FUNCS: &[&str] = &["netlink_unicast", "netlink_broadcast"];
+
+#[derive(Parser, Debug, Default)]
+pub(crate) struct NetlinkCollectorArgs {
+    #[arg(
+        long,
+        value_parser=PossibleValuesParser::new(["route", "netfilter", "ovs"]),
+        value_delimiter=',',
+        default_value="route,netfilter,ovs",
+        help = "Comma separated list of netlink families whose messages will be collected."
+    )]
+    netlink_families: Vec<String>,
+}
+
+#[derive(Default)]
+pub(crate) struct NetlinkCollector {
+    // Used to keep a reference to our internal config map.
+    #[allow(dead_code)]
+    config_map: Option<libbpf_rs::MapHandle>,
+}
+
+impl NetlinkCollector {
+    fn config_map() -> Result<libbpf_rs::MapHandle> {
+        let opts = libbpf_sys::bpf_map_create_opts {
+            sz: mem::size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
+            ..Default::default()
+        };
+
+        // Please keep in sync with its BPF counterpart in
+        // bpf/netlink_hook.bpf.c
+        libbpf_rs::MapHandle::create(
+            libbpf_rs::MapType::Array,
+            Some("netlink_config_map"),
+            mem::size_of::<u32>() as u32,
+            mem::size_of::<netlink_config>() as u32,
+            1,
+            &opts,
+        )
+        .or_else(|e| bail!("Could not create the netlink config map: {}", e))
+    }
+
+    /// Fill the generic netlink families to report with the OpenVSwitch ones.
+    /// Returns false if none was found.
+    fn ovs_families(cfg: &mut netlink_config) -> Result<bool> {
+        let mut ids = genl_families()?
+            .into_iter()
+            .filter(|(_, name)| OVS_GENL_FAMILIES.contains(&name.as_str()))
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        ids.sort();
+
+        if ids.len() > NETLINK_GENL_FAMILIES_MAX as usize {
+            bail!("Too many OpenVSwitch generic netlink families");
+        }
+        cfg.genl_families[..ids.len()].copy_from_slice(&ids);
+
+        Ok(!ids.is_empty())
+    }
+}
+
+impl Collector for NetlinkCollector {
+    fn new() -> Result<Self> {
+        Ok(Self::default())
+    }
+
+    fn can_run(&mut self, _: &Collect) -> Result<()> {
+        for func in NETLINK_SEND_FUNCS.iter() {
+            if let Err(e) = Symbol::from_name(func) {
+                bail!("Could not find {func}: {e}");
+            }
+        }
+        Ok(())
+    }
+
+    fn init(
+        &mut self,
+        args: &Collect,
+        probes: &mut ProbeBuilderManager,
+        _: Arc<RetisEventsFactory>,
+    ) -> Result<()> {
+        let mut cfg = netlink_config {
+            broadcast_ksym: Symbol::from_name("netlink_broadcast")?.addr()?,
+            ..Default::default()
+        };
+
+        for family in args.collector_args.netlink.netlink_families.iter() {
+            let protocol = match family.as_str() {
+                "route" => NETLINK_ROUTE,
+                "netfilter" => NETLINK_NETFILTER,
+                "ovs" => {
+                    if !Self::ovs_families(&mut cfg)? {
+                        warn!("No OpenVSwitch netlink family found, is openvswitch loaded?");
+                        continue;
+                    }
+                    NETLINK_GENERIC
+                }
+                x => bail!("Unknown netlink family ({x})"),
+            };
+            cfg.protocols |= 1 << protocol;
+        }
+
+        let config_map = Self::config_map()?;
+        let cfg = unsafe { plain::as_bytes(&cfg) };
+        let key = 0_u32.to_ne_bytes();
+        config_map.update(&key, cfg, libbpf_rs::MapFlags::empty())?;
+
+        for func in NETLINK_SEND_FUNCS.iter() {
+            let mut probe = Probe::kprobe(Symbol::from_name(func)?)?;
+            probe.add_hook(
+                Hook::from(netlink_hook::DATA)
+                    .reuse_map("netlink_config_map", config_map.as_fd().as_raw_fd())?
+                    .to_owned(),
+            )?;
+            probes.register_probe(probe)?;
+        }
+
+        self.config_map = Some(config_map);
+        Ok(())
+    }
+}
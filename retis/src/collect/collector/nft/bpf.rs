@@ -1,5 +1,6 @@
 use anyhow::Result;
 
+use super::NftRules;
 use crate::{
     bindings::nft_uapi::*,
     core::events::{
@@ -31,7 +32,16 @@ pub(super) const VERD_MAX: u64 = VERD_REPEAT;
 
 #[event_section_factory(FactoryId::Nft)]
 #[derive(Default)]
-pub(crate) struct NftEventFactory {}
+pub(crate) struct NftEventFactory {
+    /// Description of the rules, to be reported in events.
+    rules: NftRules,
+}
+
+impl NftEventFactory {
+    pub(crate) fn set_rules(&mut self, rules: NftRules) {
+        self.rules = rules;
+    }
+}
 
 impl RawEventSectionFactory for NftEventFactory {
     fn create(&mut self, raw_sections: Vec<BpfRawSection>) -> Result<Box<dyn EventSection>> {
@@ -47,6 +57,17 @@ impl RawEventSectionFactory for NftEventFactory {
             -1 => None,
             _ => Some(raw.r_handle),
         };
+        if let Some(handle) = event.rule_handle {
+            event.rule = self
+                .rules
+                .get(&(
+                    raw.family,
+                    event.table_name.clone(),
+                    event.chain_name.clone(),
+                    handle,
+                ))
+                .cloned();
+        }
         match raw.verdict as i32 {
             -1 => "continue",
             -2 => "break",
@@ -64,6 +85,14 @@ impl RawEventSectionFactory for NftEventFactory {
         }
         .clone_into(&mut event.verdict);
 
+        if raw.eval == 1 {
+            event.eval = Some(NftEval {
+                rules: raw.eval_rules,
+                chains: raw.eval_chains,
+                latency: Duration::from_nanos(raw.eval_latency),
+            });
+        }
+
         // Destination chain is only valid for NFT_JUMP/NFT_GOTO.
         if raw.verdict as i32 == -3 || raw.verdict as i32 == -4 {
             event.verdict_chain_name = raw_to_string_opt!(&raw.verdict_chain_name)?;
@@ -0,0 +1,33 @@
+#ifndef __MODULE_NFT_COMMON__
+#define __MODULE_NFT_COMMON__
+
+#include <vmlinux.h>
+
+#include <common_defs.h>
+
+/* Evaluation of a chain by nft_do_chain, tracked from its entry to its exit
+ * when requested (--nft-eval).
+ */
+struct nft_eval {
+	/* Entry timestamp of nft_do_chain. */
+	u64 start;
+	/* Rules which matched and were traced. */
+	u32 rules;
+	/* Chains traversed, including the base one. */
+	u32 chains;
+} __binding;
+
+/* Chain evaluations in progress, indexed by the address of the skb being
+ * evaluated. The skb is used rather than the current task, as packets
+ * processed in softirqs run on behalf of the task they interrupted.
+ *
+ * Please keep in sync with its Rust counterpart in nft.rs.
+ */
+struct {
+	__uint(type, BPF_MAP_TYPE_HASH);
+	__uint(max_entries, 4096);
+	__type(key, u64);
+	__type(value, struct nft_eval);
+} nft_eval_map SEC(".maps");
+
+#endif /* __MODULE_NFT_COMMON__ */
@@ -3,6 +3,7 @@
 
 #include <common.h>
 #include <compat.h>
+#include <nft_common.h>
 
 #define VERD_SCALE (NFT_RETURN * -1)
 #define ALLOWED_VERDICTS(verd, mask) (1 << (verd + VERD_SCALE) & mask)
@@ -49,7 +50,14 @@ struct nft_event {
 	s64 t_handle;
 	s64 c_handle;
 	s64 r_handle;
+	/* Family of the table, e.g. NFPROTO_INET. */
+	u8 family;
 	u8 policy;
+	/* Chain evaluation, if tracked. See struct nft_eval. */
+	u8 eval;
+	u32 eval_rules;
+	u32 eval_chains;
+	u64 eval_latency;
 } __binding;
 
 /* Specialized macro. Deals with different types with similar layout. */
@@ -89,20 +97,36 @@ static __always_inline s64 nft_get_rule_handle(const struct nft_traceinfo *info,
 
 static __always_inline int nft_trace(struct nft_config *cfg,
 				     struct retis_raw_event *event,
+				     struct sk_buff *skb,
 				     const struct nft_traceinfo *info,
 				     const struct nft_chain *chain,
 				     const struct nft_verdict *verdict,
 				     const void *rule,
 				     enum nft_trace_types type)
 {
+	const struct nft_table *table;
 	struct nft_event *e;
+	struct nft_eval *eval;
 	char *name;
 	u8 policy;
 	u32 code;
+	u64 key;
 
 	policy = (type == NFT_TRACETYPE_POLICY);
 	code = policy ? (u32)BPF_CORE_READ(info, basechain, policy) :
 		(u32)BPF_CORE_READ(verdict, code);
+
+	/* Account the rule in the chain evaluation, if tracked, whatever the
+	 * verdicts to report.
+	 */
+	key = (u64)skb;
+	eval = skb ? bpf_map_lookup_elem(&nft_eval_map, &key) : NULL;
+	if (eval && type == NFT_TRACETYPE_RULE) {
+		eval->rules++;
+		if ((s32)code == NFT_JUMP || (s32)code == NFT_GOTO)
+			eval->chains++;
+	}
+
 	if (!ALLOWED_VERDICTS(code, cfg->verdicts))
 		return -ENOMSG;
 
@@ -123,10 +147,19 @@ static __always_inline int nft_trace(struct nft_config *cfg,
 	name = BPF_CORE_READ(verdict, chain, name);
 	bpf_probe_read_kernel_str(e->verdict_chain_name,
 				  sizeof(e->verdict_chain_name), name);
-	e->t_handle = BPF_CORE_READ(chain, table, handle);
+	table = BPF_CORE_READ(chain, table);
+	e->family = BPF_CORE_READ_BITFIELD_PROBED(table, family);
+	e->t_handle = BPF_CORE_READ(table, handle);
 	e->c_handle = BPF_CORE_READ(chain, handle);
 	e->r_handle = nft_get_rule_handle(info, verdict, rule);
 
+	if (eval) {
+		e->eval = 1;
+		e->eval_rules = eval->rules;
+		e->eval_chains = eval->chains;
+		e->eval_latency = bpf_ktime_get_ns() - eval->start;
+	}
+
 	return 0;
 }
 
@@ -247,8 +280,8 @@ DEFINE_HOOK(F_AND, RETIS_ALL_FILTERS,
 
 	verdict = nft_get_verdict(ctx, cfg, info);
 
-	return nft_trace(cfg, event, info, chain, verdict, rule,
-			 retis_get_nft_type(ctx, cfg));
+	return nft_trace(cfg, event, retis_get_sk_buff(ctx), info, chain,
+			 verdict, rule, retis_get_nft_type(ctx, cfg));
 )
 
 char __license[] SEC("license") = "GPL";
@@ -0,0 +1,19 @@
+#include <common.h>
+#include <nft_common.h>
+
+/* Hook for kprobe:nft_do_chain. */
+DEFINE_HOOK_RAW(
+	u64 skb = (u64)retis_get_sk_buff(ctx);
+	struct nft_eval eval = {
+		.start = ctx->timestamp,
+		.chains = 1,
+	};
+
+	if (!skb)
+		return 0;
+
+	bpf_map_update_elem(&nft_eval_map, &skb, &eval, BPF_ANY);
+	return 0;
+)
+
+char __license[] SEC("license") = "GPL";
@@ -0,0 +1,13 @@
+#include <common.h>
+#include <nft_common.h>
+
+/* Hook for kretprobe:nft_do_chain. */
+DEFINE_HOOK_RAW(
+	u64 skb = (u64)retis_get_sk_buff(ctx);
+
+	/* The chain evaluation is done. */
+	bpf_map_delete_elem(&nft_eval_map, &skb);
+	return 0;
+)
+
+char __license[] SEC("license") = "GPL";
@@ -9,3 +9,9 @@ pub(crate) use bpf::NftEventFactory;
 mod nft_hook {
     include!("bpf/.out/nft.rs");
 }
+mod nft_do_chain_hook {
+    include!("bpf/.out/nft_do_chain.rs");
+}
+mod nft_do_chain_ret_hook {
+    include!("bpf/.out/nft_do_chain_ret.rs");
+}
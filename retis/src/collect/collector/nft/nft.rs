@@ -1,4 +1,6 @@
 use std::{
+    collections::HashMap,
+    ffi::CStr,
     mem,
     os::fd::{AsFd, AsRawFd},
     process::{Command, Stdio},
@@ -8,19 +10,20 @@ use std::{
 use anyhow::{anyhow, bail, Result};
 use clap::{arg, builder::PossibleValuesParser, Parser};
 use libbpf_rs::MapCore;
-use log::info;
+use log::{debug, info};
 use serde_json::json;
 
-use super::{bpf::*, nft_hook};
+use super::{bpf::*, nft_do_chain_hook, nft_do_chain_ret_hook, nft_hook};
 use crate::{
-    bindings::nft_uapi::nft_config,
+    bindings::{nft_common_uapi::nft_eval, nft_uapi::nft_config},
     collect::{cli::Collect, Collector},
     core::{
         events::*,
         inspect,
         kernel::Symbol,
-        probe::{Hook, Probe, ProbeBuilderManager},
+        probe::{Hook, Probe, ProbeBuilderManager, ProbeOption},
     },
+    helpers::netlink::{parse_attrs, NlSocket},
 };
 
 static NFT_BIN: &str = "nft";
@@ -38,6 +41,116 @@ pub(crate) struct NftCollectorArgs {
 Note that stolen verdicts might not be visible if a filter has been specified using the -f option."
     )]
     nft_verdicts: Vec<String>,
+    #[arg(
+        long,
+        help = "Track the evaluation of base chains by nft_do_chain and report in events the number of
+rules which matched, the number of chains traversed and the time spent so far. Helps finding
+packets hitting pathological paths in the ruleset. Adds probes on nft_do_chain."
+    )]
+    nft_eval: bool,
+}
+
+// Netfilter netlink definitions, see include/uapi/linux/netfilter/nfnetlink.h
+// and include/uapi/linux/netfilter/nf_tables.h.
+const NFNL_SUBSYS_NFTABLES: u16 = 10;
+const NFT_MSG_NEWRULE: u16 = 6;
+const NFT_MSG_GETRULE: u16 = 7;
+const NFTA_RULE_TABLE: u16 = 1;
+const NFTA_RULE_CHAIN: u16 = 2;
+const NFTA_RULE_HANDLE: u16 = 3;
+const NFTA_RULE_EXPRESSIONS: u16 = 4;
+const NFTA_RULE_USERDATA: u16 = 7;
+const NFTA_LIST_ELEM: u16 = 1;
+const NFTA_EXPR_NAME: u16 = 1;
+// Rule comment in the user data, see libnftnl's include/libnftnl/udata.h.
+const NFTNL_UDATA_RULE_COMMENT: u8 = 0;
+// Size of struct nfgenmsg.
+const NFGENMSG_LEN: usize = 4;
+
+/// Description of nft rules, indexed by family, table name, chain name and
+/// rule handle.
+pub(crate) type NftRules = HashMap<(u8, String, String, i64), String>;
+
+/// Retrieve the description of the rules currently in the ruleset, for events
+/// to show the rule they matched. Rules added later are not known.
+pub(crate) fn nft_rules() -> Result<NftRules> {
+    let mut rules = HashMap::new();
+
+    // struct nfgenmsg: all families, NFNETLINK_V0 and resource id.
+    let req = [libc::AF_UNSPEC as u8, 0, 0, 0];
+    NlSocket::new(libc::NETLINK_NETFILTER)?.dump(
+        (NFNL_SUBSYS_NFTABLES << 8) | NFT_MSG_GETRULE,
+        &req,
+        |msg| {
+            if msg.r#type == (NFNL_SUBSYS_NFTABLES << 8) | NFT_MSG_NEWRULE {
+                if let Some((key, rule)) = parse_rule(msg.payload) {
+                    rules.insert(key, rule);
+                }
+            }
+            Ok(())
+        },
+    )?;
+
+    debug!("Retrieved the description of {} nft rule(s)", rules.len());
+    Ok(rules)
+}
+
+/// Parse a rule as dumped by NFT_MSG_GETRULE. Its description lists the
+/// expressions it is made of, followed by its comment if any, e.g. `payload
+/// cmp counter immediate comment "allow ssh"`.
+fn parse_rule(payload: &[u8]) -> Option<((u8, String, String, i64), String)> {
+    let family = *payload.first()?;
+    let (mut table, mut chain, mut handle) = (None, None, None);
+    let mut desc = Vec::new();
+    let mut comment = None;
+
+    let string = |data: &[u8]| {
+        CStr::from_bytes_until_nul(data)
+            .ok()
+            .map(|s| s.to_string_lossy().into_owned())
+    };
+
+    for (r#type, data) in parse_attrs(payload.get(NFGENMSG_LEN..)?) {
+        match r#type {
+            NFTA_RULE_TABLE => table = string(data),
+            NFTA_RULE_CHAIN => chain = string(data),
+            NFTA_RULE_HANDLE if data.len() == 8 => {
+                handle = Some(i64::from_be_bytes(data.try_into().ok()?))
+            }
+            NFTA_RULE_EXPRESSIONS => parse_attrs(data)
+                .iter()
+                .filter(|(r#type, _)| *r#type == NFTA_LIST_ELEM)
+                .filter_map(|(_, expr)| {
+                    parse_attrs(expr)
+                        .iter()
+                        .find(|(r#type, _)| *r#type == NFTA_EXPR_NAME)
+                        .and_then(|(_, name)| string(name))
+                })
+                .for_each(|name| desc.push(name)),
+            NFTA_RULE_USERDATA => comment = parse_comment(data),
+            _ => (),
+        }
+    }
+
+    if let Some(comment) = comment {
+        desc.push(format!("comment \"{comment}\""));
+    }
+    Some(((family, table?, chain?, handle?), desc.join(" ")))
+}
+
+/// Retrieve the comment of a rule from its user data, made of type-length-value
+/// entries.
+fn parse_comment(mut udata: &[u8]) -> Option<String> {
+    while let [r#type, len, rest @ ..] = udata {
+        let value = rest.get(..*len as usize)?;
+        if *r#type == NFTNL_UDATA_RULE_COMMENT {
+            return CStr::from_bytes_until_nul(value)
+                .ok()
+                .map(|s| s.to_string_lossy().into_owned());
+        }
+        udata = &rest[*len as usize..];
+    }
+    None
 }
 
 #[derive(Default)]
@@ -46,6 +159,8 @@ pub(crate) struct NftCollector {
     // Used to keep a reference to our internal config map.
     #[allow(dead_code)]
     config_map: Option<libbpf_rs::MapHandle>,
+    #[allow(dead_code)]
+    eval_map: Option<libbpf_rs::MapHandle>,
 }
 
 impl NftCollector {
@@ -117,6 +232,25 @@ impl NftCollector {
         )
         .or_else(|e| bail!("Could not create the nft config map: {}", e))
     }
+
+    fn eval_map() -> Result<libbpf_rs::MapHandle> {
+        let opts = libbpf_sys::bpf_map_create_opts {
+            sz: mem::size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
+            ..Default::default()
+        };
+
+        // Please keep in sync with its BPF counterpart in
+        // bpf/include/nft_common.h
+        libbpf_rs::MapHandle::create(
+            libbpf_rs::MapType::Hash,
+            Some("nft_eval_map"),
+            mem::size_of::<u64>() as u32,
+            mem::size_of::<nft_eval>() as u32,
+            4096,
+            &opts,
+        )
+        .or_else(|e| bail!("Could not create the nft eval map: {}", e))
+    }
 }
 
 impl Collector for NftCollector {
@@ -209,15 +343,44 @@ impl Collector for NftCollector {
         let key = 0_u32.to_ne_bytes();
         config_map.update(&key, cfg, libbpf_rs::MapFlags::empty())?;
 
+        let eval_map = Self::eval_map()?;
+        let eval_fd = eval_map.as_fd().as_raw_fd();
+
         let mut nft_probe = Probe::kprobe(sym)?;
         nft_probe.add_hook(
             Hook::from(nft_hook::DATA)
                 .reuse_map("nft_config_map", config_map.as_fd().as_raw_fd())?
+                .reuse_map("nft_eval_map", eval_fd)?
                 .to_owned(),
         )?;
         probes.register_probe(nft_probe)?;
 
+        // Track the chain evaluations from the entry of nft_do_chain to its
+        // exit.
+        if args.collector_args.nft.nft_eval {
+            let sym = Symbol::from_name("nft_do_chain")?;
+
+            let mut probe = Probe::kprobe(sym.clone())?;
+            probe.set_option(ProbeOption::NoGenericHook)?;
+            probe.add_hook(
+                Hook::from(nft_do_chain_hook::DATA)
+                    .reuse_map("nft_eval_map", eval_fd)?
+                    .to_owned(),
+            )?;
+            probes.register_probe(probe)?;
+
+            let mut probe = Probe::kretprobe(sym)?;
+            probe.set_option(ProbeOption::NoGenericHook)?;
+            probe.add_hook(
+                Hook::from(nft_do_chain_ret_hook::DATA)
+                    .reuse_map("nft_eval_map", eval_fd)?
+                    .to_owned(),
+            )?;
+            probes.register_probe(probe)?;
+        }
+
         self.config_map = Some(config_map);
+        self.eval_map = Some(eval_map);
         Ok(())
     }
 
@@ -228,3 +391,50 @@ impl Collector for NftCollector {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nlattr(r#type: u16, data: &[u8]) -> Vec<u8> {
+        let mut attr = Vec::new();
+        attr.extend_from_slice(&((4 + data.len()) as u16).to_ne_bytes());
+        attr.extend_from_slice(&r#type.to_ne_bytes());
+        attr.extend_from_slice(data);
+        attr.resize(attr.len().next_multiple_of(4), 0);
+        attr
+    }
+
+    #[test]
+    fn rule() {
+        let expr = |name: &[u8]| nlattr(NFTA_LIST_ELEM, &nlattr(NFTA_EXPR_NAME, name));
+
+        // inet family, NFNETLINK_V0.
+        let mut payload = vec![1, 0, 0, 0];
+        payload.extend(nlattr(NFTA_RULE_TABLE, b"firewalld\0"));
+        payload.extend(nlattr(NFTA_RULE_CHAIN, b"filter_INPUT\0"));
+        payload.extend(nlattr(NFTA_RULE_HANDLE, &170i64.to_be_bytes()));
+        payload.extend(nlattr(
+            NFTA_RULE_EXPRESSIONS,
+            &[expr(b"payload\0"), expr(b"cmp\0"), expr(b"immediate\0")].concat(),
+        ));
+
+        let key = (1, "firewalld".to_string(), "filter_INPUT".to_string(), 170);
+        assert_eq!(
+            parse_rule(&payload),
+            Some((key.clone(), "payload cmp immediate".to_string()))
+        );
+
+        payload.extend(nlattr(NFTA_RULE_USERDATA, b"\x00\x0aallow ssh\0"));
+        assert_eq!(
+            parse_rule(&payload),
+            Some((
+                key,
+                "payload cmp immediate comment \"allow ssh\"".to_string()
+            ))
+        );
+
+        // Rules without a handle can't be matched.
+        assert_eq!(parse_rule(&payload[..4]), None);
+    }
+}
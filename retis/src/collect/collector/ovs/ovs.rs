@@ -3,12 +3,13 @@ use std::{
     mem,
     os::fd::{AsFd, AsRawFd},
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, bail, Result};
 use clap::{arg, Parser};
 use libbpf_rs::MapCore;
+use log::{debug, info};
 
 use super::hooks;
 use crate::{
@@ -19,24 +20,23 @@ use crate::{
     collect::{cli::Collect, Collector},
     core::{
         events::*,
-        inspect,
+        inspect::{
+            self,
+            fields::{FieldReq, OptionalField},
+        },
         kernel::Symbol,
-        probe::{user::UsdtProbe, Hook, Probe, ProbeBuilderManager, ProbeOption},
+        probe::{
+            user::UsdtProbe, Hook, Probe, ProbeBuilderManager, ProbeOption, ProbeRuntimeManager,
+        },
         tracking::gc::TrackingGC,
         user::proc::{Process, ThreadInfo},
     },
+    events::*,
     helpers::signals::Running,
 };
 
-// GC runs in a thread every OVS_TRACKING_GC_INTERVAL seconds to collect and
-// remove old entries.
-const OVS_TRACKING_GC_INTERVAL: u64 = 5;
-
-// Time in seconds after entries in the upcall tracking maps are considered outdated
-// and should be manually removed. It's a tradeoff between having consistent
-// data and not having the map full of old entries. However, this logic
-// shouldn't happen much — or it is a bug.
-const TRACKING_OLD_LIMIT: u64 = 60;
+// Interval at which ovs-vswitchd is looked for, to detect restarts.
+const OVS_RESTART_CHECK_INTERVAL: Duration = Duration::from_secs(2);
 
 #[derive(Parser, Debug, Default)]
 pub(crate) struct OvsCollectorArgs {
@@ -63,6 +63,10 @@ pub(crate) struct OvsCollector {
     /* Batch tracking maps. */
     upcall_batches: Option<libbpf_rs::MapHandle>,
     pid_to_batch: Option<libbpf_rs::MapHandle>,
+    /* Restart detection. */
+    ovs_pid: i32,
+    last_check: Option<Instant>,
+    events_factory: Option<Arc<RetisEventsFactory>>,
 }
 
 impl Collector for OvsCollector {
@@ -70,6 +74,13 @@ impl Collector for OvsCollector {
         Ok(Self::default())
     }
 
+    fn optional_fields(&self) -> Vec<OptionalField> {
+        vec![OptionalField::new(
+            "drop action",
+            FieldReq::EnumValue("ovs_action_attr", "OVS_ACTION_ATTR_DROP"),
+        )]
+    }
+
     // Check if the OvS collector can run. Some potential errors are silenced,
     // to avoid returning an error if we can't inspect a given area for some
     // reasons.
@@ -96,15 +107,20 @@ impl Collector for OvsCollector {
         &mut self,
         cli: &Collect,
         probes: &mut ProbeBuilderManager,
-        _: Arc<RetisEventsFactory>,
+        events_factory: Arc<RetisEventsFactory>,
     ) -> Result<()> {
         self.track = cli.collector_args.ovs.ovs_track;
         self.inflight_upcalls_map = Some(Self::create_inflight_upcalls_map()?);
 
         // Create tracking maps and add USDT hooks.
-        self.init_tracking_maps()?;
+        self.init_tracking_maps(cli)?;
         if self.track {
-            self.add_usdt_hooks(probes)?;
+            let ovs = Process::from_cmd("ovs-vswitchd")?;
+            self.usdt_probes(&ovs)?
+                .drain(..)
+                .try_for_each(|p| probes.register_probe(p))?;
+            self.ovs_pid = ovs.pid();
+            self.events_factory = Some(events_factory);
         }
         // Add targetted hooks.
         // Upcall related hooks:
@@ -130,6 +146,55 @@ impl Collector for OvsCollector {
         }
         Ok(())
     }
+
+    // Detect ovs-vswitchd restarts and attach the USDT probes to the new
+    // process, so userspace visibility isn't lost.
+    fn refresh(&mut self, probes: &mut ProbeRuntimeManager) -> Result<()> {
+        if !self.track
+            || self
+                .last_check
+                .is_some_and(|t| t.elapsed() < OVS_RESTART_CHECK_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.last_check = Some(Instant::now());
+
+        // ovs-vswitchd might not be running again yet.
+        let pid = match Process::pid_of("ovs-vswitchd") {
+            Ok(pid) if pid != self.ovs_pid => pid,
+            _ => return Ok(()),
+        };
+
+        let ovs = Process::from_pid(pid)?;
+        // Wait for the handler threads to be started.
+        if !ovs
+            .thread_info()?
+            .iter()
+            .any(|t| t.comm.contains("handler"))
+        {
+            debug!("ovs-vswitchd restarted (pid {pid}) but has no handler yet");
+            return Ok(());
+        }
+
+        let old_pid = self.ovs_pid;
+        self.ovs_pid = pid;
+        for mut probe in self.usdt_probes(&ovs)?.drain(..) {
+            probes.attach_probe(&mut probe)?;
+        }
+        info!("ovs-vswitchd restarted (pid {old_pid} -> {pid}), USDT probes attached");
+
+        if let Some(events_factory) = &self.events_factory {
+            events_factory.add_event(|event| {
+                event.insert_section(
+                    SectionId::Ovs,
+                    Box::new(OvsEvent::Restart {
+                        restart: RestartEvent { old_pid, pid },
+                    }),
+                )
+            })?;
+        }
+        Ok(())
+    }
 }
 
 impl OvsCollector {
@@ -329,15 +394,15 @@ impl OvsCollector {
         Ok(())
     }
 
-    /// Add USDT hooks.
-    fn add_usdt_hooks(&mut self, probes: &mut ProbeBuilderManager) -> Result<()> {
-        let ovs = Process::from_cmd("ovs-vswitchd")?;
+    /// Get the USDT probes of an ovs-vswitchd process, with their hooks. The
+    /// batch maps are (re)created for the process handler threads.
+    fn usdt_probes(&mut self, ovs: &Process) -> Result<Vec<Probe>> {
         if !ovs.is_usdt("main::run_start")? {
             bail!(
                 "Cannot find USDT probes in ovs-vswitchd. Was it built with --enable-usdt-probes?"
             );
         }
-        self.create_batch_maps(&ovs)?;
+        self.create_batch_maps(ovs)?;
         let upcall_batches_fd = self
             .upcall_batches
             .as_ref()
@@ -358,32 +423,33 @@ impl OvsCollector {
         user_exec_hook.reuse_map("flow_exec_tracking", self.flow_exec_tracking_fd)?;
         let mut batch_probes = vec![
             (
-                Probe::usdt(UsdtProbe::new(&ovs, "dpif_recv::recv_upcall")?)?,
+                Probe::usdt(UsdtProbe::new(ovs, "dpif_recv::recv_upcall")?)?,
                 user_recv_hook,
             ),
             (
                 Probe::usdt(UsdtProbe::new(
-                    &ovs,
+                    ovs,
                     "dpif_netlink_operate__::op_flow_execute",
                 )?)?,
                 user_exec_hook,
             ),
             (
-                Probe::usdt(UsdtProbe::new(&ovs, "dpif_netlink_operate__::op_flow_put")?)?,
+                Probe::usdt(UsdtProbe::new(ovs, "dpif_netlink_operate__::op_flow_put")?)?,
                 Hook::from(hooks::user_op_put::DATA),
             ),
         ];
 
+        let mut probes = Vec::new();
         while let Some((mut probe, mut hook)) = batch_probes.pop() {
             hook.reuse_map("upcall_batches", upcall_batches_fd)?
                 .reuse_map("pid_to_batch", pid_to_batch_fd)?;
             probe.add_hook(hook)?;
-            probes.register_probe(probe)?;
+            probes.push(probe);
         }
-        Ok(())
+        Ok(probes)
     }
 
-    fn init_tracking_maps(&mut self) -> Result<()> {
+    fn init_tracking_maps(&mut self, cli: &Collect) -> Result<()> {
         let upcall_tracking = Self::create_upcall_tracking_map()?;
         let flow_exec_tracking = Self::create_flow_exec_tracking_map()?;
         self.upcall_tracking_fd = upcall_tracking.as_fd().as_raw_fd();
@@ -394,15 +460,17 @@ impl OvsCollector {
             ("flow_exec_tracking", flow_exec_tracking),
         ]);
 
-        self.gc = Some(
-            TrackingGC::new("ovs-tracking-gc", tracking_maps, |v| {
+        self.gc = Some(TrackingGC::new(
+            "ovs-tracking-gc",
+            tracking_maps,
+            cli.tracking_gc_interval,
+            cli.tracking_gc_limit,
+            |v| {
                 let insert_time =
                     u64::from_ne_bytes(v[0..8].try_into().map_err(|e| anyhow!("{:?}", e))?);
                 Ok(Duration::from_nanos(insert_time))
-            })
-            .interval(OVS_TRACKING_GC_INTERVAL)
-            .limit(TRACKING_OLD_LIMIT),
-        );
+            },
+        ));
         Ok(())
     }
 }
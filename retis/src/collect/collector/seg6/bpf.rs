@@ -0,0 +1,71 @@
+//! Rust<>BPF types definitions for the seg6 module.
+//! Please keep this file in sync with its BPF counterpart in bpf/seg6_hook.bpf.c
+
+use anyhow::{bail, Result};
+
+use crate::{
+    bindings::seg6_hook_uapi::*,
+    core::events::{
+        parse_single_raw_section, BpfRawSection, EventSectionFactory, FactoryId,
+        RawEventSectionFactory,
+    },
+    event_section_factory,
+    events::*,
+};
+
+#[event_section_factory(FactoryId::Seg6)]
+#[derive(Default)]
+pub(crate) struct Seg6EventFactory {}
+
+impl RawEventSectionFactory for Seg6EventFactory {
+    fn create(&mut self, raw_sections: Vec<BpfRawSection>) -> Result<Box<dyn EventSection>> {
+        let raw = parse_single_raw_section::<seg6_event>(&raw_sections)?;
+
+        Ok(Box::new(Seg6Event {
+            behavior: match raw.kind as u32 {
+                SEG6_ENCAP => "H.Encaps".to_string(),
+                SEG6_INLINE => "H.Insert".to_string(),
+                SEG6_LOCAL => local_action(raw.action),
+                x => bail!("Unknown seg6 event kind ({x})"),
+            },
+        }))
+    }
+}
+
+/// Convert a seg6_local action to the name of its behavior.
+/// See include/uapi/linux/seg6_local.h.
+fn local_action(action: u32) -> String {
+    match action {
+        1 => "End",
+        2 => "End.X",
+        3 => "End.T",
+        4 => "End.DX2",
+        5 => "End.DX6",
+        6 => "End.DX4",
+        7 => "End.DT6",
+        8 => "End.DT4",
+        9 => "End.B6",
+        10 => "End.B6.Encaps",
+        11 => "End.BM",
+        12 => "End.S",
+        13 => "End.AS",
+        14 => "End.AM",
+        15 => "End.BPF",
+        16 => "End.DT46",
+        x => return format!("unknown ({x})"),
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn action() {
+        assert_eq!(local_action(1), "End");
+        assert_eq!(local_action(8), "End.DT4");
+        assert_eq!(local_action(16), "End.DT46");
+        assert_eq!(local_action(42), "unknown (42)");
+    }
+}
@@ -0,0 +1,88 @@
+#include <vmlinux.h>
+#include <bpf/bpf_core_read.h>
+
+#include <common.h>
+
+/* Seg6 hook configuration. Holds the address of the probed functions, to know
+ * where the hook is running.
+ *
+ * encap_ksym:  seg6_do_srh_encap, SRH pushed in a new outer IPv6 header.
+ * inline_ksym: seg6_do_srh_inline, SRH inserted in the existing IPv6 header.
+ *
+ * Other probes are the seg6_local behaviors (input_action_end*).
+ */
+struct seg6_config {
+	u64 encap_ksym;
+	u64 inline_ksym;
+} __binding;
+struct {
+	__uint(type, BPF_MAP_TYPE_ARRAY);
+	__uint(max_entries, 1);
+	__type(key, u32);
+	__type(value, struct seg6_config);
+} seg6_config_map SEC(".maps");
+
+/* Type local to net/ipv6/seg6_local.c, which is not part of vmlinux.h. Keep in
+ * sync with the kernel definition.
+ */
+struct seg6_local_lwt___retis {
+	int action;
+} __attribute__((preserve_access_index));
+
+enum seg6_event_kind {
+	SEG6_ENCAP = 1,
+	SEG6_INLINE,
+	SEG6_LOCAL,
+} __binding;
+
+struct seg6_event {
+	u8 kind;
+	/* seg6_local action (SEG6_LOCAL_ACTION_*), for SEG6_LOCAL. */
+	u32 action;
+} __binding;
+
+DEFINE_HOOK_RAW(
+	struct seg6_local_lwt___retis *slwt;
+	struct seg6_config *cfg;
+	struct seg6_event *e;
+	u32 key = 0;
+	u8 kind;
+
+	if (!RETIS_TRACKABLE(ctx->filters_ret))
+		return -ENOMSG;
+
+	cfg = bpf_map_lookup_elem(&seg6_config_map, &key);
+	if (!cfg)
+		return 0;
+
+	if (ctx->ksym == cfg->encap_ksym || ctx->ksym == cfg->inline_ksym) {
+		/* Encapsulation functions are probed on return, for the SRH
+		 * to be part of the packet. Failures are not reported.
+		 */
+		if ((int)ctx->regs.ret)
+			return -ENOMSG;
+
+		kind = ctx->ksym == cfg->encap_ksym ? SEG6_ENCAP : SEG6_INLINE;
+		e = get_event_zsection(event, COLLECTOR_SEG6, 1, sizeof(*e));
+		if (!e)
+			return 0;
+
+		e->kind = kind;
+		return 0;
+	}
+
+	/* seg6_local behaviors: input_action_end*(skb, slwt). */
+	slwt = retis_get_param(ctx, 1, struct seg6_local_lwt___retis *);
+	if (!slwt)
+		return -ENOMSG;
+
+	e = get_event_zsection(event, COLLECTOR_SEG6, 1, sizeof(*e));
+	if (!e)
+		return 0;
+
+	e->kind = SEG6_LOCAL;
+	e->action = BPF_CORE_READ(slwt, action);
+	return 0;
+)
+
+char __license[] SEC("license") = "GPL";
@@ -0,0 +1,16 @@
+//! # Seg6 module
+//!
+//! Provides support for reporting the IPv6 Segment Routing (SRv6) processing
+//! of packets: encapsulation and seg6_local behaviors.
+
+// Re-export seg6.rs
+#[allow(clippy::module_inception)]
+pub(crate) mod seg6;
+pub(crate) use seg6::*;
+
+pub(crate) mod bpf;
+pub(crate) use bpf::Seg6EventFactory;
+
+mod seg6_hook {
+    include!("bpf/.out/seg6_hook.rs");
+}
@@ -0,0 +1,135 @@
+use std::{
+    mem,
+    os::fd::{AsFd, AsRawFd},
+    sync::Arc,
+};
+
+use anyhow::{bail, Result};
+use libbpf_rs::MapCore;
+use log::debug;
+
+use super::seg6_hook;
+use crate::{
+    bindings::seg6_hook_uapi::seg6_config,
+    collect::{cli::Collect, Collector},
+    core::{
+        events::*,
+        kernel::Symbol,
+        probe::{Hook, Probe, ProbeBuilderManager},
+    },
+};
+
+/// Functions adding an SRH to packets (seg6 lightweight tunnels), in the encap
+/// and inline modes.
+const SEG6_ENCAP_FUNCS: &[&str] = &["seg6_do_srh_encap", "seg6_do_srh_inline"];
+
+/// Functions implementing the seg6_local behaviors. Not all of them are
+/// available on all kernels.
+const SEG6_LOCAL_FUNCS: &[&str] = &[
+    "input_action_end",
+    "input_action_end_x",
+    "input_action_end_t",
+    "input_action_end_dx2",
+    "input_action_end_dx6",
+    "input_action_end_dx4",
+    "input_action_end_dt6",
+    "input_action_end_dt4",
+    "input_action_end_dt46",
+    "input_action_end_b6",
+    "input_action_end_b6_encap",
+    "input_action_end_bpf",
+];
+
+#[derive(Default)]
+pub(crate) struct Seg6Collector {
+    // Used to keep a reference to our internal config map.
+    #[allow(dead_code)]
+    config_map: Option<libbpf_rs::MapHandle>,
+}
+
+impl Seg6Collector {
+    fn config_map() -> Result<libbpf_rs::MapHandle> {
+        let opts = libbpf_sys::bpf_map_create_opts {
+            sz: mem::size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
+            ..Default::default()
+        };
+
+        // Please keep in sync with its BPF counterpart in
+        // bpf/seg6_hook.bpf.c
+        libbpf_rs::MapHandle::create(
+            libbpf_rs::MapType::Array,
+            Some("seg6_config_map"),
+            mem::size_of::<u32>() as u32,
+            mem::size_of::<seg6_config>() as u32,
+            1,
+            &opts,
+        )
+        .or_else(|e| bail!("Could not create the seg6 config map: {}", e))
+    }
+}
+
+impl Collector for Seg6Collector {
+    fn new() -> Result<Self> {
+        Ok(Self::default())
+    }
+
+    fn can_run(&mut self, _: &Collect) -> Result<()> {
+        for func in SEG6_ENCAP_FUNCS.iter() {
+            if let Err(e) = Symbol::from_name(func) {
+                bail!("Could not find {func} (is CONFIG_IPV6_SEG6_LWTUNNEL enabled?): {e}");
+            }
+        }
+        Ok(())
+    }
+
+    fn init(
+        &mut self,
+        _: &Collect,
+        probes: &mut ProbeBuilderManager,
+        _: Arc<RetisEventsFactory>,
+    ) -> Result<()> {
+        let cfg = seg6_config {
+            encap_ksym: Symbol::from_name(SEG6_ENCAP_FUNCS[0])?.addr()?,
+            inline_ksym: Symbol::from_name(SEG6_ENCAP_FUNCS[1])?.addr()?,
+        };
+
+        let config_map = Self::config_map()?;
+        let key = 0_u32.to_ne_bytes();
+        config_map.update(
+            &key,
+            unsafe { plain::as_bytes(&cfg) },
+            libbpf_rs::MapFlags::empty(),
+        )?;
+
+        let hook = Hook::from(seg6_hook::DATA)
+            .reuse_map("seg6_config_map", config_map.as_fd().as_raw_fd())?
+            .to_owned();
+
+        // Encapsulation functions are probed on return, once the SRH was
+        // added to the packet.
+        for func in SEG6_ENCAP_FUNCS.iter() {
+            let mut probe = Probe::kretprobe(Symbol::from_name(func)?)?;
+            probe.add_hook(hook.clone())?;
+            probes.register_probe(probe)?;
+        }
+
+        // Behaviors are probed on entry, while the SRH still describes the
+        // segment being processed.
+        for func in SEG6_LOCAL_FUNCS.iter() {
+            let symbol = match Symbol::from_name(func) {
+                Ok(symbol) => symbol,
+                Err(e) => {
+                    debug!("Not probing {func}: {e}");
+                    continue;
+                }
+            };
+
+            let mut probe = Probe::kprobe(symbol)?;
+            probe.add_hook(hook.clone())?;
+            probes.register_probe(probe)?;
+        }
+
+        self.config_map = Some(config_map);
+        Ok(())
+    }
+}
@@ -5,9 +5,14 @@
 //! Please keep this file in sync with its BPF counterpart in bpf/skb_hook.bpf.c
 
 use anyhow::bail;
-use std::str;
+use std::{
+    collections::HashMap,
+    net::{Ipv4Addr, Ipv6Addr},
+    str,
+};
 
 use anyhow::{anyhow, Result};
+use log::warn;
 use pnet_packet::{
     arp::ArpPacket, ethernet::*, icmp::IcmpPacket, icmpv6::Icmpv6Packet, ip::*, ipv4::*, ipv6::*,
     tcp::TcpPacket, udp::UdpPacket, Packet,
@@ -17,7 +22,8 @@ use crate::{
     bindings::if_vlan_uapi::*,
     bindings::skb_hook_uapi::*,
     core::events::{
-        parse_raw_section, BpfRawSection, EventSectionFactory, FactoryId, RawEventSectionFactory,
+        parse_raw_section, parse_raw_section_with_data, BpfRawSection, EventSectionFactory,
+        FactoryId, RawEventSectionFactory,
     },
     event_section_factory,
     events::{
@@ -89,6 +95,39 @@ pub(super) fn unmarshal_ipv6(ip: &Ipv6Packet) -> Result<SkbIpEvent> {
     })
 }
 
+/// IPv6 routing header type of Segment Routing Headers, see RFC 8754.
+const IPV6_SRCRT_TYPE_4: u8 = 4;
+
+/// Unmarshal an IPv6 Segment Routing Header. Returns the SRH along with the
+/// protocol of the following header and the SRH length, or None if the routing
+/// header is not an SRH.
+pub(super) fn unmarshal_srh(data: &[u8]) -> Option<(SkbSrhEvent, IpNextHeaderProtocol, usize)> {
+    if data.len() < 8 || data[2] != IPV6_SRCRT_TYPE_4 {
+        return None;
+    }
+
+    let len = (data[1] as usize + 1) * 8;
+    let segments = data[8..len.min(data.len())]
+        .chunks_exact(16)
+        .take(data[4] as usize + 1)
+        .map_while(|s| <[u8; 16]>::try_from(s).ok())
+        .map(|s| Ipv6Addr::from(s).to_string())
+        .collect::<Vec<_>>();
+
+    Some((
+        SkbSrhEvent {
+            segments_left: data[3],
+            last_entry: data[4],
+            active: segments.get(data[3] as usize).cloned(),
+            flags: data[5],
+            tag: u16::from_be_bytes([data[6], data[7]]),
+            segments,
+        },
+        IpNextHeaderProtocol(data[0]),
+        len,
+    ))
+}
+
 pub(super) fn unmarshal_tcp(tcp: &TcpPacket) -> Result<SkbTcpEvent> {
     Ok(SkbTcpEvent {
         sport: tcp.get_source(),
@@ -109,6 +148,145 @@ pub(super) fn unmarshal_udp(udp: &UdpPacket) -> Result<SkbUdpEvent> {
     })
 }
 
+/// Parses an SCTP common header, along the types of the chunks following it.
+pub(super) fn unmarshal_sctp(data: &[u8]) -> Option<SkbSctpEvent> {
+    let hdr = data.get(..12)?;
+
+    let mut chunks = Vec::new();
+    let mut offset = 12;
+    while let Some(chunk) = data.get(offset..(offset + 4)) {
+        chunks.push(chunk[0]);
+
+        // The chunk length includes its header but not its padding.
+        let len = u16::from_be_bytes([chunk[2], chunk[3]]) as usize;
+        if len < 4 {
+            break;
+        }
+        offset += len.next_multiple_of(4);
+    }
+
+    Some(SkbSctpEvent {
+        sport: u16::from_be_bytes([hdr[0], hdr[1]]),
+        dport: u16::from_be_bytes([hdr[2], hdr[3]]),
+        vtag: u32::from_be_bytes([hdr[4], hdr[5], hdr[6], hdr[7]]),
+        chunks,
+    })
+}
+
+/// IANA assigned DNS port.
+const DNS_PORT: u16 = 53;
+/// Maximum number of compression pointers followed when parsing a DNS name.
+const DNS_MAX_POINTERS: usize = 16;
+
+/// Parses a DNS message header and its questions and answers, as long as they
+/// are captured.
+pub(super) fn unmarshal_dns(msg: &[u8]) -> Option<SkbDnsEvent> {
+    let hdr = msg.get(..12)?;
+    let field = |i: usize| u16::from_be_bytes([hdr[i], hdr[i + 1]]);
+    let flags = field(2);
+
+    let mut dns = SkbDnsEvent {
+        id: field(0),
+        response: flags & 0x8000 != 0,
+        opcode: ((flags >> 11) & 0xf) as u8,
+        rcode: (flags & 0xf) as u8,
+        questions: Vec::new(),
+        answers: Vec::new(),
+    };
+
+    let mut offset = 12;
+    for _ in 0..field(4) {
+        let (name, next) = match dns_name(msg, offset) {
+            Some(name) => name,
+            None => return Some(dns),
+        };
+        let fixed = match msg.get(next..(next + 4)) {
+            Some(fixed) => fixed,
+            None => return Some(dns),
+        };
+
+        dns.questions.push(SkbDnsQuestion {
+            name,
+            r#type: u16::from_be_bytes([fixed[0], fixed[1]]),
+        });
+        offset = next + 4;
+    }
+
+    for _ in 0..field(6) {
+        let (name, next) = match dns_name(msg, offset) {
+            Some(name) => name,
+            None => break,
+        };
+        // Type, class, TTL and data length.
+        let fixed = match msg.get(next..(next + 10)) {
+            Some(fixed) => fixed,
+            None => break,
+        };
+        let r#type = u16::from_be_bytes([fixed[0], fixed[1]]);
+        let len = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+        let start = next + 10;
+
+        let data = msg
+            .get(start..(start + len))
+            .and_then(|rdata| match r#type {
+                1 => Some(Ipv4Addr::from(<[u8; 4]>::try_from(rdata).ok()?).to_string()),
+                28 => Some(Ipv6Addr::from(<[u8; 16]>::try_from(rdata).ok()?).to_string()),
+                2 | 5 | 12 => dns_name(msg, start).map(|(name, _)| name),
+                _ => None,
+            });
+
+        dns.answers.push(SkbDnsAnswer {
+            name,
+            r#type,
+            ttl: u32::from_be_bytes([fixed[4], fixed[5], fixed[6], fixed[7]]),
+            data,
+        });
+        offset = start + len;
+    }
+
+    Some(dns)
+}
+
+/// Parses a domain name, possibly compressed, starting at `offset` in a DNS
+/// message. Returns it along the offset following it.
+fn dns_name(msg: &[u8], offset: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut pos = offset;
+    let mut next = None;
+    let mut pointers = 0;
+
+    loop {
+        let len = *msg.get(pos)? as usize;
+        match len & 0xc0 {
+            0 if len == 0 => {
+                next.get_or_insert(pos + 1);
+                break;
+            }
+            0 => {
+                let label = msg.get((pos + 1)..(pos + 1 + len))?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                pos += 1 + len;
+            }
+            // Compression pointer, the name continues somewhere else.
+            0xc0 => {
+                pointers += 1;
+                if pointers > DNS_MAX_POINTERS {
+                    return None;
+                }
+                next.get_or_insert(pos + 2);
+                pos = u16::from_be_bytes([len as u8 & 0x3f, *msg.get(pos + 1)?]) as usize;
+            }
+            _ => return None,
+        }
+    }
+
+    let name = match labels.is_empty() {
+        true => ".".to_string(),
+        false => labels.join("."),
+    };
+    Some((name, next?))
+}
+
 pub(super) fn unmarshal_icmp(icmp: &IcmpPacket) -> Result<SkbIcmpEvent> {
     Ok(SkbIcmpEvent {
         r#type: icmp.get_icmp_type().0,
@@ -167,6 +345,13 @@ pub(super) fn unmarshal_meta(raw_section: &BpfRawSection) -> Result<SkbMetaEvent
         csum: raw.csum,
         csum_level: raw.csum_level,
         priority: raw.priority,
+        pkt_type: raw.pkt_type,
+        protocol: raw.protocol,
+        headroom: raw.headroom,
+        tailroom: raw.tailroom,
+        truesize: raw.truesize,
+        tail: raw.tail,
+        end: raw.end,
     })
 }
 
@@ -178,9 +363,47 @@ pub(super) fn unmarshal_vlan(raw_section: &BpfRawSection) -> Result<SkbVlanEvent
         dei: raw.dei == 1,
         vid: raw.vid,
         acceleration: raw.acceleration == 1,
+        ..Default::default()
     })
 }
 
+/// Parses an 802.1Q or 802.1ad tag, returning it along the ethertype of its
+/// payload.
+fn unmarshal_vlan_tag(tpid: u16, data: &[u8]) -> Option<(SkbVlanTag, EtherType)> {
+    let tci = u16::from_be_bytes(data.get(..2)?.try_into().ok()?);
+    let etype = u16::from_be_bytes(data.get(2..4)?.try_into().ok()?);
+    Some((SkbVlanTag::from_tci(tpid, tci), EtherType(etype)))
+}
+
+/// Merges the VLAN tags found in the packet with the one reported by the VLAN
+/// section, if any. An accelerated tag is the outermost one; otherwise the
+/// VLAN section reports the first tag of the packet.
+fn merge_vlan_tags(vlan: &mut Option<SkbVlanEvent>, mut tags: Vec<SkbVlanTag>) {
+    if tags.is_empty() {
+        return;
+    }
+
+    match vlan {
+        Some(vlan) if vlan.acceleration => vlan.inner = tags,
+        Some(vlan) => {
+            vlan.tpid = Some(tags[0].tpid);
+            vlan.inner = tags.split_off(1);
+        }
+        None => {
+            let inner = tags.split_off(1);
+            let outer = &tags[0];
+            *vlan = Some(SkbVlanEvent {
+                pcp: outer.pcp,
+                dei: outer.dei,
+                vid: outer.vid,
+                acceleration: false,
+                tpid: Some(outer.tpid),
+                inner,
+            });
+        }
+    }
+}
+
 pub(super) fn unmarshal_data_ref(raw_section: &BpfRawSection) -> Result<SkbDataRefEvent> {
     let raw = parse_raw_section::<skb_data_ref_event>(raw_section)?;
 
@@ -205,22 +428,82 @@ pub(super) fn unmarshal_gso(raw_section: &BpfRawSection) -> Result<SkbGsoEvent>
     })
 }
 
+pub(super) fn unmarshal_softirq(raw_section: &BpfRawSection) -> Result<SkbSoftirqEvent> {
+    let raw = parse_raw_section::<skb_softirq_event>(raw_section)?;
+
+    Ok(SkbSoftirqEvent {
+        delay: Duration::from_nanos(raw.delay),
+        running: Duration::from_nanos(raw.running),
+    })
+}
+
+pub(super) fn unmarshal_frags(raw_section: &BpfRawSection) -> Result<SkbFragsEvent> {
+    let raw = parse_raw_section::<skb_frags_event>(raw_section)?;
+
+    Ok(SkbFragsEvent {
+        nr_frags: raw.nr_frags,
+        sizes: raw
+            .sizes
+            .iter()
+            .take(raw.nr_frags as usize)
+            .copied()
+            .collect(),
+        frag_list: raw.frag_list == 1,
+    })
+}
+
+// Packet types, see include/uapi/linux/if_packet.h.
+const PACKET_BROADCAST: u8 = 1;
+const PACKET_MULTICAST: u8 = 2;
+const PACKET_OTHERHOST: u8 = 3;
+const PACKET_LOOPBACK: u8 = 5;
+
+pub(super) fn unmarshal_origin(raw_section: &BpfRawSection) -> Result<SkbOrigin> {
+    let raw = parse_raw_section::<skb_origin_event>(raw_section)?;
+    let has_sk = raw.has_sk == 1;
+
+    Ok(match raw.iif {
+        _ if raw.pkt_type == PACKET_LOOPBACK => SkbOrigin::Local,
+        // skb->skb_iif is set when the packet is processed by the stack, early
+        // in the Rx path (drivers, GRO) it is not set yet. Only received
+        // packets have their packet type set to something else than
+        // PACKET_HOST at this point. Otherwise the section is only reported
+        // for packets bound to a socket.
+        0 => match raw.pkt_type {
+            PACKET_BROADCAST | PACKET_MULTICAST | PACKET_OTHERHOST => SkbOrigin::Received,
+            _ => SkbOrigin::Local,
+        },
+        // Packets delivered to the host can be seen on a different device
+        // than the one they were received on (e.g. VRF); the socket tells them
+        // apart from forwarded ones once it was looked up.
+        iif if raw.ifindex != 0 && raw.ifindex != iif && !has_sk => SkbOrigin::Forwarded,
+        _ => SkbOrigin::Received,
+    })
+}
+
+/// Parses the raw packet, filling the related sections of the event. The VLAN
+/// tags found in the packet are returned, to be merged with the VLAN section.
 pub(super) fn unmarshal_packet(
     event: &mut SkbEvent,
     raw_section: &BpfRawSection,
     report_eth: bool,
-) -> Result<()> {
-    let raw = parse_raw_section::<skb_packet_event>(raw_section)?;
+) -> Result<Vec<SkbVlanTag>> {
+    let (raw, data) = parse_raw_section_with_data::<skb_packet_event>(raw_section)?;
+    let packet = data
+        .get(..(raw.capture_len as usize))
+        .ok_or_else(|| anyhow!("Packet data is shorter than its capture length"))?;
 
     // First add the raw packet part in the event.
     event.packet = Some(SkbPacketEvent {
         len: raw.len,
         capture_len: raw.capture_len,
-        packet: RawPacket(raw.packet[..(raw.capture_len as usize)].to_vec()),
+        packet: RawPacket(packet.to_vec()),
+        l3_offset: None,
+        l4_offset: None,
     });
 
     // Then start parsing the raw packet to generate other sections.
-    let eth = EthernetPacket::new(&raw.packet[..(raw.capture_len as usize)]).ok_or_else(|| {
+    let eth = EthernetPacket::new(packet).ok_or_else(|| {
         anyhow!("Could not parse Ethernet packet (buffer size less than minimal)")
     })?;
 
@@ -228,42 +511,106 @@ pub(super) fn unmarshal_packet(
     // could still get invalid ones, if the data at the right offset looks like
     // an ethernet packet; but what else can we do?
     if etype_str(eth.get_ethertype().0).is_none() {
-        return Ok(());
+        return Ok(Vec::new());
     }
 
     if report_eth && raw.fake_eth == 0 {
         event.eth = Some(unmarshal_eth(&eth)?);
     }
 
-    match eth.get_ethertype() {
+    // Offsets of the network and transport headers, so fields can later be
+    // extracted without dissecting the packet again.
+    let mut l3_offset = EthernetPacket::minimum_packet_size() as u32;
+
+    // 802.1Q and 802.1ad (QinQ) tags, the actual ethertype follows.
+    let (mut etype, mut payload) = (eth.get_ethertype(), eth.payload());
+    let mut tags = Vec::new();
+    while matches!(etype.0, 0x8100 | 0x88a8 | 0x9100) {
+        match unmarshal_vlan_tag(etype.0, payload) {
+            Some((tag, next)) => {
+                tags.push(tag);
+                etype = next;
+                payload = &payload[4..];
+                l3_offset += 4;
+            }
+            None => break,
+        }
+    }
+
+    let offsets = match etype {
+        EtherTypes::Arp | EtherTypes::Ipv4 | EtherTypes::Ipv6 => {
+            unmarshal_l3(event, etype, payload, l3_offset)?
+        }
+        // If we did not generate any data in the skb section, this means we do
+        // not support yet the protocol used. At least provide the ethertype (we
+        // already checked it looked valid).
+        _ => {
+            if event.eth.is_none() {
+                event.eth = Some(unmarshal_eth(&eth)?);
+            }
+            (None, None)
+        }
+    };
+
+    if let Some(packet) = event.packet.as_mut() {
+        (packet.l3_offset, packet.l4_offset) = offsets;
+    }
+
+    Ok(tags)
+}
+
+/// Parses the network and transport headers, `payload` starting at the network
+/// header of type `etype`, found at `l3_offset` in the packet. Returns the
+/// offsets of the headers which could be parsed.
+fn unmarshal_l3(
+    event: &mut SkbEvent,
+    etype: EtherType,
+    payload: &[u8],
+    l3_offset: u32,
+) -> Result<(Option<u32>, Option<u32>)> {
+    let mut offsets = (None, None);
+
+    match etype {
         EtherTypes::Arp => {
-            if let Some(eth) = ArpPacket::new(eth.payload()) {
+            if let Some(eth) = ArpPacket::new(payload) {
                 event.arp = unmarshal_arp(&eth)?;
+                offsets = (Some(l3_offset), None);
             };
         }
         EtherTypes::Ipv4 => {
-            if let Some(ip) = Ipv4Packet::new(eth.payload()) {
+            if let Some(ip) = Ipv4Packet::new(payload) {
                 event.ip = Some(unmarshal_ipv4(&ip)?);
+                offsets = (
+                    Some(l3_offset),
+                    Some(l3_offset + ip.get_header_length() as u32 * 4),
+                );
                 unmarshal_l4(event, ip.get_next_level_protocol(), ip.payload())?;
             };
         }
         EtherTypes::Ipv6 => {
-            if let Some(ip) = Ipv6Packet::new(eth.payload()) {
+            if let Some(ip) = Ipv6Packet::new(payload) {
                 event.ip = Some(unmarshal_ipv6(&ip)?);
-                unmarshal_l4(event, ip.get_next_header(), ip.payload())?;
+                let mut l4_offset = l3_offset + Ipv6Packet::minimum_packet_size() as u32;
+                let (mut protocol, mut payload) = (ip.get_next_header(), ip.payload());
+
+                // Segment Routing Header (SRv6), the actual payload follows.
+                if protocol == IpNextHeaderProtocols::Ipv6Route {
+                    if let Some((srh, next, len)) = unmarshal_srh(payload) {
+                        event.srh = Some(srh);
+                        l4_offset += len as u32;
+                        protocol = next;
+                        payload = payload.get(len..).unwrap_or_default();
+                    }
+                }
+
+                offsets = (Some(l3_offset), Some(l4_offset));
+                unmarshal_l4(event, protocol, payload)?;
             };
         }
-        // If we did not generate any data in the skb section, this means we do
-        // not support yet the protocol used. At least provide the ethertype (we
-        // already checked it looked valid).
-        _ => {
-            if event.eth.is_none() {
-                event.eth = Some(unmarshal_eth(&eth)?);
-            }
-        }
+        _ => (),
     }
 
-    Ok(())
+    Ok(offsets)
 }
 
 fn unmarshal_l4(
@@ -275,13 +622,26 @@ fn unmarshal_l4(
         IpNextHeaderProtocols::Tcp => {
             if let Some(tcp) = TcpPacket::new(payload) {
                 event.tcp = Some(unmarshal_tcp(&tcp)?);
+                // DNS over TCP messages are prefixed by their length.
+                if tcp.get_source() == DNS_PORT || tcp.get_destination() == DNS_PORT {
+                    event.dns = tcp.payload().get(2..).and_then(unmarshal_dns);
+                }
             }
         }
         IpNextHeaderProtocols::Udp => {
             if let Some(udp) = UdpPacket::new(payload) {
                 event.udp = Some(unmarshal_udp(&udp)?);
+                if udp.get_source() == DNS_PORT || udp.get_destination() == DNS_PORT {
+                    event.dns = unmarshal_dns(udp.payload());
+                }
+                if let Some((encap, len)) =
+                    unmarshal_udp_encap(udp.get_destination(), udp.payload())
+                {
+                    unmarshal_encap(event, encap, udp.payload().get(len..).unwrap_or_default())?;
+                }
             }
         }
+        IpNextHeaderProtocols::Sctp => event.sctp = unmarshal_sctp(payload),
         IpNextHeaderProtocols::Icmp => {
             if let Some(icmp) = IcmpPacket::new(payload) {
                 event.icmp = Some(unmarshal_icmp(&icmp)?);
@@ -292,28 +652,187 @@ fn unmarshal_l4(
                 event.icmpv6 = Some(unmarshal_icmpv6(&icmpv6)?);
             }
         }
+        IpNextHeaderProtocols::Gre => {
+            if let Some((encap, len)) = unmarshal_gre(payload) {
+                unmarshal_encap(event, encap, payload.get(len..).unwrap_or_default())?;
+            }
+        }
         _ => (),
     }
 
     Ok(())
 }
 
+/// Ethertype of Ethernet frames carried by tunnels (Transparent Ethernet
+/// Bridging).
+const ETH_P_TEB: u16 = 0x6558;
+/// IANA assigned UDP ports of VXLAN and GENEVE.
+const VXLAN_PORT: u16 = 4789;
+const GENEVE_PORT: u16 = 6081;
+
+/// Parses a VXLAN or GENEVE header, based on the UDP destination port. Returns
+/// it along its length.
+fn unmarshal_udp_encap(dport: u16, data: &[u8]) -> Option<(SkbEncapEvent, usize)> {
+    let hdr = data.get(..8)?;
+    let vni = u32::from_be_bytes([0, hdr[4], hdr[5], hdr[6]]);
+
+    match dport {
+        // The VNI is only valid if the I flag is set.
+        VXLAN_PORT => Some((
+            SkbEncapEvent {
+                r#type: SkbEncapType::Vxlan,
+                id: (hdr[0] & 0x08 != 0).then_some(vni),
+                protocol: ETH_P_TEB,
+            },
+            8,
+        )),
+        // Only version 0 is defined, options follow the fixed header.
+        GENEVE_PORT if hdr[0] >> 6 == 0 => Some((
+            SkbEncapEvent {
+                r#type: SkbEncapType::Geneve,
+                id: Some(vni),
+                protocol: u16::from_be_bytes([hdr[2], hdr[3]]),
+            },
+            8 + (hdr[0] & 0x3f) as usize * 4,
+        )),
+        _ => None,
+    }
+}
+
+/// Parses a GRE header (RFC 2784 and 2890), returning it along its length.
+fn unmarshal_gre(data: &[u8]) -> Option<(SkbEncapEvent, usize)> {
+    let flags = u16::from_be_bytes(data.get(..2)?.try_into().ok()?);
+    // Version 1 is PPTP, not a tunnel encapsulation.
+    if flags & 0x7 != 0 {
+        return None;
+    }
+
+    let protocol = u16::from_be_bytes(data.get(2..4)?.try_into().ok()?);
+    let mut len = 4;
+    // Checksum.
+    if flags & 0x8000 != 0 {
+        len += 4;
+    }
+    let mut id = None;
+    if flags & 0x2000 != 0 {
+        id = Some(u32::from_be_bytes(data.get(len..len + 4)?.try_into().ok()?));
+        len += 4;
+    }
+    // Sequence number.
+    if flags & 0x1000 != 0 {
+        len += 4;
+    }
+
+    Some((
+        SkbEncapEvent {
+            r#type: SkbEncapType::Gre,
+            id,
+            protocol,
+        },
+        len,
+    ))
+}
+
+/// Fills the encapsulation of the event and parses the headers of the
+/// encapsulated packet, `payload` following the encapsulation header.
+fn unmarshal_encap(event: &mut SkbEvent, encap: SkbEncapEvent, payload: &[u8]) -> Result<()> {
+    let mut inner = SkbEvent::default();
+
+    match encap.protocol {
+        ETH_P_TEB => {
+            if let Some(eth) = EthernetPacket::new(payload) {
+                inner.eth = Some(unmarshal_eth(&eth)?);
+                unmarshal_l3(&mut inner, eth.get_ethertype(), eth.payload(), 0)?;
+            }
+        }
+        protocol => {
+            unmarshal_l3(&mut inner, EtherType(protocol), payload, 0)?;
+        }
+    }
+
+    event.encap = Some(encap);
+    if inner.eth.is_some() || inner.arp.is_some() || inner.ip.is_some() {
+        event.inner = Some(SkbInnerEvent {
+            eth: inner.eth,
+            arp: inner.arp,
+            ip: inner.ip,
+            tcp: inner.tcp,
+            udp: inner.udp,
+            sctp: inner.sctp,
+            icmp: inner.icmp,
+            icmpv6: inner.icmpv6,
+        });
+    }
+    Ok(())
+}
+
+/// Net device features reported in events, see `SkbFeaturesEvent`.
+fn is_offload(feature: &str) -> bool {
+    [
+        "checksum",
+        "segmentation",
+        "scatter-gather",
+        "gso",
+        "gro",
+        "lro",
+    ]
+    .iter()
+    .any(|k| feature.contains(k))
+}
+
 #[event_section_factory(FactoryId::Skb)]
 #[derive(Default)]
 pub(crate) struct SkbEventFactory {
     // Should we report the Ethernet header.
     pub(super) report_eth: bool,
+    // Names of the net device features, indexed by their bit. Retrieved on
+    // first use.
+    feature_names: Option<Vec<String>>,
+    // Last decoded features, per ifindex.
+    features: HashMap<u32, SkbFeaturesEvent>,
 }
 
 impl SkbEventFactory {
     pub(crate) fn report_eth(&mut self, on: bool) {
         self.report_eth = on;
     }
+
+    fn unmarshal_features(&mut self, raw_section: &BpfRawSection) -> Result<SkbFeaturesEvent> {
+        let raw = parse_raw_section::<skb_features_event>(raw_section)?;
+
+        // Features rarely change, only decode them when they do.
+        if let Some(cached) = self.features.get(&raw.ifindex) {
+            if cached.raw == raw.features {
+                return Ok(cached.clone());
+            }
+        }
+
+        let names = self.feature_names.get_or_insert_with(|| {
+            helpers::ethtool::feature_names().unwrap_or_else(|e| {
+                warn!("Could not retrieve the net device feature names: {e}");
+                Vec::new()
+            })
+        });
+
+        let event = SkbFeaturesEvent {
+            raw: raw.features,
+            offloads: names
+                .iter()
+                .take(64)
+                .enumerate()
+                .filter(|(bit, name)| raw.features & (1 << bit) != 0 && is_offload(name))
+                .map(|(_, name)| name.clone())
+                .collect(),
+        };
+        self.features.insert(raw.ifindex, event.clone());
+        Ok(event)
+    }
 }
 
 impl RawEventSectionFactory for SkbEventFactory {
     fn create(&mut self, raw_sections: Vec<BpfRawSection>) -> Result<Box<dyn EventSection>> {
         let mut event = SkbEvent::default();
+        let mut tags = Vec::new();
 
         for section in raw_sections.iter() {
             match section.header.data_type as u32 {
@@ -323,10 +842,15 @@ impl RawEventSectionFactory for SkbEventFactory {
                 SECTION_META => event.meta = Some(unmarshal_meta(section)?),
                 SECTION_DATA_REF => event.data_ref = Some(unmarshal_data_ref(section)?),
                 SECTION_GSO => event.gso = Some(unmarshal_gso(section)?),
-                SECTION_PACKET => unmarshal_packet(&mut event, section, self.report_eth)?,
+                SECTION_ORIGIN => event.origin = Some(unmarshal_origin(section)?),
+                SECTION_FEATURES => event.features = Some(self.unmarshal_features(section)?),
+                SECTION_SOFTIRQ => event.softirq = Some(unmarshal_softirq(section)?),
+                SECTION_FRAGS => event.frags = Some(unmarshal_frags(section)?),
+                SECTION_PACKET => tags = unmarshal_packet(&mut event, section, self.report_eth)?,
                 x => bail!("Unknown data type ({x})"),
             }
         }
+        merge_vlan_tags(&mut event.vlan, tags);
 
         Ok(Box::new(event))
     }
@@ -370,33 +894,213 @@ pub(crate) mod benchmark {
         }
     }
 
-    impl RawSectionBuilder for skb_packet_event {
+    impl RawSectionBuilder for skb_origin_event {
         fn build_raw(out: &mut Vec<u8>) -> Result<()> {
             let data = Self {
-                len: 66,
-                capture_len: 66,
-                packet: [
-                    46, 137, 59, 254, 34, 122, 42, 186, 90, 193, 129, 79, 8, 0, 69, 0, 0, 52, 32,
-                    32, 64, 0, 55, 6, 237, 160, 1, 1, 1, 1, 10, 0, 42, 2, 1, 187, 157, 12, 31, 149,
-                    22, 86, 145, 251, 180, 241, 128, 17, 0, 8, 17, 72, 0, 0, 1, 1, 8, 10, 28, 109,
-                    231, 120, 127, 134, 144, 92, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                ],
+                ifindex: 2,
+                iif: 2,
                 ..Default::default()
             };
             build_raw_section(
                 out,
                 FactoryId::Skb as u8,
-                SECTION_PACKET as u8,
+                SECTION_ORIGIN as u8,
                 &mut as_u8_vec(&data),
             );
             Ok(())
         }
     }
+
+    // TCP/IPv4 packet reported in raw packet sections.
+    const PACKET: [u8; 66] = [
+        46, 137, 59, 254, 34, 122, 42, 186, 90, 193, 129, 79, 8, 0, 69, 0, 0, 52, 32, 32, 64, 0,
+        55, 6, 237, 160, 1, 1, 1, 1, 10, 0, 42, 2, 1, 187, 157, 12, 31, 149, 22, 86, 145, 251, 180,
+        241, 128, 17, 0, 8, 17, 72, 0, 0, 1, 1, 8, 10, 28, 109, 231, 120, 127, 134, 144, 92,
+    ];
+
+    // Build a raw packet section, the packet following the fixed size part.
+    fn build_packet_section(out: &mut Vec<u8>, packet: &[u8]) {
+        let data = skb_packet_event {
+            len: packet.len() as u32,
+            capture_len: packet.len() as u32,
+            ..Default::default()
+        };
+        let mut raw = as_u8_vec(&data);
+        raw.extend_from_slice(packet);
+        build_raw_section(out, FactoryId::Skb as u8, SECTION_PACKET as u8, &mut raw);
+    }
+
+    impl RawSectionBuilder for skb_packet_event {
+        fn build_raw(out: &mut Vec<u8>) -> Result<()> {
+            build_packet_section(out, &PACKET);
+            Ok(())
+        }
+
+        fn build_raw_with(out: &mut Vec<u8>, config: &RawEventConfig) -> Result<()> {
+            let mut packet = PACKET;
+            // Derive the source address (10.x.x.x) and port from the flow.
+            let flow = config.flow.to_be_bytes();
+            packet[26] = 10;
+            packet[27..30].copy_from_slice(&flow[1..4]);
+            packet[34..36].copy_from_slice(&(1024 + (config.flow % 64511) as u16).to_be_bytes());
+            build_packet_section(out, &packet);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srh() {
+        // SRH with two segments, followed by an IPv6 header.
+        let mut data = vec![41, 4, 4, 1, 1, 0, 0, 42];
+        data.extend(Ipv6Addr::new(0xfc00, 0, 0, 0, 0, 0, 0, 1).octets());
+        data.extend(Ipv6Addr::new(0xfc00, 0, 0, 0, 0, 0, 0, 2).octets());
+
+        let (srh, next, len) = unmarshal_srh(&data).unwrap();
+        assert_eq!(next, IpNextHeaderProtocols::Ipv6);
+        assert_eq!(len, 40);
+        assert_eq!(srh.segments_left, 1);
+        assert_eq!(srh.tag, 42);
+        assert_eq!(srh.segments, vec!["fc00::1", "fc00::2"]);
+        assert_eq!(srh.active.as_deref(), Some("fc00::2"));
+
+        // Truncated segment list.
+        let (srh, _, _) = unmarshal_srh(&data[..30]).unwrap();
+        assert_eq!(srh.segments, vec!["fc00::1"]);
+        assert_eq!(srh.active, None);
+
+        // Not an SRH.
+        data[2] = 0;
+        assert!(unmarshal_srh(&data).is_none());
+    }
+
+    #[test]
+    fn sctp() {
+        // INIT chunk (20 bytes), followed by a truncated DATA one.
+        let mut data = vec![0x8e, 0x3c, 0x96, 0x0c, 0, 0, 0xc0, 0xde, 0, 0, 0, 0];
+        data.extend([1, 0, 0, 20]);
+        data.extend([0; 16]);
+        data.extend([0, 3, 0, 17, 0, 0]);
+
+        let sctp = unmarshal_sctp(&data).unwrap();
+        assert_eq!((sctp.sport, sctp.dport, sctp.vtag), (36412, 38412, 0xc0de));
+        assert_eq!(sctp.chunks, vec![1, 0]);
+
+        // Invalid chunk length.
+        data[15] = 2;
+        assert_eq!(unmarshal_sctp(&data).unwrap().chunks, vec![1]);
+
+        assert!(unmarshal_sctp(&data[..8]).is_none());
+    }
+
+    #[test]
+    fn dns() {
+        // Response to an AAAA query of www.example.com, with a CNAME and an
+        // AAAA records using compressed names.
+        let mut msg = vec![0x1a, 0x2b, 0x81, 0x80, 0, 1, 0, 2, 0, 0, 0, 0];
+        msg.extend(b"\x03www\x07example\x03com\x00");
+        msg.extend([0, 28, 0, 1]);
+        msg.extend([0xc0, 12, 0, 5, 0, 1, 0, 0, 1, 44, 0, 2, 0xc0, 16]);
+        msg.extend([0xc0, 16, 0, 28, 0, 1, 0, 0, 1, 44, 0, 16]);
+        msg.extend(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1).octets());
+
+        let dns = unmarshal_dns(&msg).unwrap();
+        assert_eq!(
+            (dns.id, dns.response, dns.opcode, dns.rcode),
+            (0x1a2b, true, 0, 0)
+        );
+        assert_eq!(dns.questions.len(), 1);
+        assert_eq!(
+            (dns.questions[0].name.as_str(), dns.questions[0].r#type),
+            ("www.example.com", 28)
+        );
+        let answers = dns
+            .answers
+            .iter()
+            .map(|a| (a.name.as_str(), a.r#type, a.ttl, a.data.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            answers,
+            vec![
+                ("www.example.com", 5, 300, Some("example.com")),
+                ("example.com", 28, 300, Some("2001:db8::1")),
+            ]
+        );
+
+        // Truncated in the data of the last answer, and in its header.
+        let dns = unmarshal_dns(&msg[..(msg.len() - 8)]).unwrap();
+        assert_eq!(dns.answers.len(), 2);
+        assert_eq!(dns.answers[1].data, None);
+        assert_eq!(
+            unmarshal_dns(&msg[..(msg.len() - 20)])
+                .unwrap()
+                .answers
+                .len(),
+            1
+        );
+
+        // Compression loop.
+        let mut msg = vec![0x1a, 0x2b, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        msg.extend([0xc0, 12, 0, 1, 0, 1]);
+        assert!(unmarshal_dns(&msg).unwrap().questions.is_empty());
+
+        assert!(unmarshal_dns(&msg[..8]).is_none());
+    }
+
+    #[test]
+    fn encap() {
+        // VXLAN, VNI 42.
+        let (vxlan, len) = unmarshal_udp_encap(4789, &[8, 0, 0, 0, 0, 0, 42, 0]).unwrap();
+        assert!(matches!(vxlan.r#type, SkbEncapType::Vxlan));
+        assert_eq!((vxlan.id, vxlan.protocol, len), (Some(42), 0x6558, 8));
+        assert!(unmarshal_udp_encap(4789, &[8, 0, 0, 0]).is_none());
+        assert!(unmarshal_udp_encap(53, &[8, 0, 0, 0, 0, 0, 42, 0]).is_none());
+
+        // GENEVE with 8 bytes of options, carrying IPv4.
+        let (geneve, len) =
+            unmarshal_udp_encap(6081, &[2, 0, 0x08, 0x00, 0, 0x01, 0x00, 0]).unwrap();
+        assert!(matches!(geneve.r#type, SkbEncapType::Geneve));
+        assert_eq!((geneve.id, geneve.protocol, len), (Some(256), 0x0800, 16));
+
+        // GRE with a checksum and a key, carrying IPv6.
+        let (gre, len) =
+            unmarshal_gre(&[0xa0, 0, 0x86, 0xdd, 0, 0, 0, 0, 0, 0, 0x12, 0x34]).unwrap();
+        assert!(matches!(gre.r#type, SkbEncapType::Gre));
+        assert_eq!((gre.id, gre.protocol, len), (Some(0x1234), 0x86dd, 12));
+        assert!(unmarshal_gre(&[0xa0, 0, 0x86, 0xdd, 0, 0, 0, 0]).is_none());
+        // PPTP.
+        assert!(unmarshal_gre(&[0x30, 0x81, 0x88, 0x0b]).is_none());
+    }
+
+    #[test]
+    fn vlan_tags() {
+        // 802.1ad tag (id 10), followed by an 802.1Q one (id 100) and IPv4.
+        let (outer, next) = unmarshal_vlan_tag(0x88a8, &[0x00, 0x0a, 0x81, 0x00]).unwrap();
+        assert_eq!(next, EtherTypes::Vlan);
+        let (inner, next) = unmarshal_vlan_tag(0x8100, &[0x30, 0x64, 0x08, 0x00]).unwrap();
+        assert_eq!(next, EtherTypes::Ipv4);
+        assert_eq!((inner.pcp, inner.dei, inner.vid), (1, true, 100));
+        assert!(unmarshal_vlan_tag(0x8100, &[0x30, 0x64, 0x08]).is_none());
+
+        let mut vlan = None;
+        merge_vlan_tags(&mut vlan, vec![outer.clone(), inner.clone()]);
+        let vlan = vlan.unwrap();
+        assert_eq!((vlan.tpid, vlan.vid), (Some(0x88a8), 10));
+        assert_eq!(vlan.inner.len(), 1);
+
+        // Accelerated tag, all tags in the packet are inner ones.
+        let mut vlan = Some(SkbVlanEvent {
+            vid: 42,
+            acceleration: true,
+            ..Default::default()
+        });
+        merge_vlan_tags(&mut vlan, vec![outer, inner]);
+        let vlan = vlan.unwrap();
+        assert_eq!((vlan.tpid, vlan.vid), (None, 42));
+        assert_eq!(vlan.inner.len(), 2);
+    }
 }
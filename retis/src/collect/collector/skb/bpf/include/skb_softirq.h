@@ -0,0 +1,38 @@
+#ifndef __MODULE_SKB_SOFTIRQ__
+#define __MODULE_SKB_SOFTIRQ__
+
+#include <vmlinux.h>
+
+#include <common_defs.h>
+
+/* Keep in sync with include/linux/interrupt.h */
+#define NET_RX_SOFTIRQ	3
+
+/* State of the NET_RX softirq on a CPU, tracked when requested
+ * (--skb-sections softirq).
+ */
+struct skb_softirq_state {
+	/* Time the softirq was raised, if pending. */
+	u64 raised;
+	/* Time the softirq started running, if running. */
+	u64 entry;
+	/* Time between the softirq being raised and it running. */
+	u64 delay;
+} __binding;
+
+/* Please keep in sync with its Rust counterpart in skb.rs. */
+struct {
+	__uint(type, BPF_MAP_TYPE_PERCPU_ARRAY);
+	__uint(max_entries, 1);
+	__type(key, u32);
+	__type(value, struct skb_softirq_state);
+} skb_softirq_map SEC(".maps");
+
+static __always_inline struct skb_softirq_state *skb_softirq_state(void)
+{
+	u32 key = 0;
+
+	return bpf_map_lookup_elem(&skb_softirq_map, &key);
+}
+
+#endif /* __MODULE_SKB_SOFTIRQ__ */
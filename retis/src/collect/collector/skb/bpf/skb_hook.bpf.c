@@ -4,6 +4,7 @@
 
 #include <common.h>
 #include <if_vlan.h>
+#include <skb_softirq.h>
 
 #define BIT(x) (1 << (x))
 
@@ -20,6 +21,10 @@ enum skb_sections {
 	SECTION_META,
 	SECTION_DATA_REF,
 	SECTION_GSO,
+	SECTION_ORIGIN,
+	SECTION_FEATURES,
+	SECTION_SOFTIRQ,
+	SECTION_FRAGS,
 } __binding;
 
 /* Skb hook configuration. A map is used to set the config from
@@ -27,6 +32,8 @@ enum skb_sections {
  */
 struct skb_config {
 	u64 sections;
+	/* Maximum number of bytes of the packets to report. */
+	u32 capture_len;
 } __binding;
 struct {
 	__uint(type, BPF_MAP_TYPE_ARRAY);
@@ -53,6 +60,13 @@ struct skb_meta_event {
 	u32 csum;
 	u8 csum_level;
 	u32 priority;
+	u8 pkt_type;
+	u16 protocol;
+	u32 headroom;
+	u32 tailroom;
+	u32 truesize;
+	u32 tail;
+	u32 end;
 } __binding;
 struct skb_data_ref_event {
 	u8 nohdr;
@@ -68,11 +82,33 @@ struct skb_gso_event {
 	u32 gso_segs;
 	u32 gso_type;
 } __binding;
+struct skb_features_event {
+	u64 features;
+	u32 ifindex;
+} __binding;
+struct skb_softirq_event {
+	u64 delay;
+	u64 running;
+} __binding;
+/* Maximum number of fragment sizes reported. */
+BINDING_DEF(SKB_FRAGS_MAX, 8)
+struct skb_frags_event {
+	u32 sizes[SKB_FRAGS_MAX];
+	u8 nr_frags;
+	u8 frag_list;
+} __binding;
+struct skb_origin_event {
+	u32 ifindex;
+	u32 iif;
+	u8 pkt_type;
+	u8 has_sk;
+} __binding;
+/* Maximum packet capture length, bounded by the room left in events. */
+BINDING_DEF(PACKET_CAPTURE_MAX, 512)
+/* Followed by capture_len bytes of packet data, the section size varies. */
 struct skb_packet_event {
 	u32 len;
 	u32 capture_len;
-#define PACKET_CAPTURE_SIZE	255
-	u8 packet[PACKET_CAPTURE_SIZE];
 	u8 fake_eth;
 } __binding;
 
@@ -125,7 +161,7 @@ static __always_inline u16 skb_protocol(struct sk_buff *skb)
 }
 
 static __always_inline int process_packet(struct retis_raw_event *event,
-					  struct sk_buff *skb)
+					  struct sk_buff *skb, u32 capture_len)
 {
 	/* Use int instead of the underlying (smaller) unsigned type to allow
 	 * signed arithmetic operations.
@@ -136,6 +172,10 @@ static __always_inline int process_packet(struct retis_raw_event *event,
 	u16 network;
 	u32 len;
 
+	/* Keep the verifier happy, this is checked in userspace. */
+	if (capture_len > PACKET_CAPTURE_MAX)
+		capture_len = PACKET_CAPTURE_MAX;
+
 	head = BPF_CORE_READ(skb, head);
 	headroom = BPF_CORE_READ(skb, data) - head;
 
@@ -153,19 +193,19 @@ static __always_inline int process_packet(struct retis_raw_event *event,
 		long mac_offset, size;
 
 		mac_offset = mac - headroom;
-		size = MIN(linear_len - mac_offset, PACKET_CAPTURE_SIZE);
-		if (size <= 0)
+		size = MIN(linear_len - mac_offset, capture_len);
+		if (size <= 0 || size > PACKET_CAPTURE_MAX)
 			return 0;
 
 		e = get_event_section(event, COLLECTOR_SKB, SECTION_PACKET,
-				      sizeof(*e));
+				      sizeof(*e) + size);
 		if (!e)
 			return 0;
 
 		e->len = len - mac_offset;
 		e->capture_len = size;
 		e->fake_eth = 0;
-		bpf_probe_read_kernel(e->packet, size, head + mac);
+		bpf_probe_read_kernel(e + 1, size, head + mac);
 	/* Valid network offset with an unset or invalid mac offset: we can fake
 	 * the eth header.
 	 */
@@ -182,25 +222,24 @@ static __always_inline int process_packet(struct retis_raw_event *event,
 
 		network_offset = network - headroom;
 		size = MIN(linear_len - network_offset,
-			   PACKET_CAPTURE_SIZE - sizeof(struct ethhdr));
-		if (size <= 0)
+			   (long)capture_len - (long)sizeof(struct ethhdr));
+		if (size <= 0 || size > PACKET_CAPTURE_MAX - sizeof(struct ethhdr))
 			return 0;
 
 		e = get_event_section(event, COLLECTOR_SKB, SECTION_PACKET,
-				      sizeof(*e));
+				      sizeof(*e) + sizeof(*eth) + size);
 		if (!e)
 			return 0;
 
 		/* Fake eth header */
-		eth = (struct ethhdr *)e->packet;
+		eth = (struct ethhdr *)(e + 1);
 		__builtin_memset(eth, 0, sizeof(*eth));
 		eth->h_proto = etype;
 
 		e->len = len - network_offset + sizeof(*eth);
 		e->capture_len = size + sizeof(struct ethhdr);
 		e->fake_eth = 1;
-		bpf_probe_read_kernel(e->packet + sizeof(*eth), size,
-				      head + network);
+		bpf_probe_read_kernel(eth + 1, size, head + network);
 	/* Can't guess any useful packet offset */
 	} else {
 		return 0;
@@ -209,6 +248,67 @@ static __always_inline int process_packet(struct retis_raw_event *event,
 	return 0;
 }
 
+/* Retrieve the skb state the packet origin (local, received or forwarded) is
+ * derived from in userspace. The section is only reported when the origin can
+ * be guessed.
+ */
+static __always_inline int process_origin(struct retis_raw_event *event,
+					  struct sk_buff *skb,
+					  struct net_device *dev)
+{
+	struct skb_origin_event *e;
+	u8 pkt_type, has_sk;
+	u32 iif;
+
+	iif = BPF_CORE_READ(skb, skb_iif);
+	pkt_type = (u8)BPF_CORE_READ_BITFIELD_PROBED(skb, pkt_type);
+	has_sk = BPF_CORE_READ(skb, sk) ? 1 : 0;
+
+	/* Packets not processed by the stack yet (no input interface) and not
+	 * bound to a socket can be either local or received ones, unless their
+	 * packet type tells otherwise. See include/uapi/linux/if_packet.h for
+	 * the packet type values.
+	 */
+	if (!iif && !has_sk) {
+		switch (pkt_type) {
+		case 1: /* PACKET_BROADCAST */
+		case 2: /* PACKET_MULTICAST */
+		case 3: /* PACKET_OTHERHOST */
+		case 5: /* PACKET_LOOPBACK */
+			break;
+		default:
+			return 0;
+		}
+	}
+
+	e = get_event_section(event, COLLECTOR_SKB, SECTION_ORIGIN, sizeof(*e));
+	if (!e)
+		return 0;
+
+	e->ifindex = dev ? BPF_CORE_READ(dev, ifindex) : 0;
+	e->iif = iif;
+	e->pkt_type = pkt_type;
+	e->has_sk = has_sk;
+
+	return 0;
+}
+
+/* skb_frag_t is a struct skb_frag in recent kernels, a struct bio_vec in older
+ * ones.
+ */
+static __always_inline u32 skb_frag_size(void *frags, int i)
+{
+	if (bpf_core_type_exists(struct skb_frag)) {
+		struct skb_frag *frag =
+			frags + i * bpf_core_type_size(struct skb_frag);
+		return BPF_CORE_READ(frag, len);
+	} else {
+		struct bio_vec *frag =
+			frags + i * bpf_core_type_size(struct bio_vec);
+		return BPF_CORE_READ(frag, bv_len);
+	}
+}
+
 /* Must be called with a valid skb pointer */
 static __always_inline int process_skb(struct retis_raw_event *event,
 				       struct sk_buff *skb)
@@ -225,7 +325,10 @@ static __always_inline int process_skb(struct retis_raw_event *event,
 	dev = BPF_CORE_READ(skb, dev);
 
 	/* Always retrieve the raw packet */
-	process_packet(event, skb);
+	process_packet(event, skb, cfg->capture_len);
+
+	/* Always retrieve what's needed to guess the packet origin, if any */
+	process_origin(event, skb, dev);
 
 	if (cfg->sections & BIT(SECTION_DEV) && dev) {
 		int ifindex = BPF_CORE_READ(dev, ifindex);
@@ -243,6 +346,17 @@ static __always_inline int process_skb(struct retis_raw_event *event,
 		}
 	}
 
+	if (cfg->sections & BIT(SECTION_FEATURES) && dev) {
+		struct skb_features_event *e =
+			get_event_section(event, COLLECTOR_SKB,
+					  SECTION_FEATURES, sizeof(*e));
+		if (!e)
+			return 0;
+
+		e->features = BPF_CORE_READ(dev, features);
+		e->ifindex = BPF_CORE_READ(dev, ifindex);
+	}
+
 	if (cfg->sections & BIT(SECTION_NS)) {
 		struct skb_netns_event *e;
 		u32 netns;
@@ -271,6 +385,7 @@ static __always_inline int process_skb(struct retis_raw_event *event,
 
 skip_netns:
 	if (cfg->sections & BIT(SECTION_META)) {
+		unsigned char *head = BPF_CORE_READ(skb, head);
 		struct skb_meta_event *e =
 			get_event_section(event, COLLECTOR_SKB,
 					  SECTION_META, sizeof(*e));
@@ -284,6 +399,18 @@ skip_netns:
 		e->csum = BPF_CORE_READ(skb, csum);
 		e->csum_level = (u8)BPF_CORE_READ_BITFIELD_PROBED(skb, csum_level);
 		e->priority = BPF_CORE_READ(skb, priority);
+		e->pkt_type = (u8)BPF_CORE_READ_BITFIELD_PROBED(skb, pkt_type);
+		e->protocol = bpf_ntohs(BPF_CORE_READ(skb, protocol));
+
+		/* Buffer geometry. tail and end are offsets from head (as
+		 * sk_buff_data_t is an offset on 64-bit arches). As in
+		 * skb_tailroom(), there is no tailroom for non-linear skbs.
+		 */
+		e->headroom = BPF_CORE_READ(skb, data) - head;
+		e->tail = BPF_CORE_READ(skb, tail);
+		e->end = BPF_CORE_READ(skb, end);
+		e->tailroom = e->data_len ? 0 : e->end - e->tail;
+		e->truesize = BPF_CORE_READ(skb, truesize);
 	}
 
 	if (cfg->sections & BIT(SECTION_DATA_REF)) {
@@ -350,6 +477,51 @@ skip_netns:
 	}
 
 skip_gso:
+	if (cfg->sections & BIT(SECTION_FRAGS)) {
+		struct skb_shared_info *shinfo;
+		struct skb_frags_event *e;
+		void *frags;
+		u8 nr_frags;
+		int i;
+
+		shinfo = (void *)(BPF_CORE_READ(skb, head) + BPF_CORE_READ(skb, end));
+		nr_frags = BPF_CORE_READ(shinfo, nr_frags);
+		/* Only report non-linear skbs. */
+		if (!nr_frags && !BPF_CORE_READ(shinfo, frag_list))
+			goto skip_frags;
+
+		e = get_event_section(event, COLLECTOR_SKB, SECTION_FRAGS,
+				      sizeof(*e));
+		if (!e)
+			return 0;
+
+		e->nr_frags = nr_frags;
+		e->frag_list = !!BPF_CORE_READ(shinfo, frag_list);
+
+		frags = (void *)shinfo +
+			bpf_core_field_offset(struct skb_shared_info, frags);
+		for (i = 0; i < SKB_FRAGS_MAX && i < nr_frags; i++)
+			e->sizes[i] = skb_frag_size(frags, i);
+	}
+
+skip_frags:
+	if (cfg->sections & BIT(SECTION_SOFTIRQ)) {
+		struct skb_softirq_state *state = skb_softirq_state();
+		struct skb_softirq_event *e;
+
+		/* Only packets processed in the NET_RX softirq are reported. */
+		if (!state || !state->entry)
+			return 0;
+
+		e = get_event_section(event, COLLECTOR_SKB, SECTION_SOFTIRQ,
+				      sizeof(*e));
+		if (!e)
+			return 0;
+
+		e->delay = state->delay;
+		e->running = bpf_ktime_get_ns() - state->entry;
+	}
+
 	return 0;
 }
 
@@ -0,0 +1,22 @@
+#include <common.h>
+#include <skb_softirq.h>
+
+/* Hook for tp:irq:softirq_entry. */
+DEFINE_HOOK_RAW(
+	struct skb_softirq_state *state;
+
+	if (retis_get_param(ctx, 0, u32) != NET_RX_SOFTIRQ)
+		return 0;
+
+	state = skb_softirq_state();
+	if (!state)
+		return 0;
+
+	state->entry = ctx->timestamp;
+	state->delay = state->raised ? ctx->timestamp - state->raised : 0;
+	state->raised = 0;
+
+	return 0;
+)
+
+char __license[] SEC("license") = "GPL";
@@ -0,0 +1,18 @@
+#include <common.h>
+#include <skb_softirq.h>
+
+/* Hook for tp:irq:softirq_exit. */
+DEFINE_HOOK_RAW(
+	struct skb_softirq_state *state;
+
+	if (retis_get_param(ctx, 0, u32) != NET_RX_SOFTIRQ)
+		return 0;
+
+	state = skb_softirq_state();
+	if (state)
+		state->entry = 0;
+
+	return 0;
+)
+
+char __license[] SEC("license") = "GPL";
@@ -0,0 +1,21 @@
+#include <common.h>
+#include <skb_softirq.h>
+
+/* Hook for tp:irq:softirq_raise. The NET_RX softirq is raised when NAPI is
+ * scheduled, usually from the device hardware IRQ.
+ */
+DEFINE_HOOK_RAW(
+	struct skb_softirq_state *state;
+
+	if (retis_get_param(ctx, 0, u32) != NET_RX_SOFTIRQ)
+		return 0;
+
+	state = skb_softirq_state();
+	/* Only track the first raise while the softirq is pending. */
+	if (state && !state->raised)
+		state->raised = ctx->timestamp;
+
+	return 0;
+)
+
+char __license[] SEC("license") = "GPL";
@@ -13,3 +13,12 @@ pub(crate) use bpf::SkbEventFactory;
 mod skb_hook {
     include!("bpf/.out/skb_hook.rs");
 }
+mod skb_softirq_raise_hook {
+    include!("bpf/.out/skb_softirq_raise.rs");
+}
+mod skb_softirq_entry_hook {
+    include!("bpf/.out/skb_softirq_entry.rs");
+}
+mod skb_softirq_exit_hook {
+    include!("bpf/.out/skb_softirq_exit.rs");
+}
@@ -9,13 +9,15 @@ use clap::{arg, builder::PossibleValuesParser, Parser};
 use libbpf_rs::MapCore;
 use log::warn;
 
-use super::skb_hook;
+use super::{skb_hook, skb_softirq_entry_hook, skb_softirq_exit_hook, skb_softirq_raise_hook};
 use crate::{
-    bindings::skb_hook_uapi::*,
+    bindings::{skb_hook_uapi::*, skb_softirq_uapi::skb_softirq_state},
     collect::{cli::Collect, Collector},
     core::{
         events::*,
-        probe::{Hook, ProbeBuilderManager},
+        inspect::fields::{FieldReq, OptionalField},
+        kernel::Symbol,
+        probe::{Hook, Probe, ProbeBuilderManager, ProbeOption},
     },
 };
 
@@ -24,7 +26,8 @@ pub(crate) struct SkbCollectorArgs {
     #[arg(
         long,
         value_parser=PossibleValuesParser::new([
-            "all", "eth", "vlan", "dev", "ns", "meta", "dataref", "gso",
+            "all", "eth", "vlan", "dev", "ns", "meta", "dataref", "gso", "frags", "features",
+            "softirq",
             // Below values are deprecated.
             "arp", "ip", "tcp", "udp", "icmp", "packet",
         ]),
@@ -37,22 +40,40 @@ Supported values:
 - vlan:    include 802.1Q VLAN information (id, pcp, dei, acceleration)
 - dev:     include network device information.
 - ns:      include network namespace information.
-- meta:    include skb metadata information (len, data_len, hash, etc).
+- meta:    include skb metadata information (len, data_len, hash, pkt_type, etc).
 - dataref: include data & refcnt information (cloned, users, data refs, etc).
 - gso:     include generic segmentation offload (GSO) information.
-- all:     all of the above.
+- frags:   include, for non-linear skbs, the number of fragments and their sizes (up to 8) and
+           whether a fragment list is used.
+- features: include the offload features of the net device (checksum, TSO, GRO, etc).
+- softirq: include, for packets processed in the NET_RX softirq, the time it waited to run
+           after being raised (e.g. by the device IRQ) and the time it was running for. This
+           helps telling CPU contention apart from network delays. Adds probes on the irq
+           softirq tracepoints.
+- all:     all of the above, but softirq.
 
 The following values are now always retrieved and their use is deprecated:
 packet, arp, ip, tcp, udp, icmp."
     )]
     pub(crate) skb_sections: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "BYTES",
+        default_value_t = 255,
+        value_parser = clap::value_parser!(u32).range(14..=PACKET_CAPTURE_MAX as i64),
+        help = "Maximum number of bytes of the packets to report, starting at the Ethernet header.
+Bigger values allow looking deeper in packets (e.g. encapsulated ones) but leave less room in events
+for other data, smaller ones reduce the overhead. At most 512 bytes can be reported."
+    )]
+    pub(crate) skb_capture_len: u32,
 }
 
 #[derive(Default)]
 pub(crate) struct SkbCollector {
-    // Used to keep a reference to our internal config map.
-    #[allow(dead_code)]
-    config_map: Option<libbpf_rs::MapHandle>,
+    // Internal maps (config and softirq state), kept open for the duration of
+    // the collection as the probes reuse their fds.
+    _maps: Vec<libbpf_rs::MapHandle>,
 }
 
 impl Collector for SkbCollector {
@@ -64,6 +85,13 @@ impl Collector for SkbCollector {
         Some(vec!["struct sk_buff *"])
     }
 
+    fn optional_fields(&self) -> Vec<OptionalField> {
+        vec![OptionalField::new(
+            "gso.flags",
+            FieldReq::Member("skb_shared_info", "flags"),
+        )]
+    }
+
     fn init(
         &mut self,
         args: &Collect,
@@ -76,13 +104,16 @@ impl Collector for SkbCollector {
 
         for category in args.collector_args.skb.skb_sections.iter() {
             match category.as_str() {
-                "all" => sections |= !0_u64,
+                "all" => sections |= !(1 << SECTION_SOFTIRQ),
                 "vlan" => sections |= 1 << SECTION_VLAN,
                 "dev" => sections |= 1 << SECTION_DEV,
                 "ns" => sections |= 1 << SECTION_NS,
                 "meta" => sections |= 1 << SECTION_META,
                 "dataref" => sections |= 1 << SECTION_DATA_REF,
                 "gso" => sections |= 1 << SECTION_GSO,
+                "frags" => sections |= 1 << SECTION_FRAGS,
+                "features" => sections |= 1 << SECTION_FEATURES,
+                "softirq" => sections |= 1 << SECTION_SOFTIRQ,
                 "eth" => (),
                 "packet" | "arp" | "ip" | "tcp" | "udp" | "icmp" => {
                     warn!(
@@ -98,20 +129,47 @@ impl Collector for SkbCollector {
         let config_map = Self::config_map()?;
 
         // Set the config.
-        let cfg = skb_config { sections };
+        let cfg = skb_config {
+            sections,
+            capture_len: args.collector_args.skb.skb_capture_len,
+        };
         let cfg = unsafe { plain::as_bytes(&cfg) };
 
         let key = 0_u32.to_ne_bytes();
         config_map.update(&key, cfg, libbpf_rs::MapFlags::empty())?;
 
+        let mut hook = Hook::from(skb_hook::DATA);
+        hook.reuse_map("skb_config_map", config_map.as_fd().as_raw_fd())?;
+
+        // Track the state of the NET_RX softirq on each CPU.
+        if sections & (1 << SECTION_SOFTIRQ) != 0 {
+            let softirq_map = Self::softirq_map()?;
+            let softirq_fd = softirq_map.as_fd().as_raw_fd();
+
+            hook.reuse_map("skb_softirq_map", softirq_fd)?;
+
+            for (tp, data) in [
+                ("irq:softirq_raise", skb_softirq_raise_hook::DATA),
+                ("irq:softirq_entry", skb_softirq_entry_hook::DATA),
+                ("irq:softirq_exit", skb_softirq_exit_hook::DATA),
+            ] {
+                let mut probe = Probe::raw_tracepoint(Symbol::from_name(tp)?)?;
+                probe.set_option(ProbeOption::NoGenericHook)?;
+                probe.add_hook(
+                    Hook::from(data)
+                        .reuse_map("skb_softirq_map", softirq_fd)?
+                        .to_owned(),
+                )?;
+                probes.register_probe(probe)?;
+            }
+
+            self._maps.push(softirq_map);
+        }
+
         // Register our generic skb hook.
-        probes.register_kernel_hook(
-            Hook::from(skb_hook::DATA)
-                .reuse_map("skb_config_map", config_map.as_fd().as_raw_fd())?
-                .to_owned(),
-        )?;
+        probes.register_kernel_hook(hook)?;
 
-        self.config_map = Some(config_map);
+        self._maps.push(config_map);
         Ok(())
     }
 }
@@ -134,4 +192,23 @@ impl SkbCollector {
         )
         .or_else(|e| bail!("Could not create the skb config map: {}", e))
     }
+
+    fn softirq_map() -> Result<libbpf_rs::MapHandle> {
+        let opts = libbpf_sys::bpf_map_create_opts {
+            sz: mem::size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
+            ..Default::default()
+        };
+
+        // Please keep in sync with its BPF counterpart in
+        // bpf/include/skb_softirq.h
+        libbpf_rs::MapHandle::create(
+            libbpf_rs::MapType::PercpuArray,
+            Some("skb_softirq_map"),
+            mem::size_of::<u32>() as u32,
+            mem::size_of::<skb_softirq_state>() as u32,
+            1,
+            &opts,
+        )
+        .or_else(|e| bail!("Could not create the skb softirq map: {}", e))
+    }
 }
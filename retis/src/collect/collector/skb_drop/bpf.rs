@@ -131,3 +131,29 @@ impl SkbDropEventFactory {
         )
     }
 }
+
+#[cfg(feature = "benchmark")]
+pub(crate) mod benchmark {
+    use anyhow::Result;
+
+    use crate::{
+        benchmark::helpers::*, bindings::skb_drop_hook_uapi::skb_drop_event,
+        core::events::FactoryId,
+    };
+
+    impl RawSectionBuilder for skb_drop_event {
+        fn build_raw(out: &mut Vec<u8>) -> Result<()> {
+            let data = Self::default();
+            build_raw_section(out, FactoryId::SkbDrop as u8, 0, &mut as_u8_vec(&data));
+            Ok(())
+        }
+
+        fn build_raw_with(out: &mut Vec<u8>, config: &RawEventConfig) -> Result<()> {
+            let data = Self {
+                drop_reason: config.drop_reason.unwrap_or(-1),
+            };
+            build_raw_section(out, FactoryId::SkbDrop as u8, 0, &mut as_u8_vec(&data));
+            Ok(())
+        }
+    }
+}
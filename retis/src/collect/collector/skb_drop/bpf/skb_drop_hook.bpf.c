@@ -10,22 +10,33 @@ struct skb_drop_event {
 
 DEFINE_HOOK(F_AND, RETIS_ALL_FILTERS,
 	struct skb_drop_event *e;
+	s32 reason = -1;
 
 	/* Check if the kernel knows about skb drop reasons, and if so check we
 	 * can retrieve it. This should be the common case. In case the kernel
 	 * doesn't know skb drop reasons, this hook will generate fake events
 	 * and will only be attached to specific hooks.
 	 */
-	if (bpf_core_type_exists(enum skb_drop_reason) &&
-	    !retis_arg_valid(ctx, skb_drop_reason))
-		return 0;
+	if (bpf_core_type_exists(enum skb_drop_reason)) {
+		if (!retis_arg_valid(ctx, skb_drop_reason))
+			return 0;
+
+		reason = retis_get_skb_drop_reason(ctx);
+
+		/* Some kernels report consumed skbs through skb:kfree_skb and
+		 * the bulk free functions are used for both consumed and
+		 * dropped skbs; those are not drops.
+		 */
+		if (bpf_core_enum_value_exists(enum skb_drop_reason, SKB_CONSUMED) &&
+		    reason == bpf_core_enum_value(enum skb_drop_reason, SKB_CONSUMED))
+			return 0;
+	}
 
 	e = get_event_section(event, COLLECTOR_SKB_DROP, 1, sizeof(*e));
 	if (!e)
 		return 0;
 
-	e->drop_reason = bpf_core_type_exists(enum skb_drop_reason) ?
-		retis_get_skb_drop_reason(ctx) : -1;
+	e->drop_reason = reason;
 
 	return 0;
 )
@@ -1,19 +1,28 @@
 use std::sync::Arc;
 
 use anyhow::{bail, Result};
-use log::warn;
+use log::debug;
 
 use super::skb_drop_hook;
 use crate::{
     collect::{cli::Collect, Collector},
     core::{
         events::*,
-        inspect::{inspector, kernel_version::KernelVersionReq},
+        inspect::{
+            fields::{FieldReq, OptionalField},
+            inspector,
+            kernel_version::KernelVersionReq,
+        },
         kernel::Symbol,
         probe::{Hook, Probe, ProbeBuilderManager},
     },
 };
 
+/// Functions freeing lists of skbs at once, with a drop reason. Each skb is
+/// still reported by skb:kfree_skb, probing those adds the context of the bulk
+/// free (e.g. GSO segments or a frag list being dropped).
+const BULK_FREE_FUNCS: &[&str] = &["kfree_skb_list_reason"];
+
 pub(crate) struct SkbDropCollector {
     reasons_available: bool,
 }
@@ -33,6 +42,13 @@ impl Collector for SkbDropCollector {
         ])
     }
 
+    fn optional_fields(&self) -> Vec<OptionalField> {
+        vec![OptionalField::new(
+            "drop_reason",
+            FieldReq::Type("skb_drop_reason"),
+        )]
+    }
+
     fn can_run(&mut self, _: &Collect) -> Result<()> {
         let inspector = inspector()?;
 
@@ -52,12 +68,11 @@ impl Collector for SkbDropCollector {
 
                 // Skb drop reasons were introduced in kernel v5.17 and are not
                 // a build config option; if not found in such case bail out. On
-                // older kernel, still allow the collector to run, with a
-                // warning.
+                // older kernel, still allow the collector to run; the drop
+                // reason is then reported as a missing field.
                 if KernelVersionReq::parse(">= 5.17")?.matches(kver) {
                     bail!("Could not retrieve skb drop reasons from the kernel");
                 } else {
-                    warn!("This kernel doesn't provide skb drop reasons");
                     self.reasons_available = false;
                 }
             }
@@ -89,6 +104,17 @@ impl Collector for SkbDropCollector {
             bail!("Could not attach to skb:kfree_skb: {}", e);
         }
 
+        // Bulk free functions are only probed when drop reasons are available,
+        // as the hook is then generic. They might not exist on older kernels.
+        if self.reasons_available {
+            for func in BULK_FREE_FUNCS.iter() {
+                match Symbol::from_name(func) {
+                    Ok(symbol) => probes.register_probe(Probe::kprobe(symbol)?)?,
+                    Err(e) => debug!("Not probing {func}: {e}"),
+                }
+            }
+        }
+
         Ok(())
     }
 }
@@ -69,5 +69,15 @@ pub(crate) mod benchmark {
             build_raw_section(out, FactoryId::SkbTracking as u8, 0, &mut as_u8_vec(&data));
             Ok(())
         }
+
+        fn build_raw_with(out: &mut Vec<u8>, config: &RawEventConfig) -> Result<()> {
+            let data = Self {
+                orig_head: config.orig_head,
+                timestamp: config.tracking_timestamp,
+                skb: config.orig_head,
+            };
+            build_raw_section(out, FactoryId::SkbTracking as u8, 0, &mut as_u8_vec(&data));
+            Ok(())
+        }
     }
 }
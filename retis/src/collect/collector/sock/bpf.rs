@@ -0,0 +1,44 @@
+//! Rust<>BPF types definitions for the sock module.
+//! Please keep this file in sync with its BPF counterpart in bpf/sock_hook.bpf.c
+
+use anyhow::Result;
+
+use crate::{
+    bindings::sock_hook_uapi::sock_event,
+    core::events::{
+        parse_single_raw_section, BpfRawSection, EventSectionFactory, FactoryId,
+        RawEventSectionFactory,
+    },
+    event_section_factory,
+    events::*,
+};
+
+#[event_section_factory(FactoryId::Sock)]
+#[derive(Default)]
+pub(crate) struct SockEventFactory {}
+
+impl RawEventSectionFactory for SockEventFactory {
+    fn create(&mut self, raw_sections: Vec<BpfRawSection>) -> Result<Box<dyn EventSection>> {
+        let raw = parse_single_raw_section::<sock_event>(&raw_sections)?;
+
+        Ok(Box::new(SockEvent {
+            cookie: match raw.cookie {
+                0 => None,
+                cookie => Some(cookie),
+            },
+            protocol: raw.protocol,
+            rmem_alloc: raw.rmem_alloc,
+            rcvbuf: raw.rcvbuf,
+            rcvq_len: raw.rcvq_len,
+            tcp_unread: match raw.is_tcp {
+                0 => None,
+                _ => Some(raw.tcp_unread),
+            },
+            wmem_alloc: raw.wmem_alloc,
+            wmem_queued: raw.wmem_queued,
+            sndbuf: raw.sndbuf,
+            forward_alloc: raw.forward_alloc,
+            memory_pressure: raw.memory_pressure != 0,
+        }))
+    }
+}
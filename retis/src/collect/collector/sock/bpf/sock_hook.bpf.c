@@ -0,0 +1,81 @@
+#include <vmlinux.h>
+#include <bpf/bpf_core_read.h>
+
+#include <common.h>
+
+/* Socket memory accounting and limits. Please keep in sync with its Rust
+ * counterpart in collect::collector::sock.
+ */
+struct sock_event {
+	u64 cookie;
+	/* Receive queue memory and limit. */
+	s32 rmem_alloc;
+	s32 rcvbuf;
+	/* Packets in the receive queue. */
+	u32 rcvq_len;
+	/* TCP only: bytes received but not read yet by the application. */
+	u32 tcp_unread;
+	/* Transmit memory (committed and queued) and limit. */
+	s32 wmem_alloc;
+	s32 wmem_queued;
+	s32 sndbuf;
+	/* Memory pre-allocated to the socket, can be negative. */
+	s32 forward_alloc;
+	u16 protocol;
+	/* Whether the socket protocol is under memory pressure. */
+	u8 memory_pressure;
+	u8 is_tcp;
+} __binding;
+
+DEFINE_HOOK_RAW(
+	unsigned long *pressure, val = 0;
+	struct sock_event *e;
+	struct sock *sk;
+
+	/* Some events related to socket memory have no skb (e.g. a sender
+	 * waiting for memory), filters can't apply to those. Otherwise only
+	 * report events matching the filters.
+	 */
+	if (retis_get_sk_buff(ctx) && !RETIS_TRACKABLE(ctx->filters_ret))
+		return 0;
+
+	sk = retis_get_sock(ctx);
+	if (!sk)
+		return 0;
+
+	e = get_event_section(event, COLLECTOR_SOCK, 1, sizeof(*e));
+	if (!e)
+		return 0;
+
+	/* The cookie is only set once requested (e.g. by ss or a BPF
+	 * program); 0 means it wasn't.
+	 */
+	e->cookie = BPF_CORE_READ(sk, __sk_common.skc_cookie.counter);
+	e->rmem_alloc = BPF_CORE_READ(sk, sk_backlog.rmem_alloc.counter);
+	e->rcvbuf = BPF_CORE_READ(sk, sk_rcvbuf);
+	e->wmem_alloc = BPF_CORE_READ(sk, sk_wmem_alloc.refs.counter);
+	e->wmem_queued = BPF_CORE_READ(sk, sk_wmem_queued);
+	e->sndbuf = BPF_CORE_READ(sk, sk_sndbuf);
+	e->forward_alloc = BPF_CORE_READ(sk, sk_forward_alloc);
+	e->protocol = BPF_CORE_READ_BITFIELD_PROBED(sk, sk_protocol);
+	e->rcvq_len = BPF_CORE_READ(sk, sk_receive_queue.qlen);
+
+	if (e->protocol == IPPROTO_TCP &&
+	    BPF_CORE_READ_BITFIELD_PROBED(sk, sk_type) == SOCK_STREAM) {
+		struct tcp_sock *tp = (struct tcp_sock *)sk;
+
+		e->tcp_unread = BPF_CORE_READ(tp, rcv_nxt) -
+				BPF_CORE_READ(tp, copied_seq);
+		e->is_tcp = 1;
+	}
+
+	/* Only some protocols (e.g. TCP) track memory pressure. */
+	pressure = BPF_CORE_READ(sk, __sk_common.skc_prot, memory_pressure);
+	if (pressure)
+		bpf_probe_read_kernel(&val, sizeof(val), pressure);
+	e->memory_pressure = !!val;
+
+	return 0;
+)
+
+char __license[] SEC("license") = "GPL";
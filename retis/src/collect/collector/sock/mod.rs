@@ -0,0 +1,16 @@
+//! # Sock module
+//!
+//! Provides support for retrieving socket memory usage and limits, to report
+//! socket buffer exhaustion and memory pressure.
+
+// Re-export sock.rs
+#[allow(clippy::module_inception)]
+pub(crate) mod sock;
+pub(crate) use sock::*;
+
+pub(crate) mod bpf;
+pub(crate) use bpf::SockEventFactory;
+
+mod sock_hook {
+    include!("bpf/.out/sock_hook.rs");
+}
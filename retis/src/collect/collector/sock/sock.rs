@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use log::debug;
+
+use super::sock_hook;
+use crate::{
+    collect::{cli::Collect, Collector},
+    core::{
+        events::*,
+        inspect::inspector,
+        kernel::Symbol,
+        probe::{Hook, Probe, ProbeBuilderManager},
+    },
+};
+
+/// Tracepoints hit when a socket runs out of memory: the receive queue being
+/// full and memory allocations exceeding the socket or protocol limits.
+const SOCK_MEM_TRACEPOINTS: &[&str] = &["sock:sock_rcvqueue_full", "sock:sock_exceed_buf_limit"];
+
+/// Functions delivering packets to sockets, reporting the receive queue
+/// occupancy as packets are queued. Those are static functions and might be
+/// inlined, in which case they are not probed.
+const SOCK_DELIVERY_FUNCTIONS: &[&str] =
+    &["tcp_queue_rcv", "udp_queue_rcv_skb", "udpv6_queue_rcv_skb"];
+
+#[derive(Default)]
+pub(crate) struct SockCollector {}
+
+impl Collector for SockCollector {
+    fn new() -> Result<Self> {
+        Ok(Self::default())
+    }
+
+    fn known_kernel_types(&self) -> Option<Vec<&'static str>> {
+        Some(vec!["struct sock *"])
+    }
+
+    fn can_run(&mut self, _: &Collect) -> Result<()> {
+        // Make sure the targets we probe are available on this kernel.
+        for target in SOCK_MEM_TRACEPOINTS
+            .iter()
+            .chain(&["sk_stream_wait_memory"])
+        {
+            if let Err(e) = Symbol::from_name(target) {
+                bail!("Could not find {target}: {e}");
+            }
+        }
+        Ok(())
+    }
+
+    fn init(
+        &mut self,
+        _: &Collect,
+        probes: &mut ProbeBuilderManager,
+        _: Arc<RetisEventsFactory>,
+    ) -> Result<()> {
+        // Register our generic socket hook, reporting the socket memory usage
+        // on all probes having a socket parameter.
+        probes.register_kernel_hook(Hook::from(sock_hook::DATA))?;
+
+        for tp in SOCK_MEM_TRACEPOINTS.iter() {
+            if let Err(e) = probes.register_probe(Probe::raw_tracepoint(Symbol::from_name(tp)?)?) {
+                bail!("Could not attach to {tp}: {e}");
+            }
+        }
+
+        // Senders blocked waiting for the send buffer to have room.
+        probes.register_probe(Probe::kprobe(Symbol::from_name("sk_stream_wait_memory")?)?)?;
+
+        // Packets being delivered to sockets.
+        for func in SOCK_DELIVERY_FUNCTIONS.iter() {
+            match Symbol::from_name(func) {
+                Ok(symbol) => probes.register_probe(Probe::kprobe(symbol)?)?,
+                Err(_) => debug!("{func} can't be probed (inlined?), not probing it"),
+            }
+        }
+
+        // Packets dropped on a socket, e.g. because its receive buffer is
+        // full. The receiving socket is only reported in the tracepoint
+        // starting with Linux v6.11.
+        let symbol = Symbol::from_name("skb:kfree_skb")?;
+        match inspector()?
+            .kernel
+            .parameter_offset(&symbol, "struct sock *")
+        {
+            Ok(Some(_)) => probes.register_probe(Probe::raw_tracepoint(symbol)?)?,
+            _ => debug!("skb:kfree_skb does not report the receiving socket, not probing it"),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+//! Rust<>BPF types definitions for the tc module.
+//! Please keep this file in sync with its BPF counterpart in bpf/tc_hook.bpf.c
+
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::CString,
+    fs,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::ffi::OsStrExt,
+    },
+    path::Path,
+};
+
+use anyhow::Result;
+use log::debug;
+
+use crate::{
+    bindings::tc_hook_uapi::*,
+    core::events::{
+        parse_single_raw_section, BpfRawSection, EventSectionFactory, FactoryId,
+        RawEventSectionFactory,
+    },
+    event_section_factory,
+    events::*,
+    raw_to_string, raw_to_string_opt,
+};
+
+#[event_section_factory(FactoryId::Tc)]
+#[derive(Default)]
+pub(crate) struct TcEventFactory {
+    /// Paths of the programs pinned in the BPF filesystems, indexed by
+    /// program id.
+    pinned: HashMap<u32, String>,
+    /// Ids of programs found not to be pinned, to avoid looking them up for
+    /// every event.
+    not_pinned: HashSet<u32>,
+}
+
+impl RawEventSectionFactory for TcEventFactory {
+    fn create(&mut self, raw_sections: Vec<BpfRawSection>) -> Result<Box<dyn EventSection>> {
+        let raw = parse_single_raw_section::<tc_event>(&raw_sections)?;
+
+        Ok(Box::new(TcEvent {
+            prog_id: raw.prog_id,
+            prog_name: raw_to_string!(&raw.prog_name)?,
+            pinned: self.pinned_path(raw.prog_id),
+            filter_name: raw_to_string_opt!(&raw.filter_name)?,
+            prio: raw.prio,
+            action: match raw.exit {
+                0 => None,
+                _ => Some(tc_action(raw.action)),
+            },
+        }))
+    }
+}
+
+impl TcEventFactory {
+    /// Get the path a program is pinned at. The BPF filesystems are only
+    /// walked when a program is seen for the first time.
+    fn pinned_path(&mut self, id: u32) -> Option<String> {
+        if id == 0 || self.not_pinned.contains(&id) {
+            return None;
+        }
+
+        if !self.pinned.contains_key(&id) {
+            self.pinned.clear();
+            bpffs_mounts()
+                .iter()
+                .for_each(|mount| walk_bpffs(Path::new(mount), &mut self.pinned));
+
+            if !self.pinned.contains_key(&id) {
+                self.not_pinned.insert(id);
+            }
+        }
+
+        self.pinned.get(&id).cloned()
+    }
+}
+
+/// Mount points of the BPF filesystems.
+fn bpffs_mounts() -> Vec<String> {
+    match fs::read_to_string("/proc/self/mounts") {
+        Ok(mounts) => mounts
+            .lines()
+            .filter_map(|line| {
+                let fields = line.split_whitespace().collect::<Vec<_>>();
+                match fields.get(2) {
+                    Some(&"bpf") => Some(fields[1].to_string()),
+                    _ => None,
+                }
+            })
+            .collect(),
+        Err(e) => {
+            debug!("Could not read the mount points: {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// Walk a BPF filesystem and record the pinned programs.
+fn walk_bpffs(dir: &Path, pinned: &mut HashMap<u32, String>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            debug!("Could not read {}: {e}", dir.display());
+            return;
+        }
+    };
+
+    entries.flatten().for_each(|entry| {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(r#type) if r#type.is_dir() => walk_bpffs(&path, pinned),
+            Ok(r#type) if r#type.is_file() => {
+                if let Some(id) = pinned_prog_id(&path) {
+                    pinned.entry(id).or_insert(path.display().to_string());
+                }
+            }
+            _ => (),
+        }
+    });
+}
+
+/// Get the id of a pinned program, or None if the object is not a program.
+fn pinned_prog_id(path: &Path) -> Option<u32> {
+    let cpath = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let fd = unsafe { libbpf_sys::bpf_obj_get(cpath.as_ptr()) };
+    if fd < 0 {
+        return None;
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // The object type is not known in advance; only programs have a prog_id
+    // entry in their fdinfo.
+    fs::read_to_string(format!("/proc/self/fdinfo/{}", fd.as_raw_fd()))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("prog_id:"))
+        .and_then(|id| id.trim().parse().ok())
+}
+
+/// Convert an action returned by a tc filter to its name.
+/// See include/uapi/linux/pkt_cls.h.
+fn tc_action(action: i32) -> String {
+    match action {
+        -1 => "unspec".to_string(),
+        0 => "ok".to_string(),
+        1 => "reclassify".to_string(),
+        2 => "shot".to_string(),
+        3 => "pipe".to_string(),
+        4 => "stolen".to_string(),
+        5 => "queued".to_string(),
+        6 => "repeat".to_string(),
+        7 => "redirect".to_string(),
+        8 => "trap".to_string(),
+        x => format!("unknown ({x})"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn action() {
+        assert_eq!(tc_action(-1), "unspec");
+        assert_eq!(tc_action(2), "shot");
+        assert_eq!(tc_action(7), "redirect");
+        assert_eq!(tc_action(42), "unknown (42)");
+    }
+}
@@ -0,0 +1,89 @@
+#include <vmlinux.h>
+#include <bpf/bpf_core_read.h>
+
+#include <common.h>
+
+BINDING_DEF(TC_PROG_NAME_LEN, 16)
+BINDING_DEF(TC_FILTER_NAME_LEN, 64)
+
+/* Types of the cls_bpf module, which are not part of vmlinux.h. Keep in sync
+ * with net/sched/cls_bpf.c; relocations are done using the module BTF.
+ */
+struct cls_bpf_head___retis {
+	struct list_head plist;
+} __attribute__((preserve_access_index));
+
+struct cls_bpf_prog___retis {
+	struct bpf_prog *filter;
+	struct list_head link;
+	const char *bpf_name;
+} __attribute__((preserve_access_index));
+
+struct tc_event {
+	u32 prog_id;
+	u32 prio;
+	s32 action;
+	u8 exit;
+	char prog_name[TC_PROG_NAME_LEN];
+	char filter_name[TC_FILTER_NAME_LEN];
+} __binding;
+
+/* Probes are added on cls_bpf_classify(skb, tp, res), on entry and on return
+ * to report the action returned by the filter.
+ */
+DEFINE_HOOK_RAW(
+	struct cls_bpf_prog___retis *prog;
+	struct cls_bpf_head___retis *head;
+	const struct tcf_proto *tp;
+	struct bpf_prog_aux *aux;
+	struct list_head *first;
+	struct tc_event *e;
+
+	if (!RETIS_TRACKABLE(ctx->filters_ret))
+		return -ENOMSG;
+
+	tp = retis_get_param(ctx, 1, const struct tcf_proto *);
+	if (!tp)
+		return -ENOMSG;
+
+	/* Only the first program of the filter is reported, which is by far
+	 * the most common setup (e.g. direct-action filters).
+	 */
+	head = (struct cls_bpf_head___retis *)BPF_CORE_READ(tp, root);
+	if (!head)
+		return -ENOMSG;
+
+	first = BPF_CORE_READ(head, plist.next);
+	if (!first || first == (void *)head +
+			bpf_core_field_offset(struct cls_bpf_head___retis, plist))
+		return -ENOMSG;
+
+	prog = (void *)first -
+		bpf_core_field_offset(struct cls_bpf_prog___retis, link);
+
+	e = get_event_zsection(event, COLLECTOR_TC, 1, sizeof(*e));
+	if (!e)
+		return 0;
+
+	aux = BPF_CORE_READ(prog, filter, aux);
+	if (aux) {
+		e->prog_id = BPF_CORE_READ(aux, id);
+		bpf_probe_read_kernel_str(e->prog_name, sizeof(e->prog_name),
+					  aux->name);
+	}
+
+	bpf_probe_read_kernel_str(e->filter_name, sizeof(e->filter_name),
+				  BPF_CORE_READ(prog, bpf_name));
+
+	/* The priority is stored in the upper 16 bits. */
+	e->prio = BPF_CORE_READ(tp, prio) >> 16;
+
+	if (ctx->probe_type == KERNEL_PROBE_KRETPROBE) {
+		e->exit = 1;
+		e->action = (s32)ctx->regs.ret;
+	}
+
+	return 0;
+)
+
+char __license[] SEC("license") = "GPL";
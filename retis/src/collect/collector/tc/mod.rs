@@ -0,0 +1,16 @@
+//! # Tc module
+//!
+//! Provides support for reporting packets entering and leaving tc BPF
+//! programs (cls_bpf filters), along with the action they returned.
+
+// Re-export tc.rs
+#[allow(clippy::module_inception)]
+pub(crate) mod tc;
+pub(crate) use tc::*;
+
+pub(crate) mod bpf;
+pub(crate) use bpf::TcEventFactory;
+
+mod tc_hook {
+    include!("bpf/.out/tc_hook.rs");
+}
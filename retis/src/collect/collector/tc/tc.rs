@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+
+use super::tc_hook;
+use crate::{
+    collect::{cli::Collect, Collector},
+    core::{
+        events::*,
+        kernel::Symbol,
+        probe::{Hook, Probe, ProbeBuilderManager},
+    },
+};
+
+/// Function running the BPF programs of a cls_bpf filter.
+const CLS_BPF_CLASSIFY: &str = "cls_bpf_classify";
+
+#[derive(Default)]
+pub(crate) struct TcCollector {}
+
+impl Collector for TcCollector {
+    fn new() -> Result<Self> {
+        Ok(Self::default())
+    }
+
+    fn can_run(&mut self, _: &Collect) -> Result<()> {
+        // cls_bpf is usually built as a module, only loaded once a tc BPF
+        // filter was added.
+        if let Err(e) = Symbol::from_name(CLS_BPF_CLASSIFY) {
+            bail!("Could not find {CLS_BPF_CLASSIFY} (is the cls_bpf module loaded?): {e}");
+        }
+        Ok(())
+    }
+
+    fn init(
+        &mut self,
+        _: &Collect,
+        probes: &mut ProbeBuilderManager,
+        _: Arc<RetisEventsFactory>,
+    ) -> Result<()> {
+        let hook = Hook::from(tc_hook::DATA);
+
+        // Report packets entering the programs, and leaving them to get the
+        // returned action.
+        let mut probe = Probe::kprobe(Symbol::from_name(CLS_BPF_CLASSIFY)?)?;
+        probe.add_hook(hook.clone())?;
+        probes.register_probe(probe)?;
+
+        let mut probe = Probe::kretprobe(Symbol::from_name(CLS_BPF_CLASSIFY)?)?;
+        probe.add_hook(hook)?;
+        probes.register_probe(probe)?;
+
+        Ok(())
+    }
+}
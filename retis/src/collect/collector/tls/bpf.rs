@@ -0,0 +1,105 @@
+//! Rust<>BPF types definitions for the tls module.
+//! Please keep this file in sync with its BPF counterpart in bpf/tls_hook.bpf.c
+
+use anyhow::{bail, Result};
+
+use crate::{
+    bindings::tls_hook_uapi::*,
+    core::events::{
+        parse_single_raw_section, BpfRawSection, EventSectionFactory, FactoryId,
+        RawEventSectionFactory,
+    },
+    event_section_factory,
+    events::*,
+    helpers::net::parse_inet_addr,
+};
+
+#[event_section_factory(FactoryId::Tls)]
+#[derive(Default)]
+pub(crate) struct TlsEventFactory {}
+
+impl RawEventSectionFactory for TlsEventFactory {
+    fn create(&mut self, raw_sections: Vec<BpfRawSection>) -> Result<Box<dyn EventSection>> {
+        let raw = parse_single_raw_section::<tls_event>(&raw_sections)?;
+
+        let (dir, error) = match raw.dir as u32 {
+            TLS_TX => ("tx", None),
+            TLS_RX => ("rx", (raw.ret < 0).then_some(raw.ret)),
+            x => bail!("Unknown tls event direction ({x})"),
+        };
+
+        Ok(Box::new(TlsEvent {
+            dir: dir.to_string(),
+            mode: mode(raw.conf),
+            version: version(raw.version),
+            cipher: cipher(raw.cipher),
+            seq: seq(&raw.rec_seq, error.is_none()),
+            saddr: parse_inet_addr(raw.family, &raw.saddr)?,
+            sport: raw.sport,
+            daddr: parse_inet_addr(raw.family, &raw.daddr)?,
+            dport: raw.dport,
+            error,
+        }))
+    }
+}
+
+/// Retrieve the sequence number of the processed record. The one held by the
+/// context is advanced once a record was successfully processed.
+fn seq(rec_seq: &[u8; 8], processed: bool) -> u64 {
+    let seq = u64::from_be_bytes(*rec_seq);
+    match processed {
+        true => seq.wrapping_sub(1),
+        false => seq,
+    }
+}
+
+/// Convert a TLS context configuration to its mode. See net/tls/tls.h.
+fn mode(conf: u8) -> String {
+    match conf {
+        0 => "base".to_string(),
+        1 => "sw".to_string(),
+        2 => "hw".to_string(),
+        3 => "hw_record".to_string(),
+        x => format!("unknown ({x})"),
+    }
+}
+
+/// See include/uapi/linux/tls.h.
+fn version(version: u16) -> String {
+    match version {
+        0x0303 => "1.2".to_string(),
+        0x0304 => "1.3".to_string(),
+        x => format!("{x:#06x}"),
+    }
+}
+
+/// See include/uapi/linux/tls.h.
+fn cipher(cipher: u16) -> String {
+    match cipher {
+        51 => "aes-gcm-128",
+        52 => "aes-gcm-256",
+        53 => "aes-ccm-128",
+        54 => "chacha20-poly1305",
+        55 => "sm4-gcm",
+        56 => "sm4-ccm",
+        57 => "aria-gcm-128",
+        58 => "aria-gcm-256",
+        x => return format!("unknown ({x})"),
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        assert_eq!(seq(&[0, 0, 0, 0, 0, 0, 1, 2], true), 257);
+        assert_eq!(seq(&[0, 0, 0, 0, 0, 0, 1, 2], false), 258);
+        assert_eq!(mode(2), "hw");
+        assert_eq!(version(0x0304), "1.3");
+        assert_eq!(cipher(52), "aes-gcm-256");
+        assert_eq!(cipher(42), "unknown (42)");
+    }
+}
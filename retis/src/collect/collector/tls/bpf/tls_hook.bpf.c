@@ -0,0 +1,149 @@
+#include <vmlinux.h>
+#include <bpf/bpf_core_read.h>
+#include <bpf/bpf_endian.h>
+
+#include <common.h>
+
+#define AF_INET		2
+#define AF_INET6	10
+
+/* TLS hook configuration. Holds the address of the probed functions, to know
+ * where the hook is running.
+ *
+ * tx_ksym: tls_push_sg, pushing an encrypted record to TCP.
+ * rx_ksym: tls_rx_one_record or decrypt_skb_update (older kernels), on return,
+ *	    decrypting a received record.
+ */
+struct tls_config {
+	u64 tx_ksym;
+	u64 rx_ksym;
+} __binding;
+struct {
+	__uint(type, BPF_MAP_TYPE_ARRAY);
+	__uint(max_entries, 1);
+	__type(key, u32);
+	__type(value, struct tls_config);
+} tls_config_map SEC(".maps");
+
+enum tls_event_dir {
+	TLS_TX = 1,
+	TLS_RX,
+} __binding;
+
+struct tls_event {
+	u8 dir;
+	/* tx_conf or rx_conf of the TLS context: TLS_BASE, TLS_SW, TLS_HW or
+	 * TLS_HW_RECORD.
+	 */
+	u8 conf;
+	u16 version;
+	u16 cipher;
+	/* Record sequence number held by the context, in network order. It is
+	 * advanced once a record was successfully processed, and thus holds the
+	 * one of the next record at this point.
+	 */
+	u8 rec_seq[8];
+	/* Socket the records are sent or received on. */
+	u16 family;
+	u16 sport;
+	u16 dport;
+	u8 saddr[16];
+	u8 daddr[16];
+	/* Value returned by the rx decryption function. */
+	s32 ret;
+} __binding;
+
+/* Older kernels hold pointers in struct cipher_context. */
+struct cipher_context___old {
+	char *iv;
+	char *rec_seq;
+} __attribute__((preserve_access_index));
+
+static __always_inline void tls_fill_sock(struct tls_event *e, struct sock *sk)
+{
+	e->family = BPF_CORE_READ(sk, __sk_common.skc_family);
+	e->sport = BPF_CORE_READ(sk, __sk_common.skc_num);
+	e->dport = bpf_ntohs(BPF_CORE_READ(sk, __sk_common.skc_dport));
+
+	if (e->family == AF_INET) {
+		bpf_core_read(e->saddr, sizeof(u32),
+			      &sk->__sk_common.skc_rcv_saddr);
+		bpf_core_read(e->daddr, sizeof(u32),
+			      &sk->__sk_common.skc_daddr);
+	} else if (e->family == AF_INET6) {
+		bpf_core_read(e->saddr, sizeof(e->saddr),
+			      &sk->__sk_common.skc_v6_rcv_saddr);
+		bpf_core_read(e->daddr, sizeof(e->daddr),
+			      &sk->__sk_common.skc_v6_daddr);
+	}
+}
+
+static __always_inline void tls_fill_rec_seq(struct tls_event *e,
+					     struct cipher_context *cctx)
+{
+	struct cipher_context___old *old = (void *)cctx;
+	char *rec_seq;
+
+	if (bpf_core_field_size(cctx->iv) != sizeof(void *)) {
+		bpf_core_read(e->rec_seq, sizeof(e->rec_seq), &cctx->rec_seq);
+		return;
+	}
+
+	rec_seq = BPF_CORE_READ(old, rec_seq);
+	if (rec_seq)
+		bpf_probe_read_kernel(e->rec_seq, sizeof(e->rec_seq), rec_seq);
+}
+
+DEFINE_HOOK_RAW(
+	struct tls_context *tls_ctx;
+	struct tls_config *cfg;
+	struct tls_event *e;
+	struct sock *sk;
+	u32 key = 0;
+
+	/* Records are not always tied to a packet, filters can't apply to
+	 * those. Otherwise only report events matching the filters.
+	 */
+	if (retis_get_sk_buff(ctx) && !RETIS_TRACKABLE(ctx->filters_ret))
+		return -ENOMSG;
+
+	cfg = bpf_map_lookup_elem(&tls_config_map, &key);
+	if (!cfg)
+		return 0;
+
+	/* Both tls_push_sg and the rx functions take the socket first. */
+	sk = retis_get_param(ctx, 0, struct sock *);
+	if (!sk)
+		return -ENOMSG;
+
+	tls_ctx = BPF_CORE_READ((struct inet_connection_sock *)sk,
+				icsk_ulp_data);
+	if (!tls_ctx)
+		return -ENOMSG;
+
+	e = get_event_zsection(event, COLLECTOR_TLS, 1, sizeof(*e));
+	if (!e)
+		return 0;
+
+	if (ctx->ksym == cfg->tx_ksym) {
+		e->dir = TLS_TX;
+		e->conf = BPF_CORE_READ_BITFIELD_PROBED(tls_ctx, tx_conf);
+		e->version = BPF_CORE_READ(tls_ctx, crypto_send.info.version);
+		e->cipher = BPF_CORE_READ(tls_ctx, crypto_send.info.cipher_type);
+		tls_fill_rec_seq(e, &tls_ctx->tx);
+	} else if (ctx->ksym == cfg->rx_ksym) {
+		e->dir = TLS_RX;
+		e->conf = BPF_CORE_READ_BITFIELD_PROBED(tls_ctx, rx_conf);
+		e->version = BPF_CORE_READ(tls_ctx, crypto_recv.info.version);
+		e->cipher = BPF_CORE_READ(tls_ctx, crypto_recv.info.cipher_type);
+		tls_fill_rec_seq(e, &tls_ctx->rx);
+		e->ret = (s32)ctx->regs.ret;
+	} else {
+		return -ENOMSG;
+	}
+
+	tls_fill_sock(e, sk);
+	return 0;
+)
+
+char __license[] SEC("license") = "GPL";
@@ -0,0 +1,16 @@
+//! # TLS module
+//!
+//! Provides support for reporting the records sent and received on kTLS
+//! sockets, including decryption failures.
+
+// Re-export tls.rs
+#[allow(clippy::module_inception)]
+pub(crate) mod tls;
+pub(crate) use tls::*;
+
+pub(crate) mod bpf;
+pub(crate) use bpf::TlsEventFactory;
+
+mod tls_hook {
+    include!("bpf/.out/tls_hook.rs");
+}
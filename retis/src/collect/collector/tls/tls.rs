@@ -0,0 +1,116 @@
+use std::{
+    mem,
+    os::fd::{AsFd, AsRawFd},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, bail, Result};
+use libbpf_rs::MapCore;
+
+use super::tls_hook;
+use crate::{
+    bindings::tls_hook_uapi::tls_config,
+    collect::{cli::Collect, Collector},
+    core::{
+        events::*,
+        kernel::Symbol,
+        probe::{Hook, Probe, ProbeBuilderManager},
+    },
+};
+
+/// Function pushing encrypted records to TCP, for both the software and the
+/// device offload implementations.
+const TLS_TX_FUNC: &str = "tls_push_sg";
+
+/// Functions decrypting a received record; the first one found is used.
+/// decrypt_skb_update was replaced by tls_rx_one_record in Linux v6.0.
+const TLS_RX_FUNCS: &[&str] = &["tls_rx_one_record", "decrypt_skb_update"];
+
+#[derive(Default)]
+pub(crate) struct TlsCollector {
+    // Used to keep a reference to our internal config map.
+    #[allow(dead_code)]
+    config_map: Option<libbpf_rs::MapHandle>,
+}
+
+impl TlsCollector {
+    fn config_map() -> Result<libbpf_rs::MapHandle> {
+        let opts = libbpf_sys::bpf_map_create_opts {
+            sz: mem::size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
+            ..Default::default()
+        };
+
+        // Please keep in sync with its BPF counterpart in
+        // bpf/tls_hook.bpf.c
+        libbpf_rs::MapHandle::create(
+            libbpf_rs::MapType::Array,
+            Some("tls_config_map"),
+            mem::size_of::<u32>() as u32,
+            mem::size_of::<tls_config>() as u32,
+            1,
+            &opts,
+        )
+        .or_else(|e| bail!("Could not create the tls config map: {}", e))
+    }
+
+    fn rx_symbol() -> Result<Symbol> {
+        TLS_RX_FUNCS
+            .iter()
+            .find_map(|func| Symbol::from_name(func).ok())
+            .ok_or_else(|| anyhow!("Could not find any of {}", TLS_RX_FUNCS.join(", ")))
+    }
+}
+
+impl Collector for TlsCollector {
+    fn new() -> Result<Self> {
+        Ok(Self::default())
+    }
+
+    fn can_run(&mut self, _: &Collect) -> Result<()> {
+        if let Err(e) = Symbol::from_name(TLS_TX_FUNC) {
+            bail!("Could not find {TLS_TX_FUNC} (is the tls module loaded?): {e}");
+        }
+        Self::rx_symbol()?;
+        Ok(())
+    }
+
+    fn init(
+        &mut self,
+        _: &Collect,
+        probes: &mut ProbeBuilderManager,
+        _: Arc<RetisEventsFactory>,
+    ) -> Result<()> {
+        let tx = Symbol::from_name(TLS_TX_FUNC)?;
+        let rx = Self::rx_symbol()?;
+
+        let cfg = tls_config {
+            tx_ksym: tx.addr()?,
+            rx_ksym: rx.addr()?,
+        };
+
+        let config_map = Self::config_map()?;
+        let key = 0_u32.to_ne_bytes();
+        config_map.update(
+            &key,
+            unsafe { plain::as_bytes(&cfg) },
+            libbpf_rs::MapFlags::empty(),
+        )?;
+
+        let hook = Hook::from(tls_hook::DATA)
+            .reuse_map("tls_config_map", config_map.as_fd().as_raw_fd())?
+            .to_owned();
+
+        let mut probe = Probe::kprobe(tx)?;
+        probe.add_hook(hook.clone())?;
+        probes.register_probe(probe)?;
+
+        // Received records are probed on return, to report decryption
+        // failures.
+        let mut probe = Probe::kretprobe(rx)?;
+        probe.add_hook(hook)?;
+        probes.register_probe(probe)?;
+
+        self.config_map = Some(config_map);
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+//! # Configuration reload
+//!
+//! Options not changing what is collected (output format, clock
+//! synchronization and checkpoint intervals) can be set in a configuration
+//! file given with `--config`, overriding the command line ones. The file is
+//! read again when the collection receives SIGHUP or a `reload` request on its
+//! control socket (see `retis reload`), so those options can be adjusted
+//! without restarting the collection. Options not set in the file fall back to
+//! their command line value.
+//!
+//! The file uses YAML, e.g.:
+//!
+//! ```yaml
+//! format: single-line
+//! utc: true
+//! clock-sync-interval: 30s
+//! checkpoint-interval: 1m
+//! ```
+
+use std::{
+    fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Result};
+use clap::ValueEnum;
+use log::{info, warn};
+use serde::Deserialize;
+use signal_hook::{consts::SIGHUP, iterator::Signals};
+
+use super::cli::Collect;
+use crate::{cli::CliDisplayFormat, events::*, helpers::time::parse_duration};
+
+/// Content of the configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct ConfigFile {
+    format: Option<String>,
+    format_template: Option<String>,
+    utc: Option<bool>,
+    clock_sync_interval: Option<ConfigDuration>,
+    /// 0 disables checkpoints.
+    checkpoint_interval: Option<ConfigDuration>,
+}
+
+/// Duration in the configuration file, either a number of seconds or a string
+/// using the command line format (e.g. `1m30s`).
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ConfigDuration {
+    Secs(u64),
+    Str(String),
+}
+
+impl ConfigDuration {
+    fn to_duration(&self) -> Result<Duration> {
+        match self {
+            ConfigDuration::Secs(secs) => Ok(Duration::from_secs(*secs)),
+            ConfigDuration::Str(duration) => parse_duration(duration),
+        }
+    }
+}
+
+/// Collection options which can be changed while collecting.
+#[derive(Debug)]
+pub(crate) struct ReloadableOptions {
+    pub(crate) format: CliDisplayFormat,
+    pub(crate) format_template: Option<EventTemplate>,
+    pub(crate) utc: bool,
+    pub(crate) clock_sync_interval: Duration,
+    pub(crate) checkpoint_interval: Option<Duration>,
+}
+
+impl ReloadableOptions {
+    /// Get the options from the command line, overridden by the ones set in
+    /// the configuration file, if any.
+    pub(crate) fn new(collect: &Collect) -> Result<Self> {
+        let mut options = Self {
+            format: collect.format,
+            format_template: collect.format_template.clone(),
+            utc: collect.utc,
+            clock_sync_interval: collect.clock_sync_interval,
+            checkpoint_interval: collect.checkpoint_interval,
+        };
+
+        if let Some(path) = &collect.config {
+            options.apply(&read_config(path)?)?;
+        }
+        Ok(options)
+    }
+
+    fn apply(&mut self, config: &ConfigFile) -> Result<()> {
+        if let Some(format) = &config.format {
+            self.format = CliDisplayFormat::from_str(format, true)
+                .map_err(|e| anyhow!("Invalid format '{format}': {e}"))?;
+            // An explicit format replaces a template given on the command
+            // line.
+            self.format_template = None;
+        }
+        if let Some(template) = &config.format_template {
+            self.format_template = Some(template.parse()?);
+        }
+        if let Some(utc) = config.utc {
+            self.utc = utc;
+        }
+        if let Some(interval) = &config.clock_sync_interval {
+            self.clock_sync_interval = interval.to_duration()?;
+        }
+        if let Some(interval) = &config.checkpoint_interval {
+            let interval = interval.to_duration()?;
+            self.checkpoint_interval = (!interval.is_zero()).then_some(interval);
+        }
+        Ok(())
+    }
+}
+
+fn read_config(path: &Path) -> Result<ConfigFile> {
+    let content =
+        fs::read_to_string(path).map_err(|e| anyhow!("Could not read {}: {e}", path.display()))?;
+    if content.trim().is_empty() {
+        return Ok(ConfigFile::default());
+    }
+
+    serde_yaml::from_str(&content).map_err(|e| anyhow!("Invalid configuration file: {e}"))
+}
+
+/// Pending reload request, set on SIGHUP or by the control socket and handled
+/// by the collection loop.
+#[derive(Clone, Default)]
+pub(crate) struct ReloadRequest(Arc<AtomicBool>);
+
+impl ReloadRequest {
+    pub(crate) fn request(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Check if a reload was requested, clearing the request.
+    pub(crate) fn take(&self) -> bool {
+        self.0.swap(false, Ordering::Relaxed)
+    }
+}
+
+/// Turns SIGHUP into reload requests.
+pub(crate) struct ReloadListener {
+    handle: signal_hook::iterator::Handle,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl ReloadListener {
+    pub(crate) fn new(reload: ReloadRequest) -> Result<Self> {
+        let mut sigs = Signals::new([SIGHUP])?;
+        let handle = sigs.handle();
+        let thread = thread::spawn(move || {
+            for _ in sigs.forever() {
+                info!("Received SIGHUP, reloading the configuration");
+                reload.request();
+            }
+        });
+
+        Ok(Self {
+            handle,
+            thread: Some(thread),
+        })
+    }
+
+    pub(crate) fn stop(&mut self) -> Result<()> {
+        self.handle.close();
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                bail!("Could not join the reload thread");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reload the options, keeping the current ones if the configuration file
+/// can't be used.
+pub(crate) fn reload(collect: &Collect, options: &mut ReloadableOptions) -> bool {
+    match ReloadableOptions::new(collect) {
+        Ok(new) => {
+            info!("Configuration reloaded");
+            *options = new;
+            true
+        }
+        Err(e) => {
+            warn!("Could not reload the configuration: {e}");
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_file() {
+        let mut options = ReloadableOptions {
+            format: CliDisplayFormat::MultiLine,
+            format_template: Some("{common.timestamp}".parse().unwrap()),
+            utc: false,
+            clock_sync_interval: Duration::from_secs(60),
+            checkpoint_interval: Some(Duration::from_secs(5)),
+        };
+
+        let config: ConfigFile =
+            serde_yaml::from_str("format: single-line\nutc: true\ncheckpoint-interval: 0\n")
+                .unwrap();
+        options.apply(&config).unwrap();
+        assert_eq!(options.format, CliDisplayFormat::SingleLine);
+        assert!(options.format_template.is_none());
+        assert!(options.utc);
+        assert_eq!(options.clock_sync_interval, Duration::from_secs(60));
+        assert_eq!(options.checkpoint_interval, None);
+
+        let config: ConfigFile =
+            serde_yaml::from_str("clock-sync-interval: 30\ncheckpoint-interval: 1m30s\n").unwrap();
+        options.apply(&config).unwrap();
+        assert_eq!(options.clock_sync_interval, Duration::from_secs(30));
+        assert_eq!(options.checkpoint_interval, Some(Duration::from_secs(90)));
+
+        let config: ConfigFile = serde_yaml::from_str("checkpoint-interval: 10 parsecs\n").unwrap();
+        assert!(options.apply(&config).is_err());
+
+        let config: ConfigFile = serde_yaml::from_str("format: foo\n").unwrap();
+        assert!(options.apply(&config).is_err());
+        assert!(serde_yaml::from_str::<ConfigFile>("mem-limit: 10\n").is_err());
+    }
+}
@@ -0,0 +1,280 @@
+//! # Control
+//!
+//! Control socket of a running collection, used to change its filters or
+//! reload its configuration without restarting it. The socket is created at
+//! `/run/retis/control.<pid>` and accepts one request per connection, as a
+//! single line; a single line is sent back, either `ok` or `error: <reason>`.
+//! The `retis filter` and `retis reload` commands send the requests.
+//!
+//! Supported requests:
+//! - `filter-meta <filter>`: replace the meta filter. An empty filter removes
+//!   it.
+//! - `reload`: read the configuration file (`--config`) again.
+//!
+//! Packet filters are compiled into the probes when those are loaded and can't
+//! be changed while collecting.
+//!
+//! The socket is only accessible to its owner (root).
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, ErrorKind, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    process, thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Result};
+use clap::{Parser, Subcommand};
+use log::{info, warn};
+
+use super::{
+    config::ReloadRequest,
+    explain::Explain,
+    marker::{collect_file, running_collections, MARKER_DIR},
+};
+use crate::{
+    cli::*,
+    core::filters::meta::filter::{set_meta_filter, FilterMeta},
+    helpers::signals::Running,
+};
+
+/// Control socket of a collection.
+fn control_socket(pid: u32) -> PathBuf {
+    PathBuf::from(format!("{MARKER_DIR}/control.{pid}"))
+}
+
+/// Listens for requests on the control socket of the current collection.
+pub(crate) struct ControlListener {
+    pid: u32,
+    run: Running,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl ControlListener {
+    /// Create the control socket and start handling requests. `meta_map` is
+    /// the meta filter map used by the probes. `reload` is set when the
+    /// collection uses a configuration file.
+    pub(crate) fn new(
+        meta_map: libbpf_rs::MapHandle,
+        reload: Option<ReloadRequest>,
+    ) -> Result<Self> {
+        let pid = process::id();
+        let path = control_socket(pid);
+
+        fs::create_dir_all(MARKER_DIR)?;
+        let _ = fs::remove_file(&path);
+        // Create the socket with restrictive permissions right away, so it is
+        // never accessible to other users.
+        let umask = unsafe { libc::umask(0o077) };
+        let listener = UnixListener::bind(&path);
+        unsafe { libc::umask(umask) };
+        let listener = listener?;
+        // Do not block, so the thread can be stopped.
+        listener.set_nonblocking(true)?;
+
+        let run = Running::new();
+        let thread_run = run.clone();
+        let thread = thread::spawn(move || {
+            while thread_run.running() {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        if let Err(e) = handle_request(stream, &meta_map, reload.as_ref()) {
+                            warn!("Could not handle control request: {e}");
+                        }
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(200))
+                    }
+                    Err(e) => warn!("Could not accept control connection: {e}"),
+                }
+            }
+        });
+
+        Ok(Self {
+            pid,
+            run,
+            thread: Some(thread),
+        })
+    }
+
+    /// Stop handling requests and remove the control socket.
+    pub(crate) fn stop(&mut self) -> Result<()> {
+        self.run.terminate();
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                bail!("Could not join the control thread");
+            }
+        }
+
+        fs::remove_file(control_socket(self.pid))?;
+        Ok(())
+    }
+}
+
+/// Read a request from a control connection, apply it and send the result
+/// back.
+fn handle_request(
+    stream: UnixStream,
+    meta_map: &libbpf_rs::MapHandle,
+    reload: Option<&ReloadRequest>,
+) -> Result<()> {
+    // The listener is non-blocking, but not the connections.
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+
+    let mut request = String::new();
+    BufReader::new(&stream).read_line(&mut request)?;
+
+    let reply = match apply_request(request.trim_end_matches('\n'), meta_map, reload) {
+        Ok(()) => "ok".to_string(),
+        Err(e) => format!("error: {e}"),
+    };
+    writeln!(&stream, "{reply}")?;
+    Ok(())
+}
+
+fn apply_request(
+    request: &str,
+    meta_map: &libbpf_rs::MapHandle,
+    reload: Option<&ReloadRequest>,
+) -> Result<()> {
+    let (cmd, arg) = request.split_once(' ').unwrap_or((request, ""));
+
+    match cmd {
+        "filter-meta" => {
+            let arg = arg.trim();
+            match arg.is_empty() {
+                true => {
+                    set_meta_filter(meta_map, None)?;
+                    info!("Meta filter removed");
+                }
+                false => {
+                    let filter = FilterMeta::from_string(arg.to_string())
+                        .map_err(|e| anyhow!("meta filter: {e}"))?;
+                    set_meta_filter(meta_map, Some(&filter))?;
+                    info!("Meta filter changed to '{arg}'");
+                }
+            }
+        }
+        "reload" => match reload {
+            // The configuration is reloaded by the collection loop.
+            Some(reload) => reload.request(),
+            None => bail!("No configuration file (--config) used"),
+        },
+        x => bail!("Unknown request '{x}'"),
+    }
+    Ok(())
+}
+
+/// Find the collection to send a request to: the one given by `pid`, or the
+/// only one running.
+fn target_collection(pid: Option<u32>) -> Result<u32> {
+    Ok(match pid {
+        Some(pid) => {
+            if !collect_file(pid).exists() {
+                bail!("No running collection with pid {pid}");
+            }
+            pid
+        }
+        None => match running_collections()?.as_slice() {
+            [] => bail!("No running collection found"),
+            [pid] => *pid,
+            _ => bail!("Multiple running collections found, please use --pid"),
+        },
+    })
+}
+
+/// Send a request to the control socket of a collection and wait for its
+/// reply.
+fn request(pid: u32, request: &str) -> Result<()> {
+    let stream = UnixStream::connect(control_socket(pid))
+        .map_err(|e| anyhow!("Could not connect to collection {pid}: {e}"))?;
+    writeln!(&stream, "{}", request.replace('\n', " "))?;
+
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply)?;
+
+    match reply.trim_end() {
+        "ok" => Ok(()),
+        reply => match reply.strip_prefix("error: ") {
+            Some(e) => bail!("Collection {pid}: {e}"),
+            None => bail!("Collection {pid}: unexpected reply '{reply}'"),
+        },
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum FilterSubCommand {
+    /// Show the programs filters compile to, the kernel structure members meta
+    /// filters resolve to and whether the running kernel supports them.
+    #[command(arg_required_else_help = true)]
+    Explain(Explain),
+}
+
+/// Change the filters of a running collection.
+///
+/// The new filters apply to packets not already tracked; events collected so
+/// far and the collection state are kept. Only the meta filter can be changed,
+/// packet filters are compiled into the probes. Filters can be checked
+/// beforehand using `retis filter explain`.
+#[derive(Parser, Debug, Default)]
+#[command(
+    name = "filter",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub(crate) struct UpdateFilter {
+    /// New meta filter, using the same syntax as `retis collect
+    /// --filter-meta`. An empty filter removes the current one.
+    #[arg(long, required = true)]
+    filter_meta: Option<String>,
+
+    /// Pid of the collection to update. Can be omitted if a single collection
+    /// is running.
+    #[arg(long)]
+    pid: Option<u32>,
+
+    #[command(subcommand)]
+    command: Option<FilterSubCommand>,
+}
+
+impl SubCommandParserRunner for UpdateFilter {
+    fn run(&mut self) -> Result<()> {
+        if let Some(FilterSubCommand::Explain(explain)) = &self.command {
+            return explain.run();
+        }
+
+        let pid = target_collection(self.pid)?;
+        let filter = self.filter_meta.as_deref().unwrap_or_default();
+
+        request(pid, &format!("filter-meta {filter}"))?;
+        info!("Filter of collection {pid} updated");
+        Ok(())
+    }
+}
+
+/// Reload the configuration file of a running collection.
+///
+/// Only the options set with `retis collect --config` can be changed: output
+/// format, UTC time, clock synchronization and checkpoint intervals. This is
+/// equivalent to sending SIGHUP to the collection.
+#[derive(Parser, Debug, Default)]
+#[command(name = "reload")]
+pub(crate) struct Reload {
+    /// Pid of the collection to reload. Can be omitted if a single collection
+    /// is running.
+    #[arg(long)]
+    pid: Option<u32>,
+}
+
+impl SubCommandParserRunner for Reload {
+    fn run(&mut self) -> Result<()> {
+        let pid = target_collection(self.pid)?;
+
+        request(pid, "reload")?;
+        info!("Configuration reload of collection {pid} requested");
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+//! # Ethtool statistics
+//!
+//! Snapshots of the driver statistics of network interfaces (`ethtool -S`)
+//! taken when a collection starts and stops (`--ethtool-stats`). Counters which
+//! changed in between are reported in an event at the end of the collection, so
+//! kernel events can be reconciled with NIC level counters.
+
+use std::{collections::HashMap, ffi::CString, fs};
+
+use anyhow::{bail, Result};
+use log::{debug, warn};
+
+use crate::{events::*, helpers::ethtool};
+
+/// Statistics of an interface when the collection started.
+struct IfSnapshot {
+    name: String,
+    ifindex: u32,
+    stats: Vec<(String, u64)>,
+}
+
+pub(crate) struct EthtoolStats {
+    snapshots: Vec<IfSnapshot>,
+}
+
+impl EthtoolStats {
+    /// Snapshot the statistics of the given interfaces, or of all the
+    /// interfaces supporting it if none is given.
+    pub(crate) fn snapshot(ifaces: &[String]) -> Result<Self> {
+        let mut snapshots = Vec::new();
+
+        if ifaces.is_empty() {
+            for entry in fs::read_dir("/sys/class/net")? {
+                let name = entry?.file_name().to_string_lossy().to_string();
+                match ethtool::stats(&name) {
+                    Ok(stats) => snapshots.push(IfSnapshot {
+                        ifindex: ifindex(&name)?,
+                        name,
+                        stats,
+                    }),
+                    Err(e) => debug!("No ethtool statistics for {name}: {e}"),
+                }
+            }
+        } else {
+            for name in ifaces.iter() {
+                match ethtool::stats(name) {
+                    Ok(stats) => snapshots.push(IfSnapshot {
+                        name: name.clone(),
+                        ifindex: ifindex(name)?,
+                        stats,
+                    }),
+                    Err(e) => bail!("Could not get the ethtool statistics of {name}: {e}"),
+                }
+            }
+        }
+
+        Ok(Self { snapshots })
+    }
+
+    /// Snapshot the statistics again and report the counters which changed.
+    pub(crate) fn event(&self) -> EthtoolStatsEvent {
+        let mut interfaces = Vec::new();
+
+        for snapshot in self.snapshots.iter() {
+            let stats = match ethtool::stats(&snapshot.name) {
+                Ok(stats) => stats,
+                Err(e) => {
+                    warn!(
+                        "Could not get the ethtool statistics of {}: {e}",
+                        snapshot.name
+                    );
+                    continue;
+                }
+            };
+
+            let counters = deltas(&snapshot.stats, &stats);
+            if !counters.is_empty() {
+                interfaces.push(EthtoolIfStats {
+                    name: snapshot.name.clone(),
+                    ifindex: snapshot.ifindex,
+                    counters,
+                });
+            }
+        }
+
+        EthtoolStatsEvent { interfaces }
+    }
+}
+
+fn ifindex(name: &str) -> Result<u32> {
+    match unsafe { libc::if_nametoindex(CString::new(name)?.as_ptr()) } {
+        0 => bail!("Unknown interface {name}"),
+        ifindex => Ok(ifindex),
+    }
+}
+
+/// Compute the counters which changed between two snapshots. Counters going
+/// backward (e.g. after a device reset) can't be reported reliably and are
+/// skipped.
+fn deltas(start: &[(String, u64)], end: &[(String, u64)]) -> Vec<EthtoolCounter> {
+    let start: HashMap<_, _> = start.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+
+    end.iter()
+        .filter_map(|(name, val)| {
+            let delta = val.checked_sub(*start.get(name.as_str())?)?;
+            (delta != 0).then(|| EthtoolCounter {
+                name: name.clone(),
+                delta,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ethtool_deltas() {
+        let stat = |name: &str, val| (name.to_string(), val);
+        let counters = deltas(
+            &[
+                stat("rx_drops", 3),
+                stat("tx_kicks", 10),
+                stat("rx_kicks", 5),
+            ],
+            &[
+                stat("rx_drops", 5),
+                stat("tx_kicks", 10),
+                stat("rx_kicks", 1),
+                stat("new", 1),
+            ],
+        );
+
+        assert_eq!(counters.len(), 1);
+        assert_eq!(counters[0].name, "rx_drops");
+        assert_eq!(counters[0].delta, 2);
+
+        assert!(EthtoolStats::snapshot(&["lo".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,112 @@
+//! # Explain
+//!
+//! Explains packet and meta filters, for debugging them: the compiled
+//! programs, the kernel structure members the meta filters resolve to and
+//! whether the running kernel can run them. Used by `retis filter explain`.
+
+use anyhow::{anyhow, Result};
+use clap::Args;
+
+use crate::{
+    bindings::packet_filter_uapi,
+    core::{
+        filters::{meta::filter::FilterMeta, packets::filter::FilterPacket},
+        inspect::inspector,
+    },
+};
+
+/// Filters to explain.
+#[derive(Args, Debug, Default)]
+pub(crate) struct Explain {
+    /// Packet filter, using the same syntax as `retis collect --filter-packet`.
+    #[arg(short, long)]
+    filter_packet: Option<String>,
+
+    /// Meta filter, using the same syntax as `retis collect --filter-meta`.
+    #[arg(short = 'm', long)]
+    filter_meta: Option<String>,
+}
+
+impl Explain {
+    pub(crate) fn run(&self) -> Result<()> {
+        if let Some(filter) = &self.filter_packet {
+            explain_packet_filter(filter)?;
+        }
+        if let Some(filter) = &self.filter_meta {
+            explain_meta_filter(filter)?;
+        }
+        Ok(())
+    }
+}
+
+fn explain_packet_filter(filter: &str) -> Result<()> {
+    println!("Packet filter: {filter}");
+
+    // Same logic as the collect command: the L2 filter is mandatory while the
+    // L3 one is only used when it can be generated.
+    for (name, layer) in [
+        ("L2, from the mac header", packet_filter_uapi::FILTER_L2),
+        ("L3, from the network header", packet_filter_uapi::FILTER_L3),
+    ] {
+        println!("\n{name}:");
+
+        if layer == packet_filter_uapi::FILTER_L3 && filter.contains("ether[") {
+            println!("  not generated (ether[n:m] not allowed), never matches");
+            continue;
+        }
+
+        let ebpf = match FilterPacket::from_string_opt(filter.to_string(), layer) {
+            Ok(ebpf) => ebpf,
+            Err(e) if layer == packet_filter_uapi::FILTER_L3 => {
+                println!("  not generated ({e}), never matches");
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let cbpf = FilterPacket::cbpf_image(filter, layer)?;
+
+        println!("  cBPF ({} instructions):", cbpf.len());
+        cbpf.iter().for_each(|insn| println!("    {insn}"));
+
+        let ebpf = ebpf.image();
+        println!(
+            "  eBPF ({}/{} instructions):",
+            ebpf.len(),
+            packet_filter_uapi::FILTER_MAX_INSNS
+        );
+        ebpf.iter().for_each(|insn| println!("    {insn}"));
+    }
+
+    // The generated eBPF programs read the packet data using
+    // bpf_probe_read_kernel.
+    let supported = inspector()?
+        .kernel
+        .btf
+        .has_enum_value("bpf_func_id", "BPF_FUNC_probe_read_kernel");
+    if supported {
+        println!("\nKernel support: yes\n");
+    } else {
+        println!("\nKernel support: no (bpf_probe_read_kernel is not available)\n");
+    }
+
+    Ok(())
+}
+
+fn explain_meta_filter(filter: &str) -> Result<()> {
+    println!("Meta filter: {filter}");
+
+    // Meta filters are resolved using the BTF of the running kernel: if the
+    // filter compiles, the kernel supports it.
+    let meta = FilterMeta::from_string(filter.to_string())
+        .map_err(|e| anyhow!("meta filter not supported by the running kernel: {e}"))?;
+
+    println!("\nResolved operations:");
+    meta.describe().iter().for_each(|op| println!("  {op}"));
+
+    println!(
+        "\nKernel support: yes (resolved using the BTF of {})\n",
+        inspector()?.kernel.version().full
+    );
+
+    Ok(())
+}
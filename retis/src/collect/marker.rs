@@ -0,0 +1,263 @@
+//! # Marker
+//!
+//! User-inserted markers. A running collection can be asked to inject marker
+//! events into its stream, so post-processing can segment a capture by
+//! experiment phases. A marker is requested by appending its text (one marker
+//! per line) to the marker file of the collection, `/run/retis/marker.<pid>`,
+//! and by sending SIGUSR1 to the collecting process. Sending SIGUSR1 without
+//! writing to the marker file injects an empty marker. The `retis mark`
+//! command does both.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    process,
+    sync::Arc,
+    thread,
+};
+
+use anyhow::{bail, Result};
+use clap::Parser;
+use log::{info, warn};
+use signal_hook::{consts::SIGUSR1, iterator::Signals};
+
+use crate::{cli::*, core::events::RetisEventsFactory, events::*};
+
+/// Directory holding the files used to interact with running collections.
+pub(super) const MARKER_DIR: &str = "/run/retis";
+
+/// File advertising a running collection.
+pub(super) fn collect_file(pid: u32) -> PathBuf {
+    Path::new(MARKER_DIR).join(format!("collect.{pid}"))
+}
+
+/// File holding the markers requested for a running collection.
+fn marker_file(dir: &Path, pid: u32) -> PathBuf {
+    dir.join(format!("marker.{pid}"))
+}
+
+/// Listens for marker requests and injects marker events in the stream. The
+/// files advertising the collection are removed when the listener is dropped,
+/// including on error paths.
+pub(crate) struct MarkerListener {
+    dir: PathBuf,
+    pid: u32,
+    handle: signal_hook::iterator::Handle,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl MarkerListener {
+    /// Advertise the current collection and start listening for marker
+    /// requests.
+    pub(crate) fn new(events_factory: Arc<RetisEventsFactory>) -> Result<Self> {
+        Self::new_in(Path::new(MARKER_DIR), events_factory)
+    }
+
+    fn new_in(dir: &Path, events_factory: Arc<RetisEventsFactory>) -> Result<Self> {
+        let pid = process::id();
+
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(format!("collect.{pid}")), "")?;
+
+        let mut sigs = Signals::new([SIGUSR1])?;
+        let handle = sigs.handle();
+        let thread_dir = dir.to_path_buf();
+        let thread = thread::spawn(move || {
+            for _ in sigs.forever() {
+                if let Err(e) = inject_markers(&thread_dir, pid, &events_factory) {
+                    warn!("Could not inject marker: {e}");
+                }
+            }
+        });
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            pid,
+            handle,
+            thread: Some(thread),
+        })
+    }
+
+    /// Stop listening for marker requests.
+    pub(crate) fn stop(&mut self) -> Result<()> {
+        self.handle.close();
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                bail!("Could not join the marker thread");
+            }
+        }
+
+        let _ = fs::remove_file(marker_file(&self.dir, self.pid));
+        fs::remove_file(self.dir.join(format!("collect.{}", self.pid)))?;
+        Ok(())
+    }
+}
+
+impl Drop for MarkerListener {
+    fn drop(&mut self) {
+        // Files might already have been removed by stop().
+        self.handle.close();
+        let _ = fs::remove_file(marker_file(&self.dir, self.pid));
+        let _ = fs::remove_file(self.dir.join(format!("collect.{}", self.pid)));
+    }
+}
+
+/// Read the markers requested for a collection. The marker file is moved
+/// before being read, so concurrent requests are not lost.
+fn read_markers(dir: &Path, pid: u32) -> Result<Vec<String>> {
+    let file = marker_file(dir, pid);
+    let pending = dir.join(format!("marker.{pid}.pending"));
+
+    match fs::rename(&file, &pending) {
+        Ok(_) => (),
+        // Plain SIGUSR1, inject an empty marker.
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![String::new()]),
+        Err(e) => return Err(e.into()),
+    }
+
+    let markers = fs::read_to_string(&pending)?
+        .lines()
+        .map(String::from)
+        .collect();
+    fs::remove_file(&pending)?;
+    Ok(markers)
+}
+
+fn inject_markers(dir: &Path, pid: u32, events_factory: &RetisEventsFactory) -> Result<()> {
+    read_markers(dir, pid)?.into_iter().try_for_each(|text| {
+        events_factory.add_event(|event| {
+            event.insert_section(
+                SectionId::Marker,
+                Box::new(MarkerEvent { text: text.clone() }),
+            )
+        })
+    })
+}
+
+/// Pids of the running collections.
+pub(super) fn running_collections() -> Result<Vec<u32>> {
+    let dir = match fs::read_dir(MARKER_DIR) {
+        Ok(dir) => dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(dir
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("collect.")?
+                .parse::<u32>()
+                .ok()
+        })
+        // Skip collections which did not clean up after themselves.
+        // Safety: signal 0 only checks the process exists.
+        .filter(|pid| unsafe { libc::kill(*pid as libc::pid_t, 0) } == 0)
+        .collect())
+}
+
+/// Append a marker to the marker file of a collection.
+fn write_marker(dir: &Path, pid: u32, text: &str) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .mode(0o600)
+        .open(marker_file(dir, pid))?;
+    writeln!(file, "{}", text.replace('\n', " "))?;
+    Ok(())
+}
+
+/// Request a marker to be injected in a running collection.
+fn request_marker(pid: u32, text: &str) -> Result<()> {
+    write_marker(Path::new(MARKER_DIR), pid, text)?;
+
+    // Safety: sending a signal to a process we checked is a collection.
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGUSR1) } != 0 {
+        bail!(
+            "Could not signal collection {pid}: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    Ok(())
+}
+
+/// Insert a marker in running collections.
+///
+/// The marker is added as an event in the collection stream, which allows to
+/// segment a capture by experiment phases during post-processing.
+#[derive(Parser, Debug, Default)]
+#[command(name = "mark")]
+pub(crate) struct Mark {
+    /// Text of the marker.
+    #[arg(default_value = "")]
+    text: String,
+
+    /// Pid of the collection to mark. All running collections are marked if
+    /// not set.
+    #[arg(long)]
+    pid: Option<u32>,
+}
+
+impl SubCommandParserRunner for Mark {
+    fn run(&mut self) -> Result<()> {
+        let pids = match self.pid {
+            Some(pid) => {
+                if !collect_file(pid).exists() {
+                    bail!("No running collection with pid {pid}");
+                }
+                vec![pid]
+            }
+            None => running_collections()?,
+        };
+
+        if pids.is_empty() {
+            bail!("No running collection found");
+        }
+
+        pids.iter().try_for_each(|pid| {
+            request_marker(*pid, &self.text)?;
+            info!("Marker requested for collection {pid}");
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marker() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid = process::id();
+        let factory = Arc::new(RetisEventsFactory::default());
+
+        let listener = MarkerListener::new_in(dir.path(), factory.clone()).unwrap();
+        assert!(dir.path().join(format!("collect.{pid}")).exists());
+
+        write_marker(dir.path(), pid, "phase 1").unwrap();
+        write_marker(dir.path(), pid, "phase\n2").unwrap();
+        inject_markers(dir.path(), pid, &factory).unwrap();
+        // No marker requested, an empty one is injected.
+        inject_markers(dir.path(), pid, &factory).unwrap();
+
+        let markers = std::iter::from_fn(|| factory.next_event())
+            .map(|e| {
+                e.get_section::<MarkerEvent>(SectionId::Marker)
+                    .unwrap()
+                    .text
+                    .clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(markers, vec!["phase 1", "phase 2", ""]);
+
+        // Files are removed when the listener goes away, even without stop().
+        write_marker(dir.path(), pid, "leftover").unwrap();
+        drop(listener);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
@@ -21,4 +21,17 @@ pub(crate) mod collect;
 pub(crate) use collect::*;
 
 pub(crate) mod cli;
+pub(crate) mod cmd;
 pub(crate) mod collector;
+pub(crate) mod config;
+pub(crate) mod control;
+pub(crate) mod ethtool;
+pub(crate) mod explain;
+pub(crate) mod marker;
+pub(crate) mod netstat;
+pub(crate) mod pcap_stream;
+pub(crate) mod postmortem;
+pub(crate) mod probe_test;
+pub(crate) mod remote;
+pub(crate) mod replay;
+pub(crate) mod status;
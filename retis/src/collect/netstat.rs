@@ -0,0 +1,174 @@
+//! # Netstat
+//!
+//! Snapshots of the network stack counters (`/proc/net/snmp` and
+//! `/proc/net/netstat`, as shown by `nstat`) taken when a collection starts and
+//! stops (`--netstat`). Counters which changed in between are reported in an
+//! event at the end of the collection, so they can be correlated with the
+//! captured events (e.g. `TcpExtListenDrops` vs dropped packets).
+
+use std::{collections::HashMap, fs};
+
+use anyhow::{bail, Result};
+use log::warn;
+
+use crate::events::*;
+
+/// Files holding the counters, using the same format.
+const NETSTAT_FILES: &[&str] = &["/proc/net/snmp", "/proc/net/netstat"];
+
+pub(crate) struct Netstat {
+    counters: Vec<(String, i64)>,
+}
+
+impl Netstat {
+    /// Snapshot the network stack counters.
+    pub(crate) fn snapshot() -> Result<Self> {
+        Ok(Self {
+            counters: read_counters()?,
+        })
+    }
+
+    /// Snapshot the counters again and report the ones which changed.
+    pub(crate) fn event(&self) -> NetstatEvent {
+        let counters = match read_counters() {
+            Ok(counters) => deltas(&self.counters, &counters),
+            Err(e) => {
+                warn!("Could not get the network stack counters: {e}");
+                Vec::new()
+            }
+        };
+
+        NetstatEvent { counters }
+    }
+}
+
+fn read_counters() -> Result<Vec<(String, i64)>> {
+    let mut counters = Vec::new();
+    for file in NETSTAT_FILES.iter() {
+        counters.append(&mut parse(&fs::read_to_string(file)?)?);
+    }
+    Ok(counters)
+}
+
+/// Parse the content of a /proc/net/{snmp,netstat} file. Counters are reported
+/// in pairs of lines, the first one holding their names and the second one
+/// their values, both prefixed by the group name (e.g. `TcpExt:`). Names are
+/// flattened the way `nstat` does, e.g. `TcpExtListenDrops`.
+fn parse(content: &str) -> Result<Vec<(String, i64)>> {
+    let mut counters = Vec::new();
+    let mut lines = content.lines();
+
+    while let Some(names) = lines.next() {
+        let values = match lines.next() {
+            Some(values) => values,
+            None => bail!("Missing values for line '{names}'"),
+        };
+
+        let (group, names) = match names.split_once(':') {
+            Some(split) => split,
+            None => bail!("Invalid counters line '{names}'"),
+        };
+        let values = match values.split_once(':') {
+            Some((vgroup, values)) if vgroup == group => values,
+            _ => bail!("Invalid values line for group {group}"),
+        };
+
+        for (name, value) in names.split_whitespace().zip(values.split_whitespace()) {
+            counters.push((format!("{group}{name}"), value.parse()?));
+        }
+    }
+
+    Ok(counters)
+}
+
+/// Compute the counters which changed between two snapshots.
+fn deltas(start: &[(String, i64)], end: &[(String, i64)]) -> Vec<NetstatCounter> {
+    let start: HashMap<_, _> = start.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+
+    end.iter()
+        .filter_map(|(name, val)| {
+            let delta = val.wrapping_sub(*start.get(name.as_str())?);
+            (delta != 0).then(|| NetstatCounter {
+                name: name.clone(),
+                delta,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn netstat_parse() {
+        let start = parse(
+            "Ip: Forwarding DefaultTTL InReceives
+Ip: 1 64 1000
+Tcp: RtoAlgorithm MaxConn CurrEstab
+Tcp: 1 -1 10
+TcpExt: SyncookiesSent ListenOverflows ListenDrops
+TcpExt: 0 3 3
+",
+        )
+        .unwrap();
+        assert_eq!(start.len(), 9);
+        assert_eq!(start[4], ("TcpMaxConn".to_string(), -1));
+        assert_eq!(start[8], ("TcpExtListenDrops".to_string(), 3));
+
+        let end = parse(
+            "Ip: Forwarding DefaultTTL InReceives
+Ip: 1 64 1500
+Tcp: RtoAlgorithm MaxConn CurrEstab
+Tcp: 1 -1 8
+TcpExt: SyncookiesSent ListenOverflows ListenDrops
+TcpExt: 0 5 5
+",
+        )
+        .unwrap();
+
+        let counters = deltas(&start, &end)
+            .into_iter()
+            .map(|c| (c.name, c.delta))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            counters,
+            vec![
+                ("IpInReceives".to_string(), 500),
+                ("TcpCurrEstab".to_string(), -2),
+                ("TcpExtListenOverflows".to_string(), 2),
+                ("TcpExtListenDrops".to_string(), 2),
+            ]
+        );
+
+        assert!(parse("Ip: Forwarding\n").is_err());
+        assert!(parse("Ip: Forwarding\nTcp: 1\n").is_err());
+    }
+
+    #[test]
+    fn netstat_parse_snmp() {
+        // Excerpt of a /proc/net/snmp file.
+        let counters = parse(
+            "Ip: Forwarding DefaultTTL InReceives InHdrErrors InAddrErrors ForwDatagrams
+Ip: 2 64 60242 0 0 0
+Icmp: InMsgs InErrors InCsumErrors
+Icmp: 0 0 0
+IcmpMsg: InType3 OutType3
+IcmpMsg: 12 12
+Tcp: RtoAlgorithm RtoMin RtoMax MaxConn ActiveOpens PassiveOpens AttemptFails EstabResets
+Tcp: 1 200 120000 -1 82 75 0 117
+Udp: InDatagrams NoPorts InErrors OutDatagrams RcvbufErrors SndbufErrors
+Udp: 18 0 0 18 0 0
+UdpLite: InDatagrams NoPorts InErrors OutDatagrams RcvbufErrors SndbufErrors
+UdpLite: 0 0 0 0 0 0
+",
+        )
+        .unwrap();
+
+        assert_eq!(counters.len(), 31);
+        assert_eq!(counters[2], ("IpInReceives".to_string(), 60242));
+        assert_eq!(counters[9], ("IcmpMsgInType3".to_string(), 12));
+        assert_eq!(counters[14], ("TcpMaxConn".to_string(), -1));
+        assert_eq!(counters[30], ("UdpLiteSndbufErrors".to_string(), 0));
+    }
+}
@@ -0,0 +1,94 @@
+//! # Pcap stream
+//!
+//! Streams the packets of the events, while collecting, to a file or to a named
+//! pipe in the PCAP-ng format (`--pcap-out`). This allows analyzing packets
+//! live along the events, e.g. using `wireshark -k -i <fifo>`.
+
+use std::{
+    ffi::CString,
+    fs::{self, File, OpenOptions},
+    io,
+    os::unix::{ffi::OsStrExt, fs::FileTypeExt},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Result};
+use log::{info, warn};
+
+use crate::{events::*, process::pcap::EventParser};
+
+pub(crate) struct PcapStream {
+    path: PathBuf,
+    /// Was the named pipe created by us, in which case it is removed once
+    /// done.
+    created: bool,
+    /// PCAP writer. Unset if writing failed, e.g. the reader went away.
+    parser: Option<EventParser<File>>,
+}
+
+impl PcapStream {
+    /// Open the PCAP stream. If the target does not exist, a named pipe is
+    /// created. Opening a named pipe blocks until a reader opens it.
+    pub(crate) fn new(path: &Path) -> Result<Self> {
+        let created = !path.exists();
+        if created {
+            let cpath = CString::new(path.as_os_str().as_bytes())?;
+            if unsafe { libc::mkfifo(cpath.as_ptr(), 0o600) } < 0 {
+                bail!(
+                    "Could not create named pipe '{}': {}",
+                    path.display(),
+                    io::Error::last_os_error()
+                );
+            }
+        }
+
+        if fs::metadata(path)?.file_type().is_fifo() {
+            info!("Waiting for a reader on '{}'", path.display());
+        }
+
+        let file = OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .open(path)
+            .map_err(|e| anyhow!("Could not open '{}': {e}", path.display()))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            created,
+            parser: Some(EventParser::new(file)?),
+        })
+    }
+
+    /// Write the packet of an event, if any. Streaming stops on errors, but
+    /// the collection goes on.
+    pub(crate) fn process_one(&mut self, event: &Event) {
+        let parser = match self.parser.as_mut() {
+            Some(parser) => parser,
+            None => return,
+        };
+
+        // Only events coming from kernel probes can hold a packet.
+        if event.kernel().is_none() {
+            return;
+        }
+
+        if let Err(e) = parser.parse(event) {
+            warn!(
+                "Stopped streaming packets to '{}': {e}",
+                self.path.display()
+            );
+            self.parser = None;
+        }
+    }
+
+    /// Close the stream and remove the named pipe if we created it.
+    pub(crate) fn finish(self) -> Result<()> {
+        drop(self.parser);
+
+        if self.created {
+            fs::remove_file(&self.path)
+                .or_else(|e| bail!("Could not remove '{}': {e}", self.path.display()))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+//! # Post-mortem
+//!
+//! Recovers the events which were still in the BPF ring buffer when the kernel
+//! crashed, from a crash dump. The collection must have recorded the location
+//! of the ring buffer (`retis collect --postmortem`).
+
+use std::{fs::OpenOptions, io::BufWriter, path::PathBuf};
+
+use anyhow::{bail, Result};
+use clap::Parser;
+use log::{info, warn};
+
+use crate::{
+    cli::*,
+    collect::collector::section_factories,
+    core::events::{
+        parse_raw_event,
+        postmortem::{recover_records, VmCore},
+    },
+    events::{file::FileEventsFactory, *},
+    process::display::{PrintEvent, PrintEventFormat},
+};
+
+/// Recover the events lost in a kernel crash from a crash dump (expert).
+///
+/// The events not yet consumed by Retis when the kernel crashed are extracted from the ring buffer
+/// found in the crash dump and written to a new events file. The collection must have been started
+/// using `--postmortem` and its events file is used to locate the ring buffer. Only ELF crash dumps
+/// are supported (e.g. /proc/vmcore or `makedumpfile -E`), and events should be recovered using
+/// the same kernel as the one the collection ran on. Stack traces can't be recovered.
+#[derive(Parser, Debug, Default)]
+#[command(name = "postmortem")]
+pub(crate) struct Postmortem {
+    /// Kernel crash dump, in the ELF format.
+    pub(super) vmcore: PathBuf,
+
+    /// Events file written by the collection, holding the ring buffer location.
+    #[arg(long, default_value = "retis.data")]
+    pub(super) metadata: PathBuf,
+
+    /// Write the recovered events to this file.
+    #[arg(short, long, default_value = "retis.postmortem.data")]
+    pub(super) out: PathBuf,
+}
+
+impl SubCommandParserRunner for Postmortem {
+    fn run(&mut self) -> Result<()> {
+        // The startup event is the first one of a collection.
+        let mut factory = FileEventsFactory::new(self.metadata.as_path())?;
+        let startup = match factory.next_event()? {
+            Some(event) if event.startup().is_some() => event,
+            _ => bail!("No startup event found in '{}'", self.metadata.display()),
+        };
+        let layout = match startup.startup().and_then(|s| s.events_ringbuf.clone()) {
+            Some(layout) => layout,
+            None => bail!(
+                "The ring buffer location was not recorded, the collection must use --postmortem"
+            ),
+        };
+
+        let vmcore = VmCore::open(&self.vmcore)?;
+        let records = recover_records(&vmcore, &layout)?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.out)
+            .or_else(|_| bail!("Could not create or open '{}'", self.out.display()))?;
+        let mut printer = PrintEvent::new(Box::new(BufWriter::new(file)), PrintEventFormat::Json);
+
+        // Keep the startup event so the recovered events can be displayed
+        // using the collection clock offset.
+        printer.process_one(&startup)?;
+
+        let mut section_factories = section_factories()?;
+        let mut count = 0;
+        for record in records.iter() {
+            match parse_raw_event(record, &mut section_factories) {
+                Ok(event) => {
+                    printer.process_one(&event)?;
+                    count += 1;
+                }
+                Err(e) => warn!("Could not parse recovered event: {e}"),
+            }
+        }
+
+        info!(
+            "{count} event(s) recovered and written to '{}'",
+            self.out.display()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+//! # Probe test
+//!
+//! Validates a probe interactively: a collection using the probe is started,
+//! test packets are sent in a temporary network namespace and the events file
+//! is checked for events reported by the probe. The test packets are sent over
+//! loopback to a closed UDP port, so they go through the local output and
+//! receive paths and are dropped (no socket); an ICMP port unreachable error is
+//! sent back.
+
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{BufRead, BufReader},
+    net::UdpSocket,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    path::Path,
+    process::{Command, Stdio},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Result};
+use clap::Parser;
+use log::info;
+
+use crate::{
+    cli::*,
+    core::probe::{kernel::utils::probe_from_cli, ProbeType},
+    events::*,
+    helpers::manifest::{manifest_path, open_events},
+};
+
+/// Destination port of the test packets. Nothing listens on it in the
+/// temporary network namespace.
+const TEST_PORT: u16 = 9;
+/// Number of test packets sent.
+const TEST_PACKETS: usize = 3;
+/// Time given to the events to reach the events file once the test packets
+/// were sent.
+const SETTLE_TIME: Duration = Duration::from_secs(1);
+
+/// Check a probe sees packets, using crafted test packets (expert).
+///
+/// A collection using the probe is started and test packets (UDP to a closed port, over loopback)
+/// are sent in a temporary network namespace. The probe is validated if it reports events for
+/// those packets. Probes not on the path of the test packets (e.g. forwarding or driver functions)
+/// can't be validated this way.
+///
+/// Example: retis probe-test --probe tp:skb:kfree_skb
+#[derive(Parser, Debug, Default)]
+#[command(name = "probe-test")]
+pub(crate) struct ProbeTest {
+    /// Probe to test, using the same format as `retis collect --probe`.
+    #[arg(short, long)]
+    probe: String,
+
+    /// Maximum time to wait for the probe to be attached, in seconds.
+    #[arg(long, default_value = "30")]
+    timeout: u64,
+}
+
+impl SubCommandParserRunner for ProbeTest {
+    fn run(&mut self) -> Result<()> {
+        // Resolve the probe first, to fail early and to know which events to
+        // look for.
+        let targets = probe_from_cli(&self.probe, |_| true)?
+            .iter()
+            .filter_map(|p| match p.r#type() {
+                ProbeType::Kprobe(kp) => Some(("kprobe", kp.symbol.name())),
+                ProbeType::Kretprobe(kp) => Some(("kretprobe", kp.symbol.name())),
+                ProbeType::RawTracepoint(kp) => Some(("raw_tracepoint", kp.symbol.name())),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+
+        let out = std::env::temp_dir().join(format!("retis-probe-test.{}", std::process::id()));
+        let res = self.test(&out, &targets);
+        let _ = fs::remove_file(&out);
+        let _ = fs::remove_file(manifest_path(&out));
+
+        match res? {
+            0 => bail!(
+                "No event was reported by {}; the probe might not be on the path of the test packets",
+                self.probe
+            ),
+            count => info!("{count} event(s) reported by {}, the probe works", self.probe),
+        }
+        Ok(())
+    }
+}
+
+impl ProbeTest {
+    /// Run the test collection, writing events to `out`, and return the
+    /// number of events reported by the probe.
+    fn test(&self, out: &Path, targets: &HashSet<(&str, String)>) -> Result<usize> {
+        // The collection reports when the probes are attached using a status
+        // fd, the write end of a pipe it inherits.
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } < 0 {
+            bail!("Could not create pipe: {}", std::io::Error::last_os_error());
+        }
+        let (status, status_wr) =
+            unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+        unsafe { libc::fcntl(status.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+
+        let mut child = Command::new(std::env::current_exe()?)
+            .args(["collect", "-c", "skb", "--probe", &self.probe])
+            .args(["--filter-packet", &format!("udp dst port {TEST_PORT}")])
+            .arg("--status-fd")
+            .arg(status_wr.as_raw_fd().to_string())
+            .arg("-o")
+            .arg(out)
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|e| anyhow!("Could not start the collection: {e}"))?;
+        // Only the collection should hold the write end, for the pipe to be
+        // closed if it exits.
+        drop(status_wr);
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            BufReader::new(File::from(status))
+                .lines()
+                .map_while(|line| line.ok())
+                .filter_map(|line| serde_json::from_str::<serde_json::Value>(&line).ok())
+                .filter(|record| record["status"] == "probes_attached")
+                .for_each(|_| {
+                    let _ = tx.send(());
+                });
+        });
+
+        let attached = rx.recv_timeout(Duration::from_secs(self.timeout));
+        if attached.is_ok() {
+            send_test_packets()?;
+            thread::sleep(SETTLE_TIME);
+        }
+
+        // Stop the collection, the same way Ctrl+C would.
+        unsafe { libc::kill(child.id() as i32, libc::SIGINT) };
+        let exit = child.wait()?;
+
+        if attached.is_err() {
+            bail!("The collection did not start ({exit})");
+        }
+
+        let mut factory = open_events(out)?;
+        let mut count = 0;
+        while let Some(event) = factory.next_event()? {
+            if let Some(kernel) = event.kernel() {
+                if targets.contains(&(kernel.probe_type.as_str(), kernel.symbol.clone())) {
+                    count += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+}
+
+/// Send the test packets from a temporary network namespace. The namespace
+/// only lives as long as the thread sending the packets.
+fn send_test_packets() -> Result<()> {
+    thread::spawn(|| -> Result<()> {
+        if unsafe { libc::unshare(libc::CLONE_NEWNET) } < 0 {
+            bail!(
+                "Could not create a network namespace: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+        set_link_up("lo")?;
+
+        let socket = UdpSocket::bind("127.0.0.1:0")?;
+        for _ in 0..TEST_PACKETS {
+            socket.send_to(b"retis probe-test", ("127.0.0.1", TEST_PORT))?;
+        }
+        Ok(())
+    })
+    .join()
+    .map_err(|_| anyhow!("Could not join the test packets thread"))?
+}
+
+/// Set a link up, in the network namespace of the calling thread.
+fn set_link_up(name: &str) -> Result<()> {
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| anyhow!("Could not open socket: {e}"))?;
+
+    let mut ifr: libc::ifreq = unsafe { std::mem::zeroed() };
+    name.bytes()
+        .take(libc::IFNAMSIZ - 1)
+        .enumerate()
+        .for_each(|(i, c)| ifr.ifr_name[i] = c as libc::c_char);
+
+    if unsafe { libc::ioctl(socket.as_raw_fd(), libc::SIOCGIFFLAGS, &mut ifr) } < 0 {
+        bail!(
+            "Could not get the flags of {name}: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    unsafe { ifr.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short };
+    if unsafe { libc::ioctl(socket.as_raw_fd(), libc::SIOCSIFFLAGS, &ifr) } < 0 {
+        bail!(
+            "Could not set {name} up: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,482 @@
+//! # Remote
+//!
+//! Runs a collection on multiple hosts over SSH and merges their events
+//! locally. Each remote collection writes its events to stdout (`--out -`,
+//! compressed), which is streamed back to a per-host file. Once all
+//! collections are done, the per-host files are merged into a single one: each
+//! event gets a `host` section and its timestamp is rebased on the monotonic
+//! clock of the first host, using the wall-clock offsets the collections
+//! recorded, so events of all hosts can be ordered and displayed together.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter},
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Result};
+use clap::Parser;
+use log::{info, warn};
+
+use crate::{
+    cli::*,
+    events::{file::FileEventsFactory, *},
+    helpers::signals::Running,
+    process::display::{PrintEvent, PrintEventFormat},
+};
+
+/// Collect events on multiple hosts over SSH.
+///
+/// Runs `retis collect` with the given arguments on each host, streams the events back and merges
+/// them in a single file once all collections are done. Events are ordered using the wall-clock
+/// time of their host and tagged with it. Collections are stopped using Ctrl+C, or when they end on
+/// their own (e.g. when using --cmd).
+///
+/// Hosts must be reachable using `ssh <HOST>` without a password prompt, and retis must either be
+/// installed on the hosts or pushed using --push.
+///
+/// Example: retis remote --host node1,node2 -- collect -c skb,skb-drop
+#[derive(Parser, Debug, Default)]
+#[command(name = "remote")]
+pub(crate) struct Remote {
+    /// Comma separated list of hosts to collect on, as given to ssh (e.g. user@host).
+    #[arg(long, value_delimiter = ',', required = true)]
+    host: Vec<String>,
+
+    /// Copy the local retis binary to the hosts before collecting.
+    #[arg(long)]
+    push: bool,
+
+    /// Path of retis on the hosts. Defaults to "retis", or to "/tmp/retis" with --push.
+    #[arg(long)]
+    remote_path: Option<String>,
+
+    /// File to write the merged events to. The events of each host are kept in "<OUT>.<HOST>".
+    #[arg(short, long, default_value = "retis.data")]
+    out: PathBuf,
+
+    /// Command to run on the hosts and its arguments. Only `collect` is supported, its output
+    /// options (--out, --print, --compress) can't be used.
+    #[arg(
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        required = true,
+        value_name = "COMMAND"
+    )]
+    args: Vec<String>,
+}
+
+impl SubCommandParserRunner for Remote {
+    fn run(&mut self) -> Result<()> {
+        if self.args.first().map(String::as_str) != Some("collect") {
+            bail!("Only the collect command can be run remotely");
+        }
+        check_collect_args(&self.args[1..])?;
+
+        let run = Running::new();
+        run.register_term_signals()?;
+
+        let retis = match (&self.remote_path, self.push) {
+            (Some(path), _) => path.clone(),
+            (None, true) => "/tmp/retis".to_string(),
+            (None, false) => "retis".to_string(),
+        };
+        if self.push {
+            let exe = std::env::current_exe()?;
+            for host in self.host.iter() {
+                push(&exe, host, &retis)?;
+            }
+        }
+
+        let mut collections = Vec::new();
+        for host in self.host.iter() {
+            let file = host_file(&self.out, host);
+            collections.push(RemoteCollection::start(host, &retis, &self.args, &file)?);
+        }
+        info!("Collecting on {} host(s)", collections.len());
+
+        while run.running() && collections.iter_mut().any(|c| c.running()) {
+            thread::sleep(Duration::from_millis(200));
+        }
+
+        // Stop all collections, in parallel, before waiting for them.
+        collections.iter_mut().for_each(|c| c.stop());
+        for collection in collections.iter_mut() {
+            if let Err(e) = collection.wait() {
+                warn!("{e}");
+            }
+        }
+
+        let mut hosts = Vec::new();
+        for host in self.host.iter() {
+            let file = host_file(&self.out, host);
+            match FileEventsFactory::new(&file) {
+                Ok(mut factory) => hosts.push(HostEvents::new(
+                    host,
+                    Box::new(move || factory.next_event()),
+                )),
+                Err(e) => warn!("Skipping events of {host}: {e}"),
+            }
+        }
+
+        let mut printer = PrintEvent::new(
+            Box::new(BufWriter::new(File::create(&self.out).map_err(|e| {
+                anyhow!("Could not create {}: {e}", self.out.display())
+            })?)),
+            PrintEventFormat::Json,
+        );
+        let count = merge(hosts, |event| printer.process_one(event))?;
+        printer.flush()?;
+
+        info!("{count} event(s) merged in {}", self.out.display());
+        Ok(())
+    }
+}
+
+/// Reject the collect output options, as the events are always streamed back
+/// to the local host.
+fn check_collect_args(args: &[String]) -> Result<()> {
+    for arg in args.iter() {
+        let name = arg.split('=').next().unwrap_or(arg);
+        if matches!(name, "--out" | "--print" | "--compress")
+            || (arg.starts_with("-o") && !arg.starts_with("--"))
+        {
+            bail!(
+                "Output options (-o/--out, --print, --compress) can't be used with remote collections, use 'retis remote --out' instead"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// File holding the events of a single host.
+fn host_file(out: &Path, host: &str) -> PathBuf {
+    let mut file = out.as_os_str().to_owned();
+    file.push(format!(".{host}"));
+    PathBuf::from(file)
+}
+
+/// Quote an argument for a POSIX shell.
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Copy the retis binary to a host.
+fn push(exe: &Path, host: &str, path: &str) -> Result<()> {
+    let status = Command::new("scp")
+        .arg("-q")
+        .arg(exe)
+        .arg(format!("{host}:{path}"))
+        .status()
+        .map_err(|e| anyhow!("Failed to execute scp: {e}"))?;
+
+    match status.success() {
+        true => Ok(()),
+        false => bail!("Could not copy retis to {host} ({status})"),
+    }
+}
+
+/// A collection running on a remote host.
+struct RemoteCollection {
+    host: String,
+    child: Child,
+    /// Thread copying the events to the host file.
+    copy: Option<thread::JoinHandle<io::Result<u64>>>,
+}
+
+impl RemoteCollection {
+    fn start(host: &str, retis: &str, args: &[String], file: &Path) -> Result<Self> {
+        // The collection runs in the background and is stopped (SIGTERM) when
+        // the ssh standard input is closed, so it can cleanly stop and flush
+        // its events. Standard input is saved in fd 3 as background commands
+        // have theirs redirected from /dev/null.
+        let cmd = format!(
+            "{} {} -o - --compress zstd {}",
+            shell_quote(retis),
+            shell_quote(&args[0]),
+            args[1..]
+                .iter()
+                .map(|a| shell_quote(a))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        let script = format!(
+            "exec 3<&0; {cmd} & pid=$!; (cat <&3 >/dev/null; kill $pid) >/dev/null 2>&1 & wait $pid"
+        );
+
+        let mut child = Command::new("ssh")
+            .arg(host)
+            .arg(script)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            // Do not receive the terminal signals, collections are stopped
+            // using their standard input.
+            .process_group(0)
+            .spawn()
+            .map_err(|e| anyhow!("Failed to execute ssh: {e}"))?;
+
+        let mut stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("Could not get the output of {host}"))?;
+        let mut out =
+            File::create(file).map_err(|e| anyhow!("Could not create {}: {e}", file.display()))?;
+        let copy = thread::spawn(move || io::copy(&mut stdout, &mut out));
+
+        Ok(Self {
+            host: host.to_string(),
+            child,
+            copy: Some(copy),
+        })
+    }
+
+    fn running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// Request the collection to stop.
+    fn stop(&mut self) {
+        // Closing the standard input stops the collection.
+        drop(self.child.stdin.take());
+    }
+
+    /// Wait for the collection to end and for its events to be received.
+    fn wait(&mut self) -> Result<()> {
+        let status = self.child.wait()?;
+        if let Some(copy) = self.copy.take() {
+            match copy.join() {
+                Ok(Ok(size)) => info!("Received {size} bytes of events from {}", self.host),
+                Ok(Err(e)) => bail!("Could not receive the events of {}: {e}", self.host),
+                Err(_) => bail!("Could not join the copy thread of {}", self.host),
+            }
+        }
+
+        if !status.success() {
+            bail!("Collection on {} failed ({status})", self.host);
+        }
+        Ok(())
+    }
+}
+
+/// Events of a host being merged.
+struct HostEvents {
+    name: String,
+    source: Box<dyn FnMut() -> Result<Option<Event>>>,
+    /// Next event of the host, with its rebased timestamp.
+    next: Option<(u64, Event)>,
+    /// Offset of the host monotonic clock to the wall-clock time, as last
+    /// reported by the host.
+    offset: Option<TimeSpec>,
+    /// Last rebased timestamp.
+    last: u64,
+}
+
+impl HostEvents {
+    fn new(name: &str, source: Box<dyn FnMut() -> Result<Option<Event>>>) -> Self {
+        Self {
+            name: name.to_string(),
+            source,
+            next: None,
+            offset: None,
+            last: 0,
+        }
+    }
+
+    /// Read the next event of the host and prepare it for merging. The clock
+    /// offset of the first host reporting one is used as the reference.
+    fn advance(&mut self, reference: &mut Option<TimeSpec>) -> Result<()> {
+        let mut event = match (self.source)()? {
+            Some(event) => event,
+            None => {
+                self.next = None;
+                return Ok(());
+            }
+        };
+
+        // Clock offsets are the same for all hosts once merged.
+        if let Some(startup) = event.get_section_mut::<StartupEvent>(SectionId::Startup) {
+            self.offset = Some(startup.clock_monotonic_offset);
+            startup.clock_monotonic_offset =
+                *reference.get_or_insert(startup.clock_monotonic_offset);
+        }
+        if let Some(sync) = event.get_section_mut::<ClockSyncEvent>(SectionId::ClockSync) {
+            self.offset = Some(sync.clock_monotonic_offset);
+            sync.clock_monotonic_offset = *reference.get_or_insert(sync.clock_monotonic_offset);
+        }
+
+        if let Some(common) = event.get_section_mut::<CommonEvent>(SectionId::Common) {
+            if let (Some(offset), Some(reference)) = (self.offset, *reference) {
+                let rebased = common.timestamp as i128 + (offset - reference).as_nanos();
+                common.timestamp = rebased.clamp(0, u64::MAX as i128) as u64;
+            }
+            self.last = common.timestamp;
+        }
+
+        event.insert_section(
+            SectionId::Host,
+            Box::new(HostEvent {
+                name: self.name.clone(),
+            }),
+        )?;
+        self.next = Some((self.last, event));
+        Ok(())
+    }
+}
+
+/// Merge the events of multiple hosts, in order, and return the number of
+/// merged events.
+fn merge<F>(mut hosts: Vec<HostEvents>, mut output: F) -> Result<u64>
+where
+    F: FnMut(&Event) -> Result<()>,
+{
+    let mut reference = None;
+    for host in hosts.iter_mut() {
+        host.advance(&mut reference)?;
+    }
+
+    let mut count = 0;
+    loop {
+        let next = hosts
+            .iter()
+            .enumerate()
+            .filter_map(|(i, host)| host.next.as_ref().map(|(ts, _)| (i, *ts)))
+            .min_by_key(|(_, ts)| *ts);
+        let host = match next {
+            Some((i, _)) => &mut hosts[i],
+            None => break,
+        };
+
+        if let Some((_, event)) = host.next.take() {
+            output(&event)?;
+            count += 1;
+        }
+        host.advance(&mut reference)?;
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(timestamp: u64, offset: Option<(SectionId, i64)>) -> Event {
+        let mut event = Event::new();
+        event
+            .insert_section(
+                SectionId::Common,
+                Box::new(CommonEvent {
+                    timestamp,
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+
+        match offset {
+            Some((SectionId::Startup, sec)) => event
+                .insert_section(
+                    SectionId::Startup,
+                    Box::new(StartupEvent {
+                        retis_version: "unspec".to_string(),
+                        clock_monotonic_offset: TimeSpec::new(sec, 0),
+                        attached_probes: Vec::new(),
+                        events_ringbuf: None,
+                        session_id: String::new(),
+                    }),
+                )
+                .unwrap(),
+            Some((_, sec)) => event
+                .insert_section(
+                    SectionId::ClockSync,
+                    Box::new(ClockSyncEvent {
+                        clock_monotonic_offset: TimeSpec::new(sec, 0),
+                    }),
+                )
+                .unwrap(),
+            None => (),
+        }
+        event
+    }
+
+    fn host(name: &str, events: Vec<Event>) -> HostEvents {
+        let mut events = events.into_iter();
+        HostEvents::new(name, Box::new(move || Ok(events.next())))
+    }
+
+    #[test]
+    fn collect_args() {
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+        assert!(check_collect_args(&args(&["-c", "skb", "-f", "tcp port 80"])).is_ok());
+        assert!(check_collect_args(&args(&["-m", "sk_buff.mark == 1"])).is_ok());
+        assert!(check_collect_args(&args(&["-o"])).is_err());
+        assert!(check_collect_args(&args(&["-ofile"])).is_err());
+        assert!(check_collect_args(&args(&["--out", "file"])).is_err());
+        assert!(check_collect_args(&args(&["--out=file"])).is_err());
+        assert!(check_collect_args(&args(&["-c", "skb", "--print"])).is_err());
+        assert!(check_collect_args(&args(&["--compress", "zstd"])).is_err());
+    }
+
+    #[test]
+    fn merge_hosts() {
+        const SEC: u64 = 1_000_000_000;
+
+        // Host b booted 10s after host a, its monotonic clock is 10s behind.
+        let a = host(
+            "a",
+            vec![
+                event(100 * SEC, Some((SectionId::Startup, 1000))),
+                event(101 * SEC, None),
+                event(104 * SEC, None),
+            ],
+        );
+        let b = host(
+            "b",
+            vec![
+                event(90 * SEC, Some((SectionId::Startup, 1010))),
+                event(92 * SEC, None),
+                // Host b clock drifted by 1s.
+                event(92 * SEC + 1, Some((SectionId::ClockSync, 1011))),
+                event(92 * SEC + 2, None),
+            ],
+        );
+
+        let mut merged = Vec::new();
+        let count = merge(vec![a, b], |event| {
+            merged.push((
+                event.host().unwrap().name.clone(),
+                event.common().unwrap().timestamp,
+            ));
+            if let Some(startup) = event.startup() {
+                assert_eq!(startup.clock_monotonic_offset.sec(), 1000);
+            }
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(count, 7);
+        assert_eq!(
+            merged,
+            vec![
+                ("a".to_string(), 100 * SEC),
+                ("b".to_string(), 100 * SEC),
+                ("a".to_string(), 101 * SEC),
+                ("b".to_string(), 102 * SEC),
+                ("b".to_string(), 103 * SEC + 1),
+                ("b".to_string(), 103 * SEC + 2),
+                ("a".to_string(), 104 * SEC),
+            ]
+        );
+    }
+
+    #[test]
+    fn quote() {
+        assert_eq!(shell_quote("-f"), "'-f'");
+        assert_eq!(shell_quote("tcp port 80"), "'tcp port 80'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+}
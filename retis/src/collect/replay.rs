@@ -0,0 +1,147 @@
+//! # Replay
+//!
+//! Parses raw events recorded during a collection (`retis collect --raw-out`)
+//! as if they were coming from the BPF programs, to reproduce and test the
+//! unmarshaling of events offline.
+
+use std::{fs::OpenOptions, io::BufWriter, path::PathBuf};
+
+use anyhow::{bail, Result};
+use clap::Parser;
+use log::{info, warn};
+
+use crate::{
+    cli::*,
+    collect::collector::section_factories,
+    core::{
+        events::{
+            parse_raw_event,
+            raw::{RawReader, RawRecord},
+        },
+        probe::kernel::KernelEventFactory,
+    },
+    process::display::{PrintEvent, PrintEventFormat},
+};
+
+/// Replay raw events recorded during a collection (expert).
+///
+/// Raw events, recorded using `retis collect --raw-out`, are parsed as if they were coming from the
+/// BPF programs and the resulting events are written to a new events file. This allows to reproduce
+/// and test the parsing of events without a live kernel. Events should be replayed using the same
+/// version of Retis and on a machine of the same architecture as the collection. Kernel symbols are
+/// taken from the raw events file, but other kernel data (e.g. drop reasons) is still resolved
+/// using the running kernel and stack traces are not available.
+#[derive(Parser, Debug, Default)]
+#[command(name = "replay")]
+pub(crate) struct Replay {
+    /// File holding the raw events.
+    pub(super) input: PathBuf,
+
+    /// Write the parsed events to this file.
+    #[arg(short, long, default_value = "retis.replay.data")]
+    pub(super) out: PathBuf,
+
+    /// Fail on the first event which can't be parsed, instead of skipping it.
+    #[arg(long)]
+    pub(super) strict: bool,
+}
+
+impl SubCommandParserRunner for Replay {
+    fn run(&mut self) -> Result<()> {
+        let mut reader = RawReader::new(&self.input)?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.out)
+            .or_else(|_| bail!("Could not create or open '{}'", self.out.display()))?;
+        let mut printer = PrintEvent::new(Box::new(BufWriter::new(file)), PrintEventFormat::Json);
+
+        let mut section_factories = section_factories()?;
+        let (mut count, mut invalid) = (0, 0);
+        while let Some(record) = reader.next_record()? {
+            let raw = match record {
+                RawRecord::Event(raw) => raw,
+                RawRecord::Symbol(addr, name) => {
+                    match KernelEventFactory::from_factories(&mut section_factories) {
+                        Some(kernel) => kernel.add_symbol(addr, name),
+                        None => bail!("Can't get kernel section factory"),
+                    }
+                    continue;
+                }
+            };
+            match parse_raw_event(&raw, &mut section_factories) {
+                Ok(event) => {
+                    if let (true, Some(parse_error)) = (self.strict, event.parse_error()) {
+                        let errors = parse_error
+                            .errors
+                            .iter()
+                            .map(|e| format!("{}: {}", e.owner, e.error))
+                            .collect::<Vec<_>>();
+                        bail!(
+                            "Could not parse raw event #{}: {}",
+                            count + invalid,
+                            errors.join(", ")
+                        );
+                    }
+                    printer.process_one(&event)?;
+                    count += 1;
+                }
+                Err(e) if self.strict => {
+                    bail!("Could not parse raw event #{}: {e}", count + invalid)
+                }
+                Err(e) => {
+                    warn!("Could not parse raw event: {e}");
+                    invalid += 1;
+                }
+            }
+        }
+        printer.flush()?;
+
+        info!(
+            "{count} event(s) replayed and written to '{}'",
+            self.out.display()
+        );
+        if invalid > 0 {
+            warn!("{invalid} raw event(s) could not be parsed");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    // The fixture holds raw events recorded on a little endian machine,
+    // using kernel symbols not found in the test kallsyms file.
+    #[test]
+    #[cfg(target_endian = "little")]
+    fn replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("retis.replay.data");
+
+        Replay {
+            input: PathBuf::from("test_data/raw/events.raw"),
+            out: out.clone(),
+            strict: true,
+        }
+        .run()
+        .unwrap();
+
+        let parse = |path| -> Vec<serde_json::Value> {
+            fs::read_to_string(path)
+                .unwrap()
+                .lines()
+                .map(|l| serde_json::from_str(l).unwrap())
+                .collect()
+        };
+        assert_eq!(
+            parse(out),
+            parse(PathBuf::from("test_data/raw/events.json"))
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! # Status
+//!
+//! Machine-readable status of a collection, for wrappers and GUIs to track its
+//! state without parsing the logs. Status records are written as JSON objects,
+//! one per line, to a file descriptor inherited from the parent process
+//! (`--status-fd`).
+
+use std::{
+    fs::File,
+    io::Write,
+    os::fd::{BorrowedFd, RawFd},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Result};
+use log::warn;
+use serde::Serialize;
+
+/// Status record of a collection.
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub(crate) enum Status<'a> {
+    /// The collection is starting.
+    Started {
+        pid: u32,
+        session_id: &'a str,
+        output: Option<&'a Path>,
+    },
+    /// Probes were attached and events are being collected.
+    ProbesAttached { probes: usize },
+    /// The first event was received.
+    FirstEvent,
+    /// The collection is stopping.
+    Stopping { events: u64 },
+    /// Events lost by the probes (e.g. the events map was full) and dropped to
+    /// stay under the memory limit, and eBPF log messages lost (the log map
+    /// was full).
+    Losses { probes: u64, memory: u64, logs: u64 },
+}
+
+/// Record written to the status fd.
+#[derive(Serialize)]
+struct StatusRecord<'a> {
+    /// Time the record was emitted at, in nanoseconds since the epoch.
+    timestamp: u64,
+    #[serde(flatten)]
+    status: &'a Status<'a>,
+}
+
+/// Writes status records, if asked to.
+#[derive(Default)]
+pub(crate) struct StatusReporter {
+    file: Option<File>,
+}
+
+impl StatusReporter {
+    pub(crate) fn new(fd: Option<RawFd>) -> Result<Self> {
+        let fd = match fd {
+            Some(fd) => fd,
+            None => return Ok(Self::default()),
+        };
+
+        if unsafe { libc::fcntl(fd, libc::F_GETFD) } < 0 {
+            bail!("Invalid status fd ({fd})");
+        }
+
+        // Work on a duplicate of the fd, so the inherited one (which can be
+        // e.g. stdout) is not closed when the reporter is dropped.
+        // Safety: the fd was checked to be valid above.
+        let fd = unsafe { BorrowedFd::borrow_raw(fd) }.try_clone_to_owned()?;
+
+        Ok(Self {
+            file: Some(File::from(fd)),
+        })
+    }
+
+    /// Report a status. Failing to do so is not fatal to the collection;
+    /// reporting is stopped instead.
+    pub(crate) fn report(&mut self, status: Status) {
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => return,
+        };
+
+        if let Err(e) = record(&status).and_then(|r| Ok(file.write_all(r.as_bytes())?)) {
+            warn!("Could not report status, stopping reporting it: {e}");
+            self.file = None;
+        }
+    }
+}
+
+/// Format a status record, as a single line.
+fn record(status: &Status) -> Result<String> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64;
+
+    let mut record = serde_json::to_string(&StatusRecord { timestamp, status })?;
+    record.push('\n');
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::fd::AsRawFd;
+
+    use super::*;
+
+    #[test]
+    fn status_record() {
+        let check = |status, expected: &str| {
+            let r = record(&status).unwrap();
+            assert!(r.starts_with(r#"{"timestamp":"#));
+            assert!(r.ends_with(&format!("{expected}}}\n")));
+        };
+
+        check(
+            Status::Started {
+                pid: 42,
+                session_id: "id",
+                output: Some(Path::new("retis.data")),
+            },
+            r#","status":"started","pid":42,"session_id":"id","output":"retis.data""#,
+        );
+        check(Status::FirstEvent, r#","status":"first_event""#);
+        check(
+            Status::Losses {
+                probes: 1,
+                memory: 0,
+                logs: 2,
+            },
+            r#","status":"losses","probes":1,"memory":0,"logs":2"#,
+        );
+
+        assert!(StatusReporter::new(Some(-1)).is_err());
+        assert!(StatusReporter::new(None).unwrap().file.is_none());
+    }
+
+    #[test]
+    fn status_fd_kept_open() {
+        let file = tempfile::tempfile().unwrap();
+        let fd = file.as_raw_fd();
+
+        let mut reporter = StatusReporter::new(Some(fd)).unwrap();
+        reporter.report(Status::FirstEvent);
+        drop(reporter);
+
+        assert!(unsafe { libc::fcntl(fd, libc::F_GETFD) } >= 0);
+    }
+}
@@ -8,19 +8,29 @@ use std::{
     collections::HashMap,
     mem,
     os::fd::{AsFd, AsRawFd, RawFd},
-    sync::mpsc,
+    panic,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc,
+    },
     thread,
     time::Duration,
 };
 
 use anyhow::{anyhow, bail, Result};
 use btf_rs::Type;
-use log::{error, log, Level};
+use libbpf_rs::MapCore;
+use log::{error, info, log, warn, Level};
+use once_cell::sync::OnceCell;
 use plain::Plain;
 
 use crate::{
-    bindings::events_uapi::*, core::inspect::inspector, event_section_factory, events::*,
-    helpers::signals::Running,
+    bindings::events_uapi::*,
+    core::{events::raw::RawRecorder, inspect::inspector, probe::kernel::KernelEventFactory},
+    event_section_factory,
+    events::*,
+    helpers::{mem::MemGuard, signals::Running},
 };
 
 /// Raw event sections for common.
@@ -30,6 +40,9 @@ pub(super) const COMMON_SECTION_TASK: u64 = 1;
 /// Timeout when polling for new events from BPF.
 const BPF_EVENTS_POLL_TIMEOUT_MS: u64 = 200;
 
+/// Number of pages per cpu of the perf buffers, when used.
+const BPF_PERF_BUF_PAGES: usize = 256;
+
 /// Macro used to convert c_char into String.
 /// The macro returns error if the conversion fails.
 #[macro_export]
@@ -83,61 +96,164 @@ pub(crate) enum EventResult {
     Timeout,
 }
 
+/// Returns true if events should be reported using perf buffers, which is used
+/// as a fallback on kernels not supporting BPF ring buffers. The result is
+/// cached as it must be consistent between the maps and the BPF programs.
+pub(crate) fn events_use_perf_buf() -> bool {
+    static PERF_BUF: OnceCell<bool> = OnceCell::new();
+
+    *PERF_BUF.get_or_init(|| match libbpf_rs::MapType::RingBuf.is_supported() {
+        Ok(supported) => !supported,
+        Err(e) => {
+            warn!("Could not detect BPF ring buffer support, assuming it is supported ({e})");
+            false
+        }
+    })
+}
+
+/// Size of the log map for it to hold `entries` log messages. Ring buffer
+/// sizes must be a power of 2 multiple of the page size; each message also
+/// comes with a ring buffer header.
+fn log_map_size(entries: u32) -> Result<u32> {
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if page_size <= 0 {
+        bail!("Could not get the page size");
+    }
+
+    let entry_size = mem::size_of::<retis_log_event>() as u32 + libbpf_sys::BPF_RINGBUF_HDR_SZ;
+    match entry_size.checked_mul(entries) {
+        Some(size) => Ok(size.next_power_of_two().max(page_size as u32)),
+        None => bail!("Too many log map entries ({entries})"),
+    }
+}
+
+/// Read a per-cpu counter map, holding a single u64, and sum its values.
+#[cfg(not(test))]
+fn read_counter(map: &libbpf_rs::MapHandle) -> Result<u64> {
+    Ok(map
+        .lookup_percpu(&0u32.to_ne_bytes(), libbpf_rs::MapFlags::ANY)?
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|count| count.as_slice().try_into().ok())
+        .map(u64::from_ne_bytes)
+        .sum())
+}
+
 /// BPF events factory retrieving and unmarshaling events coming from the BPF
 /// parts.
 #[cfg(not(test))]
 pub(crate) struct BpfEventsFactory {
     map: libbpf_rs::MapHandle,
     log_map: libbpf_rs::MapHandle,
+    /// Per-cpu count of log messages lost because the log map was full,
+    /// shared by all BPF objects.
+    log_lost_map: libbpf_rs::MapHandle,
+    /// Per-cpu sequence numbers of events, shared by all BPF objects.
+    seq_map: libbpf_rs::MapHandle,
+    /// Per-cpu count of events which could not be reserved in the events map,
+    /// shared by all BPF objects.
+    lost_map: libbpf_rs::MapHandle,
+    /// Number of events lost by the perf buffers, when used.
+    perf_lost: Arc<AtomicU64>,
     /// Receiver channel to retrieve events from the processing loop.
     rxc: Option<mpsc::Receiver<Event>>,
     /// Polling thread handle.
     handle: Option<thread::JoinHandle<()>>,
     log_handle: Option<thread::JoinHandle<()>>,
     run_state: Running,
+    /// Memory guard, if a memory limit was set.
+    mem_guard: Option<MemGuard>,
+    /// Recorder of the raw events, if asked to.
+    raw_recorder: Option<RawRecorder>,
 }
 
 #[cfg(not(test))]
 impl BpfEventsFactory {
-    pub(crate) fn new() -> Result<BpfEventsFactory> {
-        let opts = libbpf_sys::bpf_map_create_opts {
-            sz: mem::size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
-            ..Default::default()
-        };
-        let map = libbpf_rs::MapHandle::create(
-            libbpf_rs::MapType::RingBuf,
-            Some("events_map"),
-            0,
-            0,
+    /// Create a new factory. The log map is sized to hold `log_entries` log
+    /// messages.
+    pub(crate) fn new(log_entries: u32) -> Result<BpfEventsFactory> {
+        if events_use_perf_buf() {
+            info!("BPF ring buffers are not supported, falling back to perf buffers (eBPF logs won't be reported)");
+        }
+
+        let map = Self::create_map(
+            "events_map",
             mem::size_of::<RawEvent>() as u32 * BPF_EVENTS_MAX,
-            &opts,
         )
         .or_else(|e| bail!("Failed to create events map: {}", e))?;
 
-        let opts = libbpf_sys::bpf_map_create_opts {
-            sz: mem::size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
-            ..Default::default()
-        };
-        let log_map = libbpf_rs::MapHandle::create(
-            libbpf_rs::MapType::RingBuf,
-            Some("log_map"),
-            0,
-            0,
-            mem::size_of::<retis_log_event>() as u32 * LOG_EVENTS_MAX,
-            &opts,
-        )
-        .or_else(|e| bail!("Failed to create log map: {}", e))?;
+        let log_map = Self::create_map("log_map", log_map_size(log_entries)?)
+            .or_else(|e| bail!("Failed to create log map: {}", e))?;
+
+        let log_lost_map = Self::create_counter_map("log_lost_map")
+            .or_else(|e| bail!("Failed to create log lost map: {}", e))?;
+
+        let seq_map = Self::create_counter_map("events_seq_map")
+            .or_else(|e| bail!("Failed to create events sequence map: {}", e))?;
+
+        let lost_map = Self::create_counter_map("events_lost_map")
+            .or_else(|e| bail!("Failed to create events lost map: {}", e))?;
 
         Ok(BpfEventsFactory {
             map,
             log_map,
+            log_lost_map,
+            seq_map,
+            lost_map,
+            perf_lost: Arc::new(AtomicU64::new(0)),
             rxc: None,
             handle: None,
             log_handle: None,
             run_state: Running::new(),
+            mem_guard: None,
+            raw_recorder: None,
+        })
+    }
+
+    /// Create a map to report data from the BPF parts: a ring buffer of the
+    /// given size, or a perf event array if falling back to perf buffers.
+    fn create_map(name: &str, size: u32) -> Result<libbpf_rs::MapHandle> {
+        let opts = libbpf_sys::bpf_map_create_opts {
+            sz: mem::size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
+            ..Default::default()
+        };
+
+        Ok(match events_use_perf_buf() {
+            false => libbpf_rs::MapHandle::create(
+                libbpf_rs::MapType::RingBuf,
+                Some(name),
+                0,
+                0,
+                size,
+                &opts,
+            )?,
+            // Perf event arrays hold one perf event fd per cpu.
+            true => libbpf_rs::MapHandle::create(
+                libbpf_rs::MapType::PerfEventArray,
+                Some(name),
+                mem::size_of::<u32>() as u32,
+                mem::size_of::<u32>() as u32,
+                libbpf_rs::num_possible_cpus()? as u32,
+                &opts,
+            )?,
         })
     }
 
+    /// Create a per-cpu map holding a single u64 counter.
+    fn create_counter_map(name: &str) -> Result<libbpf_rs::MapHandle> {
+        Ok(libbpf_rs::MapHandle::create(
+            libbpf_rs::MapType::PercpuArray,
+            Some(name),
+            mem::size_of::<u32>() as u32,
+            mem::size_of::<u64>() as u32,
+            1,
+            &libbpf_sys::bpf_map_create_opts {
+                sz: mem::size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
+                ..Default::default()
+            },
+        )?)
+    }
+
     /// Get the events map fd for reuse.
     pub(crate) fn map_fd(&self) -> RawFd {
         self.map.as_fd().as_raw_fd()
@@ -148,6 +264,52 @@ impl BpfEventsFactory {
         self.log_map.as_fd().as_raw_fd()
     }
 
+    /// Get the log lost map fd for reuse.
+    pub(crate) fn log_lost_map_fd(&self) -> RawFd {
+        self.log_lost_map.as_fd().as_raw_fd()
+    }
+
+    /// Get the number of log messages lost because the log map was full.
+    pub(crate) fn lost_logs(&self) -> Result<u64> {
+        read_counter(&self.log_lost_map)
+    }
+
+    /// Get the events lost map fd for reuse.
+    pub(crate) fn lost_map_fd(&self) -> RawFd {
+        self.lost_map.as_fd().as_raw_fd()
+    }
+
+    /// Get the number of events the BPF programs could not report since the
+    /// factory was created, e.g. because the events map was full.
+    pub(crate) fn lost_events(&self) -> Result<u64> {
+        Ok(read_counter(&self.lost_map)? + self.perf_lost.load(Ordering::Relaxed))
+    }
+
+    /// Get the events sequence map fd for reuse.
+    pub(crate) fn seq_map_fd(&self) -> RawFd {
+        self.seq_map.as_fd().as_raw_fd()
+    }
+
+    /// Record the raw events to a file, before they are parsed. Must be
+    /// called before starting the factory.
+    pub(crate) fn record_raw(&mut self, path: &Path) -> Result<()> {
+        self.raw_recorder = Some(RawRecorder::new(path)?);
+        Ok(())
+    }
+
+    /// Get the physical location of the events ring buffer, for post-mortem
+    /// analysis.
+    pub(crate) fn ringbuf_layout(&self) -> Result<RingBufLayout> {
+        if events_use_perf_buf() {
+            bail!("Events are reported using perf buffers");
+        }
+
+        super::postmortem::ringbuf_layout(
+            self.map.as_fd(),
+            mem::size_of::<RawEvent>() as u64 * BPF_EVENTS_MAX as u64,
+        )
+    }
+
     fn ringbuf_handler<CB>(
         &self,
         map: &libbpf_rs::MapHandle,
@@ -177,13 +339,51 @@ impl BpfEventsFactory {
             }
         }))
     }
+
+    fn perfbuf_handler<CB>(
+        &self,
+        map: &libbpf_rs::MapHandle,
+        mut pb_handler: CB,
+    ) -> Result<thread::JoinHandle<()>>
+    where
+        CB: FnMut(&[u8]) -> i32 + 'static,
+    {
+        let perf_lost = self.perf_lost.clone();
+        let pb = libbpf_rs::PerfBufferBuilder::new(map)
+            .pages(BPF_PERF_BUF_PAGES)
+            .sample_cb(move |_, data: &[u8]| {
+                pb_handler(data);
+            })
+            .lost_cb(move |cpu, count| {
+                perf_lost.fetch_add(count, Ordering::Relaxed);
+                warn!("Lost {count} event(s) on cpu {cpu}");
+            })
+            .build()?;
+        let rs = self.run_state.clone();
+        // Start an event polling thread.
+        Ok(thread::spawn(move || {
+            while rs.running() {
+                if let Err(e) = pb.poll(Duration::from_millis(BPF_EVENTS_POLL_TIMEOUT_MS)) {
+                    match e.kind() {
+                        libbpf_rs::ErrorKind::Interrupted => (),
+                        _ => error!("Unexpected error while polling ({e})"),
+                    }
+                }
+            }
+        }))
+    }
 }
 
 #[cfg(not(test))]
 impl BpfEventsFactory {
     /// This starts the event polling mechanism. A dedicated thread is started
-    /// for events to be retrieved and processed.
-    pub(crate) fn start(&mut self, mut section_factories: SectionFactories) -> Result<()> {
+    /// for events to be retrieved and processed. If a memory guard is given,
+    /// events are dropped before being parsed when it asks to shed load.
+    pub(crate) fn start(
+        &mut self,
+        mut section_factories: SectionFactories,
+        mem_guard: Option<MemGuard>,
+    ) -> Result<()> {
         if section_factories.is_empty() {
             bail!("No section factory, can't parse events, aborting");
         }
@@ -192,6 +392,9 @@ impl BpfEventsFactory {
         let (txc, rxc) = mpsc::channel();
         self.rxc = Some(rxc);
 
+        self.mem_guard = mem_guard.clone();
+        let mut raw_recorder = self.raw_recorder.take();
+
         let run_state = self.run_state.clone();
         // Closure to handle the raw events coming from the BPF part.
         let process_event = move |data: &[u8]| -> i32 {
@@ -203,8 +406,27 @@ impl BpfEventsFactory {
             if !run_state.running() {
                 return -4;
             }
+            // Shed load if we're approaching the memory limit.
+            if mem_guard.as_ref().is_some_and(|g| !g.keep()) {
+                return 0;
+            }
             // Parse the raw event.
-            let event = match parse_raw_event(data, &mut section_factories) {
+            let event = parse_raw_event(data, &mut section_factories);
+            // Record the raw event, if asked to, along with the kernel symbols
+            // resolved while parsing it. Stop recording on errors.
+            if let Some(recorder) = raw_recorder.as_mut() {
+                let res = match KernelEventFactory::from_factories(&mut section_factories) {
+                    Some(kernel) => recorder.record_symbols(kernel.symbols()),
+                    None => Ok(()),
+                }
+                .and_then(|_| recorder.record(data));
+
+                if let Err(e) = res {
+                    error!("Could not record raw event, stopping raw recording: {e}");
+                    raw_recorder = None;
+                }
+            }
+            let event = match event {
                 Ok(event) => event,
                 Err(e) => {
                     error!("Could not parse raw event: {}", e);
@@ -213,8 +435,13 @@ impl BpfEventsFactory {
             };
 
             // Send the event into the events channel for future retrieval.
-            if let Err(e) = txc.send(event) {
-                error!("Could not send event: {}", e);
+            match txc.send(event) {
+                Ok(_) => {
+                    if let Some(guard) = &mem_guard {
+                        guard.queued();
+                    }
+                }
+                Err(e) => error!("Could not send event: {}", e),
             }
 
             0
@@ -262,10 +489,15 @@ impl BpfEventsFactory {
             0
         };
 
-        // Finally make our ring buffers and associate maps to their
-        // respective events processing closure.
-        self.handle = Some(self.ringbuf_handler(&self.map, process_event)?);
-        self.log_handle = Some(self.ringbuf_handler(&self.log_map, process_log)?);
+        // Finally make our ring (or perf) buffers and associate maps to their
+        // respective events processing closure. eBPF logs are not reported
+        // when using perf buffers.
+        if events_use_perf_buf() {
+            self.handle = Some(self.perfbuf_handler(&self.map, process_event)?);
+        } else {
+            self.handle = Some(self.ringbuf_handler(&self.map, process_event)?);
+            self.log_handle = Some(self.ringbuf_handler(&self.log_map, process_log)?);
+        }
 
         Ok(())
     }
@@ -292,14 +524,19 @@ impl BpfEventsFactory {
             None => bail!("Can't get event, no rx channel found."),
         };
 
-        Ok(match timeout {
+        let event = match timeout {
             Some(timeout) => match rxc.recv_timeout(timeout) {
-                Ok(event) => EventResult::Event(event),
-                Err(mpsc::RecvTimeoutError::Timeout) => EventResult::Timeout,
+                Ok(event) => event,
+                Err(mpsc::RecvTimeoutError::Timeout) => return Ok(EventResult::Timeout),
                 Err(e) => return Err(anyhow!(e)),
             },
-            None => EventResult::Event(rxc.recv()?),
-        })
+            None => rxc.recv()?,
+        };
+
+        if let Some(guard) = &self.mem_guard {
+            guard.dequeued();
+        }
+        Ok(EventResult::Event(event))
     }
 }
 
@@ -378,21 +615,56 @@ pub(crate) fn parse_raw_event<'a>(
             .push(raw_section);
     }
 
+    // Sections failing to be parsed are reported in a parse error section,
+    // keeping the rest of the event. The common section is required though.
     let mut event = Event::new();
+    let mut errors = Vec::new();
     raw_sections.drain().try_for_each(|(owner, sections)| {
-        let factory = factories
+        let section = factories
             .get_mut(&owner)
-            .ok_or_else(|| anyhow!("Unknown factory {}", owner as u8))?;
+            .ok_or_else(|| anyhow!("Unknown factory"))
+            .and_then(|factory| create_section(factory.as_mut(), sections))
+            .and_then(|section| event.insert_section(SectionId::from_u8(section.id())?, section));
 
-        let section = factory
-            .create(sections)
-            .map_err(|e| anyhow!("Factory {} failed to parse section: {e}", owner as u8))?;
-        event.insert_section(SectionId::from_u8(section.id())?, section)
+        match section {
+            Err(e) if owner == FactoryId::Common => {
+                bail!("Factory {} failed to parse section: {e}", owner as u8)
+            }
+            Err(e) => {
+                error!("Factory {} failed to parse section: {e}", owner as u8);
+                errors.push(SectionParseError {
+                    owner: format!("{owner:?}"),
+                    error: e.to_string(),
+                });
+            }
+            Ok(()) => (),
+        }
+        Ok(())
     })?;
 
+    if !errors.is_empty() {
+        event.insert_section(SectionId::ParseError, Box::new(ParseErrorEvent { errors }))?;
+    }
+
     Ok(event)
 }
 
+/// Create a section using its factory, turning panics into errors so a buggy
+/// factory can't bring the events polling thread down.
+fn create_section(
+    factory: &mut dyn EventSectionFactory,
+    sections: Vec<BpfRawSection>,
+) -> Result<Box<dyn EventSection>> {
+    panic::catch_unwind(panic::AssertUnwindSafe(|| factory.create(sections))).unwrap_or_else(|e| {
+        let msg = e
+            .downcast_ref::<&str>()
+            .map(|msg| msg.to_string())
+            .or_else(|| e.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown reason".to_string());
+        bail!("Factory panicked: {msg}")
+    })
+}
+
 /// Helper to check a raw section validity and parse it into a structured type.
 pub(crate) fn parse_raw_section<'a, T>(raw_section: &'a BpfRawSection) -> Result<&'a T> {
     if raw_section.data.len() != mem::size_of::<T>() {
@@ -407,6 +679,24 @@ pub(crate) fn parse_raw_section<'a, T>(raw_section: &'a BpfRawSection) -> Result
     Ok(unsafe { mem::transmute::<&u8, &T>(&raw_section.data[0]) })
 }
 
+/// Helper to parse a raw section made of a fixed size part, parsed into a
+/// structured type, followed by variable size data.
+pub(crate) fn parse_raw_section_with_data<'a, T>(
+    raw_section: &'a BpfRawSection,
+) -> Result<(&'a T, &'a [u8])> {
+    if raw_section.data.len() < mem::size_of::<T>() {
+        bail!(
+            "Section data {} is smaller than expected ({} < {})",
+            any::type_name::<T>(),
+            raw_section.data.len(),
+            mem::size_of::<T>()
+        );
+    }
+
+    let (fixed, data) = raw_section.data.split_at(mem::size_of::<T>());
+    Ok((unsafe { mem::transmute::<&u8, &T>(&fixed[0]) }, data))
+}
+
 /// Helper to parse a single raw section from BPF raw sections, checking the
 /// section validity and parsing it into a structured type.
 pub(crate) fn parse_single_raw_section<'a, T>(raw_sections: &'a [BpfRawSection]) -> Result<&'a T> {
@@ -453,6 +743,7 @@ impl RawEventSectionFactory for CommonEventFactory {
                     let raw = parse_raw_section::<common_event>(section)?;
 
                     common.timestamp = raw.timestamp;
+                    common.seq = Some(raw.seq);
                     common.smp_id = Some(raw.smp_id);
                 }
                 COMMON_SECTION_TASK => common.task = Some(unmarshal_task(section)?),
@@ -480,16 +771,28 @@ pub(super) fn unmarshal_task(raw_section: &BpfRawSection) -> Result<TaskEvent> {
 pub(crate) struct BpfEventsFactory;
 #[cfg(test)]
 impl BpfEventsFactory {
-    pub(crate) fn new() -> Result<BpfEventsFactory> {
+    pub(crate) fn new(_: u32) -> Result<BpfEventsFactory> {
         Ok(BpfEventsFactory {})
     }
     pub(crate) fn map_fd(&self) -> i32 {
         0
     }
+    pub(crate) fn lost_logs(&self) -> Result<u64> {
+        Ok(0)
+    }
+    pub(crate) fn lost_events(&self) -> Result<u64> {
+        Ok(0)
+    }
+    pub(crate) fn ringbuf_layout(&self) -> Result<RingBufLayout> {
+        bail!("Not supported in tests");
+    }
+    pub(crate) fn record_raw(&mut self, _: &Path) -> Result<()> {
+        Ok(())
+    }
 }
 #[cfg(test)]
 impl BpfEventsFactory {
-    pub(crate) fn start(&mut self, _: SectionFactories) -> Result<()> {
+    pub(crate) fn start(&mut self, _: SectionFactories, _: Option<MemGuard>) -> Result<()> {
         Ok(())
     }
     pub(crate) fn next_event(&mut self, _: Option<Duration>) -> Result<EventResult> {
@@ -567,8 +870,16 @@ pub(crate) enum FactoryId {
     Ovs = 7,
     Nft = 8,
     Ct = 9,
+    Sock = 10,
+    Netlink = 11,
+    Icmp = 12,
+    Tc = 13,
+    Ipvs = 14,
+    Seg6 = 15,
+    Mptcp = 16,
+    Tls = 17,
     // TODO: use std::mem::variant_count once in stable.
-    _MAX = 10,
+    _MAX = 18,
 }
 
 impl FactoryId {
@@ -585,6 +896,14 @@ impl FactoryId {
             7 => Ovs,
             8 => Nft,
             9 => Ct,
+            10 => Sock,
+            11 => Netlink,
+            12 => Icmp,
+            13 => Tc,
+            14 => Ipvs,
+            15 => Seg6,
+            16 => Mptcp,
+            17 => Tls,
             x => bail!("Can't construct a FactoryId from {}", x),
         })
     }
@@ -606,7 +925,15 @@ pub(crate) mod benchmark {
 
     impl RawSectionBuilder for common_event {
         fn build_raw(out: &mut Vec<u8>) -> Result<()> {
-            let data = Self::default();
+            Self::build_raw_with(out, &RawEventConfig::default())
+        }
+
+        fn build_raw_with(out: &mut Vec<u8>, config: &RawEventConfig) -> Result<()> {
+            let data = Self {
+                timestamp: config.timestamp,
+                seq: config.seq,
+                smp_id: config.smp_id,
+            };
             build_raw_section(
                 out,
                 FactoryId::Common as u8,
@@ -682,10 +1009,35 @@ mod tests {
         }
     }
 
+    #[event_section_factory(FactoryId::Kernel)]
+    #[derive(Default)]
+    struct FailingEventFactory {}
+
+    impl RawEventSectionFactory for FailingEventFactory {
+        fn create(&mut self, _: Vec<BpfRawSection>) -> Result<Box<dyn EventSection>> {
+            bail!("Invalid section")
+        }
+    }
+
+    #[event_section_factory(FactoryId::Userspace)]
+    #[derive(Default)]
+    struct PanickingEventFactory {}
+
+    impl RawEventSectionFactory for PanickingEventFactory {
+        fn create(&mut self, _: Vec<BpfRawSection>) -> Result<Box<dyn EventSection>> {
+            panic!("Invalid section");
+        }
+    }
+
     #[test]
     fn parse_raw_event() {
         let mut factories: SectionFactories = HashMap::new();
         factories.insert(FactoryId::Common, Box::<TestEventFactory>::default());
+        factories.insert(FactoryId::Kernel, Box::<FailingEventFactory>::default());
+        factories.insert(
+            FactoryId::Userspace,
+            Box::<PanickingEventFactory>::default(),
+        );
 
         // Empty event.
         let data = [];
@@ -808,5 +1160,76 @@ mod tests {
         let section = event.get_section::<TestEvent>(SectionId::Common).unwrap();
         assert!(section.field1 == Some(42));
         assert!(section.field2 == Some(1337));
+
+        // Valid event, a section failing to be parsed. Other sections are
+        // kept and the error is reported.
+        let data = [
+            17,
+            0,
+            // Section 1
+            SectionId::Common as u8,
+            DATA_TYPE_U64,
+            8,
+            0,
+            42,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            // Section 2
+            FactoryId::Kernel as u8,
+            0,
+            1,
+            0,
+            42,
+        ];
+        let event = super::parse_raw_event(&data, &mut factories).unwrap();
+        let section = event.get_section::<TestEvent>(SectionId::Common).unwrap();
+        assert!(section.field0 == Some(42));
+        let errors = &event.parse_error().unwrap().errors;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].owner, "Kernel");
+
+        // Valid event, a section factory panicking.
+        let data = [
+            17,
+            0,
+            // Section 1
+            SectionId::Common as u8,
+            DATA_TYPE_U64,
+            8,
+            0,
+            42,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            // Section 2
+            FactoryId::Userspace as u8,
+            0,
+            1,
+            0,
+            42,
+        ];
+        let event = super::parse_raw_event(&data, &mut factories).unwrap();
+        let errors = &event.parse_error().unwrap().errors;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].error, "Factory panicked: Invalid section");
+    }
+
+    #[test]
+    fn log_map_size() {
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u32;
+
+        assert_eq!(super::log_map_size(1).unwrap(), page_size);
+        assert_eq!(super::log_map_size(128).unwrap(), page_size.max(32 * 1024));
+        assert_eq!(super::log_map_size(65536).unwrap(), 16 * 1024 * 1024);
+        assert!(super::log_map_size(u32::MAX).is_err());
     }
 }
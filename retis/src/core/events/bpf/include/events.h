@@ -32,6 +32,14 @@ enum retis_event_owners {
 	COLLECTOR_OVS = 7,
 	COLLECTOR_NFT = 8,
 	COLLECTOR_CT = 9,
+	COLLECTOR_SOCK = 10,
+	COLLECTOR_NETLINK = 11,
+	COLLECTOR_ICMP = 12,
+	COLLECTOR_TC = 13,
+	COLLECTOR_IPVS = 14,
+	COLLECTOR_SEG6 = 15,
+	COLLECTOR_MPTCP = 16,
+	COLLECTOR_TLS = 17,
 };
 
 struct retis_raw_event {
@@ -46,7 +54,10 @@ struct retis_raw_event_section_header {
 	u16 size;
 } __packed;
 
-/* Please keep synced with its Rust counterpart. */
+/* Please keep synced with its Rust counterpart. When falling back to perf
+ * buffers, this and the log map are turned into perf event arrays by user-space
+ * before the objects are loaded.
+ */
 struct {
 	__uint(type, BPF_MAP_TYPE_RINGBUF);
 	__uint(max_entries, sizeof(struct retis_raw_event) * EVENTS_MAX);
@@ -58,13 +69,80 @@ struct {
 	__uint(max_entries, sizeof(struct retis_log_event) * LOG_EVENTS_MAX);
 } log_map SEC(".maps");
 
+/* Per-cpu count of log messages lost because the log map was full. Shared by
+ * all objects, please keep synced with its Rust counterpart.
+ */
+struct {
+	__uint(type, BPF_MAP_TYPE_PERCPU_ARRAY);
+	__uint(max_entries, 1);
+	__type(key, u32);
+	__type(value, u64);
+} log_lost_map SEC(".maps");
+
+/* Per-cpu buffer used to build events when using perf buffers, as those do not
+ * allow to reserve space in advance.
+ */
+struct {
+	__uint(type, BPF_MAP_TYPE_PERCPU_ARRAY);
+	__uint(max_entries, 1);
+	__type(key, u32);
+	__type(value, struct retis_raw_event);
+} events_scratch_map SEC(".maps");
+
+/* Per-cpu sequence number of the next event, used to detect and localize lost
+ * events in post-processing. Shared by all objects, please keep synced with its
+ * Rust counterpart.
+ */
+struct {
+	__uint(type, BPF_MAP_TYPE_PERCPU_ARRAY);
+	__uint(max_entries, 1);
+	__type(key, u32);
+	__type(value, u64);
+} events_seq_map SEC(".maps");
+
+/* Get the next sequence number of the current cpu. Numbers are consumed by
+ * events being sent and by events failing to be allocated, so the latter show
+ * up as gaps.
+ */
+static __always_inline u64 next_event_seq()
+{
+	u32 key = 0;
+	u64 *seq;
+
+	seq = bpf_map_lookup_elem(&events_seq_map, &key);
+	if (!seq)
+		return 0;
+
+	return (*seq)++;
+}
+
+/* Per-cpu count of events which could not be reserved in the events map (e.g.
+ * it was full). Shared by all objects, please keep synced with its Rust
+ * counterpart.
+ */
+struct {
+	__uint(type, BPF_MAP_TYPE_PERCPU_ARRAY);
+	__uint(max_entries, 1);
+	__type(key, u32);
+	__type(value, u64);
+} events_lost_map SEC(".maps");
+
 static __always_inline struct retis_raw_event *get_event()
 {
 	struct retis_raw_event *event;
-
-	event = bpf_ringbuf_reserve(&events_map, sizeof(*event), 0);
-	if (!event)
+	u32 key = 0;
+	u64 *lost;
+
+	if (events_perf_buf)
+		event = bpf_map_lookup_elem(&events_scratch_map, &key);
+	else
+		event = bpf_ringbuf_reserve(&events_map, sizeof(*event), 0);
+	if (!event) {
+		lost = bpf_map_lookup_elem(&events_lost_map, &key);
+		if (lost)
+			(*lost)++;
 		return NULL;
+	}
 
 	event->size = 0;
 	return event;
@@ -72,12 +150,29 @@ static __always_inline struct retis_raw_event *get_event()
 
 static __always_inline void discard_event(struct retis_raw_event *event)
 {
-	bpf_ringbuf_discard(event, 0);
+	if (!events_perf_buf)
+		bpf_ringbuf_discard(event, 0);
 }
 
-static __always_inline void send_event(struct retis_raw_event *event)
+static __always_inline void send_event(void *ctx, struct retis_raw_event *event)
 {
-	bpf_ringbuf_submit(event, 0);
+	u64 size;
+
+	if (!events_perf_buf) {
+		bpf_ringbuf_submit(event, 0);
+		return;
+	}
+
+	/* Only output the used part of the event, including its size field. The
+	 * size is bounded explicitly for the verifier; this can't be hit in
+	 * practice.
+	 */
+	size = sizeof(event->size) + event->size;
+	barrier_var(size);
+	if (size > sizeof(*event))
+		size = sizeof(*event);
+
+	bpf_perf_event_output(ctx, &events_map, BPF_F_CURRENT_CPU, event, size);
 }
 
 static __always_inline void *get_event_section(struct retis_raw_event *event,
@@ -134,6 +229,7 @@ static __always_inline u16 get_event_size(struct retis_raw_event *event)
 
 struct common_event {
 	u64 timestamp;
+	u64 seq;
 	u32 smp_id;
 } __binding;
 
@@ -5,3 +5,6 @@ pub(crate) use bpf::*;
 
 pub(crate) mod factory;
 pub(crate) use factory::*;
+
+pub(crate) mod postmortem;
+pub(crate) mod raw;
@@ -0,0 +1,258 @@
+//! # Post-mortem
+//!
+//! Helpers to recover the events still in the BPF ring buffer when the kernel
+//! crashed. At collection time the physical location of the ring buffer pages
+//! is recorded in the startup event; the pages are later read back from an ELF
+//! kernel crash dump (vmcore) and the un-consumed records are extracted.
+
+use std::{
+    fs::File,
+    io,
+    os::{
+        fd::{AsRawFd, BorrowedFd},
+        unix::fs::FileExt,
+    },
+    path::Path,
+    ptr,
+};
+
+use anyhow::{bail, Result};
+use elf::{abi::PT_LOAD, endian::AnyEndian, ElfStream};
+
+use crate::events::{PfnRange, RingBufLayout};
+
+/// Size of a ring buffer record header (`struct bpf_ringbuf_hdr`).
+const RINGBUF_HDR_SZ: usize = 8;
+/// Record is being written.
+const RINGBUF_BUSY_BIT: u32 = 1 << 31;
+/// Record was discarded by its producer.
+const RINGBUF_DISCARD_BIT: u32 = 1 << 30;
+
+/// Retrieve the page frames backing a BPF ring buffer, given its map fd and
+/// the size of its data area.
+pub(crate) fn ringbuf_layout(fd: BorrowedFd, data_size: u64) -> Result<RingBufLayout> {
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if page_size <= 0 {
+        bail!("Could not retrieve the page size");
+    }
+    let page_size = page_size as u64;
+
+    // The consumer position page is mapped at offset 0, followed by the
+    // producer position page and the data pages. Read-only mappings can cover
+    // all of them.
+    let len = (2 * page_size + data_size) as usize;
+    let addr = unsafe {
+        libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ,
+            libc::MAP_SHARED,
+            fd.as_raw_fd(),
+            0,
+        )
+    };
+    if addr == libc::MAP_FAILED {
+        bail!(
+            "Could not map the ring buffer: {}",
+            io::Error::last_os_error()
+        );
+    }
+
+    let pfns = mapping_pfns(addr as u64, len as u64, page_size);
+    unsafe { libc::munmap(addr, len) };
+
+    Ok(RingBufLayout {
+        page_size,
+        data_size,
+        pfns: pfns?,
+    })
+}
+
+/// Resolve the page frames of a mapping, grouping contiguous ones.
+fn mapping_pfns(addr: u64, len: u64, page_size: u64) -> Result<Vec<PfnRange>> {
+    let pagemap = File::open("/proc/self/pagemap")?;
+    let mut ranges: Vec<PfnRange> = Vec::new();
+
+    for vaddr in (addr..addr + len).step_by(page_size as usize) {
+        // Make sure the page is mapped before looking it up.
+        unsafe { ptr::read_volatile(vaddr as *const u8) };
+
+        let mut entry = [0u8; 8];
+        pagemap.read_exact_at(&mut entry, (vaddr / page_size) * 8)?;
+        let entry = u64::from_ne_bytes(entry);
+
+        // Bit 63 is the present bit, bits 0-54 the page frame number. Frame
+        // numbers are reported as 0 without CAP_SYS_ADMIN.
+        let pfn = entry & ((1 << 55) - 1);
+        if entry & (1 << 63) == 0 || pfn == 0 {
+            bail!("Could not retrieve the ring buffer page frames (missing CAP_SYS_ADMIN?)");
+        }
+
+        match ranges.last_mut() {
+            Some(last) if last.start + last.count == pfn => last.count += 1,
+            _ => ranges.push(PfnRange {
+                start: pfn,
+                count: 1,
+            }),
+        }
+    }
+
+    Ok(ranges)
+}
+
+/// Kernel crash dump in the ELF format, as exposed by /proc/vmcore or
+/// generated by `makedumpfile -E`.
+pub(crate) struct VmCore {
+    file: File,
+    /// Loadable segments: (physical address, file offset, size).
+    segments: Vec<(u64, u64, u64)>,
+}
+
+impl VmCore {
+    pub(crate) fn open(path: &Path) -> Result<Self> {
+        let elf = match ElfStream::<AnyEndian, _>::open_stream(File::open(path)?) {
+            Ok(elf) => elf,
+            Err(e) => bail!(
+                "Could not parse '{}', only ELF crash dumps are supported ({e})",
+                path.display()
+            ),
+        };
+
+        let segments = elf
+            .segments()
+            .iter()
+            .filter(|phdr| phdr.p_type == PT_LOAD)
+            .map(|phdr| (phdr.p_paddr, phdr.p_offset, phdr.p_filesz))
+            .collect();
+
+        Ok(Self {
+            file: File::open(path)?,
+            segments,
+        })
+    }
+
+    /// Read a page given its frame number.
+    fn read_page(&self, pfn: u64, page_size: u64) -> Result<Vec<u8>> {
+        let paddr = pfn * page_size;
+        let (start, offset, _) = match self
+            .segments
+            .iter()
+            .find(|(start, _, size)| paddr >= *start && paddr + page_size <= start + size)
+        {
+            Some(segment) => segment,
+            None => bail!("Page frame {pfn:#x} is not part of the crash dump"),
+        };
+
+        let mut page = vec![0; page_size as usize];
+        self.file.read_exact_at(&mut page, offset + paddr - start)?;
+        Ok(page)
+    }
+}
+
+/// Extract the records not consumed from a ring buffer found in a crash dump.
+pub(crate) fn recover_records(vmcore: &VmCore, layout: &RingBufLayout) -> Result<Vec<Vec<u8>>> {
+    let pfns: Vec<u64> = layout
+        .pfns
+        .iter()
+        .flat_map(|range| range.start..range.start + range.count)
+        .collect();
+    if pfns.len() as u64 != 2 + layout.data_size / layout.page_size {
+        bail!("Invalid ring buffer layout");
+    }
+
+    let position = |pfn| -> Result<u64> {
+        let page = vmcore.read_page(pfn, layout.page_size)?;
+        Ok(u64::from_ne_bytes(page[..8].try_into()?))
+    };
+    let consumer = position(pfns[0])?;
+    let producer = position(pfns[1])?;
+
+    let mut data = Vec::with_capacity(layout.data_size as usize);
+    for pfn in pfns[2..].iter() {
+        data.extend(vmcore.read_page(*pfn, layout.page_size)?);
+    }
+
+    ringbuf_records(&data, consumer, producer)
+}
+
+/// Walk the ring buffer data area from the consumer to the producer position
+/// and return the committed records.
+fn ringbuf_records(data: &[u8], consumer: u64, producer: u64) -> Result<Vec<Vec<u8>>> {
+    let size = data.len() as u64;
+    if !size.is_power_of_two() {
+        bail!("Invalid ring buffer size ({size})");
+    }
+    if producer < consumer || producer - consumer > size {
+        bail!("Inconsistent ring buffer positions (consumer {consumer}, producer {producer})");
+    }
+
+    // The kernel maps the data area twice so records can be read without
+    // caring about wrapping; do the same.
+    let data = [data, data].concat();
+    let mask = size - 1;
+
+    let mut records = Vec::new();
+    let mut pos = consumer;
+    while pos < producer {
+        let off = (pos & mask) as usize;
+        let len = u32::from_ne_bytes(data[off..off + 4].try_into()?);
+
+        // The crash happened while the record was being written.
+        if len & RINGBUF_BUSY_BIT != 0 {
+            break;
+        }
+
+        let rec_len = (len & !(RINGBUF_BUSY_BIT | RINGBUF_DISCARD_BIT)) as usize;
+        if rec_len as u64 > size {
+            bail!("Invalid ring buffer record length ({rec_len})");
+        }
+        if len & RINGBUF_DISCARD_BIT == 0 {
+            let start = off + RINGBUF_HDR_SZ;
+            records.push(data[start..start + rec_len].to_vec());
+        }
+
+        pos += ((rec_len + RINGBUF_HDR_SZ + 7) & !7) as u64;
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(data: &mut [u8], pos: usize, len: u32, payload: u8) -> usize {
+        let mask = data.len() - 1;
+        let hdr = [len.to_ne_bytes(), 0u32.to_ne_bytes()].concat();
+        let rec_len = (len & !(RINGBUF_BUSY_BIT | RINGBUF_DISCARD_BIT)) as usize;
+        hdr.iter()
+            .chain(vec![payload; rec_len].iter())
+            .enumerate()
+            .for_each(|(i, b)| data[(pos + i) & mask] = *b);
+        pos + ((rec_len + RINGBUF_HDR_SZ + 7) & !7)
+    }
+
+    #[test]
+    fn ringbuf_records() {
+        let mut data = vec![0; 64];
+
+        // Two records, the second one wrapping, and a discarded one.
+        let pos = record(&mut data, 24, 4, 1);
+        let pos = record(&mut data, pos, 20, 2);
+        let pos = record(&mut data, pos, 4 | RINGBUF_DISCARD_BIT, 3);
+        let records = super::ringbuf_records(&data, 24 + 64, pos as u64 + 64).unwrap();
+        assert_eq!(records, vec![vec![1; 4], vec![2; 20]]);
+
+        // Stop at records being written.
+        let mut data = vec![0; 64];
+        let pos = record(&mut data, 0, 4, 1);
+        let busy = record(&mut data, pos, 4 | RINGBUF_BUSY_BIT, 4);
+        let pos = record(&mut data, busy, 4, 5);
+        let records = super::ringbuf_records(&data, 0, pos as u64).unwrap();
+        assert_eq!(records, vec![vec![1; 4]]);
+
+        assert!(super::ringbuf_records(&data, 64, 0).is_err());
+        assert!(super::ringbuf_records(&data, 0, 128).is_err());
+        assert!(super::ringbuf_records(&data[..48], 0, 0).is_err());
+    }
+}
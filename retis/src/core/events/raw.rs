@@ -0,0 +1,205 @@
+//! # Raw events
+//!
+//! Record of the raw events, as received from the BPF programs and before being
+//! unmarshaled, so they can later be replayed through the section factories
+//! (see `retis replay`). This allows to test unmarshalers against real data
+//! without a live kernel.
+//!
+//! Kernel symbols resolved while parsing the events during the collection are
+//! recorded too, so events can be replayed on a machine running another
+//! kernel.
+//!
+//! Files start with a magic and then hold records, each one prefixed by its
+//! kind (u8) and size (u32): raw events, or kernel symbols (address as a u64,
+//! followed by the name). As the raw events themselves, numbers use the
+//! endianness of the machine they were recorded on.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    mem,
+    path::Path,
+};
+
+use anyhow::{anyhow, bail, Result};
+
+/// Magic found at the start of raw events files.
+const RAW_MAGIC: &[u8; 8] = b"RETISRAW";
+
+/// Kinds of the records.
+const RECORD_EVENT: u8 = 0;
+const RECORD_SYMBOL: u8 = 1;
+
+/// Record of a raw events file.
+#[derive(Debug, PartialEq)]
+pub(crate) enum RawRecord {
+    /// Raw event, as received from the BPF programs.
+    Event(Vec<u8>),
+    /// Kernel symbol resolved during the collection: address and name.
+    Symbol(u64, String),
+}
+
+/// Writes raw events to a file.
+pub(crate) struct RawRecorder {
+    writer: BufWriter<File>,
+    /// Addresses of the symbols already recorded.
+    symbols: HashSet<u64>,
+}
+
+impl RawRecorder {
+    pub(crate) fn new(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)
+            .map_err(|e| anyhow!("Could not create or open '{}': {e}", path.display()))?;
+
+        let mut writer = BufWriter::new(file);
+        writer.write_all(RAW_MAGIC)?;
+        Ok(Self {
+            writer,
+            symbols: HashSet::new(),
+        })
+    }
+
+    fn write_record(&mut self, kind: u8, data: &[&[u8]]) -> Result<()> {
+        let len: usize = data.iter().map(|d| d.len()).sum();
+
+        self.writer.write_all(&[kind])?;
+        self.writer.write_all(&(len as u32).to_ne_bytes())?;
+        data.iter().try_for_each(|d| self.writer.write_all(d))?;
+        Ok(())
+    }
+
+    /// Record a raw event. Only the used part of the event is kept, as BPF
+    /// events are fixed size buffers.
+    pub(crate) fn record(&mut self, data: &[u8]) -> Result<()> {
+        let len = match data.get(..mem::size_of::<u16>()) {
+            Some(size) => {
+                let size = u16::from_ne_bytes(size.try_into()?) as usize;
+                data.len().min(size + mem::size_of::<u16>())
+            }
+            None => data.len(),
+        };
+
+        self.write_record(RECORD_EVENT, &[&data[..len]])
+    }
+
+    /// Record the kernel symbols not recorded yet. Symbols must be recorded
+    /// before the events using them.
+    pub(crate) fn record_symbols(&mut self, symbols: &HashMap<u64, String>) -> Result<()> {
+        if symbols.len() == self.symbols.len() {
+            return Ok(());
+        }
+
+        for (addr, name) in symbols.iter() {
+            if self.symbols.insert(*addr) {
+                self.write_record(RECORD_SYMBOL, &[&addr.to_ne_bytes(), name.as_bytes()])?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// Reads raw events from a file written by `RawRecorder`.
+pub(crate) struct RawReader {
+    reader: BufReader<File>,
+}
+
+impl RawReader {
+    pub(crate) fn new(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).map_err(|e| anyhow!("Could not open '{}': {e}", path.display()))?;
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0; RAW_MAGIC.len()];
+        if reader.read_exact(&mut magic).is_err() || &magic != RAW_MAGIC {
+            bail!("'{}' is not a raw events file", path.display());
+        }
+
+        Ok(Self { reader })
+    }
+
+    /// Retrieve the next record, or None if we've reached the end of the file.
+    pub(crate) fn next_record(&mut self) -> Result<Option<RawRecord>> {
+        let mut kind = [0; 1];
+        match self.reader.read_exact(&mut kind) {
+            Ok(()) => (),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+
+        let mut len = [0; mem::size_of::<u32>()];
+        self.reader
+            .read_exact(&mut len)
+            .map_err(|e| anyhow!("Could not read record size: {e}"))?;
+        let mut data = vec![0; u32::from_ne_bytes(len) as usize];
+        self.reader
+            .read_exact(&mut data)
+            .map_err(|e| anyhow!("Could not read record: {e}"))?;
+
+        Ok(Some(match kind[0] {
+            RECORD_EVENT => RawRecord::Event(data),
+            RECORD_SYMBOL => {
+                if data.len() < mem::size_of::<u64>() {
+                    bail!("Invalid symbol record");
+                }
+                let (addr, name) = data.split_at(mem::size_of::<u64>());
+                RawRecord::Symbol(
+                    u64::from_ne_bytes(addr.try_into()?),
+                    String::from_utf8(name.to_vec())?,
+                )
+            }
+            kind => bail!("Unknown record kind {kind}"),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("retis_raw_events");
+
+        let mut recorder = RawRecorder::new(&path).unwrap();
+        let symbols = HashMap::from([(0xffffffff81001000, "consume_skb".to_string())]);
+        recorder.record_symbols(&symbols).unwrap();
+        // Unused trailing bytes are not recorded.
+        recorder.record(&[2, 0, 42, 42, 0, 0, 0]).unwrap();
+        // Symbols are only recorded once.
+        recorder.record_symbols(&symbols).unwrap();
+        recorder.record(&[1, 0, 1]).unwrap();
+        recorder.flush().unwrap();
+        drop(recorder);
+
+        let mut reader = RawReader::new(&path).unwrap();
+        assert_eq!(
+            reader.next_record().unwrap(),
+            Some(RawRecord::Symbol(
+                0xffffffff81001000,
+                "consume_skb".to_string()
+            ))
+        );
+        assert_eq!(
+            reader.next_record().unwrap(),
+            Some(RawRecord::Event(vec![2, 0, 42, 42]))
+        );
+        assert_eq!(
+            reader.next_record().unwrap(),
+            Some(RawRecord::Event(vec![1, 0, 1]))
+        );
+        assert_eq!(reader.next_record().unwrap(), None);
+
+        std::fs::write(&path, b"{}\n").unwrap();
+        assert!(RawReader::new(&path).is_err());
+    }
+}
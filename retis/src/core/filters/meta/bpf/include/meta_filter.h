@@ -38,12 +38,25 @@ union retis_meta_op {
 	} t __attribute__((aligned(8)));
 };
 
-/* Probe configuration; the key is the target symbol address */
+/* Meta filter, as a sequence of operations. Zero operations means no filter.
+ * The first operation is the target, the following ones the loads.
+ */
+struct retis_meta_filter {
+	u32 nops;
+	union retis_meta_op ops[META_OPS_MAX];
+};
+
+/* Holds the current meta filter at key 0. The whole filter is replaced at once
+ * when updated, which allows to change it while probes are running. The map is
+ * not preallocated so replaced values are only freed once no probe is using
+ * them anymore.
+ */
 struct {
-	__uint(type, BPF_MAP_TYPE_ARRAY);
-	__uint(max_entries, META_OPS_MAX);
+	__uint(type, BPF_MAP_TYPE_HASH);
+	__uint(map_flags, BPF_F_NO_PREALLOC);
+	__uint(max_entries, 1);
 	__type(key, u32);
-	__type(value, union retis_meta_op);
+	__type(value, struct retis_meta_filter);
 } filter_meta_map SEC(".maps");
 
 struct retis_meta_ctx {
@@ -71,35 +84,21 @@ struct retis_meta_ctx {
 #define PTR_BIT 1 << 6
 #define SIGN_BIT 1 << 7
 
-/* Global ro variable that identifies the number of elements in
- * filter_meta_map. Zero mean, no filter.
- */
-const volatile u32 nmeta = 0;
-
-static __always_inline long meta_process_ops(struct retis_meta_ctx *ctx)
+static __always_inline long meta_process_ops(struct retis_meta_ctx *ctx,
+					    struct retis_meta_filter *filter)
 {
 	union retis_meta_op *val;
-	u32 k = 0;
 	u64 ptr;
 	u32 i;
 
-	val = bpf_map_lookup_elem(&filter_meta_map, &k);
-	if (!val) {
-		log_error("Failed to lookup meta-filter target");
-		return -1;
-	}
-
 	/* process target */
+	val = &filter->ops[0];
 	ctx->data = &val->t.md;
 	ctx->cmp = val->t.cmp;
 	ctx->sz = val->t.sz;
 
-	for (i = 1, k = 1; i < nmeta; k++, i++) {
-		val = bpf_map_lookup_elem(&filter_meta_map, &k);
-		if (!val) {
-			log_error("Failed to lookup meta-filter member at index %u", i);
-			return -1;
-		}
+	for (i = 1; i < filter->nops && i < META_OPS_MAX; i++) {
+		val = &filter->ops[i];
 
 		/* Load Pointer */
 		if (val->l.type == PTR_BIT) {
@@ -302,17 +301,20 @@ unsigned int filter_num(struct retis_meta_ctx *ctx)
 static __always_inline
 unsigned int meta_filter(struct sk_buff *skb)
 {
+	struct retis_meta_filter *filter;
 	struct retis_meta_ctx ctx = {};
+	u32 key = 0;
 
 	/* reduce actions to load/cmp info. If no entries, return
 	 * match.
 	 */
-	if (!nmeta || nmeta > META_OPS_MAX)
+	filter = bpf_map_lookup_elem(&filter_meta_map, &key);
+	if (!filter || !filter->nops || filter->nops > META_OPS_MAX)
 		return 1;
 
 	ctx.base = skb;
 
-	if (meta_process_ops(&ctx) < 0 || !ctx.data)
+	if (meta_process_ops(&ctx, filter) < 0 || !ctx.data)
 		return 0;
 
 	if (ctx.type & PTR_BIT || ctx.nmemb > 0)
@@ -8,6 +8,7 @@ use std::fmt;
 
 use anyhow::{anyhow, bail, Result};
 use btf_rs::*;
+use libbpf_rs::MapCore;
 use plain::Plain;
 
 use crate::core::inspect::inspector;
@@ -133,6 +134,26 @@ impl MetaLoad {
     fn is_arr(&self) -> bool {
         self.nmemb > 0
     }
+
+    // Textual representation of the type of the loaded data, e.g. "u32",
+    // "char[16]" or "ptr".
+    fn type_str(&self) -> String {
+        let bits = match self.r#type & 0x1f {
+            x if x == MetaType::Char as u8 => 8,
+            x if x == MetaType::Short as u8 => 16,
+            x if x == MetaType::Int as u8 => 32,
+            x if x == MetaType::Long as u8 => 64,
+            _ => return "ptr".to_string(),
+        };
+
+        if self.is_byte() && self.is_arr() {
+            format!("char[{}]", self.nmemb)
+        } else if self.is_byte() && self.is_ptr() {
+            "char *".to_string()
+        } else {
+            format!("{}{bits}", if self.is_signed() { 's' } else { 'u' })
+        }
+    }
 }
 
 #[repr(C)]
@@ -156,7 +177,6 @@ impl MetaOp {
         unsafe { &mut self.l }
     }
 
-    #[cfg_attr(not(test), allow(dead_code))]
     fn target_ref(&self) -> &MetaTarget {
         unsafe { &self.t }
     }
@@ -669,23 +689,131 @@ impl FilterMeta {
     }
 }
 
+/// Meta filter, as stored in the meta filter map. Please keep in sync with its
+/// BPF counterpart.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct MetaFilterValue {
+    nops: u32,
+    ops: [MetaOp; META_OPS_MAX as usize],
+}
+unsafe impl Plain for MetaFilterValue {}
+
+impl MetaFilterValue {
+    fn new(filter: Option<&FilterMeta>) -> Result<Self> {
+        let mut value = MetaFilterValue {
+            nops: 0,
+            ops: [MetaOp::new(); META_OPS_MAX as usize],
+        };
+
+        if let Some(filter) = filter {
+            if filter.0.len() > value.ops.len() {
+                bail!("meta filter is too long ({} operations)", filter.0.len());
+            }
+            value.nops = filter.0.len() as u32;
+            value.ops[..filter.0.len()].copy_from_slice(&filter.0);
+        }
+
+        Ok(value)
+    }
+}
+
+impl FilterMeta {
+    /// Representation of the filter as stored in the meta filter map.
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>> {
+        let value = MetaFilterValue::new(Some(self))?;
+        Ok(unsafe { plain::as_bytes(&value) }.to_vec())
+    }
+
+    /// Textual representation of the filter operations, as resolved using the
+    /// BTF: the target first, then the loads walking the members.
+    pub(crate) fn describe(&self) -> Vec<String> {
+        let (target, loads) = match self.0.split_first() {
+            Some((target, loads)) => (target.target_ref(), loads),
+            None => return Vec::new(),
+        };
+        let leaf = match loads.last() {
+            Some(leaf) => leaf.load_ref(),
+            None => return Vec::new(),
+        };
+
+        let cmp = ["==", ">", "<", ">=", "<=", "!="]
+            .get(target.cmp as usize)
+            .unwrap_or(&"?");
+        let value = if leaf.is_ptr() || leaf.is_arr() {
+            format!(
+                "'{}'",
+                String::from_utf8_lossy(&target.md[..target.sz as usize])
+            )
+        } else {
+            let val = u64::from_ne_bytes(target.md[..8].try_into().unwrap_or_default());
+            if leaf.is_signed() {
+                format!("{}", val as i64)
+            } else {
+                format!("{val:#x}")
+            }
+        };
+
+        let mut ops = vec![format!("target: {cmp} {value} ({} bytes)", target.sz)];
+        ops.extend(loads.iter().map(|op| {
+            let load = op.load_ref();
+            let mut desc = if load.bf_size > 0 {
+                format!(
+                    "load {} at bit offset {}, {} bits",
+                    load.type_str(),
+                    load.offt,
+                    load.bf_size
+                )
+            } else {
+                format!("load {} at offset {}", load.type_str(), load.offt)
+            };
+            if load.mask != 0 {
+                desc.push_str(&format!(", mask {:#x}", load.mask));
+            }
+            desc
+        }));
+
+        ops
+    }
+}
+
 #[cfg_attr(test, allow(dead_code))]
 pub(crate) fn init_meta_map() -> Result<libbpf_rs::MapHandle> {
+    // Values are not preallocated, so a replaced filter is only freed once no
+    // probe is using it anymore.
     let opts = libbpf_sys::bpf_map_create_opts {
         sz: std::mem::size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
+        map_flags: libbpf_sys::BPF_F_NO_PREALLOC,
         ..Default::default()
     };
 
     Ok(libbpf_rs::MapHandle::create(
-        libbpf_rs::MapType::Array,
+        libbpf_rs::MapType::Hash,
         Some("filter_meta_map"),
         std::mem::size_of::<u32>() as u32,
-        std::mem::size_of::<MetaOp>() as u32,
-        META_OPS_MAX,
+        std::mem::size_of::<MetaFilterValue>() as u32,
+        1,
         &opts,
     )?)
 }
 
+/// Set the meta filter used by all probes, or remove it if `filter` is `None`.
+/// The filter is replaced at once, which makes this safe to use while probes
+/// are running.
+#[cfg_attr(test, allow(dead_code))]
+pub(crate) fn set_meta_filter(
+    map: &libbpf_rs::MapHandle,
+    filter: Option<&FilterMeta>,
+) -> Result<()> {
+    let value = MetaFilterValue::new(filter)?;
+    map.update(
+        &0_u32.to_ne_bytes(),
+        unsafe { plain::as_bytes(&value) },
+        libbpf_rs::MapFlags::ANY,
+    )?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -783,6 +911,14 @@ mod tests {
                 .unwrap(),
         );
         assert_eq!(target, 0xc0de);
+
+        assert_eq!(
+            filter.describe(),
+            vec![
+                format!("target: {op_str} 0xc0de (4 bytes)"),
+                "load u32 at offset 168".to_string(),
+            ]
+        );
     }
 
     #[test_case("==", MetaCmp::Eq ; "op is eq")]
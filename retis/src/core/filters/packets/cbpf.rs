@@ -211,6 +211,52 @@ impl BpfInsn {
     }
 }
 
+impl BpfInsn {
+    // Textual representation of the instruction at the given position, following
+    // the libpcap one (see bpf_image(3PCAP) and `tcpdump -d`).
+    pub(super) fn image(&self, pc: usize) -> String {
+        let k = self.k;
+        let (jt, jf) = (pc + 1 + self.jt as usize, pc + 1 + self.jf as usize);
+        let size = |size| match size {
+            BpfSize::Byte => "b",
+            BpfSize::Half => "h",
+            _ => "",
+        };
+
+        let insn = match self.insn_type() {
+            Ok(BpfInsnType::LdAbs(s)) => format!("ld{} [{k}]", size(s)),
+            Ok(BpfInsnType::LdInd(s)) => format!("ld{} [x + {k}]", size(s)),
+            Ok(BpfInsnType::LdLen) => "ld #pktlen".to_string(),
+            Ok(BpfInsnType::LdImm) => format!("ld #{k:#x}"),
+            Ok(BpfInsnType::LdMem) => format!("ld M[{k}]"),
+            Ok(BpfInsnType::LdxImm) => format!("ldx #{k:#x}"),
+            Ok(BpfInsnType::LdxMem) => format!("ldx M[{k}]"),
+            Ok(BpfInsnType::LdxLen) => "ldx #pktlen".to_string(),
+            Ok(BpfInsnType::LdxMsh) => format!("ldxb 4*([{k}]&0xf)"),
+            Ok(BpfInsnType::St) => format!("st M[{k}]"),
+            Ok(BpfInsnType::Stx) => format!("stx M[{k}]"),
+            Ok(BpfInsnType::AluK(op)) => format!("{} #{k:#x}", format!("{op:?}").to_lowercase()),
+            Ok(BpfInsnType::AluX(op)) => format!("{} x", format!("{op:?}").to_lowercase()),
+            Ok(BpfInsnType::AluNeg(_)) => "neg".to_string(),
+            Ok(BpfInsnType::JmpA) => format!("ja {}", pc + 1 + k as usize),
+            Ok(BpfInsnType::JmpK(op)) => format!(
+                "j{} #{k:#x} jt {jt} jf {jf}",
+                format!("{op:?}").to_lowercase()
+            ),
+            Ok(BpfInsnType::JmpX(op)) => {
+                format!("j{} x jt {jt} jf {jf}", format!("{op:?}").to_lowercase())
+            }
+            Ok(BpfInsnType::RetA) => "ret a".to_string(),
+            Ok(BpfInsnType::RetK) => format!("ret #{k}"),
+            Ok(BpfInsnType::Tax) => "tax".to_string(),
+            Ok(BpfInsnType::Txa) => "txa".to_string(),
+            Err(_) => format!("unknown {:#x}", self.opcode),
+        };
+
+        format!("({pc:03}) {insn}")
+    }
+}
+
 impl core::fmt::Debug for BpfInsn {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> Result<(), core::fmt::Error> {
         write!(f, "{} {} {} {}", self.opcode, self.jf, self.jt, self.k)?;
@@ -223,6 +269,15 @@ impl BpfProg {
     fn new() -> BpfProg {
         Default::default()
     }
+
+    // Textual representation of the program, one instruction per line.
+    pub(super) fn image(&self) -> Vec<String> {
+        self.prog
+            .iter()
+            .enumerate()
+            .map(|(pc, insn)| insn.image(pc))
+            .collect()
+    }
 }
 
 impl TryFrom<&[u8]> for BpfProg {
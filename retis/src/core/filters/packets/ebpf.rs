@@ -91,6 +91,15 @@ impl eBpfProg {
         self.0.iter().flat_map(|insn| insn.to_vec()).collect()
     }
 
+    // Textual representation of the program, one instruction per line.
+    pub(crate) fn image(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(pc, insn)| format!("{pc:4}: ({:02x}) {insn}", insn.code))
+            .collect()
+    }
+
     #[cfg(feature = "debug")]
     pub(crate) fn disasm(&self) {
         disassemble(&self.to_bytes());
@@ -1,5 +1,5 @@
 #![allow(dead_code, non_camel_case_types)]
-use std::vec;
+use std::{fmt, vec};
 
 use crate::core::{
     bpf_sys,
@@ -310,3 +310,103 @@ impl eBpfInsn {
         byte_insn
     }
 }
+
+fn size_str(code: u8) -> &'static str {
+    match code & 0x18 {
+        bpf_sys::BPF_B => "u8",
+        bpf_sys::BPF_H => "u16",
+        bpf_sys::BPF_W => "u32",
+        _ => "u64",
+    }
+}
+
+// Disassembles the instruction following the syntax of the kernel verifier
+// (and bpftool), e.g. "r0 = *(u16 *)(r8 +12)".
+impl fmt::Display for eBpfInsn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let class = self.code & 0x07;
+        let (dst, src, off, imm) = (self.dst, self.src, self.off, self.imm);
+
+        match class {
+            bpf_sys::BPF_ALU | bpf_sys::BPF_ALU64 => {
+                let r = if class == bpf_sys::BPF_ALU64 {
+                    'r'
+                } else {
+                    'w'
+                };
+                let op = match self.code & 0xf0 {
+                    bpf_sys::BPF_ADD => "+=",
+                    bpf_sys::BPF_SUB => "-=",
+                    bpf_sys::BPF_MUL => "*=",
+                    bpf_sys::BPF_DIV => "/=",
+                    bpf_sys::BPF_OR => "|=",
+                    bpf_sys::BPF_AND => "&=",
+                    bpf_sys::BPF_LSH => "<<=",
+                    bpf_sys::BPF_RSH => ">>=",
+                    bpf_sys::BPF_MOD => "%=",
+                    bpf_sys::BPF_XOR => "^=",
+                    bpf_sys::BPF_MOV => "=",
+                    bpf_sys::BPF_ARSH => "s>>=",
+                    bpf_sys::BPF_NEG => return write!(f, "{r}{dst} = -{r}{dst}"),
+                    bpf_sys::BPF_END => {
+                        let order = if self.code & 0x08 == bpf_sys::BPF_TO_BE {
+                            "be"
+                        } else {
+                            "le"
+                        };
+                        return write!(f, "r{dst} = {order}{imm} r{dst}");
+                    }
+                    _ => return write!(f, "(unknown alu {:#04x})", self.code),
+                };
+
+                if self.code & 0x08 == bpf_sys::BPF_X {
+                    write!(f, "{r}{dst} {op} {r}{src}")
+                } else {
+                    write!(f, "{r}{dst} {op} {imm:#x}")
+                }
+            }
+            bpf_sys::BPF_LDX => write!(f, "r{dst} = *({} *)(r{src} {off:+})", size_str(self.code)),
+            bpf_sys::BPF_ST => write!(f, "*({} *)(r{dst} {off:+}) = {imm:#x}", size_str(self.code)),
+            bpf_sys::BPF_STX => write!(f, "*({} *)(r{dst} {off:+}) = r{src}", size_str(self.code)),
+            bpf_sys::BPF_LD => match self.code & 0xe0 {
+                bpf_sys::BPF_ABS => write!(f, "r0 = *({} *)skb[{imm}]", size_str(self.code)),
+                bpf_sys::BPF_IND => {
+                    write!(f, "r0 = *({} *)skb[r{src} + {imm}]", size_str(self.code))
+                }
+                _ => write!(f, "(unknown ld {:#04x})", self.code),
+            },
+            bpf_sys::BPF_JMP | bpf_sys::BPF_JMP32 => {
+                let r = if class == bpf_sys::BPF_JMP { 'r' } else { 'w' };
+                let op = match self.code & 0xf0 {
+                    bpf_sys::BPF_JA => return write!(f, "goto pc{off:+}"),
+                    bpf_sys::BPF_EXIT => return write!(f, "exit"),
+                    bpf_sys::BPF_CALL => {
+                        if imm == bpf_sys::bpf_func_id::BPF_FUNC_probe_read_kernel as i32 {
+                            return write!(f, "call bpf_probe_read_kernel#{imm}");
+                        }
+                        return write!(f, "call #{imm}");
+                    }
+                    bpf_sys::BPF_JEQ => "==",
+                    bpf_sys::BPF_JGT => ">",
+                    bpf_sys::BPF_JGE => ">=",
+                    bpf_sys::BPF_JSET => "&",
+                    bpf_sys::BPF_JNE => "!=",
+                    bpf_sys::BPF_JSGT => "s>",
+                    bpf_sys::BPF_JSGE => "s>=",
+                    bpf_sys::BPF_JLT => "<",
+                    bpf_sys::BPF_JLE => "<=",
+                    bpf_sys::BPF_JSLT => "s<",
+                    bpf_sys::BPF_JSLE => "s<=",
+                    _ => return write!(f, "(unknown jmp {:#04x})", self.code),
+                };
+
+                if self.code & 0x08 == bpf_sys::BPF_X {
+                    write!(f, "if {r}{dst} {op} {r}{src} goto pc{off:+}")
+                } else {
+                    write!(f, "if {r}{dst} {op} {imm:#x} goto pc{off:+}")
+                }
+            }
+            _ => write!(f, "(unknown {:#04x})", self.code),
+        }
+    }
+}
@@ -25,10 +25,8 @@ use crate::{
 pub(crate) struct FilterPacket(eBpfProg);
 
 impl FilterPacket {
-    pub(crate) fn from_string_opt(
-        fstring: String,
-        layer_type: packet_filter_uapi::filter_type,
-    ) -> Result<Self> {
+    // Compile a pcap-filter string to a cBPF program using libpcap.
+    fn compile_cbpf(fstring: &str, layer_type: packet_filter_uapi::filter_type) -> Result<BpfProg> {
         let link_type = match layer_type {
             packet_filter_uapi::FILTER_L3 => Linktype(12), // DLT_RAW
             packet_filter_uapi::FILTER_L2 => Linktype::ETHERNET,
@@ -36,14 +34,19 @@ impl FilterPacket {
         };
 
         let bpf_capture = Capture::dead(link_type)?;
-        let program = match bpf_capture.compile(fstring.as_str(), true) {
+        let program = match bpf_capture.compile(fstring, true) {
             Ok(program) => program,
             Err(e) => bail!("Could not compile the filter: {e}"),
         };
         let insns = program.get_instructions();
-        let filter =
-            BpfProg::try_from(unsafe { mem::transmute::<&[pcap::BpfInstruction], &[u8]>(insns) })?;
+        BpfProg::try_from(unsafe { mem::transmute::<&[pcap::BpfInstruction], &[u8]>(insns) })
+    }
 
+    pub(crate) fn from_string_opt(
+        fstring: String,
+        layer_type: packet_filter_uapi::filter_type,
+    ) -> Result<Self> {
+        let filter = Self::compile_cbpf(&fstring, layer_type)?;
         let ebpf_filter = eBpfProg::try_from(filter)?;
         if ebpf_filter.len() > packet_filter_uapi::FILTER_MAX_INSNS as usize {
             bail!("Filter exceeds the maximum allowed size.");
@@ -68,4 +71,18 @@ impl FilterPacket {
     pub(crate) fn to_bytes(&self) -> Result<Vec<u8>> {
         Ok(self.0.to_bytes())
     }
+
+    // Disassembly of the eBPF filter, one instruction per line.
+    pub(crate) fn image(&self) -> Vec<String> {
+        self.0.image()
+    }
+
+    // Disassembly of the intermediate cBPF program libpcap generates for a
+    // pcap-filter string, one instruction per line.
+    pub(crate) fn cbpf_image(
+        fstring: &str,
+        layer_type: packet_filter_uapi::filter_type,
+    ) -> Result<Vec<String>> {
+        Ok(Self::compile_cbpf(fstring, layer_type)?.image())
+    }
 }
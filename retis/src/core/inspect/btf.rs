@@ -83,6 +83,34 @@ impl BtfInfo {
         Ok(None)
     }
 
+    /// Get a function's parameters, as C declarations (e.g. `struct sk_buff
+    /// *skb`). Parameters of events have no name and only their type is
+    /// reported.
+    pub(super) fn function_parameters(&self, symbol: &Symbol) -> Result<Vec<String>> {
+        // See function_nargs.
+        let fix = match symbol {
+            Symbol::Event(_) => 1,
+            _ => 0,
+        };
+
+        let (btf, proto) = self.find_prototype_btf(symbol)?;
+        proto
+            .parameters
+            .iter()
+            .skip(fix)
+            .map(|param| {
+                let r#type = BtfInfo::param_type_name(btf, param)?.unwrap_or("?".to_string());
+                Ok(match btf.resolve_name(param) {
+                    Ok(name) if !name.is_empty() => match r#type.ends_with('*') {
+                        true => format!("{type}{name}"),
+                        false => format!("{type} {name}"),
+                    },
+                    _ => r#type,
+                })
+            })
+            .collect()
+    }
+
     /// Look for a type based on its name and return both a Vec of Type objects as well as
     /// the Btf object where it was found.
     /// Subsequent lookups based on this type (such as nested types by id) must be done on
@@ -127,6 +155,48 @@ impl BtfInfo {
         self.resolve_types_by_name(&symbol.typedef_name())
     }
 
+    /// Check if a struct or union has a given member. Anonymous structs and
+    /// unions are looked into, as their members are accessed directly.
+    pub(crate) fn has_member(&self, r#struct: &str, member: &str) -> bool {
+        self.resolve_types_by_name(r#struct)
+            .unwrap_or_default()
+            .iter()
+            .any(|(btf, t)| Self::type_has_member(btf, t, member))
+    }
+
+    fn type_has_member(btf: &Btf, r#type: &Type, member: &str) -> bool {
+        let r#struct = match r#type {
+            Type::Struct(r#struct) | Type::Union(r#struct) => r#struct,
+            _ => return false,
+        };
+
+        r#struct.members.iter().any(|m| match btf.resolve_name(m) {
+            Ok(name) if name == member => true,
+            Ok(name) if name.is_empty() => btf
+                .resolve_chained_type(m)
+                .is_ok_and(|t| Self::type_has_member(btf, &t, member)),
+            _ => false,
+        })
+    }
+
+    /// Check if an enum has a given value.
+    pub(crate) fn has_enum_value(&self, r#enum: &str, value: &str) -> bool {
+        self.resolve_types_by_name(r#enum)
+            .unwrap_or_default()
+            .iter()
+            .any(|(btf, t)| match t {
+                Type::Enum(r#enum) => r#enum
+                    .members
+                    .iter()
+                    .any(|m| btf.resolve_name(m).is_ok_and(|name| name == value)),
+                Type::Enum64(r#enum) => r#enum
+                    .members
+                    .iter()
+                    .any(|m| btf.resolve_name(m).is_ok_and(|name| name == value)),
+                _ => false,
+            })
+    }
+
     /// Look for symbol prototype. Return the prototype and the Btf object that contains it.
     pub(crate) fn find_prototype_btf(&self, symbol: &Symbol) -> Result<(&Btf, btf_rs::FuncProto)> {
         for (btf, t) in self.resolve_types_by_symbol(symbol)? {
@@ -143,6 +213,12 @@ impl BtfInfo {
 
     /// Determine if a parameter is from a specific type.
     fn is_param_type(btf: &Btf, param: &btf_rs::Parameter, r#type: &str) -> Result<bool> {
+        Ok(Self::param_type_name(btf, param)?.is_some_and(|name| name == r#type))
+    }
+
+    /// Get the type name of a parameter, C style (e.g. `struct sk_buff *`), if
+    /// supported.
+    fn param_type_name(btf: &Btf, param: &btf_rs::Parameter) -> Result<Option<String>> {
         let mut resolved = btf.resolve_chained_type(param)?;
         let mut full_name = String::new();
 
@@ -160,7 +236,7 @@ impl BtfInfo {
                 Type::Volatile(t) => btf.resolve_chained_type(&t)?,
                 Type::Const(t) => btf.resolve_chained_type(&t)?,
                 // FIXME: arrays are not supported at the moment.
-                Type::Array(_) => return Ok(false),
+                Type::Array(_) => return Ok(None),
                 _ => break,
             }
         }
@@ -174,7 +250,8 @@ impl BtfInfo {
             Type::Typedef(t) => btf.resolve_name(&t)?,
             Type::Float(t) => btf.resolve_name(&t)?,
             Type::Enum64(t) => format!("enum {}", btf.resolve_name(&t)?),
-            _ => return Ok(false),
+            Type::Void => "void".to_string(),
+            _ => return Ok(None),
         };
         full_name.push_str(type_name.as_str());
 
@@ -186,7 +263,7 @@ impl BtfInfo {
         // We do not get the symbol name; useless and not always there (e.g.
         // raw tracepoints).
 
-        Ok(r#type == full_name)
+        Ok(Some(full_name))
     }
 
     fn get_function_prototype(btf: &Btf, func: &Type) -> Result<btf_rs::FuncProto> {
@@ -319,4 +396,37 @@ mod tests {
             .unwrap()
             .is_none());
     }
+
+    #[test]
+    fn function_parameters() {
+        let btf = BtfInfo::new().unwrap();
+        assert_eq!(
+            btf.function_parameters(&Symbol::Func("kfree_skb_reason".to_string()))
+                .unwrap(),
+            vec!["struct sk_buff *skb", "enum skb_drop_reason reason"]
+        );
+
+        // Events parameters have no name.
+        assert_eq!(
+            btf.function_parameters(&Symbol::Event("skb:kfree_skb".to_string()))
+                .unwrap(),
+            vec!["struct sk_buff *", "void *", "enum skb_drop_reason"]
+        );
+    }
+
+    #[test]
+    fn optional_fields() {
+        let btf = BtfInfo::new().unwrap();
+
+        assert!(btf.has_member("sk_buff", "_nfct"));
+        // Member of an anonymous union.
+        assert!(btf.has_member("sk_buff", "dev"));
+        assert!(!btf.has_member("sk_buff", "invalid"));
+        assert!(!btf.has_member("invalid", "dev"));
+
+        assert!(btf.has_enum_value("skb_drop_reason", "SKB_DROP_REASON_NOT_SPECIFIED"));
+        assert!(!btf.has_enum_value("skb_drop_reason", "INVALID"));
+        // Not known by the test kernel.
+        assert!(!btf.has_enum_value("ovs_action_attr", "OVS_ACTION_ATTR_DROP"));
+    }
 }
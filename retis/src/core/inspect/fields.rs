@@ -0,0 +1,59 @@
+//! # Fields
+//!
+//! Some event fields can only be retrieved on kernels having a given type,
+//! struct member or enum value; the BPF side checks for those using CO-RE and
+//! silently skips the field otherwise. Optional fields are described here so
+//! their availability on the running kernel can be reported to the user.
+
+use std::fmt;
+
+use anyhow::Result;
+
+use super::inspector;
+
+/// Kernel requirement for an optional event field to be reported.
+pub(crate) enum FieldReq {
+    /// A type must exist, e.g. `skb_drop_reason`.
+    Type(&'static str),
+    /// A struct or union must have a given member, e.g. `nf_conn`, `mark`.
+    Member(&'static str, &'static str),
+    /// An enum must have a given value.
+    EnumValue(&'static str, &'static str),
+}
+
+impl fmt::Display for FieldReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldReq::Type(name) => write!(f, "no type {name}"),
+            FieldReq::Member(r#struct, member) => write!(f, "no member {member} in {}", r#struct),
+            FieldReq::EnumValue(r#enum, value) => {
+                write!(f, "no value {value} in enum {}", r#enum)
+            }
+        }
+    }
+}
+
+/// Event field only reported on kernels matching a requirement.
+pub(crate) struct OptionalField {
+    /// Name of the field(s) in the event.
+    pub(crate) name: &'static str,
+    /// Kernel requirement for the field to be reported.
+    pub(crate) req: FieldReq,
+}
+
+impl OptionalField {
+    pub(crate) fn new(name: &'static str, req: FieldReq) -> Self {
+        Self { name, req }
+    }
+
+    /// Check if the field can be reported on the running kernel.
+    pub(crate) fn available(&self) -> Result<bool> {
+        let btf = &inspector()?.kernel.btf;
+
+        Ok(match self.req {
+            FieldReq::Type(name) => btf.resolve_types_by_name(name).is_ok(),
+            FieldReq::Member(r#struct, member) => btf.has_member(r#struct, member),
+            FieldReq::EnumValue(r#enum, value) => btf.has_enum_value(r#enum, value),
+        })
+    }
+}
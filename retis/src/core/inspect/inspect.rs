@@ -9,19 +9,31 @@ static INSPECTOR: OnceCell<Inspector> = OnceCell::new();
 
 /// Gets a reference on the inspector.
 pub(crate) fn inspector() -> Result<&'static Inspector> {
-    INSPECTOR.get_or_try_init(|| Inspector::from(None))
+    INSPECTOR.get_or_try_init(|| Inspector::from(None, &SymbolsConfig::default()))
 }
 
 /// Initialize the inspector with custom parameters, fail is already
 /// initialized.
-pub(crate) fn init_inspector(kconf: &PathBuf) -> Result<()> {
-    let inspector = Inspector::from(Some(kconf))?;
+pub(crate) fn init_inspector(kconf: Option<&PathBuf>, syms: &SymbolsConfig) -> Result<()> {
+    let inspector = Inspector::from(kconf, syms)?;
     if INSPECTOR.set(inspector).is_err() {
         bail!("Could not init inspector: was already initialized.");
     }
     Ok(())
 }
 
+/// Source of the kernel symbols, when not using the ones of the running
+/// kernel.
+#[derive(Default)]
+pub(crate) struct SymbolsConfig {
+    /// Symbol map in the kallsyms format (e.g. a copy of /proc/kallsyms or a
+    /// System.map file).
+    pub(crate) path: Option<PathBuf>,
+    /// KASLR offset to apply to the addresses of the symbol map. Detected
+    /// using the running kernel symbols if not set.
+    pub(crate) kaslr_offset: Option<u64>,
+}
+
 /// Provides helpers to inspect various information about the system and the
 /// kernel. Used as a singleton.
 pub(crate) struct Inspector {
@@ -30,9 +42,9 @@ pub(crate) struct Inspector {
 }
 
 impl Inspector {
-    fn from(kconf: Option<&PathBuf>) -> Result<Inspector> {
+    fn from(kconf: Option<&PathBuf>, syms: &SymbolsConfig) -> Result<Inspector> {
         Ok(Inspector {
-            kernel: KernelInspector::from(kconf)?,
+            kernel: KernelInspector::from(kconf, syms)?,
         })
     }
 }
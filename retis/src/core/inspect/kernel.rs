@@ -5,26 +5,29 @@ use std::{
     fmt::Display,
     fs,
     io::Read,
-    ops::Bound::{Included, Unbounded},
     path::{Path, PathBuf},
     str,
 };
 
 use anyhow::{anyhow, bail, Result};
 use flate2::bufread::GzDecoder;
-use log::warn;
+use log::{info, warn};
 use regex::Regex;
 
-use super::{btf::BtfInfo, kernel_version::KernelVersion, BASE_TEST_DIR};
+use super::{
+    btf::BtfInfo,
+    kernel_version::KernelVersion,
+    symbols::{SymbolMap, Symbolizer},
+    SymbolsConfig, BASE_TEST_DIR,
+};
 use crate::core::kernel::Symbol;
-use crate::helpers::bimap::BiBTreeMap;
 
 /// Provides helpers to inspect probe related information in the kernel.
 pub(crate) struct KernelInspector {
     /// Btf information.
     pub(crate) btf: BtfInfo,
-    /// Symbols bi-directional map (addr<>name).
-    symbols: BiBTreeMap<u64, String>,
+    /// Kernel symbols resolution.
+    symbols: Box<dyn Symbolizer>,
     /// Set of traceable events (e.g. tracepoints).
     traceable_events: Option<HashSet<String>>,
     /// Set of traceable functions (e.g. kprobes).
@@ -43,7 +46,7 @@ pub(crate) struct KernelInspector {
 }
 
 impl KernelInspector {
-    pub(crate) fn from(kconf: Option<&PathBuf>) -> Result<KernelInspector> {
+    pub(crate) fn from(kconf: Option<&PathBuf>, syms: &SymbolsConfig) -> Result<KernelInspector> {
         let (symbols_file, events_file, funcs_file, modules_file) =
             match cfg!(test) || cfg!(feature = "benchmark") {
                 false => (
@@ -62,30 +65,7 @@ impl KernelInspector {
         let btf = BtfInfo::new()?;
 
         // First parse the symbol file.
-        let mut symbols = BiBTreeMap::new();
-        // Lines have to be processed backward in order to overwrite
-        // duplicate addresses and keep the first (which is the last
-        // inserted in the common case involving module init
-        // functions) instead of the last one.
-        for line in fs::read_to_string(symbols_file)?.lines().rev() {
-            let data: Vec<&str> = line.split(' ').collect();
-            if data.len() < 3 {
-                bail!("Invalid kallsyms line: {}", line);
-            }
-
-            let symbol: &str = data[2]
-                .split('\t')
-                .next()
-                .ok_or_else(|| anyhow!("Couldn't get symbol name for {}", data[0]))?;
-
-            symbols.insert(u64::from_str_radix(data[0], 16)?, String::from(symbol));
-        }
-
-        // If all symbols have a 0-address, only the last one will be left in
-        // the map after the above.
-        if symbols.len() == 1 {
-            bail!("Retis likely does not have the rights to read the symbol addresses from /proc/kallsyms.");
-        }
+        let symbols = Self::symbols(&symbols_file, syms)?;
 
         let version = KernelVersion::new()?;
         let config = Self::parse_kernel_config(&version.full, kconf)?;
@@ -113,6 +93,41 @@ impl KernelInspector {
         Ok(inspector)
     }
 
+    /// Load the kernel symbols, from the running kernel or from a user
+    /// provided symbol map.
+    fn symbols(kallsyms: &str, syms: &SymbolsConfig) -> Result<Box<dyn Symbolizer>> {
+        let path = match &syms.path {
+            Some(path) => path,
+            None => return Ok(Box::new(SymbolMap::from_file(kallsyms, 0)?)),
+        };
+
+        let kaslr_offset = match syms.kaslr_offset {
+            Some(offset) => offset,
+            // The offset can't be guessed reliably for a map not coming from
+            // the running kernel: it must be given explicitly.
+            None => match Self::is_same_file(path, kallsyms) {
+                true => 0,
+                false => bail!(
+                    "--kaslr-offset is required when using a symbol map not from the running kernel (use 0 if the map holds runtime addresses)"
+                ),
+            },
+        };
+
+        info!(
+            "Using kernel symbols from {} (KASLR offset {kaslr_offset:#x})",
+            path.display()
+        );
+        Ok(Box::new(SymbolMap::from_file(path, kaslr_offset)?))
+    }
+
+    /// Check if two paths point to the same existing file.
+    fn is_same_file<P: AsRef<Path>, Q: AsRef<Path>>(a: P, b: Q) -> bool {
+        match (fs::canonicalize(a), fs::canonicalize(b)) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+    }
+
     /// Convert a file containing a list of str (one per line) into a HashSet.
     /// Returns None if the file can't be read.
     fn file_to_hashset<P>(target: P) -> Option<HashSet<String>>
@@ -246,30 +261,17 @@ impl KernelInspector {
 
     /// Return a symbol name given its address, if a relationship is found.
     pub(crate) fn get_symbol_name(&self, addr: u64) -> Result<String> {
-        Ok(self
-            .symbols
-            .get_by_left(&addr)
-            .ok_or_else(|| anyhow!("Can't get symbol name for {}", addr))?
-            .clone())
+        self.symbols.name(addr)
     }
 
     /// Return a symbol address given its name, if a relationship is found.
     pub(crate) fn get_symbol_addr(&self, name: &str) -> Result<u64> {
-        Ok(*self
-            .symbols
-            .get_by_right(&name.to_string())
-            .ok_or_else(|| anyhow!("Can't get symbol address for {}", name))?)
+        self.symbols.addr(name)
     }
 
     /// Given an address, try to find the nearest symbol, if any.
     pub(crate) fn find_nearest_symbol(&self, target: u64) -> Result<u64> {
-        let bounding = (Unbounded, Included(target));
-        let nearest = self.symbols.range_by_left(&bounding).next_back();
-
-        match nearest {
-            Some(symbol) => Ok(*symbol.0),
-            None => bail!("Can't get a symbol near {}", target),
-        }
+        self.symbols.nearest(target)
     }
 
     /// Check if an event is traceable. Return None if we can't know.
@@ -316,6 +318,11 @@ impl KernelInspector {
         self.btf.function_nargs(symbol)
     }
 
+    /// Get a function's parameters, as C declarations.
+    pub(crate) fn function_parameters(&self, symbol: &Symbol) -> Result<Vec<String>> {
+        self.btf.function_parameters(symbol)
+    }
+
     /// Given an address, gets the name and the offset of the nearest symbol, if any.
     pub(crate) fn get_name_offt_from_addr_near(&self, addr: u64) -> Result<(String, u64)> {
         let sym_addr = self.find_nearest_symbol(addr)?;
@@ -361,18 +368,52 @@ impl KernelInspector {
 
 #[cfg(test)]
 mod tests {
-    use super::KernelInspector;
-    use std::path::PathBuf;
+    use super::{KernelInspector, SymbolsConfig};
+    use std::{fs, path::PathBuf};
 
     fn inspector() -> KernelInspector {
         let kconf = PathBuf::from("test_data/config-6.3.0-0.rc7.56.fc39.x86_64");
-        super::KernelInspector::from(Some(&kconf)).unwrap()
+        super::KernelInspector::from(Some(&kconf), &SymbolsConfig::default()).unwrap()
     }
 
     #[test]
     fn inspector_init() {
         let kconf = PathBuf::from("test_data/config-6.3.0-0.rc7.56.fc39.x86_64");
-        assert!(super::KernelInspector::from(Some(&kconf)).is_ok());
+        assert!(super::KernelInspector::from(Some(&kconf), &SymbolsConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn symbols_map() {
+        let syms = SymbolsConfig {
+            path: Some(PathBuf::from("test_data/kallsyms")),
+            kaslr_offset: None,
+        };
+        let inspector = super::KernelInspector::from(None, &syms).unwrap();
+        // Running kernel map, no offset is needed.
+        assert_eq!(
+            inspector.get_symbol_addr("consume_skb").unwrap(),
+            0xffffffff99d1da80
+        );
+
+        // A map not from the running kernel requires an explicit offset.
+        let dir = tempfile::tempdir().unwrap();
+        let copy = dir.path().join("kallsyms");
+        fs::copy("test_data/kallsyms", &copy).unwrap();
+        let syms = SymbolsConfig {
+            path: Some(copy),
+            kaslr_offset: None,
+        };
+        assert!(super::KernelInspector::from(None, &syms).is_err());
+
+        let syms = SymbolsConfig {
+            kaslr_offset: Some(0x1000),
+            ..syms
+        };
+        let inspector = super::KernelInspector::from(None, &syms).unwrap();
+        assert_eq!(
+            inspector.get_symbol_addr("consume_skb").unwrap(),
+            0xffffffff99d1ea80
+        );
     }
 
     #[test]
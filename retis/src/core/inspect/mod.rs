@@ -16,5 +16,7 @@ pub(crate) static BASE_TEST_DIR: &str = match (cfg!(test), cfg!(feature = "bench
 
 mod btf;
 pub(crate) mod check;
+pub(crate) mod fields;
 mod kernel;
 pub(crate) mod kernel_version;
+pub(crate) mod symbols;
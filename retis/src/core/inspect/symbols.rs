@@ -0,0 +1,148 @@
+//! # Kernel symbols
+//!
+//! Kernel symbol resolution is abstracted behind the `Symbolizer` trait, so
+//! symbol sources other than /proc/kallsyms can be used. This is required for
+//! analyses performed off-host, where the running kernel is not the one the
+//! events were collected on.
+//!
+//! Symbolizers always work with runtime addresses, i.e. addresses including
+//! the KASLR offset of the kernel. Sources holding link-time addresses (e.g. a
+//! System.map file) must be given the KASLR offset to apply.
+
+use std::{
+    fs,
+    ops::Bound::{Included, Unbounded},
+    path::Path,
+};
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::helpers::bimap::BiBTreeMap;
+
+/// Kernel symbol resolution.
+pub(crate) trait Symbolizer: Send + Sync {
+    /// Return a symbol name given its address.
+    fn name(&self, addr: u64) -> Result<String>;
+    /// Return a symbol address given its name.
+    fn addr(&self, name: &str) -> Result<u64>;
+    /// Return the address of the nearest symbol at or before `addr`.
+    fn nearest(&self, addr: u64) -> Result<u64>;
+}
+
+/// Symbolizer backed by a map in the kallsyms format (`<addr> <type> <name>
+/// [module]`): /proc/kallsyms, a copy of it taken on another host, a
+/// System.map file or a symbol list extracted from a crash dump.
+pub(crate) struct SymbolMap {
+    /// Symbols bi-directional map (addr<>name).
+    symbols: BiBTreeMap<u64, String>,
+}
+
+impl SymbolMap {
+    /// Load a symbol map from a file. `kaslr_offset` is added to all the
+    /// non-absolute addresses; it must be 0 for files holding runtime
+    /// addresses, such as /proc/kallsyms.
+    pub(crate) fn from_file<P: AsRef<Path>>(path: P, kaslr_offset: u64) -> Result<Self> {
+        let path = path.as_ref();
+        Self::parse(
+            &fs::read_to_string(path)
+                .map_err(|e| anyhow!("Could not read {}: {e}", path.display()))?,
+            kaslr_offset,
+        )
+    }
+
+    fn parse(content: &str, kaslr_offset: u64) -> Result<Self> {
+        let mut symbols = BiBTreeMap::new();
+        // Lines have to be processed backward in order to overwrite
+        // duplicate addresses and keep the first (which is the last
+        // inserted in the common case involving module init
+        // functions) instead of the last one.
+        for line in content.lines().rev() {
+            let data: Vec<&str> = line.split(' ').collect();
+            if data.len() < 3 {
+                bail!("Invalid kallsyms line: {}", line);
+            }
+
+            let symbol: &str = data[2]
+                .split('\t')
+                .next()
+                .ok_or_else(|| anyhow!("Couldn't get symbol name for {}", data[0]))?;
+
+            let mut addr = u64::from_str_radix(data[0], 16)?;
+            // Absolute symbols (e.g. per-cpu ones) are not relocated.
+            if !data[1].eq_ignore_ascii_case("a") {
+                addr = addr.wrapping_add(kaslr_offset);
+            }
+
+            symbols.insert(addr, String::from(symbol));
+        }
+
+        // If all symbols have a 0-address, only the last one will be left in
+        // the map after the above.
+        if symbols.len() == 1 {
+            bail!("Retis likely does not have the rights to read the symbol addresses from /proc/kallsyms.");
+        }
+
+        Ok(Self { symbols })
+    }
+}
+
+/// Parse a KASLR offset, given in hexadecimal (e.g. `0x1a000000`).
+pub(crate) fn parse_kaslr_offset(offset: &str) -> Result<u64> {
+    let offset = offset.trim();
+    u64::from_str_radix(offset.trim_start_matches("0x"), 16)
+        .map_err(|e| anyhow!("Invalid KASLR offset '{offset}' ({e})"))
+}
+
+impl Symbolizer for SymbolMap {
+    fn name(&self, addr: u64) -> Result<String> {
+        Ok(self
+            .symbols
+            .get_by_left(&addr)
+            .ok_or_else(|| anyhow!("Can't get symbol name for {}", addr))?
+            .clone())
+    }
+
+    fn addr(&self, name: &str) -> Result<u64> {
+        Ok(*self
+            .symbols
+            .get_by_right(&name.to_string())
+            .ok_or_else(|| anyhow!("Can't get symbol address for {}", name))?)
+    }
+
+    fn nearest(&self, addr: u64) -> Result<u64> {
+        let bounding = (Unbounded, Included(addr));
+        match self.symbols.range_by_left(&bounding).next_back() {
+            Some(symbol) => Ok(*symbol.0),
+            None => bail!("Can't get a symbol near {}", addr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str = "0000000000001000 A cpu_debug_store
+ffffffff81000000 T _text
+ffffffff81001000 T consume_skb
+ffffffff81002000 t nft_do_chain\t[nf_tables]
+";
+
+    #[test]
+    fn symbol_map() {
+        let map = SymbolMap::parse(MAP, 0).unwrap();
+        assert_eq!(map.name(0xffffffff81001000).unwrap(), "consume_skb");
+        assert_eq!(map.addr("nft_do_chain").unwrap(), 0xffffffff81002000);
+        assert_eq!(map.nearest(0xffffffff81001042).unwrap(), 0xffffffff81001000);
+        assert!(map.nearest(0x10).is_err());
+
+        let relocated = SymbolMap::parse(MAP, 0x1a000000).unwrap();
+        assert_eq!(relocated.addr("consume_skb").unwrap(), 0xffffffff9b001000);
+        // Absolute symbols are not relocated.
+        assert_eq!(relocated.addr("cpu_debug_store").unwrap(), 0x1000);
+
+        assert_eq!(parse_kaslr_offset("0x1a000000").unwrap(), 0x1a000000);
+        assert_eq!(parse_kaslr_offset("1a000000").unwrap(), 0x1a000000);
+        assert!(parse_kaslr_offset("foo").is_err());
+    }
+}
@@ -2,7 +2,7 @@ use std::fmt;
 
 use anyhow::{bail, Result};
 
-use crate::core::inspect::inspector;
+use crate::core::{inspect::inspector, probe::kernel::fallback::kprobe_fallback};
 
 /// Kernel symbol representation. Only supports traceable symbols: events and
 /// functions.
@@ -23,6 +23,11 @@ impl Symbol {
         }
 
         if inspector()?.kernel.btf.find_prototype_btf(&self).is_err() {
+            // Events can still be probed using an equivalent kprobe.
+            if kprobe_fallback(&self).is_some() {
+                return Ok(self);
+            }
+
             bail!(
                 "Symbol {} is not supported (no corresponding BTF definition)",
                 self
@@ -190,6 +195,12 @@ impl Symbol {
         inspector()?.kernel.function_nargs(self)
     }
 
+    /// Get the symbol parameters, as C declarations (e.g. `struct sk_buff
+    /// *skb`).
+    pub(crate) fn parameters(&self) -> Result<Vec<String>> {
+        inspector()?.kernel.function_parameters(self)
+    }
+
     /// Get a parameter offset given its type, if found. Can be used to check a
     /// function has a given parameter by using:
     /// `function_parameter_offset()?.is_some()`.
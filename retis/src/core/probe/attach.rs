@@ -0,0 +1,269 @@
+//! # Attach
+//!
+//! Tracks the attachment of probes: reports the progress while attaching a
+//! large number of probes, enforces an optional attach timeout and reports
+//! probes which failed to attach or took long to do so.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Result};
+use log::{info, warn};
+
+/// Attaching a probe taking longer than this is reported.
+const ATTACH_SLOW: Duration = Duration::from_secs(1);
+/// Interval between two progress reports.
+const ATTACH_PROGRESS_INTERVAL: Duration = Duration::from_secs(2);
+/// Maximum number of threads used to attach probes in parallel.
+const ATTACH_THREADS_MAX: usize = 8;
+/// Maximum number of slow probes reported.
+const ATTACH_SLOW_REPORT_MAX: usize = 10;
+
+#[derive(Default)]
+struct AttachReport {
+    /// Probes which failed to attach, along with the reason.
+    failed: Vec<(String, String)>,
+    /// Probes which were not attached as the timeout was reached.
+    timed_out: Vec<String>,
+    /// Probes which took long to attach, along with the time it took.
+    slow: Vec<(String, Duration)>,
+}
+
+/// Progress of the attachment of a set of probes. Can be shared between the
+/// threads attaching the probes.
+pub(crate) struct AttachProgress {
+    total: usize,
+    attached: AtomicUsize,
+    done: AtomicUsize,
+    deadline: Option<Instant>,
+    last_report: Mutex<Instant>,
+    report: Mutex<AttachReport>,
+}
+
+impl AttachProgress {
+    /// Start tracking the attachment of `total` probes, which must be done
+    /// before the optional timeout elapses.
+    pub(crate) fn new(total: usize, timeout: Option<Duration>) -> Self {
+        let now = Instant::now();
+        Self {
+            total,
+            attached: AtomicUsize::new(0),
+            done: AtomicUsize::new(0),
+            deadline: timeout.map(|t| now + t),
+            last_report: Mutex::new(now),
+            report: Mutex::new(AttachReport::default()),
+        }
+    }
+
+    /// Attach a single probe, described by `desc`, using the given function.
+    /// Returns None if the probe failed to attach or if the timeout was
+    /// reached, the probe being accounted as such.
+    pub(crate) fn attach<T, F>(&self, desc: &str, attach: F) -> Option<T>
+    where
+        F: FnOnce() -> Result<T>,
+    {
+        if self.expired() {
+            self.lock_report().timed_out.push(desc.to_string());
+            self.done.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+
+        let start = Instant::now();
+        let res = attach();
+        let elapsed = start.elapsed();
+
+        {
+            let mut report = self.lock_report();
+            if elapsed >= ATTACH_SLOW {
+                report.slow.push((desc.to_string(), elapsed));
+            }
+            if let Err(e) = &res {
+                report.failed.push((desc.to_string(), e.to_string()));
+            }
+        }
+
+        if res.is_ok() {
+            self.attached.fetch_add(1, Ordering::Relaxed);
+        }
+        self.done.fetch_add(1, Ordering::Relaxed);
+        self.report_progress();
+
+        res.ok()
+    }
+
+    /// Attach multiple targets in parallel using the given function. Each
+    /// target is a tuple of the probe description and of the value given to
+    /// the attach function. Results are returned in the targets order.
+    pub(crate) fn attach_parallel<T, F>(
+        &self,
+        targets: &[(String, String)],
+        attach: F,
+    ) -> Vec<Option<T>>
+    where
+        T: Send,
+        F: Fn(&str) -> Result<T> + Sync,
+    {
+        if targets.is_empty() {
+            return Vec::new();
+        }
+
+        let threads = thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .clamp(1, ATTACH_THREADS_MAX);
+        let chunk_size = targets.len().div_ceil(threads);
+        let attach = &attach;
+
+        thread::scope(|s| {
+            let handles = targets
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    s.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|(desc, target)| self.attach(desc, || attach(target)))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .zip(targets.chunks(chunk_size))
+                .flat_map(|(handle, chunk)| {
+                    // A panic while attaching a chunk is not expected, account
+                    // for its probes as failures.
+                    handle.join().unwrap_or_else(|_| {
+                        chunk
+                            .iter()
+                            .map(|(desc, _)| {
+                                self.attach::<T, _>(desc, || bail!("Attach thread panicked"))
+                            })
+                            .collect()
+                    })
+                })
+                .collect()
+        })
+    }
+
+    /// Check if the attach timeout was reached.
+    pub(crate) fn expired(&self) -> bool {
+        self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+
+    /// Account for probes attached at once, outside of this helper.
+    pub(crate) fn account_attached(&self, count: usize) {
+        self.attached.fetch_add(count, Ordering::Relaxed);
+        self.done.fetch_add(count, Ordering::Relaxed);
+        self.report_progress();
+    }
+
+    /// Number of probes successfully attached.
+    pub(crate) fn attached(&self) -> usize {
+        self.attached.load(Ordering::Relaxed)
+    }
+
+    /// Report probes which were not attached or took long to. Fails if any
+    /// probe failed to attach; probes which were not attached because of the
+    /// timeout are only reported.
+    pub(crate) fn report(&self) -> Result<()> {
+        let mut report = self.lock_report();
+
+        if !report.slow.is_empty() {
+            report.slow.sort_by_key(|(_, t)| std::cmp::Reverse(*t));
+            warn!(
+                "{} probe(s) took more than {}s to attach:",
+                report.slow.len(),
+                ATTACH_SLOW.as_secs()
+            );
+            report
+                .slow
+                .iter()
+                .take(ATTACH_SLOW_REPORT_MAX)
+                .for_each(|(desc, t)| warn!("  {desc} ({:.1}s)", t.as_secs_f64()));
+            if report.slow.len() > ATTACH_SLOW_REPORT_MAX {
+                warn!(
+                    "  ... and {} more",
+                    report.slow.len() - ATTACH_SLOW_REPORT_MAX
+                );
+            }
+        }
+
+        if !report.timed_out.is_empty() {
+            warn!(
+                "Attach timeout reached, {} probe(s) were not attached:",
+                report.timed_out.len()
+            );
+            report.timed_out.iter().for_each(|desc| warn!("  {desc}"));
+        }
+
+        if !report.failed.is_empty() {
+            bail!(
+                "Could not attach {} probe(s):\n{}",
+                report.failed.len(),
+                report
+                    .failed
+                    .iter()
+                    .map(|(desc, e)| format!("  {desc}: {e}"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+
+        Ok(())
+    }
+
+    fn lock_report(&self) -> std::sync::MutexGuard<'_, AttachReport> {
+        // The lock can't be poisoned as we do not panic while holding it.
+        self.report.lock().unwrap()
+    }
+
+    fn report_progress(&self) {
+        let mut last = match self.last_report.try_lock() {
+            Ok(last) => last,
+            // Another thread is reporting.
+            Err(_) => return,
+        };
+
+        if last.elapsed() >= ATTACH_PROGRESS_INTERVAL {
+            info!(
+                "Attaching probes: {}/{}",
+                self.done.load(Ordering::Relaxed),
+                self.total
+            );
+            *last = Instant::now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attach_progress() {
+        let progress = AttachProgress::new(4, None);
+        let targets = (0..4)
+            .map(|i| (format!("probe{i}"), i.to_string()))
+            .collect::<Vec<_>>();
+
+        let res = progress.attach_parallel(&targets, |t| match t {
+            "2" => bail!("failed"),
+            t => Ok(t.parse::<u32>()?),
+        });
+        assert_eq!(res, vec![Some(0), Some(1), None, Some(3)]);
+        assert_eq!(progress.attached(), 3);
+        assert!(progress.report().is_err());
+
+        // Probes are not attached once the timeout is reached.
+        let progress = AttachProgress::new(1, Some(Duration::ZERO));
+        assert!(progress.attach("probe", || Ok(())).is_none());
+        assert_eq!(progress.attached(), 0);
+        assert!(progress.report().is_ok());
+    }
+}
@@ -60,19 +60,35 @@ enum {
 /* Current log level. Actually set by user-space. */
 const volatile u8 log_level = LOG_INFO;
 
+/* Report events using perf buffers instead of BPF ring buffers, for kernels not
+ * supporting the later. Actually set by user-space. It lives in its own section
+ * so it can be set in all objects, including hooks.
+ */
+const volatile bool events_perf_buf SEC(".rodata.events") = false;
+
 /* Log macros must be used carefully and preferrably in the
  * {error,slow} path.
  * Useful exceptions must use a high log level (ideally LOG_TRACE).
+ *
+ * Logs are not reported when using perf buffers, as outputting to those
+ * requires the program context. Logs not fitting in the log map are counted in
+ * log_lost_map.
  */
 #define retis_log(lvl, fmt, args...)					\
 ({									\
-	if (lvl <= log_level) {						\
+	if (lvl <= log_level && !events_perf_buf) {			\
 		struct retis_log_event *__log =				\
 			bpf_ringbuf_reserve(&log_map, sizeof(struct retis_log_event), 0); \
 		if (__log) {						\
 			__log->level = lvl;				\
 			BPF_SNPRINTF(__log->msg, sizeof(__log->msg), fmt, args); \
 			bpf_ringbuf_submit(__log, BPF_RB_FORCE_WAKEUP);	\
+		} else {						\
+			u32 __key = 0;					\
+			u64 *__lost =					\
+				bpf_map_lookup_elem(&log_lost_map, &__key); \
+			if (__lost)					\
+				(*__lost)++;				\
 		}							\
 	}								\
 })
@@ -91,10 +107,14 @@ struct retis_counters_key {
 	u64 pid;
 };
 
-/* Contains the counters of the error path.  This is then processed
- * and reported from user-space. */
+/* Contains the counters of the error path and the probe overhead
+ * measurements.  This is then processed and reported from user-space. */
 struct retis_counters {
 	u64 dropped_events;
+	/* Number of overhead samples, their total and maximum duration. */
+	u64 overhead_samples;
+	u64 overhead_total_ns;
+	u64 overhead_max_ns;
 };
 
 /* Probe configuration; the key is the target symbol address */
@@ -119,6 +139,34 @@ static __always_inline void err_report(u64 sym_addr, u32 pid)
 		__sync_fetch_and_add(&err_counters->dropped_events, 1);
 }
 
+/* Only one probe execution out of OVERHEAD_SAMPLING_RATE is measured, to limit
+ * the cost of the measurements themselves. Must be a power of 2.
+ */
+#define OVERHEAD_SAMPLING_RATE	16
+
+static __always_inline bool overhead_sample()
+{
+	return !(bpf_get_prandom_u32() & (OVERHEAD_SAMPLING_RATE - 1));
+}
+
+static __always_inline void overhead_report(u64 sym_addr, u32 pid, u64 ns)
+{
+	struct retis_counters *counters;
+	struct retis_counters_key key;
+
+	key.pid = pid;
+	key.sym_addr = sym_addr;
+	counters = bpf_map_lookup_elem(&counters_map, &key);
+	if (!counters)
+		return;
+
+	__sync_fetch_and_add(&counters->overhead_samples, 1);
+	__sync_fetch_and_add(&counters->overhead_total_ns, ns);
+	/* Racy, but good enough for reporting. */
+	if (ns > counters->overhead_max_ns)
+		counters->overhead_max_ns = ns;
+}
+
 #ifndef likely
 #define likely(x) __builtin_expect(!!(x), 1)
 #endif
@@ -3,13 +3,22 @@
 //! ProbeBuilder defines the ProbeBuider trait and some useful utility functions
 //!
 use std::{
-    ffi::OsStr,
+    ffi::{CString, OsStr},
+    io,
+    marker::PhantomData,
+    mem,
     os::fd::{BorrowedFd, RawFd},
+    ptr::{self, NonNull},
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
+use libbpf_rs::AsRawLibbpf;
 
-use crate::core::{filters::Filter, probe::*};
+use crate::core::{
+    events::events_use_perf_buf,
+    filters::Filter,
+    probe::{attach::AttachProgress, *},
+};
 
 /// Trait representing the interface used to create and handle probes. We use a
 /// trait here as we're supporting various attach types.
@@ -30,6 +39,16 @@ pub(super) trait ProbeBuilder {
     ) -> Result<()>;
     /// Attach a probe to a given target (function, tracepoint, etc).
     fn attach(&mut self, probe: &Probe) -> Result<()>;
+    /// Attach multiple probes, accounting for them in the given progress.
+    /// Returns for each probe whether it was attached. Builders able to attach
+    /// probes in parallel should override this; by default probes are attached
+    /// one after the other.
+    fn attach_many(&mut self, probes: &[&Probe], progress: &AttachProgress) -> Result<Vec<bool>> {
+        Ok(probes
+            .iter()
+            .map(|p| progress.attach(&p.to_string(), || self.attach(p)).is_some())
+            .collect())
+    }
     /// Detach all probes installed by the builder (function,
     /// tracepoint, etc).
     fn detach(&mut self) -> Result<()>;
@@ -56,6 +75,113 @@ pub(super) fn reuse_map_fds(
     Ok(())
 }
 
+/// A loaded program shared between threads, to attach it to multiple targets
+/// in parallel. Attaching a program only reads it on the libbpf side, which
+/// is safe to do concurrently.
+pub(super) struct SharedProg<'a>(NonNull<libbpf_sys::bpf_program>, PhantomData<&'a ()>);
+
+unsafe impl Send for SharedProg<'_> {}
+unsafe impl Sync for SharedProg<'_> {}
+
+impl<'a> SharedProg<'a> {
+    pub(super) fn new(prog: &'a libbpf_rs::ProgramMut) -> Self {
+        Self(prog.as_libbpf_object(), PhantomData)
+    }
+
+    /// Attach the program to a kprobe (or a kretprobe).
+    pub(super) fn attach_kprobe(&self, retprobe: bool, func: &str) -> Result<libbpf_rs::Link> {
+        let c_func = CString::new(func)?;
+        let link = unsafe {
+            libbpf_sys::bpf_program__attach_kprobe(self.0.as_ptr(), retprobe, c_func.as_ptr())
+        };
+        let link = NonNull::new(link).ok_or_else(|| {
+            anyhow!(
+                "Failed to attach {} to {func}: {}",
+                if retprobe { "kretprobe" } else { "kprobe" },
+                io::Error::last_os_error()
+            )
+        })?;
+
+        // The link comes from libbpf and was checked for errors.
+        Ok(unsafe { libbpf_rs::Link::from_ptr(link) })
+    }
+}
+
+/// Attach a kprobe program to multiple functions at once, using a kprobe multi
+/// link. The program must have been loaded with the kprobe multi expected
+/// attach type.
+pub(super) fn attach_kprobe_multi(
+    prog: &libbpf_rs::ProgramMut,
+    funcs: &[String],
+) -> Result<libbpf_rs::Link> {
+    let funcs = funcs
+        .iter()
+        .map(|f| CString::new(f.as_str()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut syms = funcs.iter().map(|f| f.as_ptr()).collect::<Vec<_>>();
+
+    let opts = libbpf_sys::bpf_kprobe_multi_opts {
+        sz: mem::size_of::<libbpf_sys::bpf_kprobe_multi_opts>() as libbpf_sys::size_t,
+        syms: syms.as_mut_ptr(),
+        cnt: syms.len() as libbpf_sys::size_t,
+        ..Default::default()
+    };
+
+    let link = unsafe {
+        libbpf_sys::bpf_program__attach_kprobe_multi_opts(
+            prog.as_libbpf_object().as_ptr(),
+            ptr::null(),
+            &opts,
+        )
+    };
+    let link = NonNull::new(link).ok_or_else(|| {
+        anyhow!(
+            "Failed to attach kprobe multi link to {} function(s): {}",
+            syms.len(),
+            io::Error::last_os_error()
+        )
+    })?;
+
+    // The link comes from libbpf and was checked for errors.
+    Ok(unsafe { libbpf_rs::Link::from_ptr(link) })
+}
+
+/// Configure how an object reports events. This is done by hand as the
+/// configuration is also needed in hooks, which do not have a skeleton.
+pub(super) fn set_events_output(open_obj: &mut libbpf_rs::OpenObject) -> Result<()> {
+    match events_use_perf_buf() {
+        true => use_events_perf_buf(open_obj),
+        false => Ok(()),
+    }
+}
+
+/// Make an object report events using perf buffers: its `events_perf_buf`
+/// variable is set and its events and log maps, defined as ring buffers, are
+/// turned into perf event arrays before the object is loaded.
+pub(super) fn use_events_perf_buf(open_obj: &mut libbpf_rs::OpenObject) -> Result<()> {
+    let ncpus = libbpf_rs::num_possible_cpus()? as u32;
+
+    // Objects not including the events definitions do not report events.
+    for mut map in open_obj.maps_mut() {
+        match map.name().to_str() {
+            Some("events_map" | "log_map") => {
+                // Perf event arrays hold one perf event fd per cpu.
+                map.set_type(libbpf_rs::MapType::PerfEventArray)?;
+                map.set_key_size(mem::size_of::<u32>() as u32)?;
+                map.set_value_size(mem::size_of::<u32>() as u32)?;
+                map.set_max_entries(ncpus)?;
+            }
+            // The variable is alone in its section.
+            Some(".rodata.events") => match map.initial_value_mut() {
+                Some(data) if !data.is_empty() => data[0] = 1,
+                _ => bail!("Could not set the events output configuration"),
+            },
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
 pub(super) fn replace_hooks(fd: RawFd, hooks: &[Hook]) -> Result<Vec<libbpf_rs::Link>> {
     let mut links = Vec::new();
 
@@ -68,6 +194,7 @@ pub(super) fn replace_hooks(fd: RawFd, hooks: &[Hook]) -> Result<Vec<libbpf_rs::
         // at build time.
         let map_fds: Vec<(String, RawFd)> = hook.maps.clone().into_iter().collect();
         reuse_map_fds(&mut open_obj, &map_fds)?;
+        set_events_output(&mut open_obj)?;
 
         let mut open_prog = open_obj
             .progs_mut()
@@ -42,12 +42,18 @@ pub(crate) struct CountersKey {
 unsafe impl plain::Plain for CountersKey {}
 
 // Please keep in sync with its BPF counterpart in bpf/include/common_defs.h
-/// Contains the counters of the error path.  This is then processed
-/// and reported from user-space. */
+/// Contains the counters of the error path and the probe overhead
+/// measurements.  This is then processed and reported from user-space. */
 #[derive(Default)]
 #[repr(C)]
 pub(crate) struct Counters {
     pub(crate) dropped_events: u64,
+    /// Number of overhead samples.
+    pub(crate) overhead_samples: u64,
+    /// Total duration of the overhead samples, in nanoseconds.
+    pub(crate) overhead_total_ns: u64,
+    /// Maximum duration of an overhead sample, in nanoseconds.
+    pub(crate) overhead_max_ns: u64,
 }
 unsafe impl plain::Plain for Counters {}
 
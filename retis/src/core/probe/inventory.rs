@@ -0,0 +1,282 @@
+//! # Inventory
+//!
+//! Lists the probes and BPF programs attached by other tools on the targets
+//! Retis uses. Those can interact with Retis: BPF programs attached to kprobes
+//! and tracepoints do not nest, so events can be missed when another program
+//! is running on the same CPU, and the order in which programs run on a given
+//! target is not defined.
+//!
+//! Also lists the BPF objects (programs, maps and links) loaded by Retis
+//! itself, for those to be reconciled with the output of external tools.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+};
+
+use libbpf_rs::{
+    query::{LinkInfoIter, LinkTypeInfo, MapInfoIter, ProgInfoIter, ProgInfoQueryOptions},
+    ProgramType,
+};
+use log::debug;
+
+use crate::{
+    core::probe::{Probe, ProbeType},
+    events::{AttachedProbe, BpfLink, BpfMap, BpfObjectsEvent, BpfProg},
+};
+
+/// List of the registered kprobes, including the ones not installed through
+/// BPF (perf, SystemTap, etc).
+const KPROBES_LIST: &str = "/sys/kernel/debug/kprobes/list";
+
+/// Get the probes and BPF programs attached by others on the targets of
+/// `probes`. Loaded tc BPF programs are reported if `tc` is set.
+///
+/// Must be called before Retis attaches its own probes.
+pub(crate) fn attached_probes<'a>(
+    probes: impl Iterator<Item = &'a Probe>,
+    tc: bool,
+) -> Vec<AttachedProbe> {
+    // Kprobes and kretprobes on a function are conflicting, use the function
+    // name only. Tracepoints are indexed by their attach name, as reported
+    // in links.
+    let mut funcs = HashSet::new();
+    let mut tps = HashMap::new();
+    probes.for_each(|p| match p.r#type() {
+        ProbeType::Kprobe(kp) | ProbeType::Kretprobe(kp) => {
+            funcs.insert(kp.symbol.attach_name());
+        }
+        ProbeType::RawTracepoint(kp) => {
+            tps.insert(kp.symbol.attach_name(), kp.symbol.name());
+        }
+        _ => (),
+    });
+
+    let progs = ProgInfoIter::default()
+        .map(|info| (info.id, info))
+        .collect::<HashMap<_, _>>();
+    let prog_name = |id: &u32| {
+        progs
+            .get(id)
+            .map(|info| info.name.to_string_lossy().to_string())
+            .filter(|name| !name.is_empty())
+    };
+
+    let mut attached = Vec::new();
+
+    // Kprobes do not report the program using them, if any.
+    match fs::read_to_string(KPROBES_LIST) {
+        Ok(list) => parse_kprobes_list(&list)
+            .into_iter()
+            .filter(|(_, func)| funcs.contains(func))
+            .for_each(|(r#type, func)| {
+                attached.push(AttachedProbe {
+                    target: format!("{type}:{func}"),
+                    prog_id: None,
+                    prog_name: None,
+                })
+            }),
+        Err(e) => debug!("Could not read {KPROBES_LIST}: {e}"),
+    }
+
+    LinkInfoIter::default().for_each(|link| {
+        if let LinkTypeInfo::RawTracepoint(tp) = link.info {
+            if let Some(name) = tps.get(&tp.name) {
+                attached.push(AttachedProbe {
+                    target: format!("tp:{name}"),
+                    prog_id: Some(link.prog_id),
+                    prog_name: prog_name(&link.prog_id),
+                });
+            }
+        }
+    });
+
+    if tc {
+        progs
+            .values()
+            .filter(|info| matches!(info.ty, ProgramType::SchedCls | ProgramType::SchedAct))
+            .for_each(|info| {
+                attached.push(AttachedProbe {
+                    target: "tc".to_string(),
+                    prog_id: Some(info.id),
+                    prog_name: prog_name(&info.id),
+                })
+            });
+    }
+
+    attached.sort_by(|a, b| (&a.target, a.prog_id).cmp(&(&b.target, b.prog_id)));
+    attached.dedup_by(|a, b| a.target == b.target && a.prog_id == b.prog_id);
+    attached
+}
+
+/// Get the BPF objects (programs, maps and links) currently loaded by Retis.
+/// Objects are found by looking at the file descriptors of the process; maps
+/// used by our programs and programs only referenced by our links are
+/// included as well.
+pub(crate) fn own_objects() -> BpfObjectsEvent {
+    let mut prog_ids = HashSet::new();
+    let mut map_ids = HashSet::new();
+    let mut link_types = HashMap::new();
+
+    match fs::read_dir("/proc/self/fd") {
+        Ok(entries) => entries.filter_map(|e| e.ok()).for_each(|entry| {
+            let kind = match fs::read_link(entry.path()) {
+                Ok(target) => target.to_string_lossy().to_string(),
+                Err(_) => return,
+            };
+            let fdinfo = match fs::read_to_string(format!(
+                "/proc/self/fdinfo/{}",
+                entry.file_name().to_string_lossy()
+            )) {
+                Ok(fdinfo) => fdinfo,
+                Err(_) => return,
+            };
+            let fdinfo = parse_fdinfo(&fdinfo);
+            let id = |key| fdinfo.get(key).and_then(|id| id.parse::<u32>().ok());
+
+            match kind.as_str() {
+                "anon_inode:bpf-prog" => prog_ids.extend(id("prog_id")),
+                "anon_inode:bpf-map" => map_ids.extend(id("map_id")),
+                "anon_inode:bpf_link" => {
+                    if let Some(link_id) = id("link_id") {
+                        link_types.insert(
+                            link_id,
+                            fdinfo.get("link_type").unwrap_or(&"unknown").to_string(),
+                        );
+                    }
+                    prog_ids.extend(id("prog_id"));
+                }
+                _ => (),
+            }
+        }),
+        Err(e) => debug!("Could not list our own file descriptors: {e}"),
+    }
+
+    let mut links = LinkInfoIter::default()
+        .filter_map(|link| {
+            let r#type = link_types.get(&link.id)?.clone();
+            prog_ids.insert(link.prog_id);
+            Some(BpfLink {
+                id: link.id,
+                prog_id: link.prog_id,
+                r#type,
+                target: match link.info {
+                    LinkTypeInfo::RawTracepoint(tp) => Some(tp.name),
+                    _ => None,
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+    links.sort_by_key(|link| link.id);
+
+    let mut progs =
+        ProgInfoIter::with_query_opts(ProgInfoQueryOptions::default().include_map_ids(true))
+            .filter(|info| prog_ids.contains(&info.id))
+            .map(|info| {
+                map_ids.extend(info.map_ids.iter());
+                BpfProg {
+                    id: info.id,
+                    name: info.name.to_string_lossy().to_string(),
+                    r#type: type_name(&info.ty),
+                    map_ids: info.map_ids,
+                }
+            })
+            .collect::<Vec<_>>();
+    progs.sort_by_key(|prog| prog.id);
+
+    let mut maps = MapInfoIter::default()
+        .filter(|info| map_ids.contains(&info.id))
+        .map(|info| BpfMap {
+            id: info.id,
+            name: info.name.to_string_lossy().to_string(),
+            r#type: type_name(&info.ty),
+        })
+        .collect::<Vec<_>>();
+    maps.sort_by_key(|map| map.id);
+
+    BpfObjectsEvent { progs, maps, links }
+}
+
+/// Parse the content of a BPF object fdinfo file into its key/value pairs.
+/// Lines look like:
+///
+/// `prog_id:\t42`
+fn parse_fdinfo(fdinfo: &str) -> HashMap<&str, &str> {
+    fdinfo
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, val)| (key.trim(), val.trim()))
+        .collect()
+}
+
+/// Convert a libbpf-rs program or map type to a name close to the one used by
+/// bpftool, e.g. `RawTracepoint` to `raw_tracepoint`.
+fn type_name<T: std::fmt::Debug>(r#type: &T) -> String {
+    let mut name = String::new();
+    format!("{type:?}").chars().for_each(|c| {
+        if c.is_ascii_uppercase() && !name.is_empty() {
+            name.push('_');
+        }
+        name.push(c.to_ascii_lowercase());
+    });
+    name
+}
+
+/// Parse the kprobes list, as found in debugfs, and return the type (kprobe
+/// or kretprobe) and function of each probe. Lines look like:
+///
+/// `ffffffff8b4a2e50  k  ip_rcv+0x0    [FTRACE]`
+fn parse_kprobes_list(list: &str) -> Vec<(&'static str, String)> {
+    list.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().skip(1);
+            let r#type = match fields.next()? {
+                "k" => "kprobe",
+                "r" => "kretprobe",
+                _ => return None,
+            };
+            let func = fields.next()?.split('+').next()?;
+            Some((r#type, func.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kprobes_list() {
+        let list = "ffffffff8b4a2e50  k  ip_rcv+0x0    [FTRACE]
+ffffffff8b4a3e70  r  tcp_v4_rcv+0x0  [DISABLED][FTRACE]
+ffffffffc0a1b2c3  k  nft_do_chain+0x4  [nf_tables]
+garbage
+";
+        assert_eq!(
+            parse_kprobes_list(list),
+            vec![
+                ("kprobe", "ip_rcv".to_string()),
+                ("kretprobe", "tcp_v4_rcv".to_string()),
+                ("kprobe", "nft_do_chain".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn fdinfo() {
+        let fdinfo = "pos:\t0
+flags:\t02000002
+link_type:\traw_tracepoint
+link_id:\t12
+prog_id:\t42
+tp_name:\tskb:kfree_skb
+";
+        let fdinfo = parse_fdinfo(fdinfo);
+        assert_eq!(fdinfo.get("link_type"), Some(&"raw_tracepoint"));
+        assert_eq!(fdinfo.get("link_id"), Some(&"12"));
+        assert_eq!(fdinfo.get("tp_name"), Some(&"skb:kfree_skb"));
+
+        assert_eq!(type_name(&ProgramType::RawTracepoint), "raw_tracepoint");
+        assert_eq!(type_name(&ProgramType::Kprobe), "kprobe");
+    }
+}
@@ -0,0 +1,218 @@
+//! # Automatic probes
+//!
+//! Probes covering the receive, forwarding, local delivery and transmit paths
+//! of the traffic matched by a packet filter, for users not knowing where to
+//! look in the networking stack. The traffic type is guessed from the
+//! keywords of the pcap-filter expression; negations (or expressions without
+//! known keywords) make it match all traffic types. Probes which can't be
+//! found on the running kernel (e.g. IPv6 is not available) are skipped.
+
+use std::net::IpAddr;
+
+use anyhow::{bail, Result};
+use log::debug;
+
+use super::utils::probe_from_cli;
+use crate::core::{kernel::Symbol, probe::Probe};
+
+/// Traffic types matched by a packet filter.
+#[derive(Debug, Default, PartialEq)]
+struct Traffic {
+    arp: bool,
+    ipv4: bool,
+    ipv6: bool,
+    tcp: bool,
+    udp: bool,
+    icmp: bool,
+}
+
+impl Traffic {
+    /// Guess the traffic types matched by a pcap-filter expression.
+    fn from_filter(filter: &str) -> Self {
+        let words = filter
+            .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+            .filter(|w| !w.is_empty())
+            .collect::<Vec<_>>();
+
+        let mut traffic = Traffic::default();
+        if words.iter().any(|w| *w == "not" || w.starts_with('!')) {
+            traffic.all_l3();
+            traffic.all_l4();
+            return traffic;
+        }
+
+        let mut ports = false;
+        for word in words.iter() {
+            match *word {
+                "arp" | "rarp" => traffic.arp = true,
+                "ip" => traffic.ipv4 = true,
+                "ip6" => traffic.ipv6 = true,
+                "tcp" => traffic.tcp = true,
+                "udp" => traffic.udp = true,
+                "icmp" => (traffic.ipv4, traffic.icmp) = (true, true),
+                "icmp6" => (traffic.ipv6, traffic.icmp) = (true, true),
+                "port" | "portrange" => ports = true,
+                addr => match addr.split('/').next().unwrap_or(addr).parse::<IpAddr>() {
+                    Ok(IpAddr::V4(_)) => traffic.ipv4 = true,
+                    Ok(IpAddr::V6(_)) => traffic.ipv6 = true,
+                    Err(_) => (),
+                },
+            }
+        }
+
+        let l4 = traffic.tcp || traffic.udp || traffic.icmp;
+        if !traffic.arp && !traffic.ipv4 && !traffic.ipv6 {
+            traffic.all_l3();
+            // L4 protocols or ports are IP only.
+            traffic.arp = !l4 && !ports;
+        }
+        if ports && !l4 {
+            (traffic.tcp, traffic.udp) = (true, true);
+        } else if !l4 && (traffic.ipv4 || traffic.ipv6) {
+            traffic.all_l4();
+        }
+
+        traffic
+    }
+
+    fn all_l3(&mut self) {
+        (self.arp, self.ipv4, self.ipv6) = (true, true, true);
+    }
+
+    fn all_l4(&mut self) {
+        (self.tcp, self.udp, self.icmp) = (true, true, true);
+    }
+
+    /// Probes, following the `--probe` syntax, covering the traffic.
+    fn probes(&self) -> Vec<&'static str> {
+        // Receive, transmit and drop points common to all traffic.
+        let mut probes = vec![
+            "tp:net:netif_receive_skb",
+            "tp:net:net_dev_start_xmit",
+            "tp:skb:kfree_skb",
+        ];
+
+        if self.arp {
+            probes.extend(["kprobe:arp_rcv", "kprobe:arp_xmit"]);
+        }
+        if self.ipv4 {
+            probes.extend([
+                "kprobe:ip_rcv",
+                "kprobe:ip_forward",
+                "kprobe:ip_local_deliver",
+                "kprobe:ip_output",
+            ]);
+            if self.tcp {
+                probes.push("kprobe:tcp_v4_rcv");
+            }
+            if self.udp {
+                probes.push("kprobe:udp_rcv");
+            }
+            if self.icmp {
+                probes.push("kprobe:icmp_rcv");
+            }
+        }
+        if self.ipv6 {
+            probes.extend([
+                "kprobe:ipv6_rcv",
+                "kprobe:ip6_forward",
+                "kprobe:ip6_input",
+                "kprobe:ip6_output",
+            ]);
+            if self.tcp {
+                probes.push("kprobe:tcp_v6_rcv");
+            }
+            if self.udp {
+                probes.push("kprobe:udpv6_rcv");
+            }
+            if self.icmp {
+                probes.push("kprobe:icmpv6_rcv");
+            }
+        }
+
+        probes
+    }
+}
+
+/// Probes, following the `--probe` syntax, covering the traffic matched by a
+/// packet filter.
+pub(crate) fn auto_probes_list(filter: &str) -> Vec<&'static str> {
+    Traffic::from_filter(filter).probes()
+}
+
+/// Resolve the probes covering the traffic matched by a packet filter on the
+/// running kernel. Probes which can't be found, or not matching the filter,
+/// are skipped.
+pub(crate) fn auto_probes<F>(packet_filter: &str, filter: F) -> Result<Vec<Probe>>
+where
+    F: Fn(&Symbol) -> bool + Copy,
+{
+    let mut probes = Vec::new();
+    for probe in auto_probes_list(packet_filter) {
+        match probe_from_cli(probe, filter) {
+            Ok(mut p) => probes.append(&mut p),
+            Err(e) => debug!("Automatic probes: skipping {probe} ({e})"),
+        }
+    }
+
+    if probes.is_empty() {
+        bail!("No automatic probe could be added");
+    }
+    Ok(probes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traffic() {
+        let traffic = |arp, ipv4, ipv6, tcp, udp, icmp| Traffic {
+            arp,
+            ipv4,
+            ipv6,
+            tcp,
+            udp,
+            icmp,
+        };
+
+        assert_eq!(
+            Traffic::from_filter("tcp port 80"),
+            traffic(false, true, true, true, false, false)
+        );
+        assert_eq!(
+            Traffic::from_filter("ip6 and udp"),
+            traffic(false, false, true, false, true, false)
+        );
+        assert_eq!(
+            Traffic::from_filter("host 10.0.0.1 and port 53"),
+            traffic(false, true, false, true, true, false)
+        );
+        assert_eq!(
+            Traffic::from_filter("icmp6"),
+            traffic(false, false, true, false, false, true)
+        );
+        assert_eq!(
+            Traffic::from_filter("arp"),
+            traffic(true, false, false, false, false, false)
+        );
+        assert_eq!(
+            Traffic::from_filter("net 10.0.0.0/8"),
+            traffic(false, true, false, true, true, true)
+        );
+        assert_eq!(
+            Traffic::from_filter("ether host 00:11:22:33:44:55"),
+            traffic(true, true, true, true, true, true)
+        );
+        assert_eq!(
+            Traffic::from_filter("not tcp"),
+            traffic(true, true, true, true, true, true)
+        );
+
+        let probes = auto_probes_list("tcp and host 10.0.0.1");
+        assert!(probes.contains(&"kprobe:tcp_v4_rcv"));
+        assert!(probes.contains(&"tp:net:netif_receive_skb"));
+        assert!(!probes.contains(&"kprobe:udp_rcv"));
+        assert!(!probes.iter().any(|p| p.contains("ip6")));
+    }
+}
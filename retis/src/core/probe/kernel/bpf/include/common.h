@@ -25,6 +25,7 @@ struct kernel_event {
 struct retis_probe_config {
 	struct retis_probe_offsets offsets;
 	u8 stack_trace;
+	u8 measure_overhead;
 } __binding;
 
 /* Probe configuration; the key is the target symbol address */
@@ -212,6 +213,7 @@ static __always_inline int chain(struct retis_context *ctx)
 	volatile u16 pass_threshold;
 	struct common_event *e;
 	struct kernel_event *k;
+	bool measure;
 
 	/* Check if the collection is enabled, otherwise bail out. Once we have
 	 * a positive result, cache it.
@@ -227,6 +229,7 @@ static __always_inline int chain(struct retis_context *ctx)
 		return 0;
 
 	ctx->offsets = cfg->offsets;
+	measure = cfg->measure_overhead && overhead_sample();
 
 	filter(ctx);
 
@@ -248,6 +251,7 @@ static __always_inline int chain(struct retis_context *ctx)
 
 	event = get_event();
 	if (!event) {
+		next_event_seq();
 		err_report(ctx->ksym, 0);
 		goto exit;
 	}
@@ -306,9 +310,10 @@ static __always_inline int chain(struct retis_context *ctx)
 	CALL_HOOK(8)
 	CALL_HOOK(9)
 
-	if (get_event_size(event) > pass_threshold)
-		send_event(event);
-	else
+	if (get_event_size(event) > pass_threshold) {
+		e->seq = next_event_seq();
+		send_event(ctx->orig_ctx, event);
+	} else
 discard_event:
 		discard_event(event);
 
@@ -319,6 +324,10 @@ exit:
 	if (RETIS_TRACKABLE(ctx->filters_ret))
 		track_skb_end(ctx);
 
+	/* The probe entry timestamp is used as the start of the measurement. */
+	if (measure)
+		overhead_report(ctx->ksym, 0, bpf_ktime_get_ns() - ctx->timestamp);
+
 	return 0;
 }
 
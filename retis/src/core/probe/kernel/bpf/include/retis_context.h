@@ -27,6 +27,7 @@ struct retis_probe_offsets {
 	s8 net;	 /* netns */
 	s8 nft_pktinfo;
 	s8 nft_traceinfo;
+	s8 sock;
 };
 
 /* Common representation of the register values provided to the probes, as this
@@ -108,6 +109,8 @@ struct retis_context {
 	RETIS_GET(ctx, nft_pktinfo, struct nft_pktinfo *)
 #define retis_get_nft_traceinfo(ctx)	\
 	RETIS_GET(ctx, nft_traceinfo, struct nft_traceinfo *)
+#define retis_get_sock(ctx)		\
+	RETIS_GET(ctx, sock, struct sock *)
 
 /* Returns the skb trying to get it first from the arguments (common case)
  * and if not found from the nft_pktinfo (useful for nft).
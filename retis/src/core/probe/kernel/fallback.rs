@@ -0,0 +1,122 @@
+//! # Fallback
+//!
+//! Degradation logic for kernels not supporting some probe types. Raw
+//! tracepoints can't be used if the kernel does not support the corresponding
+//! BPF program type or if the tracepoint BTF definition is missing; in such
+//! cases an equivalent kprobe is used instead, if one is known. The type of the
+//! probe actually used is reported in the kernel event section.
+
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use once_cell::sync::OnceCell;
+
+use super::KernelProbe;
+use crate::core::{
+    inspect::inspector,
+    kernel::Symbol,
+    probe::{Probe, ProbeType},
+};
+
+/// Equivalent functions of raw tracepoints, in order of preference. Functions
+/// must be reached in the same conditions as the tracepoint and have its
+/// relevant parameters.
+const RAW_TRACEPOINT_FALLBACKS: &[(&str, &[&str])] = &[
+    (
+        "skb:kfree_skb",
+        &["sk_skb_reason_drop", "kfree_skb_reason", "kfree_skb"],
+    ),
+    ("skb:consume_skb", &["consume_skb"]),
+    ("openvswitch:ovs_dp_upcall", &["ovs_dp_upcall"]),
+];
+
+/// Check if raw tracepoint programs are supported by the running kernel.
+fn raw_tracepoint_supported() -> bool {
+    static SUPPORTED: OnceCell<bool> = OnceCell::new();
+
+    // Feature probing requires privileges we don't have while testing.
+    if cfg!(test) {
+        return true;
+    }
+
+    *SUPPORTED.get_or_init(
+        || match libbpf_rs::ProgramType::RawTracepoint.is_supported() {
+            Ok(supported) => supported,
+            Err(e) => {
+                warn!("Could not detect raw tracepoint support, assuming it is supported ({e})");
+                true
+            }
+        },
+    )
+}
+
+/// Check if an event can be probed using a raw tracepoint.
+fn raw_tracepoint_usable(symbol: &Symbol) -> bool {
+    raw_tracepoint_supported()
+        && inspector().is_ok_and(|i| i.kernel.btf.find_prototype_btf(symbol).is_ok())
+}
+
+/// Get the function to probe in place of an event, if any.
+pub(crate) fn kprobe_fallback(symbol: &Symbol) -> Option<Symbol> {
+    let event = match symbol {
+        Symbol::Event(name) => name,
+        Symbol::Func(_) => return None,
+    };
+
+    RAW_TRACEPOINT_FALLBACKS
+        .iter()
+        .find(|(tp, _)| tp == event)?
+        .1
+        .iter()
+        .find_map(|func| match Symbol::from_name(func) {
+            Ok(symbol @ Symbol::Func(_)) => Some(symbol),
+            _ => None,
+        })
+}
+
+/// Convert a raw tracepoint probe to an equivalent kprobe if it can't be used
+/// on the running kernel. Other probes are left untouched.
+pub(crate) fn probe_fallback(probe: &mut Probe) -> Result<()> {
+    let symbol = match probe.r#type() {
+        ProbeType::RawTracepoint(kp) if !raw_tracepoint_usable(&kp.symbol) => &kp.symbol,
+        _ => return Ok(()),
+    };
+
+    let func = kprobe_fallback(symbol).ok_or_else(|| {
+        anyhow!("Raw tracepoint {symbol} is not supported and has no kprobe fallback")
+    })?;
+    info!("Raw tracepoint {symbol} is not supported, using a kprobe on {func} instead");
+
+    *probe.type_mut() = ProbeType::Kprobe(KernelProbe::new(func)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kprobe_fallback() {
+        let symbol = Symbol::from_name("skb:kfree_skb").unwrap();
+        assert_eq!(
+            super::kprobe_fallback(&symbol).unwrap().name(),
+            "kfree_skb_reason"
+        );
+
+        let symbol = Symbol::from_name("skb:consume_skb").unwrap();
+        assert_eq!(
+            super::kprobe_fallback(&symbol).unwrap().name(),
+            "consume_skb"
+        );
+
+        // No fallback for functions and unknown events.
+        let symbol = Symbol::from_name("consume_skb").unwrap();
+        assert!(super::kprobe_fallback(&symbol).is_none());
+        let symbol = Symbol::from_name("net:netif_receive_skb").unwrap();
+        assert!(super::kprobe_fallback(&symbol).is_none());
+
+        // Supported raw tracepoints are left untouched.
+        let mut probe = Probe::raw_tracepoint(Symbol::from_name("skb:kfree_skb").unwrap()).unwrap();
+        probe_fallback(&mut probe).unwrap();
+        assert!(matches!(probe.r#type(), ProbeType::RawTracepoint(_)));
+    }
+}
@@ -12,7 +12,7 @@ use crate::{
     core::{
         events::{
             parse_single_raw_section, BpfRawSection, EventSectionFactory, FactoryId,
-            RawEventSectionFactory,
+            RawEventSectionFactory, SectionFactories,
         },
         kernel::Symbol,
         probe::{
@@ -43,11 +43,13 @@ impl KernelProbe {
     pub(crate) fn gen_config(&self, options: &[ProbeOption]) -> Result<retis_probe_config> {
         let mut config = inspect_symbol(&self.symbol)?;
 
-        #[allow(clippy::single_match)]
         options.iter().for_each(|o| match o {
             ProbeOption::StackTrace => {
                 config.stack_trace = 1;
             }
+            ProbeOption::MeasureOverhead => {
+                config.measure_overhead = 1;
+            }
             _ => (),
         });
 
@@ -82,17 +84,40 @@ pub(crate) struct KernelEventFactory {
 }
 
 impl KernelEventFactory {
+    /// Get the kernel section factory out of the section factories, if any.
+    pub(crate) fn from_factories(factories: &mut SectionFactories) -> Option<&mut Self> {
+        factories
+            .get_mut(&FactoryId::Kernel)?
+            .as_any_mut()
+            .downcast_mut::<Self>()
+    }
+
+    /// Symbols resolved so far, by address.
+    pub(crate) fn symbols(&self) -> &HashMap<u64, String> {
+        &self.symbols_cache
+    }
+
+    /// Add a symbol to the cache, e.g. when replaying events recorded on
+    /// another machine.
+    pub(crate) fn add_symbol(&mut self, addr: u64, name: String) {
+        self.symbols_cache.insert(addr, name);
+    }
+
     #[cfg(not(test))]
     fn unmarshal_stackid(&self, event: &mut KernelEvent, stackid: i32) -> Result<()> {
+        // The stack map is not available when events are not parsed during
+        // the collection (e.g. when recovered from a crash dump).
+        let stack_map = match self.stack_map.as_ref() {
+            Some(stack_map) => stack_map,
+            None => return Ok(()),
+        };
+
         if stackid >= 0 {
             let mut stack_trace: Vec<String> = Vec::new();
             // Only stack_map.lookup() gets intentionally performed. This means that at some point
             // it's possible that stack_map's entries could be exhausted.
-            if let Some(stack_bytes) = self
-                .stack_map
-                .as_ref()
-                .expect("Stack map is None")
-                .lookup(&stackid.to_ne_bytes(), libbpf_rs::MapFlags::ANY)?
+            if let Some(stack_bytes) =
+                stack_map.lookup(&stackid.to_ne_bytes(), libbpf_rs::MapFlags::ANY)?
             {
                 let sstack: &[u64] = unsafe {
                     std::slice::from_raw_parts(
@@ -161,9 +186,18 @@ pub(crate) mod benchmark {
 
     impl RawSectionBuilder for kernel_event {
         fn build_raw(out: &mut Vec<u8>) -> Result<()> {
-            let data = Self {
+            let config = RawEventConfig {
                 symbol: Symbol::from_name("openvswitch:ovs_do_execute_action")?.addr()?,
-                type_: 2, // Raw tracepoint.
+                probe_type: 2, // Raw tracepoint.
+                ..Default::default()
+            };
+            Self::build_raw_with(out, &config)
+        }
+
+        fn build_raw_with(out: &mut Vec<u8>, config: &RawEventConfig) -> Result<()> {
+            let data = Self {
+                symbol: config.symbol,
+                type_: config.probe_type,
                 stack_id: -1,
             };
             build_raw_section(out, FactoryId::Kernel as u8, 0, &mut as_u8_vec(&data));
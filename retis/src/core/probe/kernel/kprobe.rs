@@ -7,9 +7,17 @@
 use std::os::fd::{AsFd, AsRawFd, RawFd};
 
 use anyhow::{anyhow, bail, Result};
-use libbpf_rs::skel::{OpenSkel, Skel};
+use libbpf_rs::{
+    skel::{OpenSkel, Skel},
+    AsRawLibbpf,
+};
+use log::debug;
 
-use crate::core::{filters::Filter, probe::builder::*, probe::*, workaround::*};
+use crate::core::{
+    filters::Filter,
+    probe::{attach::AttachProgress, builder::*, *},
+    workaround::*,
+};
 
 mod kprobe_bpf {
     include!("bpf/.out/kprobe.skel.rs");
@@ -20,35 +28,43 @@ use kprobe_bpf::*;
 pub(crate) struct KprobeBuilder<'a> {
     links: Vec<libbpf_rs::Link>,
     skel: Option<SkelStorage<KprobeSkel<'a>>>,
+    /// Program loaded to be attached using kprobe multi links. Loaded on first
+    /// use, Some(None) meaning it could not be loaded.
+    multi_skel: Option<Option<SkelStorage<KprobeSkel<'a>>>>,
+    map_fds: Vec<(String, RawFd)>,
+    hooks: Vec<Hook>,
 }
 
-impl<'a> ProbeBuilder for KprobeBuilder<'a> {
-    fn new() -> KprobeBuilder<'a> {
-        KprobeBuilder::default()
-    }
-
-    fn init(
-        &mut self,
-        map_fds: Vec<(String, RawFd)>,
-        hooks: Vec<Hook>,
-        filters: Vec<Filter>,
-    ) -> Result<()> {
-        if self.skel.is_some() {
-            bail!("Kprobe builder already initialized");
-        }
-
+impl<'a> KprobeBuilder<'a> {
+    /// Load the kprobe program. If `multi` is true, the program is loaded to be
+    /// attached using kprobe multi links; it then can't be attached using
+    /// regular kprobes.
+    fn load(&mut self, multi: bool) -> Result<SkelStorage<KprobeSkel<'a>>> {
         let mut skel = OpenSkelStorage::new::<KprobeSkelBuilder>()?;
 
-        skel.maps.rodata_data.nhooks = hooks.len() as u32;
+        skel.maps.rodata_data.nhooks = self.hooks.len() as u32;
         skel.maps.rodata_data.log_level = log::max_level() as u8;
 
-        filters.iter().for_each(|f| {
-            if let Filter::Meta(m) = f {
-                skel.maps.rodata_data.nmeta = m.0.len() as u32
+        if multi {
+            let prog = skel
+                .open_object_mut()
+                .progs_mut()
+                .find(|p| p.name() == "probe_kprobe")
+                .ok_or_else(|| anyhow!("Couldn't get program"))?;
+            // Not wrapped by libbpf-rs.
+            let ret = unsafe {
+                libbpf_sys::bpf_program__set_expected_attach_type(
+                    prog.as_libbpf_object().as_ptr(),
+                    libbpf_sys::BPF_TRACE_KPROBE_MULTI,
+                )
+            };
+            if ret < 0 {
+                bail!("Could not set the kprobe multi attach type ({ret})");
             }
-        });
+        }
 
-        reuse_map_fds(skel.open_object_mut(), &map_fds)?;
+        reuse_map_fds(skel.open_object_mut(), &self.map_fds)?;
+        set_events_output(skel.open_object_mut())?;
 
         let skel = SkelStorage::load(skel)?;
         let fd = skel
@@ -58,10 +74,57 @@ impl<'a> ProbeBuilder for KprobeBuilder<'a> {
             .ok_or_else(|| anyhow!("Couldn't get program"))?
             .as_fd()
             .as_raw_fd();
-        let mut links = replace_hooks(fd, &hooks)?;
+        let mut links = replace_hooks(fd, &self.hooks)?;
         self.links.append(&mut links);
 
-        self.skel = Some(skel);
+        Ok(skel)
+    }
+
+    /// Attach all the given functions at once using a kprobe multi link.
+    /// Returns None if kprobe multi links can't be used, in which case probes
+    /// should be attached individually.
+    fn attach_multi(&mut self, funcs: &[String]) -> Option<libbpf_rs::Link> {
+        if self.multi_skel.is_none() {
+            self.multi_skel = Some(
+                self.load(true)
+                    .map_err(|e| debug!("Could not load the kprobe multi program: {e}"))
+                    .ok(),
+            );
+        }
+
+        let prog = self
+            .multi_skel
+            .as_ref()?
+            .as_ref()?
+            .object()
+            .progs_mut()
+            .find(|p| p.name() == "probe_kprobe")?;
+
+        attach_kprobe_multi(&prog, funcs)
+            .map_err(|e| debug!("Falling back to individual kprobes: {e}"))
+            .ok()
+    }
+}
+
+impl<'a> ProbeBuilder for KprobeBuilder<'a> {
+    fn new() -> KprobeBuilder<'a> {
+        KprobeBuilder::default()
+    }
+
+    fn init(
+        &mut self,
+        map_fds: Vec<(String, RawFd)>,
+        hooks: Vec<Hook>,
+        _filters: Vec<Filter>,
+    ) -> Result<()> {
+        if self.skel.is_some() {
+            bail!("Kprobe builder already initialized");
+        }
+
+        self.map_fds = map_fds;
+        self.hooks = hooks;
+
+        self.skel = Some(self.load(false)?);
         Ok(())
     }
 
@@ -84,6 +147,54 @@ impl<'a> ProbeBuilder for KprobeBuilder<'a> {
         Ok(())
     }
 
+    fn attach_many(&mut self, probes: &[&Probe], progress: &AttachProgress) -> Result<Vec<bool>> {
+        if self.skel.is_none() {
+            bail!("Kprobe builder is uninitialized");
+        }
+        let targets = probes
+            .iter()
+            .map(|p| match p.r#type() {
+                ProbeType::Kprobe(probe) => Ok((p.to_string(), probe.symbol.attach_name())),
+                _ => bail!("Wrong probe type {}", p),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // First try attaching all probes at once using a kprobe multi link,
+        // which is much cheaper than attaching individual kprobes.
+        if targets.len() > 1 && !progress.expired() {
+            let funcs = targets.iter().map(|(_, f)| f.clone()).collect::<Vec<_>>();
+            if let Some(link) = self.attach_multi(&funcs) {
+                debug!(
+                    "Attached {} kprobe(s) using a kprobe multi link",
+                    funcs.len()
+                );
+                self.links.push(link);
+                progress.account_attached(targets.len());
+                return Ok(vec![true; targets.len()]);
+            }
+        }
+
+        // Unwrap as we checked the builder was initialized above.
+        let obj = self.skel.as_ref().unwrap().object();
+        let prog = obj
+            .progs_mut()
+            .find(|p| p.name() == "probe_kprobe")
+            .ok_or_else(|| anyhow!("Couldn't get program"))?;
+        let prog = SharedProg::new(&prog);
+
+        Ok(progress
+            .attach_parallel(&targets, |func| prog.attach_kprobe(false, func))
+            .into_iter()
+            .map(|link| match link {
+                Some(link) => {
+                    self.links.push(link);
+                    true
+                }
+                None => false,
+            })
+            .collect())
+    }
+
     fn detach(&mut self) -> Result<()> {
         self.links.drain(..);
         Ok(())
@@ -121,4 +232,31 @@ mod tests {
             .attach(&Probe::kprobe(Symbol::from_name("consume_skb").unwrap()).unwrap())
             .is_ok());
     }
+
+    #[test]
+    #[serial(libbpf)]
+    #[cfg_attr(not(feature = "test_cap_bpf"), ignore)]
+    fn load_perf_buf() {
+        let _ = register_filter_handler(
+            "kprobe/probe",
+            libbpf_rs::ProgramType::Kprobe,
+            Some(fixup_filter_load_fn),
+        );
+
+        let mut skel = OpenSkelStorage::new::<KprobeSkelBuilder>().unwrap();
+        use_events_perf_buf(skel.open_object_mut()).unwrap();
+
+        for map in skel.open_object_mut().maps() {
+            match map.name().to_str() {
+                Some("events_map" | "log_map") => {
+                    assert_eq!(map.map_type(), libbpf_rs::MapType::PerfEventArray)
+                }
+                Some(".rodata.events") => assert_eq!(map.initial_value().unwrap()[0], 1),
+                _ => (),
+            }
+        }
+
+        // The program using perf buffers must be accepted by the verifier.
+        assert!(SkelStorage::load(skel).is_ok());
+    }
 }
@@ -13,7 +13,11 @@ use std::os::fd::{AsFd, AsRawFd, RawFd};
 use anyhow::{anyhow, bail, Result};
 use libbpf_rs::skel::{OpenSkel, Skel};
 
-use crate::core::{filters::Filter, probe::builder::*, probe::*, workaround::*};
+use crate::core::{
+    filters::Filter,
+    probe::{attach::AttachProgress, builder::*, *},
+    workaround::*,
+};
 
 mod kretprobe_bpf {
     include!("bpf/.out/kretprobe.skel.rs");
@@ -35,7 +39,7 @@ impl<'a> ProbeBuilder for KretprobeBuilder<'a> {
         &mut self,
         map_fds: Vec<(String, RawFd)>,
         hooks: Vec<Hook>,
-        filters: Vec<Filter>,
+        _filters: Vec<Filter>,
     ) -> Result<()> {
         if self.skel.is_some() {
             bail!("Kretprobe builder already initialized");
@@ -46,13 +50,8 @@ impl<'a> ProbeBuilder for KretprobeBuilder<'a> {
         skel.maps.rodata_data.nhooks = hooks.len() as u32;
         skel.maps.rodata_data.log_level = log::max_level() as u8;
 
-        filters.iter().for_each(|f| {
-            if let Filter::Meta(m) = f {
-                skel.maps.rodata_data.nmeta = m.0.len() as u32
-            }
-        });
-
         reuse_map_fds(skel.open_object_mut(), &map_fds)?;
+        set_events_output(skel.open_object_mut())?;
 
         let skel = SkelStorage::load(skel)?;
         let fd = skel
@@ -98,6 +97,50 @@ impl<'a> ProbeBuilder for KretprobeBuilder<'a> {
         Ok(())
     }
 
+    fn attach_many(&mut self, probes: &[&Probe], progress: &AttachProgress) -> Result<Vec<bool>> {
+        let obj = match &mut self.skel {
+            Some(skel) => skel.object(),
+            _ => bail!("Kretprobe builder is uninitialized"),
+        };
+        let targets = probes
+            .iter()
+            .map(|p| match p.r#type() {
+                ProbeType::Kretprobe(probe) => Ok((p.to_string(), probe.symbol.attach_name())),
+                _ => bail!("Wrong probe type {}", p),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let (mut kretprobe, mut kprobe) = (None, None);
+        for prog in obj.progs_mut() {
+            if prog.name() == "probe_kretprobe_kretprobe" {
+                kretprobe = Some(prog);
+            } else if prog.name() == "probe_kretprobe_kprobe" {
+                kprobe = Some(prog);
+            }
+        }
+        let kretprobe = kretprobe.ok_or_else(|| anyhow!("Couldn't get kretprobe program"))?;
+        let kprobe = kprobe.ok_or_else(|| anyhow!("Couldn't get kprobe program"))?;
+        let (kretprobe, kprobe) = (SharedProg::new(&kretprobe), SharedProg::new(&kprobe));
+
+        // Attach the kretprobe first, as done in attach().
+        Ok(progress
+            .attach_parallel(&targets, |func| {
+                Ok([
+                    kretprobe.attach_kprobe(true, func)?,
+                    kprobe.attach_kprobe(false, func)?,
+                ])
+            })
+            .into_iter()
+            .map(|links| match links {
+                Some(links) => {
+                    self.links.extend(links);
+                    true
+                }
+                None => false,
+            })
+            .collect())
+    }
+
     fn detach(&mut self) -> Result<()> {
         self.links.drain(..);
         Ok(())
@@ -22,7 +22,10 @@
 pub(crate) mod kernel;
 pub(crate) use kernel::*;
 
+pub(crate) mod auto;
 pub(crate) mod config;
+pub(crate) mod fallback;
+pub(crate) mod packs;
 pub(crate) mod probe_stack;
 
 mod inspect;
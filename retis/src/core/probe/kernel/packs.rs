@@ -0,0 +1,159 @@
+//! # Probe packs
+//!
+//! Curated sets of probes on network drivers' functions, so their data path
+//! can be traced without looking for the right functions in the kernel
+//! sources. Driver functions come and go between kernel versions: probes of a
+//! pack which can't be found on the running kernel are skipped, a pack only
+//! fails if none of its probes can be added.
+
+use anyhow::{bail, Result};
+use log::debug;
+
+use super::utils::probe_from_cli;
+use crate::core::{kernel::Symbol, probe::Probe};
+
+/// Set of probes on a driver's functions.
+pub(crate) struct ProbePack {
+    /// Name of the pack, `<driver>-<path>`.
+    pub(crate) name: &'static str,
+    /// Kernel module of the driver.
+    pub(crate) driver: &'static str,
+    /// Short description, shown in the help.
+    pub(crate) about: &'static str,
+    /// Probes, following the `--probe` syntax. Wildcards are not allowed.
+    pub(crate) probes: &'static [&'static str],
+}
+
+/// Probe packs shipped with Retis.
+pub(crate) const PROBE_PACKS: &[ProbePack] = &[
+    ProbePack {
+        name: "mlx5-tx",
+        driver: "mlx5_core",
+        about: "mlx5 transmit path, including queue selection and IPsec/kTLS offloads",
+        probes: &[
+            "kprobe:mlx5e_select_queue",
+            "kprobe:mlx5e_xmit",
+            "kprobe:mlx5e_ipsec_handle_tx_skb",
+            "kprobe:mlx5e_ktls_handle_tx_skb",
+        ],
+    },
+    ProbePack {
+        name: "mlx5-rx",
+        driver: "mlx5_core",
+        about: "mlx5 receive path, IPsec/kTLS/MACsec offloads",
+        probes: &[
+            "kprobe:mlx5e_ipsec_offload_handle_rx_skb",
+            "kprobe:mlx5e_ktls_handle_rx_skb",
+            "kprobe:mlx5e_macsec_offload_handle_rx_skb",
+        ],
+    },
+    ProbePack {
+        name: "ixgbe-tx",
+        driver: "ixgbe",
+        about: "ixgbe transmit path",
+        probes: &[
+            "kprobe:ixgbe_xmit_frame",
+            "kprobe:ixgbe_xmit_frame_ring",
+            "kprobe:ixgbe_tx_csum",
+        ],
+    },
+    ProbePack {
+        name: "ixgbe-rx",
+        driver: "ixgbe",
+        about: "ixgbe receive path, from the descriptor processing to the stack",
+        probes: &[
+            "kprobe:ixgbe_cleanup_headers",
+            "kprobe:ixgbe_process_skb_fields",
+            "kprobe:ixgbe_rx_skb",
+        ],
+    },
+    ProbePack {
+        name: "virtio_net-tx",
+        driver: "virtio_net",
+        about: "virtio-net transmit path",
+        probes: &["kprobe:start_xmit", "kprobe:xmit_skb"],
+    },
+    ProbePack {
+        name: "virtio_net-rx",
+        driver: "virtio_net",
+        about: "virtio-net receive path",
+        probes: &["kprobe:virtio_skb_set_hash", "kprobe:virtnet_receive_done"],
+    },
+    ProbePack {
+        name: "veth-tx",
+        driver: "veth",
+        about: "veth transmit path, up to the peer's queues",
+        probes: &["kprobe:veth_xmit", "kprobe:veth_forward_skb"],
+    },
+    ProbePack {
+        name: "veth-rx",
+        driver: "veth",
+        about: "veth receive path, when NAPI (XDP or GRO) is used on the peer",
+        probes: &["kprobe:veth_xdp_rcv_skb"],
+    },
+];
+
+/// Get a probe pack by its name.
+pub(crate) fn probe_pack(name: &str) -> Result<&'static ProbePack> {
+    match PROBE_PACKS.iter().find(|pack| pack.name == name) {
+        Some(pack) => Ok(pack),
+        None => bail!("Unknown probe pack '{name}'"),
+    }
+}
+
+impl ProbePack {
+    /// Resolve the probes of the pack on the running kernel. Probes which
+    /// can't be found, or not matching the filter, are skipped.
+    pub(crate) fn probes<F>(&self, filter: F) -> Result<Vec<Probe>>
+    where
+        F: Fn(&Symbol) -> bool + Copy,
+    {
+        let mut probes = Vec::new();
+        for probe in self.probes.iter() {
+            match probe_from_cli(probe, filter) {
+                Ok(mut p) => probes.append(&mut p),
+                Err(e) => debug!("Probe pack {}: skipping {probe} ({e})", self.name),
+            }
+        }
+
+        if probes.is_empty() {
+            bail!(
+                "Probe pack '{}': no probe could be added, is the {} driver loaded?",
+                self.name,
+                self.driver
+            );
+        }
+        Ok(probes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::core::probe::kernel::utils::{parse_cli_probe, CliProbeType};
+
+    #[test]
+    fn probe_packs() {
+        let mut names = HashSet::new();
+        for pack in PROBE_PACKS.iter() {
+            assert!(names.insert(pack.name), "duplicate pack {}", pack.name);
+            assert!(!pack.probes.is_empty());
+            assert!(probe_pack(pack.name).is_ok());
+
+            for probe in pack.probes.iter() {
+                let (r#type, target) = parse_cli_probe(probe).unwrap();
+                assert!(matches!(
+                    r#type,
+                    CliProbeType::Kprobe | CliProbeType::Kretprobe
+                ));
+                assert!(!target.contains('*'), "wildcard in {probe}");
+            }
+        }
+
+        assert!(probe_pack("foo-tx").is_err());
+        // Drivers are not loaded in the test environment.
+        assert!(probe_pack("mlx5-tx").unwrap().probes(|_| true).is_err());
+    }
+}
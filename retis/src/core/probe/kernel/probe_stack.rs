@@ -23,6 +23,9 @@ pub(crate) struct ProbeStack {
     probes: HashSet<String>,
     /// Should the stack stay in the event?
     keep_stack: bool,
+    /// If set, only drop events are considered and only the given number of
+    /// functions preceding the drop are probed.
+    drop_depth: Option<usize>,
     /// Set of kernel types known by collectors, so we only probe functions that
     /// can generate an event.
     known_kernel_types: HashSet<String>,
@@ -31,6 +34,7 @@ pub(crate) struct ProbeStack {
 impl ProbeStack {
     pub(crate) fn new(
         keep_stack: bool,
+        drop_depth: Option<usize>,
         attached_probes: Vec<String>,
         known_kernel_types: HashSet<String>,
     ) -> Self {
@@ -45,10 +49,26 @@ impl ProbeStack {
         Self {
             probes: HashSet::from_iter(attached_probes),
             keep_stack,
+            drop_depth,
             known_kernel_types,
         }
     }
 
+    /// Get the probes to add for the functions of a stack trace, up to `depth`
+    /// of them. Functions already considered or not compatible are skipped and
+    /// do not count.
+    fn new_probes(&mut self, stack: &[String], depth: usize) -> Result<Vec<Probe>> {
+        let known_kernel_types = &self.known_kernel_types;
+
+        stack
+            .iter()
+            .filter_map(|line| line.split_once('+').map(|(func, _)| func))
+            .filter(|func| self.probes.insert(func.to_string()))
+            .filter_map(|func| compatible_probe(func, known_kernel_types).transpose())
+            .take(depth)
+            .collect()
+    }
+
     /// Process a new event and detect additional functions to add a probe too.
     /// This is called in the event retrieval logic and should try not to
     /// propagate non-fatal errors.
@@ -57,6 +77,15 @@ impl ProbeStack {
         mgr: &mut ProbeRuntimeManager,
         event: &mut Event,
     ) -> Result<()> {
+        // When following drops, only the stack of drop events is used.
+        let depth = match self.drop_depth {
+            Some(depth) => match event.skb_drop() {
+                Some(_) => depth,
+                None => 0,
+            },
+            None => usize::MAX,
+        };
+
         let kernel = match event.get_section_mut::<KernelEvent>(SectionId::Kernel) {
             Some(kernel) => kernel,
             None => return Ok(()),
@@ -66,58 +95,15 @@ impl ProbeStack {
             None => return Ok(()),
         };
 
-        stack.raw().iter().try_for_each(|line| -> Result<()> {
-            let func = match line.split_once('+') {
-                Some((func, _)) => func,
-                _ => return Ok(()),
-            };
-
-            if self.probes.insert(func.to_string()) {
-                // Filter out functions not having a BTF representation.
-                let types = inspector()?.kernel.btf.resolve_types_by_name(func);
-                if types.is_err()
-                    || !types
-                        .unwrap()
-                        .iter()
-                        .any(|(_, t)| matches!(t, Type::Func(_)))
-                {
-                    return Ok(());
-                }
-
-                let symbol = match Symbol::from_name(func) {
-                    Ok(symbol) => symbol,
-                    _ => return Ok(()),
-                };
-
-                // Filter out symbols not operating on a type we can retrieve
-                // data from.
-                if !self
-                    .known_kernel_types
-                    .iter()
-                    .any(|t| match symbol.parameter_offset(t) {
-                        Ok(ret) => ret.is_some(),
-                        _ => false,
-                    })
-                {
-                    return Ok(());
-                }
-
-                let mut probe = match Probe::kprobe(symbol) {
-                    Ok(probe) => probe,
-                    _ => return Ok(()),
-                };
-
-                #[cfg(not(test))]
-                if let Err(e) = mgr.attach_generic_probe(&mut probe) {
-                    warn!("Could not attach additional probe {probe}: {e}");
-                    return Ok(());
-                }
-
-                debug!("Added probe to {}", func);
+        for mut probe in self.new_probes(stack.raw(), depth)? {
+            #[cfg(not(test))]
+            if let Err(e) = mgr.attach_generic_probe(&mut probe) {
+                warn!("Could not attach additional probe {probe}: {e}");
+                continue;
             }
 
-            Ok(())
-        })?;
+            debug!("Added probe {probe}");
+        }
 
         if !self.keep_stack {
             kernel.stack_trace = None;
@@ -126,3 +112,80 @@ impl ProbeStack {
         Ok(())
     }
 }
+
+/// Get a kprobe for a function, if it is compatible: having a BTF
+/// representation and operating on a type we can retrieve data from.
+fn compatible_probe(func: &str, known_kernel_types: &HashSet<String>) -> Result<Option<Probe>> {
+    // Filter out functions not having a BTF representation.
+    let types = inspector()?.kernel.btf.resolve_types_by_name(func);
+    if types.is_err()
+        || !types
+            .unwrap()
+            .iter()
+            .any(|(_, t)| matches!(t, Type::Func(_)))
+    {
+        return Ok(None);
+    }
+
+    let symbol = match Symbol::from_name(func) {
+        Ok(symbol) => symbol,
+        _ => return Ok(None),
+    };
+
+    // Filter out symbols not operating on a type we can retrieve data from.
+    if !known_kernel_types
+        .iter()
+        .any(|t| match symbol.parameter_offset(t) {
+            Ok(ret) => ret.is_some(),
+            _ => false,
+        })
+    {
+        return Ok(None);
+    }
+
+    Ok(Probe::kprobe(symbol).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_probes() {
+        let mut probe_stack = ProbeStack::new(
+            false,
+            Some(2),
+            vec!["kprobe:ip_rcv".to_string()],
+            HashSet::from(["struct sk_buff *".to_string()]),
+        );
+        let stack = [
+            "kfree_skb_reason+0x1a",
+            // Not operating on a known type.
+            "schedule+0x2c",
+            // Already probed.
+            "ip_rcv+0x10",
+            // Not a kernel function.
+            "not_a_function+0x4",
+            "tcp_v4_rcv+0x300",
+            "consume_skb+0x10",
+        ]
+        .map(String::from);
+
+        let probes = |probe_stack: &mut ProbeStack| {
+            probe_stack
+                .new_probes(&stack, 2)
+                .unwrap()
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            probes(&mut probe_stack),
+            ["kprobe:kfree_skb_reason", "kprobe:tcp_v4_rcv"]
+        );
+        // Functions already considered are skipped.
+        assert_eq!(probes(&mut probe_stack), ["kprobe:consume_skb"]);
+        assert!(probes(&mut probe_stack).is_empty());
+    }
+}
@@ -20,7 +20,6 @@ use raw_tracepoint_bpf::*;
 #[derive(Default)]
 pub(crate) struct RawTracepointBuilder<'a> {
     hooks: Vec<Hook>,
-    filters: Vec<Filter>,
     links: Vec<libbpf_rs::Link>,
     skel: Option<SkelStorage<RawTracepointSkel<'a>>>,
     map_fds: Vec<(String, RawFd)>,
@@ -35,11 +34,10 @@ impl<'a> ProbeBuilder for RawTracepointBuilder<'a> {
         &mut self,
         map_fds: Vec<(String, RawFd)>,
         hooks: Vec<Hook>,
-        filters: Vec<Filter>,
+        _filters: Vec<Filter>,
     ) -> Result<()> {
         self.map_fds = map_fds;
         self.hooks = hooks;
-        self.filters = filters;
 
         Ok(())
     }
@@ -57,13 +55,8 @@ impl<'a> ProbeBuilder for RawTracepointBuilder<'a> {
         skel.maps.rodata_data.nhooks = self.hooks.len() as u32;
         skel.maps.rodata_data.log_level = log::max_level() as u8;
 
-        self.filters.iter().for_each(|f| {
-            if let Filter::Meta(m) = f {
-                skel.maps.rodata_data.nmeta = m.0.len() as u32
-            }
-        });
-
         reuse_map_fds(skel.open_object_mut(), &self.map_fds)?;
+        set_events_output(skel.open_object_mut())?;
 
         let skel = SkelStorage::load(skel)?;
         let prog = skel
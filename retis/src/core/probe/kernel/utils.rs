@@ -1,8 +1,12 @@
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 
 use crate::core::{
     kernel::symbol::{matching_events_to_symbols, matching_functions_to_symbols, Symbol},
-    probe::Probe,
+    probe::{
+        user::{UprobeProbe, UsdtProbe},
+        Probe,
+    },
+    user::proc::Process,
 };
 
 /// Probe type for probes given through cli arguments.
@@ -10,6 +14,8 @@ pub(crate) enum CliProbeType {
     Kprobe,
     Kretprobe,
     RawTracepoint,
+    Uprobe,
+    Usdt,
 }
 
 impl CliProbeType {
@@ -19,6 +25,8 @@ impl CliProbeType {
             Kprobe => "kprobe",
             Kretprobe => "kretprobe",
             RawTracepoint => "raw_tracepoint",
+            Uprobe => "uprobe",
+            Usdt => "usdt",
         }
     }
 }
@@ -33,6 +41,8 @@ pub(crate) fn parse_cli_probe(input: &str) -> Result<(CliProbeType, &str)> {
             "kprobe" | "k" => (Kprobe, target),
             "kretprobe" | "kr" => (Kretprobe, target),
             "raw_tracepoint" | "tp" => (RawTracepoint, target),
+            "uprobe" | "u" => (Uprobe, target),
+            "usdt" => (Usdt, target),
             // If a single ':' was found in the probe name but we didn't match
             // any known type, defaults to trying using it as a raw tracepoint.
             _ if input.chars().filter(|c| *c == ':').count() == 1 => (RawTracepoint, input),
@@ -55,9 +65,13 @@ where
 
     // Convert the target to a list of matching ones for probe types
     // supporting it.
-    let mut symbols = match r#type {
-        Kprobe | Kretprobe => matching_functions_to_symbols(target)?,
-        RawTracepoint => matching_events_to_symbols(target)?,
+    let (mut symbols, new_probe): (_, fn(Symbol) -> Result<Probe>) = match r#type {
+        Kprobe => (matching_functions_to_symbols(target)?, Probe::kprobe),
+        Kretprobe => (matching_functions_to_symbols(target)?, Probe::kretprobe),
+        RawTracepoint => (matching_events_to_symbols(target)?, Probe::raw_tracepoint),
+        // Userspace probes do not target kernel symbols and are not filtered.
+        Uprobe => return Ok(vec![uprobe_from_cli(target)?]),
+        Usdt => return Ok(vec![usdt_from_cli(target)?]),
     };
 
     let mut probes = Vec::new();
@@ -67,16 +81,45 @@ where
             continue;
         }
 
-        probes.push(match r#type {
-            Kprobe => Probe::kprobe(symbol)?,
-            Kretprobe => Probe::kretprobe(symbol)?,
-            RawTracepoint => Probe::raw_tracepoint(symbol)?,
-        })
+        probes.push(new_probe(symbol)?);
     }
 
     Ok(probes)
 }
 
+/// Parse a uprobe target, in the PATH:FUNCTION format.
+fn uprobe_from_cli(target: &str) -> Result<Probe> {
+    let (path, function) = target
+        .rsplit_once(':')
+        .filter(|(path, function)| !path.is_empty() && !function.is_empty())
+        .ok_or_else(|| anyhow!("Invalid uprobe target {target}, format is PATH:FUNCTION"))?;
+
+    Probe::uprobe(UprobeProbe::new(path, function)?)
+}
+
+/// Parse a USDT target, in the BINARY:PROVIDER::NAME or PID:PROVIDER::NAME
+/// format.
+fn usdt_from_cli(target: &str) -> Result<Probe> {
+    let (binary, usdt) = target
+        .split_once(':')
+        .filter(|(binary, usdt)| !binary.is_empty() && usdt.contains("::"))
+        .ok_or_else(|| {
+            anyhow!("Invalid USDT target {target}, format is (BINARY|PID):PROVIDER::NAME")
+        })?;
+
+    // A pid targets a single process, a binary all the processes running it.
+    let proc = match binary.parse::<i32>() {
+        Ok(pid) => Process::from_pid(pid),
+        Err(_) => Process::all(binary),
+    }
+    .map_err(|e| anyhow!("Cannot inspect {binary}: {e}"))?;
+
+    if !proc.is_usdt(usdt)? {
+        bail!("Cannot find USDT {usdt} in {binary}");
+    }
+    Probe::usdt(UsdtProbe::new(&proc, usdt)?)
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -106,6 +149,31 @@ mod tests {
         assert!(super::probe_from_cli("tp:kfree_*", filter).is_err());
         assert!(super::probe_from_cli("*foo*", filter).is_err());
 
+        // Uprobes. Only the ones of the test binary can be expected to exist.
+        let exe = std::env::current_exe().unwrap();
+        assert!(super::probe_from_cli(&format!("uprobe:{}:main", exe.display()), filter).is_ok());
+        assert!(super::probe_from_cli(&format!("u:{}:main", exe.display()), filter).is_ok());
+        assert!(
+            super::probe_from_cli(&format!("uprobe:{}:foobar", exe.display()), filter).is_err()
+        );
+        assert!(super::probe_from_cli("uprobe:/no/such/binary:main", filter).is_err());
+        assert!(super::probe_from_cli("uprobe:main", filter).is_err());
+
+        // USDT probes, defined in the test binary.
+        ::probe::probe!(retis_test, cli_usdt);
+        let pid = std::process::id();
+        assert!(super::probe_from_cli(
+            &format!("usdt:{}:retis_test::cli_usdt", exe.display()),
+            filter
+        )
+        .is_ok());
+        assert!(super::probe_from_cli(&format!("usdt:{pid}:retis_test::cli_usdt"), filter).is_ok());
+        assert!(super::probe_from_cli(&format!("usdt:{pid}:retis_test::foobar"), filter).is_err());
+        assert!(super::probe_from_cli(&format!("usdt:{pid}:retis_test"), filter).is_err());
+        assert!(
+            super::probe_from_cli("usdt:/no/such/binary:retis_test::cli_usdt", filter).is_err()
+        );
+
         // Invalid probe: wrong TYPE.
         assert!(super::probe_from_cli("kprobe:skb:kfree_skb", filter).is_err());
         assert!(super::probe_from_cli("foo:kfree_skb", filter).is_err());
@@ -4,6 +4,7 @@ use std::{
     cmp,
     collections::{HashMap, HashSet},
     os::fd::{AsFd, AsRawFd, RawFd},
+    time::Duration,
 };
 
 use anyhow::{anyhow, bail, Result};
@@ -14,17 +15,21 @@ use plain::Plain;
 use super::common::{Counters, CountersKey};
 use super::*;
 use super::{
+    attach::AttachProgress,
     builder::ProbeBuilder,
-    kernel::{kprobe, kretprobe, raw_tracepoint},
-    user::usdt,
+    kernel::{fallback::probe_fallback, kprobe, kretprobe, raw_tracepoint},
+    user::{uprobe, usdt},
 };
 
 use super::{common::*, kernel::config::init_config_map};
-use crate::core::{
-    filters::{self, fixup_filter_load_fn, register_filter_handler, Filter},
-    kernel::Symbol,
-    probe::user::UsdtProbe,
-    user::proc::Process,
+use crate::{
+    core::{
+        filters::{self, fixup_filter_load_fn, register_filter_handler, Filter},
+        kernel::Symbol,
+        probe::user::{UprobeProbe, UsdtProbe},
+        user::proc::Process,
+    },
+    events,
 };
 
 // Keep in sync with their BPF counterparts in bpf/include/common.h
@@ -126,17 +131,9 @@ impl ProbeManager {
                     filters::register_filter(*magic, filter)?;
                 }
                 #[allow(unused_variables)]
-                Filter::Meta(ops) =>
-                {
+                Filter::Meta(filter) => {
                     #[cfg(not(test))]
-                    for (p, op) in ops.0.iter().enumerate() {
-                        let pos = u32::try_from(p)?.to_ne_bytes();
-                        builder.meta_map.update(
-                            &pos,
-                            unsafe { plain::as_bytes(op) },
-                            libbpf_rs::MapFlags::ANY,
-                        )?;
-                    }
+                    filters::meta::filter::set_meta_filter(&builder.meta_map, Some(filter))?;
                 }
             }
         }
@@ -164,6 +161,8 @@ impl ProbeManager {
             config_map: builder.config_map,
             #[cfg(not(test))]
             counters_map: builder.counters_map,
+            #[cfg(not(test))]
+            meta_map: builder.meta_map,
             map_fds: builder.maps.into_iter().collect(),
             hooks: builder.generic_hooks.into_iter().collect(),
             generic_builders: HashMap::new(),
@@ -173,17 +172,13 @@ impl ProbeManager {
         };
 
         // Install probes.
+        let progress = AttachProgress::new(builder.probes.len(), builder.attach_timeout);
         #[cfg(not(test))]
-        builder
-            .probes
-            .values_mut()
-            .try_for_each(|p| match p.is_generic() {
-                true => runtime.attach_generic_probe(p),
-                false => runtime.attach_targeted_probe(p),
-            })?;
+        runtime.attach_probes(builder.probes.values_mut().collect(), &progress)?;
+        progress.report()?;
 
         // All probes loaded, issue an info log.
-        info!("{} probe(s) loaded", builder.probes.len());
+        info!("{} probe(s) loaded", progress.attached());
 
         #[cfg(not(test))]
         {
@@ -213,6 +208,10 @@ pub(crate) struct ProbeBuilderManager {
     global_probes_options: Vec<ProbeOption>,
     /// HashMap of map names and file descriptors, to be reused in all hooks.
     maps: HashMap<String, RawFd>,
+    /// Time after which probes not yet attached are skipped.
+    attach_timeout: Option<Duration>,
+    /// Collector registering hooks, see `set_collector`.
+    collector: Option<String>,
     /// Common configuration for all probes.
     #[cfg(not(test))]
     global_config_map: libbpf_rs::MapHandle,
@@ -238,6 +237,8 @@ impl ProbeBuilderManager {
             filters: Vec::new(),
             global_probes_options: Vec::new(),
             maps: HashMap::new(),
+            attach_timeout: None,
+            collector: None,
             #[cfg(not(test))]
             global_config_map: init_global_config_map()?,
             #[cfg(not(test))]
@@ -289,6 +290,24 @@ impl ProbeBuilderManager {
         Ok(())
     }
 
+    /// Set the time after which probes not yet attached are skipped, instead
+    /// of waiting for all of them to be attached.
+    pub(crate) fn set_attach_timeout(&mut self, timeout: Duration) {
+        self.attach_timeout = Some(timeout);
+    }
+
+    /// Set the collector registering the next hooks, if any. Hooks are tagged
+    /// with it so probes can be described later on.
+    pub(crate) fn set_collector(&mut self, collector: Option<&str>) {
+        self.collector = collector.map(String::from);
+    }
+
+    fn tag_hook(&self, hook: &mut Hook) {
+        if hook.collector.is_none() {
+            hook.collector = self.collector.clone();
+        }
+    }
+
     /// Request to attach a dynamic probe to `Probe`.
     ///
     /// ```
@@ -299,6 +318,11 @@ impl ProbeBuilderManager {
     /// mgr.register_probe(Probe::raw_tracepoint(symbol).unwrap()).unwrap();
     /// ```
     pub(crate) fn register_probe(&mut self, mut probe: Probe) -> Result<()> {
+        // Degrade the probe if it can't be used on the running kernel.
+        probe_fallback(&mut probe)?;
+
+        probe.hooks.iter_mut().for_each(|h| self.tag_hook(h));
+
         let key = probe.key();
 
         let len = probe.hooks_len();
@@ -323,6 +347,11 @@ impl ProbeBuilderManager {
         Ok(())
     }
 
+    /// Get the probes registered so far.
+    pub(crate) fn probes(&self) -> impl Iterator<Item = &Probe> {
+        self.probes.values()
+    }
+
     /// Request to reuse a map fd. Useful for sharing maps across probes, for
     /// configuration, event reporting, or other use cases.
     ///
@@ -374,7 +403,7 @@ impl ProbeBuilderManager {
     ///
     /// mgr.register_kernel_hook(Hook::from(hook::DATA))?;
     /// ```
-    pub(crate) fn register_kernel_hook(&mut self, hook: Hook) -> Result<()> {
+    pub(crate) fn register_kernel_hook(&mut self, mut hook: Hook) -> Result<()> {
         let mut max: usize = 0;
         self.probes.iter().for_each(|(_, p)| {
             max = cmp::max(max, p.hooks_len());
@@ -384,10 +413,28 @@ impl ProbeBuilderManager {
             bail!("Hook list is already full");
         }
 
+        self.tag_hook(&mut hook);
         self.generic_hooks.push(hook);
         Ok(())
     }
 
+    /// Get the collectors having a hook on a probe, both targeted and generic
+    /// ones.
+    pub(crate) fn probe_collectors(&self, probe: &Probe) -> Vec<String> {
+        let mut hooks = probe.hooks.iter().collect::<Vec<_>>();
+        if probe.supports_generic_hooks() {
+            hooks.extend(self.generic_hooks.iter());
+        }
+
+        let mut collectors = hooks
+            .iter()
+            .filter_map(|h| h.collector.clone())
+            .collect::<Vec<_>>();
+        collectors.sort();
+        collectors.dedup();
+        collectors
+    }
+
     fn check_probe_max(&self) -> Result<()> {
         if self.probes.len() >= PROBE_MAX {
             bail!(
@@ -407,6 +454,9 @@ pub(crate) struct ProbeRuntimeManager {
     /// Global per-probe map used to report counters.
     #[cfg(not(test))]
     counters_map: libbpf_rs::MapHandle,
+    /// Global map used to pass meta filter actions.
+    #[cfg(not(test))]
+    meta_map: libbpf_rs::MapHandle,
     generic_builders: HashMap<usize, Box<dyn ProbeBuilder>>,
     targeted_builders: Vec<Box<dyn ProbeBuilder>>,
     map_fds: Vec<(String, RawFd)>,
@@ -416,10 +466,10 @@ pub(crate) struct ProbeRuntimeManager {
 }
 
 impl ProbeRuntimeManager {
-    /// Internal function installing a probe using a type-specific builder.
+    /// Internal function loading a probe configuration and counters, before
+    /// it is attached using a type-specific builder.
     #[cfg(not(test))]
-    fn attach_probe(
-        builder: &mut Box<dyn ProbeBuilder>,
+    fn prepare_probe(
         config_map: &mut libbpf_rs::MapHandle,
         counters_map: &mut libbpf_rs::MapHandle,
         probe: &mut Probe,
@@ -441,6 +491,9 @@ impl ProbeRuntimeManager {
             ProbeType::Usdt(ref mut up) => {
                 (counters_key, counters) = up.gen_counters()?;
             }
+            // Uprobes are hit by any process running the binary, their
+            // counters can't be keyed in advance.
+            ProbeType::Uprobe(_) => return Ok(()),
         }
 
         counters_map.update(
@@ -449,9 +502,7 @@ impl ProbeRuntimeManager {
             libbpf_rs::MapFlags::ANY,
         )?;
 
-        // Finally attach a probe to the target.
-        debug!("Attaching probe to {}", probe);
-        builder.attach(probe)
+        Ok(())
     }
 
     /// Generate a new builder for the given probe.
@@ -461,6 +512,7 @@ impl ProbeRuntimeManager {
             ProbeType::Kretprobe(_) => Box::new(kretprobe::KretprobeBuilder::new()),
             ProbeType::RawTracepoint(_) => Box::new(raw_tracepoint::RawTracepointBuilder::new()),
             ProbeType::Usdt(_) => Box::new(usdt::UsdtBuilder::new()),
+            ProbeType::Uprobe(_) => Box::new(uprobe::UprobeBuilder::new()),
         }
     }
 
@@ -476,6 +528,7 @@ impl ProbeRuntimeManager {
             Probe::kretprobe(Symbol::from_name_no_inspect("dummy"))?,
             Probe::raw_tracepoint(Symbol::from_name_no_inspect("dummy:dummy"))?,
             Probe::usdt(UsdtProbe::dummy())?,
+            Probe::uprobe(UprobeProbe::dummy())?,
         ];
 
         let mut builders = HashMap::new();
@@ -500,13 +553,63 @@ impl ProbeRuntimeManager {
         Ok(())
     }
 
-    /// Attach a new targeted probe.
+    /// Attach a set of probes, accounting for them in the given progress.
+    /// Generic probes sharing a builder are attached together, which allows
+    /// builders to attach them in parallel.
     #[cfg(not(test))]
-    fn attach_targeted_probe(&mut self, probe: &mut Probe) -> Result<()> {
-        if !self.probes.insert(probe.key()) {
-            bail!("A probe on {probe} is already attached");
+    fn attach_probes(&mut self, probes: Vec<&mut Probe>, progress: &AttachProgress) -> Result<()> {
+        let mut generic: HashMap<usize, Vec<&mut Probe>> = HashMap::new();
+
+        for probe in probes.into_iter() {
+            if !self.probes.insert(probe.key()) {
+                bail!("A probe on {probe} is already attached");
+            }
+
+            match probe.is_generic() {
+                true => generic.entry(probe.type_key()).or_default().push(probe),
+                false => {
+                    let desc = probe.to_string();
+                    if progress
+                        .attach(&desc, || self.attach_targeted_probe(probe))
+                        .is_none()
+                    {
+                        self.probes.remove(&probe.key());
+                    }
+                }
+            }
         }
 
+        if generic.is_empty() {
+            return Ok(());
+        }
+        self.gen_generic_builders()?;
+
+        for (type_key, mut probes) in generic.into_iter() {
+            probes.iter_mut().try_for_each(|p| {
+                Self::prepare_probe(&mut self.config_map, &mut self.counters_map, p)
+            })?;
+
+            let probes = probes.iter().map(|p| &**p).collect::<Vec<_>>();
+            debug!("Attaching {} generic probe(s)", probes.len());
+
+            // Unwrap as generic builders are available for all probe types.
+            let builder = self.generic_builders.get_mut(&type_key).unwrap();
+            builder
+                .attach_many(&probes, progress)?
+                .iter()
+                .zip(probes.iter())
+                .filter(|(attached, _)| !**attached)
+                .for_each(|(_, p)| {
+                    self.probes.remove(&p.key());
+                });
+        }
+
+        Ok(())
+    }
+
+    /// Attach a new targeted probe.
+    #[cfg(not(test))]
+    fn attach_targeted_probe(&mut self, probe: &mut Probe) -> Result<()> {
         let mut builder = Self::gen_builder(probe);
 
         let mut hooks = probe.hooks.clone();
@@ -516,16 +619,29 @@ impl ProbeRuntimeManager {
 
         builder.init(self.map_fds.clone(), hooks, self.filters.clone())?;
 
-        Self::attach_probe(
-            &mut builder,
-            &mut self.config_map,
-            &mut self.counters_map,
-            probe,
-        )?;
+        Self::prepare_probe(&mut self.config_map, &mut self.counters_map, probe)?;
+        debug!("Attaching probe to {}", probe);
+        builder.attach(probe)?;
+
         self.targeted_builders.push(builder);
         Ok(())
     }
 
+    /// Attach a new targeted probe while collecting, e.g. to follow a process
+    /// which restarted. A probe with the same key might already be attached,
+    /// targeting the previous process.
+    #[cfg(not(test))]
+    pub(crate) fn attach_probe(&mut self, probe: &mut Probe) -> Result<()> {
+        self.attach_targeted_probe(probe)?;
+        self.probes.insert(probe.key());
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub(crate) fn attach_probe(&mut self, _: &mut Probe) -> Result<()> {
+        bail!("No probe can be attached while testing")
+    }
+
     /// Attach a new generic probe.
     #[cfg(not(test))]
     pub(crate) fn attach_generic_probe(&mut self, probe: &mut Probe) -> Result<()> {
@@ -535,8 +651,10 @@ impl ProbeRuntimeManager {
 
         self.gen_generic_builders()?;
 
+        Self::prepare_probe(&mut self.config_map, &mut self.counters_map, probe)?;
+        debug!("Attaching probe to {}", probe);
         let builder = self.generic_builders.get_mut(&probe.r#type_key()).unwrap();
-        Self::attach_probe(builder, &mut self.config_map, &mut self.counters_map, probe)
+        builder.attach(probe)
     }
 
     /// Get the list of all currently attached probes.
@@ -544,6 +662,18 @@ impl ProbeRuntimeManager {
         self.probes.clone().into_iter().collect()
     }
 
+    /// Get a new handle to the meta filter map, so the meta filter can be
+    /// changed while probes are running.
+    #[cfg(not(test))]
+    pub(crate) fn meta_map(&self) -> Result<libbpf_rs::MapHandle> {
+        Ok(libbpf_rs::MapHandle::try_from(&self.meta_map)?)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn meta_map(&self) -> Result<libbpf_rs::MapHandle> {
+        bail!("No meta filter map while testing")
+    }
+
     /// Detach all probes.
     pub(crate) fn detach(&mut self) -> Result<()> {
         self.generic_builders
@@ -555,12 +685,79 @@ impl ProbeRuntimeManager {
     }
 
     #[cfg(test)]
-    pub(crate) fn report_counters(&self) -> Result<()> {
+    pub(crate) fn report_counters(&self) -> Result<u64> {
+        Ok(0)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn report_overhead(&self) -> Result<()> {
         Ok(())
     }
 
+    /// Report the overhead of probes, as measured in their BPF counterpart.
+    /// Probes are sorted by their average overhead.
     #[cfg(not(test))]
-    pub(crate) fn report_counters(&self) -> Result<()> {
+    pub(crate) fn report_overhead(&self) -> Result<()> {
+        let mut counters_key = CountersKey::default();
+        let mut counters = Counters::default();
+        let mut overhead = Vec::new();
+
+        for k in self.counters_map.keys() {
+            counters_key
+                .copy_from_bytes(&k)
+                .or_else(|_| bail!("Cannot retrieve the counters map key"))?;
+            // Only kernel probes are measured.
+            if counters_key.pid != 0 {
+                continue;
+            }
+
+            if let Some(counters_val) = self.counters_map.lookup(&k, libbpf_rs::MapFlags::ANY)? {
+                counters
+                    .copy_from_bytes(&counters_val)
+                    .or_else(|_| bail!("Cannot retrieve the counters map value"))?;
+                if counters.overhead_samples == 0 {
+                    continue;
+                }
+
+                overhead.push((
+                    Symbol::from_addr(counters_key.sym_addr)?.to_string(),
+                    counters.overhead_samples,
+                    events::Duration::from_nanos(
+                        counters.overhead_total_ns / counters.overhead_samples,
+                    ),
+                    events::Duration::from_nanos(counters.overhead_max_ns),
+                ));
+            }
+        }
+
+        if overhead.is_empty() {
+            info!("No probe overhead sample was collected");
+            return Ok(());
+        }
+
+        overhead.sort_by_key(|(_, _, avg, _)| cmp::Reverse(*avg));
+        let width = overhead
+            .iter()
+            .map(|(name, ..)| name.len())
+            .max()
+            .unwrap_or_default()
+            .max("probe".len());
+
+        info!("Probes overhead (sampled):");
+        info!(
+            "  {:width$}  {:>10}  {:>10}  {:>10}",
+            "probe", "samples", "avg", "max"
+        );
+        overhead.iter().for_each(|(name, samples, avg, max)| {
+            info!("  {name:width$}  {samples:>10}  {avg:>10}  {max:>10}")
+        });
+
+        Ok(())
+    }
+
+    /// Report the events lost by the probes and return their total number.
+    #[cfg(not(test))]
+    pub(crate) fn report_counters(&self) -> Result<u64> {
         let mut counters_key = CountersKey::default();
         let mut counters = Counters::default();
         let mut total_lost: u64 = 0;
@@ -607,7 +804,7 @@ impl ProbeRuntimeManager {
             warn!("total events lost: {total_lost}");
         }
 
-        Ok(())
+        Ok(total_lost)
     }
 }
 
@@ -684,6 +881,24 @@ mod tests {
         assert!(mgr.register_probe(probe).is_err());
     }
 
+    #[test]
+    fn probe_collectors() {
+        let mut mgr = ProbeBuilderManager::new().unwrap();
+
+        mgr.set_collector(Some("skb"));
+        assert!(mgr.register_kernel_hook(Hook::from(HOOK)).is_ok());
+        mgr.set_collector(Some("ovs"));
+        let mut probe = kprobe!("kfree_skb_reason");
+        probe.add_hook(Hook::from(HOOK)).unwrap();
+        assert!(mgr.register_probe(probe).is_ok());
+        mgr.set_collector(None);
+        assert!(mgr.register_probe(kprobe!("consume_skb")).is_ok());
+
+        let collectors = |key: &str| mgr.probe_collectors(mgr.probes.get(key).unwrap());
+        assert_eq!(collectors("kprobe:kfree_skb_reason"), vec!["ovs", "skb"]);
+        assert_eq!(collectors("kprobe:consume_skb"), vec!["skb"]);
+    }
+
     #[test]
     fn reuse_map() {
         let mut mgr = ProbeBuilderManager::new().unwrap();
@@ -2,9 +2,11 @@
 //!
 //! Module providing a public API to attach to various types of probes.
 
+pub(crate) mod attach;
 mod builder;
 
 pub(crate) mod common;
+pub(crate) mod inventory;
 pub(crate) mod kernel;
 
 pub(crate) mod manager;
@@ -7,7 +7,7 @@ use std::{
 use anyhow::{bail, Result};
 
 use super::kernel::KernelProbe;
-use super::user::UsdtProbe;
+use super::user::{UprobeProbe, UsdtProbe};
 use crate::core::kernel;
 
 /// Probe types supported by this program. This is the main object given to
@@ -21,6 +21,7 @@ pub(crate) enum ProbeType {
     RawTracepoint(KernelProbe),
     #[allow(dead_code)]
     Usdt(UsdtProbe),
+    Uprobe(UprobeProbe),
 }
 
 /// Probe options, to toggle opt-in/out features.
@@ -28,6 +29,7 @@ pub(crate) enum ProbeType {
 pub(crate) enum ProbeOption {
     StackTrace,
     NoGenericHook,
+    MeasureOverhead,
 }
 
 /// Represents a probe we can install in a target (kernel, user space program,
@@ -82,6 +84,12 @@ impl Probe {
         Ok(Probe::from(r#type))
     }
 
+    /// Create a new uprobe.
+    pub(crate) fn uprobe(uprobe: UprobeProbe) -> Result<Probe> {
+        let r#type = ProbeType::Uprobe(uprobe);
+        Ok(Probe::from(r#type))
+    }
+
     /// Retrieve a reference to the underlying ProbeType.
     #[allow(dead_code)]
     pub(crate) fn r#type(&self) -> &ProbeType {
@@ -107,15 +115,20 @@ impl Probe {
             ProbeType::Kretprobe(_) => 1,
             ProbeType::RawTracepoint(_) => 2,
             ProbeType::Usdt(_) => 3,
+            ProbeType::Uprobe(_) => 4,
         }
     }
 
     /// Append a new targeted hook to the probe.
     pub(crate) fn add_hook(&mut self, hook: Hook) -> Result<()> {
-        if let ProbeType::Usdt(_) = self.r#type() {
-            if !self.hooks.is_empty() {
-                bail!("USDT probes only support a single hook");
+        match self.r#type() {
+            ProbeType::Usdt(_) if !self.hooks.is_empty() => {
+                bail!("USDT probes only support a single hook")
+            }
+            ProbeType::Uprobe(_) if !self.hooks.is_empty() => {
+                bail!("Uprobes only support a single hook")
             }
+            _ => (),
         }
 
         self.hooks.push(hook);
@@ -135,7 +148,7 @@ impl Probe {
 
     /// Are generic hooks supported by the of probe?
     pub(crate) fn supports_generic_hooks(&self) -> bool {
-        !matches!(self.r#type(), ProbeType::Usdt(_))
+        !matches!(self.r#type(), ProbeType::Usdt(_) | ProbeType::Uprobe(_))
             && !self.options.contains(&ProbeOption::NoGenericHook)
     }
 
@@ -166,12 +179,14 @@ impl Probe {
         }
 
         // Merge options.
-        // - ProbeOption::StackTrace: if any of the probes has it, it should be
-        //   set in the resulting probe.
+        // - ProbeOption::StackTrace & ProbeOption::MeasureOverhead: if any of
+        //   the probes has it, it should be set in the resulting probe.
         // - ProbeOption::NoGenericHook: has to be set in both probes to be set in the
         //   resulting probe.
-        if let Some(opt) = other.options.take(&ProbeOption::StackTrace) {
-            self.options.insert(opt);
+        for opt in [ProbeOption::StackTrace, ProbeOption::MeasureOverhead] {
+            if let Some(opt) = other.options.take(&opt) {
+                self.options.insert(opt);
+            }
         }
         if !other.options.contains(&ProbeOption::NoGenericHook) {
             self.options.remove(&ProbeOption::NoGenericHook);
@@ -191,6 +206,7 @@ impl fmt::Display for Probe {
             ProbeType::Kretprobe(symbol) => write!(f, "kretprobe:{symbol}"),
             ProbeType::RawTracepoint(symbol) => write!(f, "tp:{symbol}"),
             ProbeType::Usdt(symbol) => write!(f, "usdt {symbol}"),
+            ProbeType::Uprobe(symbol) => write!(f, "uprobe:{symbol}"),
         }
     }
 }
@@ -202,6 +218,8 @@ pub(crate) struct Hook {
     pub(super) bpf_prog: &'static [u8],
     /// HashMap of maps names and their fd, for reuse by the hook.
     pub(super) maps: HashMap<String, RawFd>,
+    /// Name of the collector which registered the hook, if any.
+    pub(super) collector: Option<String>,
 }
 
 impl Hook {
@@ -210,6 +228,7 @@ impl Hook {
         Hook {
             bpf_prog,
             maps: HashMap::new(),
+            collector: None,
         }
     }
 
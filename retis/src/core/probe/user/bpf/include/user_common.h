@@ -10,6 +10,13 @@
 
 enum userspace_event_type {
 	USDT = 1,
+	UPROBE,
+};
+
+/* Userspace sections of the event data. */
+enum userspace_sections {
+	USER_SECTION_EVENT = 1,
+	USER_SECTION_STACK,
 };
 
 /* Userspace section of the event data. */
@@ -17,8 +24,19 @@ struct user_event {
 	u64 symbol;
 	u64 pid;
 	u8  event_type;
+	/* Start time of the process, to detect pid reuse. */
+	u64 start_time;
 } __packed;
 
+/* Maximum number of frames in userspace stack traces. */
+#define USER_STACK_MAX	32
+
+/* Userspace stack trace of the event, from the probed function. */
+struct user_stack_event {
+	u64 nr;
+	u64 ips[USER_STACK_MAX];
+};
+
 /* Userspace context */
 struct user_ctx {
 	long args[BPF_USDT_MAX_ARG_CNT];
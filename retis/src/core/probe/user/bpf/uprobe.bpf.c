@@ -0,0 +1,109 @@
+#include <vmlinux.h>
+#include <bpf/bpf_core_read.h>
+#include <bpf/bpf_helpers.h>
+#include <bpf/bpf_tracing.h>
+
+#include <user_common.h>
+
+/* Hook placeholder */
+__attribute__ ((noinline))
+int hook0(struct user_ctx *ctx, struct retis_raw_event *event) {
+	volatile int ret = 0;
+	if (!ctx || !event)
+		return 0;
+	return ret;
+}
+
+static __always_inline void get_args(struct user_ctx *uctx,
+				     struct pt_regs *ctx)
+{
+	uctx->args[0] = PT_REGS_PARM1(ctx);
+	uctx->args[1] = PT_REGS_PARM2(ctx);
+	uctx->args[2] = PT_REGS_PARM3(ctx);
+	uctx->args[3] = PT_REGS_PARM4(ctx);
+	uctx->args[4] = PT_REGS_PARM5(ctx);
+	uctx->num = 5;
+}
+
+SEC("uprobe")
+int probe_uprobe(struct pt_regs *ctx)
+{
+	u64 pid = bpf_get_current_pid_tgid();
+	u64 sym_addr = PT_REGS_IP(ctx);
+	struct retis_raw_event *event;
+	struct common_task_event *ti;
+	struct user_stack_event *st;
+	static bool enabled = false;
+	struct user_ctx uctx = {};
+	struct common_event *e;
+	struct task_struct *task;
+	struct user_event *u;
+	long size;
+
+	/* Check if the collection is enabled, otherwise bail out. Once we have
+	 * a positive result, cache it.
+	 */
+	if (unlikely(!enabled)) {
+		enabled = collection_enabled();
+		if (!enabled)
+			return 0;
+	}
+
+	get_args(&uctx, ctx);
+
+	event = get_event();
+	if (!event) {
+		next_event_seq();
+		err_report(sym_addr, pid >> 32);
+		return 0;
+	}
+
+	e = get_event_section(event, COMMON, COMMON_SECTION_CORE, sizeof(*e));
+	if (!e)
+		goto discard_event;
+
+	uctx.timestamp = bpf_ktime_get_ns();
+	e->timestamp = uctx.timestamp;
+	e->smp_id = bpf_get_smp_processor_id();
+
+	ti = get_event_zsection(event, COMMON, COMMON_SECTION_TASK, sizeof(*ti));
+	if (!ti)
+		goto discard_event;
+
+	ti->pid = pid;
+	bpf_get_current_comm(ti->comm, sizeof(ti->comm));
+
+	u = get_event_section(event, USERSPACE, USER_SECTION_EVENT, sizeof(*u));
+	if (!u)
+		goto discard_event;
+
+	u->symbol = sym_addr;
+	u->pid = pid;
+	u->event_type = UPROBE;
+
+	task = (struct task_struct *)bpf_get_current_task();
+	u->start_time = BPF_CORE_READ(task, group_leader, start_time);
+
+	st = get_event_section(event, USERSPACE, USER_SECTION_STACK, sizeof(*st));
+	if (!st)
+		goto discard_event;
+
+	size = bpf_get_stack(ctx, st->ips, sizeof(st->ips), BPF_F_USER_STACK);
+	st->nr = size > 0 ? size / sizeof(st->ips[0]) : 0;
+
+	/* Uprobes only support a single hook. Unlike USDT probes, the event
+	 * is reported even if the hook did not add data to it.
+	 */
+	hook0(&uctx, event);
+
+	e->seq = next_event_seq();
+	send_event(ctx, event);
+	return 0;
+
+discard_event:
+	discard_event(event);
+
+	return 0;
+}
+
+char __license[] SEC("license") = "GPL";
@@ -1,9 +1,15 @@
 #include <vmlinux.h>
+#include <bpf/bpf_core_read.h>
 #include <bpf/bpf_helpers.h>
 #include <bpf/usdt.bpf.h>
 
 #include <user_common.h>
 
+/* Set when no hook is attached to the probe (e.g. probes given on the command
+ * line), in which case all events are reported.
+ */
+const volatile bool report_all = false;
+
 /* Hook placeholder */
 __attribute__ ((noinline))
 int hook0(struct user_ctx *ctx, struct retis_raw_event *event) {
@@ -58,6 +64,7 @@ int probe_usdt(struct pt_regs *ctx)
 	volatile u16 pass_threshold;
 	struct user_ctx uctx = {};
 	struct common_event *e;
+	struct task_struct *task;
 	struct user_event *u;
 
 	/* Check if the collection is enabled, otherwise bail out. Once we have
@@ -74,6 +81,7 @@ int probe_usdt(struct pt_regs *ctx)
 
 	event = get_event();
 	if (!event) {
+		next_event_seq();
 		err_report(sym_addr, pid >> 32);
 		return 0;
 	}
@@ -93,7 +101,7 @@ int probe_usdt(struct pt_regs *ctx)
 	ti->pid = pid;
 	bpf_get_current_comm(ti->comm, sizeof(ti->comm));
 
-	u = get_event_section(event, USERSPACE, 1, sizeof(*u));
+	u = get_event_section(event, USERSPACE, USER_SECTION_EVENT, sizeof(*u));
 	if (!u)
 		goto discard_event;
 
@@ -101,14 +109,18 @@ int probe_usdt(struct pt_regs *ctx)
 	u->pid = pid;
 	u->event_type = USDT;
 
+	task = (struct task_struct *)bpf_get_current_task();
+	u->start_time = BPF_CORE_READ(task, group_leader, start_time);
+
 	pass_threshold = get_event_size(event);
 	barrier_var(pass_threshold);
 
 	/* UST only supports a single hook. */
 	hook0(&uctx, event);
 
-	if (get_event_size(event) > pass_threshold) {
-		send_event(event);
+	if (report_all || get_event_size(event) > pass_threshold) {
+		e->seq = next_event_seq();
+		send_event(ctx, event);
 		return 0;
 	}
 
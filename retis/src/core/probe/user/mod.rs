@@ -9,4 +9,5 @@ pub(crate) mod user;
 #[allow(unused_imports)]
 pub(crate) use user::*;
 
+pub(crate) mod uprobe;
 pub(crate) mod usdt;
@@ -0,0 +1,88 @@
+use std::os::fd::{AsFd, AsRawFd, RawFd};
+
+use anyhow::{anyhow, bail, Result};
+use libbpf_rs::{
+    skel::{OpenSkel, Skel},
+    UprobeOpts,
+};
+
+use crate::core::{
+    filters::Filter,
+    probe::{builder::*, Hook, Probe, ProbeType},
+    workaround::*,
+};
+
+mod uprobe_bpf {
+    include!("bpf/.out/uprobe.skel.rs");
+}
+use uprobe_bpf::*;
+
+#[derive(Default)]
+pub(crate) struct UprobeBuilder<'a> {
+    links: Vec<libbpf_rs::Link>,
+    skel: Option<SkelStorage<UprobeSkel<'a>>>,
+    map_fds: Vec<(String, RawFd)>,
+    hooks: Vec<Hook>,
+}
+
+impl<'a> ProbeBuilder for UprobeBuilder<'a> {
+    fn new() -> UprobeBuilder<'a> {
+        UprobeBuilder::default()
+    }
+
+    fn init(
+        &mut self,
+        map_fds: Vec<(String, RawFd)>,
+        hooks: Vec<Hook>,
+        _filters: Vec<Filter>,
+    ) -> Result<()> {
+        self.map_fds = map_fds;
+        if hooks.len() > 1 {
+            bail!("Uprobes only support a single hook");
+        }
+        self.hooks = hooks;
+        Ok(())
+    }
+
+    fn attach(&mut self, probe: &Probe) -> Result<()> {
+        let probe = match probe.r#type() {
+            ProbeType::Uprobe(uprobe) => uprobe,
+            _ => bail!("Wrong probe type"),
+        };
+
+        let mut skel = OpenSkelStorage::new::<UprobeSkelBuilder>()?;
+        skel.maps.rodata_data.log_level = log::max_level() as u8;
+
+        reuse_map_fds(skel.open_object_mut(), &self.map_fds)?;
+        set_events_output(skel.open_object_mut())?;
+
+        let skel = SkelStorage::load(skel)?;
+        let prog = skel
+            .object()
+            .progs_mut()
+            .find(|p| p.name() == "probe_uprobe")
+            .ok_or_else(|| anyhow!("Couldn't get program"))?;
+        let mut links = replace_hooks(prog.as_fd().as_raw_fd(), &self.hooks)?;
+        self.links.append(&mut links);
+
+        // Probe all the processes running the binary, libbpf resolves the
+        // function offset.
+        self.links.push(prog.attach_uprobe_with_opts(
+            -1,
+            &probe.path,
+            0,
+            UprobeOpts {
+                func_name: probe.function.clone(),
+                ..Default::default()
+            },
+        )?);
+        self.skel = Some(skel);
+
+        Ok(())
+    }
+
+    fn detach(&mut self) -> Result<()> {
+        self.links.drain(..);
+        Ok(())
+    }
+}
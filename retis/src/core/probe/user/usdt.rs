@@ -49,8 +49,10 @@ impl<'a> ProbeBuilder for UsdtBuilder<'a> {
 
         let mut skel = OpenSkelStorage::new::<UsdtSkelBuilder>()?;
         skel.maps.rodata_data.log_level = log::max_level() as u8;
+        skel.maps.rodata_data.report_all = self.hooks.is_empty();
 
         reuse_map_fds(skel.open_object_mut(), &self.map_fds)?;
+        set_events_output(skel.open_object_mut())?;
 
         let skel = SkelStorage::load(skel)?;
         let prog = skel
@@ -1,19 +1,31 @@
 #![allow(dead_code)] // FIXME
 
-use std::{any::Any, collections::HashMap, fmt, path::PathBuf};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt,
+    path::PathBuf,
+};
 
 use anyhow::{anyhow, bail, Result};
+use log::debug;
 
 use crate::{
     core::{
         events::{BpfRawSection, EventSectionFactory, FactoryId, RawEventSectionFactory},
         probe::common::{Counters, CountersKey},
-        user::proc::Process,
+        user::proc::{Binary, Process},
     },
     event_section_factory,
     events::*,
 };
 
+// Keep in sync with their BPF counterparts in bpf/include/user_common.h.
+const USER_TYPE_USDT: u8 = 1;
+const USER_TYPE_UPROBE: u8 = 2;
+const USER_SECTION_EVENT: u8 = 1;
+const USER_SECTION_STACK: u8 = 2;
+const USER_STACK_MAX: usize = 32;
+
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct UsdtProbe {
     /// The provider name.
@@ -79,24 +91,83 @@ impl fmt::Display for UsdtProbe {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct UprobeProbe {
+    /// The target's path.
+    pub path: PathBuf,
+    /// The probed function.
+    pub function: String,
+}
+
+impl UprobeProbe {
+    /// Return a new UprobeProbe, on a function of the binary found at `path`.
+    /// All processes running the binary are probed.
+    pub(crate) fn new(path: &str, function: &str) -> Result<Self> {
+        let path = PathBuf::from(path);
+        if !path.exists() {
+            bail!("Cannot find binary {}", path.display());
+        }
+
+        if Binary::new(path.clone())?
+            .function_addr(function)?
+            .is_none()
+        {
+            bail!("Cannot find function {function} in {}", path.display());
+        }
+
+        Ok(UprobeProbe {
+            path,
+            function: function.to_string(),
+        })
+    }
+
+    /// Creates a dummy UprobeProbe. Using it like a valid one is buggy.
+    pub(crate) fn dummy() -> Self {
+        Self {
+            path: PathBuf::new(),
+            function: "".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for UprobeProbe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.path.display(), self.function)
+    }
+}
+
+/// Format a function and an offset in it, as in stack traces.
+fn fmt_function(func: Option<(&str, u64)>, addr: u64) -> String {
+    match func {
+        Some((name, 0)) => name.to_string(),
+        Some((name, offset)) => format!("{name}+{offset:#x}"),
+        None => format!("{addr:#x}"),
+    }
+}
+
 #[event_section_factory(FactoryId::Userspace)]
 #[derive(Default)]
 pub(crate) struct UserEventFactory {
-    cache: HashMap<String, Box<dyn Any>>,
+    /// Processes information, indexed by pid, along with their start time.
+    procs: HashMap<i32, (u64, Process)>,
 }
 
 impl RawEventSectionFactory for UserEventFactory {
-    fn create(&mut self, mut raw_sections: Vec<BpfRawSection>) -> Result<Box<dyn EventSection>> {
-        if raw_sections.len() != 1 {
-            bail!("User event from BPF must be a single section")
+    fn create(&mut self, raw_sections: Vec<BpfRawSection>) -> Result<Box<dyn EventSection>> {
+        let mut raw = None;
+        let mut stack = None;
+        for section in raw_sections.into_iter() {
+            match section.header.data_type {
+                USER_SECTION_EVENT => raw = Some(section),
+                USER_SECTION_STACK => stack = Some(section),
+                x => bail!("Unknown user section data type ({x})"),
+            }
         }
+        let raw = raw.ok_or_else(|| anyhow!("User event from BPF has no event section"))?;
 
-        // Unwrap as we just checked the vector contains 1 element.
-        let raw = raw_sections.pop().unwrap();
-
-        if raw.data.len() != 17 {
+        if raw.data.len() != 25 {
             bail!(
-                "Section data is not the expected size {} != 17",
+                "Section data is not the expected size {} != 25",
                 raw.data.len()
             );
         }
@@ -104,35 +175,61 @@ impl RawEventSectionFactory for UserEventFactory {
         let symbol = u64::from_ne_bytes(raw.data[0..8].try_into()?);
         let pid_tid = u64::from_ne_bytes(raw.data[8..16].try_into()?);
         let r#type = u8::from_ne_bytes(raw.data[16..17].try_into()?);
+        let start_time = u64::from_ne_bytes(raw.data[17..25].try_into()?);
 
         // Split pid and tid
         let pid = (pid_tid >> 32) as i32;
         let tid = (pid_tid & 0xFFFFFFFF) as i32;
 
-        let pid_key = format!("user_proc_{pid}");
-        // Try to obtain the Process object from the Context.
-        let proc = match self.cache.get(&pid_key) {
-            Some(val) => val.downcast_ref::<Process>(),
-            None => {
-                // Not found, create it, insert it and retrieve it.
-                let proc = Box::new(Process::from_pid(pid)?);
-                self.cache.insert(pid_key.clone(), proc);
-                self.cache
-                    .get(&pid_key)
-                    .ok_or_else(|| anyhow!("Failed to insert process"))?
-                    .downcast_ref::<Process>()
+        // Process information is cached. A different start time means the pid
+        // was reused (e.g. a daemon restarted) and the information is stale.
+        let proc = match self.procs.entry(pid) {
+            Entry::Occupied(mut entry) => {
+                if entry.get().0 != start_time {
+                    debug!("Process {pid} was restarted, refreshing its information");
+                    entry.insert((start_time, Process::from_pid(pid)?));
+                }
+                &entry.into_mut().1
             }
+            Entry::Vacant(entry) => &entry.insert((start_time, Process::from_pid(pid)?)).1,
+        };
+
+        let name = match r#type {
+            USER_TYPE_UPROBE => proc
+                .get_function_from_addr(symbol)?
+                .map(|(func, _)| func.to_string()),
+            _ => proc
+                .get_note_from_symbol(symbol)?
+                .map(|note| note.to_string()),
         }
-        .ok_or_else(|| anyhow!("Failed to retrieve process information"))?;
-
-        let note = proc
-            .get_note_from_symbol(symbol)?
-            .ok_or_else(|| anyhow!("Failed to get symbol information"))?;
+        .ok_or_else(|| anyhow!("Failed to get symbol information"))?;
+
+        let stack_trace = match stack {
+            Some(stack) => {
+                if stack.data.len() != 8 * (USER_STACK_MAX + 1) {
+                    bail!(
+                        "Stack section data is not the expected size {} != {}",
+                        stack.data.len(),
+                        8 * (USER_STACK_MAX + 1)
+                    );
+                }
+
+                let nr = u64::from_ne_bytes(stack.data[0..8].try_into()?) as usize;
+                let mut frames = Vec::new();
+                for ip in stack.data[8..].chunks_exact(8).take(nr) {
+                    let ip = u64::from_ne_bytes(ip.try_into()?);
+                    frames.push(fmt_function(proc.get_function_from_addr(ip)?, ip));
+                }
+                Some(StackTrace(frames))
+            }
+            None => None,
+        };
 
         Ok(Box::new(UserEvent {
             pid,
             tid,
-            symbol: format!("{note}"),
+            start_time,
+            symbol: name,
             ip: symbol,
             path: proc
                 .path()
@@ -140,10 +237,12 @@ impl RawEventSectionFactory for UserEventFactory {
                 .ok_or_else(|| anyhow!("Wrong binary path"))?
                 .to_string(),
             probe_type: match r#type {
-                1 => "usdt",
+                USER_TYPE_USDT => "usdt",
+                USER_TYPE_UPROBE => "uprobe",
                 _ => "unknown",
             }
             .to_string(),
+            stack_trace,
         }))
     }
 }
@@ -6,11 +6,17 @@
 //! This module provides a utility object that can take care of removeing
 //! stale entries
 
-use std::{collections::HashMap, ops::Fn, sync::Arc, thread, time::Duration};
+use std::{
+    collections::HashMap,
+    ops::Fn,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use libbpf_rs::MapCore;
-use log::{error, warn};
+use log::{error, info, warn};
 use nix::time;
 
 use crate::helpers::signals::Running;
@@ -22,9 +28,11 @@ pub(crate) struct TrackingGC {
     // the duration of the entry.
     extract_age: Arc<dyn Fn(Vec<u8>) -> Result<Duration> + Send + Sync + 'static>,
     // Interval of GC runs
-    interval: u64,
+    interval: Duration,
     // Maximum age of entries. Older entires will be removed
-    limit: u64,
+    limit: Duration,
+    // Interval of map usage reports, if enabled
+    report: Option<Duration>,
     // The name of the thread
     name: String,
 
@@ -32,14 +40,18 @@ pub(crate) struct TrackingGC {
 }
 
 impl TrackingGC {
-    // 60 seconds
-    const DEFAULT_OLD_LIMIT: u64 = 60;
-    // 5 seconds
-    const DEFAULT_INTERVAL: u64 = 5;
+    // Maximum time the GC thread sleeps, to stop timely
+    const MAX_SLEEP: Duration = Duration::from_secs(1);
+    // Number of oldest entries shown in reports
+    const REPORT_OLDEST: usize = 5;
 
+    /// Create a GC removing the entries of `maps` older than `limit`, every
+    /// `interval`.
     pub(crate) fn new<F>(
         name: &'static str,
         mut maps: HashMap<&'static str, libbpf_rs::MapHandle>,
+        interval: Duration,
+        limit: Duration,
         extract_age: F,
     ) -> Self
     where
@@ -48,41 +60,63 @@ impl TrackingGC {
         TrackingGC {
             maps: Some(maps.drain().map(|(n, m)| (n.to_string(), m)).collect()),
             extract_age: Arc::new(extract_age),
-            interval: Self::DEFAULT_INTERVAL,
-            limit: Self::DEFAULT_OLD_LIMIT,
+            interval,
+            limit,
+            report: None,
             name: name.to_string(),
             thread: None,
         }
     }
 
-    pub(crate) fn interval(mut self, interval: u64) -> Self {
-        self.interval = interval;
-        self
-    }
-
-    pub(crate) fn limit(mut self, limit: u64) -> Self {
-        self.limit = limit;
+    /// Periodically report the maps usage (number of entries and oldest ones),
+    /// every `interval`. This helps diagnosing tracking issues.
+    pub(crate) fn report(mut self, interval: Duration) -> Self {
+        self.report = Some(interval);
         self
     }
 
     pub(crate) fn start(&mut self, state: Running) -> Result<()> {
+        if self.interval.is_zero() || self.report.is_some_and(|r| r.is_zero()) {
+            bail!(
+                "{}: GC and report intervals must be greater than zero",
+                self.name
+            );
+        }
+
         let interval = self.interval;
         let limit = self.limit;
+        let report = self.report;
         let mut maps = self.maps.take().unwrap();
         let extract_age = self.extract_age.clone();
         self.thread = Some(thread::Builder::new().name(self.name.clone()).spawn(move || {
-            let running = || -> bool {
-                // Let's run every interval seconds.
-                for _ in 0..interval {
-                    thread::sleep(Duration::from_secs(1));
-                    if !state.running() {
-                        return false;
+            let start = Instant::now();
+            let mut next_gc = start + interval;
+            let mut next_report = report.map(|report| start + report);
+            loop {
+                // Sleep until the next thing to do, waking up regularly to
+                // check if we should stop.
+                let next = next_report.map_or(next_gc, |r| r.min(next_gc));
+                thread::sleep(next.saturating_duration_since(Instant::now()).min(Self::MAX_SLEEP));
+                if !state.running() {
+                    break;
+                }
+
+                if let (Some(at), Some(report)) = (next_report.as_mut(), report) {
+                    if Instant::now() >= *at {
+                        *at = Instant::now() + report;
+                        for (name, map) in maps.iter() {
+                            if let Err(e) = Self::report_usage(name, map, &*extract_age) {
+                                error!("{name}: could not report the map usage: {e}");
+                            }
+                        }
                     }
                 }
-                true
-            };
 
-            while running() {
+                if Instant::now() < next_gc {
+                    continue;
+                }
+                next_gc = Instant::now() + interval;
+
                 let now = Duration::from(time::clock_gettime(time::ClockId::CLOCK_MONOTONIC).unwrap());
 
                 // Loop through the tracking map entries and see if we see old
@@ -99,9 +133,7 @@ impl TrackingGC {
                                     continue;
                                 }
                             };
-                            if now.saturating_sub(age)
-                                > Duration::from_secs(limit)
-                            {
+                            if now.saturating_sub(age) > limit {
                                 to_remove.push(key);
                             }
                         }
@@ -118,6 +150,48 @@ impl TrackingGC {
         Ok(())
     }
 
+    /// Report the number of entries of a map and its oldest ones.
+    fn report_usage(
+        name: &str,
+        map: &libbpf_rs::MapHandle,
+        extract_age: &dyn Fn(Vec<u8>) -> Result<Duration>,
+    ) -> Result<()> {
+        let now = Duration::from(time::clock_gettime(time::ClockId::CLOCK_MONOTONIC)?);
+
+        let mut entries = map
+            .keys()
+            .filter_map(|key| match map.lookup(&key, libbpf_rs::MapFlags::ANY) {
+                Ok(Some(raw)) => Some((key, (extract_age)(raw).ok()?)),
+                _ => None,
+            })
+            .map(|(key, age)| (key, now.saturating_sub(age)))
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| b.1.cmp(&a.1));
+
+        let oldest = entries
+            .iter()
+            .take(Self::REPORT_OLDEST)
+            .map(|(key, age)| {
+                format!(
+                    "{} ({:.1}s)",
+                    Self::format_key(map, key.clone()),
+                    age.as_secs_f64()
+                )
+            })
+            .collect::<Vec<_>>();
+
+        info!(
+            "{name} tracking map: {}/{} entries{}",
+            entries.len(),
+            map.info()?.max_entries,
+            match oldest.is_empty() {
+                true => String::new(),
+                false => format!(", oldest: {}", oldest.join(", ")),
+            }
+        );
+        Ok(())
+    }
+
     pub(crate) fn join(&mut self) -> Result<()> {
         if let Some(thread) = self.thread.take() {
             thread
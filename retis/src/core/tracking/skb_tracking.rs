@@ -100,16 +100,6 @@ use crate::{
     },
 };
 
-// GC runs in a thread every SKB_TRACKING_GC_INTERVAL seconds to collect and
-// remove old entries.
-const SKB_TRACKING_GC_INTERVAL: u64 = 5;
-
-// Time in seconds after entries in the skb tracking map are considered outdated
-// and should be manually removed. It's a tradeoff between having consistent
-// data and not having the map full of old entries. However, this logic
-// shouldn't happen much — or it is a bug.
-const TRACKING_OLD_LIMIT: u64 = 60;
-
 fn config_map() -> Result<libbpf_rs::MapHandle> {
     let opts = libbpf_sys::bpf_map_create_opts {
         sz: mem::size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
@@ -146,8 +136,13 @@ fn tracking_map() -> Result<libbpf_rs::MapHandle> {
     .or_else(|e| bail!("Could not create the tracking map: {}", e))
 }
 
+/// Set up skb tracking. Stale entries of the tracking map, older than
+/// `gc_limit`, are removed every `gc_interval`.
 pub(crate) fn init_tracking(
     probes: &mut ProbeBuilderManager,
+    gc_interval: Duration,
+    gc_limit: Duration,
+    report: Option<Duration>,
 ) -> Result<(TrackingGC, libbpf_rs::MapHandle)> {
     let config_map = config_map()?;
     let tracking_map = tracking_map()?;
@@ -251,18 +246,23 @@ pub(crate) fn init_tracking(
     // in the BPF part for most if not all skbs but we might lose some
     // information (and tracked functions might fail resulting in incorrect
     // information).
-    Ok((
-        TrackingGC::new(
-            "skb-tracking-gc",
-            HashMap::from([("skb_tracking", tracking_map)]),
-            |v| {
-                let mut info = tracking_info::default();
-                plain::copy_from_bytes(&mut info, &v[..]).map_err(|e| anyhow!("{:?}", e))?;
-                Ok(Duration::from_nanos(info.last_seen))
-            },
-        )
-        .interval(SKB_TRACKING_GC_INTERVAL)
-        .limit(TRACKING_OLD_LIMIT),
-        config_map,
-    ))
+    let mut gc = TrackingGC::new(
+        "skb-tracking-gc",
+        HashMap::from([("skb_tracking", tracking_map)]),
+        gc_interval,
+        gc_limit,
+        |v| {
+            let mut info = tracking_info::default();
+            plain::copy_from_bytes(&mut info, &v[..]).map_err(|e| anyhow!("{:?}", e))?;
+            Ok(Duration::from_nanos(info.last_seen))
+        },
+    );
+
+    // Optionally report the tracking map usage, to help diagnosing tracking
+    // issues in long captures.
+    if let Some(report) = report {
+        gc = gc.report(report);
+    }
+
+    Ok((gc, config_map))
 }
@@ -5,7 +5,7 @@
 #![allow(dead_code)] // FIXME
 
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{btree_map::Entry, BTreeMap, HashMap},
     ffi::CStr,
     fmt, fs,
     io::{BufRead, BufReader, Cursor},
@@ -19,8 +19,9 @@ use byteorder::BigEndian as Endian;
 #[cfg(target_endian = "little")]
 use byteorder::LittleEndian as Endian;
 use byteorder::ReadBytesExt;
-use elf::{endian::AnyEndian, note::Note, ElfStream};
+use elf::{abi::STT_FUNC, endian::AnyEndian, note::Note, ElfStream};
 use log::warn;
+use once_cell::unsync::OnceCell;
 
 /// Integer to represent all pids.
 const PID_ALL: i32 = -1;
@@ -225,6 +226,40 @@ impl Map {
     }
 }
 
+/// Function symbols of a binary, indexed by address, along with their name and
+/// size.
+type Functions = BTreeMap<u64, (String, u64)>;
+
+/// Load the function symbols of a binary, from its symbol table and its dynamic
+/// one (the only one left in stripped binaries).
+fn load_functions(path: &Path) -> Result<Functions> {
+    let file = fs::File::open(path)?;
+    let mut elf = ElfStream::<AnyEndian, _>::open_stream(file)?;
+    let mut functions = BTreeMap::new();
+
+    for dynamic in [false, true] {
+        let tables = match dynamic {
+            false => elf.symbol_table()?,
+            true => elf.dynamic_symbol_table()?,
+        };
+        let (symtab, strtab) = match tables {
+            Some(tables) => tables,
+            None => continue,
+        };
+
+        for sym in symtab
+            .iter()
+            .filter(|s| s.st_symtype() == STT_FUNC && s.st_value != 0)
+        {
+            if let Entry::Vacant(entry) = functions.entry(sym.st_value) {
+                entry.insert((strtab.get(sym.st_name as usize)?.to_string(), sym.st_size));
+            }
+        }
+    }
+
+    Ok(functions)
+}
+
 /// Object that represents a binary (library or executable) that might have USDT probes.
 #[derive(Debug)]
 pub(crate) struct Binary {
@@ -232,6 +267,8 @@ pub(crate) struct Binary {
     path: PathBuf,
     /// USDT information.
     usdt_info: Option<UsdtInfo>,
+    /// Function symbols, loaded on first use.
+    functions: OnceCell<Functions>,
     /// Virtual memory mapping of this binary in a process.
     map: Map,
 }
@@ -249,6 +286,7 @@ impl Binary {
         Ok(Binary {
             path,
             usdt_info,
+            functions: OnceCell::new(),
             map: Map::default(),
         })
     }
@@ -284,6 +322,42 @@ impl Binary {
             .as_ref()
             .map_or(Ok(None), |info| info.get_note_from_offset(offset))
     }
+
+    fn functions(&self) -> Result<&Functions> {
+        self.functions
+            .get_or_try_init(|| load_functions(&self.path))
+    }
+
+    /// Returns the address of a function, from the binary symbols.
+    pub(crate) fn function_addr(&self, name: &str) -> Result<Option<u64>> {
+        Ok(self
+            .functions()?
+            .iter()
+            .find(|(_, (func, _))| func == name)
+            .map(|(addr, _)| *addr))
+    }
+
+    /// Retrieves the function containing the given address, along with the
+    /// offset of the address in the function.
+    pub(crate) fn get_function_from_addr(&self, addr: u64) -> Result<Option<(&str, u64)>> {
+        if self.map.contains_addr(addr) {
+            // Safely calculate the offset as we know the map exists and contains addr.
+            let offset = addr - self.map.addr_start;
+            return self.get_function_from_offset(offset);
+        }
+        Ok(None)
+    }
+
+    /// Retrieves the function containing the given offset, along with the
+    /// offset in the function.
+    pub(crate) fn get_function_from_offset(&self, offset: u64) -> Result<Option<(&str, u64)>> {
+        Ok(self
+            .functions()?
+            .range(..=offset)
+            .next_back()
+            .filter(|(addr, (_, size))| offset < *addr + (*size).max(1))
+            .map(|(addr, (func, _))| (func.as_str(), offset - addr)))
+    }
 }
 
 /// Object that represents one running process to which probes can be attached.
@@ -321,6 +395,10 @@ impl Process {
         Process::new(pid, path)
     }
 
+    /// Paths found in /proc/<pid>/{exe,maps} are relative to the process'
+    /// root. Binaries of processes running in other mount namespaces (e.g. in
+    /// containers) are resolved through /proc/<pid>/root, so they can be
+    /// inspected and probed from the host.
     fn new(pid: i32, bin_path: PathBuf) -> Result<Process> {
         if pid == PID_ALL {
             return Ok(Process {
@@ -331,6 +409,8 @@ impl Process {
             });
         }
 
+        let root = get_process_root(pid)?;
+
         // Process Map objects for both exec and library binaries.
         let map_entries = get_process_maps(pid)?;
         // Temporarily store library maps in a path-indexed HashMap.
@@ -348,11 +428,15 @@ impl Process {
                 exec_map.extend(&map);
             // Check the path is one of a library as /proc/<pid>/maps contains
             // all mapped files, e.g. /dev/zero.
-            } else if is_shared_library(path.as_path()) {
-                libs_map.entry(path).or_insert(Map::default()).extend(&map);
+            } else {
+                let path = resolve_path(&root, &path);
+                if is_shared_library(path.as_path()) {
+                    libs_map.entry(path).or_insert(Map::default()).extend(&map);
+                }
             }
         }
 
+        let bin_path = resolve_path(&root, &bin_path);
         let pie = is_shared_library(&bin_path);
         let exec = Binary::new_loaded(bin_path, exec_map)?;
         // library objects are stored in a BTreeMap indexed by its map's addr_start for fast lookups.
@@ -370,6 +454,12 @@ impl Process {
 
     /// Create a new Process object with a specific cmd.
     pub(crate) fn from_cmd(cmd: &str) -> Result<Process> {
+        Process::from_pid(Process::pid_of(cmd)?)
+    }
+
+    /// Get the pid of the process running the given command, without
+    /// inspecting it.
+    pub(crate) fn pid_of(cmd: &str) -> Result<i32> {
         let mut pid: i32 = PID_ALL;
         // Look in /proc for a process with this cmd.
         for entry in Path::new("/proc/").read_dir()? {
@@ -403,7 +493,7 @@ impl Process {
         if pid == PID_ALL {
             bail!(ProcessError::NotFound);
         }
-        Process::from_pid(pid)
+        Ok(pid)
     }
 
     /// Create a new Process object that represent all existing and future processes with a
@@ -442,6 +532,24 @@ impl Process {
         }
     }
 
+    /// Gets the function containing an address of the process, along with the
+    /// offset of the address in the function.
+    pub(crate) fn get_function_from_addr(&self, addr: u64) -> Result<Option<(&str, u64)>> {
+        // Same as for USDT notes, PIE executables are treated as shared
+        // libraries.
+        let exec_func = match self.pie {
+            true => self.exec.get_function_from_addr(addr)?,
+            false => self.exec.get_function_from_offset(addr)?,
+        };
+        if exec_func.is_some() {
+            Ok(exec_func)
+        } else if let Some((_, lib)) = self.libs.range((Unbounded, Included(&addr))).next_back() {
+            lib.get_function_from_addr(addr)
+        } else {
+            Ok(None)
+        }
+    }
+
     /// Returns the USDT note and path associated with a target. Targets are specified as "provider::name".
     pub(crate) fn get_note(&self, target: &str) -> Result<Option<(&PathBuf, &UsdtNote)>> {
         // Find in the executable.
@@ -472,6 +580,31 @@ impl Process {
     }
 }
 
+/// Returns the directory paths of a process should be resolved from: / if it
+/// runs in our mount namespace, /proc/<pid>/root otherwise.
+fn get_process_root(pid: i32) -> Result<PathBuf> {
+    let proc_dir = PathBuf::from("/proc").join(pid.to_string());
+    let mnt_ns = |dir: &Path| {
+        dir.join("ns/mnt")
+            .read_link()
+            .map_err(|e| anyhow!("Cannot get the mount namespace of {}: {e}", dir.display()))
+    };
+
+    let same_ns = mnt_ns(Path::new("/proc/self"))? == mnt_ns(&proc_dir)?;
+    Ok(match same_ns {
+        true => PathBuf::from("/"),
+        false => proc_dir.join("root"),
+    })
+}
+
+/// Resolve a path, as seen by a process, from a root directory.
+fn resolve_path(root: &Path, path: &Path) -> PathBuf {
+    match path.strip_prefix("/") {
+        Ok(rel) if root != Path::new("/") => root.join(rel),
+        _ => path.to_path_buf(),
+    }
+}
+
 /// Check if a path is a shared library.
 ///
 /// There are some difficulties in checking the above:
@@ -622,6 +755,23 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn process_root() -> Result<()> {
+        assert_eq!(
+            get_process_root(std::process::id() as i32)?,
+            PathBuf::from("/")
+        );
+        assert_eq!(
+            resolve_path(Path::new("/"), Path::new("/usr/bin/foo")),
+            PathBuf::from("/usr/bin/foo")
+        );
+        assert_eq!(
+            resolve_path(Path::new("/proc/42/root"), Path::new("/usr/bin/foo")),
+            PathBuf::from("/proc/42/root/usr/bin/foo")
+        );
+        Ok(())
+    }
+
     #[test]
     fn process_from_cmd() -> Result<()> {
         let p = Process::from_cmd("cargo");
@@ -642,6 +792,29 @@ mod tests {
         Ok(())
     }
 
+    #[no_mangle]
+    #[inline(never)]
+    extern "C" fn retis_test_function() -> u64 {
+        std::hint::black_box(42)
+    }
+
+    #[test]
+    fn process_functions() -> Result<()> {
+        let p = Process::from_pid(std::process::id() as i32)?;
+        assert!(p.exec.function_addr("retis_test_function")?.is_some());
+        assert!(p
+            .exec
+            .function_addr("_no_way_a_function_with_this_name")?
+            .is_none());
+
+        let addr = retis_test_function as *const () as u64;
+        assert_eq!(
+            p.get_function_from_addr(addr)?,
+            Some(("retis_test_function", 0))
+        );
+        Ok(())
+    }
+
     #[test]
     fn process_all() -> Result<()> {
         let p = Process::all("_no_way_this_path/_exists");
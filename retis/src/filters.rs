@@ -0,0 +1,148 @@
+//! # Filters
+//!
+//! Public API of the Retis filtering engine, for other tools to compile filters
+//! the same way Retis does and to run them in their own BPF programs.
+//!
+//! Two kinds of filters are available:
+//!
+//! - [`PacketFilter`], compiled from a pcap-filter expression (see
+//!   `man pcap-filter`) to eBPF.
+//! - [`MetaFilter`], compiled from an expression on the `struct sk_buff`
+//!   fields (e.g. `sk_buff.mark == 0xc0de`) to a list of operations, using the
+//!   BTF of the running kernel.
+//!
+//! The bytecode of both filters is stable: it only changes with a new major
+//! version of Retis.
+//!
+//! ## Packet filters bytecode
+//!
+//! A sequence of eBPF instructions (`struct bpf_insn`, 8 bytes each, in host
+//! byte order) of at most [`PACKET_FILTER_MAX_INSNS`] instructions, meant to be
+//! patched in place of a placeholder function in a BPF program. The filter
+//! expects:
+//!
+//! - R1 to point to a context, readable and writable by the filter:
+//!   ```c
+//!   struct retis_packet_filter_ctx {
+//!       u32 len;   /* In: linear length. */
+//!       u32 ret;   /* Out: outcome of the match (zero if miss). */
+//!       u8 *data;  /* In: points to the beginning of the mac header for L2
+//!                     filters, of the network header for L3 ones. */
+//!   };
+//!   ```
+//! - R9 to point to the end of an 8-byte aligned stack area of
+//!   [`PACKET_FILTER_STACK_SIZE`] bytes.
+//!
+//! The outcome of the match is returned in R0: zero if the packet does not
+//! match, non-zero otherwise.
+//!
+//! ## Meta filters bytecode
+//!
+//! A `struct retis_meta_filter`, in host byte order, to be stored in a BPF map
+//! and interpreted by the BPF counterpart of the meta filter (see
+//! `meta_filter.h` in the Retis sources):
+//!
+//! ```c
+//! struct retis_meta_filter {
+//!     u32 nops;
+//!     union retis_meta_op ops[32];
+//! };
+//! ```
+
+use anyhow::Result;
+
+use crate::{
+    bindings::packet_filter_uapi,
+    core::filters::{meta::filter::FilterMeta, packets::filter::FilterPacket},
+};
+
+/// Maximum number of eBPF instructions of a packet filter.
+pub const PACKET_FILTER_MAX_INSNS: usize = packet_filter_uapi::FILTER_MAX_INSNS as usize;
+/// Size of the stack area packet filters use, in bytes.
+pub const PACKET_FILTER_STACK_SIZE: usize = packet_filter_uapi::SCRATCH_MEM_START as usize;
+
+/// Layer of the packet data a packet filter is matched against.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PacketLayer {
+    /// Data starts at the Ethernet header.
+    L2,
+    /// Data starts at the network header.
+    L3,
+}
+
+/// Packet filter, compiled from a pcap-filter expression.
+#[derive(Clone)]
+pub struct PacketFilter(FilterPacket);
+
+impl PacketFilter {
+    /// Compile a pcap-filter expression (e.g. `tcp port 80`) for packets
+    /// starting at the given layer.
+    pub fn compile(expr: &str, layer: PacketLayer) -> Result<Self> {
+        let layer = match layer {
+            PacketLayer::L2 => packet_filter_uapi::FILTER_L2,
+            PacketLayer::L3 => packet_filter_uapi::FILTER_L3,
+        };
+        Ok(Self(FilterPacket::from_string_opt(
+            expr.to_string(),
+            layer,
+        )?))
+    }
+
+    /// Filter not matching any packet.
+    pub fn reject() -> Self {
+        Self(FilterPacket::reject_filter())
+    }
+
+    /// eBPF bytecode of the filter.
+    pub fn bytecode(&self) -> Result<Vec<u8>> {
+        self.0.to_bytes()
+    }
+}
+
+/// Meta filter, compiled from an expression on the `struct sk_buff` fields.
+#[derive(Clone)]
+pub struct MetaFilter(FilterMeta);
+
+impl MetaFilter {
+    /// Compile a meta filter expression (e.g. `sk_buff.dev.name == 'eth0'`)
+    /// using the BTF of the running kernel.
+    pub fn compile(expr: &str) -> Result<Self> {
+        Ok(Self(FilterMeta::from_string(expr.to_string())?))
+    }
+
+    /// Bytecode of the filter, a `struct retis_meta_filter`.
+    pub fn bytecode(&self) -> Result<Vec<u8>> {
+        self.0.to_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packet_filter() {
+        let insns = PacketFilter::compile("tcp port 80", PacketLayer::L2)
+            .unwrap()
+            .bytecode()
+            .unwrap();
+        assert!(!insns.is_empty());
+        assert_eq!(insns.len() % 8, 0);
+        assert!(insns.len() / 8 <= PACKET_FILTER_MAX_INSNS);
+
+        assert!(PacketFilter::compile("tcp port 80", PacketLayer::L3).is_ok());
+        assert!(PacketFilter::compile("tcp port 80 and", PacketLayer::L2).is_err());
+        assert_eq!(PacketFilter::reject().bytecode().unwrap().len(), 8);
+    }
+
+    #[test]
+    fn meta_filter() {
+        let filter = MetaFilter::compile("sk_buff.mark == 0xc0de")
+            .unwrap()
+            .bytecode()
+            .unwrap();
+        assert_eq!(u32::from_ne_bytes(filter[..4].try_into().unwrap()), 2);
+
+        assert!(MetaFilter::compile("dev.mark == 0xc0de").is_err());
+    }
+}
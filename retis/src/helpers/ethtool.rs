@@ -0,0 +1,168 @@
+//! # Ethtool
+//!
+//! Minimal ethtool ioctl support, to retrieve the names of the net device
+//! features as shown by `ethtool -k` and the driver statistics as shown by
+//! `ethtool -S`.
+
+use std::{
+    io, mem,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    slice,
+};
+
+use anyhow::{bail, Result};
+
+// Ethtool definitions, see include/uapi/linux/ethtool.h.
+const ETHTOOL_GSTRINGS: u32 = 0x1b;
+const ETHTOOL_GSTATS: u32 = 0x1d;
+const ETHTOOL_GSSET_INFO: u32 = 0x37;
+const ETH_SS_STATS: u32 = 1;
+const ETH_SS_FEATURES: u32 = 4;
+const ETH_GSTRING_LEN: usize = 32;
+
+/// struct ethtool_sset_info, for a single string set.
+#[repr(C)]
+struct SsetInfo {
+    cmd: u32,
+    reserved: u32,
+    sset_mask: u64,
+    data: [u32; 1],
+}
+
+/// struct ethtool_gstrings header, followed by the strings.
+#[repr(C)]
+struct GstringsHdr {
+    cmd: u32,
+    string_set: u32,
+    len: u32,
+}
+
+/// struct ethtool_stats header, followed by the values.
+#[repr(C)]
+struct StatsHdr {
+    cmd: u32,
+    n_stats: u32,
+}
+
+/// struct ifreq, as used by SIOCETHTOOL.
+#[repr(C)]
+struct Ifreq {
+    name: [u8; libc::IFNAMSIZ],
+    data: *mut libc::c_void,
+    _pad: [u8; 16],
+}
+
+fn ethtool_ioctl(sock: &OwnedFd, ifname: &str, data: *mut libc::c_void) -> Result<()> {
+    let mut ifr = Ifreq {
+        name: [0; libc::IFNAMSIZ],
+        data,
+        _pad: [0; 16],
+    };
+    if ifname.len() >= libc::IFNAMSIZ {
+        bail!("Invalid interface name ({ifname})");
+    }
+    ifr.name[..ifname.len()].copy_from_slice(ifname.as_bytes());
+
+    if unsafe { libc::ioctl(sock.as_raw_fd(), libc::SIOCETHTOOL, &mut ifr) } < 0 {
+        bail!("Ethtool request failed: {}", io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn socket() -> Result<OwnedFd> {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        bail!("Could not open socket: {}", io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// Get the strings of a string set (`ETH_SS_*`) of an interface.
+fn strings(sock: &OwnedFd, ifname: &str, set: u32) -> Result<Vec<String>> {
+    let mut info = SsetInfo {
+        cmd: ETHTOOL_GSSET_INFO,
+        reserved: 0,
+        sset_mask: 1 << set,
+        data: [0],
+    };
+    ethtool_ioctl(sock, ifname, &mut info as *mut _ as *mut libc::c_void)?;
+    if info.sset_mask == 0 {
+        bail!("String set {set} not supported by {ifname}");
+    }
+    let count = info.data[0] as usize;
+
+    // Header followed by the strings, allocated as u32 for alignment.
+    let hdr_len = mem::size_of::<GstringsHdr>();
+    let mut buf = vec![0u32; (hdr_len + count * ETH_GSTRING_LEN).div_ceil(4)];
+    let hdr = buf.as_mut_ptr() as *mut GstringsHdr;
+    unsafe {
+        (*hdr).cmd = ETHTOOL_GSTRINGS;
+        (*hdr).string_set = set;
+        (*hdr).len = count as u32;
+    }
+    ethtool_ioctl(sock, ifname, buf.as_mut_ptr() as *mut libc::c_void)?;
+
+    let count = unsafe { (*hdr).len } as usize;
+    let bytes = unsafe {
+        slice::from_raw_parts(buf.as_ptr() as *const u8, hdr_len + count * ETH_GSTRING_LEN)
+    };
+
+    Ok(bytes[hdr_len..]
+        .chunks(ETH_GSTRING_LEN)
+        .map(|s| {
+            let end = s.iter().position(|c| *c == 0).unwrap_or(s.len());
+            String::from_utf8_lossy(&s[..end]).to_string()
+        })
+        .collect())
+}
+
+/// Get the names of the net device features, indexed by their bit in
+/// `netdev_features_t`. The loopback device is used, as the list is the same for
+/// all devices.
+pub(crate) fn feature_names() -> Result<Vec<String>> {
+    strings(&socket()?, "lo", ETH_SS_FEATURES)
+}
+
+/// Get the driver statistics of an interface, as (name, value) pairs.
+pub(crate) fn stats(ifname: &str) -> Result<Vec<(String, u64)>> {
+    let sock = socket()?;
+    let names = strings(&sock, ifname, ETH_SS_STATS)?;
+
+    // Header followed by the values.
+    let hdr_len = mem::size_of::<StatsHdr>();
+    let mut buf = vec![0u64; 1 + names.len()];
+    let hdr = buf.as_mut_ptr() as *mut StatsHdr;
+    unsafe {
+        (*hdr).cmd = ETHTOOL_GSTATS;
+        (*hdr).n_stats = names.len() as u32;
+    }
+    ethtool_ioctl(&sock, ifname, buf.as_mut_ptr() as *mut libc::c_void)?;
+
+    // The number of statistics could have changed in between; only keep the
+    // ones we have a name for.
+    let n_stats = unsafe { (*hdr).n_stats } as usize;
+    Ok(names
+        .into_iter()
+        .zip(buf[hdr_len / mem::size_of::<u64>()..].iter().take(n_stats))
+        .map(|(name, val)| (name, *val))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn features() {
+        let names = feature_names().unwrap();
+        assert_eq!(names.first().map(String::as_str), Some("tx-scatter-gather"));
+        assert!(names.iter().any(|n| n == "rx-gro"));
+    }
+
+    #[test]
+    fn stats_invalid_ifname() {
+        // Interface names can't contain '/' nor be IFNAMSIZ long.
+        assert!(stats("retis/none").is_err());
+        assert!(stats(&"x".repeat(libc::IFNAMSIZ)).is_err());
+    }
+}
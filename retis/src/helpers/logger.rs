@@ -6,15 +6,28 @@ use std::{
 
 use anyhow::Result;
 use log::{LevelFilter, Metadata, Record};
+use once_cell::sync::OnceCell;
 use termcolor::{BufferedStandardStream, Color, ColorChoice, ColorSpec, WriteColor};
 use time::{macros::format_description, OffsetDateTime};
 
+use crate::{
+    core::events::RetisEventsFactory,
+    events::{LogEvent, SectionId},
+};
+
+/// Global reference to our logger, to allow changing its configuration at
+/// runtime (e.g. `Logger::switch_to_stdout()`).
+static LOGGER: OnceCell<Arc<Logger>> = OnceCell::new();
+
 /// Our own logger implementation, to handle log:: messages.
 pub(crate) struct Logger {
     /// Max level the logger will output.
     max_level: LevelFilter,
     /// Inner writer, alongside its configuration.
     inner: Mutex<LoggerWriter>,
+    /// Events factory messages are mirrored to, as log events, along with the
+    /// max level of the mirrored messages.
+    mirror: Mutex<Option<(Arc<RetisEventsFactory>, LevelFilter)>>,
 }
 
 struct LoggerWriter {
@@ -35,14 +48,53 @@ impl Logger {
                 stderr: BufferedStandardStream::stderr(ColorChoice::Auto),
                 use_colors: Self::check_color_use(Some(stderr())),
             }),
+            mirror: Mutex::new(None),
         });
 
         log::set_max_level(max_level);
         log::set_boxed_logger(Box::new(Arc::clone(&logger)))?;
+        let _ = LOGGER.set(Arc::clone(&logger));
 
         Ok(logger)
     }
 
+    /// Get a reference to the global logger, if it was initialized.
+    pub(crate) fn get() -> Option<&'static Arc<Self>> {
+        LOGGER.get()
+    }
+
+    /// Mirror the messages up to `level` to `factory`, as log events. The
+    /// messages are still written to the output as usual, following the max
+    /// level of the logger.
+    pub(crate) fn mirror_to_events(&self, factory: Arc<RetisEventsFactory>, level: LevelFilter) {
+        log::set_max_level(self.max_level.max(level));
+        *self.mirror.lock().unwrap() = Some((factory, level));
+    }
+
+    /// Stop mirroring messages to events.
+    pub(crate) fn stop_mirror(&self) {
+        log::set_max_level(self.max_level);
+        *self.mirror.lock().unwrap() = None;
+    }
+
+    /// Add a log event for the message, if it should be mirrored.
+    fn try_mirror(&self, record: &Record) -> Result<()> {
+        if let Some((factory, level)) = self.mirror.lock().unwrap().as_ref() {
+            if record.level() <= *level {
+                factory.add_event(|event| {
+                    event.insert_section(
+                        SectionId::Log,
+                        Box::new(LogEvent {
+                            level: record.level().as_str().to_lowercase(),
+                            msg: record.args().to_string(),
+                        }),
+                    )
+                })?;
+            }
+        }
+        Ok(())
+    }
+
     pub(crate) fn try_log(&self, record: &Record) -> Result<()> {
         static LEVEL_COLORS: &[Option<Color>] = &[
             None,                // Default.
@@ -114,7 +166,7 @@ impl Logger {
 
 impl log::Log for Logger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= self.max_level
+        metadata.level() <= log::max_level()
     }
 
     fn log(&self, record: &Record) {
@@ -122,8 +174,11 @@ impl log::Log for Logger {
             return;
         }
 
-        // Not much we can do to report the error...
-        let _ = self.try_log(record);
+        // Not much we can do to report the errors...
+        let _ = self.try_mirror(record);
+        if record.level() <= self.max_level {
+            let _ = self.try_log(record);
+        }
     }
 
     fn flush(&self) {
@@ -0,0 +1,176 @@
+//! # Manifest
+//!
+//! Manifest written alongside events files (`<FILE>.manifest`) at the end of a
+//! collection. It describes the collection and holds the size and SHA-256 of
+//! the events file, which post-processing commands check so truncated or
+//! corrupted copies are detected before analyzing them.
+
+use std::{
+    fs::{self, File},
+    io::{ErrorKind, Read},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::events::file::{is_stream, FileEventsFactory};
+
+/// Description of an events file and of the collection which generated it.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Manifest {
+    pub(crate) retis_version: String,
+    /// Kernel release of the collecting host, as in `uname -r`.
+    pub(crate) kernel_release: String,
+    pub(crate) session_id: String,
+    /// Start of the collection, as a UNIX timestamp in seconds.
+    pub(crate) start: u64,
+    /// Duration of the collection, in seconds.
+    pub(crate) duration: f64,
+    /// Number of events in the file.
+    pub(crate) events: u64,
+    /// Size of the file, in bytes.
+    pub(crate) size: u64,
+    /// SHA-256 of the file, as an hex string.
+    pub(crate) sha256: String,
+}
+
+impl Manifest {
+    /// Describe the events file `file`, written by a collection started at
+    /// `start`.
+    pub(crate) fn new(
+        file: &Path,
+        session_id: &str,
+        start: SystemTime,
+        events: u64,
+    ) -> Result<Self> {
+        let (size, sha256) = digest(file)?;
+
+        Ok(Self {
+            retis_version: option_env!("RELEASE_VERSION")
+                .unwrap_or("unspec")
+                .to_string(),
+            kernel_release: nix::sys::utsname::uname()?
+                .release()
+                .to_string_lossy()
+                .to_string(),
+            session_id: session_id.to_string(),
+            start: start.duration_since(UNIX_EPOCH)?.as_secs(),
+            duration: start.elapsed()?.as_secs_f64(),
+            events,
+            size,
+            sha256,
+        })
+    }
+
+    /// Write the manifest of the events file `file`.
+    pub(crate) fn write(&self, file: &Path) -> Result<()> {
+        let path = manifest_path(file);
+        fs::write(&path, serde_json::to_string_pretty(self)? + "\n")
+            .map_err(|e| anyhow!("Could not write {}: {e}", path.display()))
+    }
+
+    /// Check the events file `file` matches its manifest, if it has one.
+    pub(crate) fn verify(file: &Path) -> Result<()> {
+        let path = manifest_path(file);
+        let manifest: Self = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| anyhow!("Invalid manifest {}: {e}", path.display()))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => bail!("Could not read {}: {e}", path.display()),
+        };
+
+        let (size, sha256) = digest(file)?;
+        if size != manifest.size {
+            bail!(
+                "{} is truncated or incomplete: {size} bytes, {} expected by its manifest (remove {} to ignore)",
+                file.display(),
+                manifest.size,
+                path.display(),
+            );
+        }
+        if sha256 != manifest.sha256 {
+            bail!(
+                "{} is corrupted: its SHA-256 does not match its manifest (remove {} to ignore)",
+                file.display(),
+                path.display(),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Manifest of an events file.
+pub(crate) fn manifest_path(file: &Path) -> PathBuf {
+    let mut path = file.as_os_str().to_owned();
+    path.push(".manifest");
+    PathBuf::from(path)
+}
+
+/// Compute the size and SHA-256 of a file.
+fn digest(file: &Path) -> Result<(u64, String)> {
+    let mut input =
+        File::open(file).map_err(|e| anyhow!("Could not open {}: {e}", file.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    let mut size = 0;
+
+    loop {
+        let len = match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        hasher.update(&buf[..len]);
+        size += len as u64;
+    }
+
+    let sha256 = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    Ok((size, sha256))
+}
+
+/// Open an events file for processing, after checking it matches its manifest.
+/// Streams (e.g. `-` for the standard input) can only be read once and are not
+/// checked.
+pub(crate) fn open_events(file: &Path) -> Result<FileEventsFactory> {
+    if !is_stream(file) {
+        Manifest::verify(file)?;
+    }
+    FileEventsFactory::new(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("events.json");
+        fs::write(&file, "{}\n").unwrap();
+
+        // Files without a manifest are not checked.
+        assert!(Manifest::verify(&file).is_ok());
+
+        let manifest = Manifest::new(&file, "id", SystemTime::now(), 1).unwrap();
+        assert_eq!(manifest.size, 3);
+        assert_eq!(
+            manifest.sha256,
+            "ca3d163bab055381827226140568f3bef7eaac187cebd76878e0b63e9e442356"
+        );
+        manifest.write(&file).unwrap();
+        assert!(Manifest::verify(&file).is_ok());
+
+        fs::write(&file, "{}").unwrap();
+        assert!(Manifest::verify(&file).is_err());
+        fs::write(&file, "[]\n").unwrap();
+        assert!(Manifest::verify(&file).is_err());
+    }
+}
@@ -0,0 +1,262 @@
+//! # Mem
+//!
+//! Memory usage guardrails. Monitors the process resident memory and the
+//! events channel depth, and sheds load when approaching a configured limit so
+//! Retis can run on memory-constrained nodes without being OOM killed.
+
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Result};
+use log::{info, warn};
+use nix::unistd::{sysconf, SysconfVar};
+
+use crate::events::Bytes;
+
+/// Ratio of the limit above which events start to be sampled.
+const MEM_SAMPLING_RATIO: f64 = 0.8;
+/// Only one event out of this number is kept while sampling.
+const MEM_SAMPLING_RATE: u64 = 10;
+/// Interval between two memory usage checks.
+const MEM_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Load shedding state.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ShedState {
+    /// All events are kept.
+    Normal = 0,
+    /// Only a sample of the events is kept.
+    Sampling = 1,
+    /// All events are dropped.
+    Dropping = 2,
+}
+
+impl ShedState {
+    fn from_u8(val: u8) -> ShedState {
+        match val {
+            1 => ShedState::Sampling,
+            2 => ShedState::Dropping,
+            _ => ShedState::Normal,
+        }
+    }
+}
+
+#[derive(Default)]
+struct MemGuardInner {
+    /// Memory limit, in bytes.
+    limit: u64,
+    /// Current shedding state.
+    state: AtomicU8,
+    /// Events seen while shedding load, used for sampling.
+    seen: AtomicU64,
+    /// Events dropped to stay under the limit.
+    dropped: AtomicU64,
+    /// Events queued in the events channel, not yet processed.
+    depth: AtomicUsize,
+    /// Maximum events channel depth seen.
+    peak_depth: AtomicUsize,
+    /// Maximum resident memory seen, in bytes.
+    peak_rss: AtomicU64,
+}
+
+/// Memory guard shared between the events polling thread, deciding if events
+/// should be kept, and the processing loop, updating the memory usage.
+#[derive(Clone)]
+pub(crate) struct MemGuard {
+    inner: Arc<MemGuardInner>,
+    page_size: u64,
+    last_check: Option<Instant>,
+}
+
+impl MemGuard {
+    pub(crate) fn new(limit: u64) -> Result<MemGuard> {
+        let page_size = sysconf(SysconfVar::PAGE_SIZE)?
+            .ok_or_else(|| anyhow!("Could not retrieve the page size"))?;
+
+        let guard = MemGuard {
+            inner: Arc::new(MemGuardInner {
+                limit,
+                ..Default::default()
+            }),
+            page_size: page_size as u64,
+            last_check: None,
+        };
+        // Make sure we can actually monitor the memory usage.
+        guard.rss()?;
+
+        Ok(guard)
+    }
+
+    /// Get the resident memory of the process, in bytes.
+    fn rss(&self) -> Result<u64> {
+        let statm = fs::read_to_string("/proc/self/statm")?;
+        let pages = statm
+            .split_whitespace()
+            .nth(1)
+            .ok_or_else(|| anyhow!("Invalid /proc/self/statm format"))?
+            .parse::<u64>()?;
+
+        Ok(pages * self.page_size)
+    }
+
+    /// Check the memory usage and update the shedding state accordingly. Checks
+    /// are rate limited, this can be called for every processed event.
+    pub(crate) fn update(&mut self) -> Result<()> {
+        if self
+            .last_check
+            .is_some_and(|last| last.elapsed() < MEM_CHECK_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.last_check = Some(Instant::now());
+
+        let inner = &self.inner;
+        let rss = self.rss()?;
+        inner.peak_rss.fetch_max(rss, Ordering::Relaxed);
+
+        let state = if rss >= inner.limit {
+            ShedState::Dropping
+        } else if rss as f64 >= inner.limit as f64 * MEM_SAMPLING_RATIO {
+            ShedState::Sampling
+        } else {
+            ShedState::Normal
+        };
+
+        let prev = ShedState::from_u8(inner.state.swap(state as u8, Ordering::Relaxed));
+        if prev != state {
+            let usage = format!(
+                "memory usage {}, limit {}, {} event(s) pending",
+                Bytes(rss),
+                Bytes(inner.limit),
+                inner.depth.load(Ordering::Relaxed)
+            );
+            match state {
+                ShedState::Dropping => {
+                    warn!("Dropping events to stay under the memory limit ({usage})")
+                }
+                ShedState::Sampling => warn!(
+                    "Approaching the memory limit, keeping 1 event out of {MEM_SAMPLING_RATE} ({usage})"
+                ),
+                ShedState::Normal => {
+                    info!("Memory usage back to normal, keeping all events ({usage})")
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check if an event should be kept, accounting for the ones that are not.
+    pub(crate) fn keep(&self) -> bool {
+        let inner = &self.inner;
+        let keep = match ShedState::from_u8(inner.state.load(Ordering::Relaxed)) {
+            ShedState::Normal => true,
+            ShedState::Sampling => inner
+                .seen
+                .fetch_add(1, Ordering::Relaxed)
+                .is_multiple_of(MEM_SAMPLING_RATE),
+            ShedState::Dropping => false,
+        };
+
+        if !keep {
+            inner.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        keep
+    }
+
+    /// Account for an event being queued in the events channel.
+    pub(crate) fn queued(&self) {
+        let depth = self.inner.depth.fetch_add(1, Ordering::Relaxed) + 1;
+        self.inner.peak_depth.fetch_max(depth, Ordering::Relaxed);
+    }
+
+    /// Account for an event being dequeued from the events channel.
+    pub(crate) fn dequeued(&self) {
+        self.inner.depth.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Number of events dropped to stay under the limit.
+    pub(crate) fn dropped(&self) -> u64 {
+        self.inner.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Report the memory guard statistics.
+    pub(crate) fn report(&self) {
+        let inner = &self.inner;
+        let stats = format!(
+            "peak memory usage {}, limit {}, peak of {} pending event(s)",
+            Bytes(inner.peak_rss.load(Ordering::Relaxed)),
+            Bytes(inner.limit),
+            inner.peak_depth.load(Ordering::Relaxed),
+        );
+
+        match inner.dropped.load(Ordering::Relaxed) {
+            0 => info!("No event dropped to stay under the memory limit ({stats})"),
+            dropped => warn!("{dropped} event(s) dropped to stay under the memory limit ({stats})"),
+        }
+    }
+}
+
+/// Parse a size in bytes, with an optional K, M or G suffix (powers of 1024).
+pub(crate) fn parse_size(size: &str) -> Result<u64> {
+    let size = size.trim();
+    let (num, shift) = match size.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&size[..i], 10),
+        Some((i, 'm' | 'M')) => (&size[..i], 20),
+        Some((i, 'g' | 'G')) => (&size[..i], 30),
+        Some(_) => (size, 0),
+        None => bail!("Empty size"),
+    };
+
+    let num = num
+        .parse::<u64>()
+        .map_err(|e| anyhow!("Invalid size '{size}' ({e})"))?;
+    num.checked_shl(shift)
+        .filter(|s| s >> shift == num)
+        .ok_or_else(|| anyhow!("Size '{size}' is too large"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_size() {
+        assert_eq!(super::parse_size("4096").unwrap(), 4096);
+        assert_eq!(super::parse_size("4K").unwrap(), 4096);
+        assert_eq!(super::parse_size("512M").unwrap(), 512 << 20);
+        assert_eq!(super::parse_size("2g").unwrap(), 2 << 30);
+
+        assert!(super::parse_size("").is_err());
+        assert!(super::parse_size("M").is_err());
+        assert!(super::parse_size("-1M").is_err());
+        assert!(super::parse_size("1T").is_err());
+        assert!(super::parse_size("18446744073709551615G").is_err());
+    }
+
+    #[test]
+    fn shedding() {
+        let mut guard = MemGuard::new(u64::MAX).unwrap();
+        guard.update().unwrap();
+        assert!((0..100).all(|_| guard.keep()));
+
+        guard
+            .inner
+            .state
+            .store(ShedState::Sampling as u8, Ordering::Relaxed);
+        assert_eq!((0..100).filter(|_| guard.keep()).count(), 10);
+
+        guard
+            .inner
+            .state
+            .store(ShedState::Dropping as u8, Ordering::Relaxed);
+        assert!(!guard.keep());
+        assert_eq!(guard.inner.dropped.load(Ordering::Relaxed), 91);
+    }
+}
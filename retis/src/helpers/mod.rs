@@ -1,6 +1,10 @@
 pub(crate) mod bimap;
+pub(crate) mod ethtool;
 pub(crate) mod logger;
+pub(crate) mod manifest;
+pub(crate) mod mem;
 pub(crate) mod net;
+pub(crate) mod netlink;
 pub(crate) mod pager;
 pub(crate) mod signals;
 pub(crate) mod time;
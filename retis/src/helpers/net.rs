@@ -1,4 +1,10 @@
-use anyhow::{anyhow, Result};
+use std::net::Ipv6Addr;
+
+use anyhow::{anyhow, bail, Result};
+
+/// Address families, see include/linux/socket.h.
+pub(crate) const AF_INET: u16 = 2;
+pub(crate) const AF_INET6: u16 = 10;
 
 /// Parses an Ethernet address into a String.
 pub(crate) fn parse_eth_addr(raw: &[u8; 6]) -> Result<String> {
@@ -54,9 +60,21 @@ pub(crate) fn parse_ipv4_addr(raw: u32) -> Result<String> {
     Ok(addr)
 }
 
+/// Parses an IPv4 or IPv6 address of the `af` family, stored in a 16 bytes
+/// buffer (e.g. union nf_inet_addr), into a String.
+pub(crate) fn parse_inet_addr(af: u16, raw: &[u8; 16]) -> Result<String> {
+    Ok(match af {
+        AF_INET => parse_ipv4_addr(u32::from_be_bytes(raw[..4].try_into()?))?,
+        AF_INET6 => format!("{}", Ipv6Addr::from(*raw)),
+        x => bail!("Unsupported address family ({x})"),
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use std::net::Ipv4Addr;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use super::{AF_INET, AF_INET6};
 
     #[test]
     fn ethaddr_to_string() {
@@ -82,4 +100,14 @@ mod tests {
             &super::parse_ipv4_addr(Ipv4Addr::new(127, 0, 0, 0).into()).unwrap() == "127.0.0.0"
         );
     }
+
+    #[test]
+    fn inet_to_string() {
+        let mut raw = [0; 16];
+        raw[..4].copy_from_slice(&[10, 96, 0, 10]);
+        assert_eq!(super::parse_inet_addr(AF_INET, &raw).unwrap(), "10.96.0.10");
+        raw = Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 0xa).octets();
+        assert_eq!(super::parse_inet_addr(AF_INET6, &raw).unwrap(), "fd00::a");
+        assert!(super::parse_inet_addr(0, &raw).is_err());
+    }
 }
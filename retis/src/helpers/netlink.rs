@@ -0,0 +1,262 @@
+//! # Netlink
+//!
+//! Minimal netlink support, to dump kernel tables (e.g. the neighbor table or
+//! the generic netlink families) and parse the replies.
+
+use std::{
+    collections::HashMap,
+    ffi::CStr,
+    io,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+};
+
+use anyhow::{bail, Result};
+
+// Netlink message and attribute header sizes, see struct nlmsghdr and struct
+// nlattr in the kernel uapi.
+pub(crate) const NLMSG_HDRLEN: usize = 16;
+const NLA_HDRLEN: usize = 4;
+// Generic netlink header size, see struct genlmsghdr.
+pub(crate) const GENL_HDRLEN: usize = 4;
+
+// Generic netlink controller definitions, see include/uapi/linux/genetlink.h.
+const GENL_ID_CTRL: u16 = 0x10;
+const CTRL_CMD_GETFAMILY: u8 = 3;
+const CTRL_ATTR_FAMILY_ID: u16 = 1;
+const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+
+/// Netlink message.
+pub(crate) struct NlMsg<'a> {
+    pub(crate) r#type: u16,
+    pub(crate) payload: &'a [u8],
+}
+
+fn u16_at(buf: &[u8], off: usize) -> u16 {
+    u16::from_ne_bytes([buf[off], buf[off + 1]])
+}
+
+/// Align a netlink message or attribute length.
+fn align(len: usize) -> usize {
+    len.next_multiple_of(4)
+}
+
+/// Parse the netlink messages found in a buffer.
+pub(crate) fn parse_msgs(mut buf: &[u8]) -> Result<Vec<NlMsg<'_>>> {
+    let mut msgs = Vec::new();
+
+    while buf.len() >= NLMSG_HDRLEN {
+        let len = u32::from_ne_bytes(buf[0..4].try_into()?) as usize;
+        if len < NLMSG_HDRLEN || len > buf.len() {
+            bail!("Malformed netlink message");
+        }
+
+        msgs.push(NlMsg {
+            r#type: u16_at(buf, 4),
+            payload: &buf[NLMSG_HDRLEN..len],
+        });
+        buf = &buf[align(len).min(buf.len())..];
+    }
+
+    Ok(msgs)
+}
+
+/// Parse the netlink attributes found in a buffer, returning their type and
+/// data. Parsing stops at the first malformed attribute.
+pub(crate) fn parse_attrs(mut buf: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut attrs = Vec::new();
+
+    while buf.len() >= NLA_HDRLEN {
+        let len = u16_at(buf, 0) as usize;
+        if len < NLA_HDRLEN || len > buf.len() {
+            break;
+        }
+
+        // Strip the NLA_F_NESTED and NLA_F_NET_BYTEORDER flags.
+        attrs.push((u16_at(buf, 2) & 0x3fff, &buf[NLA_HDRLEN..len]));
+        buf = &buf[align(len).min(buf.len())..];
+    }
+
+    attrs
+}
+
+/// Netlink socket connected to the kernel.
+pub(crate) struct NlSocket {
+    fd: OwnedFd,
+    seq: u32,
+}
+
+impl NlSocket {
+    /// Open a netlink socket for the given protocol, e.g. NETLINK_ROUTE.
+    pub(crate) fn new(protocol: libc::c_int) -> Result<NlSocket> {
+        // Safety: plain socket creation, the fd is checked below.
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                protocol,
+            )
+        };
+        if fd < 0 {
+            bail!(
+                "Could not open a netlink socket: {}",
+                io::Error::last_os_error()
+            );
+        }
+
+        Ok(NlSocket {
+            // Safety: fd is a valid file descriptor we own.
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            seq: 0,
+        })
+    }
+
+    /// Issue a dump request with the given message type and payload, calling
+    /// `f` for each message of the reply.
+    pub(crate) fn dump<F>(&mut self, r#type: u16, payload: &[u8], mut f: F) -> Result<()>
+    where
+        F: FnMut(&NlMsg) -> Result<()>,
+    {
+        self.seq += 1;
+
+        let len = NLMSG_HDRLEN + payload.len();
+        let mut req = Vec::with_capacity(len);
+        req.extend_from_slice(&(len as u32).to_ne_bytes());
+        req.extend_from_slice(&r#type.to_ne_bytes());
+        req.extend_from_slice(&((libc::NLM_F_REQUEST | libc::NLM_F_DUMP) as u16).to_ne_bytes());
+        req.extend_from_slice(&self.seq.to_ne_bytes());
+        req.extend_from_slice(&0u32.to_ne_bytes());
+        req.extend_from_slice(payload);
+
+        // Safety: req is a valid buffer of the given length.
+        if unsafe { libc::send(self.fd.as_raw_fd(), req.as_ptr() as *const _, len, 0) } < 0 {
+            bail!(
+                "Could not send a netlink request: {}",
+                io::Error::last_os_error()
+            );
+        }
+
+        let mut buf = vec![0u8; 32 * 1024];
+        loop {
+            // Safety: buf is a valid buffer of the given length.
+            let len = unsafe {
+                libc::recv(
+                    self.fd.as_raw_fd(),
+                    buf.as_mut_ptr() as *mut _,
+                    buf.len(),
+                    0,
+                )
+            };
+            if len < 0 {
+                bail!(
+                    "Could not read a netlink reply: {}",
+                    io::Error::last_os_error()
+                );
+            } else if len == 0 {
+                return Ok(());
+            }
+
+            for msg in parse_msgs(&buf[..len as usize])? {
+                match msg.r#type as libc::c_int {
+                    libc::NLMSG_DONE => return Ok(()),
+                    libc::NLMSG_ERROR => {
+                        let err = match msg.payload.get(0..4) {
+                            Some(err) => i32::from_ne_bytes(err.try_into()?),
+                            None => 0,
+                        };
+                        bail!(
+                            "Netlink request failed: {}",
+                            io::Error::from_raw_os_error(-err)
+                        );
+                    }
+                    _ => f(&msg)?,
+                }
+            }
+        }
+    }
+}
+
+/// Parse a generic netlink family description, returning its id and name.
+fn parse_genl_family(payload: &[u8]) -> Option<(u16, String)> {
+    let (mut id, mut name) = (None, None);
+
+    parse_attrs(payload.get(GENL_HDRLEN..)?)
+        .iter()
+        .for_each(|(r#type, data)| match *r#type {
+            CTRL_ATTR_FAMILY_ID if data.len() >= 2 => id = Some(u16_at(data, 0)),
+            CTRL_ATTR_FAMILY_NAME => {
+                name = CStr::from_bytes_until_nul(data)
+                    .ok()
+                    .map(|n| n.to_string_lossy().into_owned())
+            }
+            _ => (),
+        });
+
+    Some((id?, name?))
+}
+
+/// Retrieve the generic netlink families registered in the kernel, as a map
+/// of their ids to their names.
+pub(crate) fn genl_families() -> Result<HashMap<u16, String>> {
+    let mut families = HashMap::new();
+
+    // struct genlmsghdr: cmd, version and reserved fields.
+    let req = [CTRL_CMD_GETFAMILY, 1, 0, 0];
+    NlSocket::new(libc::NETLINK_GENERIC)?.dump(GENL_ID_CTRL, &req, |msg| {
+        if msg.r#type == GENL_ID_CTRL {
+            if let Some((id, name)) = parse_genl_family(msg.payload) {
+                families.insert(id, name);
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(families)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nlattr(r#type: u16, data: &[u8]) -> Vec<u8> {
+        let mut attr = Vec::new();
+        attr.extend_from_slice(&((NLA_HDRLEN + data.len()) as u16).to_ne_bytes());
+        attr.extend_from_slice(&r#type.to_ne_bytes());
+        attr.extend_from_slice(data);
+        attr.resize(align(attr.len()), 0);
+        attr
+    }
+
+    #[test]
+    fn parse_msgs() {
+        let mut buf = Vec::new();
+        for (r#type, payload) in [(2u16, &[1u8, 2, 3][..]), (3, &[0; 4])] {
+            buf.extend_from_slice(&((NLMSG_HDRLEN + payload.len()) as u32).to_ne_bytes());
+            buf.extend_from_slice(&r#type.to_ne_bytes());
+            buf.extend_from_slice(&[0; 10]);
+            buf.extend_from_slice(payload);
+            buf.resize(align(buf.len()), 0);
+        }
+
+        let msgs = super::parse_msgs(&buf).unwrap();
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(msgs[0].r#type, 2);
+        assert_eq!(msgs[0].payload, &[1, 2, 3]);
+        assert_eq!(msgs[1].r#type, 3);
+
+        buf[0] = 0xff;
+        assert!(super::parse_msgs(&buf).is_err());
+    }
+
+    #[test]
+    fn parse_genl_family() {
+        let mut payload = vec![CTRL_CMD_GETFAMILY, 1, 0, 0];
+        payload.extend(nlattr(CTRL_ATTR_FAMILY_ID, &0x20u16.to_ne_bytes()));
+        payload.extend(nlattr(CTRL_ATTR_FAMILY_NAME, b"ovs_flow\0"));
+
+        assert_eq!(
+            super::parse_genl_family(&payload),
+            Some((0x20, "ovs_flow".to_string()))
+        );
+        assert_eq!(super::parse_genl_family(&payload[..8]), None);
+    }
+}
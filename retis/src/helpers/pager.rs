@@ -1,37 +1,113 @@
 use std::{env, path::PathBuf};
 
-use log::debug;
+use anyhow::{bail, Result};
+use clap::Args;
+use log::{debug, warn};
 use pager::Pager;
 
 use crate::helpers::logger::Logger;
 
+/// Default pager command, used when no pager is explicitly set. `-F` makes the
+/// pager to only take effect if the output length is > the terminal one, `-R`
+/// enables interpretation of color sequences and `-X` makes the output to not
+/// be cleared on exit.
+const DEFAULT_PAGER: &str = "less -FRX";
+
+/// Pager related arguments, to be flattened in subcommands generating
+/// potentially long outputs.
+#[derive(Args, Debug, Default)]
+pub(crate) struct PagerArgs {
+    #[arg(
+        long,
+        conflicts_with = "no_pager",
+        help = "Pager command used to display the output. Defaults to $PAGER if set, or to \"less -FRX\"."
+    )]
+    pub(crate) pager: Option<String>,
+    #[arg(
+        long,
+        help = "Do not use a pager, even if the output is larger than the terminal."
+    )]
+    pub(crate) no_pager: bool,
+    #[arg(
+        long,
+        conflicts_with = "no_pager",
+        help = "Start the pager at the first line matching PATTERN and highlight all matches.
+Only supported when `less` is used as a pager. PATTERN can't contain whitespaces."
+    )]
+    pub(crate) search: Option<String>,
+}
+
 /// If successful, enables the pager for all output to stdout following this
 /// call. Useful in some commands when the output is quite long and we want to
 /// enable searching and scrolling.
 ///
-/// We're using less if no pager (PAGER env variable) is defined. `-F` makes the
-/// pager to only take effect if the output length is > the terminal one, `-R`
-/// enables interpretation of color sequences and `-X` makes the output to not
-/// be cleared on exit.
-pub(crate) fn try_enable_pager(logger: &Logger) {
-    // Always try to use `PAGER` and if we can, use our own pager as a fallback.
-    if env::var("PAGER").is_ok() || can_use_own_pager() {
-        let mut pager = Pager::with_default_pager("less -FRX");
-        pager.setup();
-
-        // When the pager is enabled we need to output the error messages on
-        // stdout so they're also caught; otherwise they would be hidden at
-        // least until returning from the pager.
-        //
-        // It's OK to do this after enabling the pager as this is done early w/o
-        // other threads running.
-        if pager.is_on() {
-            debug!("Pager is enabled");
+/// The pager command is selected from (in order): the `--pager` argument, the
+/// `PAGER` env variable and our own default pager (if `less` is available).
+/// The pager is not used if stdout is not a terminal or if the `NOPAGER` env
+/// variable is set.
+///
+/// This must be called early, before any other thread is started, as enabling
+/// the pager involves forking the current process.
+pub(crate) fn try_enable_pager(args: &PagerArgs) -> Result<()> {
+    if args.no_pager {
+        return Ok(());
+    }
+
+    let cmd = match pager_command(args, env::var("PAGER").ok().as_deref(), can_use_own_pager())? {
+        Some(cmd) => cmd,
+        None => return Ok(()),
+    };
+
+    debug!("Using pager command '{cmd}'");
+    let mut pager = Pager::with_pager(&cmd);
+    pager.setup();
+
+    // When the pager is enabled we need to output the error messages on stdout
+    // so they're also caught; otherwise they would be hidden at least until
+    // returning from the pager.
+    //
+    // It's OK to do this after enabling the pager as this is done early w/o
+    // other threads running.
+    if pager.is_on() {
+        debug!("Pager is enabled");
+        if let Some(logger) = Logger::get() {
             logger.switch_to_stdout();
+        }
+    } else {
+        debug!("Pager is disabled");
+    }
+
+    Ok(())
+}
+
+/// Compute the pager command to use, if any.
+fn pager_command(args: &PagerArgs, env_pager: Option<&str>, own: bool) -> Result<Option<String>> {
+    let mut cmd = match (&args.pager, env_pager) {
+        (Some(pager), _) => pager.clone(),
+        (None, Some(pager)) => pager.to_string(),
+        (None, None) if own => DEFAULT_PAGER.to_string(),
+        _ => return Ok(None),
+    };
+
+    if let Some(pattern) = &args.search {
+        if pattern.is_empty() || pattern.contains(char::is_whitespace) {
+            bail!("Invalid search pattern '{pattern}' (empty or containing whitespaces)");
+        }
+
+        // Only less is supported for now, as we rely on its +/ command.
+        let is_less = cmd
+            .split_whitespace()
+            .next()
+            .and_then(|bin| bin.rsplit('/').next())
+            .is_some_and(|bin| bin == "less");
+        if is_less {
+            cmd.push_str(&format!(" +/{pattern}"));
         } else {
-            debug!("Pager is disabled");
+            warn!("Search pattern ignored as the pager is not less ({cmd})");
         }
     }
+
+    Ok(Some(cmd))
 }
 
 /// Checks if the pager can be used by checking the `less` command is available
@@ -50,3 +126,44 @@ fn can_use_own_pager() -> bool {
     }
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pager_cmd() {
+        let mut args = PagerArgs::default();
+
+        assert_eq!(pager_command(&args, None, false).unwrap(), None);
+        assert_eq!(
+            pager_command(&args, None, true).unwrap().as_deref(),
+            Some("less -FRX")
+        );
+        assert_eq!(
+            pager_command(&args, Some("more"), true).unwrap().as_deref(),
+            Some("more")
+        );
+
+        args.pager = Some("/usr/bin/less -S".to_string());
+        assert_eq!(
+            pager_command(&args, Some("more"), true).unwrap().as_deref(),
+            Some("/usr/bin/less -S")
+        );
+
+        args.search = Some("drop".to_string());
+        assert_eq!(
+            pager_command(&args, None, true).unwrap().as_deref(),
+            Some("/usr/bin/less -S +/drop")
+        );
+
+        args.pager = None;
+        assert_eq!(
+            pager_command(&args, Some("more"), true).unwrap().as_deref(),
+            Some("more")
+        );
+
+        args.search = Some("foo bar".to_string());
+        assert!(pager_command(&args, None, true).is_err());
+    }
+}
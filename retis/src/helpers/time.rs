@@ -1,4 +1,6 @@
-use anyhow::{bail, Result};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
 use nix::time::{clock_gettime, ClockId};
 
 use crate::events::TimeSpec;
@@ -23,3 +25,69 @@ pub(crate) fn monotonic_clock_offset() -> Result<TimeSpec> {
 
     Ok(TimeSpec::new(offset.tv_sec(), offset.tv_nsec()))
 }
+
+/// Parse a duration given as a number of seconds or using units, Go-style
+/// (e.g. `500ms`, `30s`, `1m30s`, `2h`).
+pub(crate) fn parse_duration(duration: &str) -> Result<Duration> {
+    parse_duration_with_unit(duration, Duration::from_secs)
+}
+
+/// Same as [`parse_duration`], rejecting zero durations.
+pub(crate) fn parse_nonzero_duration(duration: &str) -> Result<Duration> {
+    match parse_duration(duration)? {
+        d if d.is_zero() => bail!("Duration must be greater than zero"),
+        d => Ok(d),
+    }
+}
+
+/// Parse a duration using units, Go-style. Numbers without a unit are
+/// converted using `unit`.
+pub(crate) fn parse_duration_with_unit(
+    duration: &str,
+    unit: fn(u64) -> Duration,
+) -> Result<Duration> {
+    let duration = duration.trim();
+    if let Ok(num) = duration.parse::<u64>() {
+        return Ok(unit(num));
+    }
+
+    humantime::parse_duration(duration)
+        .map_err(|e| anyhow!("Invalid duration '{duration}' ({e}), use e.g. 500ms, 30s or 1m30s"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration() {
+        assert_eq!(
+            super::parse_duration("10").unwrap(),
+            Duration::from_secs(10)
+        );
+        assert_eq!(super::parse_duration(" 0 ").unwrap(), Duration::ZERO);
+        assert_eq!(
+            super::parse_duration("500ms").unwrap(),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            super::parse_duration("1m30s").unwrap(),
+            Duration::from_secs(90)
+        );
+        assert_eq!(
+            super::parse_duration("1h 5m").unwrap(),
+            Duration::from_secs(3900)
+        );
+        assert_eq!(
+            parse_duration_with_unit("250", Duration::from_millis).unwrap(),
+            Duration::from_millis(250)
+        );
+
+        assert!(super::parse_duration("").is_err());
+        assert!(super::parse_duration("-1s").is_err());
+        assert!(super::parse_duration("10 parsecs").is_err());
+        assert!(super::parse_duration("1.5s").is_err());
+        assert!(parse_nonzero_duration("0s").is_err());
+        assert!(parse_nonzero_duration("1ms").is_ok());
+    }
+}
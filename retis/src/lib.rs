@@ -0,0 +1,94 @@
+//! # Retis
+//!
+//! Tracing packets in the Linux networking stack. This library backs the
+//! `retis` binary; its only public API is the [`filters`] module, giving
+//! access to the Retis filtering engine.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use log::{info, trace, warn, LevelFilter};
+
+mod bindings;
+mod cli;
+mod collect;
+mod core;
+pub mod filters;
+mod generate;
+mod helpers;
+mod inspect;
+mod process;
+mod profiles;
+
+#[cfg(feature = "benchmark")]
+mod benchmark;
+
+use crate::{
+    cli::get_cli,
+    core::inspect::{init_inspector, SymbolsConfig},
+    helpers::logger::Logger,
+};
+
+// Re-export events crate. It's not really an import but a re-export so events appear as module
+// inside the crate rather than an external crate. However, clippy doesn't like it.
+#[allow(clippy::single_component_path_imports)]
+use events;
+// Re-export derive macros.
+use retis_derive::*;
+
+/// Run Retis, using the command line arguments of the process.
+pub fn run() -> Result<()> {
+    let mut cli = get_cli()?.build();
+    let log_level = cli.main_config.log_level.as_str();
+    let log_level = LevelFilter::from_str(log_level)
+        .map_err(|e| anyhow!("Invalid log_level: {log_level} ({e})"))?;
+    Logger::init(log_level)?;
+    set_libbpf_rs_print_callback(log_level);
+
+    // Save the --kconf and kernel symbols options values before using the cli
+    // object to dispatch the command.
+    let kconf_opt = cli.main_config.kconf.clone();
+    let syms = SymbolsConfig {
+        path: cli.main_config.symbols.clone(),
+        kaslr_offset: cli.main_config.kaslr_offset,
+    };
+
+    // Step 3: dispatch the command.
+    let command = cli.get_subcommand_mut()?;
+
+    // Per-command early fixups.
+    //
+    // If the user provided a custom kernel config location or symbol map, use
+    // them early to initialize the inspector. As the inspector is only used by
+    // the collect, filter, inspect and postmortem commands, only initialize it
+    // there for now.
+    if ["collect", "filter", "inspect", "postmortem"].contains(&command.name().as_str())
+        && (kconf_opt.is_some() || syms.path.is_some())
+    {
+        init_inspector(kconf_opt.as_ref(), &syms)?;
+    }
+
+    let mut runner = command.runner()?;
+    runner.run(cli)?;
+    Ok(())
+}
+
+fn set_libbpf_rs_print_callback(level: LevelFilter) {
+    let libbpf_rs_print = |level, msg: String| {
+        let msg = msg.trim_end_matches('\n');
+        match level {
+            libbpf_rs::PrintLevel::Debug => trace!("{msg}"),
+            libbpf_rs::PrintLevel::Info => info!("{msg}"),
+            libbpf_rs::PrintLevel::Warn => warn!("{msg}"),
+        }
+    };
+
+    libbpf_rs::set_print(match level {
+        LevelFilter::Error | LevelFilter::Off => None,
+        LevelFilter::Warn => Some((libbpf_rs::PrintLevel::Warn, libbpf_rs_print)),
+        LevelFilter::Info | LevelFilter::Debug => {
+            Some((libbpf_rs::PrintLevel::Info, libbpf_rs_print))
+        }
+        LevelFilter::Trace => Some((libbpf_rs::PrintLevel::Debug, libbpf_rs_print)),
+    });
+}
@@ -0,0 +1,435 @@
+//! # Analyze
+//!
+//! Computes per-flow statistics from series of events, each series describing
+//! the path of a single packet (see `retis sort`): the latency between two
+//! probes, retransmissions and drops.
+//!
+//! Retransmissions are detected using the TCP sequence number and payload
+//! length of segments carrying data (or SYN/FIN), when seen again in a later
+//! series of the same flow. IP options and IPv6 extension headers are not
+//! accounted when computing the payload length, so this is an approximation.
+//!
+//! Packets using UDP segmentation (GSO or GRO) account for all the datagrams
+//! they hold.
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashSet},
+    fmt::Write,
+};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::events::*;
+
+/// TCP FIN and SYN flags, consuming a sequence number as data does.
+const TCP_FIN_SYN: u8 = 0x03;
+
+/// Statistics of a single flow, while analyzing series.
+#[derive(Default)]
+struct Flow {
+    /// Number of packets seen.
+    packets: u64,
+    /// Number of retransmitted TCP segments.
+    retransmits: u64,
+    /// Number of packets dropped.
+    drops: u64,
+    /// Latency samples, in nanoseconds.
+    latencies: Vec<u64>,
+    /// TCP segments (sequence number, payload length) seen so far.
+    segments: HashSet<(u32, u16)>,
+}
+
+/// Per-flow statistics computed from series of events.
+pub(crate) struct SeriesAnalyzer {
+    /// Probes (symbols) the latency is measured between. If not set, the
+    /// latency is measured between the first and last event of the series.
+    probes: Option<(String, String)>,
+    /// Flows found so far, indexed by their description.
+    flows: BTreeMap<String, Flow>,
+}
+
+impl SeriesAnalyzer {
+    pub(crate) fn new(probes: Option<(String, String)>) -> Self {
+        Self {
+            probes,
+            flows: BTreeMap::new(),
+        }
+    }
+
+    /// Add a series to the analysis. Series without IP information are
+    /// ignored, as they can't be associated with a flow.
+    pub(crate) fn add(&mut self, series: &EventSeries) {
+        let skb = match series
+            .events
+            .iter()
+            .filter_map(|e| e.skb())
+            .find(|skb| skb.ip.is_some())
+        {
+            Some(skb) => skb,
+            None => return,
+        };
+
+        let latency = self.latency(series);
+        let flow = self.flows.entry(flow_name(skb)).or_default();
+        flow.packets += skb.udp_segments().map_or(1, |(segs, _)| segs as u64);
+
+        if series.events.iter().any(|e| e.skb_drop().is_some()) {
+            flow.drops += 1;
+        }
+
+        if let Some(segment) = tcp_segment(skb) {
+            if !flow.segments.insert(segment) {
+                flow.retransmits += 1;
+            }
+        }
+
+        if let Some(latency) = latency {
+            flow.latencies.push(latency);
+        }
+    }
+
+    /// Latency of a series, in nanoseconds, if it went through the probes.
+    fn latency(&self, series: &EventSeries) -> Option<u64> {
+        let latency = match &self.probes {
+            Some((from, to)) => {
+                series.latency_between(|e| symbol(e) == Some(from), |e| symbol(e) == Some(to))
+            }
+            None => match series.events.len() {
+                0 | 1 => None,
+                _ => series.duration(),
+            },
+        }?;
+
+        Some(latency.as_nanos())
+    }
+
+    /// Build the report of the analysis. Flows are sorted by number of
+    /// packets.
+    pub(crate) fn report(&self) -> AnalyzeReport {
+        let mut flows = self
+            .flows
+            .iter()
+            .map(|(name, flow)| FlowReport {
+                flow: name.clone(),
+                packets: flow.packets,
+                retransmits: flow.retransmits,
+                drops: flow.drops,
+                latency: Latency::from_samples(&flow.latencies),
+            })
+            .collect::<Vec<_>>();
+        flows.sort_by_key(|f| Reverse(f.packets));
+
+        AnalyzeReport {
+            from: self.probes.as_ref().map(|(from, _)| from.clone()),
+            to: self.probes.as_ref().map(|(_, to)| to.clone()),
+            flows,
+        }
+    }
+}
+
+/// Latency summary, in nanoseconds.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct Latency {
+    pub(crate) samples: usize,
+    pub(crate) min: u64,
+    pub(crate) avg: u64,
+    pub(crate) p50: u64,
+    pub(crate) p99: u64,
+    pub(crate) max: u64,
+}
+
+impl Latency {
+    pub(crate) fn from_samples(samples: &[u64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        let n = sorted.len();
+
+        Some(Self {
+            samples: n,
+            min: sorted[0],
+            avg: (sorted.iter().map(|l| *l as u128).sum::<u128>() / n as u128) as u64,
+            p50: sorted[(n - 1) * 50 / 100],
+            p99: sorted[(n - 1) * 99 / 100],
+            max: sorted[n - 1],
+        })
+    }
+}
+
+/// Statistics of a single flow.
+#[derive(Serialize)]
+pub(crate) struct FlowReport {
+    pub(crate) flow: String,
+    pub(crate) packets: u64,
+    pub(crate) retransmits: u64,
+    pub(crate) drops: u64,
+    pub(crate) latency: Option<Latency>,
+}
+
+/// Result of the analysis of series of events.
+#[derive(Serialize)]
+pub(crate) struct AnalyzeReport {
+    /// Probe the latency is measured from, if not the first event.
+    pub(crate) from: Option<String>,
+    /// Probe the latency is measured to, if not the last event.
+    pub(crate) to: Option<String>,
+    pub(crate) flows: Vec<FlowReport>,
+}
+
+impl AnalyzeReport {
+    /// Render the report as a table. Latencies are shown in microseconds.
+    pub(crate) fn render(&self) -> Result<String> {
+        let mut out = String::new();
+
+        match (&self.from, &self.to) {
+            (Some(from), Some(to)) => writeln!(out, "Latency from {from} to {to}")?,
+            _ => writeln!(
+                out,
+                "Latency from the first to the last event of each packet"
+            )?,
+        }
+
+        if self.flows.is_empty() {
+            writeln!(out, "No flow found")?;
+            return Ok(out);
+        }
+
+        let width = self
+            .flows
+            .iter()
+            .map(|f| f.flow.len())
+            .max()
+            .unwrap_or_default()
+            .max(4);
+        writeln!(
+            out,
+            "\n{:width$}  {:>8}  {:>8}  {:>8}  latency (us) min/avg/p50/p99/max",
+            "flow", "packets", "retrans", "drops"
+        )?;
+
+        let us = |ns: u64| format!("{:.1}", ns as f64 / 1000.0);
+        for flow in self.flows.iter() {
+            let latency = match &flow.latency {
+                Some(l) => format!(
+                    "{}/{}/{}/{}/{} ({} samples)",
+                    us(l.min),
+                    us(l.avg),
+                    us(l.p50),
+                    us(l.p99),
+                    us(l.max),
+                    l.samples
+                ),
+                None => "-".to_string(),
+            };
+            writeln!(
+                out,
+                "{:width$}  {:>8}  {:>8}  {:>8}  {latency}",
+                flow.flow, flow.packets, flow.retransmits, flow.drops
+            )?;
+        }
+
+        Ok(out)
+    }
+}
+
+/// Symbol of the probe which generated an event.
+fn symbol(event: &Event) -> Option<&String> {
+    match (event.kernel(), event.user()) {
+        (Some(kernel), _) => Some(&kernel.symbol),
+        (_, Some(user)) => Some(&user.symbol),
+        _ => None,
+    }
+}
+
+/// Name of the flow a packet belongs to. Flows are directional.
+fn flow_name(skb: &SkbEvent) -> String {
+    let ip = match &skb.ip {
+        Some(ip) => ip,
+        None => return String::new(),
+    };
+
+    let ports = match (&skb.tcp, &skb.udp, &skb.sctp) {
+        (Some(tcp), _, _) => Some((tcp.sport, tcp.dport)),
+        (_, Some(udp), _) => Some((udp.sport, udp.dport)),
+        (_, _, Some(sctp)) => Some((sctp.sport, sctp.dport)),
+        _ => None,
+    };
+    let proto = match ip.protocol {
+        1 => "icmp".to_string(),
+        6 => "tcp".to_string(),
+        17 => "udp".to_string(),
+        58 => "icmpv6".to_string(),
+        132 => "sctp".to_string(),
+        x => format!("proto {x}"),
+    };
+
+    match ports {
+        Some((sport, dport)) => format!("{proto} {}.{sport} > {}.{dport}", ip.saddr, ip.daddr),
+        None => format!("{proto} {} > {}", ip.saddr, ip.daddr),
+    }
+}
+
+/// Sequence number and payload length of a TCP segment, if it carries data
+/// (or SYN/FIN).
+fn tcp_segment(skb: &SkbEvent) -> Option<(u32, u16)> {
+    let (ip, tcp) = match (&skb.ip, &skb.tcp) {
+        (Some(ip), Some(tcp)) => (ip, tcp),
+        _ => return None,
+    };
+
+    let ip_hdr = match ip.version {
+        SkbIpVersion::V4 { .. } => 20,
+        SkbIpVersion::V6 { .. } => 0,
+    };
+    let payload = ip
+        .len
+        .saturating_sub(ip_hdr)
+        .saturating_sub(tcp.doff as u16 * 4);
+
+    match payload > 0 || tcp.flags & TCP_FIN_SYN != 0 {
+        true => Some((tcp.seq, payload)),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(timestamp: u64, symbol: &str, seq: u32, len: u16, drop: bool) -> Event {
+        let mut event = Event::new();
+        event
+            .insert_section(
+                SectionId::Common,
+                Box::new(CommonEvent {
+                    timestamp,
+                    seq: None,
+                    smp_id: None,
+                    task: None,
+                }),
+            )
+            .unwrap();
+        event
+            .insert_section(
+                SectionId::Kernel,
+                Box::new(KernelEvent {
+                    symbol: symbol.to_string(),
+                    probe_type: "kprobe".to_string(),
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        event
+            .insert_section(
+                SectionId::Skb,
+                Box::new(SkbEvent {
+                    ip: Some(SkbIpEvent {
+                        saddr: "10.0.0.1".to_string(),
+                        daddr: "10.0.0.2".to_string(),
+                        version: SkbIpVersion::V4 {
+                            v4: SkbIpv4Event {
+                                tos: 0,
+                                id: 0,
+                                flags: 0,
+                                offset: 0,
+                            },
+                        },
+                        protocol: 6,
+                        len,
+                        ttl: 64,
+                        ecn: 0,
+                    }),
+                    tcp: Some(SkbTcpEvent {
+                        sport: 4242,
+                        dport: 80,
+                        seq,
+                        ack_seq: 0,
+                        window: 0,
+                        doff: 5,
+                        flags: 0x10,
+                    }),
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        if drop {
+            event
+                .insert_section(
+                    SectionId::SkbDrop,
+                    Box::new(SkbDropEvent {
+                        subsys: None,
+                        drop_reason: "NO_SOCKET".to_string(),
+                    }),
+                )
+                .unwrap();
+        }
+        event
+    }
+
+    #[test]
+    fn analyze() {
+        let series = |start: u64, seq: u32, len: u16, drop: bool| EventSeries {
+            events: vec![
+                event(start, "ip_rcv", seq, len, false),
+                event(start + 2000, "tcp_v4_rcv", seq, len, false),
+                event(start + 5000, "tcp_rcv_established", seq, len, drop),
+            ],
+        };
+
+        let mut analyzer = SeriesAnalyzer::new(Some((
+            "tcp_v4_rcv".to_string(),
+            "tcp_rcv_established".to_string(),
+        )));
+        analyzer.add(&series(1000, 1, 140, false));
+        analyzer.add(&series(2000, 101, 140, false));
+        // Retransmission.
+        analyzer.add(&series(3000, 101, 140, true));
+        // Pure ACKs are not retransmissions.
+        analyzer.add(&series(4000, 201, 40, false));
+        analyzer.add(&series(5000, 201, 40, false));
+        analyzer.add(&EventSeries { events: Vec::new() });
+
+        let report = analyzer.report();
+        assert_eq!(report.flows.len(), 1);
+        let flow = &report.flows[0];
+        assert_eq!(flow.flow, "tcp 10.0.0.1.4242 > 10.0.0.2.80");
+        assert_eq!(flow.packets, 5);
+        assert_eq!(flow.retransmits, 1);
+        assert_eq!(flow.drops, 1);
+        assert_eq!(
+            flow.latency,
+            Some(Latency {
+                samples: 5,
+                min: 3000,
+                avg: 3000,
+                p50: 3000,
+                p99: 3000,
+                max: 3000,
+            })
+        );
+        assert!(report
+            .render()
+            .unwrap()
+            .contains("3.0/3.0/3.0/3.0/3.0 (5 samples)"));
+
+        // Without probes, the latency is measured between the first and last
+        // events.
+        let mut analyzer = SeriesAnalyzer::new(None);
+        analyzer.add(&series(1000, 1, 140, false));
+        assert_eq!(
+            analyzer.report().flows[0].latency.as_ref().unwrap().max,
+            5000
+        );
+
+        // Series not going through the probes have no latency.
+        let mut analyzer =
+            SeriesAnalyzer::new(Some(("ip_rcv".to_string(), "ip_forward".to_string())));
+        analyzer.add(&series(1000, 1, 140, false));
+        assert!(analyzer.report().flows[0].latency.is_none());
+    }
+}
@@ -0,0 +1,101 @@
+//! # Analyze
+//!
+//! Analyze computes per-flow latency, retransmissions and drops from sorted
+//! events.
+
+use std::{
+    fs,
+    io::{stdout, ErrorKind, Write},
+    path::PathBuf,
+};
+
+use anyhow::{bail, Result};
+use clap::Parser;
+
+use crate::{
+    cli::*,
+    events::file::FileType,
+    helpers::{
+        manifest::open_events,
+        pager::{try_enable_pager, PagerArgs},
+        signals::Running,
+    },
+    process::analyze::SeriesAnalyzer,
+};
+
+/// Compute per-flow latency, retransmissions and drops from sorted events.
+///
+/// Reads series of events (one per packet, see `retis sort`) from the INPUT file and reports, for
+/// each flow, the number of packets, retransmitted TCP segments and drops, and the latency between
+/// two probes (e.g. from ip_rcv to tcp_rcv_established). If no probe is given, the latency is
+/// measured between the first and the last event of each packet.
+#[derive(Parser, Debug, Default)]
+#[command(name = "analyze")]
+pub(crate) struct Analyze {
+    /// File from which to read sorted events, `-` for the standard input.
+    #[arg(default_value = "retis.data")]
+    pub(super) input: PathBuf,
+
+    /// Symbol of the probe the latency is measured from (e.g. ip_rcv).
+    #[arg(long, requires = "to")]
+    pub(super) from: Option<String>,
+
+    /// Symbol of the probe the latency is measured to (e.g. tcp_rcv_established).
+    #[arg(long, requires = "from")]
+    pub(super) to: Option<String>,
+
+    /// Also write the report, in JSON, to the given file. Latencies are reported in nanoseconds.
+    #[arg(long, value_name = "FILE")]
+    pub(super) json: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub(super) pager: PagerArgs,
+}
+
+impl SubCommandParserRunner for Analyze {
+    fn run(&mut self) -> Result<()> {
+        // Try setting up the pager. This must be done before any other thread
+        // is started.
+        try_enable_pager(&self.pager)?;
+
+        // Create running instance that will handle signal termination.
+        let run = Running::new();
+        run.register_term_signals()?;
+
+        // Create event factory.
+        let mut factory = open_events(self.input.as_path())?;
+        if matches!(factory.file_type(), FileType::Event) {
+            bail!(
+                "{} does not contain sorted events, run `retis sort` first",
+                self.input.display()
+            );
+        }
+
+        let probes = match (&self.from, &self.to) {
+            (Some(from), Some(to)) => Some((from.clone(), to.clone())),
+            _ => None,
+        };
+        let mut analyzer = SeriesAnalyzer::new(probes);
+
+        while run.running() {
+            match factory.next_series()? {
+                Some(series) => analyzer.add(&series),
+                None => break,
+            }
+        }
+
+        let report = analyzer.report();
+        if let Some(json) = &self.json {
+            fs::write(json, serde_json::to_string_pretty(&report)?)
+                .or_else(|e| bail!("Could not write '{}': {e}", json.display()))?;
+        }
+
+        if let Err(e) = stdout().write_all(report.render()?.as_bytes()) {
+            if e.kind() != ErrorKind::BrokenPipe {
+                return Err(e.into());
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+//! # Convert
+//!
+//! Convert event files between the json and the binary formats, validating
+//! events against their section definitions.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::BufWriter,
+    path::PathBuf,
+};
+
+use anyhow::{bail, Result};
+use clap::{Parser, ValueEnum};
+use log::{info, warn};
+
+use crate::{
+    cli::*,
+    events::{
+        binary::BinaryWriter,
+        file::{is_stream, FileFormat, FileType},
+        *,
+    },
+    helpers::{manifest::open_events, signals::Running},
+    process::display::*,
+};
+
+/// Output format of the convert command.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum)]
+pub(crate) enum ConvertFormat {
+    Json,
+    Binary,
+}
+
+/// Convert stored events between the json and the binary formats.
+///
+/// Each event is validated against the definition of its sections while being
+/// converted. Invalid events are reported and skipped, unless --strict is used
+/// or the input is a binary file (which can't be read past invalid data).
+#[derive(Parser, Debug, Default)]
+#[command(name = "convert")]
+pub(crate) struct Convert {
+    /// File from which to read events, `-` for the standard input.
+    #[arg(default_value = "retis.data")]
+    pub(super) input: PathBuf,
+
+    /// File to write the converted events to.
+    #[arg(short, long)]
+    pub(super) out: PathBuf,
+
+    /// Output format. Defaults to the opposite of the input format.
+    #[arg(long, value_enum)]
+    pub(super) to: Option<ConvertFormat>,
+
+    /// Fail on the first invalid event instead of skipping it.
+    #[arg(long)]
+    pub(super) strict: bool,
+}
+
+/// Writes events or series to the converted file.
+enum ConvertWriter {
+    JsonEvents(PrintEvent),
+    JsonSeries(PrintSeries),
+    Binary(BinaryWriter<BufWriter<File>>),
+}
+
+impl ConvertWriter {
+    fn write(&mut self, series: &EventSeries) -> Result<()> {
+        match self {
+            ConvertWriter::JsonEvents(p) => series.events.iter().try_for_each(|e| p.process_one(e)),
+            ConvertWriter::JsonSeries(p) => p.process_one(series),
+            ConvertWriter::Binary(b) => match b.file_type() {
+                FileType::Event => series.events.iter().try_for_each(|e| b.write_event(e)),
+                FileType::Series => b.write_series(series),
+            },
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match self {
+            ConvertWriter::JsonEvents(p) => p.flush(),
+            ConvertWriter::JsonSeries(p) => p.flush(),
+            ConvertWriter::Binary(b) => b.flush(),
+        }
+    }
+}
+
+impl SubCommandParserRunner for Convert {
+    fn run(&mut self) -> Result<()> {
+        // Create running instance that will handle signal termination.
+        let run = Running::new();
+        run.register_term_signals()?;
+
+        let mut factory = open_events(self.input.as_path())?;
+        let filetype = factory.file_type().clone();
+
+        let to = self.to.unwrap_or(match factory.file_format() {
+            FileFormat::Json => ConvertFormat::Binary,
+            FileFormat::Binary => ConvertFormat::Json,
+        });
+
+        // Make sure we don't use the same file as the result will be the
+        // deletion of the original one.
+        if let Some(out) = self
+            .out
+            .canonicalize()
+            .ok()
+            .filter(|_| !is_stream(&self.input))
+        {
+            if out.eq(&self.input.canonicalize()?) {
+                bail!("Cannot convert a file in-place. Please specify an output file that's different to the input one.");
+            }
+        }
+
+        let out = BufWriter::new(
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&self.out)
+                .or_else(|_| bail!("Could not create or open '{}'", self.out.display()))?,
+        );
+        let mut writer = match (to, &filetype) {
+            (ConvertFormat::Json, FileType::Event) => {
+                ConvertWriter::JsonEvents(PrintEvent::new(Box::new(out), PrintEventFormat::Json))
+            }
+            (ConvertFormat::Json, FileType::Series) => {
+                ConvertWriter::JsonSeries(PrintSeries::new(Box::new(out), PrintEventFormat::Json))
+            }
+            (ConvertFormat::Binary, _) => {
+                ConvertWriter::Binary(BinaryWriter::new(out, filetype.clone())?)
+            }
+        };
+
+        let (mut converted, mut invalid) = (0, 0);
+        while run.running() {
+            let res = match filetype {
+                FileType::Event => factory.next_event().map(|e| {
+                    e.map(|e| {
+                        let mut series = EventSeries::default();
+                        series.events.push(e);
+                        series
+                    })
+                }),
+                FileType::Series => factory.next_series(),
+            };
+
+            let series = match res {
+                Ok(Some(series)) => series,
+                Ok(None) => break,
+                Err(e) => {
+                    // Binary files can't be resynchronized after an error.
+                    if self.strict || factory.file_format() == FileFormat::Binary {
+                        bail!("Invalid event ({} converted so far): {e}", converted);
+                    }
+                    warn!("Skipping invalid event: {e}");
+                    invalid += 1;
+                    continue;
+                }
+            };
+
+            if let Some(e) = series.events.iter().find(|e| e.common().is_none()) {
+                let err = format!(
+                    "Event has no common section (sections: {})",
+                    e.sections()
+                        .map(|s| s.to_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                if self.strict {
+                    bail!(err);
+                }
+                warn!("Skipping invalid event: {err}");
+                invalid += 1;
+                continue;
+            }
+
+            writer.write(&series)?;
+            converted += 1;
+        }
+
+        writer.flush()?;
+
+        let unit = match filetype {
+            FileType::Event => "event",
+            FileType::Series => "series",
+        };
+        info!("{converted} {unit}(s) converted to {to:?}");
+        if invalid != 0 {
+            warn!("{invalid} invalid {unit}(s) were skipped");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+//! # Flamegraph
+//!
+//! Flamegraph aggregates stack traces from stored events and renders them as
+//! folded stacks or as an SVG flamegraph.
+
+use std::{
+    fs,
+    io::{stdout, ErrorKind, Write},
+    path::PathBuf,
+};
+
+use anyhow::{bail, Result};
+use clap::Parser;
+
+use crate::{
+    cli::*,
+    events::file::FileType,
+    helpers::{manifest::open_events, signals::Running},
+    process::flamegraph::{FlamegraphWeight, StackAggregator},
+};
+
+/// Generate a flamegraph from the stack traces of stored events.
+///
+/// Events must have been collected using `--stack`. Stack traces are aggregated and written in
+/// the folded format (one line per unique stack, usable by most flamegraph tools) or, if the output
+/// file has the .svg extension, directly as an SVG flamegraph.
+#[derive(Parser, Debug, Default)]
+#[command(name = "flamegraph")]
+pub(crate) struct Flamegraph {
+    /// File from which to read events, `-` for the standard input.
+    #[arg(default_value = "retis.data")]
+    pub(super) input: PathBuf,
+
+    /// Write the output to a file rather than stdout. Files with the .svg extension get an SVG
+    /// flamegraph, others the folded stacks.
+    #[arg(short, long)]
+    pub(super) out: Option<PathBuf>,
+
+    /// What stack traces are weighted by.
+    #[arg(long, value_enum, default_value = "events")]
+    pub(super) weight: FlamegraphWeight,
+
+    /// Only consider events reporting a drop.
+    #[arg(long)]
+    pub(super) drops: bool,
+
+    /// Group stack traces by drop reason, which are added as root frames.
+    #[arg(long)]
+    pub(super) by_reason: bool,
+}
+
+impl SubCommandParserRunner for Flamegraph {
+    fn run(&mut self) -> Result<()> {
+        // Create running instance that will handle signal termination.
+        let run = Running::new();
+        run.register_term_signals()?;
+
+        // Create event factory.
+        let mut factory = open_events(self.input.as_path())?;
+        let mut aggregator = StackAggregator::new(self.weight, self.drops, self.by_reason);
+
+        match factory.file_type() {
+            FileType::Event => {
+                while run.running() {
+                    match factory.next_event()? {
+                        Some(event) => aggregator.add(&event),
+                        None => break,
+                    }
+                }
+            }
+            FileType::Series => {
+                while run.running() {
+                    match factory.next_series()? {
+                        Some(series) => series.events.iter().for_each(|e| aggregator.add(e)),
+                        None => break,
+                    }
+                }
+            }
+        }
+
+        match &self.out {
+            Some(out) => {
+                let content = match out.extension().and_then(|ext| ext.to_str()) {
+                    Some("svg") => {
+                        aggregator.svg(&format!("Stack traces from {}", self.input.display()))?
+                    }
+                    _ => aggregator.folded(),
+                };
+                fs::write(out, content)
+                    .or_else(|e| bail!("Could not write '{}': {e}", out.display()))?;
+            }
+            None => {
+                if let Err(e) = stdout().write_all(aggregator.folded().as_bytes()) {
+                    if e.kind() != ErrorKind::BrokenPipe {
+                        return Err(e.into());
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
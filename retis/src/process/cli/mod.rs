@@ -2,6 +2,15 @@
 //!
 //! Provides cli commands to perform some post-processing.
 
+pub(crate) mod analyze;
+pub(crate) use analyze::*;
+
+pub(crate) mod convert;
+pub(crate) use convert::*;
+
+pub(crate) mod flamegraph;
+pub(crate) use flamegraph::*;
+
 pub(crate) mod pcap;
 pub(crate) use self::pcap::*;
 
@@ -15,3 +24,9 @@ pub(crate) use python::*;
 
 pub(crate) mod sort;
 pub(crate) use sort::*;
+
+pub(crate) mod stats;
+pub(crate) use stats::*;
+
+pub(crate) mod timeline;
+pub(crate) use timeline::*;
@@ -1,199 +1,32 @@
 use std::{
-    borrow::Cow,
-    collections::HashMap,
-    fs::OpenOptions,
-    io::Write,
+    collections::{hash_map::Entry, HashMap},
+    fs::{self, File, OpenOptions},
     path::{Path, PathBuf},
-    time::Duration,
 };
 
-use anyhow::{anyhow, bail, Result};
-use clap::{arg, Parser};
-use log::{info, warn};
-use pcap_file::{
-    pcapng::{
-        blocks::{
-            enhanced_packet::{EnhancedPacketBlock, EnhancedPacketOption},
-            interface_description::{InterfaceDescriptionBlock, InterfaceDescriptionOption},
-        },
-        PcapNgBlock, PcapNgWriter,
-    },
-    DataLink,
-};
+use anyhow::{bail, Result};
+use clap::{arg, Parser, ValueEnum};
+use log::info;
 
 use crate::{
     cli::*,
     core::{kernel::Symbol, probe::kernel::utils::*},
-    events::{file::FileEventsFactory, CommonEvent, KernelEvent, SkbEvent, *},
-    helpers::signals::Running,
+    events::*,
+    helpers::{manifest::open_events, signals::Running},
+    process::{
+        output::{OpenFiles, ReopenableFile},
+        pcap::{EventParser, EventParserStats},
+        tracking::AddTracking,
+    },
 };
 
-/// Statistics of the event parser about events (processed, skipped, etc).
-#[derive(Default)]
-struct EventParserStats {
-    /// Events that were processed by the parser. Aka. all events that were
-    /// matched by the filter.
-    processed: u32,
-    /// Events w/o an skb section (skipped).
-    missing_skb: u32,
-    /// Events w/o a packet section (skipped).
-    missing_packet: u32,
-    /// Events w/o a dev section (fake one was used instead).
-    missing_dev: u32,
-    /// Events w/o a netns section (fake one was used instead).
-    missing_ns: u32,
-}
-
-/// Events parser: handles the logic to convert our events to the PCAP format
-/// that is represented by the internal writer.
-struct EventParser<'a, W: Write> {
-    writer: &'a mut PcapNgWriter<W>,
-    /// Known network interfaces and their PCAP id: netns|ifindex -> pcap id.
-    ifaces: HashMap<u64, u32>,
-    /// Statistics.
-    stats: EventParserStats,
-}
-
-// Unwrap a Some(_) value or return from the function.
-macro_rules! some_or_return {
-    ($section: expr, $stat: expr) => {
-        match $section {
-            Some(val) => val,
-            None => {
-                $stat += 1;
-                return Ok(());
-            }
-        }
-    };
-}
-
-impl<'a, W: Write> EventParser<'a, W> {
-    /// Creates a new EventParser from a PcapNgWriter<W: Write>.
-    fn from(writer: &'a mut PcapNgWriter<W>) -> Self {
-        Self {
-            writer,
-            ifaces: HashMap::new(),
-            stats: EventParserStats::default(),
-        }
-    }
-
-    /// Parse & process a single Retis event.
-    fn parse(&mut self, event: &Event) -> Result<()> {
-        // Having a common & a kernel section is mandatory for now, seeing a
-        // filtered event w/o one of those is bogus.
-        let common = event
-            .get_section::<CommonEvent>(SectionId::Common)
-            .ok_or_else(|| anyhow!("No common section in event"))?;
-        let kernel = event
-            .get_section::<KernelEvent>(SectionId::Kernel)
-            .ok_or_else(|| anyhow!("No skb section in event"))?;
-
-        self.stats.processed += 1;
-
-        // The skb & packet sections are mandatory for us to generate PCAP
-        // events, but they might not be present in some filtered events. Stats
-        // are kept here to inform the user.
-        let skb = some_or_return!(
-            event.get_section::<SkbEvent>(SectionId::Skb),
-            self.stats.missing_skb
-        );
-        let packet = some_or_return!(skb.packet.as_ref(), self.stats.missing_packet);
-
-        // The dev & ns sections are best to have but not mandatory to generate
-        // an event. If not found, fake them.
-        let (ifindex, ifname) = match skb.dev.as_ref() {
-            Some(dev) => (dev.ifindex, dev.name.as_str()),
-            None => {
-                self.stats.missing_dev += 1;
-                (0, "?")
-            }
-        };
-        let netns = match skb.ns.as_ref() {
-            Some(ns) => ns.netns,
-            None => {
-                self.stats.missing_ns += 1;
-                0
-            }
-        };
-
-        // If we see this iface for the first time, add a description block.
-        let key: u64 = (netns as u64) << 32 | ifindex as u64;
-        let id = match self.ifaces.contains_key(&key) {
-            // Unwrap if contains is true.
-            true => *self.ifaces.get(&key).unwrap(),
-            false => {
-                self.writer.write_block(
-                    &InterfaceDescriptionBlock {
-                        linktype: DataLink::ETHERNET,
-                        snaplen: 0xffff,
-                        options: vec![
-                            InterfaceDescriptionOption::IfName(Cow::Owned(format!(
-                                "{} ({})",
-                                ifname, netns
-                            ))),
-                            InterfaceDescriptionOption::IfDescription(Cow::Owned(match ifindex {
-                                0 => "Fake interface".to_string(),
-                                _ => format!("ifindex={}", ifindex),
-                            })),
-                        ],
-                    }
-                    .into_block(),
-                )?;
-
-                let id = self.ifaces.len() as u32;
-                self.ifaces.insert(key, id);
-                id
-            }
-        };
-
-        // Add the packet itself.
-        self.writer.write_block(
-            &EnhancedPacketBlock {
-                interface_id: id,
-                timestamp: Duration::from_nanos(common.timestamp),
-                original_len: packet.len,
-                data: Cow::Borrowed(&packet.packet.0),
-                options: vec![EnhancedPacketOption::Comment(Cow::Owned(format!(
-                    "probe={}:{}",
-                    &kernel.probe_type, &kernel.symbol
-                )))],
-            }
-            .into_block(),
-        )?;
-
-        Ok(())
-    }
-
-    /// Report parser statistics. Should be called after processing was
-    /// completed.
-    fn report_stats(&self) {
-        info!("{} event(s) were processed", self.stats.processed);
-
-        if self.stats.missing_skb != 0 {
-            warn!(
-                "{} event(s) were skipped because of missing skb information",
-                self.stats.missing_skb
-            );
-        }
-        if self.stats.missing_packet != 0 {
-            warn!(
-                "{} event(s) were skipped because of missing raw packet",
-                self.stats.missing_packet
-            );
-        }
-        if self.stats.missing_dev != 0 {
-            warn!(
-                "{} event(s) are using a fake net device (no device information was found)",
-                self.stats.missing_dev
-            );
-        }
-        if self.stats.missing_ns != 0 {
-            warn!(
-                "{} event(s) are using a fake netns (no netns information was found)",
-                self.stats.missing_ns
-            );
-        }
-    }
+/// How to split the generated PCAP output in multiple files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum SplitBy {
+    /// One file per 5-tuple (protocol, addresses and ports).
+    Flow,
+    /// One file per series of events, as generated by the sort command.
+    Series,
 }
 
 /// Generate a PCAP file from stored events.
@@ -210,10 +43,22 @@ See `retis collect --help` for more details on the probe format."
     #[arg(
         short,
         long,
-        help = "Write the generated PCAP output to a file rather than stdio"
+        help = "Write the generated PCAP output to a file rather than stdio. When --split-by is
+used, this is the directory the PCAP files are written to."
     )]
     pub(super) out: Option<PathBuf>,
-    #[arg(default_value = "retis.data", help = "File from which to read events")]
+    #[arg(
+        long,
+        value_enum,
+        requires = "out",
+        help = "Write one PCAP file per flow (5-tuple) or per series of events in the --out
+directory. Packets without a flow or series are written to 'other.pcapng'."
+    )]
+    pub(super) split_by: Option<SplitBy>,
+    #[arg(
+        default_value = "retis.data",
+        help = "File from which to read events, `-` for the standard input"
+    )]
     pub(super) input: PathBuf,
 }
 
@@ -223,18 +68,21 @@ impl SubCommandParserRunner for Pcap {
         let symbol = Symbol::from_name_no_inspect(target);
 
         // Create a PCAP writer to push our events / metadata.
-        let mut writer = PcapNgWriter::new(match &self.out {
-            Some(file) => OpenOptions::new()
-                .create(true)
-                .write(true)
-                .truncate(true)
-                .open(file)
-                .or_else(|_| bail!("Could not create or open '{}'", file.display()))?,
-            None => OpenOptions::new()
-                .write(true)
-                .open("/proc/self/fd/1")
-                .or_else(|_| bail!("Could not open stdout"))?,
-        })?;
+        let mut output = match (self.split_by, &self.out) {
+            (Some(by), Some(dir)) => PcapOutput::split(by, dir)?,
+            (_, out) => PcapOutput::Single(EventParser::new(match out {
+                Some(file) => OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .truncate(true)
+                    .open(file)
+                    .or_else(|_| bail!("Could not create or open '{}'", file.display()))?,
+                None => OpenOptions::new()
+                    .write(true)
+                    .open("/proc/self/fd/1")
+                    .or_else(|_| bail!("Could not open stdout"))?,
+            })?),
+        };
 
         // Filtering logic.
         let filter = |r#type: &str, name: &str| -> bool {
@@ -244,43 +92,178 @@ impl SubCommandParserRunner for Pcap {
             false
         };
 
-        handle_events(
-            self.input.as_path(),
-            &filter,
-            &mut EventParser::from(&mut writer),
-        )
+        handle_events(self.input.as_path(), &filter, &mut output)
+    }
+}
+
+/// PCAP output, either a single file or one file per flow / series.
+enum PcapOutput {
+    Single(EventParser<File>),
+    Split {
+        by: SplitBy,
+        dir: PathBuf,
+        /// Adds series information to events.
+        tracker: AddTracking,
+        /// PCAP writers, indexed by their file name. Their files are
+        /// released when too many are open and reopened in append mode, the
+        /// PCAP header being only written when the file is created.
+        parsers: HashMap<String, EventParser<ReopenableFile>>,
+        /// PCAP writers currently holding an open file.
+        open: OpenFiles<String>,
+    },
+}
+
+impl PcapOutput {
+    fn split(by: SplitBy, dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .or_else(|e| bail!("Could not create directory '{}': {e}", dir.display()))?;
+
+        Ok(PcapOutput::Split {
+            by,
+            dir: dir.to_path_buf(),
+            tracker: AddTracking::new(),
+            parsers: HashMap::new(),
+            open: OpenFiles::default(),
+        })
+    }
+
+    /// Track an event. Must be called for all events, in order, before
+    /// filtering.
+    fn track(&mut self, event: &mut Event) -> Result<()> {
+        match self {
+            PcapOutput::Split {
+                by: SplitBy::Series,
+                tracker,
+                ..
+            } => tracker.process_one(event),
+            _ => Ok(()),
+        }
+    }
+
+    fn parse(&mut self, event: &Event) -> Result<()> {
+        match self {
+            PcapOutput::Single(parser) => parser.parse(event),
+            PcapOutput::Split {
+                by,
+                dir,
+                parsers,
+                open,
+                ..
+            } => {
+                let name = format!(
+                    "{}.pcapng",
+                    match by {
+                        SplitBy::Flow => flow_name(event),
+                        SplitBy::Series => series_name(event),
+                    }
+                    .unwrap_or_else(|| "other".to_string())
+                );
+
+                // Release the file of the least recently used parser, if too
+                // many are open.
+                if let Some(old) = open.used(name.clone()) {
+                    if let Some(old) = parsers.get_mut(&old) {
+                        old.flush()?;
+                    }
+                }
+
+                let parser = match parsers.entry(name) {
+                    Entry::Occupied(parser) => parser.into_mut(),
+                    Entry::Vacant(entry) => {
+                        let file = ReopenableFile::create(&dir.join(entry.key()))?;
+                        entry.insert(EventParser::new(file)?)
+                    }
+                };
+                parser.parse(event)
+            }
+        }
+    }
+
+    /// Flush the output and report statistics.
+    fn finish(&mut self) -> Result<()> {
+        match self {
+            PcapOutput::Single(parser) => {
+                parser.report_stats();
+                parser.flush()
+            }
+            PcapOutput::Split { dir, parsers, .. } => {
+                let mut stats = EventParserStats::default();
+                parsers.values_mut().try_for_each(|p| {
+                    stats.add(p.stats());
+                    p.flush()
+                })?;
+                stats.report();
+
+                info!("{} file(s) written to '{}'", parsers.len(), dir.display());
+                Ok(())
+            }
+        }
     }
 }
 
+/// Name of the flow (5-tuple) of an event, if any.
+pub(super) fn flow_name(event: &Event) -> Option<String> {
+    let skb = event.skb()?;
+    let ip = skb.ip.as_ref()?;
+
+    let (proto, ports) = match ip.protocol {
+        6 => (
+            "tcp".to_string(),
+            skb.tcp.as_ref().map(|tcp| (tcp.sport, tcp.dport)),
+        ),
+        17 => (
+            "udp".to_string(),
+            skb.udp.as_ref().map(|udp| (udp.sport, udp.dport)),
+        ),
+        132 => (
+            "sctp".to_string(),
+            skb.sctp.as_ref().map(|sctp| (sctp.sport, sctp.dport)),
+        ),
+        1 => ("icmp".to_string(), None),
+        58 => ("icmpv6".to_string(), None),
+        proto => (format!("proto{proto}"), None),
+    };
+
+    Some(match ports {
+        Some((sport, dport)) => format!("{proto}_{}-{sport}_{}-{dport}", ip.saddr, ip.daddr),
+        None => format!("{proto}_{}_{}", ip.saddr, ip.daddr),
+    })
+}
+
+/// Name of the series of an event, if any.
+fn series_name(event: &Event) -> Option<String> {
+    let info = event.tracking()?;
+    Some(format!("series_{:x}", info.skb.tracking_id()))
+}
+
 /// Internal logic to retrieve our events to feed the parser.
-fn handle_events<W>(
+fn handle_events(
     input: &Path,
     filter: &dyn Fn(&str, &str) -> bool,
-    parser: &mut EventParser<W>,
-) -> Result<()>
-where
-    W: Write,
-{
+    output: &mut PcapOutput,
+) -> Result<()> {
     // Create running instance that will handle signal termination.
     let run = Running::new();
     run.register_term_signals()?;
 
     // Start our events factory.
-    let mut factory = FileEventsFactory::new(input)?;
+    let mut factory = open_events(input)?;
 
     // See if we matched (not processed!) at least one event.
     let mut matched = false;
     while run.running() {
         match factory.next_event()? {
-            Some(event) => {
-                if let Some(kernel) = event.get_section::<KernelEvent>(SectionId::Kernel) {
+            Some(mut event) => {
+                output.track(&mut event)?;
+
+                if let Some(kernel) = event.kernel() {
                     // Check the event is matching the requested symbol.
                     if !filter(&kernel.probe_type, &kernel.symbol) {
                         continue;
                     }
                     matched = true;
 
-                    parser.parse(&event)?;
+                    output.parse(&event)?;
                 }
             }
             None => break,
@@ -291,6 +274,85 @@ where
         bail!("Probe not found in the events");
     }
 
-    parser.report_stats();
-    Ok(())
+    output.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use pcap_file::pcapng::{Block, PcapNgReader};
+
+    use super::*;
+    use crate::process::output::MAX_OPEN_FILES;
+
+    fn event(id: u64) -> Event {
+        let mut event = Event::new();
+        event
+            .insert_section(
+                SectionId::Common,
+                Box::new(CommonEvent {
+                    timestamp: id,
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        event
+            .insert_section(SectionId::Kernel, Box::new(KernelEvent::default()))
+            .unwrap();
+        event
+            .insert_section(
+                SectionId::Skb,
+                Box::new(SkbEvent {
+                    packet: Some(SkbPacketEvent {
+                        len: 4,
+                        capture_len: 4,
+                        packet: RawPacket(vec![0; 4]),
+                        l3_offset: None,
+                        l4_offset: None,
+                    }),
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        event
+            .insert_section(
+                SectionId::Tracking,
+                Box::new(TrackingInfo {
+                    skb: SkbTrackingEvent {
+                        orig_head: id,
+                        timestamp: 0,
+                        skb: id,
+                    },
+                    idx: 0,
+                }),
+            )
+            .unwrap();
+        event
+    }
+
+    #[test]
+    fn split_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut output = PcapOutput::split(SplitBy::Series, dir.path()).unwrap();
+
+        // Write to more files than can be kept open, so they are reopened.
+        (0..2).for_each(|_| {
+            (0..MAX_OPEN_FILES as u64 + 10).for_each(|id| output.parse(&event(id)).unwrap())
+        });
+        output.finish().unwrap();
+
+        let file = File::open(dir.path().join("series_0.pcapng")).unwrap();
+        let mut reader = PcapNgReader::new(file).unwrap();
+        let mut blocks = Vec::new();
+        while let Some(block) = reader.next_block() {
+            blocks.push(match block.unwrap() {
+                Block::SectionHeader(_) => "shb",
+                Block::InterfaceDescription(_) => "idb",
+                Block::EnhancedPacket(_) => "epb",
+                _ => "other",
+            });
+        }
+
+        // Only one section and one interface are described.
+        assert_eq!(blocks, vec!["idb", "epb", "epb"]);
+    }
 }
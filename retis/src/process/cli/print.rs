@@ -3,9 +3,12 @@
 //! Print is a simple post-processing command that just parses events and prints them back to
 //! stdout
 
-use std::{io::stdout, path::PathBuf};
+use std::{
+    io::{stdout, ErrorKind, Write},
+    path::PathBuf,
+};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use clap::Parser;
 
 use crate::{
@@ -14,66 +17,247 @@ use crate::{
         file::{FileEventsFactory, FileType},
         *,
     },
-    helpers::signals::Running,
-    process::display::*,
+    helpers::{
+        manifest::open_events,
+        pager::{try_enable_pager, PagerArgs},
+        signals::Running,
+    },
+    process::{
+        display::*, neigh::AddNeigh, output::OutputFile, severity::AddSeverity, stats::EventCounts,
+    },
 };
 
 /// Print stored events to stdout
+///
+/// Events can also be written to files in the same pass using --out, which is useful for big
+/// captures being expensive to read multiple times.
 #[derive(Parser, Debug, Default)]
 #[command(name = "print")]
 pub(crate) struct Print {
-    /// File from which to read events.
+    /// File from which to read events, `-` for the standard input.
     #[arg(default_value = "retis.data")]
     pub(super) input: PathBuf,
     #[arg(long, help = "Format used when printing an event.")]
     #[clap(value_enum, default_value_t=CliDisplayFormat::MultiLine)]
     pub(super) format: CliDisplayFormat,
+    #[arg(
+        long,
+        conflicts_with = "format",
+        help = "Format events using a template, e.g. '{common.timestamp} {kernel.symbol}'.
+Placeholders are keys of the flattened JSON representation of events (see --format json-flat).
+Fields missing in an event are left empty. '{{' and '}}' can be used for literal braces."
+    )]
+    pub(super) format_template: Option<EventTemplate>,
     #[arg(long, help = "Print the time as UTC")]
     pub(super) utc: bool,
+    #[arg(
+        long,
+        conflicts_with = "utc",
+        help = "Print the time relative to the first event (of each series, when printing sorted
+events), followed by the time elapsed since the previous event."
+    )]
+    pub(super) relative_time: bool,
+    #[arg(
+        long,
+        help = "Show the vendor of MAC addresses, when known. Requires Retis to be built with the
+'oui' feature."
+    )]
+    pub(super) oui: bool,
+    #[arg(
+        long,
+        help = "Add the current neighbor table state for the next hops of events related to a
+neighbor resolution failure (e.g. no ARP entry)."
+    )]
+    pub(super) neigh: bool,
+    #[arg(
+        long,
+        value_name = "LEVEL",
+        num_args = 0..=1,
+        default_missing_value = "info",
+        help = "Classify events by severity (info, warn or error) based on drop reasons, nft drop
+verdicts, TCP retransmissions and ICMP errors. Events with a warn or error severity are tagged. If
+LEVEL is given, only events (or series) having at least this severity are shown."
+    )]
+    pub(super) severity: Option<Severity>,
+    #[arg(
+        long,
+        value_name = "EXPR",
+        help = "Only show events matching an expression on their fields, e.g.
+'skb.ip.saddr == \"10.0.0.1\" && kernel.symbol ~ \"drop\"'. Fields are keys of the flattened JSON
+representation of events (see --format json-flat). When printing sorted events, series having at
+least one matching event are shown."
+    )]
+    pub(super) filter_expr: Option<EventFilter>,
+    #[arg(
+        short,
+        long,
+        help = "Also write events to a file. The format is deduced from the file extension: .pcap or
+.pcapng for PCAP-ng (events with a raw packet only), .csv for CSV and JSON otherwise. Can be used
+multiple times."
+    )]
+    pub(super) out: Vec<OutputFile>,
+    #[arg(
+        long,
+        help = "Once all events are printed, print the number of events per section, probe and drop
+reason. Only the printed events (e.g. after --severity filtering) are accounted."
+    )]
+    pub(super) footer: bool,
+    #[arg(
+        short,
+        long,
+        help = "Keep the input file open once all events are printed and print new events as they are
+appended (e.g. by a concurrently running `collect --out`), like `tail -f`. Stops on Ctrl-C."
+    )]
+    pub(super) follow: bool,
+    #[command(flatten)]
+    pub(super) pager: PagerArgs,
 }
 
 impl SubCommandParserRunner for Print {
     fn run(&mut self) -> Result<()> {
+        if self.oui && !oui::OUI_AVAILABLE {
+            bail!("MAC vendors lookup is not available, Retis was built without the 'oui' feature");
+        }
+
+        // Try setting up the pager. This must be done before any other thread
+        // is started.
+        try_enable_pager(&self.pager)?;
+
         // Create running instance that will handle signal termination.
         let run = Running::new();
         run.register_term_signals()?;
 
-        // Create event factory.
-        let mut factory = FileEventsFactory::new(self.input.as_path())?;
+        self.out
+            .iter()
+            .try_for_each(|o| o.check_not_input(&self.input))?;
+
+        // Create event factory. Followed files are still being written to, their
+        // manifest (if any) can't be verified.
+        let mut factory = match self.follow {
+            true => FileEventsFactory::follow(self.input.as_path(), {
+                let run = run.clone();
+                move || run.running()
+            })?,
+            false => open_events(self.input.as_path())?,
+        };
+
+        // Neighbor table correlation.
+        let neigh = match self.neigh {
+            true => Some(AddNeigh::new()?),
+            false => None,
+        };
+
+        // Severity classification, along with the minimum severity to show.
+        let severity = self.severity.map(|min| (AddSeverity::new(), min));
 
         // Format.
         let format = DisplayFormat::new()
-            .multiline(self.format == CliDisplayFormat::MultiLine)
-            .time_format(if self.utc {
-                TimeFormat::UtcDate
-            } else {
-                TimeFormat::MonotonicTimestamp
-            });
+            .time_format(match (self.utc, self.relative_time) {
+                (true, _) => TimeFormat::UtcDate,
+                (_, true) => TimeFormat::Relative,
+                _ => TimeFormat::MonotonicTimestamp,
+            })
+            .oui(self.oui);
+
+        // Counts of the printed events, for the footer.
+        let mut counts = match self.footer {
+            true => Some(EventCounts::new()),
+            false => None,
+        };
 
         match factory.file_type() {
             FileType::Event => {
                 // Formatter & printer for events.
-                let mut event_output =
-                    PrintEvent::new(Box::new(stdout()), PrintEventFormat::Text(format));
+                let mut event_output = PrintEvent::new(
+                    Box::new(stdout()),
+                    PrintEventFormat::from_cli(self.format, self.format_template.clone(), format),
+                );
+                let mut outputs = self
+                    .out
+                    .iter()
+                    .map(|o| o.open(false))
+                    .collect::<Result<Vec<_>>>()?;
 
                 while run.running() {
                     match factory.next_event()? {
-                        Some(event) => event_output.process_one(&event)?,
+                        Some(mut event) => {
+                            if let Some(neigh) = &neigh {
+                                neigh.process_one(&mut event)?;
+                            }
+                            if let Some((classifier, min)) = &severity {
+                                if classifier.process_one(&mut event)? < *min {
+                                    continue;
+                                }
+                            }
+                            if let Some(filter) = &self.filter_expr {
+                                if !filter.matches(&event) {
+                                    continue;
+                                }
+                            }
+                            event_output.process_one(&event)?;
+                            if let Some(counts) = &mut counts {
+                                counts.add(&event);
+                            }
+                            outputs.iter_mut().try_for_each(|o| o.write_event(&event))?;
+                        }
                         None => break,
                     }
                 }
+
+                outputs.iter_mut().try_for_each(|o| o.finish())?;
             }
             FileType::Series => {
                 // Formatter & printer for series.
-                let mut series_output =
-                    PrintSeries::new(Box::new(stdout()), PrintEventFormat::Text(format));
+                let mut series_output = PrintSeries::new(
+                    Box::new(stdout()),
+                    PrintEventFormat::from_cli(self.format, self.format_template.clone(), format),
+                );
+                let mut outputs = self
+                    .out
+                    .iter()
+                    .map(|o| o.open(true))
+                    .collect::<Result<Vec<_>>>()?;
 
                 while run.running() {
                     match factory.next_series()? {
-                        Some(series) => series_output.process_one(&series)?,
+                        Some(mut series) => {
+                            if let Some(neigh) = &neigh {
+                                series
+                                    .events
+                                    .iter_mut()
+                                    .try_for_each(|e| neigh.process_one(e))?;
+                            }
+                            if let Some((classifier, min)) = &severity {
+                                if classifier.process_series(&mut series)? < *min {
+                                    continue;
+                                }
+                            }
+                            if let Some(filter) = &self.filter_expr {
+                                if !filter.matches_series(&series) {
+                                    continue;
+                                }
+                            }
+                            series_output.process_one(&series)?;
+                            if let Some(counts) = &mut counts {
+                                series.events.iter().for_each(|e| counts.add(e));
+                            }
+                            outputs
+                                .iter_mut()
+                                .try_for_each(|o| o.write_series(&series))?;
+                        }
                         None => break,
                     }
                 }
+
+                outputs.iter_mut().try_for_each(|o| o.finish())?;
+            }
+        }
+
+        if let Some(counts) = counts {
+            if let Err(e) = stdout().write_all(format!("\n{}", counts.render()?).as_bytes()) {
+                if e.kind() != ErrorKind::BrokenPipe {
+                    return Err(e.into());
+                }
             }
         }
 
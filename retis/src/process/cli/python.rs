@@ -13,7 +13,7 @@ pub(crate) struct PythonCli {
         long,
         short,
         default_value = "retis.data",
-        help = "File from which to read events"
+        help = "File from which to read events, `-` for the standard input"
     )]
     pub(super) input: PathBuf,
     #[arg(help = "Python script to execute. Omit to drop into an interactive shell.")]
@@ -3,19 +3,34 @@
 //! Sort rearranges the events so they are grouped by skb tracking id (or OVS queue_id if present)
 
 use std::{
-    fs::OpenOptions,
-    io::{stdout, BufWriter},
-    path::PathBuf,
+    collections::HashMap,
+    fs,
+    io::stdout,
+    path::{Path, PathBuf},
+    str::FromStr,
 };
 
 use anyhow::{bail, Result};
 use clap::Parser;
+use log::info;
 
 use crate::{
     cli::*,
-    events::{file::FileEventsFactory, *},
-    helpers::signals::Running,
-    process::{display::*, series::EventSorter, tracking::AddTracking},
+    events::*,
+    helpers::{
+        manifest::open_events,
+        pager::{try_enable_pager, PagerArgs},
+        signals::Running,
+    },
+    process::{
+        cli::pcap::flow_name,
+        display::*,
+        neigh::AddNeigh,
+        output::{OpenFiles, Output, OutputFile},
+        series::EventSorter,
+        severity::AddSeverity,
+        tracking::AddTracking,
+    },
 };
 
 /// The default size of the sorting buffer
@@ -29,7 +44,7 @@ const DEFAULT_BUFFER: usize = 1000;
 #[derive(Parser, Debug, Default)]
 #[command(name = "sort")]
 pub(crate) struct Sort {
-    /// File from which to read events.
+    /// File from which to read events, `-` for the standard input.
     #[arg(default_value = "retis.data")]
     pub(super) input: PathBuf,
 
@@ -44,10 +59,25 @@ pub(crate) struct Sort {
     pub(super) max_buffer: usize,
 
     /// Write event series to a file rather than to stdout.
+    ///
+    /// The format is deduced from the file extension: .pcap or .pcapng for PCAP-ng (events with a
+    /// raw packet only), .csv for CSV and JSON otherwise. Can be used multiple times to generate
+    /// multiple outputs in a single pass.
     #[arg(short, long)]
-    pub(super) out: Option<PathBuf>,
+    pub(super) out: Vec<OutputFile>,
+
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        help = "Write each series to its own file, whose path is generated from PATTERN (e.g.
+'series-%d.json'): '%d' is replaced by the number of the series, '%t' by its tracking id, '%f' by
+its flow (5-tuple) and '%%' by '%'. Directories are created as needed, e.g. 'out/%f/%t.json'.
+Series written to the same path are appended. The format is deduced from the file extension, as for
+--out."
+    )]
+    pub(super) split_output: Option<SplitOutput>,
 
-    /// Write events to stdout even if --out is used.
+    /// Write events to stdout even if --out or --split-output is used.
     #[arg(long, default_value = "false")]
     pub(super) print: bool,
 
@@ -55,20 +85,76 @@ pub(crate) struct Sort {
     #[arg(long)]
     #[clap(value_enum, default_value_t=CliDisplayFormat::MultiLine)]
     pub(super) format: CliDisplayFormat,
+    #[arg(
+        long,
+        conflicts_with = "format",
+        help = "Format events using a template, e.g. '{common.timestamp} {kernel.symbol}'.
+Placeholders are keys of the flattened JSON representation of events (see --format json-flat).
+Fields missing in an event are left empty. '{{' and '}}' can be used for literal braces."
+    )]
+    pub(super) format_template: Option<EventTemplate>,
 
     /// Print the time as UTC.
     #[arg(long)]
     pub(super) utc: bool,
+
+    /// Print the time relative to the first event of each series, followed by the time elapsed
+    /// since the previous event.
+    #[arg(long, conflicts_with = "utc")]
+    pub(super) relative_time: bool,
+
+    /// Show the vendor of MAC addresses, when known. Requires Retis to be built with the 'oui'
+    /// feature.
+    #[arg(long)]
+    pub(super) oui: bool,
+
+    #[arg(
+        long,
+        help = "Add the current neighbor table state for the next hops of events related to a
+neighbor resolution failure (e.g. no ARP entry)."
+    )]
+    pub(super) neigh: bool,
+
+    #[arg(
+        long,
+        value_name = "LEVEL",
+        num_args = 0..=1,
+        default_missing_value = "info",
+        help = "Classify events by severity (info, warn or error) based on drop reasons, nft drop
+verdicts, TCP retransmissions and ICMP errors. Events with a warn or error severity are tagged. If
+LEVEL is given, only series having at least one event of this severity are shown."
+    )]
+    pub(super) severity: Option<Severity>,
+
+    #[arg(
+        long,
+        value_name = "EXPR",
+        help = "Only show series having at least one event matching an expression on its fields, e.g.
+'skb.ip.saddr == \"10.0.0.1\" && kernel.symbol ~ \"drop\"'. Fields are keys of the flattened JSON
+representation of events (see --format json-flat)."
+    )]
+    pub(super) filter_expr: Option<EventFilter>,
+
+    #[command(flatten)]
+    pub(super) pager: PagerArgs,
 }
 
 impl SubCommandParserRunner for Sort {
     fn run(&mut self) -> Result<()> {
+        if self.oui && !oui::OUI_AVAILABLE {
+            bail!("MAC vendors lookup is not available, Retis was built without the 'oui' feature");
+        }
+
+        // Try setting up the pager. This must be done before any other thread
+        // is started.
+        try_enable_pager(&self.pager)?;
+
         // Create running instance that will handle signal termination.
         let run = Running::new();
         run.register_term_signals()?;
 
         // Create event factory.
-        let mut factory = FileEventsFactory::new(self.input.as_path())?;
+        let mut factory = open_events(self.input.as_path())?;
 
         if matches!(factory.file_type(), file::FileType::Series) {
             log::info!("File already sorted");
@@ -77,56 +163,84 @@ impl SubCommandParserRunner for Sort {
 
         let mut series = EventSorter::new();
         let mut tracker = AddTracking::new();
-        let mut printers = Vec::new();
-
-        if let Some(out) = &self.out {
-            let out = match out.canonicalize() {
-                Ok(out) => out,
-                // If the file doesn't exist we can't use fs::canonicalize() but it is not needed
-                // as that means it is not the input file.
-                Err(_) => out.clone(),
-            };
-
-            // Make sure we don't use the same file as the result will be the deletion of the
-            // original files. If the input file doesn't exist we will raise an error.
-            if out.eq(&self.input.canonicalize()?) {
-                bail!("Cannot sort a file in-place. Please specify an output file that's different to the input one.");
-            }
+        self.out
+            .iter()
+            .try_for_each(|o| o.check_not_input(&self.input))?;
+        let mut outputs = self
+            .out
+            .iter()
+            .map(|o| o.open(true))
+            .collect::<Result<Vec<_>>>()?;
 
-            printers.push(PrintSeries::new(
-                Box::new(BufWriter::new(
-                    OpenOptions::new()
-                        .create(true)
-                        .write(true)
-                        .truncate(true)
-                        .open(&out)
-                        .or_else(|_| bail!("Could not create or open '{}'", out.display()))?,
-                )),
-                PrintEventFormat::Json,
-            ));
-        }
+        let mut split = self.split_output.clone().map(SplitWriter::new);
 
-        if self.out.is_none() || self.print {
+        let mut printer = None;
+        if (self.out.is_empty() && split.is_none()) || self.print {
             let format = DisplayFormat::new()
-                .multiline(self.format == CliDisplayFormat::MultiLine)
-                .time_format(if self.utc {
-                    TimeFormat::UtcDate
-                } else {
-                    TimeFormat::MonotonicTimestamp
-                });
-
-            printers.push(PrintSeries::new(
+                .time_format(match (self.utc, self.relative_time) {
+                    (true, _) => TimeFormat::UtcDate,
+                    (_, true) => TimeFormat::Relative,
+                    _ => TimeFormat::MonotonicTimestamp,
+                })
+                .oui(self.oui);
+
+            printer = Some(PrintSeries::new(
                 Box::new(stdout()),
-                PrintEventFormat::Text(format),
+                PrintEventFormat::from_cli(self.format, self.format_template.clone(), format),
             ));
         }
 
+        let neigh = match self.neigh {
+            true => Some(AddNeigh::new()?),
+            false => None,
+        };
+        let classifier = self.severity.map(|_| AddSeverity::new());
+        let min_severity = self.severity.unwrap_or(Severity::Info);
+
+        let mut write = |series: &EventSeries| -> Result<()> {
+            // Only show series having at least one event of the requested
+            // severity.
+            let severity = series
+                .iter()
+                .filter_map(|e| e.severity())
+                .map(|s| s.level)
+                .max()
+                .unwrap_or(Severity::Info);
+            if severity < min_severity {
+                return Ok(());
+            }
+
+            if let Some(filter) = &self.filter_expr {
+                if !filter.matches_series(series) {
+                    return Ok(());
+                }
+            }
+
+            if let Some(printer) = printer.as_mut() {
+                printer.process_one(series)?;
+            }
+            if let Some(split) = split.as_mut() {
+                split.write(series, &self.input)?;
+            }
+            outputs.iter_mut().try_for_each(|o| o.write_series(series))
+        };
+
         while run.running() {
             match factory.next_event()? {
                 Some(mut event) => {
                     // Add tracking information
                     tracker.process_one(&mut event)?;
 
+                    // Add the neighbor table state.
+                    if let Some(neigh) = &neigh {
+                        neigh.process_one(&mut event)?;
+                    }
+
+                    // Classify the event.
+                    if let Some(classifier) = &classifier {
+                        classifier.process_one(&mut event)?;
+                    }
+
                     // Add to sorter
                     series.add(event);
 
@@ -135,9 +249,7 @@ impl SubCommandParserRunner for Sort {
                         while series.len() >= self.max_buffer {
                             // Flush the oldest series
                             match series.pop_oldest()? {
-                                Some(series) => printers
-                                    .iter_mut()
-                                    .try_for_each(|p| p.process_one(&series))?,
+                                Some(series) => write(&series)?,
                                 None => break,
                             };
                         }
@@ -149,15 +261,216 @@ impl SubCommandParserRunner for Sort {
         // Flush remaining events
         while series.len() > 0 {
             match series.pop_oldest()? {
-                Some(series) => printers
-                    .iter_mut()
-                    .try_for_each(|p| p.process_one(&series))?,
+                Some(series) => write(&series)?,
                 None => break,
             };
         }
 
         // Flush writers
-        printers.iter_mut().try_for_each(|p| p.flush())?;
+        if let Some(printer) = printer.as_mut() {
+            printer.flush()?;
+        }
+        outputs.iter_mut().try_for_each(|o| o.finish())?;
+        if let Some(split) = split.as_mut() {
+            split.finish()?;
+        }
         Ok(())
     }
 }
+
+/// Pattern of the paths series are written to, see `--split-output`.
+#[derive(Clone, Debug)]
+pub(crate) struct SplitOutput(String);
+
+impl FromStr for SplitOutput {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut placeholder = false;
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                continue;
+            }
+            match chars.next() {
+                Some('d' | 't' | 'f') => placeholder = true,
+                Some('%') => (),
+                _ => bail!("Invalid placeholder in '{s}', only %d, %t, %f and %% are supported"),
+            }
+        }
+
+        if !placeholder {
+            bail!("'{s}' must contain at least one of the %d, %t or %f placeholders");
+        }
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl SplitOutput {
+    /// Path of the file a series is written to, `index` being the number of
+    /// the series in the output.
+    fn path(&self, index: u64, series: &EventSeries) -> String {
+        let mut path = String::new();
+        let mut chars = self.0.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                path.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('d') => path.push_str(&index.to_string()),
+                Some('t') => path.push_str(
+                    &series
+                        .tracking_id()
+                        .map(|id| format!("{id:x}"))
+                        .unwrap_or_else(|| "other".to_string()),
+                ),
+                Some('f') => path.push_str(
+                    &series
+                        .iter()
+                        .find_map(flow_name)
+                        .unwrap_or_else(|| "other".to_string()),
+                ),
+                _ => path.push('%'),
+            }
+        }
+        path
+    }
+
+    /// Are all series written to their own path.
+    fn is_unique(&self) -> bool {
+        self.0.replace("%%", "").contains("%d")
+    }
+}
+
+/// Writes series to their own file, see `--split-output`.
+struct SplitWriter {
+    pattern: SplitOutput,
+    /// Number of series written.
+    count: u64,
+    /// Outputs series can still be written to, indexed by their path.
+    outputs: HashMap<String, Output>,
+    /// Outputs currently holding an open file.
+    open: OpenFiles<String>,
+    /// Number of files written.
+    files: u64,
+}
+
+impl SplitWriter {
+    fn new(pattern: SplitOutput) -> Self {
+        Self {
+            pattern,
+            count: 0,
+            outputs: HashMap::new(),
+            open: OpenFiles::default(),
+            files: 0,
+        }
+    }
+
+    fn write(&mut self, series: &EventSeries, input: &Path) -> Result<()> {
+        let path = self.pattern.path(self.count, series);
+        self.count += 1;
+
+        let mut output = match self.outputs.remove(&path) {
+            Some(output) => output,
+            None => self.open(&path, input)?,
+        };
+        output.write_series(series)?;
+
+        // Outputs are kept only if other series can be written to them.
+        if self.pattern.is_unique() {
+            return output.finish();
+        }
+
+        // Release the file of the least recently used output, if too many are
+        // open. It is reopened in append mode if needed.
+        if let Some(old) = self.open.used(path.clone()) {
+            if let Some(old) = self.outputs.get_mut(&old) {
+                old.flush()?;
+            }
+        }
+        self.outputs.insert(path, output);
+        Ok(())
+    }
+
+    fn open(&mut self, path: &str, input: &Path) -> Result<Output> {
+        let file = OutputFile::from_str(path)?;
+        file.check_not_input(input)?;
+        if let Some(dir) = file.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .or_else(|e| bail!("Could not create directory '{}': {e}", dir.display()))?;
+        }
+
+        self.files += 1;
+        file.open(true)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.outputs.values_mut().try_for_each(|o| o.finish())?;
+        info!("{} series written to {} file(s)", self.count, self.files);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_output() {
+        assert!(SplitOutput::from_str("series.json").is_err());
+        assert!(SplitOutput::from_str("series-%x.json").is_err());
+        assert!(SplitOutput::from_str("series-%%d.json").is_err());
+
+        let series = EventSeries::default();
+        let pattern = SplitOutput::from_str("out/%t/%%-%d.json").unwrap();
+        assert_eq!(pattern.path(3, &series), "out/other/%-3.json");
+        assert!(pattern.is_unique());
+
+        let pattern = SplitOutput::from_str("%f.pcapng").unwrap();
+        assert_eq!(pattern.path(3, &series), "other.pcapng");
+        assert!(!pattern.is_unique());
+    }
+
+    #[test]
+    fn split_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let pattern = SplitOutput::from_str(&format!("{}/%t.json", dir.path().display())).unwrap();
+        let mut split = SplitWriter::new(pattern);
+
+        let series = |id: u64| {
+            let mut event = Event::new();
+            event
+                .insert_section(
+                    SectionId::Tracking,
+                    Box::new(TrackingInfo {
+                        skb: SkbTrackingEvent {
+                            orig_head: id,
+                            timestamp: 0,
+                            skb: id,
+                        },
+                        idx: 0,
+                    }),
+                )
+                .unwrap();
+            EventSeries {
+                events: vec![event],
+            }
+        };
+
+        // Write more series than the usual file descriptors limit, then write
+        // to the files again once they were released.
+        (0..1100)
+            .try_for_each(|id| split.write(&series(id), Path::new("-")))
+            .unwrap();
+        (0..1100)
+            .try_for_each(|id| split.write(&series(id), Path::new("-")))
+            .unwrap();
+        split.finish().unwrap();
+
+        assert_eq!(split.files, 1100);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1100);
+        let content = fs::read_to_string(dir.path().join("2a.json")).unwrap();
+        assert_eq!(content.lines().count(), 2);
+    }
+}
@@ -0,0 +1,86 @@
+//! # Stats
+//!
+//! Stats summarizes the drops found in events and correlates them with the
+//! network stack counters.
+
+use std::{
+    io::{stdout, ErrorKind, Write},
+    path::PathBuf,
+};
+
+use anyhow::Result;
+use clap::Parser;
+
+use crate::{
+    cli::*,
+    events::file::FileType,
+    helpers::{
+        manifest::open_events,
+        pager::{try_enable_pager, PagerArgs},
+        signals::Running,
+    },
+    process::stats::EventStats,
+};
+
+/// Summarize drops, correlate them with the network stack counters and report the size of sections.
+///
+/// Reads events from the INPUT file, counts the drops per drop reason and, if the collection was
+/// done using `--netstat`, shows them next to the network stack counters which changed during the
+/// collection (e.g. TcpExtListenDrops vs TCP_LISTEN_OVERFLOW drops). Differences point to drops
+/// which were not captured, or not accounted in the counters. The space used by each section in the
+/// file is also reported, packet payloads and stack traces being accounted separately. For sorted
+/// files (see `retis sort`), the latency between all adjacent probes of the series is summarized,
+/// the slowest hops first.
+#[derive(Parser, Debug, Default)]
+#[command(name = "stats")]
+pub(crate) struct Stats {
+    /// File from which to read events, `-` for the standard input.
+    #[arg(default_value = "retis.data")]
+    pub(super) input: PathBuf,
+
+    #[command(flatten)]
+    pub(super) pager: PagerArgs,
+}
+
+impl SubCommandParserRunner for Stats {
+    fn run(&mut self) -> Result<()> {
+        // Try setting up the pager. This must be done before any other thread
+        // is started.
+        try_enable_pager(&self.pager)?;
+
+        // Create running instance that will handle signal termination.
+        let run = Running::new();
+        run.register_term_signals()?;
+
+        // Create event factory.
+        let mut factory = open_events(self.input.as_path())?;
+        let mut stats = EventStats::new();
+
+        match factory.file_type() {
+            FileType::Event => {
+                while run.running() {
+                    match factory.next_event()? {
+                        Some(event) => stats.add(&event),
+                        None => break,
+                    }
+                }
+            }
+            FileType::Series => {
+                while run.running() {
+                    match factory.next_series()? {
+                        Some(series) => stats.add_series(&series),
+                        None => break,
+                    }
+                }
+            }
+        }
+
+        if let Err(e) = stdout().write_all(stats.render()?.as_bytes()) {
+            if e.kind() != ErrorKind::BrokenPipe {
+                return Err(e.into());
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+//! # Timeline
+//!
+//! Timeline renders a merged, time-bucketed view of control-plane and datapath
+//! events.
+
+use std::{
+    io::{stdout, ErrorKind, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use clap::Parser;
+
+use crate::{
+    cli::*,
+    events::{file::FileType, *},
+    helpers::{
+        manifest::open_events,
+        pager::{try_enable_pager, PagerArgs},
+        signals::Running,
+        time::parse_duration_with_unit,
+    },
+    process::timeline::{EventTimeline, TimelineKind},
+};
+
+/// Show a timeline of control-plane and datapath events.
+///
+/// Reads events from the INPUT file and groups them in time buckets. Control-plane events (netlink
+/// messages) are shown individually while datapath events (drops, upcalls) are aggregated per
+/// bucket. This helps finding causality between events, e.g. drops starting right after a route
+/// change. Buckets without any event are not shown.
+#[derive(Parser, Debug, Default)]
+#[command(name = "timeline")]
+pub(crate) struct Timeline {
+    /// File from which to read events, `-` for the standard input.
+    #[arg(default_value = "retis.data")]
+    pub(super) input: PathBuf,
+
+    /// Size of the time buckets, in milliseconds or using units (e.g. 10ms, 1s, 1m).
+    #[arg(long, value_name = "DURATION", default_value = "1s", value_parser = parse_bucket)]
+    pub(super) bucket: Duration,
+
+    /// Comma separated list of the kinds of events to show.
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "netlink,drop,upcall"
+    )]
+    pub(super) kinds: Vec<TimelineKind>,
+
+    /// Print the time as UTC.
+    #[arg(long)]
+    pub(super) utc: bool,
+
+    #[command(flatten)]
+    pub(super) pager: PagerArgs,
+}
+
+impl SubCommandParserRunner for Timeline {
+    fn run(&mut self) -> Result<()> {
+        // Try setting up the pager. This must be done before any other thread
+        // is started.
+        try_enable_pager(&self.pager)?;
+
+        // Create running instance that will handle signal termination.
+        let run = Running::new();
+        run.register_term_signals()?;
+
+        // Create event factory.
+        let mut factory = open_events(self.input.as_path())?;
+
+        let format = DisplayFormat::new().time_format(if self.utc {
+            TimeFormat::UtcDate
+        } else {
+            TimeFormat::MonotonicTimestamp
+        });
+        let mut timeline =
+            EventTimeline::new(self.bucket.as_nanos() as u64, self.kinds.clone(), format);
+
+        // Events have to be all read before rendering the timeline, as they
+        // are not ordered.
+        match factory.file_type() {
+            FileType::Event => {
+                while run.running() {
+                    match factory.next_event()? {
+                        Some(event) => timeline.add(&event),
+                        None => break,
+                    }
+                }
+            }
+            FileType::Series => {
+                while run.running() {
+                    match factory.next_series()? {
+                        Some(series) => series.events.iter().for_each(|e| timeline.add(e)),
+                        None => break,
+                    }
+                }
+            }
+        }
+
+        if let Err(e) = stdout().write_all(timeline.render()?.as_bytes()) {
+            if e.kind() != ErrorKind::BrokenPipe {
+                return Err(e.into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse the bucket size, numbers without a unit being milliseconds.
+fn parse_bucket(bucket: &str) -> Result<Duration> {
+    match parse_duration_with_unit(bucket, Duration::from_millis)? {
+        bucket if bucket.is_zero() => bail!("Bucket size must be greater than zero"),
+        bucket => Ok(bucket),
+    }
+}
@@ -2,7 +2,7 @@ use std::io::{ErrorKind, Write};
 
 use anyhow::Result;
 
-use crate::events::*;
+use crate::{cli::CliDisplayFormat, events::*};
 
 /// Select the format to follow when printing events with `PrintEvent`.
 pub(crate) enum PrintEventFormat {
@@ -11,6 +11,32 @@ pub(crate) enum PrintEventFormat {
     Text(DisplayFormat),
     /// Json: display the event as JSON.
     Json,
+    /// JsonFlat: display the event as single-level JSON, using dotted keys.
+    JsonFlat,
+    /// Template(template): display the event following a user-provided
+    /// template.
+    Template(EventTemplate),
+}
+
+impl PrintEventFormat {
+    /// Select the format matching the ones given on the command line. A
+    /// template takes precedence over `format`. `display` is used for text
+    /// formats.
+    pub(crate) fn from_cli(
+        format: CliDisplayFormat,
+        template: Option<EventTemplate>,
+        display: DisplayFormat,
+    ) -> Self {
+        if let Some(template) = template {
+            return PrintEventFormat::Template(template);
+        }
+
+        match format {
+            CliDisplayFormat::SingleLine => PrintEventFormat::Text(display.multiline(false)),
+            CliDisplayFormat::MultiLine => PrintEventFormat::Text(display.multiline(true)),
+            CliDisplayFormat::JsonFlat => PrintEventFormat::JsonFlat,
+        }
+    }
 }
 
 /// Handles event individually and write to a `Write`.
@@ -24,15 +50,24 @@ impl PrintEvent {
         Self { writer, format }
     }
 
+    /// Change the format used for the next events.
+    pub(crate) fn set_format(&mut self, format: PrintEventFormat) {
+        self.format = format;
+    }
+
     /// Process events one by one (format & print).
     pub(crate) fn process_one(&mut self, e: &Event) -> Result<()> {
         match self.format {
             PrintEventFormat::Text(ref mut format) => {
-                if let Some(common) = e.get_section::<StartupEvent>(SectionId::Startup) {
-                    format.monotonic_offset = Some(common.clock_monotonic_offset);
+                format.update_clock(e);
+                // Clock synchronization events carry no information for the
+                // user, only used for displaying the time.
+                if e.clock_sync().is_some() {
+                    return Ok(());
                 }
 
                 let mut event = format!("{}", e.display(format, &FormatterConf::new()));
+                format.update_relative_time(e);
                 if !event.is_empty() {
                     event.push('\n');
                     if format.multiline {
@@ -45,8 +80,27 @@ impl PrintEvent {
                     }
                 }
             }
-            PrintEventFormat::Json => {
-                let mut event = serde_json::to_vec(&e.to_json())?;
+            PrintEventFormat::Template(ref template) => {
+                // Only report actual events, not startup or clock
+                // synchronization ones.
+                if e.common().is_none() {
+                    return Ok(());
+                }
+
+                let mut event = template.render(e);
+                event.push('\n');
+                if let Err(e) = self.writer.write_all(event.as_bytes()) {
+                    if e.kind() != ErrorKind::BrokenPipe {
+                        return Err(e.into());
+                    }
+                }
+            }
+            PrintEventFormat::Json | PrintEventFormat::JsonFlat => {
+                let json = match self.format {
+                    PrintEventFormat::JsonFlat => e.to_json_flat(),
+                    _ => e.to_json(),
+                };
+                let mut event = serde_json::to_vec(&json)?;
                 event.push(b'\n');
                 self.writer.write_all(&event)?;
             }
@@ -79,13 +133,16 @@ impl PrintSeries {
             PrintEventFormat::Text(ref mut format) => {
                 let mut fconf = FormatterConf::new();
                 let mut first = true;
+                format.reset_relative_time();
 
                 for event in series.events.iter() {
-                    if let Some(common) = event.get_section::<StartupEvent>(SectionId::Startup) {
-                        format.monotonic_offset = Some(common.clock_monotonic_offset);
+                    format.update_clock(event);
+                    if event.clock_sync().is_some() {
+                        continue;
                     }
 
                     content.push_str(&format!("{}", event.display(format, &fconf)));
+                    format.update_relative_time(event);
                     if !content.is_empty() {
                         content.push('\n');
                         if first {
@@ -106,8 +163,33 @@ impl PrintSeries {
                     }
                 }
             }
-            PrintEventFormat::Json => {
-                let mut event = serde_json::to_vec(&series.to_json())?;
+            PrintEventFormat::Template(ref template) => {
+                series
+                    .events
+                    .iter()
+                    .filter(|e| e.common().is_some())
+                    .for_each(|e| {
+                        content.push_str(&template.render(e));
+                        content.push('\n');
+                    });
+
+                // Series are separated by an empty line.
+                if !content.is_empty() {
+                    content.push('\n');
+
+                    if let Err(e) = self.writer.write_all(content.as_bytes()) {
+                        if e.kind() != ErrorKind::BrokenPipe {
+                            return Err(e.into());
+                        }
+                    }
+                }
+            }
+            PrintEventFormat::Json | PrintEventFormat::JsonFlat => {
+                let json = match self.format {
+                    PrintEventFormat::JsonFlat => series.to_json_flat(),
+                    _ => series.to_json(),
+                };
+                let mut event = serde_json::to_vec(&json)?;
                 event.push(b'\n');
                 self.writer.write_all(&event)?;
             }
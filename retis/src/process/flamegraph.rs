@@ -0,0 +1,364 @@
+//! # Flamegraph
+//!
+//! Aggregates the stack traces reported in events and renders them either in
+//! the folded format (one `root;..;leaf count` line per unique stack, as used
+//! by most flamegraph tools) or directly as an SVG flamegraph. This helps
+//! visualizing where in the kernel packets are dropped.
+
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet},
+    fmt::Write,
+    hash::{Hash, Hasher},
+};
+
+use anyhow::Result;
+use clap::ValueEnum;
+
+use crate::events::*;
+
+/// What a stack trace is weighted by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum FlamegraphWeight {
+    /// Number of events.
+    #[default]
+    Events,
+    /// Number of unique packets, as identified by the skb-tracking collector.
+    /// Events without tracking information are counted individually.
+    Packets,
+}
+
+/// Occurrences of a single folded stack.
+#[derive(Default)]
+struct StackCount {
+    /// Number of events.
+    events: u64,
+    /// Unique packets (tracking ids).
+    packets: HashSet<u128>,
+    /// Events without tracking information.
+    untracked: u64,
+}
+
+/// Aggregates stack traces found in events.
+pub(crate) struct StackAggregator {
+    weight: FlamegraphWeight,
+    /// Only consider events reporting a drop.
+    drops_only: bool,
+    /// Add the drop reason as the root frame of stacks.
+    by_reason: bool,
+    /// Occurrences, indexed by folded stack.
+    stacks: HashMap<String, StackCount>,
+}
+
+impl StackAggregator {
+    pub(crate) fn new(weight: FlamegraphWeight, drops_only: bool, by_reason: bool) -> Self {
+        Self {
+            weight,
+            drops_only,
+            by_reason,
+            stacks: HashMap::new(),
+        }
+    }
+
+    /// Add the stack trace of an event, if any.
+    pub(crate) fn add(&mut self, event: &Event) {
+        let stack = match event
+            .kernel()
+            .and_then(|kernel| kernel.stack_trace.as_ref())
+        {
+            Some(stack) if !stack.raw().is_empty() => stack,
+            _ => return,
+        };
+
+        let drop = event.skb_drop();
+        if self.drops_only && drop.is_none() {
+            return;
+        }
+
+        // Stack traces are reported from the innermost function, while folded
+        // stacks start from the root. Offsets are not relevant here.
+        let mut frames = Vec::new();
+        if self.by_reason {
+            frames.push(match drop {
+                Some(drop) => match &drop.subsys {
+                    Some(subsys) => format!("{subsys}/{}", drop.drop_reason),
+                    None => drop.drop_reason.clone(),
+                },
+                None => "no drop".to_string(),
+            });
+        }
+        frames.extend(stack.raw().iter().rev().map(|frame| {
+            frame
+                .split_once('+')
+                .map(|(func, _)| func)
+                .unwrap_or(frame)
+                // ';' is the frame separator and ' ' the count one.
+                .replace([';', ' '], "_")
+        }));
+
+        let count = self.stacks.entry(frames.join(";")).or_default();
+        count.events += 1;
+        match event.skb_tracking() {
+            Some(tracking) => {
+                count.packets.insert(tracking.tracking_id());
+            }
+            None => count.untracked += 1,
+        }
+    }
+
+    fn weight(&self, count: &StackCount) -> u64 {
+        match self.weight {
+            FlamegraphWeight::Events => count.events,
+            FlamegraphWeight::Packets => count.packets.len() as u64 + count.untracked,
+        }
+    }
+
+    /// Folded stacks and their weight, sorted by stack.
+    fn folded_stacks(&self) -> BTreeMap<&str, u64> {
+        self.stacks
+            .iter()
+            .map(|(stack, count)| (stack.as_str(), self.weight(count)))
+            .collect()
+    }
+
+    /// Render the aggregated stacks in the folded format.
+    pub(crate) fn folded(&self) -> String {
+        self.folded_stacks()
+            .iter()
+            .fold(String::new(), |mut out, (stack, weight)| {
+                let _ = writeln!(out, "{stack} {weight}");
+                out
+            })
+    }
+
+    /// Render the aggregated stacks as an SVG flamegraph.
+    pub(crate) fn svg(&self, title: &str) -> Result<String> {
+        let mut root = Frame::default();
+        self.folded_stacks().iter().for_each(|(stack, weight)| {
+            root.value += weight;
+            stack.split(';').fold(&mut root, |frame, name| {
+                let child = frame.children.entry(name.to_string()).or_default();
+                child.value += weight;
+                child
+            });
+        });
+
+        let depth = root.depth();
+        let height = SVG_TOP + depth as f64 * FRAME_HEIGHT + SVG_PADDING;
+
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r##"<?xml version="1.0" standalone="no"?>
+<svg version="1.1" width="{SVG_WIDTH}" height="{height}" xmlns="http://www.w3.org/2000/svg">
+<style>text {{ font-family: monospace; font-size: 12px; }} rect:hover {{ stroke: black; }}</style>
+<rect x="0" y="0" width="{SVG_WIDTH}" height="{height}" fill="#f8f8f8"/>
+<text x="{}" y="24" text-anchor="middle" font-size="16">{}</text>"##,
+            SVG_WIDTH / 2.0,
+            escape(title),
+        )?;
+
+        if root.value == 0 {
+            writeln!(
+                svg,
+                r#"<text x="{}" y="{}" text-anchor="middle">No stack trace found</text>"#,
+                SVG_WIDTH / 2.0,
+                SVG_TOP + FRAME_HEIGHT,
+            )?;
+        } else {
+            let scale = (SVG_WIDTH - 2.0 * SVG_PADDING) / root.value as f64;
+            let mut x = SVG_PADDING;
+            for (name, frame) in root.children.iter() {
+                frame.render(&mut svg, name, x, height - SVG_PADDING, scale, root.value)?;
+                x += frame.value as f64 * scale;
+            }
+        }
+
+        writeln!(svg, "</svg>")?;
+        Ok(svg)
+    }
+}
+
+const SVG_WIDTH: f64 = 1200.0;
+const SVG_TOP: f64 = 40.0;
+const SVG_PADDING: f64 = 10.0;
+const FRAME_HEIGHT: f64 = 16.0;
+/// Approximate width of a character, for a 12px monospace font.
+const CHAR_WIDTH: f64 = 7.2;
+
+/// Node of the flamegraph.
+#[derive(Default)]
+struct Frame {
+    /// Sum of the weights of all stacks going through this frame.
+    value: u64,
+    children: BTreeMap<String, Frame>,
+}
+
+impl Frame {
+    fn depth(&self) -> usize {
+        self.children
+            .values()
+            .map(|child| child.depth() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Render the frame and its children. `bottom` is the y coordinate of the
+    /// bottom of the frame, as roots are at the bottom of the graph.
+    fn render(
+        &self,
+        svg: &mut String,
+        name: &str,
+        x: f64,
+        bottom: f64,
+        scale: f64,
+        total: u64,
+    ) -> Result<()> {
+        let width = self.value as f64 * scale;
+        // Do not bother rendering frames too small to be seen.
+        if width < 0.1 {
+            return Ok(());
+        }
+        let y = bottom - FRAME_HEIGHT;
+
+        let label = match (width / CHAR_WIDTH) as usize {
+            n if n < 3 => String::new(),
+            n if n < name.chars().count() => {
+                format!("{}..", name.chars().take(n - 2).collect::<String>())
+            }
+            _ => name.to_string(),
+        };
+
+        writeln!(
+            svg,
+            r#"<g><title>{} ({} samples, {:.2}%)</title>
+<rect x="{x:.1}" y="{y:.1}" width="{width:.1}" height="{:.1}" fill="{}" rx="2"/>
+<text x="{:.1}" y="{:.1}">{}</text></g>"#,
+            escape(name),
+            self.value,
+            self.value as f64 * 100.0 / total as f64,
+            FRAME_HEIGHT - 1.0,
+            color(name),
+            x + 3.0,
+            y + FRAME_HEIGHT - 4.0,
+            escape(&label),
+        )?;
+
+        let mut x = x;
+        for (name, child) in self.children.iter() {
+            child.render(svg, name, x, y, scale, total)?;
+            x += child.value as f64 * scale;
+        }
+        Ok(())
+    }
+}
+
+/// Warm color derived from the frame name, so a function always has the same
+/// color.
+fn color(name: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    let hash = hasher.finish();
+
+    format!(
+        "rgb({},{},{})",
+        205 + (hash % 50),
+        (hash >> 8) % 230,
+        (hash >> 16) % 55
+    )
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(stack: &[&str], reason: Option<&str>, tracking: Option<u64>) -> Event {
+        let mut event = Event::new();
+        event
+            .insert_section(
+                SectionId::Kernel,
+                Box::new(KernelEvent {
+                    symbol: "kfree_skb".to_string(),
+                    probe_type: "raw_tracepoint".to_string(),
+                    stack_trace: Some(StackTrace(stack.iter().map(|s| s.to_string()).collect())),
+                }),
+            )
+            .unwrap();
+        if let Some(reason) = reason {
+            event
+                .insert_section(
+                    SectionId::SkbDrop,
+                    Box::new(SkbDropEvent {
+                        subsys: None,
+                        drop_reason: reason.to_string(),
+                    }),
+                )
+                .unwrap();
+        }
+        if let Some(head) = tracking {
+            event
+                .insert_section(
+                    SectionId::SkbTracking,
+                    Box::new(SkbTrackingEvent {
+                        orig_head: head,
+                        timestamp: 0,
+                        skb: 0,
+                    }),
+                )
+                .unwrap();
+        }
+        event
+    }
+
+    fn aggregate(agg: &mut StackAggregator) {
+        let stack = [
+            "kfree_skb_reason+0x10",
+            "ip_rcv+0x42",
+            "netif_receive_skb+0x8",
+        ];
+        agg.add(&event(&stack, Some("NO_SOCKET"), Some(1)));
+        agg.add(&event(&stack, Some("NO_SOCKET"), Some(1)));
+        agg.add(&event(&stack, Some("NO_SOCKET"), None));
+        agg.add(&event(&["consume_skb+0x4", "tcp_rcv+0x1"], None, Some(2)));
+        agg.add(&event(&[], None, None));
+    }
+
+    #[test]
+    fn folded() {
+        let mut agg = StackAggregator::new(FlamegraphWeight::Events, false, false);
+        aggregate(&mut agg);
+        assert_eq!(
+            agg.folded(),
+            "netif_receive_skb;ip_rcv;kfree_skb_reason 3\ntcp_rcv;consume_skb 1\n"
+        );
+
+        let mut agg = StackAggregator::new(FlamegraphWeight::Packets, true, true);
+        aggregate(&mut agg);
+        assert_eq!(
+            agg.folded(),
+            "NO_SOCKET;netif_receive_skb;ip_rcv;kfree_skb_reason 2\n"
+        );
+    }
+
+    #[test]
+    fn svg() {
+        let mut agg = StackAggregator::new(FlamegraphWeight::Events, false, false);
+        aggregate(&mut agg);
+        let svg = agg.svg("<drops>").unwrap();
+
+        assert!(svg.starts_with("<?xml"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains("&lt;drops&gt;"));
+        assert!(svg.contains("<title>kfree_skb_reason (3 samples, 75.00%)</title>"));
+        assert!(svg.contains("<title>tcp_rcv (1 samples, 25.00%)</title>"));
+
+        let agg = StackAggregator::new(FlamegraphWeight::Events, false, false);
+        assert!(agg.svg("").unwrap().contains("No stack trace found"));
+    }
+}
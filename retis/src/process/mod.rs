@@ -4,6 +4,14 @@
 
 pub(crate) mod cli;
 
+pub(crate) mod analyze;
 pub(crate) mod display;
+pub(crate) mod flamegraph;
+pub(crate) mod neigh;
+pub(crate) mod output;
+pub(crate) mod pcap;
 pub(crate) mod series;
+pub(crate) mod severity;
+pub(crate) mod stats;
+pub(crate) mod timeline;
 pub(crate) mod tracking;
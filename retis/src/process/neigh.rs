@@ -0,0 +1,493 @@
+//! Neighbor processor.
+//!
+//! Events related to a neighbor resolution failure (e.g. a packet dropped
+//! because no ARP or NDISC reply was received) are hard to make sense of
+//! without knowing the neighbor table state. The neighbor processor reads the
+//! neighbor and main routing tables of the current network namespace once and
+//! inserts a NeighEvent section into such events, with the entries matching
+//! the next hops of the packet destination.
+
+use std::{ffi::CStr, net::IpAddr, str::FromStr};
+
+use anyhow::Result;
+
+use crate::{
+    events::*,
+    helpers::{
+        net::parse_eth_addr,
+        netlink::{parse_attrs, NlSocket},
+    },
+};
+
+/// Drop reasons reported on neighbor resolution failures.
+const NEIGH_DROP_REASONS: &[&str] = &[
+    "NEIGH_CREATEFAIL",
+    "NEIGH_FAILED",
+    "NEIGH_QUEUEFULL",
+    "NEIGH_DEAD",
+];
+
+/// Kernel functions found in the stack trace of packets freed because their
+/// neighbor could not be resolved.
+const NEIGH_FAILURE_FUNCS: &[&str] = &[
+    "neigh_invalidate",
+    "neigh_timer_handler",
+    "arp_error_report",
+    "ndisc_error_report",
+];
+
+// Neighbor message header size, see struct ndmsg in the kernel uapi.
+const NDMSG_LEN: usize = 12;
+// Route message header size, see struct rtmsg in the kernel uapi.
+const RTMSG_LEN: usize = 12;
+// Route next hop header size, see struct rtnexthop in the kernel uapi.
+const RTNH_LEN: usize = 8;
+
+/// Unicast route of the main table.
+#[derive(Debug, PartialEq)]
+struct Route {
+    dst: IpAddr,
+    prefix_len: u8,
+    priority: u32,
+    /// Gateways of the route, empty if the destination is on-link.
+    gateways: Vec<IpAddr>,
+}
+
+impl Route {
+    /// Check if an address is part of the route destination.
+    fn contains(&self, addr: &IpAddr) -> bool {
+        let (dst, addr, len) = match (self.dst, addr) {
+            (IpAddr::V4(dst), IpAddr::V4(addr)) => (
+                u32::from(dst) as u128,
+                u32::from(*addr) as u128,
+                self.prefix_len as u32 + 96,
+            ),
+            (IpAddr::V6(dst), IpAddr::V6(addr)) => {
+                (u128::from(dst), u128::from(*addr), self.prefix_len as u32)
+            }
+            _ => return false,
+        };
+
+        let mask = u128::MAX.checked_shl(128 - len).unwrap_or(0);
+        dst & mask == addr & mask
+    }
+}
+
+/// AddNeigh inserts a NeighEvent section into events related to a neighbor
+/// resolution failure.
+pub(crate) struct AddNeigh {
+    /// Snapshot of the neighbor table.
+    table: Vec<(IpAddr, NeighEntry)>,
+    /// Snapshot of the main routing table.
+    routes: Vec<Route>,
+}
+
+impl AddNeigh {
+    /// Create a new neighbor processor, reading the neighbor and main routing
+    /// tables.
+    pub(crate) fn new() -> Result<Self> {
+        Ok(AddNeigh {
+            table: dump_neigh_table()?,
+            routes: dump_routes()?,
+        })
+    }
+
+    /// Get the next hops of a destination: the gateways of the most specific
+    /// route, or the destination itself if it is on-link (or not routed).
+    fn nexthops(&self, dst: IpAddr) -> Vec<IpAddr> {
+        self.routes
+            .iter()
+            .filter(|route| route.contains(&dst))
+            // Most specific route first, then lowest priority.
+            .min_by_key(|route| (u8::MAX - route.prefix_len, route.priority))
+            .filter(|route| !route.gateways.is_empty())
+            .map(|route| route.gateways.clone())
+            .unwrap_or_else(|| vec![dst])
+    }
+
+    /// Process one event, adding a NeighEvent section if the event is related
+    /// to a neighbor resolution failure and has an IP destination.
+    pub(crate) fn process_one(&self, event: &mut Event) -> Result<()> {
+        if !Self::is_neigh_failure(event) {
+            return Ok(());
+        }
+
+        let addr = match event.skb().and_then(|skb| skb.ip.as_ref()) {
+            Some(ip) => ip.daddr.clone(),
+            None => return Ok(()),
+        };
+        let nexthops = self.nexthops(IpAddr::from_str(&addr)?);
+
+        let entries = self
+            .table
+            .iter()
+            .filter(|(dst, _)| nexthops.contains(dst))
+            .map(|(_, entry)| entry.clone())
+            .collect();
+
+        event.insert_section(
+            SectionId::Neigh,
+            Box::new(NeighEvent {
+                addr,
+                nexthops: nexthops.iter().map(|nh| nh.to_string()).collect(),
+                entries,
+            }),
+        )
+    }
+
+    /// Check if an event is related to a neighbor resolution failure, based
+    /// on its drop reason or its stack trace.
+    fn is_neigh_failure(event: &Event) -> bool {
+        if let Some(drop) = event.skb_drop() {
+            if drop.subsys.is_none() && NEIGH_DROP_REASONS.contains(&drop.drop_reason.as_str()) {
+                return true;
+            }
+        }
+
+        event
+            .kernel()
+            .and_then(|kernel| kernel.stack_trace.as_ref())
+            .is_some_and(|stack| {
+                stack.raw().iter().any(|sym| {
+                    let func = sym.split('+').next().unwrap_or(sym);
+                    NEIGH_FAILURE_FUNCS.contains(&func)
+                })
+            })
+    }
+}
+
+/// Convert a neighbor state (NUD_*) to a string.
+fn neigh_state_str(state: u16) -> String {
+    match state {
+        0x00 => "none",
+        0x01 => "incomplete",
+        0x02 => "reachable",
+        0x04 => "stale",
+        0x08 => "delay",
+        0x10 => "probe",
+        0x20 => "failed",
+        0x40 => "noarp",
+        0x80 => "permanent",
+        x => return format!("unknown ({x:#x})"),
+    }
+    .to_string()
+}
+
+/// Get an interface name from its index.
+fn ifname(ifindex: u32) -> Option<String> {
+    let mut buf = [0; libc::IF_NAMESIZE];
+    // Safety: buf is IF_NAMESIZE long as required by if_indextoname.
+    match unsafe { libc::if_indextoname(ifindex, buf.as_mut_ptr()) }.is_null() {
+        true => None,
+        // Safety: if_indextoname wrote a nul-terminated string on success.
+        false => Some(
+            unsafe { CStr::from_ptr(buf.as_ptr()) }
+                .to_string_lossy()
+                .into_owned(),
+        ),
+    }
+}
+
+/// Parse a neighbor netlink message payload (struct ndmsg followed by its
+/// attributes) into a neighbor entry, if it has a destination.
+fn parse_neigh(
+    payload: &[u8],
+    ifname: impl Fn(u32) -> Option<String>,
+) -> Result<Option<(IpAddr, NeighEntry)>> {
+    if payload.len() < NDMSG_LEN {
+        return Ok(None);
+    }
+
+    let family = payload[0] as libc::c_int;
+    let ifindex = u32::from_ne_bytes(payload[4..8].try_into()?);
+    let state = u16::from_ne_bytes(payload[8..10].try_into()?);
+
+    let (mut dst, mut lladdr) = (None, None);
+    for (r#type, data) in parse_attrs(&payload[NDMSG_LEN..]) {
+        match r#type {
+            libc::NDA_DST => dst = parse_addr(family, data)?,
+            libc::NDA_LLADDR => {
+                if let Ok(raw) = <&[u8; 6]>::try_from(data) {
+                    lladdr = Some(parse_eth_addr(raw)?);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(dst.map(|dst| {
+        (
+            dst,
+            NeighEntry {
+                addr: dst.to_string(),
+                dev: ifname(ifindex),
+                lladdr,
+                state: neigh_state_str(state),
+            },
+        )
+    }))
+}
+
+/// Dump the IPv4 and IPv6 neighbor tables of the current network namespace.
+fn dump_neigh_table() -> Result<Vec<(IpAddr, NeighEntry)>> {
+    let mut table = Vec::new();
+
+    // A zeroed struct ndmsg, whose family (AF_UNSPEC) selects all neighbor
+    // tables.
+    NlSocket::new(libc::NETLINK_ROUTE)?.dump(libc::RTM_GETNEIGH, &[0; NDMSG_LEN], |msg| {
+        if msg.r#type == libc::RTM_NEWNEIGH {
+            if let Some(entry) = parse_neigh(msg.payload, ifname)? {
+                table.push(entry);
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(table)
+}
+
+/// Parse an IP address attribute of the given family.
+fn parse_addr(family: libc::c_int, data: &[u8]) -> Result<Option<IpAddr>> {
+    Ok(match (family, data.len()) {
+        (libc::AF_INET, 4) => Some(IpAddr::from(<[u8; 4]>::try_from(data)?)),
+        (libc::AF_INET6, 16) => Some(IpAddr::from(<[u8; 16]>::try_from(data)?)),
+        _ => None,
+    })
+}
+
+/// Parse the gateways of a multipath route (RTA_MULTIPATH attribute, made of
+/// struct rtnexthop followed by their attributes).
+fn parse_multipath(family: libc::c_int, mut data: &[u8]) -> Result<Vec<IpAddr>> {
+    let mut gateways = Vec::new();
+
+    while data.len() >= RTNH_LEN {
+        let len = u16::from_ne_bytes(data[0..2].try_into()?) as usize;
+        if len < RTNH_LEN || len > data.len() {
+            break;
+        }
+
+        for (r#type, attr) in parse_attrs(&data[RTNH_LEN..len]) {
+            if r#type == libc::RTA_GATEWAY {
+                gateways.extend(parse_addr(family, attr)?);
+            }
+        }
+        data = &data[len.next_multiple_of(4).min(data.len())..];
+    }
+
+    Ok(gateways)
+}
+
+/// Parse a route netlink message payload (struct rtmsg followed by its
+/// attributes), if it is a unicast route of the main table.
+fn parse_route(payload: &[u8]) -> Result<Option<Route>> {
+    if payload.len() < RTMSG_LEN {
+        return Ok(None);
+    }
+
+    let family = payload[0] as libc::c_int;
+    let prefix_len = payload[1];
+    let mut table = payload[4] as u32;
+    if payload[7] != libc::RTN_UNICAST {
+        return Ok(None);
+    }
+
+    let mut route = Route {
+        dst: match family {
+            libc::AF_INET => IpAddr::from([0; 4]),
+            libc::AF_INET6 => IpAddr::from([0; 16]),
+            _ => return Ok(None),
+        },
+        prefix_len,
+        priority: 0,
+        gateways: Vec::new(),
+    };
+    for (r#type, data) in parse_attrs(&payload[RTMSG_LEN..]) {
+        match r#type {
+            libc::RTA_DST => {
+                if let Some(dst) = parse_addr(family, data)? {
+                    route.dst = dst;
+                }
+            }
+            libc::RTA_GATEWAY => route.gateways.extend(parse_addr(family, data)?),
+            libc::RTA_MULTIPATH => route.gateways.extend(parse_multipath(family, data)?),
+            libc::RTA_PRIORITY if data.len() == 4 => {
+                route.priority = u32::from_ne_bytes(data.try_into()?)
+            }
+            libc::RTA_TABLE if data.len() == 4 => table = u32::from_ne_bytes(data.try_into()?),
+            _ => (),
+        }
+    }
+
+    Ok((table == libc::RT_TABLE_MAIN as u32).then_some(route))
+}
+
+/// Dump the IPv4 and IPv6 unicast routes of the main table of the current
+/// network namespace. Policy routing is not taken into account.
+fn dump_routes() -> Result<Vec<Route>> {
+    let mut routes = Vec::new();
+
+    // A zeroed struct rtmsg, whose family (AF_UNSPEC) selects all routes.
+    NlSocket::new(libc::NETLINK_ROUTE)?.dump(libc::RTM_GETROUTE, &[0; RTMSG_LEN], |msg| {
+        if msg.r#type == libc::RTM_NEWROUTE {
+            if let Some(route) = parse_route(msg.payload)? {
+                routes.push(route);
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(routes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_attrs(msg: &mut Vec<u8>, attrs: &[(u16, &[u8])]) {
+        attrs.iter().for_each(|(r#type, data)| {
+            msg.extend_from_slice(&((4 + data.len()) as u16).to_ne_bytes());
+            msg.extend_from_slice(&r#type.to_ne_bytes());
+            msg.extend_from_slice(data);
+            msg.resize(msg.len().next_multiple_of(4), 0);
+        });
+    }
+
+    fn ndmsg(family: u8, ifindex: u32, state: u16, attrs: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut msg = vec![family, 0, 0, 0];
+        msg.extend_from_slice(&ifindex.to_ne_bytes());
+        msg.extend_from_slice(&state.to_ne_bytes());
+        msg.extend_from_slice(&[0, 0]);
+        push_attrs(&mut msg, attrs);
+        msg
+    }
+
+    fn rtmsg(family: u8, prefix_len: u8, table: u8, attrs: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut msg = vec![family, prefix_len, 0, 0, table, 0, 0, libc::RTN_UNICAST];
+        msg.extend_from_slice(&0u32.to_ne_bytes());
+        push_attrs(&mut msg, attrs);
+        msg
+    }
+
+    fn route(dst: [u8; 4], prefix_len: u8, priority: u32, gateways: &[[u8; 4]]) -> Route {
+        Route {
+            dst: IpAddr::from(dst),
+            prefix_len,
+            priority,
+            gateways: gateways.iter().map(|gw| IpAddr::from(*gw)).collect(),
+        }
+    }
+
+    #[test]
+    fn parse_route() {
+        let main = libc::RT_TABLE_MAIN;
+        let msg = rtmsg(
+            libc::AF_INET as u8,
+            24,
+            main,
+            &[
+                (libc::RTA_DST, &[10, 0, 1, 0]),
+                (libc::RTA_GATEWAY, &[10, 0, 0, 1]),
+                (libc::RTA_PRIORITY, &100u32.to_ne_bytes()),
+            ],
+        );
+        assert_eq!(
+            super::parse_route(&msg).unwrap(),
+            Some(route([10, 0, 1, 0], 24, 100, &[[10, 0, 0, 1]]))
+        );
+
+        // Default multipath route.
+        let mut nexthops = Vec::new();
+        for gw in [[10, 0, 0, 1], [10, 0, 0, 2]] {
+            let mut nh = vec![0; RTNH_LEN];
+            push_attrs(&mut nh, &[(libc::RTA_GATEWAY, &gw)]);
+            let len = nh.len() as u16;
+            nh[0..2].copy_from_slice(&len.to_ne_bytes());
+            nexthops.extend(nh);
+        }
+        let msg = rtmsg(
+            libc::AF_INET as u8,
+            0,
+            main,
+            &[(libc::RTA_MULTIPATH, &nexthops)],
+        );
+        assert_eq!(
+            super::parse_route(&msg).unwrap(),
+            Some(route([0; 4], 0, 0, &[[10, 0, 0, 1], [10, 0, 0, 2]]))
+        );
+
+        // Routes of other tables are skipped, including when the table is only
+        // given as an attribute.
+        let msg = rtmsg(libc::AF_INET as u8, 0, 255, &[]);
+        assert!(super::parse_route(&msg).unwrap().is_none());
+        let msg = rtmsg(
+            libc::AF_INET as u8,
+            0,
+            0,
+            &[(libc::RTA_TABLE, &1000u32.to_ne_bytes())],
+        );
+        assert!(super::parse_route(&msg).unwrap().is_none());
+    }
+
+    #[test]
+    fn nexthops() {
+        let neigh = AddNeigh {
+            table: Vec::new(),
+            routes: vec![
+                route([0; 4], 0, 0, &[[10, 0, 0, 1]]),
+                route([10, 0, 0, 0], 24, 0, &[]),
+                route([10, 0, 1, 0], 24, 200, &[[10, 0, 0, 2]]),
+                route([10, 0, 1, 0], 24, 100, &[[10, 0, 0, 3]]),
+                route([10, 0, 1, 42], 32, 0, &[[10, 0, 0, 4], [10, 0, 0, 5]]),
+            ],
+        };
+        let nexthops = |dst: [u8; 4]| -> Vec<IpAddr> { neigh.nexthops(IpAddr::from(dst)) };
+
+        // On-link.
+        assert_eq!(nexthops([10, 0, 0, 42]), [IpAddr::from([10, 0, 0, 42])]);
+        // Most specific route, then lowest priority.
+        assert_eq!(nexthops([10, 0, 1, 1]), [IpAddr::from([10, 0, 0, 3])]);
+        assert_eq!(
+            nexthops([10, 0, 1, 42]),
+            [IpAddr::from([10, 0, 0, 4]), IpAddr::from([10, 0, 0, 5])]
+        );
+        // Default route.
+        assert_eq!(nexthops([192, 168, 0, 1]), [IpAddr::from([10, 0, 0, 1])]);
+        // Not routed.
+        let v6 = IpAddr::from([0xfe80, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(neigh.nexthops(v6), [v6]);
+    }
+
+    #[test]
+    fn parse_neigh() {
+        let msg = ndmsg(
+            libc::AF_INET as u8,
+            2,
+            0x02,
+            &[
+                (libc::NDA_DST, &[10, 0, 0, 1]),
+                (libc::NDA_LLADDR, &[0x0a, 0x58, 0x0a, 0xf4, 0x00, 0x01]),
+            ],
+        );
+        let (dst, entry) = super::parse_neigh(&msg, |i| Some(format!("eth{i}")))
+            .unwrap()
+            .unwrap();
+        assert_eq!(dst, IpAddr::from([10, 0, 0, 1]));
+        assert_eq!(entry.addr, "10.0.0.1");
+        assert_eq!(entry.dev.as_deref(), Some("eth2"));
+        assert_eq!(entry.lladdr.as_deref(), Some("0a:58:0a:f4:00:01"));
+        assert_eq!(entry.state, "reachable");
+
+        let mut v6 = [0; 16];
+        v6[0..2].copy_from_slice(&[0xfe, 0x80]);
+        v6[15] = 1;
+        let msg = ndmsg(libc::AF_INET6 as u8, 3, 0x20, &[(libc::NDA_DST, &v6)]);
+        let (dst, entry) = super::parse_neigh(&msg, |_| None).unwrap().unwrap();
+        assert_eq!(dst, IpAddr::from(v6));
+        assert!(entry.dev.is_none() && entry.lladdr.is_none());
+        assert_eq!(entry.state, "failed");
+
+        // Entries without a destination are skipped.
+        let msg = ndmsg(libc::AF_INET as u8, 2, 0x02, &[]);
+        assert!(super::parse_neigh(&msg, |_| None).unwrap().is_none());
+    }
+}
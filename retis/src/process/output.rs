@@ -0,0 +1,401 @@
+//! # Output
+//!
+//! Writes events to files in various formats, so a single pass over the input
+//! can generate multiple outputs. The format of an output is deduced from the
+//! extension of its file:
+//! - `.pcap` and `.pcapng`: PCAP-ng, for events having a raw packet.
+//! - `.csv`: one line per event, with the main fields of the event.
+//! - Anything else: JSON, which can be read back by Retis.
+
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{bail, Result};
+
+use crate::{
+    events::{file::is_stream, *},
+    process::{display::*, pcap::EventParser},
+};
+
+/// Format of an output file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum OutputFormat {
+    Json,
+    Pcap,
+    Csv,
+}
+
+/// Output file, as given on the command line.
+#[derive(Clone, Debug)]
+pub(crate) struct OutputFile {
+    pub(crate) path: PathBuf,
+    pub(crate) format: OutputFormat,
+}
+
+impl FromStr for OutputFile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.is_empty() {
+            bail!("Output file can't be empty");
+        }
+
+        let path = PathBuf::from(s);
+        let format = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("pcap") | Some("pcapng") => OutputFormat::Pcap,
+            Some("csv") => OutputFormat::Csv,
+            _ => OutputFormat::Json,
+        };
+
+        Ok(Self { path, format })
+    }
+}
+
+impl OutputFile {
+    /// Make sure the output isn't the input file, as it would be truncated.
+    pub(crate) fn check_not_input(&self, input: &Path) -> Result<()> {
+        // Streams are not truncated by writing to them.
+        if is_stream(input) {
+            return Ok(());
+        }
+
+        let out = match self.path.canonicalize() {
+            Ok(out) => out,
+            // If the file doesn't exist we can't use fs::canonicalize() but it
+            // is not needed as that means it is not the input file.
+            Err(_) => return Ok(()),
+        };
+
+        if out.eq(&input.canonicalize()?) {
+            bail!(
+                "Cannot write to the input file '{}'. Please specify a different output file.",
+                input.display()
+            );
+        }
+        Ok(())
+    }
+
+    /// Create the output file and its writer. If `series` is true JSON outputs
+    /// are written as series, otherwise as single events.
+    pub(crate) fn open(&self, series: bool) -> Result<Output> {
+        let file = ReopenableFile::create(&self.path)?;
+
+        Ok(match (self.format, series) {
+            (OutputFormat::Json, false) => {
+                Output::JsonEvents(PrintEvent::new(Box::new(file), PrintEventFormat::Json))
+            }
+            (OutputFormat::Json, true) => {
+                Output::JsonSeries(PrintSeries::new(Box::new(file), PrintEventFormat::Json))
+            }
+            (OutputFormat::Pcap, _) => Output::Pcap(EventParser::new(file)?),
+            (OutputFormat::Csv, _) => Output::Csv(CsvWriter::new(file)?),
+        })
+    }
+}
+
+/// Writes events or series to an output file.
+pub(crate) enum Output {
+    JsonEvents(PrintEvent),
+    JsonSeries(PrintSeries),
+    Pcap(EventParser<ReopenableFile>),
+    Csv(CsvWriter<ReopenableFile>),
+}
+
+impl Output {
+    /// Write a single event.
+    pub(crate) fn write_event(&mut self, event: &Event) -> Result<()> {
+        match self {
+            Output::JsonEvents(p) => p.process_one(event),
+            Output::JsonSeries(_) => bail!("Can't write a single event to a series output"),
+            Output::Pcap(p) => {
+                // Only events generated by a probe can be converted.
+                if event.kernel().is_none() {
+                    return Ok(());
+                }
+                p.parse(event)
+            }
+            Output::Csv(c) => c.write(event),
+        }
+    }
+
+    /// Write a series. Outputs not handling series write its events one by one.
+    pub(crate) fn write_series(&mut self, series: &EventSeries) -> Result<()> {
+        match self {
+            Output::JsonSeries(p) => p.process_one(series),
+            _ => series.events.iter().try_for_each(|e| self.write_event(e)),
+        }
+    }
+
+    /// Flush the output, releasing its file descriptor until the next write.
+    pub(crate) fn flush(&mut self) -> Result<()> {
+        match self {
+            Output::JsonEvents(p) => p.flush(),
+            Output::JsonSeries(p) => p.flush(),
+            Output::Pcap(p) => p.flush(),
+            Output::Csv(c) => c.flush(),
+        }
+    }
+
+    /// Flush the output. Should be called after all events were written.
+    pub(crate) fn finish(&mut self) -> Result<()> {
+        if let Output::Pcap(p) = self {
+            p.report_stats();
+        }
+        self.flush()
+    }
+}
+
+/// Maximum number of files kept open at once when writing many outputs.
+pub(crate) const MAX_OPEN_FILES: usize = 256;
+
+/// File writer releasing its file descriptor when flushed, the file being
+/// reopened in append mode on the next write. This allows keeping many outputs
+/// around without exhausting the file descriptors.
+pub(crate) struct ReopenableFile {
+    path: PathBuf,
+    file: Option<BufWriter<File>>,
+}
+
+impl ReopenableFile {
+    /// Create the file, truncating it if it already exists.
+    pub(crate) fn create(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)
+            .or_else(|_| bail!("Could not create or open '{}'", path.display()))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Some(BufWriter::new(file)),
+        })
+    }
+}
+
+impl Write for ReopenableFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => self.file.insert(BufWriter::new(
+                OpenOptions::new().append(true).open(&self.path)?,
+            )),
+        };
+        file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(file) = self.file.as_mut() {
+            file.flush()?;
+        }
+        self.file = None;
+        Ok(())
+    }
+}
+
+/// Tracks which outputs hold an open file, so the least recently used ones can
+/// be released once more than `MAX_OPEN_FILES` are open.
+#[derive(Default)]
+pub(crate) struct OpenFiles<K> {
+    keys: VecDeque<K>,
+}
+
+impl<K: PartialEq> OpenFiles<K> {
+    /// Mark an output as used. Returns the output whose file should be
+    /// released, if any.
+    pub(crate) fn used(&mut self, key: K) -> Option<K> {
+        if self.keys.back() == Some(&key) {
+            return None;
+        }
+        if let Some(pos) = self.keys.iter().position(|k| *k == key) {
+            self.keys.remove(pos);
+        }
+
+        self.keys.push_back(key);
+        match self.keys.len() > MAX_OPEN_FILES {
+            true => self.keys.pop_front(),
+            false => None,
+        }
+    }
+}
+
+/// Writes the main fields of events as CSV.
+pub(crate) struct CsvWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> CsvWriter<W> {
+    const HEADER: &'static str = "timestamp,cpu,comm,probe,tracking_id,ifname,netns,saddr,daddr,\
+                                  protocol,sport,dport,len,drop_reason";
+
+    /// Create a new CSV writer and write the header line.
+    pub(crate) fn new(mut writer: W) -> Result<Self> {
+        writeln!(writer, "{}", Self::HEADER)?;
+        Ok(Self { writer })
+    }
+
+    /// Write a single event as a CSV line. Unknown fields are left empty.
+    pub(crate) fn write(&mut self, event: &Event) -> Result<()> {
+        let common = match event.common() {
+            Some(common) => common,
+            // Not an actual event (e.g. startup event).
+            None => return Ok(()),
+        };
+        let kernel = event.kernel();
+        let tracking = event.skb_tracking();
+        let skb = event.skb();
+        let drop = event.skb_drop();
+
+        let ip = skb.and_then(|skb| skb.ip.as_ref());
+        let ports = skb.and_then(|skb| {
+            skb.tcp
+                .as_ref()
+                .map(|tcp| (tcp.sport, tcp.dport))
+                .or_else(|| skb.udp.as_ref().map(|udp| (udp.sport, udp.dport)))
+                .or_else(|| skb.sctp.as_ref().map(|sctp| (sctp.sport, sctp.dport)))
+        });
+
+        let fields = [
+            common.timestamp.to_string(),
+            opt(common.smp_id),
+            common
+                .task
+                .as_ref()
+                .map(|task| task.comm.clone())
+                .unwrap_or_default(),
+            kernel
+                .map(|k| format!("{}:{}", k.probe_type, k.symbol))
+                .unwrap_or_default(),
+            opt(tracking.map(|t| format!("{:x}", t.tracking_id()))),
+            opt(skb.and_then(|skb| skb.dev.as_ref().map(|dev| dev.name.clone()))),
+            opt(skb.and_then(|skb| skb.ns.as_ref().map(|ns| ns.netns))),
+            opt(ip.map(|ip| ip.saddr.clone())),
+            opt(ip.map(|ip| ip.daddr.clone())),
+            opt(ip.map(|ip| ip.protocol)),
+            opt(ports.map(|p| p.0)),
+            opt(ports.map(|p| p.1)),
+            opt(skb.and_then(|skb| skb.packet.as_ref().map(|p| p.len))),
+            opt(drop.map(|drop| match &drop.subsys {
+                Some(subsys) => format!("{subsys}/{}", drop.drop_reason),
+                None => drop.drop_reason.clone(),
+            })),
+        ];
+
+        let line = fields
+            .iter()
+            .map(|f| escape(f))
+            .collect::<Vec<_>>()
+            .join(",");
+        writeln!(self.writer, "{line}")?;
+        Ok(())
+    }
+
+    /// Flush the underlying writer.
+    pub(crate) fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+fn opt<T: ToString>(val: Option<T>) -> String {
+    val.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Quote a CSV field if needed.
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_format() {
+        let format = |s: &str| OutputFile::from_str(s).unwrap().format;
+
+        assert_eq!(format("out.json"), OutputFormat::Json);
+        assert_eq!(format("retis.data"), OutputFormat::Json);
+        assert_eq!(format("out"), OutputFormat::Json);
+        assert_eq!(format("/tmp/out.pcap"), OutputFormat::Pcap);
+        assert_eq!(format("out.pcapng"), OutputFormat::Pcap);
+        assert_eq!(format("out.csv"), OutputFormat::Csv);
+        assert!(OutputFile::from_str("").is_err());
+    }
+
+    #[test]
+    fn open_files() {
+        let mut open = OpenFiles::default();
+        (0..MAX_OPEN_FILES).for_each(|i| assert_eq!(open.used(i), None));
+
+        // Using an output again makes it the most recently used one.
+        assert_eq!(open.used(0), None);
+        assert_eq!(open.used(MAX_OPEN_FILES), Some(1));
+        assert_eq!(open.used(MAX_OPEN_FILES + 1), Some(2));
+        assert_eq!(open.keys.len(), MAX_OPEN_FILES);
+    }
+
+    #[test]
+    fn reopenable_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out");
+
+        let mut file = ReopenableFile::create(&path).unwrap();
+        file.write_all(b"foo\n").unwrap();
+        file.flush().unwrap();
+        assert!(file.file.is_none());
+
+        file.write_all(b"bar\n").unwrap();
+        file.flush().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "foo\nbar\n");
+    }
+
+    #[test]
+    fn csv() {
+        let mut event = Event::new();
+        event
+            .insert_section(
+                SectionId::Common,
+                Box::new(CommonEvent {
+                    timestamp: 42,
+                    seq: None,
+                    smp_id: Some(1),
+                    task: Some(TaskEvent {
+                        pid: 1,
+                        tgid: 1,
+                        comm: "a,\"b\"".to_string(),
+                    }),
+                }),
+            )
+            .unwrap();
+        event
+            .insert_section(
+                SectionId::SkbDrop,
+                Box::new(SkbDropEvent {
+                    subsys: None,
+                    drop_reason: "NO_SOCKET".to_string(),
+                }),
+            )
+            .unwrap();
+
+        let mut csv = CsvWriter::new(Vec::new()).unwrap();
+        csv.write(&event).unwrap();
+        csv.write(&Event::new()).unwrap();
+
+        assert_eq!(
+            String::from_utf8(csv.writer).unwrap(),
+            format!(
+                "{}\n42,1,\"a,\"\"b\"\"\",,,,,,,,,,,NO_SOCKET\n",
+                CsvWriter::<Vec<u8>>::HEADER
+            )
+        );
+    }
+}
@@ -0,0 +1,280 @@
+//! Severity classifier.
+//!
+//! In dense traces, events reporting an issue (drops, retransmissions, etc)
+//! are easily missed. The classifier assigns a severity to events based on a
+//! set of rules and inserts a SeverityEvent section into the ones standing out
+//! (warn and error), so they can be highlighted and filtered.
+
+use anyhow::Result;
+
+use crate::events::*;
+
+/// Kernel functions and tracepoints reporting TCP retransmissions.
+const TCP_RETRANSMIT_SYMBOLS: &[&str] = &[
+    "tcp_retransmit_skb",
+    "__tcp_retransmit_skb",
+    "tcp_retransmit_timer",
+    "tcp:tcp_retransmit_skb",
+    "tcp:tcp_retransmit_synack",
+];
+
+/// Tracepoints reporting the release of an skb. Those are only hit once the
+/// last reference was dropped, `skb->users` must then be 1.
+const SKB_FREE_TRACEPOINTS: &[&str] = &["skb:kfree_skb", "skb:consume_skb"];
+
+/// Byte written to freed slab objects when slab poisoning is enabled (see
+/// POISON_FREE in include/linux/poison.h).
+const SLAB_POISON_FREE: u8 = 0x6b;
+
+/// AddSeverity classifies events and inserts a SeverityEvent section into
+/// events having a severity higher than info.
+#[derive(Default)]
+pub(crate) struct AddSeverity {}
+
+impl AddSeverity {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Process one event and return its severity.
+    pub(crate) fn process_one(&self, event: &mut Event) -> Result<Severity> {
+        // Events might already have been classified (e.g. when reading events
+        // written by a previous classification pass).
+        if let Some(severity) = event.severity() {
+            return Ok(severity.level);
+        }
+
+        let (level, reason) = match Self::classify(event) {
+            Some(classification) => classification,
+            None => return Ok(Severity::Info),
+        };
+
+        event.insert_section(
+            SectionId::Severity,
+            Box::new(SeverityEvent { level, reason }),
+        )?;
+        Ok(level)
+    }
+
+    /// Process all events of a series and return the highest severity found.
+    pub(crate) fn process_series(&self, series: &mut EventSeries) -> Result<Severity> {
+        series
+            .events
+            .iter_mut()
+            .try_fold(Severity::Info, |max, e| Ok(max.max(self.process_one(e)?)))
+    }
+
+    /// Apply the classification rules to an event. The rule with the highest
+    /// severity wins; `None` is returned for info events.
+    fn classify(event: &Event) -> Option<(Severity, String)> {
+        let mut rules = Vec::new();
+
+        if let Some(drop) = event.skb_drop() {
+            rules.push(match drop.drop_reason.as_str() {
+                // The reason is not known, the drop might be expected.
+                "NOT_SPECIFIED" => (Severity::Warn, "drop".to_string()),
+                reason => (Severity::Error, format!("drop ({reason})")),
+            });
+        }
+
+        if let Some(nft) = event.nft() {
+            if nft.verdict == "drop" {
+                rules.push((Severity::Error, "nft drop verdict".to_string()));
+            }
+        }
+
+        if let Some(tc) = event.tc() {
+            if tc.action.as_deref() == Some("shot") {
+                rules.push((Severity::Error, format!("tc bpf drop ({})", tc.prog_name)));
+            }
+        }
+
+        if let Some(kernel) = event.kernel() {
+            if TCP_RETRANSMIT_SYMBOLS.contains(&kernel.symbol.as_str()) {
+                rules.push((Severity::Warn, "tcp retransmission".to_string()));
+            }
+        }
+
+        if let Some(icmp) = event.icmp() {
+            let reason = match icmp.kind {
+                IcmpEventKind::GlobalRateLimit | IcmpEventKind::PeerRateLimit => {
+                    "icmp rate limited"
+                }
+                IcmpEventKind::NoSocketUnreach | IcmpEventKind::NoSocketReset => "no socket",
+                IcmpEventKind::LocalError | IcmpEventKind::LocalReset => {
+                    "locally originated reject"
+                }
+            };
+            rules.push((
+                Severity::Warn,
+                match event.reject_origin() {
+                    Some(origin) => format!("{reason} by {origin}"),
+                    None => reason.to_string(),
+                },
+            ));
+        }
+
+        if let Some(ipvs) = event.ipvs() {
+            if ipvs.unscheduled.as_deref() == Some("no_dest") {
+                rules.push((Severity::Warn, "ipvs no destination".to_string()));
+            }
+        }
+
+        if let Some(data_ref) = event.skb().and_then(|skb| skb.data_ref.as_ref()) {
+            let symbol = event
+                .kernel()
+                .map(|k| k.symbol.as_str())
+                .unwrap_or_default();
+            if let Some(reason) = refcount_anomaly(data_ref, symbol) {
+                rules.push((Severity::Warn, reason));
+            }
+        }
+
+        if let Some(tls) = event.tls() {
+            if tls.error == Some(TLS_DECRYPT_ERROR) {
+                rules.push((Severity::Error, "tls decryption failure".to_string()));
+            }
+        }
+
+        // Keep the first rule of the highest severity.
+        rules.into_iter().rev().max_by_key(|(level, _)| *level)
+    }
+}
+
+/// Look for implausible skb reference counts, as seen when an skb is used or
+/// freed after being released.
+fn refcount_anomaly(data_ref: &SkbDataRefEvent, symbol: &str) -> Option<String> {
+    let freed = SKB_FREE_TRACEPOINTS.contains(&symbol);
+    let implausible = |count| count == 0 || count == SLAB_POISON_FREE;
+
+    if implausible(data_ref.users) || implausible(data_ref.dataref) {
+        return Some(format!(
+            "skb {} (users {} dataref {})",
+            match freed {
+                true => "double free",
+                false => "use after free",
+            },
+            data_ref.users,
+            data_ref.dataref
+        ));
+    }
+
+    if freed && data_ref.users != 1 {
+        return Some(format!("skb freed with users {}", data_ref.users));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(symbol: &str, drop_reason: Option<&str>) -> Event {
+        let mut event = Event::new();
+        event
+            .insert_section(
+                SectionId::Kernel,
+                Box::new(KernelEvent {
+                    symbol: symbol.to_string(),
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        if let Some(reason) = drop_reason {
+            event
+                .insert_section(
+                    SectionId::SkbDrop,
+                    Box::new(SkbDropEvent {
+                        subsys: None,
+                        drop_reason: reason.to_string(),
+                    }),
+                )
+                .unwrap();
+        }
+        event
+    }
+
+    #[test]
+    fn classify() {
+        let add = AddSeverity::new();
+
+        let mut e = event("ip_rcv", None);
+        assert_eq!(add.process_one(&mut e).unwrap(), Severity::Info);
+        assert!(e.severity().is_none());
+
+        let mut e = event("tcp:tcp_retransmit_skb", None);
+        assert_eq!(add.process_one(&mut e).unwrap(), Severity::Warn);
+        assert_eq!(e.severity().unwrap().reason, "tcp retransmission");
+
+        let mut e = event("skb:kfree_skb", Some("NOT_SPECIFIED"));
+        assert_eq!(add.process_one(&mut e).unwrap(), Severity::Warn);
+
+        let mut e = event("tcp_retransmit_skb", Some("NO_SOCKET"));
+        assert_eq!(add.process_one(&mut e).unwrap(), Severity::Error);
+        assert_eq!(e.severity().unwrap().reason, "drop (NO_SOCKET)");
+        // Already classified events are not modified.
+        assert_eq!(add.process_one(&mut e).unwrap(), Severity::Error);
+
+        let mut series = EventSeries {
+            events: vec![
+                event("ip_rcv", None),
+                event("skb:kfree_skb", Some("NO_SOCKET")),
+            ],
+        };
+        assert_eq!(add.process_series(&mut series).unwrap(), Severity::Error);
+
+        let mut e = Event::new();
+        e.insert_section(
+            SectionId::Kernel,
+            Box::new(KernelEvent {
+                symbol: "__icmp_send".to_string(),
+                stack_trace: Some(StackTrace(vec![
+                    "__icmp_send+0x0".to_string(),
+                    "nf_send_unreach+0x5e".to_string(),
+                ])),
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+        e.insert_section(
+            SectionId::Icmp,
+            Box::new(IcmpEvent {
+                kind: IcmpEventKind::LocalError,
+                error: None,
+            }),
+        )
+        .unwrap();
+        assert_eq!(add.process_one(&mut e).unwrap(), Severity::Warn);
+        assert_eq!(
+            e.severity().unwrap().reason,
+            "locally originated reject by nf_send_unreach"
+        );
+    }
+
+    #[test]
+    fn refcount() {
+        let data_ref = |users, dataref| SkbDataRefEvent {
+            nohdr: false,
+            cloned: false,
+            fclone: 0,
+            users,
+            dataref,
+        };
+
+        assert!(refcount_anomaly(&data_ref(1, 1), "skb:kfree_skb").is_none());
+        assert!(refcount_anomaly(&data_ref(2, 1), "ip_rcv").is_none());
+        assert_eq!(
+            refcount_anomaly(&data_ref(2, 1), "skb:consume_skb").unwrap(),
+            "skb freed with users 2"
+        );
+        assert_eq!(
+            refcount_anomaly(&data_ref(0, 1), "skb:kfree_skb").unwrap(),
+            "skb double free (users 0 dataref 1)"
+        );
+        assert_eq!(
+            refcount_anomaly(&data_ref(1, 0x6b), "ip_rcv").unwrap(),
+            "skb use after free (users 1 dataref 107)"
+        );
+    }
+}
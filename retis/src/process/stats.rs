@@ -0,0 +1,637 @@
+//! # Stats
+//!
+//! Summarizes the drops found in events and correlates them with the network
+//! stack counters snapshotted during the collection (`--netstat`). Counters
+//! are incremented on drops Retis might not see (e.g. probes not installed,
+//! filtered out packets) while some drops are not accounted in any counter;
+//! comparing both helps finding what was missed.
+//!
+//! For sorted files (see `retis sort`), the latency distribution between every
+//! pair of adjacent probes found in the series is reported, the slowest hops
+//! first, to help finding where packets spend time.
+//!
+//! It also reports the space used by each section in the events file, to help
+//! choosing which ones to disable to shrink the captures, and the events lost
+//! before reaching userspace, found using the per-cpu sequence numbers of
+//! events.
+//!
+//! A lighter summary, counting events per section, probe and drop reason, is
+//! available for the events being printed (see `retis print --footer`).
+
+use std::{cmp::Reverse, collections::BTreeMap, fmt::Write};
+
+use anyhow::Result;
+
+use super::analyze::Latency;
+use crate::events::*;
+
+/// Network stack counters and the drop reasons the kernel reports when
+/// incrementing them.
+const CORRELATIONS: &[(&str, &[&str])] = &[
+    (
+        "TcpExtListenDrops",
+        &["TCP_LISTEN_OVERFLOW", "TCP_REQQFULLDROP"],
+    ),
+    ("TcpExtListenOverflows", &["TCP_LISTEN_OVERFLOW"]),
+    ("TcpExtTCPReqQFullDrop", &["TCP_REQQFULLDROP"]),
+    ("TcpExtTCPBacklogDrop", &["SOCKET_BACKLOG"]),
+    ("TcpExtTCPZeroWindowDrop", &["TCP_ZEROWINDOW"]),
+    ("TcpExtTCPOFODrop", &["TCP_OFO_DROP"]),
+    ("TcpExtTCPMinTTLDrop", &["TCP_MINTTL"]),
+    ("TcpExtPAWSEstabRejected", &["TCP_RFC7323_PAWS"]),
+    ("TcpExtTCPMD5NotFound", &["TCP_MD5NOTFOUND"]),
+    ("TcpExtTCPMD5Unexpected", &["TCP_MD5UNEXPECTED"]),
+    ("TcpExtTCPMD5Failure", &["TCP_MD5FAILURE"]),
+    ("TcpInCsumErrors", &["TCP_CSUM"]),
+    ("UdpInCsumErrors", &["UDP_CSUM"]),
+    ("UdpNoPorts", &["NO_SOCKET"]),
+    ("UdpRcvbufErrors", &["SOCKET_RCVBUFF"]),
+    ("IpInHdrErrors", &["IP_INHDR"]),
+    ("IpInAddrErrors", &["IP_INADDRERRORS"]),
+    ("IpInNoRoutes", &["IP_INNOROUTES"]),
+    ("IpOutNoRoutes", &["IP_OUTNOROUTES"]),
+];
+
+/// Parts of sections accounted separately, as they usually account for most of
+/// the size of the events (packet payloads and stack traces).
+const SUBSECTIONS: &[(&str, &str)] = &[("skb", "packet"), ("kernel", "stack_trace")];
+
+/// Maximum number of gaps reported per cpu.
+const MAX_GAPS: usize = 5;
+
+/// Cpu events were generated on: host name (for merged files) and cpu id.
+type Cpu = (Option<String>, u32);
+
+/// Drops, network stack counters, sections size and lost events found in
+/// events.
+#[derive(Default)]
+pub(crate) struct EventStats {
+    /// Number of drops per drop reason.
+    drops: BTreeMap<String, u64>,
+    /// Network stack counters changes, if reported.
+    netstat: Option<Vec<NetstatCounter>>,
+    /// Size in bytes of the JSON representation of sections, per section (or
+    /// subsection, see `SUBSECTIONS`).
+    sizes: BTreeMap<String, u64>,
+    /// Sequence numbers and timestamps of events, per host and cpu.
+    seqs: BTreeMap<Cpu, Vec<(u64, u64)>>,
+    /// Latency samples in nanoseconds between adjacent probes of series, per
+    /// pair of probes.
+    hops: BTreeMap<(String, String), Vec<u64>>,
+}
+
+impl EventStats {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an event to the stats. Events can be added in any order.
+    pub(crate) fn add(&mut self, event: &Event) {
+        if let Some(drop) = event.skb_drop() {
+            *self.drops.entry(drop_reason(drop)).or_default() += 1;
+        }
+
+        if let Some(netstat) = event.netstat() {
+            self.netstat = Some(netstat.counters.clone());
+        }
+
+        if let Some(common) = event.common() {
+            if let (Some(seq), Some(cpu)) = (common.seq, common.smp_id) {
+                let host = event.host().map(|h| h.name.clone());
+                self.seqs
+                    .entry((host, cpu))
+                    .or_default()
+                    .push((seq, common.timestamp));
+            }
+        }
+
+        if let serde_json::Value::Object(sections) = event.to_json() {
+            for (name, section) in sections.iter() {
+                self.add_size(name, section);
+            }
+        }
+    }
+
+    /// Add a series of events to the stats: its events and the latency
+    /// between its adjacent probes.
+    pub(crate) fn add_series(&mut self, series: &EventSeries) {
+        series.events.iter().for_each(|e| self.add(e));
+
+        let probes = series
+            .events
+            .iter()
+            .filter_map(|e| Some((probe_name(e)?, e.common()?.timestamp)))
+            .collect::<Vec<_>>();
+        for hop in probes.windows(2) {
+            let ((from, start), (to, end)) = (&hop[0], &hop[1]);
+            self.hops
+                .entry((from.clone(), to.clone()))
+                .or_default()
+                .push(end.saturating_sub(*start));
+        }
+    }
+
+    fn add_size(&mut self, name: &str, section: &serde_json::Value) {
+        let mut size = json_size(section);
+
+        for (parent, key) in SUBSECTIONS.iter() {
+            if *parent != name {
+                continue;
+            }
+            if let Some(sub) = section.get(key).filter(|sub| !sub.is_null()) {
+                let sub_size = json_size(sub);
+                *self.sizes.entry(format!("{name}.{key}")).or_default() += sub_size;
+                size -= sub_size;
+            }
+        }
+
+        *self.sizes.entry(name.to_string()).or_default() += size;
+    }
+
+    /// Render the stats.
+    pub(crate) fn render(&self) -> Result<String> {
+        let mut out = String::new();
+
+        writeln!(out, "Captured drops: {}", self.drops.values().sum::<u64>())?;
+        for (reason, count) in self.drops.iter() {
+            writeln!(out, "  {reason} {count}")?;
+        }
+
+        self.render_netstat(&mut out)?;
+        self.render_hops(&mut out)?;
+        self.render_lost(&mut out)?;
+        self.render_sizes(&mut out)?;
+
+        Ok(out)
+    }
+
+    fn render_netstat(&self, out: &mut String) -> Result<()> {
+        let netstat = match &self.netstat {
+            Some(netstat) => netstat,
+            None => {
+                writeln!(
+                    out,
+                    "\nNo network stack counters found (see `retis collect --netstat`)"
+                )?;
+                return Ok(());
+            }
+        };
+
+        let drops = |reason: &str| self.drops.get(reason).copied().unwrap_or_default();
+        let delta = |name: &str| {
+            netstat
+                .iter()
+                .find(|c| c.name == name)
+                .map(|c| c.delta)
+                .unwrap_or_default()
+        };
+
+        writeln!(out, "\nNetwork stack counters vs captured drops:")?;
+        let mut correlated = Vec::new();
+        for (name, reasons) in CORRELATIONS.iter() {
+            let delta = delta(name);
+            if delta == 0 && reasons.iter().all(|r| drops(r) == 0) {
+                continue;
+            }
+
+            let reasons = reasons
+                .iter()
+                .map(|r| format!("{r} {}", drops(r)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(out, "  {name} {delta:+}: {reasons}")?;
+            correlated.push(*name);
+        }
+        if correlated.is_empty() {
+            writeln!(out, "  none")?;
+        }
+
+        let others = netstat
+            .iter()
+            .filter(|c| !correlated.contains(&c.name.as_str()))
+            .collect::<Vec<_>>();
+        if !others.is_empty() {
+            writeln!(out, "\nOther network stack counters:")?;
+            for counter in others.iter() {
+                writeln!(out, "  {} {:+}", counter.name, counter.delta)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn render_hops(&self, out: &mut String) -> Result<()> {
+        let mut hops = self
+            .hops
+            .iter()
+            .filter_map(|((from, to), samples)| Some((from, to, Latency::from_samples(samples)?)))
+            .collect::<Vec<_>>();
+        if hops.is_empty() {
+            return Ok(());
+        }
+        hops.sort_by_key(|(_, _, l)| (Reverse(l.p50), Reverse(l.p99)));
+
+        let from_width = hops.iter().map(|h| h.0.len()).max().unwrap_or_default();
+        let to_width = hops.iter().map(|h| h.1.len()).max().unwrap_or_default();
+        writeln!(out, "\nLatency between adjacent probes, slowest first:")?;
+        writeln!(
+            out,
+            "  {:from_width$}    {:to_width$}  {:>8}  latency (us) min/avg/p50/p99/max",
+            "from", "to", "samples"
+        )?;
+
+        let us = |ns: u64| format!("{:.1}", ns as f64 / 1000.0);
+        for (from, to, l) in hops.iter() {
+            writeln!(
+                out,
+                "  {from:from_width$} -> {to:to_width$}  {:>8}  {}/{}/{}/{}/{}",
+                l.samples,
+                us(l.min),
+                us(l.avg),
+                us(l.p50),
+                us(l.p99),
+                us(l.max)
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn render_lost(&self, out: &mut String) -> Result<()> {
+        if self.seqs.is_empty() {
+            return Ok(());
+        }
+
+        // Events are not necessarily in sequence order (e.g. sorted files,
+        // nested probes), look for gaps once sorted.
+        let mut lost = Vec::new();
+        for ((host, cpu), seqs) in self.seqs.iter() {
+            let mut seqs = seqs.clone();
+            seqs.sort_unstable();
+            seqs.dedup_by_key(|(seq, _)| *seq);
+
+            let gaps = seqs
+                .windows(2)
+                .filter(|w| w[1].0 > w[0].0 + 1)
+                .map(|w| (w[1].0 - w[0].0 - 1, w[0], w[1]))
+                .collect::<Vec<_>>();
+            if gaps.is_empty() {
+                continue;
+            }
+
+            let cpu = match host {
+                Some(host) => format!("{host} cpu {cpu}"),
+                None => format!("cpu {cpu}"),
+            };
+            lost.push((cpu, gaps));
+        }
+
+        let total = lost
+            .iter()
+            .flat_map(|(_, gaps)| gaps.iter().map(|g| g.0))
+            .sum::<u64>();
+        writeln!(out, "\nLost events: {total}")?;
+        for (cpu, gaps) in lost.iter() {
+            writeln!(
+                out,
+                "  {cpu} {} in {} gap(s)",
+                gaps.iter().map(|g| g.0).sum::<u64>(),
+                gaps.len()
+            )?;
+            for (count, prev, next) in gaps.iter().take(MAX_GAPS) {
+                writeln!(
+                    out,
+                    "    {count} between seq {} ({}) and {} ({})",
+                    prev.0, prev.1, next.0, next.1
+                )?;
+            }
+            if gaps.len() > MAX_GAPS {
+                writeln!(out, "    ...")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn render_sizes(&self, out: &mut String) -> Result<()> {
+        let total = self.sizes.values().sum::<u64>();
+        if total == 0 {
+            return Ok(());
+        }
+
+        let mut sizes = self.sizes.iter().collect::<Vec<_>>();
+        sizes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+        writeln!(out, "\nSize per section: {total} bytes")?;
+        for (name, size) in sizes.iter() {
+            writeln!(
+                out,
+                "  {name} {size} ({:.1}%)",
+                **size as f64 * 100.0 / total as f64
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Number of events per section, probe and drop reason.
+#[derive(Default)]
+pub(crate) struct EventCounts {
+    /// Total number of events.
+    events: u64,
+    /// Number of events per section.
+    sections: BTreeMap<String, u64>,
+    /// Number of events per probe, named as `<type>:<symbol>`.
+    probes: BTreeMap<String, u64>,
+    /// Number of drops per drop reason.
+    drops: BTreeMap<String, u64>,
+}
+
+impl EventCounts {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Account an event.
+    pub(crate) fn add(&mut self, event: &Event) {
+        self.events += 1;
+
+        event.sections().for_each(|id| {
+            *self.sections.entry(id.to_str().to_string()).or_default() += 1;
+        });
+
+        if let Some(probe) = probe_name(event) {
+            *self.probes.entry(probe).or_default() += 1;
+        }
+
+        if let Some(drop) = event.skb_drop() {
+            *self.drops.entry(drop_reason(drop)).or_default() += 1;
+        }
+    }
+
+    /// Render the counts, most frequent entries first.
+    pub(crate) fn render(&self) -> Result<String> {
+        let mut out = String::new();
+
+        writeln!(out, "Events: {}", self.events)?;
+        for (title, counts) in [
+            ("Sections", &self.sections),
+            ("Probes", &self.probes),
+            ("Drop reasons", &self.drops),
+        ] {
+            if counts.is_empty() {
+                continue;
+            }
+
+            let mut counts = counts.iter().collect::<Vec<_>>();
+            counts.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+            writeln!(out, "{title}:")?;
+            for (name, count) in counts.iter() {
+                writeln!(out, "  {name} {count}")?;
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+/// Probe which generated an event, named as `<type>:<symbol>`.
+fn probe_name(event: &Event) -> Option<String> {
+    match (event.kernel(), event.user()) {
+        (Some(kernel), _) => Some(format!(
+            "{}:{}",
+            match kernel.probe_type.as_str() {
+                "raw_tracepoint" => "tp",
+                x => x,
+            },
+            kernel.symbol
+        )),
+        (_, Some(user)) => Some(format!("{}:{}", user.probe_type, user.symbol)),
+        _ => None,
+    }
+}
+
+/// Drop reason of a drop event, prefixed by its subsystem if any.
+fn drop_reason(drop: &SkbDropEvent) -> String {
+    match &drop.subsys {
+        Some(subsys) => format!("{subsys}/{}", drop.drop_reason),
+        None => drop.drop_reason.clone(),
+    }
+}
+
+/// Size of a value once encoded, as in events files.
+fn json_size(value: &serde_json::Value) -> u64 {
+    value.to_string().len() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(section: (SectionId, Box<dyn EventSection>)) -> Event {
+        let mut event = Event::new();
+        event.insert_section(section.0, section.1).unwrap();
+        event
+    }
+
+    fn skb_drop(reason: &str) -> Event {
+        event((
+            SectionId::SkbDrop,
+            Box::new(SkbDropEvent {
+                subsys: None,
+                drop_reason: reason.to_string(),
+            }),
+        ))
+    }
+
+    #[test]
+    fn stats() {
+        let mut stats = EventStats::new();
+        stats.add(&skb_drop("TCP_LISTEN_OVERFLOW"));
+        stats.add(&skb_drop("TCP_LISTEN_OVERFLOW"));
+        stats.add(&skb_drop("NOT_SPECIFIED"));
+
+        assert_eq!(
+            stats.render().unwrap(),
+            "Captured drops: 3
+  NOT_SPECIFIED 1
+  TCP_LISTEN_OVERFLOW 2
+
+No network stack counters found (see `retis collect --netstat`)
+
+Size per section: 105 bytes
+  skb-drop 105 (100.0%)
+"
+        );
+
+        let counter = |name: &str, delta| NetstatCounter {
+            name: name.to_string(),
+            delta,
+        };
+        stats.add(&event((
+            SectionId::Netstat,
+            Box::new(NetstatEvent {
+                counters: vec![
+                    counter("IpInReceives", 1500),
+                    counter("TcpExtListenOverflows", 5),
+                    counter("TcpExtListenDrops", 5),
+                ],
+            }),
+        )));
+
+        assert_eq!(
+            stats.render().unwrap(),
+            "Captured drops: 3
+  NOT_SPECIFIED 1
+  TCP_LISTEN_OVERFLOW 2
+
+Network stack counters vs captured drops:
+  TcpExtListenDrops +5: TCP_LISTEN_OVERFLOW 2, TCP_REQQFULLDROP 0
+  TcpExtListenOverflows +5: TCP_LISTEN_OVERFLOW 2
+
+Other network stack counters:
+  IpInReceives +1500
+
+Size per section: 238 bytes
+  netstat 133 (55.9%)
+  skb-drop 105 (44.1%)
+"
+        );
+    }
+
+    #[test]
+    fn sizes() {
+        let mut stats = EventStats::new();
+        stats.add(&event((
+            SectionId::Kernel,
+            Box::new(KernelEvent {
+                symbol: "kfree_skb_reason".to_string(),
+                probe_type: "raw_tracepoint".to_string(),
+                stack_trace: Some(StackTrace(vec![
+                    "kfree_skb_reason+0x0".to_string(),
+                    "tcp_v4_rcv+0x1f2".to_string(),
+                ])),
+            }),
+        )));
+        stats.add(&skb_drop("NO_SOCKET"));
+
+        assert_eq!(
+            stats.render().unwrap(),
+            "Captured drops: 1
+  NO_SOCKET 1
+
+No network stack counters found (see `retis collect --netstat`)
+
+Size per section: 144 bytes
+  kernel 74 (51.4%)
+  kernel.stack_trace 43 (29.9%)
+  skb-drop 27 (18.8%)
+"
+        );
+    }
+
+    #[test]
+    fn counts() {
+        let mut counts = EventCounts::new();
+        let mut drop = skb_drop("NO_SOCKET");
+        drop.insert_section(
+            SectionId::Kernel,
+            Box::new(KernelEvent {
+                symbol: "skb:kfree_skb".to_string(),
+                probe_type: "raw_tracepoint".to_string(),
+                stack_trace: None,
+            }),
+        )
+        .unwrap();
+        counts.add(&drop);
+        counts.add(&drop);
+        counts.add(&skb_drop("NOT_SPECIFIED"));
+
+        assert_eq!(
+            counts.render().unwrap(),
+            "Events: 3
+Sections:
+  skb-drop 3
+  kernel 2
+Probes:
+  tp:skb:kfree_skb 2
+Drop reasons:
+  NO_SOCKET 2
+  NOT_SPECIFIED 1
+"
+        );
+    }
+
+    #[test]
+    fn lost() {
+        let mut stats = EventStats::new();
+        for (seq, cpu) in [(0, 0), (2, 0), (1, 0), (0, 1), (3, 1), (4, 1), (7, 1)] {
+            stats.add(&event((
+                SectionId::Common,
+                Box::new(CommonEvent {
+                    timestamp: 100 + seq,
+                    seq: Some(seq),
+                    smp_id: Some(cpu),
+                    ..Default::default()
+                }),
+            )));
+        }
+
+        let out = stats.render().unwrap();
+        assert!(out.contains(
+            "
+Lost events: 4
+  cpu 1 4 in 2 gap(s)
+    2 between seq 0 (100) and 3 (103)
+    2 between seq 4 (104) and 7 (107)
+"
+        ));
+    }
+
+    #[test]
+    fn hops() {
+        let probe = |symbol: &str, timestamp| {
+            let mut event = event((
+                SectionId::Common,
+                Box::new(CommonEvent {
+                    timestamp,
+                    ..Default::default()
+                }),
+            ));
+            event
+                .insert_section(
+                    SectionId::Kernel,
+                    Box::new(KernelEvent {
+                        symbol: symbol.to_string(),
+                        probe_type: "kprobe".to_string(),
+                        stack_trace: None,
+                    }),
+                )
+                .unwrap();
+            event
+        };
+
+        let mut stats = EventStats::new();
+        for (start, forward, xmit) in [(0, 10_000, 15_000), (100_000, 130_000, 131_000)] {
+            stats.add_series(&EventSeries {
+                events: vec![
+                    probe("ip_rcv", start),
+                    probe("ip_forward", forward),
+                    probe("dev_queue_xmit", xmit),
+                ],
+            });
+        }
+
+        let out = stats.render().unwrap();
+        assert!(out.contains(
+            "
+Latency between adjacent probes, slowest first:
+  from                 to                      samples  latency (us) min/avg/p50/p99/max
+  kprobe:ip_rcv     -> kprobe:ip_forward             2  10.0/20.0/10.0/10.0/30.0
+  kprobe:ip_forward -> kprobe:dev_queue_xmit         2  1.0/3.0/1.0/1.0/5.0
+"
+        ));
+    }
+}
@@ -0,0 +1,237 @@
+//! # Timeline
+//!
+//! Merges events of different kinds (control-plane netlink messages, packet
+//! drops, OpenVSwitch upcalls) into a single time-bucketed view. Control-plane
+//! events are reported individually while datapath ones are aggregated per
+//! bucket, which makes it easy to spot causality, e.g. drops starting right
+//! after a route change.
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use anyhow::Result;
+use clap::ValueEnum;
+
+use crate::events::*;
+
+/// Kinds of events reported in a timeline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum TimelineKind {
+    /// Netlink messages (route changes, flow installs, etc).
+    Netlink,
+    /// Packet drops, aggregated by drop reason.
+    Drop,
+    /// OpenVSwitch upcalls, aggregated.
+    Upcall,
+}
+
+/// Events found in a single time bucket.
+#[derive(Default)]
+struct Bucket {
+    /// Control-plane events, rendered.
+    control: Vec<String>,
+    /// Number of drops per drop reason.
+    drops: BTreeMap<String, u64>,
+    /// Number of upcalls.
+    upcalls: u64,
+}
+
+/// Time-bucketed view of events.
+pub(crate) struct EventTimeline {
+    /// Bucket size, in nanoseconds.
+    bucket: u64,
+    /// Kinds of events to report.
+    kinds: Vec<TimelineKind>,
+    /// Format used to display events and timestamps.
+    format: DisplayFormat,
+    /// Buckets having at least one event, indexed by their start time.
+    buckets: BTreeMap<u64, Bucket>,
+}
+
+impl EventTimeline {
+    /// Create a new timeline with the given bucket size, in nanoseconds.
+    pub(crate) fn new(bucket: u64, kinds: Vec<TimelineKind>, format: DisplayFormat) -> Self {
+        Self {
+            bucket: bucket.max(1),
+            kinds,
+            format,
+            buckets: BTreeMap::new(),
+        }
+    }
+
+    /// Add an event to the timeline. Events can be added in any order.
+    pub(crate) fn add(&mut self, event: &Event) {
+        self.format.update_clock(event);
+
+        let common = match event.common() {
+            Some(common) => common,
+            None => return,
+        };
+        let start = common.timestamp - common.timestamp % self.bucket;
+
+        // Markers are always reported as they segment the timeline.
+        if let Some(marker) = event.marker() {
+            let conf = FormatterConf::new();
+            let control = format!(
+                "{} {}",
+                common.display(&self.format, &conf),
+                marker.display(&self.format, &conf)
+            );
+            self.bucket_mut(start).control.push(control);
+        }
+
+        if self.kinds.contains(&TimelineKind::Netlink) {
+            if let Some(netlink) = event.netlink() {
+                let conf = FormatterConf::new();
+                let control = format!(
+                    "{} {}",
+                    common.display(&self.format, &conf),
+                    netlink.display(&self.format, &conf)
+                );
+                self.bucket_mut(start).control.push(control);
+            }
+        }
+
+        if self.kinds.contains(&TimelineKind::Drop) {
+            if let Some(drop) = event.skb_drop() {
+                let reason = match &drop.subsys {
+                    Some(subsys) => format!("{subsys}/{}", drop.drop_reason),
+                    None => drop.drop_reason.clone(),
+                };
+                *self.bucket_mut(start).drops.entry(reason).or_default() += 1;
+            }
+        }
+
+        if self.kinds.contains(&TimelineKind::Upcall) {
+            if let Some(OvsEvent::Upcall { .. }) = event.ovs() {
+                self.bucket_mut(start).upcalls += 1;
+            }
+        }
+    }
+
+    fn bucket_mut(&mut self, start: u64) -> &mut Bucket {
+        self.buckets.entry(start).or_default()
+    }
+
+    /// Render the timeline. Buckets without any event are skipped.
+    pub(crate) fn render(&self) -> Result<String> {
+        let mut out = String::new();
+        let first = match self.buckets.keys().next() {
+            Some(first) => *first,
+            None => return Ok(out),
+        };
+
+        for (start, bucket) in self.buckets.iter() {
+            let time = CommonEvent {
+                timestamp: *start,
+                seq: None,
+                smp_id: None,
+                task: None,
+            };
+            writeln!(
+                out,
+                "{} (+{:.3}s)",
+                time.display(&self.format, &FormatterConf::new()),
+                (start - first) as f64 / 1e9
+            )?;
+
+            for control in bucket.control.iter() {
+                writeln!(out, "  {control}")?;
+            }
+
+            if !bucket.drops.is_empty() {
+                let reasons = bucket
+                    .drops
+                    .iter()
+                    .map(|(reason, count)| format!("{reason} ({count})"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                writeln!(
+                    out,
+                    "  drops {}: {reasons}",
+                    bucket.drops.values().sum::<u64>()
+                )?;
+            }
+
+            if bucket.upcalls > 0 {
+                writeln!(out, "  upcalls {}", bucket.upcalls)?;
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(timestamp: u64, section: Option<(SectionId, Box<dyn EventSection>)>) -> Event {
+        let mut event = Event::new();
+        event
+            .insert_section(
+                SectionId::Common,
+                Box::new(CommonEvent {
+                    timestamp,
+                    seq: None,
+                    smp_id: None,
+                    task: None,
+                }),
+            )
+            .unwrap();
+        if let Some((id, section)) = section {
+            event.insert_section(id, section).unwrap();
+        }
+        event
+    }
+
+    fn skb_drop(reason: &str) -> Option<(SectionId, Box<dyn EventSection>)> {
+        Some((
+            SectionId::SkbDrop,
+            Box::new(SkbDropEvent {
+                subsys: None,
+                drop_reason: reason.to_string(),
+            }),
+        ))
+    }
+
+    #[test]
+    fn timeline() {
+        let netlink = NetlinkEvent {
+            protocol: "route".to_string(),
+            family: None,
+            r#type: 25,
+            cmd: None,
+            msg: Some("RTM_DELROUTE".to_string()),
+            flags: 0x5,
+            seq: 1,
+            len: 60,
+            portid: 1234,
+            dst_portid: Some(0),
+            group: None,
+        };
+
+        let mut timeline = EventTimeline::new(
+            1_000_000_000,
+            vec![TimelineKind::Netlink, TimelineKind::Drop],
+            DisplayFormat::new(),
+        );
+        // Events are not ordered and some are not reported.
+        timeline.add(&event(2_500_000_000, skb_drop("NO_ROUTE")));
+        timeline.add(&event(
+            1_200_000_000,
+            Some((SectionId::Netlink, Box::new(netlink))),
+        ));
+        timeline.add(&event(2_100_000_000, skb_drop("NO_ROUTE")));
+        timeline.add(&event(2_200_000_000, skb_drop("NO_SOCKET")));
+        timeline.add(&event(3_000_000_000, None));
+
+        assert_eq!(
+            timeline.render().unwrap(),
+            "1000000000 (+0.000s)
+  1200000000 netlink route RTM_DELROUTE (25) flags 0x5 seq 1 len 60 1234 > kernel
+2000000000 (+1.000s)
+  drops 3: NO_ROUTE (2), NO_SOCKET (1)
+"
+        );
+    }
+}
@@ -0,0 +1,22 @@
+use anyhow::Result;
+use serial_test::serial;
+
+use crate::harness::*;
+
+#[test]
+#[serial]
+#[cfg_attr(not(feature = "test_functional"), ignore)]
+fn bridge_forwarding() -> Result<()> {
+    let topo = Topology::two_ns_bridged("bridge-fwd", "rtsbr", false)?;
+
+    let events = Retis::new(&["-c", "skb", "-f", "icmp", "-p", "kprobe:br_forward"])
+        .cmd(&topo.cmd("ns0", "ping -c 1 10.0.42.2"))
+        .collect()?;
+
+    // Echo request and reply are forwarded by the bridge.
+    let forwarded = probed(&events, "br_forward");
+    assert_eq!(forwarded.len(), 2);
+    assert!(forwarded.iter().any(|e| dev(e) == Some("rtsbr0")));
+    assert!(forwarded.iter().any(|e| dev(e) == Some("rtsbr1")));
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use anyhow::Result;
+use serial_test::serial;
+
+use crate::harness::*;
+
+#[test]
+#[serial]
+#[cfg_attr(not(feature = "test_functional"), ignore)]
+fn drop_no_socket() -> Result<()> {
+    let topo = Topology::two_ns_simple("drop-no-socket")?;
+
+    // Nothing listens on port 443 in ns1, the SYN is dropped.
+    let events = Retis::new(&["-c", "skb-drop,skb", "-f", "tcp"])
+        .cmd(&topo.cmd("ns0", "socat -T 1 - TCP:10.0.42.2:443"))
+        .collect()?;
+
+    let drop = events
+        .iter()
+        .find(|e| {
+            e.skb_drop()
+                .is_some_and(|drop| drop.drop_reason == "NO_SOCKET")
+        })
+        .expect("No NO_SOCKET drop found");
+    assert_eq!(dev(drop), Some("veth10"));
+
+    let tcp = drop.skb().unwrap().tcp.as_ref().unwrap();
+    assert_eq!(tcp.dport, 443);
+    Ok(())
+}
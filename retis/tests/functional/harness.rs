@@ -0,0 +1,262 @@
+//! # Harness
+//!
+//! Helpers to build disposable topologies using network namespaces, veth
+//! pairs, Linux bridges and OvS bridges, to run collections against them and
+//! to inspect the events they produced. Everything created by a topology is
+//! removed when it is dropped, even if the test failed.
+
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Result};
+use events::{file::FileEventsFactory, Event};
+
+/// Run a command, failing if it does not succeed.
+pub(crate) fn run(cmd: &[&str]) -> Result<String> {
+    let output = Command::new(cmd[0]).args(&cmd[1..]).output()?;
+    if !output.status.success() {
+        bail!(
+            "'{}' failed: {}",
+            cmd.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Network topology built for a test. Namespaces are named after the topology
+/// name, so tests using different names do not conflict.
+pub(crate) struct Topology {
+    name: String,
+    netns: Vec<String>,
+    /// Links created in the root namespace (veth ends, bridges).
+    links: Vec<String>,
+    ovs_bridges: Vec<String>,
+}
+
+impl Topology {
+    pub(crate) fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            netns: Vec::new(),
+            links: Vec::new(),
+            ovs_bridges: Vec::new(),
+        }
+    }
+
+    /// Full name of a namespace of the topology.
+    pub(crate) fn ns(&self, name: &str) -> String {
+        format!("{}-{name}", self.name)
+    }
+
+    /// Add a network namespace, with its loopback interface up.
+    pub(crate) fn add_netns(&mut self, name: &str) -> Result<&mut Self> {
+        let ns = self.ns(name);
+        run(&["ip", "netns", "add", &ns])?;
+        self.netns.push(ns);
+        self.exec(name, &["ip", "link", "set", "lo", "up"])?;
+        Ok(self)
+    }
+
+    /// Run a command in a namespace of the topology.
+    pub(crate) fn exec(&self, ns: &str, cmd: &[&str]) -> Result<String> {
+        let ns = self.ns(ns);
+        run(&[&["ip", "netns", "exec", ns.as_str()][..], cmd].concat())
+    }
+
+    /// Command running `cmd` in a namespace of the topology, as a string
+    /// suitable for `retis collect --cmd`.
+    pub(crate) fn cmd(&self, ns: &str, cmd: &str) -> String {
+        format!("ip netns exec {} {cmd}", self.ns(ns))
+    }
+
+    /// Add a veth pair between two namespaces (or the root namespace if
+    /// `None`), with optional addresses (in the CIDR notation).
+    pub(crate) fn add_veth(
+        &mut self,
+        a: (Option<&str>, &str, Option<&str>),
+        b: (Option<&str>, &str, Option<&str>),
+    ) -> Result<&mut Self> {
+        run(&[
+            "ip", "link", "add", a.1, "type", "veth", "peer", "name", b.1,
+        ])?;
+        // The pair is removed along with either end; track the end left in the
+        // root namespace, if any, as namespaces are removed anyway.
+        if a.0.is_none() {
+            self.links.push(a.1.to_string());
+        } else if b.0.is_none() {
+            self.links.push(b.1.to_string());
+        }
+
+        for (ns, ifname, addr) in [a, b] {
+            match ns {
+                Some(ns) => {
+                    run(&["ip", "link", "set", ifname, "netns", &self.ns(ns)])?;
+                    if let Some(addr) = addr {
+                        self.exec(ns, &["ip", "addr", "add", addr, "dev", ifname])?;
+                    }
+                    self.exec(ns, &["ip", "link", "set", ifname, "up"])?;
+                }
+                None => {
+                    if let Some(addr) = addr {
+                        run(&["ip", "addr", "add", addr, "dev", ifname])?;
+                    }
+                    run(&["ip", "link", "set", ifname, "up"])?;
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    /// Add a Linux bridge in the root namespace, enslaving the given ports.
+    pub(crate) fn add_bridge(&mut self, name: &str, ports: &[&str]) -> Result<&mut Self> {
+        run(&["ip", "link", "add", name, "type", "bridge"])?;
+        self.links.push(name.to_string());
+        for port in ports.iter() {
+            run(&["ip", "link", "set", port, "master", name])?;
+        }
+        run(&["ip", "link", "set", name, "up"])?;
+        Ok(self)
+    }
+
+    /// Add an OvS bridge with the given ports, using the normal action. OvS
+    /// must be running.
+    pub(crate) fn add_ovs_bridge(&mut self, name: &str, ports: &[&str]) -> Result<&mut Self> {
+        run(&["ovs-vsctl", "add-br", name])?;
+        self.ovs_bridges.push(name.to_string());
+        for port in ports.iter() {
+            run(&["ovs-vsctl", "add-port", name, port])?;
+        }
+        run(&["ip", "link", "set", name, "up"])?;
+        Ok(self)
+    }
+
+    /// Two namespaces, `ns0` and `ns1`, directly connected using a veth pair
+    /// (`veth01` in ns0 with 10.0.42.1, `veth10` in ns1 with 10.0.42.2).
+    pub(crate) fn two_ns_simple(name: &str) -> Result<Self> {
+        let mut topo = Self::new(name);
+        topo.add_netns("ns0")?.add_netns("ns1")?.add_veth(
+            (Some("ns0"), "veth01", Some("10.0.42.1/24")),
+            (Some("ns1"), "veth10", Some("10.0.42.2/24")),
+        )?;
+        Ok(topo)
+    }
+
+    /// Two namespaces, `ns0` (10.0.42.1) and `ns1` (10.0.42.2), connected
+    /// through a bridge in the root namespace. Ports are named `<name>0` and
+    /// `<name>1`. `ovs` selects an OvS bridge instead of a Linux one.
+    pub(crate) fn two_ns_bridged(name: &str, bridge: &str, ovs: bool) -> Result<Self> {
+        let (p0, p1) = (format!("{bridge}0"), format!("{bridge}1"));
+        let mut topo = Self::new(name);
+        topo.add_netns("ns0")?
+            .add_netns("ns1")?
+            .add_veth(
+                (Some("ns0"), "veth0", Some("10.0.42.1/24")),
+                (None, &p0, None),
+            )?
+            .add_veth(
+                (Some("ns1"), "veth1", Some("10.0.42.2/24")),
+                (None, &p1, None),
+            )?;
+        match ovs {
+            true => topo.add_ovs_bridge(bridge, &[&p0, &p1])?,
+            false => topo.add_bridge(bridge, &[&p0, &p1])?,
+        };
+        Ok(topo)
+    }
+}
+
+impl Drop for Topology {
+    fn drop(&mut self) {
+        // Best effort, as the topology might be partially built.
+        self.ovs_bridges.iter().for_each(|br| {
+            let _ = run(&["ovs-vsctl", "--if-exists", "del-br", br]);
+        });
+        self.links.iter().rev().for_each(|link| {
+            let _ = run(&["ip", "link", "del", link]);
+        });
+        self.netns.iter().for_each(|ns| {
+            let _ = run(&["ip", "netns", "del", ns]);
+        });
+    }
+}
+
+/// Runs a collection and retrieves its events. Collections are stopped once
+/// their commands (`--cmd`) are done.
+pub(crate) struct Retis {
+    args: Vec<String>,
+    cmds: Vec<String>,
+    out: PathBuf,
+}
+
+impl Retis {
+    pub(crate) fn new(args: &[&str]) -> Self {
+        Self {
+            args: args.iter().map(|a| a.to_string()).collect(),
+            cmds: Vec::new(),
+            out: env::temp_dir().join(format!("retis-functional-{}.data", std::process::id())),
+        }
+    }
+
+    /// Add a command to run during the collection.
+    pub(crate) fn cmd(&mut self, cmd: &str) -> &mut Self {
+        self.cmds.push(cmd.to_string());
+        self
+    }
+
+    /// Run the collection and return its events.
+    pub(crate) fn collect(&self) -> Result<Vec<Event>> {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_retis"));
+        cmd.arg("collect")
+            .args(&self.args)
+            .arg("-o")
+            .arg(&self.out)
+            // Commands failing are part of some tests (e.g. connections being
+            // refused).
+            .args(["--cmd-failure", "continue"])
+            .stdout(Stdio::null());
+        for c in self.cmds.iter() {
+            cmd.args(["--cmd", c]);
+        }
+
+        let output = cmd.output()?;
+        if !output.status.success() {
+            bail!(
+                "Collection failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let mut factory = FileEventsFactory::new(&self.out)?;
+        let mut events = Vec::new();
+        while let Some(event) = factory.next_event()? {
+            events.push(event);
+        }
+        Ok(events)
+    }
+}
+
+impl Drop for Retis {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.out);
+        let mut manifest = self.out.clone().into_os_string();
+        manifest.push(".manifest");
+        let _ = fs::remove_file(manifest);
+    }
+}
+
+/// Events generated by a given probe.
+pub(crate) fn probed<'a>(events: &'a [Event], symbol: &str) -> Vec<&'a Event> {
+    events
+        .iter()
+        .filter(|e| e.kernel().is_some_and(|k| k.symbol == symbol))
+        .collect()
+}
+
+/// Name of the device packets of an event were seen on.
+pub(crate) fn dev(event: &Event) -> Option<&str> {
+    event.skb()?.dev.as_ref().map(|dev| dev.name.as_str())
+}
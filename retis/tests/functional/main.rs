@@ -0,0 +1,17 @@
+//! # Functional tests
+//!
+//! Tests running collections against disposable network topologies and
+//! checking the events they produce, covering the collectors end to end. They
+//! require root privileges (and OvS running for the OvS tests) and are only
+//! run when the `test_functional` feature is enabled:
+//!
+//! ```text
+//! sudo -E cargo test --features test_functional --test functional
+//! ```
+
+mod harness;
+
+mod bridge;
+mod drop;
+mod ovs;
+mod skb;
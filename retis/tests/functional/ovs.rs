@@ -0,0 +1,21 @@
+use anyhow::Result;
+use serial_test::serial;
+
+use crate::harness::*;
+
+#[test]
+#[serial]
+#[cfg_attr(not(feature = "test_functional"), ignore)]
+fn ovs_upcall() -> Result<()> {
+    let topo = Topology::two_ns_bridged("ovs-upcall", "rtsovs", true)?;
+
+    let events = Retis::new(&["-c", "ovs,skb", "-f", "icmp"])
+        .cmd(&topo.cmd("ns0", "ping -c 1 10.0.42.2"))
+        .collect()?;
+
+    // No flow is installed yet, the first packet is upcalled.
+    assert!(events
+        .iter()
+        .any(|e| matches!(e.ovs(), Some(events::OvsEvent::Upcall { .. }))));
+    Ok(())
+}
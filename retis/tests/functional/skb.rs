@@ -0,0 +1,61 @@
+use anyhow::Result;
+use serial_test::serial;
+
+use crate::harness::*;
+
+#[test]
+#[serial]
+#[cfg_attr(not(feature = "test_functional"), ignore)]
+fn skb_sanity() -> Result<()> {
+    let topo = Topology::two_ns_simple("skb-sanity")?;
+
+    let events = Retis::new(&["-c", "skb", "-f", "icmp", "-p", "kprobe:ip_rcv"])
+        .cmd(&topo.cmd("ns0", "ping -c 1 10.0.42.2"))
+        .collect()?;
+
+    // Echo request in ns1 and echo reply in ns0.
+    let ip_rcv = probed(&events, "ip_rcv");
+    assert_eq!(ip_rcv.len(), 2);
+    assert_eq!(dev(ip_rcv[0]), Some("veth10"));
+    assert_eq!(dev(ip_rcv[1]), Some("veth01"));
+
+    let ip = ip_rcv[0].skb().unwrap().ip.as_ref().unwrap();
+    assert_eq!(ip.saddr, "10.0.42.1");
+    assert_eq!(ip.daddr, "10.0.42.2");
+    Ok(())
+}
+
+#[test]
+#[serial]
+#[cfg_attr(not(feature = "test_functional"), ignore)]
+fn skb_tracking() -> Result<()> {
+    let topo = Topology::two_ns_simple("skb-tracking")?;
+
+    let events = Retis::new(&[
+        "-c",
+        "skb,skb-tracking",
+        "-f",
+        "icmp",
+        "-p",
+        "tp:net:net_dev_queue",
+        "-p",
+        "tp:net:netif_receive_skb",
+    ])
+    .cmd(&topo.cmd("ns0", "ping -c 1 10.0.42.2"))
+    .collect()?;
+
+    // The echo request is seen leaving ns0 and entering ns1, as a single
+    // tracked packet.
+    let xmit = probed(&events, "net:net_dev_queue");
+    let recv = probed(&events, "net:netif_receive_skb");
+    let request = |events: &[&events::Event]| {
+        events
+            .iter()
+            .find(|e| dev(e) == Some("veth01") || dev(e) == Some("veth10"))
+            .and_then(|e| e.skb_tracking())
+            .map(|t| t.tracking_id())
+    };
+    assert!(request(&xmit).is_some());
+    assert_eq!(request(&xmit), request(&recv));
+    Ok(())
+}
@@ -83,6 +83,54 @@ def two_ns_simple(netns):
     yield netns
 
 
+@pytest.fixture
+def two_ns_bridged(netns):
+    """Fixture that creates two netns connected through a Linux bridge in the
+    root netns."""
+    ipr = IPRoute()
+
+    ipr.link("add", ifname="rtsbr", kind="bridge")
+    bridge = ipr.poll(ipr.link, "dump", timeout=5, ifname="rtsbr")[0]
+
+    for idx in [0, 1]:
+        local = f"rtsbr{idx}"
+        remote = f"veth{idx}"
+        ns_name = f"ns{idx}"
+
+        # Create a netns & a veth pair
+        ns = netns.add(ns_name)
+        ipr.link("add", ifname=local, peer=remote, kind="veth")
+
+        # Wait until links appear
+        peer, veth = ipr.poll(
+            ipr.link, "dump", timeout=5, ifname=lambda x: x in (local, remote)
+        )
+
+        # Enslave the local side to the bridge and set it up
+        ipr.link("set", ifname=local, master=bridge["index"], state="up")
+
+        # Move peer to namespace, add an IP address to it and set it up
+        ipr.link("set", ifname=remote, net_ns_fd=ns_name)
+        remote_iface = ns.link_lookup(ifname=remote)[0]
+        ns.addr(
+            "add",
+            index=remote_iface,
+            address=f"10.0.42.{idx + 1}",
+            prefixlen=24,
+        )
+        ns.link("set", index=remote_iface, state="up")
+
+    ipr.link("set", index=bridge["index"], state="up")
+    ipr.close()
+
+    yield netns
+
+    # Cleanup, veth pairs are removed along with the netns.
+    ipr = IPRoute()
+    ipr.link("del", ifname="rtsbr")
+    ipr.close()
+
+
 @pytest.fixture
 def three_ns_nat(netns):
     """Fixture that creates three netns connected through veth pairs, a VIP and DNATing
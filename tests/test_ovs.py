@@ -3,7 +3,7 @@ import time
 
 import pytest
 
-from testlib import Retis, assert_events_present, kernel_version_lt
+from testlib import Retis, assert_events_present, events_from_probe, kernel_version_lt
 
 
 def test_ovs_sanity(two_port_ovs):
@@ -18,13 +18,7 @@ def test_ovs_sanity(two_port_ovs):
 
     events = retis.events()
     print(events)
-    execs = list(
-        filter(
-            lambda e: e.get("kernel", {}).get("symbol")
-            == "openvswitch:ovs_do_execute_action",
-            events,
-        )
-    )
+    execs = events_from_probe(events, "openvswitch:ovs_do_execute_action")
 
     assert len(execs) == 2
 
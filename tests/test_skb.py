@@ -1,6 +1,6 @@
 import time
 
-from testlib import Retis, assert_events_present
+from testlib import Retis, assert_events_present, events_from_probe
 
 
 def test_skb_sanity(two_ns_simple):
@@ -14,15 +14,79 @@ def test_skb_sanity(two_ns_simple):
 
     events = retis.events()
     print(events)
-    ip_rcv_events = list(
-        filter(
-            lambda e: e.get("kernel", {}).get("symbol") == "ip_rcv",
-            events,
-        )
-    )
+    ip_rcv_events = events_from_probe(events, "ip_rcv")
 
     assert len(ip_rcv_events) == 2
 
+    # Echo request in ns1 and echo reply in ns0.
+    expected_events = [
+        {
+            "skb": {
+                "dev": {
+                    "name": "veth10",
+                },
+                "ip": {
+                    "saddr": "10.0.42.1",
+                    "daddr": "10.0.42.2",
+                },
+            },
+        },
+        {
+            "skb": {
+                "dev": {
+                    "name": "veth01",
+                },
+                "ip": {
+                    "saddr": "10.0.42.2",
+                    "daddr": "10.0.42.1",
+                },
+            },
+        },
+    ]
+    assert_events_present(ip_rcv_events, expected_events)
+
+
+def test_skb_bridge_forwarding(two_ns_bridged):
+    ns = two_ns_bridged
+    retis = Retis()
+
+    retis.collect("-c", "skb", "-f", "icmp", "-p", "kprobe:br_forward")
+    print(ns.run("ns0", "ping", "-c", "1", "10.0.42.2"))
+    retis.stop()
+
+    events = retis.events()
+    print(events)
+    forwarded = events_from_probe(events, "br_forward")
+
+    # Echo request and reply are forwarded by the bridge, each one being
+    # received on a different port.
+    assert len(forwarded) == 2
+    expected_events = [
+        {
+            "skb": {
+                "dev": {
+                    "name": "rtsbr0",
+                },
+                "ip": {
+                    "saddr": "10.0.42.1",
+                    "daddr": "10.0.42.2",
+                },
+            },
+        },
+        {
+            "skb": {
+                "dev": {
+                    "name": "rtsbr1",
+                },
+                "ip": {
+                    "saddr": "10.0.42.2",
+                    "daddr": "10.0.42.1",
+                },
+            },
+        },
+    ]
+    assert_events_present(forwarded, expected_events)
+
 
 def test_skb_tcp_cc(two_ns_simple):
     ns = two_ns_simple
@@ -295,6 +295,16 @@ def assert_events_present(events, expected):
             )
 
 
+def events_from_probe(events, symbol):
+    """Return the events generated by a given probe."""
+    return list(
+        filter(
+            lambda e: e.get("kernel", {}).get("symbol") == symbol,
+            events,
+        )
+    )
+
+
 def is_subset(superset, subset, aliases):
     """Recursively check if a dictionary is a subset of another one.
 